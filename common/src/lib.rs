@@ -1,4 +1,12 @@
-use ethereum_types::H256;
+//! Types and constants shared by the crates of the zkEVM stack, which do not
+//! depend on the prover.
+
+use std::collections::{BTreeSet, HashMap};
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use serde::{Deserialize, Serialize};
 
 /// The hash value of an account empty EVM code.
 /// 0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470
@@ -14,6 +22,192 @@ pub const EMPTY_TRIE_HASH: H256 = H256([
     108, 173, 192, 1, 98, 47, 181, 227, 99, 180, 33,
 ]);
 
+/// The RLP encoding of an account, as stored in the state trie.
+#[derive(RlpEncodable, RlpDecodable, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AccountRlp {
+    pub nonce: U256,
+    pub balance: U256,
+    pub storage_root: H256,
+    pub code_hash: H256,
+}
+
+impl Default for AccountRlp {
+    fn default() -> Self {
+        Self {
+            nonce: U256::zero(),
+            balance: U256::zero(),
+            storage_root: EMPTY_TRIE_HASH,
+            code_hash: EMPTY_CODE_HASH,
+        }
+    }
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, Clone)]
+pub struct LogRlp {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, Clone)]
+pub struct LegacyReceiptRlp {
+    pub status: bool,
+    pub cum_gas_used: U256,
+    pub bloom: Bytes,
+    pub logs: Vec<LogRlp>,
+}
+
+impl LegacyReceiptRlp {
+    // RLP encode the receipt and prepend the tx type.
+    pub fn encode(&self, tx_type: u8) -> Vec<u8> {
+        let mut bytes = rlp::encode(self).to_vec();
+        if tx_type != 0 {
+            bytes.insert(0, tx_type);
+        }
+        bytes
+    }
+}
+
+/// Precomputed JUMPDEST analyses, mapping code hashes to the offsets of the
+/// JUMPDEST instructions jumped to when executing the code.
+pub type JumpDestTables = HashMap<H256, BTreeSet<usize>>;
+
+/// The Ethereum hardfork whose rules a block follows.
+///
+/// The kernel is shared by all the supported hardforks, and selects the
+/// semantics of the block at runtime from this value, which is part of the
+/// public values.
+///
+/// The earliest supported hardfork is Berlin, as the kernel always charges
+/// gas following EIP-2929, and the latest is Cancun: the kernel implements
+/// none of the EIPs of Prague, whose blocks are rejected.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Hardfork {
+    Berlin,
+    /// Adds the base fee, `BASEFEE`, and reduces gas refunds (EIP-1559, 3198,
+    /// 3529 and 3541).
+    London,
+    /// The Merge: `DIFFICULTY` becomes `PREVRANDAO` (EIP-4399).
+    Paris,
+    /// Warms the coinbase, limits the size of initcode and adds withdrawals
+    /// (EIP-3651, 3860 and 4895).
+    Shanghai,
+    /// Adds transient storage, `MCOPY`, blob transactions, the beacon roots
+    /// contract and restricts `SELFDESTRUCT` (EIP-1153, 4788, 4844, 5656,
+    /// 6780 and 7516).
+    #[default]
+    Cancun,
+}
+
+impl Hardfork {
+    pub const ALL: [Self; 5] = [
+        Self::Berlin,
+        Self::London,
+        Self::Paris,
+        Self::Shanghai,
+        Self::Cancun,
+    ];
+
+    /// The index of this hardfork, as seen by the kernel.
+    pub const fn id(self) -> u64 {
+        self as u64
+    }
+
+    /// Returns the hardfork with the given kernel index, if any.
+    pub fn from_id(id: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|fork| fork.id() == id)
+    }
+
+    /// Whether `PUSH0` is a valid instruction, as introduced in Shanghai by
+    /// [EIP-3855](https://eips.ethereum.org/EIPS/eip-3855).
+    pub const fn enables_push0(self) -> bool {
+        self.id() >= Self::Shanghai.id()
+    }
+
+    /// Whether `SELFDESTRUCT` only deletes accounts created in the same
+    /// transaction, as introduced in Cancun by
+    /// [EIP-6780](https://eips.ethereum.org/EIPS/eip-6780).
+    pub const fn restricts_selfdestruct(self) -> bool {
+        self.id() >= Self::Cancun.id()
+    }
+
+    /// The reward of the miner of a block, in wei, which the Merge removed
+    /// ([EIP-3675](https://eips.ethereum.org/EIPS/eip-3675)). All the supported
+    /// pre-Merge hardforks follow the 2 ETH reward of Constantinople
+    /// ([EIP-1234](https://eips.ethereum.org/EIPS/eip-1234)).
+    pub fn block_reward(self) -> Option<U256> {
+        (self.id() < Self::Paris.id()).then(|| U256::from(2_000_000_000_000_000_000u128))
+    }
+
+    /// Returns the balance credits `(address, amount)` rewarding the mining of
+    /// a block, empty from Paris on.
+    ///
+    /// The miner of the block, `beneficiary`, gets the block reward plus 1/32
+    /// of it per ommer included. The miner of each ommer `(beneficiary,
+    /// number)` gets `(8 + number - block_number) / 8` of the block reward.
+    pub fn block_rewards(
+        self,
+        beneficiary: Address,
+        block_number: U256,
+        ommers: &[(Address, U256)],
+    ) -> Vec<(Address, U256)> {
+        let Some(reward) = self.block_reward() else {
+            return vec![];
+        };
+        let miner_reward = reward + reward / 32 * ommers.len();
+        std::iter::once((beneficiary, miner_reward))
+            .chain(ommers.iter().map(|&(ommer_beneficiary, number)| {
+                (ommer_beneficiary, (number + 8 - block_number) * reward / 8)
+            }))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Hardfork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Berlin => "berlin",
+            Self::London => "london",
+            Self::Paris => "paris",
+            Self::Shanghai => "shanghai",
+            Self::Cancun => "cancun",
+        };
+        f.write_str(name)
+    }
+}
+
+impl std::str::FromStr for Hardfork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|fork| fork.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unsupported hardfork {s}"))
+    }
+}
+
+#[test]
+fn test_block_rewards() {
+    let (miner, uncle_miner) = (Address::repeat_byte(1), Address::repeat_byte(2));
+    let eth = U256::exp10(18);
+
+    assert_eq!(
+        Hardfork::London.block_rewards(miner, 100.into(), &[(uncle_miner, 99.into())]),
+        [(miner, eth * 2 + eth / 16), (uncle_miner, eth * 7 / 4)]
+    );
+    assert_eq!(
+        Hardfork::Berlin.block_rewards(miner, 100.into(), &[]),
+        [(miner, eth * 2)]
+    );
+    assert!(Hardfork::Paris
+        .block_rewards(miner, 100.into(), &[(uncle_miner, 99.into())])
+        .is_empty());
+}
+
 #[test]
 fn test_empty_code_hash() {
     assert_eq!(EMPTY_CODE_HASH, keccak_hash::keccak([]));
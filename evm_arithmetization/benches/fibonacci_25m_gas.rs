@@ -179,6 +179,7 @@ fn prepare_setup() -> anyhow::Result<GenerationInputs> {
     Ok(GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         tries: tries_before,
         trie_roots_after,
         contract_code,
@@ -186,6 +187,8 @@ fn prepare_setup() -> anyhow::Result<GenerationInputs> {
             "fe07ff6d1ab215df17884b89112ccf2373597285a56c5902150313ad1a53ee57"
         )),
         global_exit_roots: vec![],
+        burn_addr: None,
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
@@ -0,0 +1,53 @@
+//! Benchmarks the STARK proof of the first segment of an empty block under
+//! each FRI security preset, printing the soundness report of each of them.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use evm_arithmetization::prover::{prove, SegmentDataIterator};
+use evm_arithmetization::testing_utils::TestBlockBuilder;
+use evm_arithmetization::{AllStark, SecurityPreset};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+const MAX_CPU_LEN_LOG: usize = 20;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let inputs = TestBlockBuilder::new().build().unwrap();
+    let all_stark = AllStark::<F, D>::default();
+
+    let mut group = c.benchmark_group("security_presets");
+    group.sample_size(10);
+    for preset in SecurityPreset::ALL {
+        println!("{}", preset.report());
+        let config = preset.all_stark_config();
+        group.bench_function(BenchmarkId::from_parameter(preset), |b| {
+            b.iter_batched(
+                || {
+                    SegmentDataIterator::<F>::new(&inputs, Some(MAX_CPU_LEN_LOG))
+                        .next()
+                        .unwrap()
+                        .unwrap()
+                },
+                |(inputs, mut segment_data)| {
+                    prove::<F, C, D>(
+                        &all_stark,
+                        &config,
+                        inputs,
+                        &mut segment_data,
+                        &mut TimingTree::default(),
+                        None,
+                    )
+                    .unwrap()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -4,6 +4,7 @@ use std::iter;
 use plonky2::field::extension::Extendable;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::CircuitConfig;
 use starky::config::StarkConfig;
 use starky::cross_table_lookup::{CrossTableLookup, TableIdx, TableWithColumns};
 use starky::evaluation_frame::StarkFrame;
@@ -15,6 +16,7 @@ use crate::byte_packing::byte_packing_stark::{self, BytePackingStark};
 use crate::cpu::cpu_stark::CpuStark;
 use crate::cpu::cpu_stark::{self, ctl_context_pruning_looked};
 use crate::cpu::membus::NUM_GP_CHANNELS;
+use crate::ec::ec_stark::{self, EcStark};
 use crate::keccak::keccak_stark;
 use crate::keccak::keccak_stark::KeccakStark;
 use crate::keccak_sponge::columns::KECCAK_RATE_BYTES;
@@ -25,6 +27,8 @@ use crate::logic::LogicStark;
 use crate::memory::memory_stark::MemoryStark;
 use crate::memory::memory_stark::{self, ctl_context_pruning_looking};
 use crate::memory_continuation::memory_continuation_stark::{self, MemoryContinuationStark};
+use crate::proof::DEFAULT_CAP_HEIGHT;
+use crate::range_check::range_check_stark::{self, RangeCheckStark};
 
 /// Structure containing all STARKs and the cross-table lookups.
 #[derive(Clone)]
@@ -38,6 +42,8 @@ pub struct AllStark<F: RichField + Extendable<D>, const D: usize> {
     pub(crate) memory_stark: MemoryStark<F, D>,
     pub(crate) mem_before_stark: MemoryContinuationStark<F, D>,
     pub(crate) mem_after_stark: MemoryContinuationStark<F, D>,
+    pub(crate) range_check_stark: RangeCheckStark<F, D>,
+    pub(crate) ec_stark: EcStark<F, D>,
     pub(crate) cross_table_lookups: Vec<CrossTableLookup<F>>,
 }
 
@@ -55,23 +61,41 @@ impl<F: RichField + Extendable<D>, const D: usize> Default for AllStark<F, D> {
             memory_stark: MemoryStark::default(),
             mem_before_stark: MemoryContinuationStark::default(),
             mem_after_stark: MemoryContinuationStark::default(),
+            range_check_stark: RangeCheckStark::default(),
+            ec_stark: EcStark::default(),
             cross_table_lookups: all_cross_table_lookups(),
         }
     }
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> AllStark<F, D> {
-    pub(crate) fn num_lookups_helper_columns(&self, config: &StarkConfig) -> [usize; NUM_TABLES] {
+    pub(crate) fn num_lookups_helper_columns(
+        &self,
+        config: &AllStarkConfig,
+    ) -> [usize; NUM_TABLES] {
         [
-            self.arithmetic_stark.num_lookup_helper_columns(config),
-            self.byte_packing_stark.num_lookup_helper_columns(config),
-            self.cpu_stark.num_lookup_helper_columns(config),
-            self.keccak_stark.num_lookup_helper_columns(config),
-            self.keccak_sponge_stark.num_lookup_helper_columns(config),
-            self.logic_stark.num_lookup_helper_columns(config),
-            self.memory_stark.num_lookup_helper_columns(config),
-            self.mem_before_stark.num_lookup_helper_columns(config),
-            self.mem_after_stark.num_lookup_helper_columns(config),
+            self.arithmetic_stark
+                .num_lookup_helper_columns(config.table(Table::Arithmetic)),
+            self.byte_packing_stark
+                .num_lookup_helper_columns(config.table(Table::BytePacking)),
+            self.cpu_stark
+                .num_lookup_helper_columns(config.table(Table::Cpu)),
+            self.keccak_stark
+                .num_lookup_helper_columns(config.table(Table::Keccak)),
+            self.keccak_sponge_stark
+                .num_lookup_helper_columns(config.table(Table::KeccakSponge)),
+            self.logic_stark
+                .num_lookup_helper_columns(config.table(Table::Logic)),
+            self.memory_stark
+                .num_lookup_helper_columns(config.table(Table::Memory)),
+            self.mem_before_stark
+                .num_lookup_helper_columns(config.table(Table::MemBefore)),
+            self.mem_after_stark
+                .num_lookup_helper_columns(config.table(Table::MemAfter)),
+            self.range_check_stark
+                .num_lookup_helper_columns(config.table(Table::RangeCheck)),
+            self.ec_stark
+                .num_lookup_helper_columns(config.table(Table::Ec)),
         ]
     }
 }
@@ -90,6 +114,8 @@ pub enum Table {
     Memory = 6,
     MemBefore = 7,
     MemAfter = 8,
+    RangeCheck = 9,
+    Ec = 10,
 }
 
 impl Deref for Table {
@@ -98,12 +124,12 @@ impl Deref for Table {
     fn deref(&self) -> &Self::Target {
         // Hacky way to implement `Deref` for `Table` so that we don't have to
         // call `Table::Foo as usize`, but perhaps too ugly to be worth it.
-        [&0, &1, &2, &3, &4, &5, &6, &7, &8][*self as TableIdx]
+        [&0, &1, &2, &3, &4, &5, &6, &7, &8, &9, &10][*self as TableIdx]
     }
 }
 
 /// Number of STARK tables.
-pub(crate) const NUM_TABLES: usize = Table::MemAfter as usize + 1;
+pub(crate) const NUM_TABLES: usize = Table::Ec as usize + 1;
 
 impl Table {
     /// Returns all STARK table indices.
@@ -118,10 +144,155 @@ impl Table {
             Self::Memory,
             Self::MemBefore,
             Self::MemAfter,
+            Self::RangeCheck,
+            Self::Ec,
         ]
     }
+
+    /// Returns whether this table may be absent from a segment proof, when
+    /// the segment produces none of its rows.
+    pub(crate) const fn is_optional(self) -> bool {
+        matches!(
+            self,
+            Self::BytePacking | Self::Keccak | Self::KeccakSponge | Self::Logic | Self::Ec
+        )
+    }
+
+    /// Returns whether this table holds the memory carried over between
+    /// segments, whose Merkle caps are part of the public values.
+    pub(crate) const fn is_memory_continuation(self) -> bool {
+        matches!(self, Self::MemBefore | Self::MemAfter)
+    }
+}
+
+/// The STARK configurations of all tables.
+///
+/// The tables share the challenges of their cross-table lookups, hence their
+/// number, but may be proven with different FRI parameters, e.g. with a higher
+/// rate for small tables such as [`Table::Logic`], trading larger proofs for
+/// faster proving.
+///
+/// The Merkle caps of the memory continuation tables are part of the public
+/// values, and are chained between segments: both tables always share their
+/// configuration, with the default cap height.
+///
+/// It also holds the configuration of the recursion circuits verifying these
+/// proofs, [`CircuitConfig::standard_recursion_config`] by default.
+#[derive(Clone, Debug)]
+pub struct AllStarkConfig {
+    by_table: [StarkConfig; NUM_TABLES],
+    recursion: CircuitConfig,
+}
+
+impl AllStarkConfig {
+    /// Returns a configuration proving all tables with `config`.
+    pub fn uniform(config: StarkConfig) -> Self {
+        check_memory_continuation_config(&config);
+        Self {
+            by_table: core::array::from_fn(|_| config.clone()),
+            recursion: CircuitConfig::standard_recursion_config(),
+        }
+    }
+
+    /// Returns a configuration proving all tables with
+    /// [`StarkConfig::standard_fast_config`].
+    pub fn standard_fast_config() -> Self {
+        Self::uniform(StarkConfig::standard_fast_config())
+    }
+
+    /// Overrides the configuration of `table`. Overriding one of the memory
+    /// continuation tables overrides both.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` doesn't use the number of challenges of the other
+    /// tables, or if it changes the cap height of the memory continuation
+    /// tables.
+    pub fn with_table_config(mut self, table: Table, config: StarkConfig) -> Self {
+        assert_eq!(
+            config.num_challenges,
+            self.num_challenges(),
+            "all tables must use the same number of challenges"
+        );
+        if table.is_memory_continuation() {
+            check_memory_continuation_config(&config);
+            self.by_table[*Table::MemBefore] = config.clone();
+            self.by_table[*Table::MemAfter] = config;
+        } else {
+            self.by_table[*table] = config;
+        }
+        self
+    }
+
+    /// Overrides the FRI rate and cap height of `table`, keeping its other
+    /// parameters.
+    pub fn with_fri_params(self, table: Table, rate_bits: usize, cap_height: usize) -> Self {
+        let mut config = self.table(table).clone();
+        config.fri_config.rate_bits = rate_bits;
+        config.fri_config.cap_height = cap_height;
+        self.with_table_config(table, config)
+    }
+
+    /// Overrides the configuration of the recursion circuits.
+    pub fn with_recursion_config(mut self, config: CircuitConfig) -> Self {
+        self.recursion = config;
+        self
+    }
+
+    /// Returns the configuration of `table`.
+    pub fn table(&self, table: Table) -> &StarkConfig {
+        &self.by_table[*table]
+    }
+
+    /// Returns the configuration of the recursion circuits.
+    pub const fn recursion_config(&self) -> &CircuitConfig {
+        &self.recursion
+    }
+
+    /// Returns the number of challenges of the cross-table lookups, shared by
+    /// all tables.
+    pub const fn num_challenges(&self) -> usize {
+        self.by_table[0].num_challenges
+    }
+
+    /// Returns a configuration for the computations involving all tables,
+    /// which only depend on the number of challenges.
+    pub(crate) fn ctl_config(&self) -> &StarkConfig {
+        &self.by_table[0]
+    }
+}
+
+impl From<StarkConfig> for AllStarkConfig {
+    fn from(config: StarkConfig) -> Self {
+        Self::uniform(config)
+    }
 }
 
+fn check_memory_continuation_config(config: &StarkConfig) {
+    assert_eq!(
+        config.fri_config.cap_height, DEFAULT_CAP_HEIGHT,
+        "the memory continuation tables must use the default cap height"
+    );
+}
+
+/// The names of the `CrossTableLookups` used for proving the EVM, in the order
+/// of [`all_cross_table_lookups`].
+#[cfg(feature = "generation")]
+pub(crate) const CTL_NAMES: [&str; 12] = [
+    "arithmetic",
+    "byte_packing",
+    "keccak_sponge",
+    "keccak_inputs",
+    "keccak_outputs",
+    "logic",
+    "memory",
+    "mem_before",
+    "mem_after",
+    "context_pruning",
+    "range_check",
+    "ec",
+];
+
 /// Returns all the `CrossTableLookups` used for proving the EVM.
 pub(crate) fn all_cross_table_lookups<F: Field>() -> Vec<CrossTableLookup<F>> {
     vec![
@@ -135,16 +306,18 @@ pub(crate) fn all_cross_table_lookups<F: Field>() -> Vec<CrossTableLookup<F>> {
         ctl_mem_before(),
         ctl_mem_after(),
         ctl_context_pruning(),
+        ctl_range_check(),
+        ctl_ec(),
     ]
 }
 
 /// `CrossTableLookup` for `ArithmeticStark`, to connect it with the `Cpu`
-/// module.
+/// and `Ec` modules.
 fn ctl_arithmetic<F: Field>() -> CrossTableLookup<F> {
-    CrossTableLookup::new(
-        vec![cpu_stark::ctl_arithmetic_base_rows()],
-        arithmetic_stark::ctl_arithmetic_rows(),
-    )
+    let all_lookers = iter::once(cpu_stark::ctl_arithmetic_base_rows())
+        .chain(ec_stark::ctl_looking_arithmetic())
+        .collect();
+    CrossTableLookup::new(all_lookers, arithmetic_stark::ctl_arithmetic_rows())
 }
 
 /// `CrossTableLookup` for `BytePackingStark`, to connect it with the `Cpu`
@@ -317,6 +490,7 @@ fn ctl_memory<F: Field>() -> CrossTableLookup<F> {
     .chain(cpu_memory_gp_ops)
     .chain(keccak_sponge_reads)
     .chain(byte_packing_ops)
+    .chain(ec_stark::ctl_looking_memory())
     .chain(iter::once(mem_before_ops))
     .collect();
     let memory_looked = TableWithColumns::new(
@@ -327,6 +501,22 @@ fn ctl_memory<F: Field>() -> CrossTableLookup<F> {
     CrossTableLookup::new(all_lookers, memory_looked)
 }
 
+/// `CrossTableLookup` for `EcStark` to connect it with the `Cpu`
+/// module.
+fn ctl_ec<F: Field>() -> CrossTableLookup<F> {
+    let cpu_looking = TableWithColumns::new(
+        *Table::Cpu,
+        cpu_stark::ctl_data_ec(),
+        cpu_stark::ctl_filter_ec(),
+    );
+    let ec_looked = TableWithColumns::new(
+        *Table::Ec,
+        ec_stark::ctl_looked_data(),
+        ec_stark::ctl_looked_filter(),
+    );
+    CrossTableLookup::new(vec![cpu_looking], ec_looked)
+}
+
 /// `CrossTableLookup` for `Cpu` to propagate stale contexts to `Memory`.
 fn ctl_context_pruning<F: Field>() -> CrossTableLookup<F> {
     CrossTableLookup::new(
@@ -368,3 +558,73 @@ fn ctl_mem_after<F: Field>() -> CrossTableLookup<F> {
     );
     CrossTableLookup::new(all_lookers, mem_after_looked)
 }
+
+/// `CrossTableLookup` for `RangeCheckStark`, to connect it with the tables
+/// range-checking their values.
+fn ctl_range_check<F: Field>() -> CrossTableLookup<F> {
+    let all_lookers = iter::empty()
+        .chain(arithmetic_stark::ctl_looking_range_checks())
+        .chain(byte_packing_stark::ctl_looking_range_checks())
+        .chain(memory_stark::ctl_looking_range_checks())
+        .collect();
+    let range_check_looked = TableWithColumns::new(
+        *Table::RangeCheck,
+        range_check_stark::ctl_looked_data(),
+        range_check_stark::ctl_looked_filter(),
+    );
+    CrossTableLookup::new(all_lookers, range_check_looked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_one_table() {
+        let config = AllStarkConfig::standard_fast_config().with_fri_params(Table::Logic, 2, 2);
+        let standard = StarkConfig::standard_fast_config();
+
+        let logic = config.table(Table::Logic);
+        assert_eq!(logic.fri_config.rate_bits, 2);
+        assert_eq!(logic.fri_config.cap_height, 2);
+        assert_eq!(
+            logic.fri_config.num_query_rounds,
+            standard.fri_config.num_query_rounds
+        );
+        for &table in Table::all().iter().filter(|&&table| table != Table::Logic) {
+            assert_eq!(
+                config.table(table).fri_config.rate_bits,
+                standard.fri_config.rate_bits
+            );
+        }
+    }
+
+    #[test]
+    fn overrides_both_memory_continuation_tables() {
+        let config = AllStarkConfig::standard_fast_config().with_fri_params(
+            Table::MemAfter,
+            2,
+            DEFAULT_CAP_HEIGHT,
+        );
+        assert_eq!(config.table(Table::MemBefore).fri_config.rate_bits, 2);
+        assert_eq!(config.table(Table::MemAfter).fri_config.rate_bits, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "all tables must use the same number of challenges")]
+    fn rejects_mismatched_num_challenges() {
+        let mut config = StarkConfig::standard_fast_config();
+        config.num_challenges += 1;
+        AllStarkConfig::standard_fast_config().with_table_config(Table::Logic, config);
+    }
+
+    #[test]
+    #[should_panic(expected = "the memory continuation tables must use the default cap height")]
+    fn rejects_memory_continuation_cap_height() {
+        AllStarkConfig::standard_fast_config().with_fri_params(
+            Table::MemBefore,
+            1,
+            DEFAULT_CAP_HEIGHT + 1,
+        );
+    }
+}
@@ -14,20 +14,25 @@
 //!  GT: X > Z, inputs X, Z, output CY, auxiliary output Y
 //!  LT: Z < X, inputs Z, X, output CY, auxiliary output Y
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use itertools::Itertools;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
-use plonky2::field::types::{Field, PrimeField64};
+use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 
 use crate::arithmetic::columns::*;
+#[cfg(any(feature = "generation", test))]
 use crate::arithmetic::utils::u256_to_array;
 
 /// Generate row for ADD, SUB, GT and LT operations.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(
     lv: &mut [F],
     filter: usize,
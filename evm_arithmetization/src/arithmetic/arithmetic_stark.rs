@@ -3,24 +3,32 @@ use core::ops::Range;
 
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(any(feature = "generation", test))]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
+#[cfg(any(feature = "generation", test))]
 use plonky2::util::transpose;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::cross_table_lookup::TableWithColumns;
 use starky::evaluation_frame::StarkEvaluationFrame;
-use starky::lookup::{Column, Filter, Lookup};
+use starky::lookup::{Column, Filter};
 use starky::stark::Stark;
 use static_assertions::const_assert;
 
 use super::columns::{op_flags, NUM_ARITH_COLUMNS};
 use super::shift;
 use crate::all_stark::{EvmStarkFrame, Table};
-use crate::arithmetic::columns::{NUM_SHARED_COLS, RANGE_COUNTER, RC_FREQUENCIES, SHARED_COLS};
-use crate::arithmetic::{addcy, byte, columns, divmod, modular, mul, Operation};
+use crate::arithmetic::columns::SHARED_COLS;
+#[cfg(any(feature = "generation", test))]
+use crate::arithmetic::Operation;
+use crate::arithmetic::{addcy, byte, columns, divmod, modular, mul};
+#[cfg(any(feature = "generation", test))]
+use crate::range_check::range_check_stark::RangeCheckMultiplicities;
 
 /// Creates a vector of `Columns` to link the 16-bit columns of the arithmetic
 /// table, split into groups of N_LIMBS at a time in `regs`, with the
@@ -116,6 +124,20 @@ pub(crate) fn ctl_arithmetic_rows<F: Field>() -> TableWithColumns<F> {
     )
 }
 
+/// Returns the `TableWithColumns` range-checking each shared column of all
+/// rows, looking into the range-check table.
+pub(crate) fn ctl_looking_range_checks<F: Field>() -> Vec<TableWithColumns<F>> {
+    SHARED_COLS
+        .map(|col| {
+            TableWithColumns::new(
+                *Table::Arithmetic,
+                vec![Column::single(col)],
+                Filter::new_simple(Column::constant(F::ONE)),
+            )
+        })
+        .collect()
+}
+
 /// Structure representing the `Arithmetic` STARK, which carries out all the
 /// arithmetic operations.
 #[derive(Copy, Clone, Default)]
@@ -123,47 +145,20 @@ pub(crate) struct ArithmeticStark<F, const D: usize> {
     pub f: PhantomData<F>,
 }
 
-pub(crate) const RANGE_MAX: usize = 1usize << 16; // Range check strict upper bound
-
+#[cfg(any(feature = "generation", test))]
 impl<F: RichField, const D: usize> ArithmeticStark<F, D> {
-    /// Expects input in *column*-major layout
-    fn generate_range_checks(&self, cols: &mut [Vec<F>]) {
-        debug_assert!(cols.len() == columns::NUM_ARITH_COLUMNS);
-
-        let n_rows = cols[0].len();
-        debug_assert!(cols.iter().all(|col| col.len() == n_rows));
-
-        for i in 0..RANGE_MAX {
-            cols[columns::RANGE_COUNTER][i] = F::from_canonical_usize(i);
-        }
-        for i in RANGE_MAX..n_rows {
-            cols[columns::RANGE_COUNTER][i] = F::from_canonical_usize(RANGE_MAX - 1);
-        }
-
-        // Generate the frequencies column.
-        for col in SHARED_COLS {
-            for i in 0..n_rows {
-                let x = cols[col][i].to_canonical_u64() as usize;
-                assert!(
-                    x < RANGE_MAX,
-                    "column value {} exceeds the max range value {}",
-                    x,
-                    RANGE_MAX
-                );
-                cols[RC_FREQUENCIES][x] += F::ONE;
-            }
-        }
-    }
-
-    pub(crate) fn generate_trace(&self, operations: Vec<Operation>) -> Vec<PolynomialValues<F>> {
+    pub(crate) fn generate_trace(
+        &self,
+        operations: Vec<Operation>,
+        min_rows: usize,
+    ) -> Vec<PolynomialValues<F>> {
         // The number of rows reserved is the smallest value that's
         // guaranteed to avoid a reallocation: The only ops that use
         // two rows are the modular operations and DIV, so the only
         // way to reach capacity is when every op is modular or DIV
         // (which is obviously unlikely in normal
-        // circumstances). (Also need at least RANGE_MAX rows to
-        // accommodate range checks.)
-        let max_rows = std::cmp::max(2 * operations.len(), RANGE_MAX);
+        // circumstances).
+        let max_rows = std::cmp::max(2 * operations.len(), min_rows);
         let mut trace_rows = Vec::with_capacity(max_rows);
 
         for op in operations {
@@ -175,18 +170,29 @@ impl<F: RichField, const D: usize> ArithmeticStark<F, D> {
             }
         }
 
-        // Pad the trace with zero rows if it doesn't have enough rows
-        // to accommodate the range check columns. Also make sure the
-        // trace length is a power of two.
-        let padded_len = trace_rows.len().next_power_of_two();
-        for _ in trace_rows.len()..std::cmp::max(padded_len, RANGE_MAX) {
+        // Pad the trace with zero rows, and make sure the trace length is a
+        // power of two.
+        let padded_len = std::cmp::max(trace_rows.len(), min_rows).next_power_of_two();
+        for _ in trace_rows.len()..padded_len {
             trace_rows.push(vec![F::ZERO; columns::NUM_ARITH_COLUMNS]);
         }
 
-        let mut trace_cols = transpose(&trace_rows);
-        self.generate_range_checks(&mut trace_cols);
+        transpose(&trace_rows)
+            .into_iter()
+            .map(PolynomialValues::new)
+            .collect()
+    }
+}
 
-        trace_cols.into_iter().map(PolynomialValues::new).collect()
+/// Records the lookups of the shared columns of all rows in the range-check
+/// table.
+#[cfg(any(feature = "generation", test))]
+pub(crate) fn count_range_checks<F: PrimeField64>(
+    trace: &[PolynomialValues<F>],
+    multiplicities: &mut RangeCheckMultiplicities,
+) {
+    for col in SHARED_COLS {
+        multiplicities.extend(trace[col].values.iter().copied());
     }
 }
 
@@ -224,17 +230,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ArithmeticSta
         let opcode_constraint = (P::ONES - lv[columns::IS_RANGE_CHECK]) * lv[columns::OPCODE_COL];
         yield_constr.constraint(opcode_constraint);
 
-        // Check the range column: First value must be 0, last row
-        // must be 2^16-1, and intermediate rows must increment by 0
-        // or 1.
-        let rc1 = lv[columns::RANGE_COUNTER];
-        let rc2 = nv[columns::RANGE_COUNTER];
-        yield_constr.constraint_first_row(rc1);
-        let incr = rc2 - rc1;
-        yield_constr.constraint_transition(incr * incr - incr);
-        let range_max = P::Scalar::from_canonical_u64((RANGE_MAX - 1) as u64);
-        yield_constr.constraint_last_row(rc1 - range_max);
-
         // Evaluate constraints for the MUL operation.
         mul::eval_packed_generic(lv, yield_constr);
         // Evaluate constraints for ADD, SUB, LT and GT operations.
@@ -283,20 +278,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ArithmeticSta
         );
         yield_constr.constraint(builder, opcode_constraint);
 
-        // Check the range column: First value must be 0, last row
-        // must be 2^16-1, and intermediate rows must increment by 0
-        // or 1.
-        let rc1 = lv[columns::RANGE_COUNTER];
-        let rc2 = nv[columns::RANGE_COUNTER];
-        yield_constr.constraint_first_row(builder, rc1);
-        let incr = builder.sub_extension(rc2, rc1);
-        let t = builder.mul_sub_extension(incr, incr, incr);
-        yield_constr.constraint_transition(builder, t);
-        let range_max =
-            builder.constant_extension(F::Extension::from_canonical_usize(RANGE_MAX - 1));
-        let t = builder.sub_extension(rc1, range_max);
-        yield_constr.constraint_last_row(builder, t);
-
         // Evaluate constraints for the MUL operation.
         mul::eval_ext_circuit(builder, lv, yield_constr);
         // Evaluate constraints for ADD, SUB, LT and GT operations.
@@ -316,15 +297,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ArithmeticSta
         3
     }
 
-    fn lookups(&self) -> Vec<Lookup<F>> {
-        vec![Lookup {
-            columns: Column::singles(SHARED_COLS).collect(),
-            table_column: Column::single(RANGE_COUNTER),
-            frequencies_column: Column::single(RC_FREQUENCIES),
-            filter_columns: vec![Default::default(); NUM_SHARED_COLS],
-        }]
-    }
-
     fn requires_ctls(&self) -> bool {
         true
     }
@@ -339,8 +311,9 @@ mod tests {
     use rand_chacha::ChaCha8Rng;
     use starky::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
 
-    use super::ArithmeticStark;
+    use super::{count_range_checks, ArithmeticStark};
     use crate::arithmetic::*;
+    use crate::range_check::range_check_stark::{RangeCheckMultiplicities, RANGE_MAX};
 
     #[test]
     fn degree() -> Result<()> {
@@ -415,14 +388,18 @@ mod tests {
 
         let ops: Vec<Operation> = vec![add, mulmod, addmod, mul, modop, lt1, lt2, lt3, div, byte];
 
-        let pols = stark.generate_trace(ops);
+        let pols = stark.generate_trace(ops, 8);
 
-        // Trace should always have NUM_ARITH_COLUMNS columns and
-        // min(RANGE_MAX, operations.len()) rows. In this case there
-        // are only 6 rows, so we should have RANGE_MAX rows.
-        assert!(
-            pols.len() == columns::NUM_ARITH_COLUMNS
-                && pols.iter().all(|v| v.len() == super::RANGE_MAX)
+        // Trace should always have NUM_ARITH_COLUMNS columns and a power of
+        // two rows. In this case there are 14 rows, padded to 16.
+        assert!(pols.len() == columns::NUM_ARITH_COLUMNS && pols.iter().all(|v| v.len() == 16));
+
+        // All the shared columns are within the range-check table.
+        let mut multiplicities = RangeCheckMultiplicities::default();
+        count_range_checks(&pols, &mut multiplicities);
+        assert_eq!(
+            multiplicities.counts().iter().sum::<u64>(),
+            (16 * columns::NUM_SHARED_COLS) as u64
         );
 
         // Each operation has a single word answer that we can check
@@ -468,7 +445,7 @@ mod tests {
 
         let mut rng = ChaCha8Rng::seed_from_u64(0x6feb51b7ec230f25);
 
-        let ops = (0..super::RANGE_MAX)
+        let ops = (0..RANGE_MAX)
             .map(|_| {
                 Operation::binary(
                     BinaryOperator::Mul,
@@ -478,17 +455,16 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let pols = stark.generate_trace(ops);
+        let pols = stark.generate_trace(ops, 0);
 
-        // Trace should always have NUM_ARITH_COLUMNS columns and
-        // min(RANGE_MAX, operations.len()) rows. In this case there
-        // are RANGE_MAX operations with one row each, so RANGE_MAX.
+        // Trace should always have NUM_ARITH_COLUMNS columns and a power of
+        // two rows. In this case there are RANGE_MAX operations with one row
+        // each, so RANGE_MAX.
         assert!(
-            pols.len() == columns::NUM_ARITH_COLUMNS
-                && pols.iter().all(|v| v.len() == super::RANGE_MAX)
+            pols.len() == columns::NUM_ARITH_COLUMNS && pols.iter().all(|v| v.len() == RANGE_MAX)
         );
 
-        let ops = (0..super::RANGE_MAX)
+        let ops = (0..RANGE_MAX)
             .map(|_| {
                 Operation::ternary(
                     TernaryOperator::MulMod,
@@ -499,14 +475,14 @@ mod tests {
             })
             .collect::<Vec<_>>();
 
-        let pols = stark.generate_trace(ops);
+        let pols = stark.generate_trace(ops, 0);
 
-        // Trace should always have NUM_ARITH_COLUMNS columns and
-        // min(RANGE_MAX, operations.len()) rows. In this case there
-        // are RANGE_MAX operations with two rows each, so 2*RANGE_MAX.
+        // Trace should always have NUM_ARITH_COLUMNS columns and a power of
+        // two rows. In this case there are RANGE_MAX operations with two rows
+        // each, so 2*RANGE_MAX.
         assert!(
             pols.len() == columns::NUM_ARITH_COLUMNS
-                && pols.iter().all(|v| v.len() == 2 * super::RANGE_MAX)
+                && pols.iter().all(|v| v.len() == 2 * RANGE_MAX)
         );
     }
 }
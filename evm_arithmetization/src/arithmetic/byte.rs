@@ -61,23 +61,30 @@
 //!    256, 512, ..., 2^16 - 256}
 //! 8. Hence y ∈ {0, 1, ..., 255}
 
+#[cfg(any(feature = "generation", test))]
 use core::ops::Range;
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
-use plonky2::field::types::{Field, PrimeField64};
+use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+#[cfg(any(feature = "generation", test))]
 use static_assertions::const_assert;
 
 use crate::arithmetic::columns::*;
+#[cfg(any(feature = "generation", test))]
 use crate::arithmetic::utils::u256_to_array;
 
 // Give meaningful names to the columns of AUX_INPUT_REGISTER_0 that
 // we're using
+#[cfg(any(feature = "generation", test))]
 const BYTE_IDX_DECOMP: Range<usize> = AUX_INPUT_REGISTER_0.start..AUX_INPUT_REGISTER_0.start + 6;
 const BYTE_IDX_DECOMP_HI: usize = AUX_INPUT_REGISTER_0.start + 5;
 const BYTE_LAST_LIMB_LO: usize = AUX_INPUT_REGISTER_0.start + 6;
@@ -98,6 +105,7 @@ const BYTE_IDX_HI_LIMB_SUM_INV_3: usize = AUX_INPUT_REGISTER_0.start + 12;
 /// 5 bits of `idx0_lo5` in `idx_decomp[0..5]`; we don't explicitly need
 /// the higher 11 bits of the first limb, so we put them in
 /// `idx_decomp[5]`. The rest of `idx_decomp` is set to 0.
+#[cfg(any(feature = "generation", test))]
 fn set_idx_decomp<F: PrimeField64>(idx_decomp: &mut [F], idx: &U256) {
     debug_assert!(idx_decomp.len() == 6);
     for i in 0..5 {
@@ -106,6 +114,7 @@ fn set_idx_decomp<F: PrimeField64>(idx_decomp: &mut [F], idx: &U256) {
     idx_decomp[5] = F::from_canonical_u16((idx.low_u64() as u16) >> 5);
 }
 
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(lv: &mut [F], idx: U256, val: U256) {
     u256_to_array(&mut lv[INPUT_REGISTER_0], idx);
     u256_to_array(&mut lv[INPUT_REGISTER_1], val);
@@ -4,9 +4,11 @@
 
 use core::ops::Range;
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
+#[cfg(any(feature = "generation", test))]
 use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -14,13 +16,14 @@ use plonky2::plonk::circuit_builder::CircuitBuilder;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 
 use crate::arithmetic::columns::*;
-use crate::arithmetic::modular::{
-    generate_modular_op, modular_constr_poly, modular_constr_poly_ext_circuit,
-};
+#[cfg(any(feature = "generation", test))]
+use crate::arithmetic::modular::generate_modular_op;
+use crate::arithmetic::modular::{modular_constr_poly, modular_constr_poly_ext_circuit};
 use crate::arithmetic::utils::*;
 
 /// Generates the output and auxiliary values for modular operations,
 /// assuming the input, modular and output limbs are already set.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate_divmod<F: PrimeField64>(
     lv: &mut [F],
     nv: &mut [F],
@@ -65,6 +68,7 @@ pub(crate) fn generate_divmod<F: PrimeField64>(
     };
 }
 /// Generate the output and auxiliary values for modular operations.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(
     lv: &mut [F],
     nv: &mut [F],
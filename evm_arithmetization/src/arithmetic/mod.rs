@@ -1,12 +1,19 @@
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
+#[cfg(any(feature = "generation", test))]
 use plonky2::field::types::PrimeField64;
 
+#[cfg(any(feature = "generation", test))]
 use self::columns::{
     INPUT_REGISTER_0, INPUT_REGISTER_1, INPUT_REGISTER_2, OPCODE_COL, OUTPUT_REGISTER,
 };
+#[cfg(any(feature = "generation", test))]
 use self::utils::u256_to_array;
+#[cfg(any(feature = "generation", test))]
 use crate::arithmetic::columns::IS_RANGE_CHECK;
+#[cfg(any(feature = "generation", test))]
 use crate::extension_tower::BN_BASE;
+#[cfg(any(feature = "generation", test))]
 use crate::util::{addmod, mulmod, submod};
 
 mod addcy;
@@ -24,6 +31,7 @@ pub(crate) mod columns;
 ///
 /// `Shl` and `Shr` are handled differently, by leveraging `Mul` and `Div`
 /// respectively.
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum BinaryOperator {
     Add,
@@ -41,6 +49,7 @@ pub(crate) enum BinaryOperator {
     Shr, // simulated with DIV
 }
 
+#[cfg(any(feature = "generation", test))]
 impl BinaryOperator {
     /// Computes the result of a binary arithmetic operation given two inputs.
     pub(crate) fn result(&self, input0: U256, input1: U256) -> U256 {
@@ -114,6 +123,7 @@ impl BinaryOperator {
 
 /// An enum representing different ternary operations.
 #[allow(clippy::enum_variant_names)]
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum TernaryOperator {
     AddMod,
@@ -121,6 +131,7 @@ pub(crate) enum TernaryOperator {
     SubMod,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl TernaryOperator {
     /// Computes the result of a ternary arithmetic operation given three
     /// inputs.
@@ -146,6 +157,7 @@ impl TernaryOperator {
 /// An enum representing arithmetic operations that can be either binary or
 /// ternary.
 #[allow(clippy::enum_variant_names)]
+#[cfg(any(feature = "generation", test))]
 #[derive(Debug)]
 pub(crate) enum Operation {
     BinaryOperation {
@@ -170,6 +182,7 @@ pub(crate) enum Operation {
     },
 }
 
+#[cfg(any(feature = "generation", test))]
 impl Operation {
     /// Creates a binary operator with given inputs.
     ///
@@ -213,6 +226,7 @@ impl Operation {
         }
     }
 
+    #[cfg(feature = "generation")]
     pub(crate) const fn range_check(
         input0: U256,
         input1: U256,
@@ -277,6 +291,7 @@ impl Operation {
 
 /// Converts a ternary arithmetic operation to one or two rows of the
 /// `ArithmeticStark` table.
+#[cfg(any(feature = "generation", test))]
 fn ternary_op_to_rows<F: PrimeField64>(
     row_filter: usize,
     input0: U256,
@@ -296,6 +311,7 @@ fn ternary_op_to_rows<F: PrimeField64>(
 
 /// Converts a binary arithmetic operation to one or two rows of the
 /// `ArithmeticStark` table.
+#[cfg(any(feature = "generation", test))]
 fn binary_op_to_rows<F: PrimeField64>(
     op: BinaryOperator,
     input0: U256,
@@ -339,6 +355,7 @@ fn binary_op_to_rows<F: PrimeField64>(
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 fn range_check_to_rows<F: PrimeField64>(
     input0: U256,
     input1: U256,
@@ -107,14 +107,20 @@
 //!   arithmetic), specialised handling of MULMOD in that case would only
 //!   require 96 columns, or 80 if the output doesn't need to be reduced.
 
+#[cfg(any(feature = "generation", test))]
 use core::ops::Range;
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
+#[cfg(any(feature = "generation", test))]
 use num::bigint::Sign;
+#[cfg(any(feature = "generation", test))]
 use num::{BigInt, One, Zero};
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
-use plonky2::field::types::{Field, PrimeField64};
+use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
@@ -153,6 +159,7 @@ const fn bn254_modulus_limbs() -> [u16; N_LIMBS] {
 /// 2^16 + ε) because of the caller's range check on the inputs (the ε
 /// allows us to convert calculated output, which can be bigger than
 /// 2^16).
+#[cfg(any(feature = "generation", test))]
 fn columns_to_bigint<const N: usize>(limbs: &[i64; N]) -> BigInt {
     const BASE: i64 = 1i64 << LIMB_BITS;
 
@@ -189,6 +196,7 @@ fn columns_to_bigint<const N: usize>(limbs: &[i64; N]) -> BigInt {
 ///
 /// Note that `N` must be at least ceil(log2(num)/16) in order to be
 /// big enough to hold `num`.
+#[cfg(any(feature = "generation", test))]
 fn bigint_to_columns<const N: usize>(num: &BigInt) -> [i64; N] {
     assert!(num.bits() <= 16 * N as u64);
     let mut output = [0i64; N];
@@ -208,6 +216,7 @@ fn bigint_to_columns<const N: usize>(num: &BigInt) -> [i64; N] {
 ///
 /// NB: `operation` can set the higher order elements in its result to
 /// zero if they are not used.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate_modular_op<F: PrimeField64>(
     lv: &[F],
     nv: &mut [F],
@@ -340,6 +349,7 @@ pub(crate) fn generate_modular_op<F: PrimeField64>(
 /// Generate the output and auxiliary values for modular operations.
 ///
 /// `filter` must be one of `columns::IS_{ADD,MUL,SUB}{MOD,FP254}`.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(
     lv: &mut [F],
     nv: &mut [F],
@@ -55,10 +55,13 @@
 //! file `modular.rs`), we don't need to check that output is reduced,
 //! since any value of output is less than β^16 and is hence reduced.
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
-use plonky2::field::types::{Field, PrimeField64};
+use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
@@ -69,6 +72,7 @@ use crate::arithmetic::utils::*;
 
 /// Given the two limbs of `left_in` and `right_in`, computes `left_in *
 /// right_in`.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate_mul<F: PrimeField64>(lv: &mut [F], left_in: [i64; 16], right_in: [i64; 16]) {
     const MASK: i64 = (1i64 << LIMB_BITS) - 1i64;
 
@@ -108,6 +112,7 @@ pub(crate) fn generate_mul<F: PrimeField64>(lv: &mut [F], left_in: [i64; 16], ri
         .copy_from_slice(&aux_limbs.map(|c| F::from_canonical_u16((c >> 16) as u16)));
 }
 
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(lv: &mut [F], left_in: U256, right_in: U256) {
     // TODO: It would probably be clearer/cleaner to read the U256
     // into an [i64;N] and then copy that to the lv table.
@@ -20,9 +20,11 @@
 //! where β = 2^16 = 2^LIMB_BITS. To verify that A, S, B and C satisfy
 //! the equations, we proceed similarly to MUL for SHL and to DIV for SHR.
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
+#[cfg(any(feature = "generation", test))]
 use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -38,6 +40,7 @@ use crate::arithmetic::utils::*;
 /// NB: if `shift >= 256`, then the third register holds 0.
 /// We leverage the functions in mul.rs and divmod.rs to carry out
 /// the computation.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn generate<F: PrimeField64>(
     lv: &mut [F],
     nv: &mut [F],
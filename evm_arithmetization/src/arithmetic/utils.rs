@@ -1,14 +1,21 @@
-use core::ops::{Add, AddAssign, Mul, Neg, Range, Shr, Sub, SubAssign};
+use core::ops::{Add, AddAssign, Mul, Range, Sub, SubAssign};
+#[cfg(any(feature = "generation", test))]
+use core::ops::{Neg, Shr};
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use plonky2::field::extension::Extendable;
+#[cfg(any(feature = "generation", test))]
 use plonky2::field::types::{Field, PrimeField64};
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
+#[cfg(any(feature = "generation", test))]
 use static_assertions::const_assert;
 
-use crate::arithmetic::columns::{LIMB_BITS, N_LIMBS};
+#[cfg(any(feature = "generation", test))]
+use crate::arithmetic::columns::LIMB_BITS;
+use crate::arithmetic::columns::N_LIMBS;
 
 /// Return an array of `N` zeros of type T.
 pub(crate) fn pol_zero<T, const N: usize>() -> [T; N]
@@ -216,6 +223,7 @@ pub(crate) fn pol_mul_lo_ext_circuit<F: RichField + Extendable<D>, const D: usiz
 }
 
 /// Adjoin M - N zeros to a, returning [a[0], a[1], ..., a[N-1], 0, 0, ..., 0].
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn pol_extend<T, const N: usize, const M: usize>(a: [T; N]) -> [T; M]
 where
     T: Copy + Default,
@@ -275,6 +283,7 @@ pub(crate) fn pol_adjoin_root_ext_circuit<
 /// NB: The result could be returned in N-1 elements, but we return
 /// N and set the last element to zero since the calling code
 /// happens to require a result zero-extended to N elements.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn pol_remove_root_2exp<const EXP: usize, T, const N: usize>(a: [T; N]) -> [T; N]
 where
     T: Copy + Default + Neg<Output = T> + Shr<usize, Output = T> + Sub<Output = T>,
@@ -310,6 +319,7 @@ pub(crate) fn read_value<const N: usize, T: Copy>(lv: &[T], value_idxs: Range<us
 /// Read the range `value_idxs` of values from `lv` into an array of
 /// length `N`, interpreting the values as `i64`s. Panics if the
 /// length of the range is not `N`.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn read_value_i64_limbs<const N: usize, F: PrimeField64>(
     lv: &[F],
     value_idxs: Range<usize>,
@@ -321,6 +331,7 @@ pub(crate) fn read_value_i64_limbs<const N: usize, F: PrimeField64>(
 #[inline]
 /// Turn a 64-bit integer into 4 16-bit limbs and convert them to field
 /// elements.
+#[cfg(any(feature = "generation", test))]
 fn u64_to_array<F: Field>(out: &mut [F], x: u64) {
     const_assert!(LIMB_BITS == 16);
     debug_assert!(out.len() == 4);
@@ -334,6 +345,7 @@ fn u64_to_array<F: Field>(out: &mut [F], x: u64) {
 /// Turn a 256-bit integer into 16 16-bit limbs and convert them to field
 /// elements.
 // TODO: Refactor/replace u256_limbs in evm/src/util.rs
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn u256_to_array<F: Field>(out: &mut [F], x: U256) {
     const_assert!(N_LIMBS == 16);
     debug_assert!(out.len() == N_LIMBS);
@@ -33,25 +33,36 @@ use std::borrow::Borrow;
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
+#[cfg(feature = "generation")]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use plonky2::timed;
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
+#[cfg(feature = "generation")]
 use plonky2::util::transpose;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use starky::cross_table_lookup::TableWithColumns;
 use starky::evaluation_frame::StarkEvaluationFrame;
-use starky::lookup::{Column, Filter, Lookup};
+use starky::lookup::{Column, Filter};
 use starky::stark::Stark;
 
 use super::NUM_BYTES;
-use crate::all_stark::EvmStarkFrame;
+use crate::all_stark::{EvmStarkFrame, Table};
 use crate::byte_packing::columns::*;
+#[cfg(feature = "generation")]
+use crate::range_check::range_check_stark::RangeCheckMultiplicities;
+#[cfg(feature = "generation")]
 use crate::witness::memory::MemoryAddress;
 
-/// Strict upper bound for the individual bytes range-check.
-const BYTE_RANGE_MAX: usize = 1usize << 8;
+/// The factor by which bytes are shifted into the upper half of the 16-bit
+/// range, so that both checks together bound them by 256.
+const BYTE_SHIFT: u64 = 1 << 8;
 
 /// Creates the vector of `Columns` for `BytePackingStark` corresponding to the
 /// final packed limbs being read/written. `CpuStark` will look into these
@@ -148,7 +159,54 @@ pub(crate) fn ctl_looking_memory_filter<F: Field>(i: usize) -> Filter<F> {
     ))
 }
 
+/// Returns the `TableWithColumns` range-checking the bytes of the active rows,
+/// looking into the 16-bit range-check table.
+///
+/// Each byte `b` is looked up both as `b` and `256 * b`: as `b < 2^16`, the
+/// latter can't wrap around the field, and lies in the range iff `b < 256`.
+pub(crate) fn ctl_looking_range_checks<F: Field>() -> Vec<TableWithColumns<F>> {
+    let filter = || {
+        Filter::new_simple(Column::sum(
+            (0..NUM_BYTES).map(|i| BYTE_PACKING_COL_MAP.index_len[i]),
+        ))
+    };
+    BYTE_PACKING_COL_MAP
+        .value_bytes
+        .into_iter()
+        .flat_map(|byte| {
+            [
+                Column::single(byte),
+                Column::linear_combination([(byte, F::from_canonical_u64(BYTE_SHIFT))]),
+            ]
+        })
+        .map(|column| TableWithColumns::new(*Table::BytePacking, vec![column], filter()))
+        .collect()
+}
+
+/// Records the lookups of the bytes of the active rows in the range-check
+/// table.
+#[cfg(feature = "generation")]
+pub(crate) fn count_range_checks<F: PrimeField64>(
+    trace: &[PolynomialValues<F>],
+    multiplicities: &mut RangeCheckMultiplicities,
+) {
+    let shift = F::from_canonical_u64(BYTE_SHIFT);
+    for row in 0..trace[0].len() {
+        let is_active = BYTE_PACKING_COL_MAP
+            .index_len
+            .iter()
+            .any(|&col| trace[col].values[row].is_one());
+        if is_active {
+            for byte in BYTE_PACKING_COL_MAP.value_bytes {
+                let byte = trace[byte].values[row];
+                multiplicities.extend([byte, byte * shift]);
+            }
+        }
+    }
+}
+
 /// Information about a byte packing operation needed for witness generation.
+#[cfg(feature = "generation")]
 #[derive(Clone, Debug)]
 pub(crate) struct BytePackingOp {
     /// Whether this is a read (packing) or write (unpacking) operation.
@@ -170,6 +228,7 @@ pub(crate) struct BytePackingStark<F, const D: usize> {
     pub(crate) f: PhantomData<F>,
 }
 
+#[cfg(feature = "generation")]
 impl<F: RichField + Extendable<D>, const D: usize> BytePackingStark<F, D> {
     pub(crate) fn generate_trace(
         &self,
@@ -185,10 +244,10 @@ impl<F: RichField + Extendable<D>, const D: usize> BytePackingStark<F, D> {
         );
         let trace_row_vecs: Vec<_> = trace_rows.into_iter().map(|row| row.to_vec()).collect();
 
-        let mut trace_cols = transpose(&trace_row_vecs);
-        self.generate_range_checks(&mut trace_cols);
-
-        trace_cols.into_iter().map(PolynomialValues::new).collect()
+        transpose(&trace_row_vecs)
+            .into_iter()
+            .map(PolynomialValues::new)
+            .collect()
     }
 
     fn generate_trace_rows(
@@ -196,7 +255,7 @@ impl<F: RichField + Extendable<D>, const D: usize> BytePackingStark<F, D> {
         ops: Vec<BytePackingOp>,
         min_rows: usize,
     ) -> Vec<[F; NUM_COLUMNS]> {
-        let num_rows = core::cmp::max(ops.len().max(BYTE_RANGE_MAX), min_rows).next_power_of_two();
+        let num_rows = core::cmp::max(ops.len(), min_rows).next_power_of_two();
         let mut rows = Vec::with_capacity(num_rows);
 
         for op in ops {
@@ -249,39 +308,6 @@ impl<F: RichField + Extendable<D>, const D: usize> BytePackingStark<F, D> {
     const fn generate_padding_row(&self) -> [F; NUM_COLUMNS] {
         [F::ZERO; NUM_COLUMNS]
     }
-
-    /// Expects input in *column*-major layout
-    fn generate_range_checks(&self, cols: &mut [Vec<F>]) {
-        debug_assert!(cols.len() == NUM_COLUMNS);
-
-        let n_rows = cols[0].len();
-        debug_assert!(cols.iter().all(|col| col.len() == n_rows));
-
-        for i in 0..BYTE_RANGE_MAX {
-            cols[BYTE_PACKING_COL_MAP.range_counter][i] = F::from_canonical_usize(i);
-        }
-        for i in BYTE_RANGE_MAX..n_rows {
-            cols[BYTE_PACKING_COL_MAP.range_counter][i] =
-                F::from_canonical_usize(BYTE_RANGE_MAX - 1);
-        }
-
-        // For each column c in cols, generate the range-check
-        // permutations and put them in the corresponding range-check
-        // columns rc_c and rc_c+1.
-        for col in 0..NUM_BYTES {
-            for i in 0..n_rows {
-                let c = BYTE_PACKING_COL_MAP.value_bytes[col];
-                let x = cols[c][i].to_canonical_u64() as usize;
-                assert!(
-                    x < BYTE_RANGE_MAX,
-                    "column value {} exceeds the max range value {}",
-                    x,
-                    BYTE_RANGE_MAX
-                );
-                cols[BYTE_PACKING_COL_MAP.rc_frequencies][x] += F::ONE;
-            }
-        }
-    }
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for BytePackingStark<F, D> {
@@ -305,17 +331,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for BytePackingSt
         let next_values: &[P; NUM_COLUMNS] = vars.get_next_values().try_into().unwrap();
         let next_values: &BytePackingColumnsView<P> = next_values.borrow();
 
-        // Check the range column: First value must be 0, last row
-        // must be 255, and intermediate rows must increment by 0
-        // or 1.
-        let rc1 = local_values.range_counter;
-        let rc2 = next_values.range_counter;
-        yield_constr.constraint_first_row(rc1);
-        let incr = rc2 - rc1;
-        yield_constr.constraint_transition(incr * incr - incr);
-        let range_max = P::Scalar::from_canonical_u64((BYTE_RANGE_MAX - 1) as u64);
-        yield_constr.constraint_last_row(rc1 - range_max);
-
         let one = P::ONES;
 
         // We filter active columns by summing all the byte indices.
@@ -363,20 +378,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for BytePackingSt
             vars.get_next_values().try_into().unwrap();
         let next_values: &BytePackingColumnsView<ExtensionTarget<D>> = next_values.borrow();
 
-        // Check the range column: First value must be 0, last row
-        // must be 255, and intermediate rows must increment by 0
-        // or 1.
-        let rc1 = local_values.range_counter;
-        let rc2 = next_values.range_counter;
-        yield_constr.constraint_first_row(builder, rc1);
-        let incr = builder.sub_extension(rc2, rc1);
-        let t = builder.mul_sub_extension(incr, incr, incr);
-        yield_constr.constraint_transition(builder, t);
-        let range_max =
-            builder.constant_extension(F::Extension::from_canonical_usize(BYTE_RANGE_MAX - 1));
-        let t = builder.sub_extension(rc1, range_max);
-        yield_constr.constraint_last_row(builder, t);
-
         // We filter active columns by summing all the byte indices.
         // Constraining each of them to be boolean is done later on below.
         let current_filter = builder.add_many_extension(local_values.index_len);
@@ -422,19 +423,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for BytePackingSt
         3
     }
 
-    fn lookups(&self) -> Vec<Lookup<F>> {
-        vec![Lookup {
-            columns: Column::singles(
-                BYTE_PACKING_COL_MAP.value_bytes[0]
-                    ..BYTE_PACKING_COL_MAP.value_bytes[0] + NUM_BYTES,
-            )
-            .collect(),
-            table_column: Column::single(BYTE_PACKING_COL_MAP.range_counter),
-            frequencies_column: Column::single(BYTE_PACKING_COL_MAP.rc_frequencies),
-            filter_columns: vec![Default::default(); NUM_BYTES],
-        }]
-    }
-
     fn requires_ctls(&self) -> bool {
         true
     }
@@ -27,12 +27,6 @@ pub(crate) struct BytePackingColumnsView<T: Copy> {
     // There are `NUM_BYTES` columns used to store the values of the bytes
     // that are being read/written for an (un)packing operation.
     pub value_bytes: [T; NUM_BYTES],
-
-    /// The counter column (used for the logUp range check) starts from 0 and
-    /// increments.
-    pub range_counter: T,
-    /// The frequencies column used in logUp.
-    pub rc_frequencies: T,
 }
 
 // `u8` is guaranteed to have a `size_of` of 1.
@@ -0,0 +1,307 @@
+//! A native checker of the constraints of all STARKs over their traces.
+//!
+//! Proving a segment whose traces break a constraint only fails once the
+//! quotient polynomials are computed, without pointing at the offending
+//! constraint. [`check_constraints`] instead evaluates the constraints of each
+//! STARK on every row of its trace, and reports the failing ones by index, in
+//! the order in which the STARK emits them.
+//!
+//! The cross-table lookups between the traces are not checked here.
+
+use core::fmt;
+
+use anyhow::Result;
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::{Field, Sample};
+use plonky2::hash::hash_types::RichField;
+use plonky2::util::log2_ceil;
+use plonky2::util::timing::TimingTree;
+use starky::constraint_consumer::ConstraintConsumer;
+use starky::evaluation_frame::StarkEvaluationFrame;
+use starky::stark::Stark;
+
+use crate::all_stark::{AllStark, AllStarkConfig, Table, NUM_TABLES};
+use crate::generation::{generate_traces, TrimmedGenerationInputs};
+use crate::prover::GenerationSegmentData;
+
+/// A constraint of a STARK which doesn't hold on a row of its trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    pub table: Table,
+    /// The index of the constraint, in the order in which the STARK evaluates
+    /// its constraints.
+    pub constraint: usize,
+    pub row: usize,
+}
+
+impl fmt::Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraint #{} of {:?} fails on row {}",
+            self.constraint, self.table, self.row
+        )
+    }
+}
+
+/// Generates the traces of a segment, and checks the constraints of all
+/// STARKs over them.
+pub fn check_segment_constraints<F: RichField + Extendable<D>, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &AllStarkConfig,
+    inputs: &TrimmedGenerationInputs,
+    segment_data: &mut GenerationSegmentData,
+) -> Result<Vec<ConstraintFailure>> {
+    let (traces, _) = generate_traces(
+        all_stark,
+        inputs,
+        config,
+        segment_data,
+        &mut TimingTree::default(),
+    )?;
+    Ok(check_constraints(all_stark, &traces))
+}
+
+/// Checks the constraints of all STARKs over their traces, in the order of
+/// [`Table`].
+///
+/// Only the first failing row of each trace is reported, along with all the
+/// constraints failing on it.
+pub fn check_constraints<F: RichField + Extendable<D>, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    traces: &[Vec<PolynomialValues<F>>; NUM_TABLES],
+) -> Vec<ConstraintFailure> {
+    let trace = |table: Table| &traces[*table];
+    [
+        check_stark(
+            &all_stark.arithmetic_stark,
+            Table::Arithmetic,
+            trace(Table::Arithmetic),
+        ),
+        check_stark(
+            &all_stark.byte_packing_stark,
+            Table::BytePacking,
+            trace(Table::BytePacking),
+        ),
+        check_stark(&all_stark.cpu_stark, Table::Cpu, trace(Table::Cpu)),
+        check_stark(&all_stark.keccak_stark, Table::Keccak, trace(Table::Keccak)),
+        check_stark(
+            &all_stark.keccak_sponge_stark,
+            Table::KeccakSponge,
+            trace(Table::KeccakSponge),
+        ),
+        check_stark(&all_stark.logic_stark, Table::Logic, trace(Table::Logic)),
+        check_stark(&all_stark.memory_stark, Table::Memory, trace(Table::Memory)),
+        check_stark(
+            &all_stark.mem_before_stark,
+            Table::MemBefore,
+            trace(Table::MemBefore),
+        ),
+        check_stark(
+            &all_stark.mem_after_stark,
+            Table::MemAfter,
+            trace(Table::MemAfter),
+        ),
+        check_stark(
+            &all_stark.range_check_stark,
+            Table::RangeCheck,
+            trace(Table::RangeCheck),
+        ),
+        check_stark(&all_stark.ec_stark, Table::Ec, trace(Table::Ec)),
+    ]
+    .concat()
+}
+
+/// Checks the constraints of `stark` over its trace, given by columns.
+///
+/// A row is first checked against a random combination of all constraints. On
+/// the first failing row, the combination is evaluated at all the powers of a
+/// root of unity, so that the value of each constraint is recovered as a
+/// coefficient of its interpolant.
+pub(crate) fn check_stark<F, S, const D: usize>(
+    stark: &S,
+    table: Table,
+    trace: &[PolynomialValues<F>],
+) -> Vec<ConstraintFailure>
+where
+    F: RichField + Extendable<D>,
+    S: Stark<F, D>,
+{
+    let num_rows = trace.first().map_or(0, |column| column.len());
+    let row_values = |row: usize| -> Vec<F> { trace.iter().map(|c| c.values[row]).collect() };
+
+    let alpha = F::rand();
+    let Some(row) = (0..num_rows).find(|&row| {
+        let next_row = (row + 1) % num_rows;
+        let selectors = RowSelectors::at(row, num_rows);
+        eval_combination(
+            stark,
+            &row_values(row),
+            &row_values(next_row),
+            selectors,
+            alpha,
+        ) != F::ZERO
+    }) else {
+        return vec![];
+    };
+
+    let num_constraints = count_constraints(stark);
+    let values = constraint_values(
+        stark,
+        &row_values(row),
+        &row_values((row + 1) % num_rows),
+        RowSelectors::at(row, num_rows),
+        log2_ceil(num_constraints),
+    );
+    // The last constraint is the constant coefficient of the interpolant.
+    values
+        .iter()
+        .take(num_constraints)
+        .enumerate()
+        .filter(|(_, &value)| value != F::ZERO)
+        .map(|(degree, _)| ConstraintFailure {
+            table,
+            constraint: num_constraints - 1 - degree,
+            row,
+        })
+        .collect()
+}
+
+/// The values of the Lagrange selectors and of the vanishing polynomial of
+/// the last row, on a given row.
+#[derive(Clone, Copy)]
+struct RowSelectors<F> {
+    z_last: F,
+    lagrange_first: F,
+    lagrange_last: F,
+}
+
+impl<F: Field> RowSelectors<F> {
+    fn at(row: usize, num_rows: usize) -> Self {
+        let is_first = row == 0;
+        let is_last = row + 1 == num_rows;
+        Self {
+            z_last: F::from_bool(!is_last),
+            lagrange_first: F::from_bool(is_first),
+            lagrange_last: F::from_bool(is_last),
+        }
+    }
+}
+
+/// Evaluates the combination of all the constraints of `stark` by powers of
+/// `alpha`.
+fn eval_combination<F, S, const D: usize>(
+    stark: &S,
+    local_values: &[F],
+    next_values: &[F],
+    selectors: RowSelectors<F>,
+    alpha: F,
+) -> F
+where
+    F: RichField + Extendable<D>,
+    S: Stark<F, D>,
+{
+    let vars = <S::EvaluationFrame<F, F, 1> as StarkEvaluationFrame<F, F>>::from_values(
+        local_values,
+        next_values,
+        &[],
+    );
+    let mut consumer = ConstraintConsumer::new(
+        vec![alpha],
+        selectors.z_last,
+        selectors.lagrange_first,
+        selectors.lagrange_last,
+    );
+    stark.eval_packed_generic(&vars, &mut consumer);
+    consumer.accumulators()[0]
+}
+
+/// Returns the values of all the constraints of `stark`, in reverse order,
+/// provided there are at most `2^log_bound` of them.
+fn constraint_values<F, S, const D: usize>(
+    stark: &S,
+    local_values: &[F],
+    next_values: &[F],
+    selectors: RowSelectors<F>,
+    log_bound: usize,
+) -> Vec<F>
+where
+    F: RichField + Extendable<D>,
+    S: Stark<F, D>,
+{
+    let evals = F::two_adic_subgroup(log_bound)
+        .into_iter()
+        .map(|alpha| eval_combination(stark, local_values, next_values, selectors, alpha))
+        .collect();
+    PolynomialValues::new(evals).ifft().coeffs
+}
+
+/// Returns the number of constraints of `stark`.
+///
+/// On random values, all constraints are nonzero with high probability, so
+/// that their number is the length of the interpolant of their combination.
+fn count_constraints<F, S, const D: usize>(stark: &S) -> usize
+where
+    F: RichField + Extendable<D>,
+    S: Stark<F, D>,
+{
+    let local_values = F::rand_vec(S::COLUMNS);
+    let next_values = F::rand_vec(S::COLUMNS);
+    let selectors = RowSelectors {
+        z_last: F::ONE,
+        lagrange_first: F::ONE,
+        lagrange_last: F::ONE,
+    };
+
+    // Grow the bound until the interpolant doesn't use all coefficients.
+    (1..)
+        .find_map(|log_bound| {
+            let values =
+                constraint_values(stark, &local_values, &next_values, selectors, log_bound);
+            let len = values
+                .iter()
+                .rposition(|&v| v != F::ZERO)
+                .map_or(0, |i| i + 1);
+            (len < values.len()).then_some(len)
+        })
+        .expect("STARKs have finitely many constraints")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::U256;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::util::timing::TimingTree;
+
+    use super::*;
+    use crate::logic::columns::LOGIC_COL_MAP;
+    use crate::logic::{LogicStark, Op, Operation};
+
+    type F = GoldilocksField;
+    const D: usize = 2;
+
+    #[test]
+    fn reports_failing_constraint() {
+        let stark = LogicStark::<F, D>::default();
+        let operations = vec![
+            Operation::new(Op::And, U256::from(0b1100), U256::from(0b1010)),
+            Operation::new(Op::Xor, U256::MAX, U256::from(42)),
+        ];
+        let mut trace = stark.generate_trace(operations, 8, &mut TimingTree::default());
+        assert_eq!(check_stark(&stark, Table::Logic, &trace), vec![]);
+
+        // Flags, then input bits, then result limbs.
+        assert_eq!(count_constraints(&stark), 4 + 2 * 256 + 8);
+
+        trace[LOGIC_COL_MAP.result[0]].values[1] += F::ONE;
+        assert_eq!(
+            check_stark(&stark, Table::Logic, &trace),
+            vec![ConstraintFailure {
+                table: Table::Logic,
+                constraint: 4 + 2 * 256,
+                row: 1,
+            }]
+        );
+    }
+}
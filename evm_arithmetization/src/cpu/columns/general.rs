@@ -29,6 +29,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
     /// Mutable view of the column required for exceptions: they are the
     /// exception code bits. SAFETY: Each view is a valid interpretation of
     /// the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn exception_mut(&mut self) -> &mut CpuExceptionView<T> {
         unsafe { &mut self.exception }
     }
@@ -41,6 +42,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the columns required for logic operations.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn logic_mut(&mut self) -> &mut CpuLogicView<T> {
         unsafe { &mut self.logic }
     }
@@ -53,6 +55,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the columns required for jump operations.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn jumps_mut(&mut self) -> &mut CpuJumpsView<T> {
         unsafe { &mut self.jumps }
     }
@@ -65,6 +68,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the columns required for shift operations.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn shift_mut(&mut self) -> &mut CpuShiftView<T> {
         unsafe { &mut self.shift }
     }
@@ -77,6 +81,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the columns required for the stack top.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn stack_mut(&mut self) -> &mut CpuStackView<T> {
         unsafe { &mut self.stack }
     }
@@ -89,6 +94,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the columns required for the push operation.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn push_mut(&mut self) -> &mut CpuPushView<T> {
         unsafe { &mut self.push }
     }
@@ -101,6 +107,7 @@ impl<T: Copy> CpuGeneralColumnsView<T> {
 
     /// Mutable view of the column for context pruning.
     /// SAFETY: Each view is a valid interpretation of the underlying array.
+    #[cfg(feature = "generation")]
     pub(crate) fn context_pruning_mut(&mut self) -> &mut CpuContextPruningView<T> {
         unsafe { &mut self.context_pruning }
     }
@@ -36,6 +36,9 @@ pub(crate) struct OpsColumnsView<T: Copy> {
     pub m_op_general: T,
     /// Combines PC and PUSH0
     pub pc_push0: T,
+    /// Combines SECP_ADD, SECP_DOUBLE, BN_G1_ADD, BN_G1_DOUBLE, SECP_MUL and
+    /// BN_G1_MUL flags.
+    pub ec_op: T,
 
     /// Flag for syscalls.
     pub syscall: T,
@@ -31,6 +31,7 @@ const KEEPS_CONTEXT: OpsColumnsView<bool> = OpsColumnsView {
     m_op_32bytes: true,
     exit_kernel: true,
     m_op_general: true,
+    ec_op: true,
     syscall: true,
     exception: true,
 };
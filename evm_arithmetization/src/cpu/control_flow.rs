@@ -8,7 +8,7 @@ use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsume
 use crate::cpu::columns::{CpuColumnsView, COL_MAP};
 use crate::cpu::kernel::aggregator::KERNEL;
 
-const NATIVE_INSTRUCTIONS: [usize; 12] = [
+const NATIVE_INSTRUCTIONS: [usize; 13] = [
     COL_MAP.op.binary_op,
     COL_MAP.op.ternary_op,
     COL_MAP.op.fp254_op,
@@ -25,6 +25,7 @@ const NATIVE_INSTRUCTIONS: [usize; 12] = [
     COL_MAP.op.context_op,
     // not EXIT_KERNEL (performs a jump)
     COL_MAP.op.m_op_general,
+    COL_MAP.op.ec_op,
     // not SYSCALL (performs a jump)
     // not exceptions (also jump)
 ];
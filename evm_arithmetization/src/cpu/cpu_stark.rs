@@ -65,6 +65,31 @@ pub(crate) fn ctl_filter_keccak_sponge<F: Field>() -> Filter<F> {
     )
 }
 
+/// Creates the vector of `Columns` corresponding to an elliptic curve point
+/// operation: whether it is a doubling, whether it is on BN254, whether it is
+/// a scalar multiplication, the base address of the inputs and the timestamp
+/// of the call.
+pub(crate) fn ctl_data_ec<F: Field>() -> Vec<Column<F>> {
+    // When executing an EC operation, GP channel 0 holds the popped base
+    // address. The lowest opcode bit is set for doublings, the second one for
+    // BN254 operations, and the third one for scalar multiplications.
+    let (context, segment, virt) = get_addr(&COL_MAP, 0);
+    let num_channels = F::from_canonical_usize(NUM_CHANNELS);
+    let timestamp = Column::linear_combination_with_constant(
+        [(COL_MAP.clock, num_channels)],
+        F::ONE - num_channels,
+    );
+    let mut cols = Column::singles(&COL_MAP.opcode_bits[..3]).collect_vec();
+    cols.extend(Column::singles([context, segment, virt]));
+    cols.push(timestamp);
+    cols
+}
+
+/// CTL filter for a call to the EC table.
+pub(crate) fn ctl_filter_ec<F: Field>() -> Filter<F> {
+    Filter::new_simple(Column::single(COL_MAP.op.ec_op))
+}
+
 /// Creates the vector of `Columns` corresponding to the two inputs and
 /// one output of a binary operation.
 fn ctl_data_binops<F: Field>() -> Vec<Column<F>> {
@@ -29,7 +29,7 @@ use crate::cpu::columns::{CpuColumnsView, COL_MAP};
 /// Note: invalid opcodes are not represented here. _Any_ opcode is permitted to
 /// decode to `is_invalid`. The kernel then verifies that the opcode was
 /// _actually_ invalid.
-const OPCODES: [(u8, usize, bool, usize); 5] = [
+const OPCODES: [(u8, usize, bool, usize); 6] = [
     // (start index of block, number of top bits to check (log2), kernel-only, flag column)
     // ADD, MUL, SUB, DIV, MOD, LT, GT and BYTE flags are handled partly manually here, and partly
     // through the Arithmetic table CTL. ADDMOD, MULMOD and SUBMOD flags are handled partly
@@ -40,6 +40,9 @@ const OPCODES: [(u8, usize, bool, usize); 5] = [
     // CTL. NOT and POP are handled manually here.
     // SHL and SHR flags are handled partly manually here, and partly through the Logic table CTL.
     // JUMPDEST and KECCAK_GENERAL are handled manually here.
+    // SECP_ADD, SECP_DOUBLE, BN_G1_ADD, BN_G1_DOUBLE, SECP_MUL and BN_G1_MUL flags are handled
+    // partly manually here, and partly through the Ec table CTL.
+    (0x28, 3, true, COL_MAP.op.ec_op),      // 0x28-0x2f
     (0x56, 1, false, COL_MAP.op.jumps),     // 0x56-0x57
     (0x80, 5, false, COL_MAP.op.dup_swap),  // 0x80-0x9f
     (0xf6, 1, true, COL_MAP.op.context_op), //0xf6-0xf7
@@ -9,7 +9,7 @@ use super::assembler::{assemble, Kernel};
 use crate::cpu::kernel::constants::evm_constants;
 use crate::cpu::kernel::parser::parse;
 
-pub const NUMBER_KERNEL_FILES: usize = 159;
+pub const NUMBER_KERNEL_FILES: usize = 164;
 
 pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
     "global jumped_to_0: PANIC",
@@ -32,6 +32,7 @@ pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
     include_str!("asm/core/create_contract_account.asm"),
     include_str!("asm/core/exception.asm"),
     include_str!("asm/core/create_receipt.asm"),
+    include_str!("asm/core/eof.asm"),
     include_str!("asm/core/gas.asm"),
     include_str!("asm/core/intrinsic_gas.asm"),
     include_str!("asm/core/jumpdest_analysis.asm"),
@@ -57,6 +58,7 @@ pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
     include_str!("asm/core/precompiles/snarkv.asm"),
     include_str!("asm/core/precompiles/blake2_f.asm"),
     include_str!("asm/core/precompiles/kzg_peval.asm"),
+    include_str!("asm/core/precompiles/p256_verify.asm"),
     include_str!("asm/curve/bls381/util.asm"),
     include_str!("asm/curve/bn254/curve_arithmetic/constants.asm"),
     include_str!("asm/curve/bn254/curve_arithmetic/curve_add.asm"),
@@ -77,7 +79,10 @@ pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
     include_str!("asm/curve/bn254/field_arithmetic/inverse.asm"),
     include_str!("asm/curve/bn254/field_arithmetic/util.asm"),
     include_str!("asm/curve/common.asm"),
+    include_str!("asm/curve/p256/curve_add.asm"),
+    include_str!("asm/curve/p256/verify.asm"),
     include_str!("asm/curve/secp256k1/curve_add.asm"),
+    include_str!("asm/curve/secp256k1/curve_mul.asm"),
     include_str!("asm/curve/secp256k1/ecrecover.asm"),
     include_str!("asm/curve/secp256k1/inverse_scalar.asm"),
     include_str!("asm/curve/secp256k1/lift_x.asm"),
@@ -165,6 +170,7 @@ pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
     include_str!("asm/transactions/type_1.asm"),
     include_str!("asm/transactions/type_2.asm"),
     include_str!("asm/transactions/type_3.asm"),
+    include_str!("asm/transactions/type_7e.asm"),
     include_str!("asm/util/assertions.asm"),
     include_str!("asm/util/basic_macros.asm"),
     include_str!("asm/util/keccak.asm"),
@@ -177,8 +183,27 @@ pub static KERNEL_FILES: [&str; NUMBER_KERNEL_FILES] = [
 
 pub static KERNEL: Lazy<Kernel> = Lazy::new(combined_kernel);
 
+/// The features of the kernel enabled by the crate features, gating its
+/// `#[cfg(feature = ...)]` blocks.
+pub(crate) fn active_features() -> HashSet<&'static str> {
+    #[allow(unused_mut)]
+    let mut features = HashSet::new();
+    #[cfg(feature = "p256_verify")]
+    features.insert("p256_verify");
+    #[cfg(feature = "eof")]
+    features.insert("eof");
+    #[cfg(feature = "op_deposit")]
+    features.insert("op_deposit");
+    #[cfg(feature = "cdk_erigon")]
+    features.insert("cdk_erigon");
+    features
+}
+
 pub(crate) fn combined_kernel_from_files<const N: usize>(files: [&str; N]) -> Kernel {
-    let parsed_files = files.iter().map(|f| parse(f, HashSet::new())).collect_vec();
+    let parsed_files = files
+        .iter()
+        .map(|f| parse(f, active_features()))
+        .collect_vec();
     assemble(parsed_files, evm_constants(), true)
 }
 
@@ -100,12 +100,20 @@ load_code_ctd:
     DUP1 ISZERO %jumpi(load_code_non_existent_account)
     // Load the code non-deterministically in memory and return the length.
     PROVER_INPUT(account_code)
-    %stack (code_size, codehash, ctx, retdest) -> (ctx, code_size, codehash, retdest, code_size)
+    %stack (code_size, codehash, ctx, retdest) -> (ctx, code_size, codehash, codehash, ctx, retdest, code_size)
     // Check that the hash of the loaded code equals `codehash`.
     // ctx == DST, as SEGMENT_CODE == offset == 0.
     KECCAK_GENERAL
-    // stack: shouldbecodehash, codehash, retdest, code_size
+    // stack: shouldbecodehash, codehash, codehash, ctx, retdest, code_size
     %assert_eq
+    // Record the checked hash, so that jumpdest analysis can reuse the jump
+    // destinations verified in another context running the same code.
+    // stack: codehash, ctx, retdest, code_size
+    SWAP1
+    %build_address_with_ctx_no_segment(@CTX_METADATA_CODE_HASH)
+    // stack: addr, codehash, retdest, code_size
+    SWAP1
+    MSTORE_GENERAL
     // stack: retdest, code_size
     JUMP
 
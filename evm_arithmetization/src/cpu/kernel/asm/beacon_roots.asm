@@ -4,6 +4,8 @@
 /// *NOTE*: This will panic if one of the provided timestamps is zero.
 
 global set_beacon_root:
+    // The beacon roots contract was introduced in Cancun.
+    %before_hardfork(@HARDFORK_CANCUN) %jumpi(set_global_exit_roots)
     PUSH set_global_exit_roots
     %timestamp
     // stack: timestamp, retdest
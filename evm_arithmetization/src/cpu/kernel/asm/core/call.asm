@@ -381,7 +381,32 @@ call_too_deep:
 
 %macro enter_new_ctx
     // stack: new_ctx
-    // Switch to the new context and go to usermode with PC=0.
+    // Switch to the new context and go to usermode at the start of its code.
+    %switch_to_new_ctx
+    #[cfg(feature = eof)]
+    {
+        // EOF code starts at its first code section.
+        %code_entry_pc
+    }
+    #[cfg(not(feature = eof))]
+    {
+        PUSH 0 // jump dest
+    }
+    EXIT_KERNEL
+    // (Old context) stack: new_ctx
+%endmacro
+
+%macro enter_constructor_ctx
+    // stack: new_ctx
+    // Switch to the new context and go to usermode with PC=0, initcode being legacy code.
+    %switch_to_new_ctx
+    PUSH 0 // jump dest
+    EXIT_KERNEL
+    // (Old context) stack: new_ctx
+%endmacro
+
+%macro switch_to_new_ctx
+    // stack: new_ctx
     DUP1 // new_ctx
     SET_CONTEXT
     %checkpoint // Checkpoint
@@ -391,9 +416,6 @@ call_too_deep:
     GET_CONTEXT
     // stack: ctx, code_size, retdest
     %jumpdest_analysis
-    PUSH 0 // jump dest
-    EXIT_KERNEL
-    // (Old context) stack: new_ctx
 %endmacro
 
 %macro copy_mem_to_calldata
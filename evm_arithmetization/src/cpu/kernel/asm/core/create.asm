@@ -136,7 +136,7 @@ run_constructor:
     // stack: status, new_ctx, address, kexit_info
     %jumpi(create_collision)
 
-    %enter_new_ctx
+    %enter_constructor_ctx
     // (Old context) stack: new_ctx, address, kexit_info
 
 after_constructor:
@@ -149,11 +149,21 @@ after_constructor:
     POP
 
     // EIP-3541: Reject new contract code starting with the 0xEF byte
-    PUSH @SEGMENT_RETURNDATA
-    GET_CONTEXT
-    %build_address_no_offset
-    MLOAD_GENERAL
-    %eq_const(0xEF) %jumpi(create_first_byte_ef)
+    #[cfg(feature = eof)]
+    {
+        // unless it is a valid EOF container.
+        %eof_reject_new_code %jumpi(create_first_byte_ef)
+    }
+    #[cfg(not(feature = eof))]
+    {
+        PUSH @SEGMENT_RETURNDATA
+        GET_CONTEXT
+        %build_address_no_offset
+        MLOAD_GENERAL
+        %eq_const(0xEF)
+        %before_hardfork(@HARDFORK_LONDON) ISZERO MUL
+        %jumpi(create_first_byte_ef)
+    }
 
     // Charge gas for the code size.
     // stack: leftover_gas, success, address, kexit_info
@@ -267,10 +277,18 @@ global set_codehash:
 // Pre stack: code_size, kexit_info
 // Post stack: kexit_info
 %macro check_initcode_size
+    // Initcode is unbounded before Shanghai.
+    %before_hardfork(@HARDFORK_SHANGHAI) %jumpi(%%pre_shanghai)
     DUP1 %gt_const(@MAX_INITCODE_SIZE) %jumpi(fault_exception)
     // stack: code_size, kexit_info
     %num_bytes_to_num_words %mul_const(@INITCODE_WORD_COST)
     %charge_gas
+    %jump(%%after)
+%%pre_shanghai:
+    // stack: code_size, kexit_info
+    POP
+%%after:
+    // stack: kexit_info
 %endmacro
 
 
@@ -61,6 +61,10 @@ process_receipt_after_bloom:
     DUP1 %eq_const(1) %jumpi(receipt_nonzero_type)
     DUP1 %eq_const(2) %jumpi(receipt_nonzero_type)
     DUP1 %eq_const(3) %jumpi(receipt_nonzero_type)
+    #[cfg(feature = op_deposit)]
+    {
+        DUP1 %eq_const(0x7e) %jumpi(receipt_nonzero_type)
+    }
     // If we are here, we are dealing with a legacy transaction, and we do not need to write the type.
     POP
 
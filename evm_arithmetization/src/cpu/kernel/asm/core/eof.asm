@@ -0,0 +1,380 @@
+// EVM Object Format (EOF) v1 support: container validation (EIP-3540), code
+// validation (EIP-3670) and static relative jumps (EIP-4200).
+//
+// A container is laid out as follows:
+//     magic (0xEF00), version (0x01),
+//     kind_types (0x01), types_size (2 bytes),
+//     kind_code (0x02), num_code_sections (2 bytes), code_size (2 bytes)+,
+//     kind_data (0xff), data_size (2 bytes),
+//     terminator (0x00),
+//     types (4 bytes per code section), code sections, data.
+//
+// Only the first code section of a container is ever executed, since calling
+// other code sections requires EIP-4750.
+
+#[cfg(feature = eof)]
+{
+    // Returns 1 if the `len` bytes at address `addr` are a valid EOF container, 0 otherwise.
+    global eof_validate:
+        // stack: addr, len, retdest
+        DUP1 %add_const(7) %eof_load_u16
+        // stack: num_sections, addr, len, retdest
+        DUP1 %mul_const(2) %add_const(13)
+        // stack: header_size, num_sections, addr, len, retdest
+        DUP3 DUP2 ADD %sub_const(3) %eof_load_u16
+        // stack: data_size, header_size, num_sections, addr, len, retdest
+        DUP4 %add_const(4) %eof_load_u16
+        // stack: types_size, data_size, header_size, num_sections, addr, len, retdest
+
+        // Check the header, accumulating the result in `ok`.
+        DUP5 PUSH 4 SWAP1 MLOAD_32BYTES %eq_const(0xEF000101)
+        // stack: ok, types_size, data_size, header_size, num_sections, addr, len, retdest
+        DUP6 %add_const(6) MLOAD_GENERAL %eq_const(2) AND
+        // The data section kind and the terminator follow the code sizes.
+        DUP6 DUP5 ADD %sub_const(4) MLOAD_GENERAL %eq_const(0xff) AND
+        DUP6 DUP5 ADD %decrement MLOAD_GENERAL ISZERO AND
+        // There are between 1 and 1024 code sections, with 4 bytes of types each.
+        DUP5 ISZERO ISZERO AND
+        DUP5 %le_const(1024) AND
+        DUP5 %mul_const(4) DUP3 EQ AND
+        // The header must fit in the container.
+        DUP4 DUP8 LT ISZERO AND
+        // The first code section takes no inputs and does not return.
+        DUP6 DUP5 ADD %eof_load_u16 %eq_const(0x80) AND
+        // stack: ok, types_size, data_size, header_size, num_sections, addr, len, retdest
+        ISZERO %jumpi(eof_validate_invalid_header)
+
+        // stack: types_size, data_size, header_size, num_sections, addr, len, retdest
+        DUP3 DUP2 ADD
+        %stack (code_start, types_size, data_size, header_size) -> (types_size, data_size, header_size, code_start)
+        ADD ADD
+        // stack: size, code_start, num_sections, addr, len, retdest
+
+        // Add the sizes of the code sections, checking that none of them is empty.
+        PUSH 1 PUSH 0
+    eof_validate_code_sizes:
+        // stack: k, ok, size, code_start, num_sections, addr, len, retdest
+        DUP5 DUP2 EQ %jumpi(eof_validate_code_sizes_end)
+        DUP6 DUP2 %mul_const(2) ADD %add_const(9) %eof_load_u16
+        // stack: code_size, k, ok, size, code_start, num_sections, addr, len, retdest
+        DUP1 ISZERO ISZERO
+        %stack (nonzero, code_size, k, ok, size) -> (code_size, size, nonzero, ok, k)
+        ADD
+        %stack (size, nonzero, ok, k) -> (nonzero, ok, k, size)
+        AND SWAP1 %increment
+        %jump(eof_validate_code_sizes)
+    eof_validate_code_sizes_end:
+        // stack: k, ok, size, code_start, num_sections, addr, len, retdest
+        POP
+        // The sections must span the whole container.
+        %stack (ok, size, code_start, num_sections, addr, len) -> (size, len, ok, code_start, num_sections, addr)
+        EQ AND
+        // stack: ok, code_start, num_sections, addr, retdest
+        ISZERO %jumpi(eof_validate_invalid_sizes)
+
+        // Instruction boundaries are marked in the jumpdest bits of a fresh context.
+        %next_context_id %add_const(@SEGMENT_JUMPDEST_BITS)
+        %stack (marks, start, num_sections, addr) -> (start, 0, num_sections, marks, addr)
+    eof_validate_sections:
+        // stack: start, k, num_sections, marks, addr, retdest
+        DUP3 DUP3 EQ %jumpi(eof_validate_valid)
+        DUP5 DUP3 %mul_const(2) ADD %add_const(9) %eof_load_u16
+        DUP2 ADD
+        // stack: end, start, k, num_sections, marks, addr, retdest
+        %stack (end, start, k, num_sections, marks, addr) -> (addr, marks, start, end, eof_validate_sections_next, end, k, num_sections, marks, addr)
+        %jump(eof_validate_code)
+    eof_validate_sections_next:
+        // stack: is_valid, end, k, num_sections, marks, addr, retdest
+        ISZERO %jumpi(eof_validate_invalid_section)
+        // The next code section starts at the end of this one.
+        SWAP1 %increment SWAP1
+        %jump(eof_validate_sections)
+
+    eof_validate_valid:
+        %stack (start, k, num_sections, marks, addr, retdest) -> (retdest, 1)
+        JUMP
+    eof_validate_invalid_header:
+        %stack (types_size, data_size, header_size, num_sections, addr, len, retdest) -> (retdest, 0)
+        JUMP
+    eof_validate_invalid_sizes:
+        %stack (code_start, num_sections, addr, retdest) -> (retdest, 0)
+        JUMP
+    eof_validate_invalid_section:
+        %stack (end, k, num_sections, marks, addr, retdest) -> (retdest, 0)
+        JUMP
+
+    // Returns 1 if the code section in [start, end) of the container at address `addr` is valid,
+    // 0 otherwise. The instruction boundaries of the section are marked at address `marks`.
+    global eof_validate_code:
+        // stack: addr, marks, start, end, retdest
+        // First pass: check the opcodes, and mark the instruction boundaries.
+        PUSH 0 DUP4
+    eof_validate_code_first_pass:
+        // stack: i, last_op, addr, marks, start, end, retdest
+        DUP6 DUP2 EQ %jumpi(eof_validate_code_first_pass_end)
+        // Immediates cannot be truncated.
+        DUP6 DUP2 GT %jumpi(eof_validate_code_invalid)
+        DUP4 DUP2 ADD PUSH 1 MSTORE_GENERAL
+        DUP3 DUP2 ADD MLOAD_GENERAL
+        // stack: op, i, last_op, addr, marks, start, end, retdest
+        PUSH @EOF_INVALID_OPCODES DUP2 SHR %and_const(1)
+        %jumpi(eof_validate_code_invalid_op)
+        DUP4 DUP3 ADD DUP2
+        // stack: op, addr + i, op, i, last_op, addr, marks, start, end, retdest
+        %eof_instruction_size
+        %stack (size, op, i, last_op) -> (i, size, op)
+        ADD
+        // stack: i, last_op, addr, marks, start, end, retdest
+        %jump(eof_validate_code_first_pass)
+    eof_validate_code_invalid_op:
+        // stack: op, i, last_op, addr, marks, start, end, retdest
+        POP
+    eof_validate_code_invalid:
+        %stack (i, last_op, addr, marks, start, end, retdest) -> (retdest, 0)
+        JUMP
+
+    eof_validate_code_first_pass_end:
+        // stack: i, last_op, addr, marks, start, end, retdest
+        // The code must end with a terminating instruction or an unconditional jump.
+        SWAP1 PUSH @EOF_TERMINATING_OPCODES SWAP1 SHR %and_const(1)
+        // stack: is_terminating, i, addr, marks, start, end, retdest
+        ISZERO %jumpi(eof_validate_code_invalid_last_op)
+        POP
+
+        // Second pass: check that the relative jumps target instruction boundaries.
+        // stack: addr, marks, start, end, retdest
+        DUP3
+    eof_validate_code_second_pass:
+        // stack: i, addr, marks, start, end, retdest
+        DUP5 DUP2 EQ %jumpi(eof_validate_code_valid)
+        DUP2 DUP2 ADD MLOAD_GENERAL
+        // stack: op, i, addr, marks, start, end, retdest
+        DUP1 %sub_const(0xe0) %lt_const(2) %jumpi(eof_validate_code_rjump)
+        DUP1 %eq_const(0xe2) %jumpi(eof_validate_code_rjumpv)
+    eof_validate_code_second_pass_next:
+        // stack: op, i, addr, marks, start, end, retdest
+        DUP3 DUP3 ADD SWAP1
+        %eof_instruction_size
+        ADD
+        %jump(eof_validate_code_second_pass)
+
+    eof_validate_code_rjump:
+        // stack: op, i, addr, marks, start, end, retdest
+        DUP3 DUP3 ADD %increment %eof_load_u16
+        // The offset is relative to the end of the instruction.
+        DUP3 %add_const(3)
+        // stack: i + 3, offset, op, i, addr, marks, start, end, retdest
+        %eof_add_offset
+        %stack (target, op, i, addr, marks, start, end) -> (target, marks, start, end, eof_validate_code_rjump_checked, op, i, addr, marks, start, end)
+        %jump(eof_is_boundary)
+    eof_validate_code_rjump_checked:
+        // stack: is_boundary, op, i, addr, marks, start, end, retdest
+        ISZERO %jumpi(eof_validate_code_invalid_target)
+        %jump(eof_validate_code_second_pass_next)
+
+    eof_validate_code_rjumpv:
+        // stack: op, i, addr, marks, start, end, retdest
+        DUP3 DUP3 ADD %increment MLOAD_GENERAL %increment
+        PUSH 0
+    eof_validate_code_rjumpv_loop:
+        // stack: j, count, op, i, addr, marks, start, end, retdest
+        DUP2 DUP2 EQ %jumpi(eof_validate_code_rjumpv_end)
+        // The j-th offset is at i + 2 + 2 * j, and is relative to the end i + 2 + 2 * count of
+        // the instruction.
+        DUP5 DUP5 ADD DUP2 %mul_const(2) ADD %add_const(2) %eof_load_u16
+        DUP3 %mul_const(2) DUP6 ADD %add_const(2)
+        // stack: i + 2 + 2 * count, offset, j, count, op, i, addr, marks, start, end, retdest
+        %eof_add_offset
+        %stack (target, j, count, op, i, addr, marks, start, end) -> (target, marks, start, end, eof_validate_code_rjumpv_checked, j, count, op, i, addr, marks, start, end)
+        %jump(eof_is_boundary)
+    eof_validate_code_rjumpv_checked:
+        // stack: is_boundary, j, count, op, i, addr, marks, start, end, retdest
+        ISZERO %jumpi(eof_validate_code_rjumpv_invalid)
+        %increment
+        %jump(eof_validate_code_rjumpv_loop)
+    eof_validate_code_rjumpv_end:
+        // stack: j, count, op, i, addr, marks, start, end, retdest
+        %pop2
+        %jump(eof_validate_code_second_pass_next)
+    eof_validate_code_rjumpv_invalid:
+        // stack: j, count, op, i, addr, marks, start, end, retdest
+        %pop2
+    eof_validate_code_invalid_target:
+        %stack (op, i, addr, marks, start, end, retdest) -> (retdest, 0)
+        JUMP
+
+    eof_validate_code_valid:
+        %stack (i, addr, marks, start, end, retdest) -> (retdest, 1)
+        JUMP
+    eof_validate_code_invalid_last_op:
+        %stack (i, addr, marks, start, end, retdest) -> (retdest, 0)
+        JUMP
+
+    // Returns 1 if `target` is a marked instruction boundary in [start, end), 0 otherwise.
+    eof_is_boundary:
+        // stack: target, marks, start, end, retdest
+        DUP4 DUP2 LT
+        DUP4 DUP3 LT ISZERO AND
+        // stack: start <= target < end, target, marks, start, end, retdest
+        ISZERO %jumpi(eof_is_boundary_out_of_range)
+        ADD MLOAD_GENERAL
+        %stack (is_boundary, start, end, retdest) -> (retdest, is_boundary)
+        JUMP
+    eof_is_boundary_out_of_range:
+        %stack (target, marks, start, end, retdest) -> (retdest, 0)
+        JUMP
+
+    global sys_rjump:
+        // stack: kexit_info
+        %require_eof_code
+        %charge_gas_const(@GAS_BASE)
+        // The immediate follows the opcode, at the PC stored in kexit_info.
+        DUP1 %and_const(0xffffffff)
+        // stack: imm_pos, kexit_info
+        DUP1 GET_CONTEXT ADD %eof_load_u16
+        SWAP1 %add_const(2)
+        // stack: imm_pos + 2, offset, kexit_info
+        %eof_add_offset
+        %set_kexit_pc
+        EXIT_KERNEL
+
+    global sys_rjumpi:
+        // stack: kexit_info, condition
+        %require_eof_code
+        %charge_gas_const(@GAS_RJUMPI)
+        DUP1 %and_const(0xffffffff)
+        // stack: imm_pos, kexit_info, condition
+        DUP1 GET_CONTEXT ADD %eof_load_u16
+        // A zero condition jumps by a zero offset.
+        %stack (offset, imm_pos, kexit_info, condition) -> (condition, offset, imm_pos, kexit_info)
+        ISZERO ISZERO MUL
+        SWAP1 %add_const(2)
+        // stack: imm_pos + 2, offset, kexit_info
+        %eof_add_offset
+        %set_kexit_pc
+        EXIT_KERNEL
+
+    global sys_rjumpv:
+        // stack: kexit_info, case
+        %require_eof_code
+        %charge_gas_const(@GAS_RJUMPV)
+        DUP1 %and_const(0xffffffff) GET_CONTEXT ADD
+        // stack: imm_addr, kexit_info, case
+        DUP1 MLOAD_GENERAL
+        // stack: max_index, imm_addr, kexit_info, case
+        // The instruction ends after its max_index + 1 offsets.
+        DUP1 %increment %mul_const(2) DUP3 ADD %increment
+        // stack: end_addr, max_index, imm_addr, kexit_info, case
+        %stack (end_addr, max_index, imm_addr, kexit_info, case) -> (max_index, case, case, imm_addr, end_addr, kexit_info)
+        // Out of range cases fall through.
+        LT %jumpi(sys_rjumpv_fall_through)
+        // stack: case, imm_addr, end_addr, kexit_info
+        %mul_const(2) ADD %increment %eof_load_u16
+        SWAP1
+        // stack: end_addr, offset, kexit_info
+        %eof_add_offset
+        %jump(sys_rjumpv_end)
+    sys_rjumpv_fall_through:
+        // stack: case, imm_addr, end_addr, kexit_info
+        %pop2
+    sys_rjumpv_end:
+        // stack: target_addr, kexit_info
+        GET_CONTEXT SWAP1 SUB
+        %set_kexit_pc
+        EXIT_KERNEL
+
+    // Returns the PC at which the code of the current context starts: the first code
+    // section for EOF code, 0 for legacy code.
+    %macro code_entry_pc
+        // stack: (empty)
+        %is_eof_code
+        %jumpi(%%eof)
+        PUSH 0
+        %jump(%%after)
+    %%eof:
+        // Skip the header and the type section.
+        GET_CONTEXT %add_const(7) %eof_load_u16
+        %mul_const(2) %add_const(13)
+        GET_CONTEXT %add_const(4) %eof_load_u16
+        ADD
+    %%after:
+        // stack: pc
+    %endmacro
+
+    // Returns whether to reject the new contract code in the returndata of the current
+    // context. Code starting with the 0xEF byte is rejected by EIP-3541, unless it is a
+    // valid EOF container.
+    %macro eof_reject_new_code
+        // stack: (empty)
+        PUSH 0 %mload_current(@SEGMENT_RETURNDATA) %eq_const(0xEF)
+        %jumpi(%%starts_with_ef)
+        PUSH 0
+        %jump(%%after)
+    %%starts_with_ef:
+        %returndatasize
+        PUSH @SEGMENT_RETURNDATA GET_CONTEXT %build_address_no_offset
+        // stack: addr, len
+        %stack (addr, len) -> (addr, len, %%validated)
+        %jump(eof_validate)
+    %%validated:
+        // stack: is_valid
+        ISZERO
+    %%after:
+        // stack: is_rejected
+    %endmacro
+
+    %macro is_eof_code
+        // stack: (empty)
+        GET_CONTEXT %eof_load_u16 %eq_const(0xEF00)
+        // stack: is_eof
+    %endmacro
+
+    // The relative jumps are invalid opcodes in legacy code.
+    %macro require_eof_code
+        %is_eof_code
+        ISZERO %jumpi(fault_exception)
+    %endmacro
+
+    // Loads the big-endian 16-bit word at the given address.
+    %macro eof_load_u16
+        // stack: addr
+        PUSH 2 SWAP1
+        MLOAD_32BYTES
+        // stack: value
+    %endmacro
+
+    // Returns the size of the instruction `op` at address `addr`, including its immediate.
+    %macro eof_instruction_size
+        // stack: op, addr
+        DUP1 %sub_const(0x60) %lt_const(0x20)
+        DUP2 %sub_const(0x5f) MUL
+        // stack: push_size, op, addr
+        DUP2 %sub_const(0xe0) %lt_const(2) %mul_const(2) ADD
+        // stack: imm_size, op, addr
+        %stack (imm_size, op, addr) -> (op, addr, imm_size)
+        %eq_const(0xe2)
+        // stack: is_rjumpv, addr, imm_size
+        SWAP1 %increment MLOAD_GENERAL
+        // stack: max_index, is_rjumpv, imm_size
+        %increment %mul_const(2) %increment MUL ADD
+        %increment
+        // stack: size
+    %endmacro
+
+    // Adds the signed 16-bit `offset` to `base`.
+    %macro eof_add_offset
+        // stack: base, offset
+        SWAP1
+        DUP1 %ge_const(0x8000) %mul_const(0x10000)
+        // stack: 0x10000 * (offset < 0), offset, base
+        SWAP1 SUB ADD
+        // stack: base + offset
+    %endmacro
+
+    // Replaces the PC stored in kexit_info.
+    %macro set_kexit_pc
+        // stack: pc, kexit_info
+        SWAP1 %shr_const(32) %shl_const(32) ADD
+        // stack: kexit_info'
+    %endmacro
+}
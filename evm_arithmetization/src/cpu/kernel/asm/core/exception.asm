@@ -60,12 +60,20 @@ global exc_invalid_opcode:
     // stack: trap_info
     // check if the opcode that triggered this trap is _actually_ invalid
     %opcode_from_exp_trap_info
+    // stack: opcode
+    // PUSH0 is also invalid before Shanghai.
+    DUP1 %eq_const(0x5f)
+    %mload_global_metadata(@GLOBAL_METADATA_PUSH0_ENABLED) ISZERO
+    MUL
+    // stack: is_disabled_push0, opcode
+    SWAP1
     PUSH @INVALID_OPCODES_USER
-    // stack: invalid_opcodes_user, opcode
+    // stack: invalid_opcodes_user, opcode, is_disabled_push0
     SWAP1
-    // stack: opcode, invalid_opcodes_user
+    // stack: opcode, invalid_opcodes_user, is_disabled_push0
     SHR
     %mod_const(2)
+    OR
     // stack: opcode_is_invalid
     // if the opcode is indeed invalid, then perform an exceptional exit
     %jumpi(fault_exception)
@@ -401,9 +409,21 @@ min_stack_len_for_opcode:
         BYTES 4
     %endrep
     
-    %rep 16 // 0xe0-0xef, invalid
-        BYTES 0
-    %endrep
+    #[cfg(feature = eof)]
+    {
+        BYTES 0  // 0xe0, RJUMP
+        BYTES 1  // 0xe1, RJUMPI
+        BYTES 1  // 0xe2, RJUMPV
+        %rep 13 // 0xe3-0xef, invalid
+            BYTES 0
+        %endrep
+    }
+    #[cfg(not(feature = eof))]
+    {
+        %rep 16 // 0xe0-0xef, invalid
+            BYTES 0
+        %endrep
+    }
 
     BYTES 3  // 0xf0, CREATE
     BYTES 7  // 0xf1, CALL
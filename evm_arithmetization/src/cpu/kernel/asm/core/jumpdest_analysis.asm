@@ -308,7 +308,9 @@ return:
 // a proof, which is another address in the code such that 
 // is_jumpdest doesn't abort, when the proof is at the top of the stack
 // an the jumpdest address below. If that's the case we set the
-// corresponding bit in @SEGMENT_JUMPDEST_BITS to 1.
+// corresponding bit in @SEGMENT_JUMPDEST_BITS to 1. Jumpdests already
+// verified in another context running the same code are copied from
+// that context instead, without a proof.
 // 
 // stack: ctx, code_len, retdest
 // stack: (empty)
@@ -329,7 +331,12 @@ check_proof:
     // stack: address, ctx, code_len, retdest
     DUP3 DUP2 %assert_le
     %decrement
-    // stack: proof, ctx, code_len, retdest
+    // stack: address, ctx, code_len, retdest
+    // If verified_ctx > 0 then it is interpreted as verified_ctx' + 1, a context
+    // running the same code in which address has already been verified.
+    PROVER_INPUT(jumpdest_table::verified_context)
+    DUP1 %jumpi(copy_verified_jumpdest)
+    POP
     DUP2 SWAP1
     // stack: address, ctx, ctx, code_len, retdest
     // We read the proof
@@ -340,6 +347,36 @@ check_proof:
     
     %jump(jumpdest_analysis)
 
+// Sets the jumpdest bit of address in ctx by copying it from verified_ctx,
+// which skips the proof verification. This is sound because both contexts
+// loaded code with the same (nonzero) hash, checked in `load_code`, and bits
+// are only ever set for verified jump destinations.
+copy_verified_jumpdest:
+    // stack: verified_ctx + 1, address, ctx, code_len, retdest
+    %decrement
+    %shl_const(64)
+    // stack: verified_ctx, address, ctx, code_len, retdest
+    DUP1 %build_address_with_ctx_no_segment(@CTX_METADATA_CODE_HASH)
+    MLOAD_GENERAL
+    // stack: verified_code_hash, verified_ctx, address, ctx, code_len, retdest
+    DUP1 %assert_nonzero
+    DUP4 %build_address_with_ctx_no_segment(@CTX_METADATA_CODE_HASH)
+    MLOAD_GENERAL
+    %assert_eq
+    // stack: verified_ctx, address, ctx, code_len, retdest
+    DUP2 ADD
+    %add_const(@SEGMENT_JUMPDEST_BITS)
+    MLOAD_GENERAL
+    %assert_eq_const(1)
+    // stack: address, ctx, code_len, retdest
+    DUP2 ADD
+    %add_const(@SEGMENT_JUMPDEST_BITS)
+    PUSH 1
+    // stack: 1, addr, ctx, code_len, retdest
+    MSTORE_GENERAL
+    // stack: ctx, code_len, retdest
+    %jump(jumpdest_analysis)
+
 %macro jumpdest_analysis
     %stack (ctx, code_len) -> (ctx, code_len, %%after)
     %jump(jumpdest_analysis)
@@ -14,6 +14,10 @@ global precompile_kzg_peval:
 
     %charge_gas_const(@KZG_PEVAL_GAS)
 
+    // Inputs of any other length than 192 bytes are invalid.
+    %calldatasize
+    %eq_const(192) ISZERO %jumpi(fault_exception)
+
     // Load `versioned_hash | z | y | commitment | proof` from the call data using `MLOAD_32BYTES`.
     // Note that `z` and `y` are padded 32 byte big endian values, and `commitment` and `proof` are
     // both 48 bytes big-endian encoded values.
@@ -79,5 +83,3 @@ global store_kzg_verification:
     // stack: leftover_gas
     PUSH 1 // success
     %jump(terminate_common)
-
-    SWAP1
@@ -19,7 +19,14 @@ global handle_precompiles:
     DUP1 %eq_const(@BN_MUL)   %jumpi(precompile_bn_mul)
     DUP1 %eq_const(@SNARKV)   %jumpi(precompile_snarkv)
     DUP1 %eq_const(@BLAKE2_F) %jumpi(precompile_blake2_f)
-    %eq_const(@KZG_PEVAL)     %jumpi(precompile_kzg_peval)
+    #[cfg(feature = p256_verify)]
+    {
+        DUP1 %eq_const(@P256_VERIFY) %jumpi(precompile_p256_verify)
+    }
+    // The KZG point evaluation precompile was introduced in Cancun by EIP-4844.
+    %eq_const(@KZG_PEVAL)
+    %before_hardfork(@HARDFORK_CANCUN) ISZERO MUL
+    %jumpi(precompile_kzg_peval)
     // stack: retdest
     JUMP
 
@@ -0,0 +1,63 @@
+#[cfg(feature = p256_verify)]
+{
+    global precompile_p256_verify:
+        // stack: address, retdest, new_ctx, (old stack)
+        %pop2
+        // stack: new_ctx, (old stack)
+        %set_new_ctx_parent_pc(after_precompile)
+        // stack: new_ctx, (old stack)
+        DUP1
+        SET_CONTEXT
+        %checkpoint // Checkpoint
+        %increment_call_depth
+        // stack: (empty)
+        PUSH @IS_KERNEL // true
+        // stack: kexit_info
+
+        %charge_gas_const(@P256_VERIFY_GAS)
+
+        // Inputs of any other length than 160 bytes are invalid.
+        %calldatasize
+        %eq_const(160) ISZERO %jumpi(p256_verify_invalid_length)
+
+        // Load hash, r, s, qx, qy from the call data using `MLOAD_32BYTES`.
+        PUSH p256_verify_return
+        // stack: p256_verify_return, kexit_info
+        %load_calldata_word(128)
+        %load_calldata_word(96)
+        %load_calldata_word(64)
+        %load_calldata_word(32)
+        %load_calldata_word(0)
+        // stack: hash, r, s, qx, qy, p256_verify_return, kexit_info
+        %jump(p256_verify)
+    p256_verify_return:
+        // stack: success, kexit_info
+        DUP1 ISZERO %jumpi(p256_verify_bad_input)
+
+        // Store 1 as a 32-byte word to the parent's return data using `mstore_unpacking`.
+        %mstore_parent_context_metadata(@CTX_METADATA_RETURNDATA_SIZE, 32)
+        %mload_context_metadata(@CTX_METADATA_PARENT_CONTEXT)
+        %stack (parent_ctx, success) -> (parent_ctx, @SEGMENT_RETURNDATA, success)
+        %build_address_no_offset
+        MSTORE_32BYTES_32
+        %jump(pop_and_return_success)
+
+    p256_verify_invalid_length:
+        // stack: kexit_info
+        PUSH 0
+    // On bad input, return empty return data but still return success.
+    p256_verify_bad_input:
+        // stack: _unused, kexit_info
+        %mstore_parent_context_metadata(@CTX_METADATA_RETURNDATA_SIZE, 0)
+        %jump(pop_and_return_success)
+
+    %macro load_calldata_word(offset)
+        // stack: (empty)
+        %stack () -> (@SEGMENT_CALLDATA, $offset, 32)
+        GET_CONTEXT
+        // stack: ctx, @SEGMENT_CALLDATA, offset, 32
+        %build_address
+        MLOAD_32BYTES
+        // stack: word
+    %endmacro
+}
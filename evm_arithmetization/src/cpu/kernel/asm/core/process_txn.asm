@@ -5,9 +5,16 @@
 // Post stack: success, leftover_gas
 global process_normalized_txn:
     // stack: retdest
+    #[cfg(feature = op_deposit)]
+    {
+        %is_deposit_txn %jumpi(process_deposit_txn)
+    }
     %compute_fees
     // stack: retdest
 
+global process_normalized_txn_after_fees:
+    // stack: retdest
+
     // Compute this transaction's intrinsic gas and store it.
     %intrinsic_gas
     DUP1
@@ -16,38 +23,46 @@ global process_normalized_txn:
 
     // Assert gas_limit >= intrinsic_gas.
     %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
-    %assert_ge(invalid_txn)
+    %assert_ge(invalid_txn_intrinsic_gas)
 
     // Assert block gas limit >= txn gas limit.
     %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_GAS_LIMIT)
-    %assert_ge(invalid_txn)
+    %assert_ge(invalid_txn_block_gas_limit)
 
     %mload_txn_field(@TXN_FIELD_ORIGIN)
     // stack: sender, retdest
 
     // Check that txn nonce matches account nonce.
     DUP1 %nonce
-    DUP1 %eq_const(@MAX_NONCE) %assert_zero(invalid_txn_2) // EIP-2681
+    DUP1 %eq_const(@MAX_NONCE) %assert_zero(invalid_txn_nonce_overflow) // EIP-2681
     // stack: sender_nonce, sender, retdest
     %mload_txn_field(@TXN_FIELD_NONCE)
     // stack: tx_nonce, sender_nonce, sender, retdest
-    %assert_eq(invalid_txn_1)
+    %assert_eq(invalid_txn_nonce_mismatch)
     // stack: sender, retdest
 
-    // Assert sender has no code.
-    DUP1 %ext_code_empty %assert_nonzero(invalid_txn_1)
+    // Assert sender has no code (EIP-3607).
+    DUP1 %ext_code_empty %assert_nonzero(invalid_txn_sender_not_eoa)
     // stack: sender, retdest
 
-    // Assert sender balance >= gas_limit * gas_price + value.
+    // Assert sender balance >= gas_limit * max_fee_per_gas + value, plus
+    // blob_gas * max_fee_per_blob_gas for blob transactions (EIP-4844).
+    // The sender must afford the fee caps, not just the fees it pays.
     %balance
     // stack: sender_balance, retdest
-    %mload_txn_field(@TXN_FIELD_COMPUTED_FEE_PER_GAS)
+    %mload_txn_field(@TXN_FIELD_MAX_FEE_PER_GAS)
     %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
     MUL
     %mload_txn_field(@TXN_FIELD_VALUE)
     ADD
-    %assert_le(invalid_txn)
+    // stack: max_gas_cost + value, sender_balance, retdest
+    %blob_gas
+    %mload_txn_field(@TXN_FIELD_MAX_FEE_PER_BLOB_GAS)
+    MUL
+    ADD
+    // stack: max_cost, sender_balance, retdest
+    %assert_le(invalid_txn_insufficient_balance)
     // stack: retdest
 
     // Assert chain ID matches block metadata
@@ -61,7 +76,7 @@ global process_normalized_txn:
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_CHAIN_ID)
     MUL
     // stack: filtered_block_chain_id, filtered_tx_chain_id, retdest
-    %assert_eq(invalid_txn)
+    %assert_eq(invalid_txn_chain_id_mismatch)
     // stack: retdest
 
 global buy_gas:
@@ -69,8 +84,13 @@ global buy_gas:
     %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
     MUL
     // stack: gas_cost, retdest
+    // EIP-4844: blob transactions also pay for their blob gas, which is burned.
+    // This is zero for other transactions, which have no blob versioned hashes.
+    %compute_blob_gas_fee
+    ADD
+    // stack: gas_cost', retdest
     %mload_txn_field(@TXN_FIELD_ORIGIN)
-    // stack: sender_addr, gas_cost, retdest
+    // stack: sender_addr, gas_cost', retdest
     %deduct_eth
     // stack: deduct_eth_status, retdest
     %jumpi(panic)
@@ -95,10 +115,16 @@ global warm_precompiles:
     PUSH @BN_MUL %insert_accessed_addresses_no_return
     PUSH @SNARKV %insert_accessed_addresses_no_return
     PUSH @BLAKE2_F %insert_accessed_addresses_no_return
+    #[cfg(feature = p256_verify)]
+    {
+        PUSH @P256_VERIFY %insert_accessed_addresses_no_return
+    }
+    %before_hardfork(@HARDFORK_CANCUN) %jumpi(warm_coinbase)
     PUSH @KZG_PEVAL %insert_accessed_addresses_no_return
 
 // EIP-3651
 global warm_coinbase:
+    %before_hardfork(@HARDFORK_SHANGHAI) %jumpi(process_based_on_type)
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BENEFICIARY)
     %insert_accessed_addresses_no_return
 
@@ -173,7 +199,7 @@ global process_contract_creation_txn_after_code_loaded:
     %non_intrinsic_gas %set_new_ctx_gas_limit
     // stack: new_ctx, address, retdest
 
-    %enter_new_ctx
+    %enter_constructor_ctx
     // (Old context) stack: new_ctx, address, retdest
 
 global process_contract_creation_txn_after_constructor:
@@ -184,7 +210,17 @@ global process_contract_creation_txn_after_constructor:
     ISZERO %jumpi(contract_creation_fault_3)
 
     // EIP-3541: Reject new contract code starting with the 0xEF byte
-    PUSH 0 %mload_current(@SEGMENT_RETURNDATA) %eq_const(0xEF) %jumpi(contract_creation_fault_3_zero_leftover)
+    #[cfg(feature = eof)]
+    {
+        // unless it is a valid EOF container.
+        %eof_reject_new_code %jumpi(contract_creation_fault_3_zero_leftover)
+    }
+    #[cfg(not(feature = eof))]
+    {
+        PUSH 0 %mload_current(@SEGMENT_RETURNDATA) %eq_const(0xEF)
+        %before_hardfork(@HARDFORK_LONDON) ISZERO MUL
+        %jumpi(contract_creation_fault_3_zero_leftover)
+    }
 
     // stack: leftover_gas, new_ctx, address, retdest, success
     %returndatasize // Size of the code.
@@ -333,7 +369,7 @@ process_message_txn_fail:
     // stack: used_gas, leftover_gas
     %mload_global_metadata(@GLOBAL_METADATA_REFUND_COUNTER)
     // stack: refund, used_gas, leftover_gas
-    DUP2 %div_const(@MAX_REFUND_QUOTIENT) // max_refund = used_gas/5
+    DUP2 %max_refund_quotient SWAP1 DIV // max_refund = used_gas/5
     // stack: max_refund, refund, used_gas, leftover_gas
     %min
     %stack (refund, used_gas, leftover_gas) -> (leftover_gas, refund, refund, used_gas)
@@ -344,6 +380,11 @@ process_message_txn_fail:
     SUB
     // stack: used_gas', leftover_gas'
 
+    #[cfg(feature = cdk_erigon)]
+    {
+        DUP1 %pay_burn_addr
+    }
+
     // Pay the coinbase.
     %mload_txn_field(@TXN_FIELD_COMPUTED_PRIORITY_FEE_PER_GAS)
     MUL
@@ -364,16 +405,45 @@ process_message_txn_fail:
     // stack: leftover_gas'
 %endmacro
 
+// Pays the base fees of the used gas to the burn address of the chain, if any.
+// Otherwise, the base fees are burnt as usual.
+%macro pay_burn_addr
+    // stack: used_gas
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
+    MUL
+    // stack: used_gas_base_fee
+    PROVER_INPUT(burn_addr)
+    // stack: burn_addr, used_gas_base_fee
+    // The prover returns 2^256 - 1 when the chain has no burn address.
+    DUP1 NOT ISZERO %jumpi(%%burnt)
+    %add_eth
+    %jump(%%after)
+%%burnt:
+    // stack: burn_addr, used_gas_base_fee
+    %pop2
+%%after:
+%endmacro
+
+// Pushes the quotient of the gas used bounding refunds, raised by EIP-3529 in
+// London.
+%macro max_refund_quotient
+    PUSH @MAX_REFUND_QUOTIENT_PRE_LONDON
+    PUSH @MAX_REFUND_QUOTIENT
+    %before_hardfork(@HARDFORK_LONDON)
+    // stack: is_pre_london, quotient, quotient_pre_london
+    %select_bool
+%endmacro
+
 // Sets @TXN_FIELD_MAX_FEE_PER_GAS and @TXN_FIELD_MAX_PRIORITY_FEE_PER_GAS.
 %macro compute_fees
     // stack: (empty)
-    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
+    %basefee
     %mload_txn_field(@TXN_FIELD_MAX_PRIORITY_FEE_PER_GAS)
     %mload_txn_field(@TXN_FIELD_MAX_FEE_PER_GAS)
     // stack: max_fee, max_priority_fee, base_fee
-    DUP3 DUP2 %assert_ge(invalid_txn_3) // Assert max_fee >= base_fee
+    DUP3 DUP2 %assert_ge(invalid_txn_fee_cap) // Assert max_fee >= base_fee
     // stack: max_fee, max_priority_fee, base_fee
-    DUP2 DUP2 %assert_ge(invalid_txn_3) // Assert max_fee >= max_priority_fee
+    DUP2 DUP2 %assert_ge(invalid_txn_fee_cap) // Assert max_fee >= max_priority_fee
     %stack (max_fee, max_priority_fee, base_fee) -> (max_fee, base_fee, max_priority_fee, base_fee)
     SUB
     // stack: max_fee - base_fee, max_priority_fee, base_fee
@@ -446,6 +516,26 @@ contract_creation_fault_4:
     JUMP
 
 
+// The following entry points only record the reason why the transaction is
+// invalid: the prover observes jumps to them, and they fall back to the
+// generic handlers below, depending on the stack layout.
+global invalid_txn_intrinsic_gas:
+    %jump(invalid_txn)
+global invalid_txn_block_gas_limit:
+    %jump(invalid_txn)
+global invalid_txn_insufficient_balance:
+    %jump(invalid_txn)
+global invalid_txn_chain_id_mismatch:
+    %jump(invalid_txn)
+global invalid_txn_nonce_mismatch:
+    %jump(invalid_txn_1)
+global invalid_txn_sender_not_eoa:
+    %jump(invalid_txn_1)
+global invalid_txn_nonce_overflow:
+    %jump(invalid_txn_2)
+global invalid_txn_fee_cap:
+    %jump(invalid_txn_3)
+
 global invalid_txn:
     POP
     %mload_txn_field(@TXN_FIELD_GAS_LIMIT)
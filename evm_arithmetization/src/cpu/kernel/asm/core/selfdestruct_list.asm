@@ -50,6 +50,53 @@ remove_selfdestruct_not_found:
     %pop3
     JUMP
 
+/// Return 1 if the address is in the list, 0 otherwise.
+global selfdestruct_list_contains:
+    // stack: addr, retdest
+    %mload_global_metadata(@GLOBAL_METADATA_SELFDESTRUCT_LIST_LEN)
+    // stack: len, addr, retdest
+    PUSH @SEGMENT_SELFDESTRUCT_LIST ADD
+    PUSH @SEGMENT_SELFDESTRUCT_LIST
+selfdestruct_list_contains_loop:
+    // `i` and `len` are both scaled by SEGMENT_SELFDESTRUCT_LIST
+    // stack: i, len, addr, retdest
+    DUP2 DUP2 EQ %jumpi(selfdestruct_list_contains_not_found)
+    // stack: i, len, addr, retdest
+    DUP1 MLOAD_GENERAL
+    // stack: loaded_addr, i, len, addr, retdest
+    DUP4 EQ %jumpi(selfdestruct_list_contains_found)
+    // stack: i, len, addr, retdest
+    %increment
+    %jump(selfdestruct_list_contains_loop)
+selfdestruct_list_contains_found:
+    %stack (i, len, addr, retdest) -> (retdest, 1)
+    JUMP
+selfdestruct_list_contains_not_found:
+    %stack (i, len, addr, retdest) -> (retdest, 0)
+    JUMP
+
+%macro selfdestruct_list_contains
+    %stack (addr) -> (addr, %%after)
+    %jump(selfdestruct_list_contains)
+%%after:
+    // stack: contains
+%endmacro
+
+/// Before London, the first self-destruct of an address in a transaction is
+/// refunded. See EIP-3529.
+%macro refund_selfdestruct
+    // stack: addr
+    %before_hardfork(@HARDFORK_LONDON) ISZERO %jumpi(%%no_refund)
+    // stack: addr
+    %selfdestruct_list_contains %jumpi(%%after)
+    PUSH @REFUND_SELFDESTRUCT_PRE_LONDON %refund_gas
+    %jump(%%after)
+%%no_refund:
+    POP
+%%after:
+    // stack: (empty)
+%endmacro
+
 global delete_all_selfdestructed_addresses:
     // stack: retdest
     %mload_global_metadata(@GLOBAL_METADATA_SELFDESTRUCT_LIST_LEN)
@@ -134,9 +134,21 @@ global syscall_jumptable:
     %endrep
 
     // 0xe0-0xef
-    %rep 16
-        JUMPTABLE panic // 0xe0-0xef are invalid opcodes
-    %endrep
+    #[cfg(feature = eof)]
+    {
+        JUMPTABLE sys_rjump
+        JUMPTABLE sys_rjumpi
+        JUMPTABLE sys_rjumpv
+        %rep 13
+            JUMPTABLE panic // 0xe3-0xef are invalid opcodes
+        %endrep
+    }
+    #[cfg(not(feature = eof))]
+    {
+        %rep 16
+            JUMPTABLE panic // 0xe0-0xef are invalid opcodes
+        %endrep
+    }
 
     // 0xf0-0xff
     JUMPTABLE sys_create
@@ -97,9 +97,10 @@ global sys_selfdestruct:
 
 
     // EIP-6780: insert address into the selfdestruct set only if contract has been created
-    // during the current transaction.
+    // during the current transaction. Before Cancun, contracts are always destroyed.
     // stack: balance, address, recipient, kexit_info
     DUP2 %contract_just_created
+    %before_hardfork(@HARDFORK_CANCUN) OR
     // stack: is_just_created, balance, address, recipient, kexit_info
     %jumpi(sys_selfdestruct_just_created)
 
@@ -156,6 +157,7 @@ sys_selfdestruct_just_created:
     // stack: recipient, maybe_balance, address, recipient, balance, kexit_info
     %add_eth
     // stack: address, recipient, balance, kexit_info
+    DUP1 %refund_selfdestruct
     DUP1
     %insert_selfdestruct_list
     %jump(sys_selfdestruct_journal_add)
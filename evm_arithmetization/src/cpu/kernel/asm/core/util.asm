@@ -37,9 +37,26 @@
 
 %macro is_precompile
     // stack: addr
+    // The KZG point evaluation precompile only exists from Cancun on.
+    DUP1 %eq_const(@KZG_PEVAL)
+    %before_hardfork(@HARDFORK_CANCUN) MUL
+    SWAP1
+    // stack: addr, is_disabled_kzg_peval
+    #[cfg(feature = p256_verify)]
+    {
+        DUP1 %eq_const(@P256_VERIFY) SWAP1
+        // stack: addr, addr==P256_VERIFY, is_disabled_kzg_peval
+    }
     DUP1 %ge_const(@ECREC) SWAP1 %le_const(@KZG_PEVAL)
     // stack: addr>=1, addr<=10
     MUL // Cheaper than AND
+    #[cfg(feature = p256_verify)]
+    {
+        // stack: 1<=addr<=10, addr==P256_VERIFY, is_disabled_kzg_peval
+        OR
+    }
+    // stack: is_candidate, is_disabled_kzg_peval
+    SUB
 %endmacro
 
 // Returns 1 if the account is non-existent, 0 otherwise.
@@ -6,6 +6,8 @@
     // stack: (empty)
 %endmacro
 
+// Credits the withdrawals of the block, followed by the rewards of pre-Merge
+// blocks, which the prover provides in the same format.
 global withdrawals:
     // stack: retdest
     PROVER_INPUT(withdrawal)
@@ -60,23 +60,24 @@ global bn_add_valid_points:
     %jumpi(bn_add_equal_first_coord)
     // stack: x0, y0, x1, y1, retdest
 
-    // Otherwise, we can use the standard formula.
-    // Compute lambda = (y0 - y1)/(x0 - x1)
-    DUP4
-    // stack: y1, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: y0, y1, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: y0 - y1, x0, y0, x1, y1, retdest
-    DUP4
-    // stack: x1, y0 - y1, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: x0, x1, y0 - y1, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: x0 - x1, y0 - y1, x0, y0, x1, y1, retdest
-    %divr_fp254
-    // stack: lambda, x0, y0, x1, y1, retdest
-    %jump(bn_add_valid_points_with_lambda)
+    // Otherwise, we can use the standard formula, carried out by the EC table.
+    // The points are written after the precomputed table of `ecrecover`, which
+    // uses the offsets 0 to 31.
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 34)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 35)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    BN_G1_ADD
+    // stack: retdest
+    // The result is written right after both points.
+    PUSH 37 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 36 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
 
 // BN254 elliptic curve addition.
 // Assumption: (x0,y0) == (0,0)
@@ -95,49 +96,6 @@ bn_add_snd_zero:
     %stack (x0, y0, x1, y1, retdest) -> (retdest, x0, y0)
     JUMP
 
-// BN254 elliptic curve addition.
-// Assumption: lambda = (y0 - y1)/(x0 - x1)
-bn_add_valid_points_with_lambda:
-    // stack: lambda, x0, y0, x1, y1, retdest
-
-    // Compute x2 = lambda^2 - x1 - x0
-    DUP2
-    // stack: x0, lambda, x0, y0, x1, y1, retdest
-    DUP5
-    // stack: x1, x0, lambda, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: lambda, x1, x0, lambda, x0, y0, x1, y1, retdest
-    DUP1
-    // stack: lambda, lambda, x1, x0, lambda, x0, y0, x1, y1, retdest
-    MULFP254
-    // stack: lambda^2, x1, x0, lambda, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: lambda^2 - x1, x0, lambda, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: x2, lambda, x0, y0, x1, y1, retdest
-
-    // Compute y2 = lambda*(x1 - x2) - y1
-    DUP1
-    // stack: x2, x2, lambda, x0, y0, x1, y1, retdest
-    DUP6
-    // stack: x1, x2, x2, lambda, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: x1 - x2, x2, lambda, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: lambda, x1 - x2, x2, lambda, x0, y0, x1, y1, retdest
-    MULFP254
-    // stack: lambda * (x1 - x2), x2, lambda, x0, y0, x1, y1, retdest
-    DUP7
-    // stack: y1, lambda * (x1 - x2), x2, lambda, x0, y0, x1, y1, retdest
-    SWAP1
-    // stack: lambda * (x1 - x2), y1, x2, lambda, x0, y0, x1, y1, retdest
-    SUBFP254
-    // stack: y2, x2, lambda, x0, y0, x1, y1, retdest
-
-    // Return x2,y2
-    %stack (y2, x2, lambda, x0, y0, x1, y1, retdest) -> (retdest, x2, y2)
-    JUMP
-
 // BN254 elliptic curve addition.
 // Assumption: (x0,y0) and (x1,y1) are valid points and x0 == x1
 bn_add_equal_first_coord:
@@ -167,38 +125,34 @@ bn_add_equal_first_coord:
 
 // BN254 elliptic curve addition.
 // Assumption: x0 == x1 and y0 == y1
-// Standard doubling formula.
 bn_add_equal_points:
     // stack: x0, y0, x1, y1, retdest
-
-    // Compute lambda = 3/2 * x0^2 / y0
-    DUP1
-    // stack: x0, x0, y0, x1, y1, retdest
-    DUP1
-    // stack: x0, x0, x0, y0, x1, y1, retdest
-    MULFP254
-    // stack: x0^2, x0, y0, x1, y1, retdest with
-    PUSH 0x183227397098d014dc2822db40c0ac2ecbc0b548b438e5469e10460b6c3e7ea5 // 3/2 in the base field
-    // stack: 3/2, x0^2, x0, y0, x1, y1, retdest
-    MULFP254
-    // stack: 3/2 * x0^2, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: y0, 3/2 * x0^2, x0, y0, x1, y1, retdest
-    %divr_fp254
-    // stack: lambda, x0, y0, x1, y1, retdest
-    %jump(bn_add_valid_points_with_lambda)
+    %stack (x0, y0, x1, y1, retdest) -> (x0, y0, retdest)
+    %jump(bn_double_valid_point)
 
 // BN254 elliptic curve doubling.
 // Assumption: (x0,y0) is a valid point.
-// Standard doubling formula.
+// Standard doubling formula, carried out by the EC table.
 global bn_double:
     // stack: x, y, retdest
     DUP2 DUP2 %ec_isidentity
     // stack: (x,y)==(0,0), x, y, retdest
     %jumpi(ec_double_retself)
-    DUP2 DUP2
-    // stack: x, y, x, y, retdest
-    %jump(bn_add_equal_points)
+bn_double_valid_point:
+    // stack: x, y, retdest
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    BN_G1_DOUBLE
+    // stack: retdest
+    // The result is written right after the point.
+    PUSH 35 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 34 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
 
 // Check if (x,y) is a valid curve point.
 // Returns (range & curve) || ident
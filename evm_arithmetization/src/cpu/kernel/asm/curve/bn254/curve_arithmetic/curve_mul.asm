@@ -1,5 +1,4 @@
-// BN254 elliptic curve scalar multiplication.
-// Uses GLV, wNAF with w=5, and a MSM algorithm.
+// BN254 elliptic curve scalar multiplication, carried out by the EC table.
 global bn_mul:
     // stack: x, y, s, retdest
     DUP2
@@ -22,20 +21,47 @@ global bn_mul:
     %bn_invalid_input
 
 bn_mul_valid_point:
-    %stack (x, y, s, retdest) -> (s, bn_mul_after_glv, x, y, bn_msm, bn_mul_end, retdest)
-    %jump(bn_glv_decompose)
-bn_mul_after_glv:
-    // stack: bneg, a, b, x, y, bn_msm, bn_mul_end, retdest
-    // Store bneg at this (otherwise unused) location. Will be used later in the MSM.
-    %mstore_current(@SEGMENT_BN_TABLE_Q, @BN_BNEG_LOC)
-    // stack: a, b, x, y, bn_msm, bn_mul_end, retdest
-    PUSH bn_mul_after_a SWAP1 PUSH @SEGMENT_BN_WNAF_A PUSH @BN_SCALAR %jump(wnaf)
-bn_mul_after_a:
-    // stack: b, x, y, bn_msm, bn_mul_end, retdest
-    PUSH bn_mul_after_b SWAP1 PUSH @SEGMENT_BN_WNAF_B PUSH @BN_SCALAR %jump(wnaf)
-bn_mul_after_b:
-    // stack: x, y, bn_msm, bn_mul_end, retdest
-    %jump(bn_precompute_table)
-bn_mul_end:
-    %stack (Ax, Ay, retdest) -> (retdest, Ax, Ay)
+    // stack: x, y, s, retdest
+    // The point has prime order N, so the scalar can be reduced modulo N.
+    %stack (x, y, s) -> (s, @BN_SCALAR, x, y)
+    MOD
+    // stack: k, x, y, retdest
+    DUP1 ISZERO
+    // stack: k==0, k, x, y, retdest
+    %jumpi(bn_mul_zero)
+    DUP1 %eq_const(1)
+    // stack: k==1, k, x, y, retdest
+    %jumpi(bn_mul_one)
+    // stack: k, x, y, retdest
+
+    // The inputs are written after the precomputed table of `ecrecover`, which
+    // uses the offsets 0 to 31.
+    %stack (k, x, y) -> (x, y, k)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 34)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    BN_G1_MUL
+    // stack: retdest
+    // The result is written right after the point and the scalar.
+    PUSH 36 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 35 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
+
+// BN254 elliptic curve scalar multiplication.
+// Assumption: k == 0 mod N
+bn_mul_zero:
+    // stack: k, x, y, retdest
+    %stack (k, x, y, retdest) -> (retdest, 0, 0)
+    JUMP
+
+// BN254 elliptic curve scalar multiplication.
+// Assumption: k == 1 mod N
+bn_mul_one:
+    // stack: k, x, y, retdest
+    %stack (k, x, y, retdest) -> (retdest, x, y)
     JUMP
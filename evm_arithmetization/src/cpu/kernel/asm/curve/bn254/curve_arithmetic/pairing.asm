@@ -10,6 +10,9 @@
 ///   = e(G,H)^0
 ///   = 1: Fp12 
 
+// TODO: The Miller loop, the final exponentiation and the G2 arithmetic they rely
+// on run in kernel code: only BN254 G1 operations are carried out in the EC table.
+
 /// def bn254_pairing(pairs: List((Curve, TwistedCurve))) -> Bool:
 ///     
 ///     for P, Q in pairs:
@@ -0,0 +1,151 @@
+// #define N 0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff // P-256 base field order
+
+#[cfg(feature = p256_verify)]
+{
+    // P-256 elliptic curve addition.
+    // Assumption: (x0,y0) and (x1,y1) are valid points.
+    global p256_add:
+        // stack: x0, y0, x1, y1, retdest
+        DUP2 DUP2 %ec_isidentity
+        // stack: (x0,y0)==(0,0), x0, y0, x1, y1, retdest
+        %jumpi(p256_add_first_zero)
+        DUP4 DUP4 %ec_isidentity
+        // stack: (x1,y1)==(0,0), x0, y0, x1, y1, retdest
+        %jumpi(p256_add_snd_zero)
+        DUP3 DUP2 EQ
+        // stack: x0 == x1, x0, y0, x1, y1, retdest
+        %jumpi(p256_add_equal_first_coord)
+
+        // Standard affine addition formula.
+        // Compute lambda = (y0 - y1)/(x0 - x1)
+        %stack (x0, y0, x1, y1) -> (y0, y1, @P256_BASE, x0, x1, @P256_BASE, x0, y0, x1, y1)
+        SUBMOD
+        // stack: y0 - y1, x0, x1, N, x0, y0, x1, y1, retdest
+        %stack (dy, x0, x1, N) -> (x0, x1, N, dy)
+        SUBMOD
+        // stack: x0 - x1, y0 - y1, x0, y0, x1, y1, retdest
+        %moddiv_p256_base
+        // stack: lambda, x0, y0, x1, y1, retdest
+        %jump(p256_add_with_lambda)
+
+    // Assumption: (x0,y0) == (0,0)
+    p256_add_first_zero:
+        %stack (x0, y0, x1, y1, retdest) -> (retdest, x1, y1)
+        JUMP
+
+    // Assumption: (x1,y1) == (0,0)
+    p256_add_snd_zero:
+        %stack (x0, y0, x1, y1, retdest) -> (retdest, x0, y0)
+        JUMP
+
+    // Assumption: lambda is the slope of the line through (x0,y0) and (x1,y1).
+    p256_add_with_lambda:
+        // stack: lambda, x0, y0, x1, y1, retdest
+
+        // Compute x2 = lambda^2 - x0 - x1
+        %stack (lambda, x0, y0, x1, y1) -> (lambda, lambda, @P256_BASE, x0, @P256_BASE, x1, @P256_BASE, lambda, x0, y0)
+        MULMOD
+        // stack: lambda^2, x0, N, x1, N, lambda, x0, y0, retdest
+        SUBMOD
+        // stack: lambda^2 - x0, x1, N, lambda, x0, y0, retdest
+        SUBMOD
+        // stack: x2, lambda, x0, y0, retdest
+
+        // Compute y2 = lambda*(x0 - x2) - y0
+        %stack (x2, lambda, x0, y0) -> (x0, x2, @P256_BASE, lambda, @P256_BASE, y0, @P256_BASE, x2)
+        SUBMOD
+        // stack: x0 - x2, lambda, N, y0, N, x2, retdest
+        MULMOD
+        // stack: lambda * (x0 - x2), y0, N, x2, retdest
+        SUBMOD
+        // stack: y2, x2, retdest
+        %stack (y2, x2, retdest) -> (retdest, x2, y2)
+        JUMP
+
+    // Assumption: (x0,y0) and (x1,y1) are valid points and x0 == x1
+    p256_add_equal_first_coord:
+        // stack: x0, y0, x1, y1, retdest
+        DUP2 DUP5 EQ
+        // stack: y1 == y0, x0, y0, x1, y1, retdest
+        %jumpi(p256_add_equal_points)
+
+        // Otherwise, one is the negation of the other so we can return (0,0).
+        %stack (x0, y0, x1, y1, retdest) -> (retdest, 0, 0)
+        JUMP
+
+    p256_add_equal_points:
+        %stack (x0, y0, x1, y1) -> (x0, y0)
+        // stack: x0, y0, retdest
+        %jump(p256_double)
+
+    // P-256 elliptic curve doubling.
+    // Assumption: (x,y) is a valid point.
+    global p256_double:
+        // stack: x, y, retdest
+        DUP2 DUP2 %ec_isidentity
+        // stack: (x,y)==(0,0), x, y, retdest
+        %jumpi(ec_double_retself)
+
+        // Compute lambda = (3 * x^2 + a) / (2 * y)
+        %stack (x, y) -> (x, x, @P256_BASE, 3, @P256_BASE, @P256_A, @P256_BASE, y, y, @P256_BASE, x, y)
+        MULMOD
+        // stack: x^2, 3, N, a, N, y, y, N, x, y, retdest
+        MULMOD
+        // stack: 3 * x^2, a, N, y, y, N, x, y, retdest
+        ADDMOD
+        // stack: 3 * x^2 + a, y, y, N, x, y, retdest
+        %stack (numerator, y0, y1, N) -> (y0, y1, N, numerator)
+        ADDMOD
+        // stack: 2 * y, 3 * x^2 + a, x, y, retdest
+        %moddiv_p256_base
+        // stack: lambda, x, y, retdest
+        %stack (lambda, x, y) -> (lambda, x, y, x, y)
+        %jump(p256_add_with_lambda)
+
+    // Check if (x,y) is a valid curve point, excluding the identity.
+    // Puts (x < N) & (y < N) & (y^2 % N == (x^3 + a * x + b) % N) on top of the stack.
+    %macro p256_check
+        // stack: x, y
+        DUP2 %lt_const(@P256_BASE)
+        DUP2 %lt_const(@P256_BASE)
+        AND
+        // stack: (x < N) & (y < N), x, y
+        %stack (in_range, x, y) -> (x, x, @P256_BASE, @P256_A, @P256_BASE, x, @P256_BASE, @P256_B, @P256_BASE, y, y, @P256_BASE, in_range)
+        MULMOD
+        // stack: x^2, a, N, x, N, b, N, y, y, N, in_range
+        ADDMOD
+        // stack: x^2 + a, x, N, b, N, y, y, N, in_range
+        MULMOD
+        // stack: x^3 + a * x, b, N, y, y, N, in_range
+        ADDMOD
+        // stack: x^3 + a * x + b, y, y, N, in_range
+        %stack (rhs, y0, y1, N) -> (y0, y1, N, rhs)
+        MULMOD
+        // stack: y^2, x^3 + a * x + b, in_range
+        EQ
+        AND
+        // stack: (x < N) & (y < N) & (y^2 % N == (x^3 + a * x + b) % N)
+    %endmacro
+
+    // Returns y * (x^-1) where the inverse is taken modulo N
+    %macro moddiv_p256_base
+        // stack: x, y
+        %inverse_p256_base
+        // stack: x^-1, y
+        %stack (x_inv, y) -> (x_inv, y, @P256_BASE)
+        MULMOD
+    %endmacro
+
+    // Non-deterministically provide the inverse modulo N.
+    %macro inverse_p256_base
+        // stack: x
+        PROVER_INPUT(ff::p256_base::inverse)
+        // stack: x^-1, x
+        %stack (inv, x) -> (inv, x, @P256_BASE, inv)
+        // stack: x^-1, x, N, x^-1
+        MULMOD
+        // stack: x^-1 * x, x^-1
+        %assert_eq_const(1)
+        // stack: x^-1
+    %endmacro
+}
@@ -0,0 +1,114 @@
+// ECDSA signature verification over the P-256 (secp256r1) curve, as specified in
+// RIP-7212: https://github.com/ethereum/RIPs/blob/master/RIPS/rip-7212.md
+
+#[cfg(feature = p256_verify)]
+{
+    // Returns 1 if (r, s) is a valid signature of hash by the public key (qx, qy), 0 otherwise.
+    global p256_verify:
+        // stack: hash, r, s, qx, qy, retdest
+
+        // Check that 0 < r < n and 0 < s < n.
+        DUP2 %p256_scalar_in_range
+        // stack: r_valid, hash, r, s, qx, qy, retdest
+        DUP4 %p256_scalar_in_range
+        AND
+        // stack: r_valid & s_valid, hash, r, s, qx, qy, retdest
+
+        // Check that the public key is a valid point.
+        DUP6 DUP6 %p256_check
+        AND
+        // stack: is_valid, hash, r, s, qx, qy, retdest
+        ISZERO %jumpi(p256_verify_invalid)
+
+        // Compute u1 = hash / s and u2 = r / s.
+        DUP3 %inverse_p256_scalar
+        // stack: s^-1, hash, r, s, qx, qy, retdest
+        %stack (s_inv, hash, r, s, qx, qy) -> (hash, s_inv, @P256_SCALAR, r, s_inv, @P256_SCALAR, qx, qy, p256_verify_after_msm, r)
+        MULMOD
+        // stack: u1, r, s^-1, n, qx, qy, p256_verify_after_msm, r, retdest
+        %stack (u1, r, s_inv, n) -> (r, s_inv, n, u1)
+        MULMOD
+        // stack: u2, u1, qx, qy, p256_verify_after_msm, r, retdest
+        SWAP1
+        // stack: u1, u2, qx, qy, p256_verify_after_msm, r, retdest
+        %jump(p256_msm)
+
+    p256_verify_after_msm:
+        // stack: x, y, r, retdest
+        DUP2 DUP2 %ec_isidentity
+        // stack: (x,y)==(0,0), x, y, r, retdest
+        %jumpi(p256_verify_identity)
+
+        // The signature is valid iff x % n == r.
+        %stack (x, y, r) -> (x, @P256_SCALAR, r)
+        MOD
+        EQ
+        // stack: x % n == r, retdest
+        SWAP1
+        JUMP
+
+    p256_verify_invalid:
+        %stack (hash, r, s, qx, qy, retdest) -> (retdest, 0)
+        JUMP
+
+    p256_verify_identity:
+        %stack (x, y, r, retdest) -> (retdest, 0)
+        JUMP
+
+    // Computes u1 * G + u2 * Q with Shamir's trick, where G is the generator of P-256.
+    // Assumption: Q is a valid point.
+    global p256_msm:
+        // stack: u1, u2, qx, qy, retdest
+        %stack (u1, u2, qx, qy) -> (0, 0, 255, u1, u2, qx, qy)
+        // stack: x, y, i, u1, u2, qx, qy, retdest
+    p256_msm_loop:
+        // stack: x, y, i, u1, u2, qx, qy, retdest
+        %stack (x, y) -> (x, y, p256_msm_after_double)
+        %jump(p256_double)
+    p256_msm_after_double:
+        // stack: x, y, i, u1, u2, qx, qy, retdest
+        DUP4 DUP4 SHR %and_const(1)
+        // stack: (u1 >> i) & 1, x, y, i, u1, u2, qx, qy, retdest
+        ISZERO %jumpi(p256_msm_after_add_g)
+        %stack (x, y) -> (x, y, @P256_GX, @P256_GY, p256_msm_after_add_g)
+        %jump(p256_add)
+    p256_msm_after_add_g:
+        // stack: x, y, i, u1, u2, qx, qy, retdest
+        DUP5 DUP4 SHR %and_const(1)
+        // stack: (u2 >> i) & 1, x, y, i, u1, u2, qx, qy, retdest
+        ISZERO %jumpi(p256_msm_after_add_q)
+        %stack (x, y, i, u1, u2, qx, qy) -> (x, y, qx, qy, p256_msm_after_add_q, i, u1, u2, qx, qy)
+        %jump(p256_add)
+    p256_msm_after_add_q:
+        // stack: x, y, i, u1, u2, qx, qy, retdest
+        DUP3 ISZERO %jumpi(p256_msm_end)
+        SWAP2 %decrement SWAP2
+        // stack: x, y, i - 1, u1, u2, qx, qy, retdest
+        %jump(p256_msm_loop)
+    p256_msm_end:
+        %stack (x, y, i, u1, u2, qx, qy, retdest) -> (retdest, x, y)
+        JUMP
+
+    // Puts 0 < x < n on top of the stack.
+    %macro p256_scalar_in_range
+        // stack: x
+        DUP1 ISZERO ISZERO
+        // stack: x != 0, x
+        SWAP1 %lt_const(@P256_SCALAR)
+        // stack: x < n, x != 0
+        AND
+    %endmacro
+
+    // Non-deterministically provide the inverse modulo n.
+    %macro inverse_p256_scalar
+        // stack: x
+        PROVER_INPUT(ff::p256_scalar::inverse)
+        // stack: x^-1, x
+        %stack (inv, x) -> (inv, x, @P256_SCALAR, inv)
+        // stack: x^-1, x, n, x^-1
+        MULMOD
+        // stack: x^-1 * x, x^-1
+        %assert_eq_const(1)
+        // stack: x^-1
+    %endmacro
+}
@@ -33,27 +33,27 @@ global secp_add_valid_points:
     EQ
     // stack: x0 == x1, x0, y0, x1, y1, retdest
     %jumpi(secp_add_equal_first_coord)
-// Standard affine addition formula.
+// Standard affine addition formula, carried out by the EC table.
+// Assumption: (x0,y0) and (x1,y1) are valid points, not the identity, and x0 != x1.
 global secp_add_valid_points_no_edge_case:
     // stack: x0, y0, x1, y1, retdest
-    // Compute lambda = (y0 - y1)/(x0 - x1)
-    %secp_base
-    // stack: N, x0, y0, x1, y1, retdest
-    DUP5
-    DUP4
-    // stack: y0, y1, N, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: y0 - y1, x0, y0, x1, y1, retdest
-    %secp_base
-    // stack: N, y0 - y1, x0, y0, x1, y1, retdest
-    DUP5
-    DUP4
-    // stack: x0, x1, N, y0 - y1, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: x0 - x1, y0 - y1, x0, y0, x1, y1, retdest
-    %moddiv_secp_base
-    // stack: lambda, x0, y0, x1, y1, retdest
-    %jump(secp_add_valid_points_with_lambda)
+    // The points are written after the precomputed table of `ecrecover`, which
+    // uses the offsets 0 to 31.
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 34)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 35)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    SECP_ADD
+    // stack: retdest
+    // The result is written right after both points.
+    PUSH 37 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 36 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
 
 // Secp256k1 elliptic curve addition.
 // Assumption: (x0,y0) == (0,0)
@@ -89,66 +89,6 @@ secp_add_snd_zero:
     // stack: retdest, x0, y0
     JUMP
 
-// Secp256k1 elliptic curve addition.
-// Assumption: lambda = (y0 - y1)/(x0 - x1)
-secp_add_valid_points_with_lambda:
-    // stack: lambda, x0, y0, x1, y1, retdest
-
-    // Compute x2 = lambda^2 - x1 - x0
-    %secp_base
-    // stack: N, lambda, x0, y0, x1, y1, retdest
-    DUP3
-    // stack: x0, N, lambda, x0, y0, x1, y1, retdest
-    %secp_base
-    // stack: N, x0, N, lambda, x0, y0, x1, y1, retdest
-    DUP7
-    // stack: x1, N, x0, N, lambda, x0, y0, x1, y1, retdest
-    %secp_base
-    // stack: N, x1, N, x0, N, lambda, x0, y0, x1, y1, retdest
-    DUP6
-    // stack: lambda, N, x1, N, x0, N, lambda, x0, y0, x1, y1, retdest
-    DUP1
-    // stack: lambda, lambda, N, x1, N, x0, N, lambda, x0, y0, x1, y1, retdest
-    MULMOD
-    // stack: lambda^2, x1, N, x0, N, lambda, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: lambda^2 - x1, x0, N, lambda, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: x2, lambda, x0, y0, x1, y1, retdest
-
-    // Compute y2 = lambda*(x1 - x2) - y1
-    %secp_base %secp_base %secp_base // Pre-load moduli for incoming SUBMODs
-    // stack: N, N, N, x2, lambda, x0, y0, x1, y1, retdest
-    DUP4
-    // stack: x2, N, N, N, x2, lambda, x0, y0, x1, y1, retdest
-    DUP9
-    // stack: x1, x2, N, N, N, x2, lambda, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: x1 - x2, N, N, x2, lambda, x0, y0, x1, y1, retdest
-    DUP5
-    // stack: lambda, x1 - x2, N, N, x2, lambda, x0, y0, x1, y1, retdest
-    MULMOD
-    // stack: lambda * (x1 - x2), N, x2, lambda, x0, y0, x1, y1, retdest
-    DUP8
-    // stack: y1, lambda * (x1 - x2), N, x2, lambda, x0, y0, x1, y1, retdest
-    SWAP1
-    // stack: lambda * (x1 - x2), y1, N, x2, lambda, x0, y0, x1, y1, retdest
-    SUBMOD
-    // stack: y2, x2, lambda, x0, y0, x1, y1, retdest
-
-    // Return x2,y2
-    SWAP5
-    // stack: x1, x2, lambda, x0, y0, y2, y1, retdest
-    POP
-    // stack: x2, lambda, x0, y0, y2, y1, retdest
-    SWAP5
-    // stack: y1, lambda, x0, y0, y2, x2, retdest
-    %pop4
-    // stack: y2, x2, retdest
-    SWAP2
-    // stack: retdest, x2, y2
-    JUMP
-
 // Secp256k1 elliptic curve addition.
 // Assumption: (x0,y0) and (x1,y1) are valid points and x0 == x1
 secp_add_equal_first_coord:
@@ -178,35 +118,36 @@ secp_add_equal_first_coord:
 
 // Secp256k1 elliptic curve addition.
 // Assumption: x0 == x1 and y0 == y1
-// Standard doubling formula.
 secp_add_equal_points:
-    // Compute lambda = 3/2 * x0^2 / y0
-    %stack (x0, y0, x1, y1, retdest) -> (x0, x0, @SECP_BASE, @SECP_BASE, x0, y0, x1, y1, retdest)
-    MULMOD
-    PUSH 0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffff7ffffe19 // 3/2 in the base field
-    MULMOD
-    DUP3
-    %moddiv_secp_base
-    %jump(secp_add_valid_points_with_lambda)
+    // stack: x0, y0, x1, y1, retdest
+    %stack (x0, y0, x1, y1, retdest) -> (x0, y0, retdest)
+    %jump(secp_double_valid_point)
 
 // Secp256k1 elliptic curve doubling.
 // Assumption: (x,y) is a valid point.
-// Standard doubling formula.
+// Standard doubling formula, carried out by the EC table.
 global secp_double:
     // stack: x, y, retdest
     DUP2 DUP2 %ec_isidentity
     // stack: (x,y)==(0,0), x, y, retdest
     %jumpi(ec_double_retself)
-
-    // Compute lambda = 3/2 * x0^2 / y0
-    %stack (x, y, retdest) -> (x, x, @SECP_BASE, @SECP_BASE, x, y, x, y, retdest)
-    MULMOD
-    PUSH 0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffff7ffffe19 // 3/2 in the base field
-    MULMOD
-    DUP3
-    %moddiv_secp_base
-    // stack: lambda, x, y, x, y, retdest
-    %jump(secp_add_valid_points_with_lambda)
+secp_double_valid_point:
+    // stack: x, y, retdest
+    // The point is written after the precomputed table of `ecrecover`, which
+    // uses the offsets 0 to 31.
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    SECP_DOUBLE
+    // stack: retdest
+    // The result is written right after the point.
+    PUSH 35 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 34 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
 
 // Push the order of the Secp256k1 scalar field.
 %macro secp_base
@@ -0,0 +1,60 @@
+// Secp256k1 elliptic curve scalar multiplication, carried out by the EC table.
+// Assumption: (x,y) is a valid point, and k < N.
+global secp_mul:
+    // stack: k, x, y, retdest
+
+    // Check if the point is the identity, or the scalar zero.
+    DUP3
+    // stack: y, k, x, y, retdest
+    DUP3
+    // stack: x, y, k, x, y, retdest
+    %ec_isidentity
+    // stack: (x,y)==(0,0), k, x, y, retdest
+    DUP2
+    // stack: k, (x,y)==(0,0), k, x, y, retdest
+    ISZERO
+    // stack: k==0, (x,y)==(0,0), k, x, y, retdest
+    OR
+    // stack: k==0 || (x,y)==(0,0), k, x, y, retdest
+    %jumpi(secp_mul_identity)
+    // stack: k, x, y, retdest
+
+    // Check if the scalar is one.
+    DUP1
+    // stack: k, k, x, y, retdest
+    %eq_const(1)
+    // stack: k==1, k, x, y, retdest
+    %jumpi(secp_mul_one)
+    // stack: k, x, y, retdest
+
+    // The inputs are written after the precomputed table of `ecrecover`, which
+    // uses the offsets 0 to 31.
+    %stack (k, x, y) -> (x, y, k)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 32)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 33)
+    %mstore_current(@SEGMENT_ECDSA_TABLE, 34)
+    // stack: retdest
+    PUSH 32 PUSH @SEGMENT_ECDSA_TABLE GET_CONTEXT %build_address
+    // stack: addr, retdest
+    SECP_MUL
+    // stack: retdest
+    // The result is written right after the point and the scalar.
+    PUSH 36 %mload_current(@SEGMENT_ECDSA_TABLE)
+    PUSH 35 %mload_current(@SEGMENT_ECDSA_TABLE)
+    // stack: x2, y2, retdest
+    %stack (x2, y2, retdest) -> (retdest, x2, y2)
+    JUMP
+
+// Secp256k1 elliptic curve scalar multiplication.
+// Assumption: k == 0 or (x,y) == (0,0)
+secp_mul_identity:
+    // stack: k, x, y, retdest
+    %stack (k, x, y, retdest) -> (retdest, 0, 0)
+    JUMP
+
+// Secp256k1 elliptic curve scalar multiplication.
+// Assumption: k == 1
+secp_mul_one:
+    // stack: k, x, y, retdest
+    %stack (k, x, y, retdest) -> (retdest, x, y)
+    JUMP
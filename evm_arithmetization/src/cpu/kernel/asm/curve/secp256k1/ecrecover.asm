@@ -54,60 +54,30 @@ ecrecover_valid_input:
     %stack (u1, y, hash, x, rinv, retdest) -> (hash, @SECP_SCALAR, @SECP_SCALAR, rinv, @SECP_SCALAR, u1, x, y, pubkey_to_addr, retdest)
     MOD SWAP1 SUB MULMOD
     // stack: u2, u1, x, y, pubkey_to_addr, retdest
-    %jump(ecdsa_msm_with_glv)
+    %jump(ecdsa_msm)
 
-// Computes `a * G + b * Q` using GLV+precomputation, where `G` is the Secp256k1 generator and `Q` is a point on the curve.
-// Pseudo-code:
-// precompute_table(G) -- precomputation table for the combinations of `G, phi(G), Q, phi(Q)`.
-// let a0, a1 = glv_decompose(a)
-// let b0, b1 = glv_decompose(b)
-// return msm_with_precomputation([a0, a1, b0, b1], [G, phi(G), Q, phi(Q)]) -- phi is the Secp endomorphism.
-ecdsa_msm_with_glv:
-    %stack (a, b, Qx, Qy, retdest) -> (a, ecdsa_after_glv_a, b, Qx, Qy, retdest)
-    %jump(secp_glv_decompose)
-ecdsa_after_glv_a:
-    %stack (a1neg, a0, a1, b, Qx, Qy, retdest) -> (b, ecdsa_after_glv_b, a1neg, a0, a1, Qx, Qy, retdest)
-    %jump(secp_glv_decompose)
-ecdsa_after_glv_b:
-    %stack (b1neg, b0, b1, a1neg, a0, a1, Qx, Qy, retdest) -> (a1neg, b1neg, Qx, Qy, ecdsa_after_precompute, a0, a1, b0, b1, retdest)
-    %jump(secp_precompute_table)
-ecdsa_after_precompute:
-    // stack: a0, a1, b0, b1, retdest
-    PUSH 0 PUSH 0 PUSH 129 // 129 is the bit length of the GLV exponents
-    // stack: i, accx, accy, a0, a1, b0, b1, retdest
-ecdsa_after_precompute_loop:
-    %stack (i, accx, accy, a0, a1, b0, b1, retdest) -> (i, b1, i, accx, accy, a0, a1, b0, b1, retdest)
-    SHR %and_const(1)
-    %stack (bit_b1, i, accx, accy, a0, a1, b0, b1, retdest) -> (i, b0, bit_b1, i, accx, accy, a0, a1, b0, b1, retdest)
-    SHR %and_const(1)
-    %stack (bit_b0, bit_b1, i, accx, accy, a0, a1, b0, b1, retdest) -> (i, a1, bit_b0, bit_b1, i, accx, accy, a0, a1, b0, b1, retdest)
-    SHR %and_const(1)
-    %stack (bit_a1, bit_b0, bit_b1, i, accx, accy, a0, a1, b0, b1, retdest) -> (i, a0, bit_a1, bit_b0, bit_b1, i, accx, accy, a0, a1, b0, b1, retdest)
-    SHR %and_const(1)
-    %mul_const(2) ADD %mul_const(2) ADD %mul_const(2) ADD
-    %stack (index, i, accx, accy, a0, a1, b0, b1, retdest) -> (index, index, i, accx, accy, a0, a1, b0, b1, retdest)
-    %mul_const(2) %add_const(1)
-    %mload_current(@SEGMENT_ECDSA_TABLE)
-    SWAP1 %mul_const(2)
-    %mload_current(@SEGMENT_ECDSA_TABLE)
-    %stack (Px, Py, i, accx, accy, a0, a1, b0, b1, retdest) -> (Px, Py, accx, accy, ecdsa_after_precompute_loop_contd, i, a0, a1, b0, b1, retdest)
+// Computes `a * G + b * Q` with two scalar multiplications in the EC table, where `G` is the Secp256k1 generator and `Q` is a point on the curve.
+ecdsa_msm:
+    // stack: a, b, Qx, Qy, retdest
+    %stack (a, b, Qx, Qy) -> (b, Qx, Qy, ecdsa_after_mul_q, a)
+    %jump(secp_mul)
+ecdsa_after_mul_q:
+    // stack: bQx, bQy, a, retdest
+    %stack (bQx, bQy, a) -> (a, @SECP_GENERATOR_X, @SECP_GENERATOR_Y, ecdsa_after_mul_g, bQx, bQy)
+    %jump(secp_mul)
+ecdsa_after_mul_g:
+    // stack: aGx, aGy, bQx, bQy, retdest
+    %stack (aGx, aGy, bQx, bQy) -> (aGx, aGy, bQx, bQy, ecdsa_after_msm)
     %jump(secp_add_valid_points)
-ecdsa_after_precompute_loop_contd:
-    %stack (accx, accy, i, a0, a1, b0, b1, retdest) -> (i, accx, accy, ecdsa_after_precompute_loop_contd2, i, a0, a1, b0, b1, retdest)
-    ISZERO %jumpi(ecdsa_after_precompute_loop_end)
-    %jump(secp_double)
-ecdsa_after_precompute_loop_contd2:
-    %stack (accx, accy, i, a0, a1, b0, b1, retdest) -> (i, 1, accx, accy, a0, a1, b0, b1, retdest)
-    SUB // i - 1
-    %jump(ecdsa_after_precompute_loop)
-ecdsa_after_precompute_loop_end:
+ecdsa_after_msm:
+    // stack: accx, accy, retdest
     // Check that the public key is not the point at infinity. See https://github.com/ethereum/eth-keys/pull/76 for discussion.
     DUP2 DUP2 ISZERO SWAP1 ISZERO MUL %jumpi(pk_is_infinity)
-    %stack (accx, accy, ecdsa_after_precompute_loop_contd2, i, a0, a1, b0, b1, retdest) -> (retdest, accx, accy)
+    %stack (accx, accy, retdest) -> (retdest, accx, accy)
     JUMP
 
 pk_is_infinity:
-    %stack (accx, accy, ecdsa_after_precompute_loop_contd2, i, a0, a1, b0, b1, pubkey_to_addr, retdest) -> (retdest, @U256_MAX)
+    %stack (accx, accy, pubkey_to_addr, retdest) -> (retdest, @U256_MAX)
     JUMP
 
 // Take a public key (PKx, PKy) and return the associated address KECCAK256(PKx || PKy)[-20:].
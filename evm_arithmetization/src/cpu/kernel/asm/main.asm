@@ -67,6 +67,11 @@ global main:
     // Initialize transient storage length
     %init_transient_storage_len
 
+    // PUSH0 is only a valid user instruction from Shanghai on. The CPU reads
+    // this flag when executing PUSH0 in user mode.
+    %before_hardfork(@HARDFORK_SHANGHAI) ISZERO
+    %mstore_global_metadata(@GLOBAL_METADATA_PUSH0_ENABLED)
+
     // Initialize the RLP DATA pointer to its initial position, 
     // skipping over the preinitialized empty node.
     PUSH @INITIAL_TXN_RLP_ADDR
@@ -131,10 +136,10 @@ global start_txns:
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_GAS_USED_BEFORE)
     // stack: init_gas_used, txn_counter, num_nibbles, txn_nb
 
-    // If txn_idx == 0, update the beacon_root and exit roots.
+    // If txn_idx == 0, check the block hashes, and update the beacon_root and exit roots.
     %mload_global_metadata(@GLOBAL_METADATA_TXN_NUMBER_BEFORE)
     ISZERO
-    %jumpi(set_beacon_root)
+    %jumpi(check_ancestor_hashes)
 
     // stack: init_gas_used, txn_counter, num_nibbles, txn_nb
 global txn_loop:
@@ -218,6 +223,7 @@ global check_state_trie:
     PUSH 1
 global check_final_state_trie:
     %set_final_tries
+global hash_final_state_trie:
     %mpt_hash_state_trie   %mload_global_metadata(@GLOBAL_METADATA_STATE_TRIE_DIGEST_AFTER)     %assert_eq
     // We don't need the trie data length here.
     POP
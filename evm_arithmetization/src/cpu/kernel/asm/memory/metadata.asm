@@ -266,9 +266,12 @@ global sys_chainid:
 
 %macro basefee
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_BASE_FEE)
+    // Blocks have no base fee before London.
+    %before_hardfork(@HARDFORK_LONDON) ISZERO MUL
 %endmacro
 
 global sys_basefee:
+    %check_hardfork(@HARDFORK_LONDON)
     // stack: kexit_info
     %charge_gas_const(@GAS_BASE)
     // stack: kexit_info
@@ -278,6 +281,7 @@ global sys_basefee:
     EXIT_KERNEL
 
 global sys_blobhash:
+    %check_hardfork(@HARDFORK_CANCUN)
     // stack: kexit_info, index
     %charge_gas_const(@GAS_HASH_OPCODE)
     // stack: kexit_info, index
@@ -311,6 +315,7 @@ global sys_blobhash:
 %endmacro
 
 global sys_blobbasefee:
+    %check_hardfork(@HARDFORK_CANCUN)
     // stack: kexit_info
     %charge_gas_const(@GAS_BASE)
     // stack: kexit_info
@@ -362,6 +367,40 @@ zero_hash:
     PUSH 0 SWAP1
     JUMP
 
+/// At the top of the block, checks the 256 previous block hashes read by
+/// `BLOCKHASH` against the public values: the last one must be the parent
+/// hash, and their concatenation must hash to the ancestors hash.
+/// Clobbers @SEGMENT_KERNEL_GENERAL, and continues with `set_beacon_root`.
+global check_ancestor_hashes:
+    PUSH 255 %mload_kernel(@SEGMENT_BLOCK_HASHES)
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_PARENT_HASH)
+    %assert_eq
+
+    // Write the hashes to @SEGMENT_KERNEL_GENERAL, from the oldest one.
+    PUSH @SEGMENT_KERNEL_GENERAL
+    PUSH 0
+ancestor_hashes_loop:
+    // stack: i, addr
+    DUP1 %eq_const(256) %jumpi(ancestor_hashes_loop_end)
+    DUP1 %mload_kernel(@SEGMENT_BLOCK_HASHES)
+    // stack: hash_i, i, addr
+    DUP3
+    MSTORE_32BYTES_32
+    // stack: addr', i, addr
+    SWAP2 POP
+    // stack: i, addr'
+    %increment
+    %jump(ancestor_hashes_loop)
+ancestor_hashes_loop_end:
+    // stack: i, addr
+    %pop2
+    PUSH 8192 PUSH @SEGMENT_KERNEL_GENERAL
+    // stack: addr, len
+    KECCAK_GENERAL
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_ANCESTORS_HASH)
+    %assert_eq
+    %jump(set_beacon_root)
+
 %macro update_mem_words
     // stack: num_words, kexit_info
     %mem_words
@@ -440,6 +479,19 @@ zero_hash:
     %jumpi(fault_exception)
 %endmacro
 
+// Returns whether the block follows the rules of a hardfork older than `fork`.
+%macro before_hardfork(fork)
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_HARDFORK)
+    %lt_const($fork)
+%endmacro
+
+// Convenience macro for opcodes introduced by the hardfork `fork`, which are
+// invalid in blocks following older hardforks.
+%macro check_hardfork(fork)
+    %before_hardfork($fork)
+    %jumpi(fault_exception)
+%endmacro
+
 // Adds the two top elements of the stack, and faults in case of overflow.
 %macro add_or_fault
     // stack: x, y
@@ -486,7 +538,12 @@ zero_hash:
 global sys_prevrandao:
     // stack: kexit_info
     %charge_gas_const(@GAS_BASE)
+    // Before the Merge, this opcode is DIFFICULTY.
+    %mload_global_metadata(@GLOBAL_METADATA_BLOCK_DIFFICULTY)
     %mload_global_metadata(@GLOBAL_METADATA_BLOCK_RANDOM)
+    %before_hardfork(@HARDFORK_PARIS)
+    // stack: is_pre_merge, random, difficulty, kexit_info
+    %select_bool
     %stack (random, kexit_info) -> (kexit_info, random)
     EXIT_KERNEL
 
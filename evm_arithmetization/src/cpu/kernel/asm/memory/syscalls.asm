@@ -264,6 +264,7 @@ extcodecopy_contd:
 
 // Same as %wcopy but with special handling in case of overlapping ranges.
 global sys_mcopy:
+    %check_hardfork(@HARDFORK_CANCUN)
     // stack: kexit_info, dest_offset, offset, size
     %wcopy_charge_gas
 
@@ -95,6 +95,7 @@ tload_found:
 // Pre stack: kexit_info, slot
 // Post stack: value
 global sys_tload:
+    %check_hardfork(@HARDFORK_CANCUN)
     // stack: kexit_info, slot
     %charge_gas_const(@GAS_WARMACCESS)
     // stack: kexit_info, slot
@@ -112,6 +113,7 @@ global sys_tload:
 // Post stack: (empty)
 
 global sys_tstore:
+    %check_hardfork(@HARDFORK_CANCUN)
     %check_static
     %charge_gas_const(@GAS_WARMACCESS)
     %stack (kexit_info, slot, value) -> (slot, value, kexit_info)
@@ -166,6 +166,10 @@ global encode_receipt:
     // The first value is either the transaction type or the payload length.
     // Since the receipt contains at least the 256-bytes long bloom filter, payload_len > 3.
     DUP1 %lt_const(4) %jumpi(encode_nonzero_receipt_type)
+    #[cfg(feature = op_deposit)]
+    {
+        DUP1 %eq_const(0x7e) %jumpi(encode_nonzero_receipt_type)
+    }
     // If we are here, then the first byte is the payload length.
     %rlp_list_len
     // stack: rlp_receipt_len, rlp_addr, value_ptr, cur_len, retdest
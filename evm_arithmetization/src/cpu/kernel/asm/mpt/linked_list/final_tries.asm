@@ -1,6 +1,8 @@
 // Given a pointer `root_ptr` to the root of a trie, insert all accounts in
 // the accounts_linked_list starting at `account_ptr_ptr` as well as the
 // respective storage slots in `storage_ptr_ptr`.
+// Accounts and slots of the initial state which were not modified are already
+// in the tries, with their initial payloads, and are not inserted again.
 // Pre stack: account_ptr_ptr, root_ptr, storage_ptr_ptr, retdest
 // Post stack: new_root_ptr.
 global insert_all_accounts:
@@ -32,6 +34,10 @@ after_insert_all_slots:
     %mstore_trie_data
     // stack: storage_ptr_ptr', key, root_ptr, account_ptr_ptr, retdest
     DUP4
+    %account_unchanged
+    %jumpi(skip_account)
+    // stack: storage_ptr_ptr', key, root_ptr, account_ptr_ptr, retdest
+    DUP4
     %increment
     MLOAD_GENERAL
     %stack
@@ -45,6 +51,13 @@ after_insert_account:
     // stack: account_ptr_ptr', root_ptr', storage_ptr_ptr', retdest
     %jump(insert_all_accounts)
 
+global skip_account:
+    // stack: storage_ptr_ptr', key, root_ptr, account_ptr_ptr, retdest
+    %stack (storage_ptr_ptr_p, key, root_ptr, account_ptr_ptr) -> (account_ptr_ptr, root_ptr, storage_ptr_ptr_p)
+    %next_account
+    // stack: account_ptr_ptr', root_ptr, storage_ptr_ptr', retdest
+    %jump(insert_all_accounts)
+
 no_more_accounts:
     // stack: key, storage_ptr_ptr, root_ptr, account_ptr_ptr, retdest
     %stack (key, storage_ptr_ptr, root_ptr, account_ptr_ptr, retdest) ->(retdest, root_ptr)
@@ -65,6 +78,10 @@ global insert_all_slots:
     JUMP
 
 insert_next_slot:
+    // stack: addr, storage_ptr_ptr, root_ptr, retdest
+    DUP2
+    %slot_unchanged
+    %jumpi(skip_slot)
     // stack: addr, storage_ptr_ptr, root_ptr, retdest
     DUP2
     %increment
@@ -89,6 +106,79 @@ after_insert_slot:
     %stack (storage_ptr_ptr_p, root_ptr_p, addr) -> (addr, storage_ptr_ptr_p, root_ptr_p)
     %jump(insert_all_slots)
 
+global skip_slot:
+    // stack: addr, storage_ptr_ptr, root_ptr, retdest
+    SWAP1
+    %next_slot
+    SWAP1
+    // stack: addr, storage_ptr_ptr', root_ptr, retdest
+    %jump(insert_all_slots)
+
+// Returns 1 if the slot at `storage_ptr_ptr` belongs to the initial storage
+// linked list and still holds its initial value, in which case it is already
+// in the storage trie, and 0 otherwise.
+// Pre stack: storage_ptr_ptr
+// Post stack: is_unchanged
+%macro slot_unchanged
+    // stack: storage_ptr_ptr
+    DUP1
+    %add_const(2)
+    MLOAD_GENERAL
+    DUP2
+    %add_const(3)
+    MLOAD_GENERAL
+    EQ
+    // stack: value == initial_value, storage_ptr_ptr
+    SWAP1
+    %mload_global_metadata(@GLOBAL_METADATA_INITIAL_STORAGE_LINKED_LIST_LEN)
+    GT
+    // stack: storage_ptr_ptr < @GLOBAL_METADATA_INITIAL_STORAGE_LINKED_LIST_LEN, value == initial_value
+    MUL // AND
+%endmacro
+
+// Returns 1 if the account at `account_ptr_ptr` belongs to the initial accounts
+// linked list and its payload, including the pointer to its storage trie, is
+// equal to its initial payload, in which case it is already in the state trie,
+// and 0 otherwise.
+// Pre stack: account_ptr_ptr
+// Post stack: is_unchanged
+%macro account_unchanged
+    // stack: account_ptr_ptr
+    DUP1
+    %mload_global_metadata(@GLOBAL_METADATA_INITIAL_ACCOUNTS_LINKED_LIST_LEN)
+    GT
+    // stack: is_unchanged, account_ptr_ptr
+    DUP2
+    %increment
+    MLOAD_GENERAL
+    DUP3
+    %add_const(2)
+    MLOAD_GENERAL
+    // stack: initial_payload_ptr, payload_ptr, is_unchanged, account_ptr_ptr
+    // Compare the nonce, balance, storage root pointer and code hash.
+    %rep 4
+        DUP2
+        %mload_trie_data
+        DUP2
+        %mload_trie_data
+        EQ
+        // stack: field == initial_field, initial_field_ptr, field_ptr, is_unchanged, account_ptr_ptr
+        DUP4
+        MUL // AND
+        SWAP3
+        POP
+        // stack: initial_field_ptr, field_ptr, is_unchanged', account_ptr_ptr
+        %increment
+        SWAP1
+        %increment
+        SWAP1
+    %endrep
+    // stack: initial_field_ptr, field_ptr, is_unchanged, account_ptr_ptr
+    %pop2
+    SWAP1
+    POP
+%endmacro
+
 // Delete all the accounts, referenced by the respective nodes in the linked list starting at 
 // `account_ptr_ptr`, which where deleted from the initial state. Delete also all slots of non-deleted accounts 
 // deleted from the storage trie.
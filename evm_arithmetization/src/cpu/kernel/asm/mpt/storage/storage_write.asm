@@ -68,11 +68,11 @@ sstore_refund:
     %jump(sstore_dirty_reset)
 
 sstore_dirty_clear1:
-    PUSH @REFUND_SCLEAR PUSH 0 SUB %refund_gas
+    %refund_sclear PUSH 0 SUB %refund_gas
     %jump(sstore_dirty_reset)
 
 sstore_dirty_clear2:
-    PUSH @REFUND_SCLEAR %refund_gas
+    %refund_sclear %refund_gas
 
 sstore_dirty_reset:
     %stack (current_value, value, original_value, slot, kexit_info) -> (original_value, value, current_value, value, original_value, slot, kexit_info)
@@ -92,7 +92,7 @@ sstore_refund_original:
     ISZERO %jumpi(sstore_sclear)
     %jump(sstore_no_refund)
 sstore_sclear:
-    PUSH @REFUND_SCLEAR %refund_gas
+    %refund_sclear %refund_gas
     %jump(sstore_no_refund)
 
 sstore_no_refund:
@@ -134,3 +134,12 @@ sstore_delete:
     %slot_to_storage_key
     %remove_slot
     EXIT_KERNEL
+
+// Pushes the refund of a storage clear, reduced by EIP-3529 in London.
+%macro refund_sclear
+    PUSH @REFUND_SCLEAR_PRE_LONDON
+    PUSH @REFUND_SCLEAR
+    %before_hardfork(@HARDFORK_LONDON)
+    // stack: is_pre_london, refund, refund_pre_london
+    %select_bool
+%endmacro
@@ -40,6 +40,16 @@ read_txn_from_memory:
     %jumpi(process_type_3_txn)
     // stack: rlp_segment, retdest
 
+    #[cfg(feature = op_deposit)]
+    {
+        DUP1
+        MLOAD_GENERAL
+        %eq_const(0x7e)
+        // stack: first_byte == 0x7e, rlp_segment, retdest
+        %jumpi(process_type_7e_txn)
+        // stack: rlp_segment, retdest
+    }
+
     // At this point, since it's not a type 1, 2 or 3 transaction,
     // it must be a legacy (aka type 0) transaction.
     %jump(process_type_0_txn)
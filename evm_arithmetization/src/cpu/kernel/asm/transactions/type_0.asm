@@ -5,6 +5,8 @@
 //     27 + y_parity
 // but as of EIP 155 it can also be encoded as
 //     35 + 2 * chain_id + y_parity
+// Any other value of v is invalid. Chain IDs may exceed 32 bits, but must fit
+// in 64 bits to match the chain ID of the block.
 //
 // If a chain_id is present in v, the signed data is
 //     keccak256(rlp([nonce, gas_price, gas_limit, to, value, data, chain_id, 0, 0]))
@@ -37,12 +39,13 @@ global process_type_0_txn:
     // stack: v > 28, v, rlp_addr, retdest
     %jumpi(process_v_new_style)
 
-    // We have an old style v, so y_parity = v - 27.
+    // We have an old style v, so y_parity = v - 27, which must be 0 or 1.
     // No chain ID is present, so we can leave TXN_FIELD_CHAIN_ID_PRESENT and
     // TXN_FIELD_CHAIN_ID with their default values of zero.
     // stack: v, rlp_addr, retdest
     %sub_const(27)
-    %stack (y_parity, rlp_addr) -> (y_parity, rlp_addr)
+    // stack: y_parity, rlp_addr, retdest
+    DUP1 %gt_const(1) %jumpi(invalid_txn_2)
     %mstore_txn_field(@TXN_FIELD_Y_PARITY)
 
     // stack: rlp_addr, retdest
@@ -50,6 +53,8 @@ global process_type_0_txn:
 
 process_v_new_style:
     // stack: v, rlp_addr, retdest
+    // Values of v between 29 and 34 encode neither style.
+    DUP1 %lt_const(35) %jumpi(invalid_txn_2)
     // We have a new style v, so chain_id_present = 1,
     // chain_id = (v - 35) / 2, and y_parity = (v - 35) % 2.
     %stack (v, rlp_addr) -> (1, v, rlp_addr)
@@ -9,6 +9,8 @@
 
 global process_type_2_txn:
     // stack: rlp_addr, retdest
+    // Dynamic fee transactions were introduced in London.
+    %before_hardfork(@HARDFORK_LONDON) %jumpi(invalid_txn_1)
     // Initial rlp address offset of 1 (skipping over the 0x02 byte)
     %add_const(1)
     // stack: rlp_addr, retdest
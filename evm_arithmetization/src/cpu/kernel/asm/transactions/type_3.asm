@@ -8,6 +8,8 @@
 
 global process_type_3_txn:
     // stack: rlp_addr, retdest
+    // Blob transactions were introduced in Cancun.
+    %before_hardfork(@HARDFORK_CANCUN) %jumpi(invalid_txn_1)
     // Initial rlp address offset of 1 (skipping over the 0x03 byte)
     %add_const(1)
     // stack: rlp_addr, retdest
@@ -155,24 +157,20 @@ store_origin:
     %jumpi(panic)
 
     // stack: address, retdest
-
-    // EIP-4844: Deduct blob_gas_fee from the sender and burn it
-    %compute_blob_gas_fee
-    DUP2
-    // stack: address, blob_gas_fee, address, retdest
-    %deduct_eth
-    // stack: deduct_eth_status, address, retdest
-    %jumpi(transfer_eth_failure)
-
-    // stack: address, retdest
+    // The blob gas fee is deducted from the sender with the gas fee, see `buy_gas`.
     %mstore_txn_field(@TXN_FIELD_ORIGIN)
     // stack: retdest
     %jump(process_normalized_txn)
 
-%macro compute_blob_gas_fee
+// The blob gas used by the transaction, i.e. `GAS_PER_BLOB` per blob.
+%macro blob_gas
     PUSH @GAS_PER_BLOB
     %get_blob_versioned_hashes_list_length
     MUL
+%endmacro
+
+%macro compute_blob_gas_fee
+    %blob_gas
     PROVER_INPUT(blobbasefee)
     MUL
 %endmacro
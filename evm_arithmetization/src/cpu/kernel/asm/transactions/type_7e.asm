@@ -0,0 +1,91 @@
+// OP Stack deposit transactions, of type 0x7E, have the format
+//     0x7E || rlp([source_hash, from, to, mint, value, gas_limit, is_system_tx, data])
+//
+// Deposits are derived from L1 and carry no signature: the sender is given by
+// the `from` field. They have no nonce either, and pay no fees since their gas
+// has been bought on L1. Before execution, `mint` wei are credited to the
+// sender, and this is not reverted if the transaction fails.
+
+#[cfg(feature = op_deposit)]
+{
+    global process_type_7e_txn:
+        // stack: rlp_addr, retdest
+        // Initial rlp address offset of 1 (skipping over the 0x7E byte)
+        %add_const(1)
+        // stack: rlp_addr, retdest
+        %decode_rlp_list_len
+        // We don't actually need the length.
+        %stack (rlp_addr, len) -> (rlp_addr)
+
+        // The source hash only identifies the deposit on L1.
+        %decode_rlp_scalar
+        %stack (rlp_addr, source_hash) -> (rlp_addr)
+        // stack: rlp_addr, retdest
+        %decode_and_store_from
+        %decode_and_store_to
+        %decode_and_store_mint
+        %decode_and_store_value
+        %decode_and_store_gas_limit
+        %decode_rlp_scalar
+        // stack: rlp_addr, is_system_tx, retdest
+        // System transactions have been disabled since the Regolith upgrade.
+        SWAP1 %jumpi(invalid_txn_1)
+        // stack: rlp_addr, retdest
+        %decode_and_store_data
+
+        // stack: rlp_addr, retdest
+        POP
+        // stack: retdest
+        %jump(process_normalized_txn)
+
+    // Mints the deposited ETH and sets a fee of zero, before processing the
+    // transaction as usual.
+    global process_deposit_txn:
+        // stack: retdest
+        %mload_txn_field(@TXN_FIELD_MINT)
+        %mload_txn_field(@TXN_FIELD_ORIGIN)
+        // stack: origin, mint, retdest
+        %add_eth
+        // stack: retdest
+
+        // Deposits have no nonce, so we use the sender's one.
+        %mload_txn_field(@TXN_FIELD_ORIGIN) %nonce
+        %mstore_txn_field(@TXN_FIELD_NONCE)
+        // stack: retdest
+        PUSH 0 %mstore_txn_field(@TXN_FIELD_MAX_FEE_PER_GAS)
+        PUSH 0 %mstore_txn_field(@TXN_FIELD_COMPUTED_FEE_PER_GAS)
+        PUSH 0 %mstore_txn_field(@TXN_FIELD_COMPUTED_PRIORITY_FEE_PER_GAS)
+        %jump(process_normalized_txn_after_fees)
+
+    // Returns whether the current transaction is a deposit.
+    %macro is_deposit_txn
+        PUSH @INITIAL_TXN_RLP_ADDR
+        MLOAD_GENERAL
+        %eq_const(0x7e)
+    %endmacro
+
+    // Decode the "from" field and store it as the origin of the transaction.
+    %macro decode_and_store_from
+        // stack: rlp_addr
+        %decode_rlp_string_len
+        // stack: rlp_addr, len
+        SWAP1 %eq_const(20) ISZERO %jumpi(invalid_txn_1) // Address is 160-bit
+        // stack: rlp_addr
+        %stack (rlp_addr) -> (rlp_addr, 20, %%after)
+        %jump(decode_int_given_len)
+    %%after:
+        // stack: rlp_addr, from
+        SWAP1
+        %mstore_txn_field(@TXN_FIELD_ORIGIN)
+        // stack: rlp_addr
+    %endmacro
+
+    // Decode the "mint" field and store it.
+    %macro decode_and_store_mint
+        // stack: rlp_addr
+        %decode_rlp_scalar
+        %stack (rlp_addr, mint) -> (mint, rlp_addr)
+        %mstore_txn_field(@TXN_FIELD_MINT)
+        // stack: rlp_addr
+    %endmacro
+}
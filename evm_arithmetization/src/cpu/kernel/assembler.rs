@@ -10,12 +10,11 @@ use serde::{Deserialize, Serialize};
 
 use super::ast::{BytesTarget, PushTarget};
 use crate::cpu::kernel::ast::Item::LocalLabelDeclaration;
-use crate::cpu::kernel::ast::{File, Item, StackReplacement};
+use crate::cpu::kernel::ast::{File, Item, ProverInputFn, StackReplacement};
 use crate::cpu::kernel::opcodes::{get_opcode, get_push_opcode};
 use crate::cpu::kernel::optimizer::optimize_asm;
 use crate::cpu::kernel::stack::stack_manipulation::expand_stack_manipulation;
 use crate::cpu::kernel::utils::u256_to_trimmed_be_bytes;
-use crate::generation::prover_input::ProverInputFn;
 
 /// The number of bytes to push when pushing an offset within the code (i.e.
 /// when assembling jumps). Ideally we would automatically use the minimal
@@ -76,6 +75,7 @@ impl Kernel {
 
     /// Get a string representation of the current offset for debugging
     /// purposes.
+    #[cfg(feature = "generation")]
     pub(crate) fn offset_name(&self, offset: usize) -> String {
         match self
             .ordered_labels
@@ -87,6 +87,21 @@ impl Kernel {
         }
     }
 
+    /// Returns the global label at or below `offset`, i.e. the label of the
+    /// routine `offset` belongs to.
+    #[cfg(feature = "generation")]
+    pub(crate) fn routine_label(&self, offset: usize) -> Option<&str> {
+        match self
+            .ordered_labels
+            .binary_search_by_key(&offset, |label| self.global_labels[label])
+        {
+            Ok(idx) => Some(&self.ordered_labels[idx]),
+            Err(0) => None,
+            Err(idx) => Some(&self.ordered_labels[idx - 1]),
+        }
+    }
+
+    #[cfg(feature = "generation")]
     pub(crate) fn offset_label(&self, offset: usize) -> Option<String> {
         self.global_labels
             .iter()
@@ -1,6 +1,5 @@
 use ethereum_types::U256;
-
-use crate::generation::prover_input::ProverInputFn;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub(crate) struct File {
@@ -84,3 +83,15 @@ pub(crate) enum BytesTarget {
     Literal(u8),
     Constant(String),
 }
+
+/// Prover input function represented as a scoped function name.
+/// Example: `PROVER_INPUT(ff::bn254_base::inverse)` is represented as
+/// `ProverInputFn([ff, bn254_base, inverse])`.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct ProverInputFn(pub(crate) Vec<String>);
+
+impl From<Vec<String>> for ProverInputFn {
+    fn from(v: Vec<String>) -> Self {
+        Self(v)
+    }
+}
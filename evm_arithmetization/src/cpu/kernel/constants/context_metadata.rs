@@ -36,10 +36,14 @@ pub(crate) enum ContextMetadata {
     /// The gas limit for this call (not the entire transaction).
     GasLimit,
     ContextCheckpointsLen,
+    /// The hash of the code under the account associated with this context,
+    /// as checked when loading it. Zero for initcode. Contexts sharing a code
+    /// hash can reuse each other's verified jump destinations.
+    CodeHash,
 }
 
 impl ContextMetadata {
-    pub(crate) const COUNT: usize = 14;
+    pub(crate) const COUNT: usize = 15;
 
     /// Unscales this virtual offset by their respective `Segment` value.
     pub(crate) const fn unscale(&self) -> usize {
@@ -62,6 +66,7 @@ impl ContextMetadata {
             Self::StackSize,
             Self::GasLimit,
             Self::ContextCheckpointsLen,
+            Self::CodeHash,
         ]
     }
 
@@ -82,6 +87,7 @@ impl ContextMetadata {
             ContextMetadata::StackSize => "CTX_METADATA_STACK_SIZE",
             ContextMetadata::GasLimit => "CTX_METADATA_GAS_LIMIT",
             ContextMetadata::ContextCheckpointsLen => "CTX_METADATA_CHECKPOINTS_LEN",
+            ContextMetadata::CodeHash => "CTX_METADATA_CODE_HASH",
         }
     }
 }
@@ -42,6 +42,7 @@ pub(crate) const STACK_LENGTH_INCREASING_OPCODES_USER: U256 = u256_from_set_inde
     0x5f..=0x8f, // PUSH*, DUP*
 ]);
 
+#[cfg(not(feature = "eof"))]
 pub(crate) const INVALID_OPCODES_USER: U256 = u256_from_set_index_ranges(&[
     0x0c..=0x0f,
     0x1e..=0x1f,
@@ -52,3 +53,40 @@ pub(crate) const INVALID_OPCODES_USER: U256 = u256_from_set_index_ranges(&[
     0xfb..=0xfc,
     0xfe..=0xfe,
 ]);
+
+/// With EOF, RJUMP, RJUMPI and RJUMPV (0xe0-0xe2) are syscalls, which fail in
+/// legacy code.
+#[cfg(feature = "eof")]
+pub(crate) const INVALID_OPCODES_USER: U256 = u256_from_set_index_ranges(&[
+    0x0c..=0x0f,
+    0x1e..=0x1f,
+    0x21..=0x2f,
+    0x4a..=0x4f,
+    0xa5..=0xdf,
+    0xe3..=0xef,
+    0xf6..=0xf9,
+    0xfb..=0xfc,
+    0xfe..=0xfe,
+]);
+
+/// Opcodes rejected by the EOF code validation: the undefined opcodes
+/// (EIP-3670), along with JUMP, JUMPI and PC, superseded by the relative jumps
+/// (EIP-4200).
+#[cfg(feature = "eof")]
+pub(crate) const EOF_INVALID_OPCODES: U256 = u256_from_set_index_ranges(&[
+    0x0c..=0x0f,
+    0x1e..=0x1f,
+    0x21..=0x2f,
+    0x4b..=0x4f,
+    0x56..=0x58,
+    0xa5..=0xdf,
+    0xe3..=0xef,
+    0xf6..=0xf9,
+    0xfb..=0xfc,
+]);
+
+/// Opcodes which may end an EOF code section: STOP, RJUMP, RETURN, REVERT,
+/// INVALID and SELFDESTRUCT.
+#[cfg(feature = "eof")]
+pub(crate) const EOF_TERMINATING_OPCODES: U256 =
+    u256_from_set_index_ranges(&[0x00..=0x00, 0xe0..=0xe0, 0xf3..=0xf3, 0xfd..=0xff]);
@@ -58,8 +58,15 @@ pub(crate) enum GlobalMetadata {
     BlockGasUsedAfter,
     /// Current block header hash
     BlockCurrentHash,
+    /// Parent block header hash, which must be the last of the previous block
+    /// hashes.
+    BlockParentHash,
+    /// Keccak hash of the previous 256 block hashes.
+    BlockAncestorsHash,
     /// EIP-4788: hash tree root of the beacon chain parent block.
     ParentBeaconBlockRoot,
+    /// EIP-4895: root of the withdrawals trie of the block header.
+    BlockWithdrawalsRoot,
 
     /// Gas to refund at the end of the transaction.
     RefundCounter,
@@ -124,10 +131,17 @@ pub(crate) enum GlobalMetadata {
     BlobVersionedHashesRlpLen,
     // Number of blob versioned hashes contained in the current type-3 transaction.
     BlobVersionedHashesLen,
+
+    /// The id of the hardfork whose rules the block follows.
+    BlockHardfork,
+    /// Whether `PUSH0` is a valid user instruction, i.e. whether the block
+    /// follows Shanghai or a later hardfork. It is read by the CPU when
+    /// executing `PUSH0` in user mode.
+    Push0Enabled,
 }
 
 impl GlobalMetadata {
-    pub(crate) const COUNT: usize = 59;
+    pub(crate) const COUNT: usize = 64;
 
     /// Unscales this virtual offset by their respective `Segment` value.
     pub(crate) const fn unscale(&self) -> usize {
@@ -195,6 +209,11 @@ impl GlobalMetadata {
             Self::BlobVersionedHashesRlpStart,
             Self::BlobVersionedHashesRlpLen,
             Self::BlobVersionedHashesLen,
+            Self::BlockHardfork,
+            Self::BlockParentHash,
+            Self::BlockAncestorsHash,
+            Self::BlockWithdrawalsRoot,
+            Self::Push0Enabled,
         ]
     }
 
@@ -228,6 +247,9 @@ impl GlobalMetadata {
             Self::BlockGasUsedBefore => "GLOBAL_METADATA_BLOCK_GAS_USED_BEFORE",
             Self::BlockGasUsedAfter => "GLOBAL_METADATA_BLOCK_GAS_USED_AFTER",
             Self::BlockCurrentHash => "GLOBAL_METADATA_BLOCK_CURRENT_HASH",
+            Self::BlockParentHash => "GLOBAL_METADATA_BLOCK_PARENT_HASH",
+            Self::BlockAncestorsHash => "GLOBAL_METADATA_BLOCK_ANCESTORS_HASH",
+            Self::BlockWithdrawalsRoot => "GLOBAL_METADATA_BLOCK_WITHDRAWALS_ROOT",
             Self::ParentBeaconBlockRoot => "GLOBAL_METADATA_PARENT_BEACON_BLOCK_ROOT",
             Self::RefundCounter => "GLOBAL_METADATA_REFUND_COUNTER",
             Self::AccessedAddressesLen => "GLOBAL_METADATA_ACCESSED_ADDRESSES_LEN",
@@ -266,6 +288,8 @@ impl GlobalMetadata {
             Self::BlobVersionedHashesRlpStart => "GLOBAL_METADATA_BLOB_VERSIONED_HASHES_RLP_START",
             Self::BlobVersionedHashesRlpLen => "GLOBAL_METADATA_BLOB_VERSIONED_HASHES_RLP_LEN",
             Self::BlobVersionedHashesLen => "GLOBAL_METADATA_BLOB_VERSIONED_HASHES_LEN",
+            Self::BlockHardfork => "GLOBAL_METADATA_BLOCK_HARDFORK",
+            Self::Push0Enabled => "GLOBAL_METADATA_PUSH0_ENABLED",
         }
     }
 }
@@ -2,14 +2,15 @@ use std::collections::HashMap;
 
 use ethereum_types::{H256, U256};
 use hex_literal::hex;
+use zk_evm_common::AccountRlp;
 
 use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
 use crate::cpu::kernel::constants::journal_entry::JournalEntry;
 use crate::cpu::kernel::constants::trie_type::PartialTrieType;
 use crate::cpu::kernel::constants::txn_fields::NormalizedTxnField;
-use crate::generation::mpt::AccountRlp;
 use crate::memory::segments::Segment;
+use crate::proof::Hardfork;
 
 pub(crate) mod context_metadata;
 mod exc_bitfields;
@@ -69,17 +70,26 @@ pub(crate) fn evm_constants() -> HashMap<String, U256> {
         cancun_constants::HISTORY_BUFFER_LENGTH.0.into(),
         cancun_constants::HISTORY_BUFFER_LENGTH.1.into(),
     );
+    for fork in Hardfork::ALL {
+        c.insert(
+            format!("HARDFORK_{}", fork.to_string().to_uppercase()),
+            fork.id().into(),
+        );
+    }
 
-    c.insert(
-        global_exit_root::GLOBAL_EXIT_ROOT_MANAGER_L2_STATE_KEY
-            .0
-            .into(),
-        U256::from_big_endian(&global_exit_root::GLOBAL_EXIT_ROOT_MANAGER_L2_STATE_KEY.1),
-    );
-    c.insert(
-        global_exit_root::GLOBAL_EXIT_ROOT_STORAGE_POS.0.into(),
-        U256::from(global_exit_root::GLOBAL_EXIT_ROOT_STORAGE_POS.1),
-    );
+    #[cfg(feature = "cdk_erigon")]
+    {
+        c.insert(
+            global_exit_root::GLOBAL_EXIT_ROOT_MANAGER_L2_STATE_KEY
+                .0
+                .into(),
+            U256::from_big_endian(&global_exit_root::GLOBAL_EXIT_ROOT_MANAGER_L2_STATE_KEY.1),
+        );
+        c.insert(
+            global_exit_root::GLOBAL_EXIT_ROOT_STORAGE_POS.0.into(),
+            U256::from(global_exit_root::GLOBAL_EXIT_ROOT_STORAGE_POS.1),
+        );
+    }
 
     for segment in Segment::all() {
         c.insert(segment.var_name().into(), (segment as usize).into());
@@ -110,6 +120,17 @@ pub(crate) fn evm_constants() -> HashMap<String, U256> {
         "STACK_LENGTH_INCREASING_OPCODES_USER".into(),
         exc_bitfields::STACK_LENGTH_INCREASING_OPCODES_USER,
     );
+    #[cfg(feature = "eof")]
+    {
+        c.insert(
+            "EOF_INVALID_OPCODES".into(),
+            exc_bitfields::EOF_INVALID_OPCODES,
+        );
+        c.insert(
+            "EOF_TERMINATING_OPCODES".into(),
+            exc_bitfields::EOF_TERMINATING_OPCODES,
+        );
+    }
     c
 }
 
@@ -166,7 +187,7 @@ const HASH_CONSTANTS: [(&str, [u8; 32]); 2] = [
     ),
 ];
 
-const EC_CONSTANTS: [(&str, [u8; 32]); 25] = [
+const EC_CONSTANTS: [(&str, [u8; 32]); 33] = [
     (
         "U256_MAX",
         hex!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
@@ -246,6 +267,14 @@ const EC_CONSTANTS: [(&str, [u8; 32]); 25] = [
         // Corresponds to `ceil(SECP_SCALAR / 2)`.
         hex!("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a1"),
     ),
+    (
+        "SECP_GENERATOR_X",
+        hex!("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+    ),
+    (
+        "SECP_GENERATOR_Y",
+        hex!("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"),
+    ),
     (
         "SECP_GLV_BETA",
         hex!("7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee"),
@@ -270,9 +299,34 @@ const EC_CONSTANTS: [(&str, [u8; 32]); 25] = [
         "SECP_GLV_B2",
         hex!("000000000000000000000000000000003086d221a7d46bcde86c90e49284eb15"),
     ),
+    (
+        "P256_BASE",
+        hex!("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff"),
+    ),
+    (
+        "P256_SCALAR",
+        hex!("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"),
+    ),
+    (
+        "P256_A",
+        // Corresponds to `-3` in the base field.
+        hex!("ffffffff00000001000000000000000000000000fffffffffffffffffffffffc"),
+    ),
+    (
+        "P256_B",
+        hex!("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"),
+    ),
+    (
+        "P256_GX",
+        hex!("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+    ),
+    (
+        "P256_GY",
+        hex!("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+    ),
 ];
 
-const GAS_CONSTANTS: [(&str, u32); 38] = [
+const GAS_CONSTANTS: [(&str, u32); 40] = [
     ("GAS_ZERO", 0),
     ("GAS_JUMPDEST", 1),
     ("GAS_BASE", 2),
@@ -311,11 +365,20 @@ const GAS_CONSTANTS: [(&str, u32); 38] = [
     ("GAS_BLOCKHASH", 20),
     ("GAS_HASH_OPCODE", 3),
     ("GAS_PER_BLOB", 131_072),
+    ("GAS_RJUMPI", 4),
+    ("GAS_RJUMPV", 4),
 ];
 
-const REFUND_CONSTANTS: [(&str, u16); 2] = [("REFUND_SCLEAR", 4_800), ("MAX_REFUND_QUOTIENT", 5)];
+const REFUND_CONSTANTS: [(&str, u16); 5] = [
+    ("REFUND_SCLEAR", 4_800),
+    ("MAX_REFUND_QUOTIENT", 5),
+    // Refunds before EIP-3529.
+    ("REFUND_SCLEAR_PRE_LONDON", 15_000),
+    ("REFUND_SELFDESTRUCT_PRE_LONDON", 24_000),
+    ("MAX_REFUND_QUOTIENT_PRE_LONDON", 2),
+];
 
-const PRECOMPILES: [(&str, u16); 10] = [
+const PRECOMPILES: [(&str, u16); 11] = [
     ("ECREC", 1),
     ("SHA256", 2),
     ("RIP160", 3),
@@ -326,9 +389,11 @@ const PRECOMPILES: [(&str, u16); 10] = [
     ("SNARKV", 8),
     ("BLAKE2_F", 9),
     ("KZG_PEVAL", 10),
+    // RIP-7212, only handled with the `p256_verify` feature.
+    ("P256_VERIFY", 0x100),
 ];
 
-const PRECOMPILES_GAS: [(&str, u16); 14] = [
+const PRECOMPILES_GAS: [(&str, u16); 15] = [
     ("ECREC_GAS", 3_000),
     ("SHA256_STATIC_GAS", 60),
     ("SHA256_DYNAMIC_GAS", 12),
@@ -343,6 +408,7 @@ const PRECOMPILES_GAS: [(&str, u16); 14] = [
     ("SNARKV_DYNAMIC_GAS", 34_000),
     ("BLAKE2_F__GAS", 1),
     ("KZG_PEVAL_GAS", 50_000),
+    ("P256_VERIFY_GAS", 3_450),
 ];
 
 const SNARKV_POINTERS: [(&str, u64); 2] = [("SNARKV_INP", 112), ("SNARKV_OUT", 100)];
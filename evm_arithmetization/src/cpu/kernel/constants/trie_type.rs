@@ -1,7 +1,10 @@
+#[cfg(feature = "generation")]
 use core::ops::Deref;
 
+#[cfg(feature = "generation")]
 use mpt_trie::partial_trie::HashedPartialTrie;
 
+#[cfg(feature = "generation")]
 use crate::Node;
 
 #[derive(Copy, Clone, Debug)]
@@ -16,6 +19,7 @@ pub(crate) enum PartialTrieType {
 impl PartialTrieType {
     pub(crate) const COUNT: usize = 5;
 
+    #[cfg(feature = "generation")]
     pub(crate) fn of(trie: &HashedPartialTrie) -> Self {
         match trie.deref() {
             Node::Empty => Self::Empty,
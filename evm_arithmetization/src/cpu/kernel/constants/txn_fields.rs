@@ -34,10 +34,15 @@ pub(crate) enum NormalizedTxnField {
     /// block's base fee.
     ComputedFeePerGas,
     ComputedPriorityFeePerGas,
+
+    /// The amount of ETH minted to the sender of an OP Stack deposit
+    /// transaction.
+    #[cfg(feature = "op_deposit")]
+    Mint,
 }
 
 impl NormalizedTxnField {
-    pub(crate) const COUNT: usize = 17;
+    pub(crate) const COUNT: usize = 17 + cfg!(feature = "op_deposit") as usize;
 
     /// Unscales this virtual offset by their respective `Segment` value.
     #[cfg(test)]
@@ -64,6 +69,8 @@ impl NormalizedTxnField {
             Self::Origin,
             Self::ComputedFeePerGas,
             Self::ComputedPriorityFeePerGas,
+            #[cfg(feature = "op_deposit")]
+            Self::Mint,
         ]
     }
 
@@ -89,6 +96,8 @@ impl NormalizedTxnField {
             NormalizedTxnField::ComputedPriorityFeePerGas => {
                 "TXN_FIELD_COMPUTED_PRIORITY_FEE_PER_GAS"
             }
+            #[cfg(feature = "op_deposit")]
+            NormalizedTxnField::Mint => "TXN_FIELD_MINT",
         }
     }
 }
@@ -0,0 +1,331 @@
+//! A debugger for the kernel, stepping through the execution of the
+//! [`Interpreter`] and stopping at breakpoints set on kernel labels or
+//! offsets.
+//!
+//! This allows to inspect the stack, memory and registers at any point of the
+//! witness generation, instead of adding log statements to the kernel.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use ethereum_types::U256;
+use plonky2::field::types::Field;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::generation::state::State;
+use crate::generation::GenerationInputs;
+use crate::memory::layout::{segment_metadata, MemoryLayout, SegmentLayout};
+use crate::memory::segments::Segment;
+use crate::witness::memory::MemoryAddress;
+use crate::witness::util::stack_peek;
+
+/// A location of the kernel code at which the execution stops.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// A global kernel label.
+    Label(String),
+    /// An offset in the kernel code.
+    Pc(usize),
+}
+
+/// The reason why the debugger gave back control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugEvent {
+    /// A single instruction was executed.
+    Stepped,
+    /// The execution reached the breakpoint at this kernel offset.
+    Breakpoint(usize),
+    /// The execution halted.
+    Halted,
+}
+
+/// A snapshot of the current execution context.
+#[derive(Clone, Debug)]
+pub struct ContextDump {
+    pub context: usize,
+    pub is_kernel: bool,
+    pub program_counter: usize,
+    /// The program counter, along with the kernel label it is at or below.
+    pub location: String,
+    pub gas_used: u64,
+    pub clock: usize,
+    /// The stack, from the top.
+    pub stack: Vec<U256>,
+    /// The non-empty memory segments of the context, in address order,
+    /// except its code and stack.
+    pub segments: Vec<SegmentDump>,
+}
+
+/// The content of a memory segment of a context, along with its layout.
+#[derive(Clone, Debug)]
+pub struct SegmentDump {
+    pub layout: SegmentLayout,
+    /// The values of the global metadata fields describing the segment, by
+    /// kernel name.
+    pub metadata: Vec<(&'static str, U256)>,
+    pub content: Vec<U256>,
+}
+
+impl fmt::Display for SegmentDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layout = &self.layout;
+        write!(
+            f,
+            "{} at {:#x} ({} cells of {} bits{})",
+            layout.name,
+            layout.address,
+            self.content.len(),
+            layout.bit_range,
+            if layout.preinitialized {
+                ", preinitialized"
+            } else {
+                ""
+            }
+        )?;
+        for (name, value) in &self.metadata {
+            write!(f, ", {name} = {value}")?;
+        }
+        writeln!(f, ":")?;
+        for (offset, value) in self.content.iter().enumerate() {
+            if !value.is_zero() {
+                writeln!(f, "  {offset:>6}: {value:#x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ContextDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "context {} ({} mode) at {}, clock {}, gas used {}",
+            self.context,
+            if self.is_kernel { "kernel" } else { "user" },
+            self.location,
+            self.clock,
+            self.gas_used
+        )?;
+        writeln!(f, "stack ({} items):", self.stack.len())?;
+        for (i, item) in self.stack.iter().enumerate() {
+            writeln!(f, "  {i:>4}: {item:#x}")?;
+        }
+        for segment in &self.segments {
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A debugger of the kernel execution.
+pub struct Debugger<F: Field> {
+    interpreter: Interpreter<F>,
+    /// The kernel offsets of the breakpoints.
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<F: Field> Debugger<F> {
+    /// Returns a debugger of the execution of the block or batch of `inputs`,
+    /// stopped at the start of the kernel.
+    pub fn new(inputs: &GenerationInputs) -> Self {
+        Self::from_interpreter(Interpreter::new_with_generation_inputs(
+            KERNEL.global_labels["init"],
+            vec![],
+            inputs,
+            None,
+        ))
+    }
+
+    /// Returns a debugger of the kernel function at `label`, called with
+    /// `initial_stack`, given from the bottom.
+    pub fn new_at_label(label: &str, initial_stack: Vec<U256>) -> Result<Self> {
+        let offset = resolve_label(label)?;
+        Ok(Self::from_interpreter(Interpreter::new(
+            offset,
+            initial_stack,
+            None,
+        )))
+    }
+
+    const fn from_interpreter(interpreter: Interpreter<F>) -> Self {
+        Self {
+            interpreter,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Sets a breakpoint, and returns the kernel offset it stops at.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> Result<usize> {
+        let offset = match breakpoint {
+            Breakpoint::Label(label) => resolve_label(&label)?,
+            Breakpoint::Pc(offset) if offset < KERNEL.code.len() => offset,
+            Breakpoint::Pc(offset) => bail!("offset {offset} is outside of the kernel"),
+        };
+        self.breakpoints.insert(offset);
+        Ok(offset)
+    }
+
+    /// Removes the breakpoint at a kernel offset, returning whether it was
+    /// set.
+    pub fn remove_breakpoint(&mut self, offset: usize) -> bool {
+        self.breakpoints.remove(&offset)
+    }
+
+    /// Returns the kernel offsets of the breakpoints.
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Returns `true` if the execution reached one of the halting offsets of
+    /// the interpreter.
+    pub fn is_halted(&self) -> bool {
+        let registers = self.interpreter.generation_state.registers;
+        registers.is_kernel
+            && self
+                .interpreter
+                .halt_offsets
+                .contains(&registers.program_counter)
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<DebugEvent> {
+        if self.is_halted() {
+            return Ok(DebugEvent::Halted);
+        }
+        self.interpreter.transition()?;
+
+        let registers = self.interpreter.generation_state.registers;
+        Ok(if self.is_halted() {
+            DebugEvent::Halted
+        } else if registers.is_kernel && self.breakpoints.contains(&registers.program_counter) {
+            DebugEvent::Breakpoint(registers.program_counter)
+        } else {
+            DebugEvent::Stepped
+        })
+    }
+
+    /// Executes instructions until the next breakpoint, or until the
+    /// execution halts.
+    pub fn resume(&mut self) -> Result<DebugEvent> {
+        loop {
+            match self.step()? {
+                DebugEvent::Stepped => continue,
+                event => return Ok(event),
+            }
+        }
+    }
+
+    /// Returns the current program counter.
+    pub const fn program_counter(&self) -> usize {
+        self.interpreter.generation_state.registers.program_counter
+    }
+
+    /// Returns the current context.
+    pub const fn context(&self) -> usize {
+        self.interpreter.context()
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub const fn clock(&self) -> usize {
+        self.interpreter.clock
+    }
+
+    /// Returns the current location in the kernel code, as its offset along
+    /// with the label it is at or below.
+    pub fn location(&self) -> String {
+        if self.interpreter.is_kernel() {
+            KERNEL.offset_name(self.program_counter())
+        } else {
+            format!("{} (user code)", self.program_counter())
+        }
+    }
+
+    /// Returns the whole stack, from the top.
+    pub fn stack(&self) -> Vec<U256> {
+        let state = &self.interpreter.generation_state;
+        (0..state.registers.stack_len)
+            .map(|i| stack_peek(state, i).expect("the item is within the stack"))
+            .collect()
+    }
+
+    /// Reads the memory of `context` in the segment of the given kernel name,
+    /// e.g. `SEGMENT_MAIN_MEMORY`.
+    pub fn memory(&self, context: usize, segment: &str, offset: usize) -> Result<U256> {
+        let segment = resolve_segment(segment)?;
+        Ok(self
+            .interpreter
+            .generation_state
+            .memory
+            .get_with_init(MemoryAddress::new(context, segment, offset)))
+    }
+
+    /// Returns the content of the segment of the given kernel name in
+    /// `context`.
+    pub fn segment(&self, context: usize, segment: &str) -> Result<Vec<U256>> {
+        Ok(self.segment_content(context, resolve_segment(segment)?))
+    }
+
+    fn segment_content(&self, context: usize, segment: Segment) -> Vec<U256> {
+        let memory = &self.interpreter.generation_state.memory;
+        if context == 0 && memory.is_preinitialized_segment(segment.unscale()) {
+            return memory
+                .get_preinit_memory(segment)
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect();
+        }
+        memory
+            .contexts
+            .get(context)
+            .map(|ctx| ctx.segments[segment.unscale()].content())
+            .unwrap_or_default()
+    }
+
+    /// Returns a snapshot of the current context.
+    pub fn dump_context(&self) -> ContextDump {
+        let registers = self.interpreter.get_registers();
+        let memory = &self.interpreter.generation_state.memory;
+        let segments = Segment::all()
+            .into_iter()
+            .zip(MemoryLayout::current().segments)
+            .filter(|&(segment, _)| segment != Segment::Code && segment != Segment::Stack)
+            .map(|(segment, layout)| SegmentDump {
+                layout,
+                metadata: segment_metadata(segment)
+                    .iter()
+                    .map(|&field| (field.var_name(), memory.read_global_metadata(field)))
+                    .collect(),
+                content: self.segment_content(registers.context, segment),
+            })
+            .filter(|segment| !segment.content.is_empty())
+            .collect();
+
+        ContextDump {
+            context: registers.context,
+            is_kernel: registers.is_kernel,
+            program_counter: registers.program_counter,
+            location: self.location(),
+            gas_used: registers.gas_used,
+            clock: self.clock(),
+            stack: self.stack(),
+            segments,
+        }
+    }
+}
+
+fn resolve_label(label: &str) -> Result<usize> {
+    KERNEL
+        .global_labels
+        .get(label)
+        .copied()
+        .ok_or_else(|| anyhow!("unknown kernel label {label}"))
+}
+
+fn resolve_segment(name: &str) -> Result<Segment> {
+    Segment::all()
+        .into_iter()
+        .find(|segment| segment.var_name() == name)
+        .ok_or_else(|| anyhow!("unknown segment {name}"))
+}
@@ -8,9 +8,10 @@
 use std::collections::{BTreeSet, HashMap};
 
 use anyhow::anyhow;
-use ethereum_types::{BigEndianHash, U256};
+use ethereum_types::{BigEndianHash, H256, U256};
 use log::Level;
 use mpt_trie::partial_trie::PartialTrie;
+use once_cell::sync::Lazy;
 use plonky2::field::types::Field;
 use serde::{Deserialize, Serialize};
 
@@ -18,17 +19,22 @@ use crate::byte_packing::byte_packing_stark::BytePackingOp;
 use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::membus::NUM_CHANNELS;
+use crate::ec::ec_stark::EcOp;
 use crate::generation::debug_inputs;
-use crate::generation::mpt::{load_linked_lists_and_txn_and_receipt_mpts, TrieRootPtrs};
+use crate::generation::mpt::{load_preinitialized_segments, TrieRootPtrs};
 use crate::generation::rlp::all_rlp_prover_inputs_reversed;
 use crate::generation::state::{
     all_ger_prover_inputs_reversed, all_withdrawals_prover_inputs_reversed, GenerationState,
     GenerationStateCheckpoint,
 };
-use crate::generation::{state::State, GenerationInputs};
-use crate::keccak_sponge::columns::KECCAK_WIDTH_BYTES;
+use crate::generation::{cpu_cycle_limit, state::State, GenerationInputs, NUM_EXTRA_CYCLES_AFTER};
+use crate::keccak::keccak_stark::NUM_ROUNDS;
+use crate::keccak_sponge::columns::{KECCAK_RATE_BYTES, KECCAK_WIDTH_BYTES};
 use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeOp;
 use crate::memory::segments::Segment;
+use crate::proof::Hardfork;
+use crate::prover::{CycleProfile, FinalTriesStats};
 use crate::util::h2u;
 use crate::witness::errors::ProgramError;
 use crate::witness::memory::{
@@ -64,46 +70,87 @@ pub(crate) struct Interpreter<F: Field> {
     pub(crate) clock: usize,
     /// Log of the maximal number of CPU cycles in one segment execution.
     max_cpu_len_log: Option<usize>,
+    /// The projected heights of the tables of the current segment.
+    projected_heights: ProjectedHeights,
+    /// The projected heights before the current operation, restored if it
+    /// fails.
+    projected_heights_checkpoint: ProjectedHeights,
+    /// The clock at which the last execution stopped, before the final
+    /// cycles.
+    pub(crate) segment_end_clock: usize,
+    /// Profile of the computation of the final state trie.
+    pub(crate) final_tries_stats: FinalTriesStats,
+    /// `true` if we are currently computing the final state trie.
+    in_final_tries: bool,
+    /// Profile of the executed instructions by kernel routine, if enabled.
+    pub(crate) cycle_profile: Option<CycleProfile>,
 }
 
+/// The projected heights of the tables which may outgrow the CPU table in a
+/// segment, such as for memory-heavy or Keccak-heavy payloads.
+#[derive(Clone, Copy, Debug, Default)]
+struct ProjectedHeights {
+    memory: usize,
+    keccak: usize,
+    keccak_sponge: usize,
+}
+
+impl ProjectedHeights {
+    /// Returns whether the dominant table reaches `2^max_len_log` rows,
+    /// accounting for the memory operations of the final cycles.
+    const fn reaches(&self, max_len_log: usize) -> bool {
+        let max_len = 1 << max_len_log;
+        self.memory + NUM_EXTRA_CYCLES_AFTER * NUM_CHANNELS >= max_len
+            || self.keccak >= max_len
+            || self.keccak_sponge >= max_len
+    }
+}
+
+/// The offsets of the kernel labels delimiting the computation of the final
+/// state trie, and of those reached when an unchanged slot or account is
+/// skipped.
+static FINAL_TRIES_LABELS: Lazy<[usize; 4]> = Lazy::new(|| {
+    [
+        "check_final_state_trie",
+        "hash_final_state_trie",
+        "skip_slot",
+        "skip_account",
+    ]
+    .map(|label| KERNEL.global_labels[label])
+});
+
 /// Simulates the CPU execution from `state` until the program counter reaches
 /// `final_label` in the current context.
 pub(crate) fn simulate_cpu_and_get_user_jumps<F: Field>(
     final_label: &str,
     state: &GenerationState<F>,
-) -> Option<HashMap<usize, Vec<usize>>> {
-    match state.jumpdest_table {
-        Some(_) => None,
-        None => {
-            let halt_pc = KERNEL.global_labels[final_label];
-            let initial_context = state.registers.context;
-            let mut interpreter = Interpreter::new_with_state_and_halt_condition(
-                state,
-                halt_pc,
-                initial_context,
-                None,
-            );
+) -> HashMap<usize, Vec<usize>> {
+    let halt_pc = KERNEL.global_labels[final_label];
+    let initial_context = state.registers.context;
+    let mut interpreter =
+        Interpreter::new_with_state_and_halt_condition(state, halt_pc, initial_context, None);
 
-            log::debug!("Simulating CPU for jumpdest analysis.");
+    log::debug!("Simulating CPU for jumpdest analysis.");
 
-            let _ = interpreter.run();
+    let _ = interpreter.run();
 
-            log::trace!("jumpdest table = {:?}", interpreter.jumpdest_table);
+    log::trace!("jumpdest table = {:?}", interpreter.jumpdest_table);
 
-            let clock = interpreter.get_clock();
+    let clock = interpreter.get_clock();
 
-            interpreter
-                .generation_state
-                .set_jumpdest_analysis_inputs(interpreter.jumpdest_table);
+    interpreter
+        .generation_state
+        .set_jumpdest_analysis_inputs(interpreter.jumpdest_table);
 
-            log::debug!(
-                "Simulated CPU for jumpdest analysis halted after {:?} cycles.",
-                clock
-            );
+    log::debug!(
+        "Simulated CPU for jumpdest analysis halted after {:?} cycles.",
+        clock
+    );
 
-            interpreter.generation_state.jumpdest_table
-        }
-    }
+    interpreter
+        .generation_state
+        .jumpdest_table
+        .unwrap_or_default()
 }
 
 /// State data required to initialize the state passed to the prover.
@@ -115,6 +162,8 @@ pub(crate) struct ExtraSegmentData {
     pub(crate) ger_prover_inputs: Vec<U256>,
     pub(crate) trie_root_ptrs: TrieRootPtrs,
     pub(crate) jumpdest_table: Option<HashMap<usize, Vec<usize>>>,
+    pub(crate) jumpdest_table_simulated: bool,
+    pub(crate) jumpdest_contexts: HashMap<H256, BTreeSet<usize>>,
     pub(crate) next_txn_index: usize,
 }
 
@@ -126,6 +175,7 @@ pub(crate) fn set_registers_and_run<F: Field>(
     interpreter.generation_state.registers.program_counter = KERNEL.global_labels["init"];
     interpreter.generation_state.registers.is_kernel = true;
     interpreter.clock = 0;
+    interpreter.projected_heights = ProjectedHeights::default();
 
     // Write initial registers.
     [
@@ -181,6 +231,12 @@ impl<F: Field> Interpreter<F> {
             is_jumpdest_analysis: false,
             clock: 0,
             max_cpu_len_log,
+            projected_heights: ProjectedHeights::default(),
+            projected_heights_checkpoint: ProjectedHeights::default(),
+            segment_end_clock: 0,
+            final_tries_stats: FinalTriesStats::default(),
+            in_final_tries: false,
+            cycle_profile: None,
         };
         interpreter.generation_state.registers.program_counter = initial_offset;
         let initial_stack_len = initial_stack.len();
@@ -194,6 +250,17 @@ impl<F: Field> Interpreter<F> {
         }
 
         interpreter.initialize_rlp_segment();
+        // Unless a test sets another one, blocks follow the default hardfork.
+        interpreter.set_global_metadata_multi_fields(&[
+            (
+                GlobalMetadata::BlockHardfork,
+                Hardfork::default().id().into(),
+            ),
+            (
+                GlobalMetadata::Push0Enabled,
+                Hardfork::default().enables_push0().into(),
+            ),
+        ]);
         interpreter
     }
 
@@ -212,6 +279,12 @@ impl<F: Field> Interpreter<F> {
             is_jumpdest_analysis: true,
             clock: 0,
             max_cpu_len_log,
+            projected_heights: ProjectedHeights::default(),
+            projected_heights_checkpoint: ProjectedHeights::default(),
+            segment_end_clock: 0,
+            final_tries_stats: FinalTriesStats::default(),
+            in_final_tries: false,
+            cycle_profile: None,
         }
     }
 
@@ -230,32 +303,22 @@ impl<F: Field> Interpreter<F> {
         // Set state's inputs. We trim unnecessary components.
         self.generation_state.inputs = inputs.trim();
 
-        // Initialize the MPT's pointers.
-        let (trie_root_ptrs, state_leaves, storage_leaves, trie_data) =
-            load_linked_lists_and_txn_and_receipt_mpts(&inputs.tries)
+        // Initialize the MPT's pointers and the preinitialized segments.
+        let (trie_root_ptrs, segments) =
+            load_preinitialized_segments(&self.generation_state.inputs.trimmed_tries)
                 .expect("Invalid MPT data for preinitialization");
 
         let trie_roots_after = &inputs.trie_roots_after;
         self.generation_state.trie_root_ptrs = trie_root_ptrs;
 
-        // Initialize the `TrieData` segment.
-        let preinit_trie_data_segment = MemorySegmentState { content: trie_data };
-        let preinit_accounts_ll_segment = MemorySegmentState {
-            content: state_leaves,
-        };
-        let preinit_storage_ll_segment = MemorySegmentState {
-            content: storage_leaves,
-        };
-        self.insert_preinitialized_segment(Segment::TrieData, preinit_trie_data_segment);
-        self.insert_preinitialized_segment(
-            Segment::AccountsLinkedList,
-            preinit_accounts_ll_segment,
-        );
-        self.insert_preinitialized_segment(Segment::StorageLinkedList, preinit_storage_ll_segment);
+        for (segment, values) in segments {
+            self.insert_preinitialized_segment(segment, values);
+        }
 
         // Update the RLP and withdrawal prover inputs.
         let rlp_prover_inputs = all_rlp_prover_inputs_reversed(&inputs.signed_txns);
-        let withdrawal_prover_inputs = all_withdrawals_prover_inputs_reversed(&inputs.withdrawals);
+        let withdrawal_prover_inputs =
+            all_withdrawals_prover_inputs_reversed(&inputs.withdrawals, &inputs.block_rewards);
         let ger_prover_inputs = all_ger_prover_inputs_reversed(&inputs.global_exit_roots);
         self.generation_state.rlp_prover_inputs = rlp_prover_inputs;
         self.generation_state.withdrawal_prover_inputs = withdrawal_prover_inputs;
@@ -277,11 +340,23 @@ impl<F: Field> Interpreter<F> {
             ),
             (GlobalMetadata::BlockGasLimit, metadata.block_gaslimit),
             (GlobalMetadata::BlockChainId, metadata.block_chain_id),
+            (
+                GlobalMetadata::BlockHardfork,
+                metadata.block_hardfork.id().into(),
+            ),
             (GlobalMetadata::BlockBaseFee, metadata.block_base_fee),
             (
                 GlobalMetadata::BlockCurrentHash,
                 h2u(inputs.block_hashes.cur_hash),
             ),
+            (
+                GlobalMetadata::BlockParentHash,
+                h2u(inputs.block_hashes.parent_hash()),
+            ),
+            (
+                GlobalMetadata::BlockAncestorsHash,
+                h2u(inputs.block_hashes.ancestors_hash()),
+            ),
             (GlobalMetadata::BlockGasUsed, metadata.block_gas_used),
             (
                 GlobalMetadata::BlockBlobGasUsed,
@@ -295,6 +370,10 @@ impl<F: Field> Interpreter<F> {
                 GlobalMetadata::ParentBeaconBlockRoot,
                 h2u(metadata.parent_beacon_block_root),
             ),
+            (
+                GlobalMetadata::BlockWithdrawalsRoot,
+                h2u(metadata.withdrawals_root),
+            ),
             (GlobalMetadata::BlockGasUsedBefore, inputs.gas_used_before),
             (GlobalMetadata::BlockGasUsedAfter, inputs.gas_used_after),
             (GlobalMetadata::TxnNumberBefore, inputs.txn_number_before),
@@ -404,7 +483,8 @@ impl<F: Field> Interpreter<F> {
     }
 
     pub(crate) fn run(&mut self) -> Result<(RegistersState, Option<MemoryState>), anyhow::Error> {
-        let (final_registers, final_mem) = self.run_cpu(self.max_cpu_len_log)?;
+        let (final_registers, final_mem) =
+            self.run_cpu(self.max_cpu_len_log.map(cpu_cycle_limit))?;
 
         #[cfg(debug_assertions)]
         {
@@ -510,6 +590,7 @@ impl<F: Field> State<F> for Interpreter<F> {
     /// reset memory operations to the empty vector.
     fn checkpoint(&mut self) -> GenerationStateCheckpoint {
         self.generation_state.traces.memory_ops = vec![];
+        self.projected_heights_checkpoint = self.projected_heights;
         GenerationStateCheckpoint {
             registers: self.generation_state.registers,
             traces: self.generation_state.traces.checkpoint(),
@@ -561,14 +642,23 @@ impl<F: Field> State<F> for Interpreter<F> {
 
     fn push_byte_packing(&mut self, _op: BytePackingOp) {}
 
-    fn push_keccak(&mut self, _input: [u64; keccak::keccak_stark::NUM_INPUTS], _clock: usize) {}
+    fn push_keccak(&mut self, _input: [u64; keccak::keccak_stark::NUM_INPUTS], _clock: usize) {
+        self.projected_heights.keccak += NUM_ROUNDS;
+    }
+
+    fn push_keccak_bytes(&mut self, _input: [u8; KECCAK_WIDTH_BYTES], _clock: usize) {
+        self.projected_heights.keccak += NUM_ROUNDS;
+    }
 
-    fn push_keccak_bytes(&mut self, _input: [u8; KECCAK_WIDTH_BYTES], _clock: usize) {}
+    fn push_keccak_sponge(&mut self, op: KeccakSpongeOp) {
+        self.projected_heights.keccak_sponge += op.input.len() / KECCAK_RATE_BYTES + 1;
+    }
 
-    fn push_keccak_sponge(&mut self, _op: KeccakSpongeOp) {}
+    fn push_ec(&mut self, _op: EcOp) {}
 
     fn rollback(&mut self, checkpoint: GenerationStateCheckpoint) {
         self.clock = checkpoint.clock;
+        self.projected_heights = self.projected_heights_checkpoint;
         self.generation_state.rollback(checkpoint)
     }
 
@@ -587,6 +677,7 @@ impl<F: Field> State<F> for Interpreter<F> {
     }
 
     fn apply_ops(&mut self, _checkpoint: GenerationStateCheckpoint) {
+        self.projected_heights.memory += self.generation_state.traces.memory_ops.len();
         self.apply_memops()
             .expect("We should not have nonzero initial values in non-preinitialized segments");
     }
@@ -598,6 +689,19 @@ impl<F: Field> State<F> for Interpreter<F> {
         stack
     }
 
+    /// Ends the segment when the CPU trace reaches its maximal length, or when
+    /// another table is projected to outgrow it, so that the segments are
+    /// balanced by their dominant table.
+    fn at_end_segment(&self, opt_cycle_limit: Option<usize>) -> bool {
+        match (opt_cycle_limit, self.max_cpu_len_log) {
+            (Some(cycle_limit), Some(max_len_log)) => {
+                self.clock == cycle_limit || self.projected_heights.reaches(max_len_log)
+            }
+            (Some(cycle_limit), None) => self.clock == cycle_limit,
+            (None, _) => false,
+        }
+    }
+
     fn get_halt_offsets(&self) -> Vec<usize> {
         self.halt_offsets.clone()
     }
@@ -629,6 +733,7 @@ impl<F: Field> State<F> for Interpreter<F> {
     }
 
     fn update_interpreter_final_registers(&mut self, final_registers: RegistersState) {
+        self.segment_end_clock = self.clock;
         {
             let registers_after = [
                 final_registers.program_counter.into(),
@@ -699,6 +804,25 @@ impl<F: Field> State<F> for Interpreter<F> {
 }
 
 impl<F: Field> Transition<F> for Interpreter<F> {
+    /// Counts the cycles spent computing the final state trie, and the slots
+    /// and accounts left untouched in the initial tries.
+    fn profile_final_tries(&mut self) {
+        let [start, end, skip_slot, skip_account] = *FINAL_TRIES_LABELS;
+        let pc = self.generation_state.registers.program_counter;
+        if pc == start {
+            self.in_final_tries = true;
+        } else if pc == end {
+            self.in_final_tries = false;
+        } else if pc == skip_slot {
+            self.final_tries_stats.skipped_slots += 1;
+        } else if pc == skip_account {
+            self.final_tries_stats.skipped_accounts += 1;
+        }
+        if self.in_final_tries {
+            self.final_tries_stats.cycles += 1;
+        }
+    }
+
     fn generate_jumpdest_analysis(&mut self, dst: usize) -> bool {
         if self.is_jumpdest_analysis && !self.generation_state.registers.is_kernel {
             self.add_jumpdest_offset(dst);
@@ -709,6 +833,14 @@ impl<F: Field> Transition<F> for Interpreter<F> {
     }
 
     fn skip_if_necessary(&mut self, op: Operation) -> Result<Operation, ProgramError> {
+        if self.is_kernel() {
+            self.profile_final_tries();
+        }
+        if let Some(profile) = self.cycle_profile.as_mut() {
+            let registers = self.generation_state.registers;
+            profile.record(registers.is_kernel, registers.program_counter);
+        }
+
         if self.is_kernel()
             && self.is_jumpdest_analysis
             && self.generation_state.registers.program_counter
@@ -775,6 +907,12 @@ fn get_mnemonic(opcode: u8) -> &'static str {
         0x1d => "SAR",
         0x20 => "KECCAK256",
         0x21 => "KECCAK_GENERAL",
+        0x28 => "SECP_ADD",
+        0x29 => "SECP_DOUBLE",
+        0x2a => "BN_G1_ADD",
+        0x2b => "BN_G1_DOUBLE",
+        0x2c => "SECP_MUL",
+        0x2e => "BN_G1_MUL",
         0x30 => "ADDRESS",
         0x31 => "BALANCE",
         0x32 => "ORIGIN",
@@ -916,6 +1054,9 @@ fn get_mnemonic(opcode: u8) -> &'static str {
         0xdd => "MSTORE_32BYTES_30",
         0xde => "MSTORE_32BYTES_31",
         0xdf => "MSTORE_32BYTES_32",
+        0xe0 => "RJUMP",
+        0xe1 => "RJUMPI",
+        0xe2 => "RJUMPV",
         0xee => "PROVER_INPUT",
         0xf0 => "CREATE",
         0xf1 => "CALL",
@@ -943,10 +1084,13 @@ mod tests {
     use plonky2::field::goldilocks_field::GoldilocksField as F;
 
     use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
-    use crate::cpu::kernel::interpreter::Interpreter;
+    use crate::cpu::kernel::interpreter::{Interpreter, ProjectedHeights};
+    use crate::cpu::membus::NUM_CHANNELS;
+    use crate::generation::state::State;
+    use crate::generation::{cpu_cycle_limit, NUM_EXTRA_CYCLES_AFTER};
     use crate::memory::segments::Segment;
     use crate::witness::memory::MemoryAddress;
-    use crate::witness::operation::CONTEXT_SCALING_FACTOR;
+    use crate::witness::memory::CONTEXT_SCALING_FACTOR;
 
     #[test]
     fn test_run_with_memory() -> anyhow::Result<()> {
@@ -1014,4 +1158,69 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_projected_heights() {
+        let max_len_log = 10;
+        let max_len = 1 << max_len_log;
+
+        assert!(!ProjectedHeights::default().reaches(max_len_log));
+        for heights in [
+            ProjectedHeights {
+                keccak: max_len,
+                ..Default::default()
+            },
+            ProjectedHeights {
+                keccak_sponge: max_len,
+                ..Default::default()
+            },
+            // The memory table leaves room for the operations of the final cycles.
+            ProjectedHeights {
+                memory: max_len - NUM_EXTRA_CYCLES_AFTER * NUM_CHANNELS,
+                ..Default::default()
+            },
+        ] {
+            assert!(heights.reaches(max_len_log));
+        }
+        for heights in [
+            ProjectedHeights {
+                keccak: max_len - 1,
+                ..Default::default()
+            },
+            ProjectedHeights {
+                keccak_sponge: max_len - 1,
+                ..Default::default()
+            },
+            ProjectedHeights {
+                memory: max_len - NUM_EXTRA_CYCLES_AFTER * NUM_CHANNELS - 1,
+                ..Default::default()
+            },
+        ] {
+            assert!(!heights.reaches(max_len_log));
+        }
+    }
+
+    #[test]
+    fn test_segment_ends_at_dominant_table() {
+        let max_len_log = 10;
+        let cycle_limit = Some(cpu_cycle_limit(max_len_log));
+
+        let mut interpreter: Interpreter<F> = Interpreter::new(0, vec![], Some(max_len_log));
+        assert!(!interpreter.at_end_segment(cycle_limit));
+        assert!(!interpreter.at_end_segment(None));
+
+        // A Keccak-heavy segment ends long before the CPU table is full.
+        let checkpoint = interpreter.checkpoint();
+        interpreter.projected_heights.keccak = 1 << max_len_log;
+        assert!(interpreter.at_end_segment(cycle_limit));
+
+        // Rolling back the segment restores the projected heights.
+        interpreter.rollback(checkpoint);
+        assert!(!interpreter.at_end_segment(cycle_limit));
+
+        // Without a maximal length, only the CPU cycles end a segment.
+        let mut interpreter: Interpreter<F> = Interpreter::new(0, vec![], None);
+        interpreter.projected_heights.keccak = 1 << max_len_log;
+        assert!(!interpreter.at_end_segment(cycle_limit));
+    }
 }
@@ -1,9 +1,14 @@
+#[cfg(any(feature = "generation", test))]
 use tiny_keccak::keccakf;
 
-use crate::keccak_sponge::columns::{KECCAK_WIDTH_BYTES, KECCAK_WIDTH_U32S};
+#[cfg(any(feature = "generation", test))]
+use crate::keccak_sponge::columns::KECCAK_WIDTH_BYTES;
+#[cfg(any(feature = "generation", test))]
+use crate::keccak_sponge::columns::KECCAK_WIDTH_U32S;
 
 /// Like tiny-keccak's `keccakf`, but deals with `u32` limbs instead of `u64`
 /// limbs.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn keccakf_u32s(state_u32s: &mut [u32; KECCAK_WIDTH_U32S]) {
     let mut state_u64s: [u64; 25] = core::array::from_fn(|i| {
         let lo = state_u32s[i * 2] as u64;
@@ -19,6 +24,7 @@ pub(crate) fn keccakf_u32s(state_u32s: &mut [u32; KECCAK_WIDTH_U32S]) {
 }
 
 /// Like tiny-keccak's `keccakf`, but deals with bytes instead of `u64` limbs.
+#[cfg(any(feature = "generation", test))]
 pub(crate) fn keccakf_u8s(state_u8s: &mut [u8; KECCAK_WIDTH_BYTES]) {
     let mut state_u64s: [u64; 25] =
         core::array::from_fn(|i| u64::from_le_bytes(state_u8s[i * 8..][..8].try_into().unwrap()));
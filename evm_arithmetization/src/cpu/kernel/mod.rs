@@ -1,8 +1,10 @@
 pub mod aggregator;
 pub mod assembler;
-mod ast;
+pub(crate) mod ast;
 pub(crate) mod constants;
 mod cost_estimator;
+#[cfg(feature = "generation")]
+pub mod debugger;
 pub(crate) mod keccak_util;
 pub mod opcodes;
 mod optimizer;
@@ -10,16 +12,16 @@ mod parser;
 pub mod stack;
 mod utils;
 
+#[cfg(feature = "generation")]
 pub(crate) mod interpreter;
 
-use std::collections::HashSet;
-
-pub use constants::cancun_constants;
 pub use constants::global_exit_root;
+pub use constants::cancun_constants;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "generation"))]
 mod tests;
 
+use aggregator::active_features;
 use assembler::assemble;
 use parser::parse;
 
@@ -28,7 +30,7 @@ use crate::cpu::kernel::constants::evm_constants;
 /// Assemble files, outputting bytes.
 /// This is for debugging the kernel only.
 pub fn assemble_to_bytes(files: &[String]) -> Vec<u8> {
-    let parsed_files: Vec<_> = files.iter().map(|f| parse(f, HashSet::new())).collect();
+    let parsed_files: Vec<_> = files.iter().map(|f| parse(f, active_features())).collect();
     let kernel = assemble(parsed_files, evm_constants(), true);
     kernel.code
 }
@@ -39,6 +39,12 @@ pub fn get_opcode(mnemonic: &str) -> u8 {
         "SAR" => 0x1d,
         "KECCAK256" => 0x20,
         "KECCAK_GENERAL" => 0x21,
+        "SECP_ADD" => 0x28,
+        "SECP_DOUBLE" => 0x29,
+        "BN_G1_ADD" => 0x2a,
+        "BN_G1_DOUBLE" => 0x2b,
+        "SECP_MUL" => 0x2c,
+        "BN_G1_MUL" => 0x2e,
         "ADDRESS" => 0x30,
         "BALANCE" => 0x31,
         "ORIGIN" => 0x32,
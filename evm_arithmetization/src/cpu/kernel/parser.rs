@@ -65,9 +65,11 @@ fn parse_conditional_block(item: Pair<Rule>, active_features: &HashSet<&str>) ->
     assert_eq!(item.as_rule(), Rule::conditional_block);
     let mut inner = item.into_inner().peekable();
 
-    let name = inner.next().unwrap().as_str();
+    let predicate = inner.next().unwrap();
+    let negated = predicate.as_rule() == Rule::negated_feature_predicate;
+    let name = predicate.into_inner().next().unwrap().as_str();
 
-    if active_features.contains(&name) {
+    if active_features.contains(&name) != negated {
         Item::ConditionalBlock(
             name.into(),
             inner.map(|i| parse_item(i, active_features)).collect(),
@@ -287,6 +289,11 @@ mod tests {
             PUSH 8
             MOD
         }
+        #[cfg(not(feature = feature_2))]
+        {
+            global foo_5:
+            PUSH 9
+        }
         "#;
 
         // Test `feature_1`.
@@ -316,6 +323,9 @@ mod tests {
             PUSH 5
             PUSH 6
             DIV
+
+        global foo_5:
+            PUSH 9
         "#;
 
         let parsed_expected = parse(expected_code, HashSet::new());
@@ -410,6 +420,9 @@ mod tests {
             PUSH 5
             PUSH 6
             DIV
+
+        global foo_5:
+            PUSH 9
         "#;
 
         let parsed_expected = parse(expected_code, HashSet::new());
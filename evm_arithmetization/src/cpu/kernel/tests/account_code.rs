@@ -22,7 +22,7 @@ use crate::generation::TrieInputs;
 use crate::memory::segments::Segment;
 use crate::util::h2u;
 use crate::witness::memory::MemoryAddress;
-use crate::witness::operation::CONTEXT_SCALING_FACTOR;
+use crate::witness::memory::CONTEXT_SCALING_FACTOR;
 
 pub(crate) fn initialize_mpts<F: Field>(
     interpreter: &mut Interpreter<F>,
@@ -30,7 +30,7 @@ pub(crate) fn initialize_mpts<F: Field>(
 ) {
     // Load all MPTs.
     let (mut trie_root_ptrs, state_leaves, storage_leaves, trie_data) =
-        load_linked_lists_and_txn_and_receipt_mpts(trie_inputs)
+        load_linked_lists_and_txn_and_receipt_mpts(&trie_inputs.trim())
             .expect("Invalid MPT data for preinitialization");
 
     interpreter.generation_state.memory.contexts[0].segments
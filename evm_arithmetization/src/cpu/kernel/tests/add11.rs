@@ -182,10 +182,13 @@ fn test_add11_yml() {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: tries_before,
         trie_roots_after,
         contract_code: contract_code.clone(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         txn_number_before: 0.into(),
@@ -363,10 +366,13 @@ fn test_add11_yml_with_exception() {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: tries_before,
         trie_roots_after,
         contract_code: contract_code.clone(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
         txn_number_before: 0.into(),
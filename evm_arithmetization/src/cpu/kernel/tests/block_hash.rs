@@ -7,6 +7,8 @@ use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
 use crate::cpu::kernel::interpreter::Interpreter;
 use crate::memory::segments::Segment;
+use crate::proof::BlockHashes;
+use crate::util::h2u;
 
 #[test]
 fn test_correct_block_hash() -> Result<()> {
@@ -124,3 +126,45 @@ fn test_block_hash_with_overflow() -> Result<()> {
 
     Ok(())
 }
+
+fn run_check_ancestor_hashes(prev_hashes: Vec<H256>, parent_hash: H256) -> Result<()> {
+    let block_hashes = BlockHashes {
+        prev_hashes,
+        cur_hash: H256::zero(),
+    };
+
+    let check_label = KERNEL.global_labels["check_ancestor_hashes"];
+    let mut interpreter: Interpreter<F> = Interpreter::new(check_label, vec![], None);
+    interpreter
+        .halt_offsets
+        .push(KERNEL.global_labels["set_beacon_root"]);
+    interpreter.set_memory_segment(
+        Segment::BlockHashes,
+        block_hashes.prev_hashes.iter().copied().map(h2u).collect(),
+    );
+    interpreter.set_global_metadata_multi_fields(&[
+        (GlobalMetadata::BlockParentHash, h2u(parent_hash)),
+        (
+            GlobalMetadata::BlockAncestorsHash,
+            h2u(block_hashes.ancestors_hash()),
+        ),
+    ]);
+    interpreter.run()?;
+
+    assert_eq!(
+        interpreter.generation_state.registers.program_counter,
+        KERNEL.global_labels["set_beacon_root"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_ancestor_hashes() -> Result<()> {
+    let prev_hashes: Vec<H256> = (0..256).map(|_| thread_rng().gen()).collect();
+    let parent_hash = prev_hashes[255];
+
+    run_check_ancestor_hashes(prev_hashes.clone(), parent_hash)?;
+    assert!(run_check_ancestor_hashes(prev_hashes, thread_rng().gen()).is_err());
+
+    Ok(())
+}
@@ -8,10 +8,12 @@ use super::{run_interpreter_with_memory, InterpreterMemoryInitialization};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::cancun_constants::POINT_EVALUATION_PRECOMPILE_RETURN_VALUE;
 use crate::cpu::kernel::constants::cancun_constants::KZG_VERSIONED_HASH;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
 use crate::cpu::kernel::interpreter::Interpreter;
-use crate::extension_tower::{Fp2, Stack, BLS381};
-use crate::memory::segments::Segment::KernelGeneral;
+use crate::extension_tower::{Fp2, Stack, BLS381, BLS_SCALAR};
+use crate::memory::segments::Segment::{Calldata, KernelGeneral};
 use crate::util::sha2;
+use crate::witness::memory::MemoryAddress;
 
 #[test]
 fn test_bls_fp2_mul() -> Result<()> {
@@ -46,7 +48,7 @@ type KzgPayload = ([u8; 48], [u8; 32], [u8; 32], [u8; 48]);
 type TestSequence = (KzgPayload, bool);
 
 /// Test cases taken from <https://github.com/ethereum/c-kzg-4844/blob/main/tests/verify_kzg_proof/kzg-mainnet/>.
-const KZG_PRECOMPILE_TEST_SEQUENCES: [TestSequence; 10] = [
+const KZG_PRECOMPILE_TEST_SEQUENCES: [TestSequence; 11] = [
     // verify_kzg_proof_case_correct_proof_02e696ada7d4631d/data.yaml
     ((hex!("c00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
     hex!("0000000000000000000000000000000000000000000000000000000000000002"),
@@ -108,23 +110,36 @@ const KZG_PRECOMPILE_TEST_SEQUENCES: [TestSequence; 10] = [
     hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000002"),
     hex!("60f840641ec0d0c0d2b77b2d5a393b329442721fad05ab78c7b98f2aa3c20ec9"),
     hex!("b30b3d1e4faccc380557792c9a0374d58fa286f5f75fea48870585393f890909cd3c53cfe4897e799fb211b4be531e43")), false),
+
+    // z equal to the BLS12-381 scalar field modulus, which would otherwise open
+    // trivially for a commitment and a proof at infinity.
+    ((hex!("c00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+    hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001"),
+    hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+    hex!("c00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000")), false),
 ];
 
+/// Returns the versioned hash of a KZG commitment.
+fn kzg_versioned_hash(commitment_bytes: &[u8; 48]) -> U256 {
+    let mut versioned_hash = sha2(commitment_bytes.to_vec());
+    const KZG_HASH_MASK: U256 = U256([
+        0xffffffffffffffff,
+        0xffffffffffffffff,
+        0xffffffffffffffff,
+        0x00ffffffffffffff,
+    ]);
+    versioned_hash &= KZG_HASH_MASK; // erase most significant byte
+    versioned_hash |= U256::from(KZG_VERSIONED_HASH) << 248; // append 1
+    versioned_hash
+}
+
 #[test]
 fn test_kzg_peval_precompile() -> Result<()> {
     for (bytes, is_correct) in KZG_PRECOMPILE_TEST_SEQUENCES.iter() {
         let commitment_bytes = bytes.0;
         let comm_hi = U256::from_big_endian(&commitment_bytes[0..32]);
         let comm_lo = U256::from_big_endian(&commitment_bytes[32..48]);
-        let mut versioned_hash = sha2(commitment_bytes.to_vec());
-        const KZG_HASH_MASK: U256 = U256([
-            0xffffffffffffffff,
-            0xffffffffffffffff,
-            0xffffffffffffffff,
-            0x00ffffffffffffff,
-        ]);
-        versioned_hash &= KZG_HASH_MASK; // erase most significant byte
-        versioned_hash |= U256::from(KZG_VERSIONED_HASH) << 248; // append 1
+        let versioned_hash = kzg_versioned_hash(&commitment_bytes);
         let z = U256::from_big_endian(&bytes.1);
         let y = U256::from_big_endian(&bytes.2);
         let proof_bytes = bytes.3;
@@ -174,3 +189,73 @@ fn test_kzg_peval_precompile() -> Result<()> {
 
     Ok(())
 }
+
+/// Runs the whole precompile on `calldata`, returning whether it succeeded.
+fn run_kzg_peval_precompile(calldata: &[u8]) -> Result<bool> {
+    let precompile_kzg_peval = KERNEL.global_labels["precompile_kzg_peval"];
+    let store_kzg_verification = KERNEL.global_labels["store_kzg_verification"];
+    let fault_exception = KERNEL.global_labels["fault_exception"];
+    let new_ctx = 1;
+
+    let mut interpreter: Interpreter<F> = Interpreter::new(
+        precompile_kzg_peval,
+        vec![new_ctx.into(), U256::from(0xdeadbeefu32)],
+        None,
+    );
+    interpreter.set_memory_multi_addresses(
+        &calldata
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| (MemoryAddress::new(new_ctx, Calldata, i), byte.into()))
+            .collect::<Vec<_>>(),
+    );
+    interpreter.set_context_metadata_field(new_ctx, ContextMetadata::GasLimit, 100_000.into());
+    interpreter.set_context_metadata_field(
+        new_ctx,
+        ContextMetadata::CalldataSize,
+        calldata.len().into(),
+    );
+    interpreter
+        .halt_offsets
+        .extend([store_kzg_verification, fault_exception]);
+    interpreter.run()?;
+
+    Ok(interpreter.generation_state.registers.program_counter == store_kzg_verification)
+}
+
+#[test]
+fn test_kzg_peval_precompile_invalid_inputs() -> Result<()> {
+    // A correct proof for a commitment at infinity, opening to 0 at z = 2.
+    let ((commitment, z, y, proof), is_correct) = KZG_PRECOMPILE_TEST_SEQUENCES[0];
+    assert!(is_correct);
+    let payload = |z: &[u8; 32], y: &[u8; 32]| {
+        let mut versioned_hash = [0u8; 32];
+        kzg_versioned_hash(&commitment).to_big_endian(&mut versioned_hash);
+        [
+            &versioned_hash[..],
+            &z[..],
+            &y[..],
+            &commitment[..],
+            &proof[..],
+        ]
+        .concat()
+    };
+    let calldata = payload(&z, &y);
+    assert_eq!(calldata.len(), 192);
+    assert!(run_kzg_peval_precompile(&calldata)?);
+
+    // Inputs must be exactly 192 bytes long.
+    assert!(!run_kzg_peval_precompile(&calldata[..191])?);
+    let mut extended_calldata = calldata.clone();
+    extended_calldata.push(0);
+    assert!(!run_kzg_peval_precompile(&extended_calldata)?);
+
+    // Both scalars must be smaller than the BLS12-381 scalar field modulus, even
+    // though they would open trivially here.
+    let mut modulus = [0u8; 32];
+    BLS_SCALAR.to_big_endian(&mut modulus);
+    assert!(!run_kzg_peval_precompile(&payload(&modulus, &y))?);
+    assert!(!run_kzg_peval_precompile(&payload(&z, &modulus))?);
+
+    Ok(())
+}
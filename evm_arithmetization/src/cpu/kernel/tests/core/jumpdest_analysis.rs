@@ -2,12 +2,16 @@ use std::collections::{BTreeSet, HashMap};
 
 use anyhow::Result;
 use ethereum_types::U256;
+use keccak_hash::keccak;
 use plonky2::field::goldilocks_field::GoldilocksField as F;
 
 use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
 use crate::cpu::kernel::interpreter::Interpreter;
 use crate::cpu::kernel::opcodes::{get_opcode, get_push_opcode};
-use crate::witness::operation::CONTEXT_SCALING_FACTOR;
+use crate::memory::segments::Segment;
+use crate::witness::memory::MemoryAddress;
+use crate::witness::memory::CONTEXT_SCALING_FACTOR;
 
 #[test]
 fn test_jumpdest_analysis() -> Result<()> {
@@ -104,6 +108,89 @@ fn test_jumpdest_analysis() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_jumpdest_analysis_reuses_verified_context() -> Result<()> {
+    // As above, we start 1 line after the first PROVER_INPUT, performed "by hand".
+    let jumpdest_analysis = KERNEL.global_labels["jumpdest_analysis"] + 1;
+    const VERIFIED_CONTEXT: usize = 3; // arbitrary
+    const CONTEXT: usize = 4; // arbitrary
+
+    let add = get_opcode("ADD");
+    let push2 = get_push_opcode(2);
+    let jumpdest = get_opcode("JUMPDEST");
+
+    #[rustfmt::skip]
+    let code: Vec<u8> = vec![
+        add,
+        jumpdest,
+        push2,
+        jumpdest, // part of PUSH2
+        jumpdest, // part of PUSH2
+        jumpdest,
+        add,
+        jumpdest,
+    ];
+    let code_len = code.len();
+    let code_hash = U256::from_big_endian(keccak(&code).as_bytes());
+    let jumpdest_bits = vec![false, true, false, false, false, true, false, true];
+
+    let mut interpreter: Interpreter<F> = Interpreter::new(jumpdest_analysis, vec![], None);
+    for context in [VERIFIED_CONTEXT, CONTEXT] {
+        interpreter.set_code(context, code.clone());
+        interpreter.set_context_metadata_field(context, ContextMetadata::CodeHash, code_hash);
+    }
+    // Jumpdests 1 and 5 have already been verified in the other context, so
+    // only jumpdest 7 needs a proof.
+    interpreter.set_memory_multi_addresses(&[
+        (
+            MemoryAddress::new(VERIFIED_CONTEXT, Segment::JumpdestBits, 1),
+            U256::one(),
+        ),
+        (
+            MemoryAddress::new(VERIFIED_CONTEXT, Segment::JumpdestBits, 5),
+            U256::one(),
+        ),
+    ]);
+    interpreter.set_jumpdest_analysis_inputs(HashMap::from([(CONTEXT, BTreeSet::from([1, 5, 7]))]));
+
+    interpreter.generation_state.registers.context = CONTEXT;
+    interpreter
+        .push(0xDEADBEEFu32.into())
+        .expect("The stack should not overflow");
+    interpreter
+        .push(code_len.into())
+        .expect("The stack should not overflow");
+    interpreter
+        .push(U256::from(CONTEXT) << CONTEXT_SCALING_FACTOR)
+        .expect("The stack should not overflow");
+
+    // We need to manually pop the jumpdest_table and push its value on the top of
+    // the stack
+    interpreter
+        .generation_state
+        .jumpdest_table
+        .as_mut()
+        .unwrap()
+        .get_mut(&CONTEXT)
+        .unwrap()
+        .pop();
+    interpreter
+        .push(8.into())
+        .expect("The stack should not overflow");
+
+    interpreter.run()?;
+    assert_eq!(interpreter.stack(), vec![]);
+
+    assert_eq!(jumpdest_bits, interpreter.get_jumpdest_bits(CONTEXT));
+    // The proofs of the copied jumpdests have been discarded along the way.
+    assert_eq!(
+        interpreter.generation_state.jumpdest_table,
+        Some(HashMap::new())
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_packed_verification() -> Result<()> {
     let write_table_if_jumpdest = KERNEL.global_labels["write_table_if_jumpdest"];
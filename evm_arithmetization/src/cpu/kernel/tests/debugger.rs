@@ -0,0 +1,78 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::debugger::{Breakpoint, DebugEvent, Debugger};
+use crate::memory::segments::Segment;
+
+#[test]
+fn test_debugger_breakpoints() -> Result<()> {
+    let exp = KERNEL.global_labels["exp"];
+    let initial_stack = vec![0xDEADBEEFu32.into(), 5.into(), 2.into()];
+    let mut debugger = Debugger::<F>::new_at_label("exp", initial_stack)?;
+    assert_eq!(
+        debugger.add_breakpoint(Breakpoint::Label("exp".into()))?,
+        exp
+    );
+    assert!(debugger
+        .add_breakpoint(Breakpoint::Label("not_a_label".into()))
+        .is_err());
+
+    assert_eq!(debugger.step()?, DebugEvent::Stepped);
+    assert_eq!(debugger.clock(), 1);
+    assert_eq!(
+        debugger.stack(),
+        vec![5.into(), 2.into(), 5.into(), 0xDEADBEEFu32.into()]
+    );
+
+    // `exp` recurses on the square of its base and half of its exponent.
+    for (x, e) in [(4, 2), (16, 1), (256, 0)] {
+        assert_eq!(debugger.resume()?, DebugEvent::Breakpoint(exp));
+        assert_eq!(debugger.location(), "exp");
+        assert_eq!(debugger.stack()[..2], [U256::from(x), U256::from(e)]);
+    }
+
+    assert!(debugger.remove_breakpoint(exp));
+    assert_eq!(debugger.resume()?, DebugEvent::Halted);
+    assert!(debugger.is_halted());
+    assert_eq!(debugger.stack(), vec![32.into()]);
+    assert_eq!(debugger.step()?, DebugEvent::Halted);
+
+    let dump = debugger.dump_context();
+    assert_eq!(dump.program_counter, 0xDEADBEEF);
+    assert_eq!(dump.stack, vec![32.into()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_debugger_memory_view() -> Result<()> {
+    let retdest = 0xDEADBEEFu32.into();
+    let addr = (Segment::RlpRaw as u64).into();
+    let initial_stack = vec![retdest, 4.into(), 0xABCD1234u32.into(), addr];
+    let mut debugger = Debugger::<F>::new_at_label("mstore_unpacking", initial_stack)?;
+    assert_eq!(debugger.resume()?, DebugEvent::Halted);
+
+    let dump = debugger.dump_context();
+    let segment = dump
+        .segments
+        .iter()
+        .find(|segment| segment.layout.name == "SEGMENT_RLP_RAW")
+        .expect("the RLP segment is written");
+    assert_eq!(segment.layout.address, Segment::RlpRaw as u64);
+    assert_eq!(
+        segment.metadata,
+        vec![("GLOBAL_METADATA_RLP_DATA_SIZE", U256::zero())]
+    );
+    assert_eq!(
+        segment.content,
+        vec![0xAB.into(), 0xCD.into(), 0x12.into(), 0x34.into()]
+    );
+    assert!(dump.to_string().contains(&format!(
+        "SEGMENT_RLP_RAW at {:#x} (4 cells of 8 bits), GLOBAL_METADATA_RLP_DATA_SIZE = 0:",
+        Segment::RlpRaw as u64
+    )));
+
+    Ok(())
+}
@@ -341,6 +341,43 @@ mod secp {
         Ok(())
     }
 
+    #[test]
+    fn test_ec_mul() -> Result<()> {
+        let ec_mul = KERNEL.global_labels["secp_mul"];
+        let identity = ("0x0", "0x0");
+        let point0 = (
+            "0xc82ccceebd739e646631b7270ed8c33e96c4940b19db91eaf67da6ec92d109b",
+            "0xe0d241d2de832656c3eed78271bb06b5602d6473742c7c48a38b9f0350a76164",
+        );
+        // point1 = 2 * point0
+        let point1 = (
+            "0x7872498939b02197c2b6f0a0f5767f36551e43f910de472fbbff0538b21f5f45",
+            "0x294e15025d935438023a0e4056892abd6405fade13cf2b3131d8755be7cebad",
+        );
+        // point2 = s * point0
+        let s = "0xd8c5f2b0e0a1b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c";
+        let point2 = (
+            "0xa6039373f96ba2952152e79591511ebe99150babeb81f4dbbad3f4e55e7b3930",
+            "0x145f79d18df93b9fa6ca58250ba11e00db5e2ff3fabce99a01671d903166b0d9",
+        );
+
+        for (k, point, expected) in [
+            ("0x0", point0, identity),
+            ("0x1", point0, point0),
+            ("0x2", point0, point1),
+            (s, point0, point2),
+            (s, identity, identity),
+        ] {
+            let initial_stack = u256ify(["0xdeadbeef", point.1, point.0, k])?;
+            let stack = run_interpreter::<F>(ec_mul, initial_stack)?
+                .stack()
+                .to_vec();
+            assert_eq!(stack, u256ify([expected.1, expected.0])?);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_glv_verify_data() -> Result<()> {
         let glv = KERNEL.global_labels["secp_glv_decompose"];
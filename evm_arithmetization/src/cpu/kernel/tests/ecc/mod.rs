@@ -1,2 +1,4 @@
 mod curve_ops;
 mod ecrecover;
+#[cfg(feature = "p256_verify")]
+mod p256;
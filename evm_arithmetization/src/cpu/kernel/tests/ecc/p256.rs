@@ -0,0 +1,75 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::tests::{run_interpreter, u256ify};
+
+const HASH: &str = "0xaf2bdbe1aa9b6ec1e2ade1d694f41fc71a831d0268e9891562113d8a62add1bf";
+const R: &str = "0xaabaf50a0ff2f0252d6c4876006bc2c711bd36e395533be55446e69caa20274c";
+const S: &str = "0xf65da650a490de8350f7262fc3e4e950e8a140eefc8773e4da08e9afbffb2531";
+const QX: &str = "0x60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+const QY: &str = "0x7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299";
+
+fn test_p256_verify(hash: &str, r: &str, s: &str, qx: &str, qy: &str, expected: u32) -> Result<()> {
+    let p256_verify = KERNEL.global_labels["p256_verify"];
+    let initial_stack = u256ify(["0xdeadbeef", qy, qx, s, r, hash])?;
+    let stack = run_interpreter::<F>(p256_verify, initial_stack)?
+        .stack()
+        .to_vec();
+    assert_eq!(stack, vec![U256::from(expected)]);
+
+    Ok(())
+}
+
+#[test]
+fn test_p256_verify_valid() -> Result<()> {
+    test_p256_verify(HASH, R, S, QX, QY, 1)?;
+    // RIP-7212 does not restrict `s` to the lower half of the scalar field.
+    test_p256_verify(
+        HASH,
+        R,
+        "0x9a259ae5b6f217daf08d9d03c1b16aed445b9beaa902aa019b0e1133c680020",
+        QX,
+        QY,
+        1,
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_p256_verify_invalid() -> Result<()> {
+    // Wrong hash.
+    test_p256_verify(
+        "0xaf2bdbe1aa9b6ec1e2ade1d694f41fc71a831d0268e9891562113d8a62add1be",
+        R,
+        S,
+        QX,
+        QY,
+        0,
+    )?;
+    // Public key not on the curve.
+    test_p256_verify(
+        HASH,
+        R,
+        S,
+        QX,
+        "0x7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462298",
+        0,
+    )?;
+    // Public key at infinity.
+    test_p256_verify(HASH, R, S, "0x0", "0x0", 0)?;
+    // `r` and `s` out of range.
+    test_p256_verify(HASH, "0x0", S, QX, QY, 0)?;
+    test_p256_verify(
+        HASH,
+        R,
+        "0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+        QX,
+        QY,
+        0,
+    )?;
+
+    Ok(())
+}
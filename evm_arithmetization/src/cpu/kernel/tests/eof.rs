@@ -0,0 +1,82 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use hex_literal::hex;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::memory::segments::Segment;
+
+/// Builds a container with a single code section.
+fn container(code: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut container = hex!("ef0001 010004 020001").to_vec();
+    container.extend_from_slice(&(code.len() as u16).to_be_bytes());
+    container.push(0xff);
+    container.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    container.push(0x00);
+    container.extend_from_slice(&hex!("00800000"));
+    container.extend_from_slice(code);
+    container.extend_from_slice(data);
+    container
+}
+
+fn is_valid(container: &[u8]) -> Result<bool> {
+    let eof_validate = KERNEL.global_labels["eof_validate"];
+    let initial_stack = vec![
+        0xdeadbeefu32.into(),
+        container.len().into(),
+        (Segment::Returndata as usize).into(),
+    ];
+
+    let mut interpreter: Interpreter<F> = Interpreter::new(eof_validate, initial_stack, None);
+    interpreter.set_memory_segment_bytes(Segment::Returndata, container.to_vec());
+    interpreter.run()?;
+
+    let stack = interpreter.stack();
+    assert_eq!(stack.len(), 1);
+    Ok(stack[0] == U256::one())
+}
+
+#[test]
+fn test_eof_validate_valid() -> Result<()> {
+    // STOP
+    assert!(is_valid(&container(&hex!("00"), &[]))?);
+    // PUSH0, RJUMPI +1, STOP, STOP
+    assert!(is_valid(&container(
+        &hex!("5f e10001 00 00"),
+        &hex!("abcdef")
+    ))?);
+    // JUMPDEST, RJUMP -4
+    assert!(is_valid(&container(&hex!("5b e0fffc"), &[]))?);
+    // PUSH0, RJUMPV [+0, +1], STOP, STOP
+    assert!(is_valid(&container(
+        &hex!("5f e2 01 0000 0001 00 00"),
+        &[]
+    ))?);
+
+    Ok(())
+}
+
+#[test]
+fn test_eof_validate_invalid() -> Result<()> {
+    // Legacy code.
+    assert!(!is_valid(&hex!("6000 00"))?);
+    // Trailing byte after the data section.
+    let mut trailing = container(&hex!("00"), &[]);
+    trailing.push(0x00);
+    assert!(!is_valid(&trailing)?);
+    // Truncated PUSH2.
+    assert!(!is_valid(&container(&hex!("6100"), &[]))?);
+    // Undefined opcode.
+    assert!(!is_valid(&container(&hex!("0c 00"), &[]))?);
+    // JUMP is superseded by the relative jumps.
+    assert!(!is_valid(&container(&hex!("5f 56 00"), &[]))?);
+    // The code does not end with a terminating instruction.
+    assert!(!is_valid(&container(&hex!("5f"), &[]))?);
+    // PUSH1 0, RJUMP -4 jumps into the immediate of PUSH1.
+    assert!(!is_valid(&container(&hex!("6000 e0fffc"), &[]))?);
+    // RJUMP +1 jumps past the end of the code section.
+    assert!(!is_valid(&container(&hex!("e00001 00"), &[]))?);
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+use plonky2::field::types::Field;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::constants::txn_fields::NormalizedTxnField;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::memory::segments::Segment;
+use crate::proof::{BlockMetadata, BlockMetadataTarget, Hardfork, PublicValuesError};
+use crate::witness::memory::MemoryAddress;
+
+fn interpreter_at(label: &str, hardfork: Hardfork, initial_stack: Vec<U256>) -> Interpreter<F> {
+    let mut interpreter: Interpreter<F> =
+        Interpreter::new(KERNEL.global_labels[label], initial_stack, None);
+    interpreter.set_global_metadata_multi_fields(&[
+        (GlobalMetadata::BlockHardfork, hardfork.id().into()),
+        (
+            GlobalMetadata::Push0Enabled,
+            hardfork.enables_push0().into(),
+        ),
+    ]);
+    interpreter
+}
+
+#[test]
+fn cancun_opcodes_fault_before_cancun() -> Result<()> {
+    let fault_exception = KERNEL.global_labels["fault_exception"];
+    let kexit_info = U256::from(0xdeadbeefu32) + (U256::one() << 32);
+
+    for label in ["sys_tload", "sys_mcopy", "sys_blobhash", "sys_blobbasefee"] {
+        let mut interpreter = interpreter_at(
+            label,
+            Hardfork::Shanghai,
+            vec![0.into(), 0.into(), 0.into(), kexit_info],
+        );
+        interpreter.halt_offsets.push(fault_exception);
+        interpreter.run()?;
+        assert_eq!(
+            interpreter.generation_state.registers.program_counter, fault_exception,
+            "{label} should fault before Cancun"
+        );
+        // The hardfork is checked before charging any gas.
+        assert_eq!(interpreter.generation_state.registers.gas_used, 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn basefee_faults_before_london() -> Result<()> {
+    let fault_exception = KERNEL.global_labels["fault_exception"];
+    let kexit_info = U256::from(0xdeadbeefu32) + (U256::one() << 32);
+
+    let mut interpreter = interpreter_at("sys_basefee", Hardfork::Berlin, vec![kexit_info]);
+    interpreter.halt_offsets.push(fault_exception);
+    interpreter.run()?;
+    assert_eq!(
+        interpreter.generation_state.registers.program_counter,
+        fault_exception
+    );
+
+    Ok(())
+}
+
+#[test]
+fn prevrandao_is_difficulty_before_paris() -> Result<()> {
+    let kexit_info = U256::from(0xdeadbeefu32) + (U256::one() << 32);
+    let difficulty = U256::from(0x020000);
+    let random = U256::from(0x1234);
+
+    for (hardfork, expected) in [(Hardfork::London, difficulty), (Hardfork::Paris, random)] {
+        let mut interpreter = interpreter_at("sys_prevrandao", hardfork, vec![kexit_info]);
+        interpreter.set_global_metadata_multi_fields(&[
+            (GlobalMetadata::BlockDifficulty, difficulty),
+            (GlobalMetadata::BlockRandom, random),
+        ]);
+        interpreter.set_context_metadata_field(0, ContextMetadata::GasLimit, 100.into());
+        interpreter.run()?;
+        assert_eq!(interpreter.stack(), vec![expected], "{hardfork}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn push0_is_invalid_before_shanghai() -> Result<()> {
+    let fault_exception = KERNEL.global_labels["fault_exception"];
+    // The exception is raised by the first instruction of the user code.
+    let trap_info = U256::zero();
+
+    for (hardfork, is_invalid) in [(Hardfork::Paris, true), (Hardfork::Shanghai, false)] {
+        let mut interpreter = interpreter_at("exc_invalid_opcode", hardfork, vec![trap_info]);
+        interpreter.set_context(1);
+        interpreter
+            .set_memory_multi_addresses(&[(MemoryAddress::new(1, Segment::Code, 0), 0x5f.into())]);
+        interpreter.halt_offsets.push(fault_exception);
+        // The handler panics if the opcode is actually valid.
+        assert_eq!(interpreter.run().is_ok(), is_invalid, "{hardfork}");
+        if is_invalid {
+            assert_eq!(
+                interpreter.generation_state.registers.program_counter,
+                fault_exception
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn blob_txns_are_invalid_before_cancun() -> Result<()> {
+    let invalid_txn_1 = KERNEL.global_labels["invalid_txn_1"];
+    let process_normalized_txn = KERNEL.global_labels["process_normalized_txn"];
+
+    const INITIAL_TXN_RLP_ADDR: usize = Segment::RlpRaw as usize + 1;
+    let retaddr = 0xDEADBEEFu32.into();
+    let mut interpreter = interpreter_at(
+        "process_type_3_txn",
+        Hardfork::Shanghai,
+        vec![retaddr, INITIAL_TXN_RLP_ADDR.into()],
+    );
+    interpreter
+        .halt_offsets
+        .extend([invalid_txn_1, process_normalized_txn]);
+    interpreter.extend_memory_segment_bytes(Segment::RlpRaw, vec![0x03, 0xc0]);
+
+    interpreter.run()?;
+
+    assert_eq!(
+        interpreter.generation_state.registers.program_counter,
+        invalid_txn_1
+    );
+    assert_eq!(
+        interpreter.get_txn_field(NormalizedTxnField::ChainIdPresent),
+        0.into()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_hardfork_public_input_is_an_error() {
+    let mut pis = vec![F::ZERO; BlockMetadataTarget::SIZE];
+    pis[BlockMetadataTarget::SIZE - 1] = F::from_canonical_u64(Hardfork::Cancun.id());
+    assert_eq!(
+        BlockMetadata::from_public_inputs(&pis).map(|metadata| metadata.block_hardfork),
+        Ok(Hardfork::Cancun)
+    );
+
+    pis[BlockMetadataTarget::SIZE - 1] = F::from_canonical_u64(Hardfork::Cancun.id() + 1);
+    assert_eq!(
+        BlockMetadata::from_public_inputs(&pis),
+        Err(PublicValuesError::UnsupportedHardfork(
+            Hardfork::Cancun.id() + 1
+        ))
+    );
+}
+
+#[test]
+fn kzg_point_evaluation_is_a_precompile_from_cancun() -> Result<()> {
+    let precompile_kzg_peval = KERNEL.global_labels["precompile_kzg_peval"];
+    let retdest = 0xdeadbeefu32;
+    let kzg_peval = 0x0a.into();
+
+    for (hardfork, is_precompile) in [(Hardfork::Shanghai, false), (Hardfork::Cancun, true)] {
+        let mut interpreter = interpreter_at(
+            "handle_precompiles",
+            hardfork,
+            vec![1.into(), retdest.into(), kzg_peval],
+        );
+        interpreter.halt_offsets.push(precompile_kzg_peval);
+        interpreter.run()?;
+        let expected = if is_precompile {
+            precompile_kzg_peval
+        } else {
+            retdest as usize
+        };
+        assert_eq!(
+            interpreter.generation_state.registers.program_counter, expected,
+            "{hardfork}"
+        );
+    }
+
+    Ok(())
+}
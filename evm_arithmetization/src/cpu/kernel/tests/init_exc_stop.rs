@@ -92,6 +92,7 @@ fn test_init_exc_stop() {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         withdrawals: vec![],
+        block_rewards: vec![],
         tries: TrieInputs {
             state_trie: state_trie_before,
             transactions_trie,
@@ -101,6 +102,7 @@ fn test_init_exc_stop() {
         trie_roots_after,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
@@ -110,6 +112,7 @@ fn test_init_exc_stop() {
             cur_hash: H256::default(),
         },
         global_exit_roots: vec![],
+        burn_addr: None,
     };
     let initial_stack = vec![];
     let initial_offset = KERNEL.global_labels["init"];
@@ -8,8 +8,12 @@ mod block_hash;
 mod bls381;
 mod bn254;
 mod core;
+mod debugger;
 mod ecc;
+#[cfg(feature = "eof")]
+mod eof;
 mod exp;
+mod hardfork;
 mod hash;
 mod init_exc_stop;
 mod kernel_consistency;
@@ -17,6 +21,7 @@ mod log;
 mod mcopy;
 mod mpt;
 mod packing;
+mod profiling;
 mod receipt;
 mod rlp;
 mod signed_syscalls;
@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::prover::CycleProfile;
+
+#[test]
+fn test_cycle_profile() -> Result<()> {
+    let exp = KERNEL.global_labels["exp"];
+    let initial_stack = vec![0xDEADBEEFu32.into(), 5.into(), 2.into()];
+    let mut interpreter: Interpreter<F> = Interpreter::new(exp, initial_stack, None);
+    interpreter.cycle_profile = Some(CycleProfile::default());
+    interpreter.run()?;
+
+    let profile = interpreter.cycle_profile.expect("profiling is enabled");
+    let clock = interpreter.clock;
+    assert_eq!(profile.total_cycles(), clock);
+    // The local labels of `exp` are attributed to it.
+    assert_eq!(
+        profile.by_routine(),
+        BTreeMap::from([("exp".to_string(), clock)])
+    );
+    assert_eq!(profile.to_folded(), format!("kernel;exp {clock}\n"));
+
+    Ok(())
+}
@@ -1 +1,3 @@
 mod parse_type_0_txn;
+#[cfg(feature = "op_deposit")]
+mod parse_type_7e_txn;
@@ -96,3 +96,68 @@ fn process_type_0_txn_invalid_sig() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn process_type_0_txn_large_chain_id() -> Result<()> {
+    let process_type_0_txn = KERNEL.global_labels["process_type_0_txn"];
+    let process_normalized_txn = KERNEL.global_labels["process_normalized_txn"];
+
+    let retaddr = 0xDEADBEEFu32.into();
+    const INITIAL_TXN_RLP_ADDR: usize = Segment::RlpRaw as usize + 1;
+    let mut interpreter: Interpreter<F> = Interpreter::new(
+        process_type_0_txn,
+        vec![retaddr, INITIAL_TXN_RLP_ADDR.into()],
+        None,
+    );
+    interpreter.halt_offsets.push(process_normalized_txn);
+
+    // Same transaction as `process_type_0_txn()`, signed with the same key as
+    // per EIP-155 on a chain with ID 2^32 + 1, hence with v = 2^33 + 37.
+    interpreter.extend_memory_segment_bytes(Segment::RlpRaw, hex!("f866050a8255f094000000000000000000000000000000000000000064824242850200000025a0bb50e2d89a4ed70663d080659fe0ad4b9bc3e06c17a227433966cb59ceee020da00c7b45f40b46c609c9d0cc73db3b4bf2efa440142dc123cd85981ee6d3076a45").to_vec());
+
+    interpreter.run()?;
+
+    assert_eq!(interpreter.get_txn_field(ChainIdPresent), 1.into());
+    assert_eq!(
+        interpreter.get_txn_field(ChainId),
+        ((1u64 << 32) + 1).into()
+    );
+    assert_eq!(interpreter.get_txn_field(YParity), 0.into());
+    assert_eq!(
+        interpreter.get_txn_field(Origin),
+        U256::from_big_endian(&hex!("2c7536e3605d9c16a7a3d7b1898e529396a65c23"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn process_type_0_txn_invalid_v() -> Result<()> {
+    let process_type_0_txn = KERNEL.global_labels["process_type_0_txn"];
+    let invalid_txn = KERNEL.global_labels["invalid_txn_2"];
+
+    // Same transaction as `process_type_0_txn()`, with v set to 26, 29 and 34,
+    // which encode neither a legacy nor an EIP-155 signature.
+    for v in [0x1a, 0x1d, 0x22] {
+        let retaddr = 0xDEADBEEFu32.into();
+        const INITIAL_TXN_RLP_ADDR: usize = Segment::RlpRaw as usize + 1;
+        let mut interpreter: Interpreter<F> = Interpreter::new(
+            process_type_0_txn,
+            vec![retaddr, INITIAL_TXN_RLP_ADDR.into()],
+            None,
+        );
+        interpreter.halt_offsets.push(invalid_txn);
+
+        let mut txn = hex!("f861050a8255f0940000000000000000000000000000000000000000648242421ca07c5c61ed975ebd286f6b027b8c504842e50a47d318e1e801719dd744fe93e6c6a01e7b5119b57dd54e175ff2f055c91f3ab1b53eba0b2c184f347cdff0e745aca2");
+        txn[32] = v;
+        interpreter.extend_memory_segment_bytes(Segment::RlpRaw, txn.to_vec());
+
+        interpreter.run()?;
+        assert_eq!(
+            interpreter.generation_state.registers.program_counter,
+            invalid_txn
+        );
+    }
+
+    Ok(())
+}
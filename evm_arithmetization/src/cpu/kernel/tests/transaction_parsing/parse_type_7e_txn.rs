@@ -0,0 +1,74 @@
+use anyhow::Result;
+use ethereum_types::U256;
+use hex_literal::hex;
+use plonky2::field::goldilocks_field::GoldilocksField as F;
+use NormalizedTxnField::*;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::txn_fields::NormalizedTxnField;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::memory::segments::Segment;
+
+#[test]
+fn process_type_7e_txn() -> Result<()> {
+    let process_type_7e_txn = KERNEL.global_labels["process_type_7e_txn"];
+    let process_normalized_txn = KERNEL.global_labels["process_normalized_txn"];
+
+    let retaddr = 0xDEADBEEFu32.into();
+    const INITIAL_TXN_RLP_ADDR: usize = Segment::RlpRaw as usize + 1;
+    let mut interpreter: Interpreter<F> = Interpreter::new(
+        process_type_7e_txn,
+        vec![retaddr, INITIAL_TXN_RLP_ADDR.into()],
+        None,
+    );
+    interpreter.halt_offsets.push(process_normalized_txn);
+
+    // 0x7e || rlp([source_hash, from, to, mint, value, gas_limit, is_system_tx,
+    // data]), with `mint = 1000`, `value = 100`, `gas_limit = 22000` and `data =
+    // 0x4242`.
+    interpreter.extend_memory_segment_bytes(Segment::RlpRaw, hex!("7ef856a011111111111111111111111111111111111111111111111111111111111111119422222222222222222222222222222222222222229433333333333333333333333333333333333333338203e8648255f080824242").to_vec());
+
+    interpreter.run()?;
+
+    assert_eq!(interpreter.get_txn_field(ChainIdPresent), 0.into());
+    assert_eq!(
+        interpreter.get_txn_field(Origin),
+        U256::from_big_endian(&[0x22; 20])
+    );
+    assert_eq!(
+        interpreter.get_txn_field(To),
+        U256::from_big_endian(&[0x33; 20])
+    );
+    assert_eq!(interpreter.get_txn_field(Mint), 1000.into());
+    assert_eq!(interpreter.get_txn_field(Value), 100.into());
+    assert_eq!(interpreter.get_txn_field(GasLimit), 22_000.into());
+    assert_eq!(interpreter.get_txn_field(DataLen), 2.into());
+    assert_eq!(interpreter.get_txn_data(), &[0x42.into(), 0x42.into()]);
+
+    Ok(())
+}
+
+#[test]
+fn process_type_7e_txn_system() -> Result<()> {
+    let process_type_7e_txn = KERNEL.global_labels["process_type_7e_txn"];
+    let invalid_txn_1 = KERNEL.global_labels["invalid_txn_1"];
+
+    let retaddr = 0xDEADBEEFu32.into();
+    const INITIAL_TXN_RLP_ADDR: usize = Segment::RlpRaw as usize + 1;
+    let mut interpreter: Interpreter<F> = Interpreter::new(
+        process_type_7e_txn,
+        vec![retaddr, INITIAL_TXN_RLP_ADDR.into()],
+        None,
+    );
+    interpreter.halt_offsets.push(invalid_txn_1);
+
+    // Same transaction as `process_type_7e_txn()`, with `is_system_tx = true`.
+    interpreter.extend_memory_segment_bytes(Segment::RlpRaw, hex!("7ef856a011111111111111111111111111111111111111111111111111111111111111119422222222222222222222222222222222222222229433333333333333333333333333333333333333338203e8648255f001824242").to_vec());
+
+    interpreter.run()?;
+
+    // System transactions are rejected before decoding the data.
+    assert_eq!(interpreter.get_txn_field(DataLen), 0.into());
+
+    Ok(())
+}
@@ -18,4 +18,4 @@ mod push0;
 mod shift;
 pub(crate) mod simple_logic;
 pub(crate) mod stack;
-mod syscalls_exceptions;
+pub(crate) mod syscalls_exceptions;
@@ -19,6 +19,8 @@ pub(crate) fn eval_packed<P: PackedField>(
     for &limb in &new_stack_top[1..] {
         yield_constr.constraint(filter * limb);
     }
+    // Unlike `PUSH0`, `PC` doesn't read any flag.
+    yield_constr.constraint(filter * lv.mem_channels[1].used);
 }
 
 /// Circuit version if `eval_packed`.
@@ -43,4 +45,9 @@ pub(crate) fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
         let constr = builder.mul_extension(filter, limb);
         yield_constr.constraint(builder, constr);
     }
+    // Unlike `PUSH0`, `PC` doesn't read any flag.
+    {
+        let constr = builder.mul_extension(filter, lv.mem_channels[1].used);
+        yield_constr.constraint(builder, constr);
+    }
 }
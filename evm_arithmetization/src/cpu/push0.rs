@@ -1,12 +1,16 @@
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
+use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 
 use crate::cpu::columns::CpuColumnsView;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::memory::segments::Segment;
 
-/// Evaluates constraints to check that we are not pushing anything.
+/// Evaluates constraints to check that we are not pushing anything, and that
+/// `PUSH0` is enabled.
 pub(crate) fn eval_packed<P: PackedField>(
     lv: &CpuColumnsView<P>,
     nv: &CpuColumnsView<P>,
@@ -17,10 +21,29 @@ pub(crate) fn eval_packed<P: PackedField>(
     for limb in nv.mem_channels[0].value {
         yield_constr.constraint(filter * limb);
     }
+
+    // Check that `PUSH0` is enabled, i.e. that the block follows Shanghai or a
+    // later hardfork. The flag is only read in user mode.
+    let push0_enabled_channel = lv.mem_channels[1];
+    yield_constr.constraint(filter * (push0_enabled_channel.value[0] - P::ONES));
+    yield_constr.constraint(filter * (push0_enabled_channel.used - (P::ONES - lv.is_kernel_mode)));
+    yield_constr.constraint(filter * (push0_enabled_channel.is_read - P::ONES));
+    yield_constr.constraint(filter * push0_enabled_channel.addr_context);
+    yield_constr.constraint(
+        filter
+            * (push0_enabled_channel.addr_segment
+                - P::Scalar::from_canonical_usize(Segment::GlobalMetadata.unscale())),
+    );
+    yield_constr.constraint(
+        filter
+            * (push0_enabled_channel.addr_virtual
+                - P::Scalar::from_canonical_usize(GlobalMetadata::Push0Enabled.unscale())),
+    );
 }
 
 /// Circuit version of `eval_packed`.
-/// Evaluates constraints to check that we are not pushing anything.
+/// Evaluates constraints to check that we are not pushing anything, and that
+/// `PUSH0` is enabled.
 pub(crate) fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut plonky2::plonk::circuit_builder::CircuitBuilder<F, D>,
     lv: &CpuColumnsView<ExtensionTarget<D>>,
@@ -33,4 +56,47 @@ pub(crate) fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
         let constr = builder.mul_extension(filter, limb);
         yield_constr.constraint(builder, constr);
     }
+
+    // Check that `PUSH0` is enabled, i.e. that the block follows Shanghai or a
+    // later hardfork. The flag is only read in user mode.
+    let push0_enabled_channel = lv.mem_channels[1];
+    {
+        let constr = builder.mul_sub_extension(filter, push0_enabled_channel.value[0], filter);
+        yield_constr.constraint(builder, constr);
+    }
+    {
+        let one = builder.one_extension();
+        let not_kernel = builder.sub_extension(one, lv.is_kernel_mode);
+        let diff = builder.sub_extension(push0_enabled_channel.used, not_kernel);
+        let constr = builder.mul_extension(filter, diff);
+        yield_constr.constraint(builder, constr);
+    }
+    {
+        let constr = builder.mul_sub_extension(filter, push0_enabled_channel.is_read, filter);
+        yield_constr.constraint(builder, constr);
+    }
+    {
+        let constr = builder.mul_extension(filter, push0_enabled_channel.addr_context);
+        yield_constr.constraint(builder, constr);
+    }
+    {
+        let constr = builder.arithmetic_extension(
+            F::ONE,
+            -F::from_canonical_usize(Segment::GlobalMetadata.unscale()),
+            filter,
+            push0_enabled_channel.addr_segment,
+            filter,
+        );
+        yield_constr.constraint(builder, constr);
+    }
+    {
+        let constr = builder.arithmetic_extension(
+            F::ONE,
+            -F::from_canonical_usize(GlobalMetadata::Push0Enabled.unscale()),
+            filter,
+            push0_enabled_channel.addr_virtual,
+            filter,
+        );
+        yield_constr.constraint(builder, constr);
+    }
 }
@@ -2,6 +2,7 @@ use ethereum_types::U256;
 use itertools::izip;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -24,6 +25,7 @@ fn limbs(x: U256) -> [u32; 8] {
 /// 0 otherwise. Then `diff @ x = num_unequal_limbs`, where `@` denotes the dot
 /// product. We set `diff_pinv = num_unequal_limbs^-1 * x` if `num_unequal_limbs
 /// != 0` and 0 otherwise. We have `diff @ diff_pinv = 1 - equal` as desired.
+#[cfg(feature = "generation")]
 pub(crate) fn generate_pinv_diff<F: Field>(val0: U256, val1: U256, lv: &mut CpuColumnsView<F>) {
     let val0_limbs = limbs(val0).map(F::from_canonical_u32);
     let val1_limbs = limbs(val1).map(F::from_canonical_u32);
@@ -37,6 +37,7 @@ pub(crate) const MIGHT_OVERFLOW: OpsColumnsView<bool> = OpsColumnsView {
     m_op_32bytes: false,
     exit_kernel: true, // Doesn't directly push, but the syscall it's returning from might.
     m_op_general: false,
+    ec_op: false,
     syscall: false,
     exception: false,
 };
@@ -71,12 +72,14 @@ const BASIC_TERNARY_OP: Option<StackBehavior> = Some(StackBehavior {
     disable_other_channels: true,
 });
 /// `StackBehavior` for JUMP.
+#[cfg(feature = "generation")]
 pub(crate) const JUMP_OP: Option<StackBehavior> = Some(StackBehavior {
     num_pops: 1,
     pushes: false,
     disable_other_channels: false,
 });
 /// `StackBehavior` for JUMPI.
+#[cfg(feature = "generation")]
 pub(crate) const JUMPI_OP: Option<StackBehavior> = Some(StackBehavior {
     num_pops: 2,
     pushes: false,
@@ -129,7 +132,7 @@ pub(crate) const STACK_BEHAVIORS: OpsColumnsView<Option<StackBehavior>> = OpsCol
     pc_push0: Some(StackBehavior {
         num_pops: 0,
         pushes: true,
-        disable_other_channels: true,
+        disable_other_channels: false, // `PUSH0` reads whether it is enabled.
     }),
     dup_swap: None,
     context_op: None,
@@ -144,6 +147,11 @@ pub(crate) const STACK_BEHAVIORS: OpsColumnsView<Option<StackBehavior>> = OpsCol
         disable_other_channels: true,
     }),
     m_op_general: None,
+    ec_op: Some(StackBehavior {
+        num_pops: 1,
+        pushes: false,
+        disable_other_channels: true,
+    }),
     syscall: Some(StackBehavior {
         num_pops: 0,
         pushes: true,
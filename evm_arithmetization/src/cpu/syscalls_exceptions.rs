@@ -14,7 +14,9 @@ use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::membus::NUM_GP_CHANNELS;
 use crate::memory::segments::Segment;
-use crate::witness::transition::EXC_STOP_CODE;
+
+/// The exception code of `exc_stop`, the exception raised to halt a segment.
+pub(crate) const EXC_STOP_CODE: u8 = 6;
 
 // Copy the constant but make it `usize`.
 const BYTES_PER_OFFSET: usize = crate::cpu::kernel::assembler::BYTES_PER_OFFSET as usize;
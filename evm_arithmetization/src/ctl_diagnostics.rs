@@ -0,0 +1,164 @@
+//! Diagnostics of the cross-table lookups of a segment.
+//!
+//! An unbalanced cross-table lookup otherwise only shows up as a nonzero sum
+//! of the running sums of its tables, with no hint of its origin.
+//! [`check_ctls`] instead checks each lookup on the traces before they are
+//! proven, and reports every unbalanced one by name, along with the tables it
+//! connects and the row values appearing more often on one side.
+//!
+//! The looked filter of the range-check lookup is a multiplicity rather than a
+//! binary flag, so this lookup is instead checked by counting the range-checked
+//! values of the looking tables.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::{anyhow, Result};
+use hashbrown::HashMap;
+use itertools::Itertools;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::{Field, PrimeField64};
+use starky::cross_table_lookup::{debug_utils, CrossTableLookup, TableIdx};
+
+use crate::all_stark::{Table, CTL_NAMES};
+use crate::arithmetic::arithmetic_stark;
+use crate::byte_packing::byte_packing_stark;
+use crate::memory::memory_stark;
+use crate::range_check::columns::MULTIPLICITY;
+use crate::range_check::range_check_stark::RangeCheckMultiplicities;
+
+/// A cross-table lookup whose looking and looked rows differ.
+#[derive(Clone, Debug)]
+pub(crate) struct CtlFailure {
+    /// The index of the lookup in the lookups of the `AllStark`.
+    pub(crate) index: usize,
+    pub(crate) name: &'static str,
+    /// The tables connected by the lookup.
+    pub(crate) tables: Vec<Table>,
+    /// The unbalanced row values, along with their locations as pairs of
+    /// table and row indices.
+    pub(crate) details: String,
+}
+
+impl fmt::Display for CtlFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CTL #{} ({}) between {:?} is unbalanced: {}",
+            self.index, self.name, self.tables, self.details
+        )
+    }
+}
+
+/// Checks each cross-table lookup on the traces of all tables, and fails with
+/// a description of all the unbalanced ones.
+pub(crate) fn check_ctls<F: PrimeField64>(
+    trace_poly_values: &[Vec<PolynomialValues<F>>],
+    cross_table_lookups: &[CrossTableLookup<F>],
+    extra_looking_values: &HashMap<TableIdx, Vec<Vec<F>>>,
+) -> Result<()> {
+    let failures = cross_table_lookups
+        .iter()
+        .enumerate()
+        .filter_map(|(index, ctl)| {
+            let name = CTL_NAMES.get(index).copied().unwrap_or("unnamed");
+            // starky panics on the first unbalanced row of a lookup, with its
+            // values and locations.
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                if name == "range_check" {
+                    check_range_checks(trace_poly_values)
+                } else {
+                    debug_utils::check_ctls(
+                        trace_poly_values,
+                        std::slice::from_ref(ctl),
+                        extra_looking_values,
+                    )
+                }
+            }));
+            let payload = outcome.err()?;
+            let details = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown mismatch".to_string());
+            Some(CtlFailure {
+                index,
+                name,
+                tables: ctl_tables(ctl),
+                details,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} cross-table lookups are unbalanced:\n{}",
+            failures.len(),
+            failures.iter().join("\n")
+        ))
+    }
+}
+
+/// Checks that the multiplicities of the range-check table match the values
+/// range-checked by the looking tables, and panics on the first mismatch.
+fn check_range_checks<F: PrimeField64>(trace_poly_values: &[Vec<PolynomialValues<F>>]) {
+    let mut expected = RangeCheckMultiplicities::default();
+    arithmetic_stark::count_range_checks(&trace_poly_values[*Table::Arithmetic], &mut expected);
+    byte_packing_stark::count_range_checks(&trace_poly_values[*Table::BytePacking], &mut expected);
+    memory_stark::count_range_checks(&trace_poly_values[*Table::Memory], &mut expected);
+
+    let multiplicities = &trace_poly_values[*Table::RangeCheck][MULTIPLICITY].values;
+    if let Some((value, (&count, multiplicity))) = expected
+        .counts()
+        .iter()
+        .zip(multiplicities)
+        .enumerate()
+        .find(|(_, (&count, &multiplicity))| F::from_canonical_u64(count) != multiplicity)
+    {
+        panic!(
+            "value {} is range-checked {} times, but has multiplicity {}",
+            value, count, multiplicity
+        );
+    }
+}
+
+/// Returns the tables connected by `ctl`.
+fn ctl_tables<F: Field>(ctl: &CrossTableLookup<F>) -> Vec<Table> {
+    Table::all()
+        .into_iter()
+        .filter(|&table| {
+            // The number of Z polynomials doesn't depend on the degree.
+            let (_, num_ctl_zs, _) =
+                CrossTableLookup::num_ctl_helpers_zs_all(std::slice::from_ref(ctl), *table, 1, 3);
+            num_ctl_zs > 0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+
+    use super::*;
+    use crate::all_stark::all_cross_table_lookups;
+
+    #[test]
+    fn names_all_ctls() {
+        let ctls = all_cross_table_lookups::<GoldilocksField>();
+        assert_eq!(ctls.len(), CTL_NAMES.len());
+        assert_eq!(
+            ctl_tables(&ctls[0]),
+            vec![Table::Arithmetic, Table::Cpu, Table::Ec],
+            "{}",
+            CTL_NAMES[0]
+        );
+        assert_eq!(
+            ctl_tables(&ctls[7]),
+            vec![Table::Memory, Table::MemBefore],
+            "{}",
+            CTL_NAMES[7]
+        );
+    }
+}
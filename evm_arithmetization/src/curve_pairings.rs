@@ -5,7 +5,9 @@ use rand::distributions::Standard;
 use rand::prelude::Distribution;
 use rand::Rng;
 
-use crate::extension_tower::{Adj, FieldExt, Fp12, Fp2, Fp6, Stack, BLS381};
+use crate::extension_tower::{Adj, FieldExt, Fp2, Stack, BLS381};
+#[cfg(any(feature = "generation", test))]
+use crate::extension_tower::{Fp12, Fp6};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct CurveAff<T>
@@ -142,6 +144,7 @@ where
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct CurveProj<T>
 where
@@ -152,6 +155,7 @@ where
     pub z: T,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl<T: FieldExt + Stack> Stack for CurveProj<T> {
     const SIZE: usize = 3 * T::SIZE;
 
@@ -198,7 +202,7 @@ where
 }
 
 /// Generates a sparse, random Fp12 element.
-#[cfg(test)]
+#[cfg(all(test, feature = "generation"))]
 pub(crate) fn gen_fp12_sparse<F, R: Rng + ?Sized>(rng: &mut R) -> Fp12<F>
 where
     F: FieldExt,
@@ -567,11 +571,14 @@ pub mod bn254 {
 // The optimal Ate pairing implementation for BLS12-381 has been taken from
 // <https://github.com/zkcrypto/bls12_381>.
 pub mod bls381 {
+    #[cfg(feature = "generation")]
     use anyhow::{anyhow, Result};
 
     use super::*;
+    #[cfg(feature = "generation")]
     use crate::extension_tower::BLS_BASE;
 
+    #[cfg(feature = "generation")]
     const B_G1: BLS381 = BLS381 {
         val: U512([4, 0, 0, 0, 0, 0, 0, 0]),
     };
@@ -679,6 +686,7 @@ pub mod bls381 {
     /// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-04#appendix-A>,
     /// based on zkcrypto/bls12_381 serialization design notes available at
     /// <https://github.com/zkcrypto/bls12_381/blob/main/src/notes/serialization.rs>.
+    #[cfg(feature = "generation")]
     pub(crate) fn g1_from_bytes(bytes: &[u8; 48]) -> Result<CurveAff<BLS381>> {
         // Obtain the three flags from the start of the byte sequence
         let compression_flag_set = ((bytes[0] >> 7) & 1) != 0;
@@ -735,6 +743,7 @@ pub mod bls381 {
 
     // The optimal Ate pairing takes a point each from the curve and its twist and
     // outputs an Fp12 element.
+    #[cfg(any(feature = "generation", test))]
     pub(crate) fn ate_optim(p: CurveAff<BLS381>, q: CurveAff<Fp2<BLS381>>) -> Fp12<BLS381> {
         let miller_output = miller_loop(p, q);
         final_exponent(miller_output)
@@ -743,6 +752,7 @@ pub mod bls381 {
     /// Miller loop for the optimal Ate pairing, which computes $f_{u,Q}(P)$
     /// with the accumulator as a point on the twist, before exponentiating
     /// by $(p^12 - 1)/r$ with $r$ the order of the multiplicative target group.
+    #[cfg(any(feature = "generation", test))]
     pub(crate) fn miller_loop(p: CurveAff<BLS381>, q: CurveAff<Fp2<BLS381>>) -> Fp12<BLS381> {
         let mut r = CurveProj::<Fp2<BLS381>> {
             x: q.x,
@@ -774,6 +784,7 @@ pub mod bls381 {
         acc.conj() // X_GENERATOR is negative
     }
 
+    #[cfg(any(feature = "generation", test))]
     fn ell(
         f: Fp12<BLS381>,
         coeffs: &(Fp2<BLS381>, Fp2<BLS381>, Fp2<BLS381>),
@@ -791,6 +802,7 @@ pub mod bls381 {
         f.mul_by_014(coeffs.2, c1, c0)
     }
 
+    #[cfg(any(feature = "generation", test))]
     fn doubling_step(r: &mut CurveProj<Fp2<BLS381>>) -> (Fp2<BLS381>, Fp2<BLS381>, Fp2<BLS381>) {
         // Adaptation of Algorithm 26, https://eprint.iacr.org/2010/354.pdf
         let tmp0 = r.x * r.x;
@@ -822,6 +834,7 @@ pub mod bls381 {
         (tmp0, tmp3, tmp6)
     }
 
+    #[cfg(any(feature = "generation", test))]
     fn addition_step(
         r: &mut CurveProj<Fp2<BLS381>>,
         q: &CurveAff<Fp2<BLS381>>,
@@ -863,6 +876,7 @@ pub mod bls381 {
     /// where N is the cyclic group order of the curve.
     ///
     /// See section 5 of <https://eprint.iacr.org/2020/875.pdf>.
+    #[cfg(any(feature = "generation", test))]
     pub(crate) fn final_exponent(f: Fp12<BLS381>) -> Fp12<BLS381> {
         let mut t0 = f.frob(6);
         let mut t1 = f.inv();
@@ -894,6 +908,7 @@ pub mod bls381 {
         t3 * t4
     }
 
+    #[cfg(any(feature = "generation", test))]
     fn fp4_square(a: Fp2<BLS381>, b: Fp2<BLS381>) -> (Fp2<BLS381>, Fp2<BLS381>) {
         let t0 = a * a;
         let t1 = b * b;
@@ -909,6 +924,7 @@ pub mod bls381 {
     // Adaptation of Algorithm 5.5.4, Guide to Pairing-Based Cryptography
     // Faster Squaring in the Cyclotomic Subgroup of Sixth Degree Extensions
     // <https://eprint.iacr.org/2009/565.pdf>.
+    #[cfg(any(feature = "generation", test))]
     fn cyclotomic_square(f: Fp12<BLS381>) -> Fp12<BLS381> {
         let mut z0 = f.z0.t0;
         let mut z4 = f.z0.t1;
@@ -958,6 +974,7 @@ pub mod bls381 {
         }
     }
 
+    #[cfg(any(feature = "generation", test))]
     fn cyclotomic_exp(f: Fp12<BLS381>) -> Fp12<BLS381> {
         let mut tmp = Fp12::<BLS381>::UNIT;
 
@@ -980,6 +997,7 @@ pub mod bls381 {
     /// The value used to generate both scalar and base fields of BLS12-381.
     /// Note that `x` is negative, and the Miller loop hence require a final
     /// conjugation in Fp12.
+    #[cfg(any(feature = "generation", test))]
     const X_GENERATOR: u64 = 0xd201000000010000;
 }
 
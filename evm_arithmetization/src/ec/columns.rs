@@ -0,0 +1,112 @@
+use core::mem::{size_of, transmute};
+
+use zk_evm_proc_macro::Columns;
+
+use crate::util::indices_arr;
+
+/// Number of 32-bit limbs of a base field element.
+pub(crate) const N_LIMBS: usize = 8;
+
+/// A view of `EcStark`'s columns.
+///
+/// Each row computes the affine sum `(x2, y2) = (x0, y0) + (x1, y1)`, where
+/// both points are equal for a doubling, through the slope `lambda`:
+///
+/// - `lambda * a = b`, with `a = x1 - x0` and `b = y1 - y0` for an addition, or
+///   `a = 2 * y0` and `b = 3 * x0^2` for a doubling,
+/// - `x2 = lambda^2 - x0 - x1`,
+/// - `y2 = lambda * (x0 - x2) - y0`.
+///
+/// A scalar multiplication `k * P` spans several consecutive rows, doubling
+/// and adding `P` to an accumulator `k_acc * P` from the most significant bit
+/// of `k`. Its first row doubles `P`, with `k_acc = 1`, each row takes the
+/// previous result as its first point, and `k_next = k` on its last row.
+/// Scalars are computed modulo the order of the curve's group.
+///
+/// All field operations are looked up in the `ArithmeticStark`, which also
+/// range-checks the limbs of all the values below.
+#[repr(C)]
+#[derive(Columns, Eq, PartialEq, Debug)]
+pub(crate) struct EcColumnsView<T: Copy> {
+    /// 1 if this row adds two distinct points, 0 otherwise.
+    pub is_add: T,
+    /// 1 if this row doubles a point, 0 otherwise.
+    pub is_double: T,
+    /// 1 if this row adds `P` to the accumulator of a scalar multiplication,
+    /// 0 otherwise.
+    pub is_mul_add: T,
+    /// 1 if this row doubles the accumulator of a scalar multiplication, 0
+    /// otherwise.
+    pub is_mul_double: T,
+    /// 1 on the first row of a scalar multiplication, 0 otherwise.
+    pub mul_first: T,
+    /// 1 on the last row of a scalar multiplication, 0 otherwise.
+    pub mul_last: T,
+    /// 1 if the points are on the BN254 curve, 0 if they are on secp256k1.
+    pub is_bn254: T,
+
+    /// The context of the base address at which the points are read.
+    pub context: T,
+    /// The segment of the base address at which the points are read.
+    pub segment: T,
+    /// The virtual base address at which the points are read. The result is
+    /// written right after the input points, or after the point and the
+    /// scalar of a scalar multiplication.
+    pub virt: T,
+
+    /// The timestamp at which the points are read and the result written.
+    pub timestamp: T,
+
+    /// The coordinates of the first point.
+    pub x0: [T; N_LIMBS],
+    pub y0: [T; N_LIMBS],
+    /// The coordinates of the second point, equal to the first one when
+    /// doubling.
+    pub x1: [T; N_LIMBS],
+    pub y1: [T; N_LIMBS],
+
+    /// The slope of the line through both points, or of the tangent.
+    pub lambda: [T; N_LIMBS],
+    /// The coordinates of the result.
+    pub x2: [T; N_LIMBS],
+    pub y2: [T; N_LIMBS],
+
+    /// The denominator and numerator of `lambda`.
+    pub a: [T; N_LIMBS],
+    pub b: [T; N_LIMBS],
+    /// `x0^2`, only used when doubling.
+    pub x0_squared: [T; N_LIMBS],
+    /// `lambda^2`.
+    pub lambda_squared: [T; N_LIMBS],
+    /// `lambda^2 - x0`.
+    pub x2_partial: [T; N_LIMBS],
+    /// `x0 - x2`.
+    pub x_diff: [T; N_LIMBS],
+    /// `lambda * (x0 - x2)`.
+    pub y2_partial: [T; N_LIMBS],
+
+    /// The point `P` and the scalar `k` of a scalar multiplication.
+    pub px: [T; N_LIMBS],
+    pub py: [T; N_LIMBS],
+    pub k: [T; N_LIMBS],
+    /// The scalars of the accumulator before and after this row of a scalar
+    /// multiplication.
+    pub k_acc: [T; N_LIMBS],
+    pub k_next: [T; N_LIMBS],
+
+    /// The inverse of the sum of the limbs of `a`, ensuring that `a` is
+    /// nonzero.
+    pub a_limbs_sum_inv: T,
+}
+
+// `u8` is guaranteed to have a `size_of` of 1.
+/// Number of columns in `EcStark`.
+pub(crate) const NUM_EC_COLUMNS: usize = size_of::<EcColumnsView<u8>>();
+
+const fn make_col_map() -> EcColumnsView<usize> {
+    let indices_arr = indices_arr::<NUM_EC_COLUMNS>();
+    unsafe { transmute::<[usize; NUM_EC_COLUMNS], EcColumnsView<usize>>(indices_arr) }
+}
+
+/// Map between the `Ec` columns and (0..`NUM_EC_COLUMNS`)
+pub(crate) const EC_COL_MAP: EcColumnsView<usize> = make_col_map();
@@ -0,0 +1,1045 @@
+use core::borrow::Borrow;
+use core::marker::PhantomData;
+
+use ethereum_types::U256;
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+#[cfg(feature = "generation")]
+use plonky2::timed;
+#[cfg(feature = "generation")]
+use plonky2::util::timing::TimingTree;
+use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use starky::cross_table_lookup::TableWithColumns;
+use starky::evaluation_frame::StarkEvaluationFrame;
+use starky::lookup::{Column, Filter, Lookup};
+use starky::stark::Stark;
+#[cfg(feature = "generation")]
+use starky::util::trace_rows_to_poly_values;
+
+use crate::all_stark::{EvmStarkFrame, Table};
+#[cfg(feature = "generation")]
+use crate::arithmetic::{self, TernaryOperator};
+use crate::ec::columns::*;
+use crate::extension_tower::BN_BASE;
+#[cfg(feature = "generation")]
+use crate::util::{addmod, mulmod, submod};
+use crate::util::{biguint_to_u256, u256_limbs, u256_to_biguint};
+use crate::witness::errors::ProgramError;
+#[cfg(feature = "generation")]
+use crate::witness::memory::MemoryAddress;
+
+/// The order of the secp256k1 base field.
+pub(crate) const SECP256K1_BASE: U256 = U256([
+    0xfffffffefffffc2f,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+]);
+
+/// The order of the secp256k1 group.
+pub(crate) const SECP256K1_SCALAR: U256 = U256([
+    0xbfd25e8cd0364141,
+    0xbaaedce6af48a03b,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+]);
+
+/// The order of the BN254 G1 group.
+pub(crate) const BN254_SCALAR: U256 = U256([
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+]);
+
+/// The curves whose points are operated on by the `EcStark`. Both are
+/// short Weierstrass curves `y^2 = x^3 + b`, sharing the same affine formulas.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Curve {
+    Secp256k1,
+    Bn254,
+}
+
+impl Curve {
+    /// Returns the order of the base field of the curve.
+    pub(crate) const fn base(self) -> U256 {
+        match self {
+            Self::Secp256k1 => SECP256K1_BASE,
+            Self::Bn254 => BN_BASE,
+        }
+    }
+
+    /// Returns the order of the group of points of the curve, which is prime.
+    pub(crate) const fn order(self) -> U256 {
+        match self {
+            Self::Secp256k1 => SECP256K1_SCALAR,
+            Self::Bn254 => BN254_SCALAR,
+        }
+    }
+}
+
+/// The point operations carried out by the `EcStark`.
+#[cfg(feature = "generation")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum EcOpKind {
+    Add,
+    Double,
+    Mul,
+}
+
+/// An addition or doubling of points of a [`Curve`], along with all the
+/// intermediate values of its affine formulas. A scalar multiplication is a
+/// sequence of such operations, see [`EcOp::mul`].
+#[cfg(feature = "generation")]
+#[derive(Clone, Debug)]
+pub(crate) struct EcOp {
+    pub(crate) curve: Curve,
+    pub(crate) is_double: bool,
+
+    /// The base address at which the input points are read.
+    pub(crate) base_address: MemoryAddress,
+
+    /// The timestamp at which the input points are read.
+    pub(crate) timestamp: usize,
+
+    /// The step of the scalar multiplication this operation belongs to, if
+    /// any.
+    mul: Option<MulStep>,
+
+    x0: U256,
+    y0: U256,
+    x1: U256,
+    y1: U256,
+    lambda: U256,
+    x2: U256,
+    y2: U256,
+    a: U256,
+    b: U256,
+    x0_squared: U256,
+    lambda_squared: U256,
+    x2_partial: U256,
+    x_diff: U256,
+    y2_partial: U256,
+}
+
+/// A step `k_next * P = 2 * (k_acc * P)` or `k_next * P = k_acc * P + P` of
+/// the scalar multiplication `k * P`.
+#[derive(Clone, Debug)]
+struct MulStep {
+    point: (U256, U256),
+    scalar: U256,
+    acc: U256,
+    next_acc: U256,
+    is_first: bool,
+    is_last: bool,
+}
+
+#[cfg(feature = "generation")]
+impl EcOp {
+    /// Adds two points with distinct x-coordinates, neither of which is the
+    /// point at infinity.
+    pub(crate) fn add(
+        curve: Curve,
+        base_address: MemoryAddress,
+        timestamp: usize,
+        (x0, y0): (U256, U256),
+        (x1, y1): (U256, U256),
+    ) -> Result<Self, ProgramError> {
+        let p = curve.base();
+        let a = submod(x1, x0, p);
+        let b = submod(y1, y0, p);
+        Self::with_slope(
+            curve,
+            false,
+            base_address,
+            timestamp,
+            [x0, y0, x1, y1],
+            [a, b, U256::zero()],
+        )
+    }
+
+    /// Doubles a point which isn't the point at infinity.
+    pub(crate) fn double(
+        curve: Curve,
+        base_address: MemoryAddress,
+        timestamp: usize,
+        (x, y): (U256, U256),
+    ) -> Result<Self, ProgramError> {
+        let p = curve.base();
+        let x_squared = mulmod(x, x, p);
+        let a = addmod(y, y, p);
+        let b = mulmod(x_squared, 3.into(), p);
+        Self::with_slope(
+            curve,
+            true,
+            base_address,
+            timestamp,
+            [x, y, x, y],
+            [a, b, x_squared],
+        )
+    }
+
+    /// Multiplies a point of the curve, other than the point at infinity, by a
+    /// scalar `2 <= k < n`, where `n` is the order of the group. The point is
+    /// doubled and added to an accumulator from the most significant bit of
+    /// `k`, so that no step hits the point at infinity or adds two points with
+    /// the same x-coordinate. Returns the steps, the last of which outputs
+    /// `k * P`.
+    pub(crate) fn mul(
+        curve: Curve,
+        base_address: MemoryAddress,
+        timestamp: usize,
+        point: (U256, U256),
+        scalar: U256,
+    ) -> Result<Vec<Self>, ProgramError> {
+        let n = curve.order();
+        if scalar < 2.into() || scalar >= n {
+            return Err(ProgramError::InvalidEcOperation);
+        }
+
+        let num_bits = scalar.bits();
+        let mut steps = Vec::with_capacity(2 * num_bits);
+        let mut acc_point = point;
+        let mut acc = U256::one();
+        for i in (0..num_bits - 1).rev() {
+            let is_first = steps.is_empty();
+            let next_acc = addmod(acc, acc, n);
+            let step = Self::double(curve, base_address, timestamp, acc_point)?
+                .with_mul_step(point, scalar, acc, next_acc, is_first);
+            (acc_point, acc) = (step.output(), next_acc);
+            steps.push(step);
+
+            if scalar.bit(i) {
+                let next_acc = addmod(acc, U256::one(), n);
+                let step = Self::add(curve, base_address, timestamp, acc_point, point)?
+                    .with_mul_step(point, scalar, acc, next_acc, false);
+                (acc_point, acc) = (step.output(), next_acc);
+                steps.push(step);
+            }
+        }
+
+        if let Some(mul) = steps.last_mut().and_then(|step| step.mul.as_mut()) {
+            mul.is_last = true;
+        }
+        Ok(steps)
+    }
+
+    fn with_mul_step(
+        self,
+        point: (U256, U256),
+        scalar: U256,
+        acc: U256,
+        next_acc: U256,
+        is_first: bool,
+    ) -> Self {
+        Self {
+            mul: Some(MulStep {
+                point,
+                scalar,
+                acc,
+                next_acc,
+                is_first,
+                is_last: false,
+            }),
+            ..self
+        }
+    }
+
+    fn with_slope(
+        curve: Curve,
+        is_double: bool,
+        base_address: MemoryAddress,
+        timestamp: usize,
+        [x0, y0, x1, y1]: [U256; 4],
+        [a, b, x0_squared]: [U256; 3],
+    ) -> Result<Self, ProgramError> {
+        let p = curve.base();
+        let lambda = mulmod(b, inverse(a, p)?, p);
+        let lambda_squared = mulmod(lambda, lambda, p);
+        let x2_partial = submod(lambda_squared, x0, p);
+        let x2 = submod(x2_partial, x1, p);
+        let x_diff = submod(x0, x2, p);
+        let y2_partial = mulmod(lambda, x_diff, p);
+        let y2 = submod(y2_partial, y0, p);
+        Ok(Self {
+            curve,
+            is_double,
+            base_address,
+            timestamp,
+            mul: None,
+            x0,
+            y0,
+            x1,
+            y1,
+            lambda,
+            x2,
+            y2,
+            a,
+            b,
+            x0_squared,
+            lambda_squared,
+            x2_partial,
+            x_diff,
+            y2_partial,
+        })
+    }
+
+    /// Returns the coordinates of the result.
+    pub(crate) const fn output(&self) -> (U256, U256) {
+        (self.x2, self.y2)
+    }
+
+    /// Returns the field operations of this point operation, looked up in the
+    /// `ArithmeticStark`.
+    pub(crate) fn arithmetic_ops(&self) -> Vec<arithmetic::Operation> {
+        use TernaryOperator::*;
+
+        let p = self.curve.base();
+        let op =
+            |operator, input0, input1| arithmetic::Operation::ternary(operator, input0, input1, p);
+        let slope_ops = if self.is_double {
+            vec![
+                op(AddMod, self.y0, self.y0),
+                op(MulMod, self.x0, self.x0),
+                op(MulMod, self.x0_squared, 3.into()),
+            ]
+        } else {
+            vec![op(SubMod, self.x1, self.x0), op(SubMod, self.y1, self.y0)]
+        };
+        // The accumulated scalar is doubled or incremented modulo the group
+        // order.
+        let scalar_ops = self.mul.as_ref().map(|mul| {
+            let increment = if self.is_double { mul.acc } else { U256::one() };
+            arithmetic::Operation::ternary(AddMod, mul.acc, increment, self.curve.order())
+        });
+        slope_ops
+            .into_iter()
+            .chain([
+                op(MulMod, self.lambda, self.a),
+                op(MulMod, self.lambda, self.lambda),
+                op(SubMod, self.lambda_squared, self.x0),
+                op(SubMod, self.x2_partial, self.x1),
+                op(SubMod, self.x0, self.x2),
+                op(MulMod, self.lambda, self.x_diff),
+                op(SubMod, self.y2_partial, self.y0),
+            ])
+            .chain(scalar_ops)
+            .collect()
+    }
+}
+
+/// Returns the inverse of `x` modulo the prime `p`, or an error if `x` is
+/// zero, i.e. if the kernel calls an addition on points with the same
+/// x-coordinate, or a doubling on a point of order 2.
+fn inverse(x: U256, p: U256) -> Result<U256, ProgramError> {
+    if x.is_zero() {
+        return Err(ProgramError::InvalidEcOperation);
+    }
+    let p = u256_to_biguint(p);
+    Ok(biguint_to_u256(u256_to_biguint(x).modpow(&(&p - 2u32), &p)))
+}
+
+/// Opcodes of the field operations looked up in the `ArithmeticStark`.
+const ADDMOD: u8 = 0x08;
+const MULMOD: u8 = 0x09;
+const SUBMOD: u8 = 0x0f;
+
+/// Creates the vector of `Columns` corresponding to:
+/// - whether the operation is a doubling, i.e. the lowest bit of its opcode,
+/// - whether it is on BN254, i.e. the second bit of its opcode,
+/// - whether it is a scalar multiplication, i.e. the third bit of its opcode,
+/// - the base address of the input points,
+/// - the timestamp at which the input points are read.
+pub(crate) fn ctl_looked_data<F: Field>() -> Vec<Column<F>> {
+    let cols = EC_COL_MAP;
+    Column::singles([
+        cols.is_double,
+        cols.is_bn254,
+        cols.mul_first,
+        cols.context,
+        cols.segment,
+        cols.virt,
+        cols.timestamp,
+    ])
+    .collect()
+}
+
+/// CTL filter for the rows carrying out a point operation called by the CPU,
+/// i.e. a standalone addition or doubling, or the first row of a scalar
+/// multiplication.
+pub(crate) fn ctl_looked_filter<F: Field>() -> Filter<F> {
+    let cols = EC_COL_MAP;
+    Filter::new_simple(Column::sum([cols.is_add, cols.is_double, cols.mul_first]))
+}
+
+/// Returns the `TableWithColumns` of all the field operations of a row, looked
+/// up in the `ArithmeticStark`, in the order of
+/// [`EcOp::arithmetic_ops`].
+pub(crate) fn ctl_looking_arithmetic<F: Field>() -> Vec<TableWithColumns<F>> {
+    let cols = EC_COL_MAP;
+    let limbs = |limb_cols: [usize; N_LIMBS]| Column::singles(limb_cols).collect::<Vec<_>>();
+    let constant = |value: u32| u256_limbs::<F>(value.into()).map(Column::constant).to_vec();
+
+    let is_adding = || Filter::new_simple(Column::sum([cols.is_add, cols.is_mul_add]));
+    let is_doubling = || Filter::new_simple(Column::sum([cols.is_double, cols.is_mul_double]));
+    let is_op = || {
+        Filter::new_simple(Column::sum([
+            cols.is_add,
+            cols.is_double,
+            cols.is_mul_add,
+            cols.is_mul_double,
+        ]))
+    };
+    let base_op = |opcode, input0, input1, output, filter| {
+        field_op(opcode, input0, input1, output, filter, Curve::base)
+    };
+
+    vec![
+        // `a = x1 - x0` and `b = y1 - y0` when adding.
+        base_op(SUBMOD, limbs(cols.x1), limbs(cols.x0), cols.a, is_adding()),
+        base_op(SUBMOD, limbs(cols.y1), limbs(cols.y0), cols.b, is_adding()),
+        // `a = 2 * y0` and `b = 3 * x0^2` when doubling.
+        base_op(
+            ADDMOD,
+            limbs(cols.y0),
+            limbs(cols.y0),
+            cols.a,
+            is_doubling(),
+        ),
+        base_op(
+            MULMOD,
+            limbs(cols.x0),
+            limbs(cols.x0),
+            cols.x0_squared,
+            is_doubling(),
+        ),
+        base_op(
+            MULMOD,
+            limbs(cols.x0_squared),
+            constant(3),
+            cols.b,
+            is_doubling(),
+        ),
+        // `lambda * a = b`, where `a` is nonzero.
+        base_op(MULMOD, limbs(cols.lambda), limbs(cols.a), cols.b, is_op()),
+        // `x2 = lambda^2 - x0 - x1`.
+        base_op(
+            MULMOD,
+            limbs(cols.lambda),
+            limbs(cols.lambda),
+            cols.lambda_squared,
+            is_op(),
+        ),
+        base_op(
+            SUBMOD,
+            limbs(cols.lambda_squared),
+            limbs(cols.x0),
+            cols.x2_partial,
+            is_op(),
+        ),
+        base_op(
+            SUBMOD,
+            limbs(cols.x2_partial),
+            limbs(cols.x1),
+            cols.x2,
+            is_op(),
+        ),
+        // `y2 = lambda * (x0 - x2) - y0`.
+        base_op(SUBMOD, limbs(cols.x0), limbs(cols.x2), cols.x_diff, is_op()),
+        base_op(
+            MULMOD,
+            limbs(cols.lambda),
+            limbs(cols.x_diff),
+            cols.y2_partial,
+            is_op(),
+        ),
+        base_op(
+            SUBMOD,
+            limbs(cols.y2_partial),
+            limbs(cols.y0),
+            cols.y2,
+            is_op(),
+        ),
+        // `k_next = 2 * k_acc` or `k_next = k_acc + 1` in a scalar
+        // multiplication, modulo the group order.
+        field_op(
+            ADDMOD,
+            limbs(cols.k_acc),
+            limbs(cols.k_acc),
+            cols.k_next,
+            Filter::new_simple(Column::single(cols.is_mul_double)),
+            Curve::order,
+        ),
+        field_op(
+            ADDMOD,
+            limbs(cols.k_acc),
+            constant(1),
+            cols.k_next,
+            Filter::new_simple(Column::single(cols.is_mul_add)),
+            Curve::order,
+        ),
+    ]
+}
+
+/// Returns the `TableWithColumns` of a field operation modulo the given
+/// modulus of the row's curve, in the format of the arithmetic CTL: the
+/// opcode, the three inputs and the output.
+fn field_op<F: Field>(
+    opcode: u8,
+    input0: Vec<Column<F>>,
+    input1: Vec<Column<F>>,
+    output: [usize; N_LIMBS],
+    filter: Filter<F>,
+    modulus: fn(Curve) -> U256,
+) -> TableWithColumns<F> {
+    let mut res = vec![Column::constant(F::from_canonical_u8(opcode))];
+    res.extend(input0);
+    res.extend(input1);
+    let secp_limbs = u256_limbs::<F>(modulus(Curve::Secp256k1));
+    let bn_limbs = u256_limbs::<F>(modulus(Curve::Bn254));
+    res.extend((0..N_LIMBS).map(|i| {
+        Column::linear_combination_with_constant(
+            [(EC_COL_MAP.is_bn254, bn_limbs[i] - secp_limbs[i])],
+            secp_limbs[i],
+        )
+    }));
+    res.extend(Column::singles(output));
+    TableWithColumns::new(*Table::Ec, res, filter)
+}
+
+/// Returns the `TableWithColumns` of the memory accesses of a row: the reads
+/// of the inputs, followed by the writes of the result.
+pub(crate) fn ctl_looking_memory<F: Field>() -> Vec<TableWithColumns<F>> {
+    let cols = EC_COL_MAP;
+    let access = |is_read: bool, virt: Column<F>, value: [usize; N_LIMBS], filter: Filter<F>| {
+        let mut res = vec![Column::constant(F::from_bool(is_read))];
+        res.extend(Column::singles([cols.context, cols.segment]));
+        res.push(virt);
+        res.extend(Column::singles(value));
+        res.push(Column::single(cols.timestamp));
+        assert_eq!(
+            res.len(),
+            crate::memory::memory_stark::ctl_data::<F>().len()
+        );
+        TableWithColumns::new(*Table::Ec, res, filter)
+    };
+    let input_virt = |i: usize| {
+        Column::linear_combination_with_constant([(cols.virt, F::ONE)], F::from_canonical_usize(i))
+    };
+    // The result is written after the four input words of an addition, the
+    // two of a doubling, or the three of a scalar multiplication.
+    let output_virt = |i: usize| {
+        Column::linear_combination_with_constant(
+            [
+                (cols.virt, F::ONE),
+                (cols.is_add, F::TWO),
+                (cols.mul_last, F::ONE),
+            ],
+            F::from_canonical_usize(2 + i),
+        )
+    };
+
+    let is_add = || Filter::new_simple(Column::single(cols.is_add));
+    let mul_first = || Filter::new_simple(Column::single(cols.mul_first));
+    let reads_point = ctl_looked_filter::<F>;
+    let writes_result =
+        || Filter::new_simple(Column::sum([cols.is_add, cols.is_double, cols.mul_last]));
+
+    vec![
+        access(true, input_virt(0), cols.x0, reads_point()),
+        access(true, input_virt(1), cols.y0, reads_point()),
+        access(true, input_virt(2), cols.x1, is_add()),
+        access(true, input_virt(3), cols.y1, is_add()),
+        access(true, input_virt(2), cols.k, mul_first()),
+        access(false, output_virt(0), cols.x2, writes_result()),
+        access(false, output_virt(1), cols.y2, writes_result()),
+    ]
+}
+
+/// Structure representing the `Ec` STARK, which carries out the additions,
+/// doublings and scalar multiplications of secp256k1 and BN254 points.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct EcStark<F, const D: usize> {
+    f: PhantomData<F>,
+}
+
+#[cfg(feature = "generation")]
+impl<F: RichField + Extendable<D>, const D: usize> EcStark<F, D> {
+    /// Generates the trace of the given point operations, with one row per
+    /// operation. The trace is padded to a power of two with all-zero rows.
+    pub(crate) fn generate_trace(
+        &self,
+        operations: Vec<EcOp>,
+        min_rows: usize,
+        timing: &mut TimingTree,
+    ) -> Vec<PolynomialValues<F>> {
+        let trace_rows = timed!(
+            timing,
+            "generate trace rows",
+            self.generate_trace_rows(operations, min_rows)
+        );
+        trace_rows_to_poly_values(trace_rows)
+    }
+
+    fn generate_trace_rows(
+        &self,
+        operations: Vec<EcOp>,
+        min_rows: usize,
+    ) -> Vec<[F; NUM_EC_COLUMNS]> {
+        let num_rows = operations.len().max(min_rows).next_power_of_two();
+        let mut rows = Vec::with_capacity(num_rows);
+        for op in operations {
+            rows.push(self.generate_row(op).into());
+        }
+        rows.resize(num_rows, [F::ZERO; NUM_EC_COLUMNS]);
+        rows
+    }
+
+    fn generate_row(&self, op: EcOp) -> EcColumnsView<F> {
+        let mut row = EcColumnsView::default();
+        let is_mul = op.mul.is_some();
+        row.is_add = F::from_bool(!is_mul && !op.is_double);
+        row.is_double = F::from_bool(!is_mul && op.is_double);
+        row.is_mul_add = F::from_bool(is_mul && !op.is_double);
+        row.is_mul_double = F::from_bool(is_mul && op.is_double);
+        row.is_bn254 = F::from_bool(op.curve == Curve::Bn254);
+        row.context = F::from_canonical_usize(op.base_address.context);
+        row.segment = F::from_canonical_usize(op.base_address.segment);
+        row.virt = F::from_canonical_usize(op.base_address.virt);
+        row.timestamp = F::from_canonical_usize(op.timestamp);
+
+        row.x0 = u256_limbs(op.x0);
+        row.y0 = u256_limbs(op.y0);
+        row.x1 = u256_limbs(op.x1);
+        row.y1 = u256_limbs(op.y1);
+        row.lambda = u256_limbs(op.lambda);
+        row.x2 = u256_limbs(op.x2);
+        row.y2 = u256_limbs(op.y2);
+        row.a = u256_limbs(op.a);
+        row.b = u256_limbs(op.b);
+        row.x0_squared = u256_limbs(op.x0_squared);
+        row.lambda_squared = u256_limbs(op.lambda_squared);
+        row.x2_partial = u256_limbs(op.x2_partial);
+        row.x_diff = u256_limbs(op.x_diff);
+        row.y2_partial = u256_limbs(op.y2_partial);
+
+        if let Some(mul) = op.mul {
+            row.mul_first = F::from_bool(mul.is_first);
+            row.mul_last = F::from_bool(mul.is_last);
+            row.px = u256_limbs(mul.point.0);
+            row.py = u256_limbs(mul.point.1);
+            row.k = u256_limbs(mul.scalar);
+            row.k_acc = u256_limbs(mul.acc);
+            row.k_next = u256_limbs(mul.next_acc);
+        }
+
+        let a_limbs_sum: F = row.a.iter().copied().sum();
+        row.a_limbs_sum_inv = a_limbs_sum.inverse();
+        row
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for EcStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize> = EvmStarkFrame<P, FE, NUM_EC_COLUMNS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        EvmStarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, NUM_EC_COLUMNS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let local_values: &[P; NUM_EC_COLUMNS] = vars.get_local_values().try_into().unwrap();
+        let local_values: &EcColumnsView<P> = local_values.borrow();
+        let next_values: &[P; NUM_EC_COLUMNS] = vars.get_next_values().try_into().unwrap();
+        let next_values: &EcColumnsView<P> = next_values.borrow();
+
+        // The operation flags are binary, and exclusive.
+        let is_add = local_values.is_add;
+        let is_double = local_values.is_double;
+        let is_mul_add = local_values.is_mul_add;
+        let is_mul_double = local_values.is_mul_double;
+        let is_mul = is_mul_add + is_mul_double;
+        let is_doubling = is_double + is_mul_double;
+        let is_op = is_add + is_double + is_mul;
+        for flag in [is_add, is_double, is_mul_add, is_mul_double, is_op] {
+            yield_constr.constraint(flag * (flag - P::ONES));
+        }
+
+        // The first row of a scalar multiplication doubles, and its last row
+        // belongs to it.
+        let mul_first = local_values.mul_first;
+        let mul_last = local_values.mul_last;
+        yield_constr.constraint(mul_first * (is_mul_double - P::ONES));
+        yield_constr.constraint(mul_last * (is_mul - P::ONES));
+
+        // The curve flag is binary.
+        let is_bn254 = local_values.is_bn254;
+        yield_constr.constraint(is_bn254 * (is_bn254 - P::ONES));
+
+        for i in 0..N_LIMBS {
+            // When doubling, the second point is the first one.
+            yield_constr.constraint(is_doubling * (local_values.x1[i] - local_values.x0[i]));
+            yield_constr.constraint(is_doubling * (local_values.y1[i] - local_values.y0[i]));
+            // A scalar multiplication adds `P` to its accumulator, which starts
+            // as `P`.
+            yield_constr.constraint(is_mul_add * (local_values.x1[i] - local_values.px[i]));
+            yield_constr.constraint(is_mul_add * (local_values.y1[i] - local_values.py[i]));
+            yield_constr.constraint(mul_first * (local_values.x0[i] - local_values.px[i]));
+            yield_constr.constraint(mul_first * (local_values.y0[i] - local_values.py[i]));
+            // Its accumulated scalar starts at 1, and ends at `k`.
+            let one = if i == 0 { P::ONES } else { P::ZEROS };
+            yield_constr.constraint(mul_first * (local_values.k_acc[i] - one));
+            yield_constr.constraint(mul_last * (local_values.k_next[i] - local_values.k[i]));
+        }
+
+        // `a` is nonzero, so that `lambda` is the only solution of
+        // `lambda * a = b`. Its limbs are range-checked through the arithmetic
+        // CTL, so that their sum doesn't wrap around.
+        let a_limbs_sum: P = local_values.a.iter().copied().sum();
+        yield_constr.constraint(is_op * (a_limbs_sum * local_values.a_limbs_sum_inv - P::ONES));
+
+        // A scalar multiplication spans consecutive rows, from its first to its
+        // last one.
+        let is_inner = is_mul - mul_last;
+        let next_is_continued =
+            next_values.is_mul_add + next_values.is_mul_double - next_values.mul_first;
+        yield_constr.constraint_first_row(is_mul - mul_first);
+        yield_constr.constraint_last_row(is_mul - mul_last);
+        yield_constr.constraint_transition(next_is_continued * (is_inner - P::ONES));
+        yield_constr.constraint_transition(is_inner * (next_is_continued - P::ONES));
+
+        // Each of its rows takes the previous result as first point, and keeps
+        // the same inputs.
+        for i in 0..N_LIMBS {
+            for (next, local) in [
+                (next_values.x0[i], local_values.x2[i]),
+                (next_values.y0[i], local_values.y2[i]),
+                (next_values.k_acc[i], local_values.k_next[i]),
+                (next_values.px[i], local_values.px[i]),
+                (next_values.py[i], local_values.py[i]),
+                (next_values.k[i], local_values.k[i]),
+            ] {
+                yield_constr.constraint_transition(next_is_continued * (next - local));
+            }
+        }
+        for (next, local) in [
+            (next_values.is_bn254, local_values.is_bn254),
+            (next_values.context, local_values.context),
+            (next_values.segment, local_values.segment),
+            (next_values.virt, local_values.virt),
+            (next_values.timestamp, local_values.timestamp),
+        ] {
+            yield_constr.constraint_transition(next_is_continued * (next - local));
+        }
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let local_values: &[ExtensionTarget<D>; NUM_EC_COLUMNS] =
+            vars.get_local_values().try_into().unwrap();
+        let local_values: &EcColumnsView<ExtensionTarget<D>> = local_values.borrow();
+        let next_values: &[ExtensionTarget<D>; NUM_EC_COLUMNS] =
+            vars.get_next_values().try_into().unwrap();
+        let next_values: &EcColumnsView<ExtensionTarget<D>> = next_values.borrow();
+
+        // The operation flags are binary, and exclusive.
+        let is_add = local_values.is_add;
+        let is_double = local_values.is_double;
+        let is_mul_add = local_values.is_mul_add;
+        let is_mul_double = local_values.is_mul_double;
+        let is_mul = builder.add_extension(is_mul_add, is_mul_double);
+        let is_doubling = builder.add_extension(is_double, is_mul_double);
+        let is_op = builder.add_many_extension([is_add, is_double, is_mul]);
+        for flag in [is_add, is_double, is_mul_add, is_mul_double, is_op] {
+            let constraint = builder.mul_sub_extension(flag, flag, flag);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        // The first row of a scalar multiplication doubles, and its last row
+        // belongs to it.
+        let mul_first = local_values.mul_first;
+        let mul_last = local_values.mul_last;
+        let constraint = builder.mul_sub_extension(mul_first, is_mul_double, mul_first);
+        yield_constr.constraint(builder, constraint);
+        let constraint = builder.mul_sub_extension(mul_last, is_mul, mul_last);
+        yield_constr.constraint(builder, constraint);
+
+        // The curve flag is binary.
+        let is_bn254 = local_values.is_bn254;
+        let constraint = builder.mul_sub_extension(is_bn254, is_bn254, is_bn254);
+        yield_constr.constraint(builder, constraint);
+
+        let one = builder.one_extension();
+        for i in 0..N_LIMBS {
+            for (filter, left, right) in [
+                // When doubling, the second point is the first one.
+                (is_doubling, local_values.x1[i], local_values.x0[i]),
+                (is_doubling, local_values.y1[i], local_values.y0[i]),
+                // A scalar multiplication adds `P` to its accumulator, which
+                // starts as `P`.
+                (is_mul_add, local_values.x1[i], local_values.px[i]),
+                (is_mul_add, local_values.y1[i], local_values.py[i]),
+                (mul_first, local_values.x0[i], local_values.px[i]),
+                (mul_first, local_values.y0[i], local_values.py[i]),
+                // Its accumulated scalar ends at `k`.
+                (mul_last, local_values.k_next[i], local_values.k[i]),
+            ] {
+                let diff = builder.sub_extension(left, right);
+                let constraint = builder.mul_extension(filter, diff);
+                yield_constr.constraint(builder, constraint);
+            }
+            // Its accumulated scalar starts at 1.
+            let constraint = if i == 0 {
+                builder.mul_sub_extension(mul_first, local_values.k_acc[i], mul_first)
+            } else {
+                builder.mul_extension(mul_first, local_values.k_acc[i])
+            };
+            yield_constr.constraint(builder, constraint);
+        }
+
+        // `a` is nonzero, so that `lambda` is the only solution of
+        // `lambda * a = b`. Its limbs are range-checked through the arithmetic
+        // CTL, so that their sum doesn't wrap around.
+        let a_limbs_sum = builder.add_many_extension(local_values.a);
+        let constraint = builder.mul_sub_extension(a_limbs_sum, local_values.a_limbs_sum_inv, one);
+        let constraint = builder.mul_extension(is_op, constraint);
+        yield_constr.constraint(builder, constraint);
+
+        // A scalar multiplication spans consecutive rows, from its first to its
+        // last one.
+        let is_inner = builder.sub_extension(is_mul, mul_last);
+        let next_is_mul = builder.add_extension(next_values.is_mul_add, next_values.is_mul_double);
+        let next_is_continued = builder.sub_extension(next_is_mul, next_values.mul_first);
+        let constraint = builder.sub_extension(is_mul, mul_first);
+        yield_constr.constraint_first_row(builder, constraint);
+        let constraint = builder.sub_extension(is_mul, mul_last);
+        yield_constr.constraint_last_row(builder, constraint);
+        let constraint = builder.mul_sub_extension(next_is_continued, is_inner, next_is_continued);
+        yield_constr.constraint_transition(builder, constraint);
+        let constraint = builder.mul_sub_extension(is_inner, next_is_continued, is_inner);
+        yield_constr.constraint_transition(builder, constraint);
+
+        // Each of its rows takes the previous result as first point, and keeps
+        // the same inputs.
+        let mut continued_pairs = vec![
+            (next_values.is_bn254, local_values.is_bn254),
+            (next_values.context, local_values.context),
+            (next_values.segment, local_values.segment),
+            (next_values.virt, local_values.virt),
+            (next_values.timestamp, local_values.timestamp),
+        ];
+        for i in 0..N_LIMBS {
+            continued_pairs.extend([
+                (next_values.x0[i], local_values.x2[i]),
+                (next_values.y0[i], local_values.y2[i]),
+                (next_values.k_acc[i], local_values.k_next[i]),
+                (next_values.px[i], local_values.px[i]),
+                (next_values.py[i], local_values.py[i]),
+                (next_values.k[i], local_values.k[i]),
+            ]);
+        }
+        for (next, local) in continued_pairs {
+            let diff = builder.sub_extension(next, local);
+            let constraint = builder.mul_extension(next_is_continued, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+
+    fn lookups(&self) -> Vec<Lookup<F>> {
+        vec![]
+    }
+}
+
+#[cfg(all(test, feature = "generation"))]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use starky::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    use super::*;
+    use crate::constraint_checker::check_stark;
+    use crate::memory::segments::Segment;
+
+    const G: (U256, U256) = (
+        U256([
+            0x59f2815b16f81798,
+            0x029bfcdb2dce28d9,
+            0x55a06295ce870b07,
+            0x79be667ef9dcbbac,
+        ]),
+        U256([
+            0x9c47d08ffb10d4b8,
+            0xfd17b448a6855419,
+            0x5da4fbfc0e1108a8,
+            0x483ada7726a3c465,
+        ]),
+    );
+    const G2: (U256, U256) = (
+        U256([
+            0xabac09b95c709ee5,
+            0x5c778e4b8cef3ca7,
+            0x3045406e95c07cd8,
+            0xc6047f9441ed7d6d,
+        ]),
+        U256([
+            0x236431a950cfe52a,
+            0xf7f632653266d0e1,
+            0xa3c58419466ceaee,
+            0x1ae168fea63dc339,
+        ]),
+    );
+    const G3: (U256, U256) = (
+        U256([
+            0x8601f113bce036f9,
+            0xb531c845836f99b0,
+            0x49344f85f89d5229,
+            0xf9308a019258c310,
+        ]),
+        U256([
+            0x6cb9fd7584b8e672,
+            0x6500a99934c2231b,
+            0x0fe337e62a37f356,
+            0x388f7b0f632de814,
+        ]),
+    );
+
+    #[test]
+    fn test_stark_degree() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = EcStark<F, D>;
+
+        let stark = S::default();
+        test_stark_low_degree(stark)
+    }
+
+    #[test]
+    fn test_stark_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = EcStark<F, D>;
+
+        let stark = S::default();
+        test_stark_circuit_constraints::<F, C, S, D>(stark)
+    }
+
+    #[test]
+    fn test_generation() {
+        type F = GoldilocksField;
+        type S = EcStark<F, 2>;
+
+        let address = MemoryAddress::new(0, Segment::EcdsaTable, 32);
+        let double = EcOp::double(Curve::Secp256k1, address, 1, G).unwrap();
+        assert_eq!(double.output(), G2);
+        assert_eq!(double.arithmetic_ops().len(), 10);
+        let add = EcOp::add(Curve::Secp256k1, address, 9, G, G2).unwrap();
+        assert_eq!(add.output(), G3);
+        assert_eq!(add.arithmetic_ops().len(), 9);
+
+        let stark = S::default();
+        let trace = stark.generate_trace(vec![double, add], 4, &mut TimingTree::default());
+        assert_eq!(trace[0].len(), 4);
+        assert_eq!(check_stark(&stark, Table::Ec, &trace), vec![]);
+    }
+
+    #[test]
+    fn test_generation_bn254() {
+        type F = GoldilocksField;
+        type S = EcStark<F, 2>;
+
+        let g = (U256::one(), U256::from(2));
+        let g2 = (
+            U256::from_str_radix(
+                "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3",
+                16,
+            )
+            .unwrap(),
+            U256::from_str_radix(
+                "15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4",
+                16,
+            )
+            .unwrap(),
+        );
+        let address = MemoryAddress::new(0, Segment::EcdsaTable, 32);
+        let double = EcOp::double(Curve::Bn254, address, 1, g).unwrap();
+        assert_eq!(double.output(), g2);
+        let add = EcOp::add(Curve::Bn254, address, 9, g, g2).unwrap();
+        let secp_double = EcOp::double(Curve::Secp256k1, address, 17, G).unwrap();
+        let mul = EcOp::mul(Curve::Bn254, address, 25, g, 2.into()).unwrap();
+        assert_eq!(mul.len(), 1);
+        assert_eq!(mul[0].output(), g2);
+
+        let stark = S::default();
+        let operations = [vec![double, add, secp_double], mul].concat();
+        let trace = stark.generate_trace(operations, 4, &mut TimingTree::default());
+        assert_eq!(check_stark(&stark, Table::Ec, &trace), vec![]);
+    }
+
+    #[test]
+    fn test_generation_mul() {
+        type F = GoldilocksField;
+        type S = EcStark<F, 2>;
+
+        let address = MemoryAddress::new(0, Segment::EcdsaTable, 32);
+        let mul3 = EcOp::mul(Curve::Secp256k1, address, 1, G, 3.into()).unwrap();
+        assert_eq!(mul3.len(), 2);
+        assert_eq!(mul3[1].output(), G3);
+        assert_eq!(mul3[0].arithmetic_ops().len(), 11);
+        assert_eq!(mul3[1].arithmetic_ops().len(), 10);
+
+        let mul6 = EcOp::mul(Curve::Secp256k1, address, 9, G, 6.into()).unwrap();
+        assert_eq!(mul6.len(), 3);
+        let g6 = EcOp::double(Curve::Secp256k1, address, 17, G3).unwrap();
+        assert_eq!(mul6[2].output(), g6.output());
+
+        let stark = S::default();
+        let operations = [mul3, vec![g6], mul6].concat();
+        let trace = stark.generate_trace(operations, 4, &mut TimingTree::default());
+        assert_eq!(trace[0].len(), 8);
+        assert_eq!(check_stark(&stark, Table::Ec, &trace), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_operations() {
+        let address = MemoryAddress::new(0, Segment::EcdsaTable, 32);
+        assert!(matches!(
+            EcOp::add(Curve::Secp256k1, address, 1, G, G),
+            Err(ProgramError::InvalidEcOperation)
+        ));
+        for scalar in [U256::one(), Curve::Secp256k1.order()] {
+            assert!(matches!(
+                EcOp::mul(Curve::Secp256k1, address, 1, G, scalar),
+                Err(ProgramError::InvalidEcOperation)
+            ));
+        }
+    }
+}
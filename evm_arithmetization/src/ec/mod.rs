@@ -0,0 +1,13 @@
+//! The EC STARK carries out the affine additions, doublings and scalar
+//! multiplications of points of secp256k1 and of the BN254 G1 group, called by
+//! the kernel through the `SECP_ADD`, `SECP_DOUBLE`, `SECP_MUL`, `BN_G1_ADD`,
+//! `BN_G1_DOUBLE` and `BN_G1_MUL` instructions. A scalar multiplication spans
+//! consecutive rows. The table reads the inputs from memory and writes the
+//! result right after them, and looks up all its field operations in the
+//! arithmetic STARK.
+//!
+//! BN254 G2 arithmetic and the Miller loop and final exponentiation of the
+//! pairing precompile are not covered, and still run in kernel code.
+
+pub mod columns;
+pub mod ec_stark;
@@ -126,6 +126,7 @@ pub(crate) const BLS_BASE: U512 = U512([
     0x0,
 ]);
 
+#[cfg(feature = "generation")]
 pub(crate) const BLS_SCALAR: U256 = U256([
     0xffffffff00000001,
     0x53bda402fffe5bfe,
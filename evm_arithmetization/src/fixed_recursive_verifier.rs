@@ -1,10 +1,15 @@
 use core::mem::{self, MaybeUninit};
 use core::ops::Range;
 use std::collections::BTreeMap;
+#[cfg(feature = "generation")]
 use std::sync::atomic::AtomicBool;
+#[cfg(feature = "generation")]
 use std::sync::Arc;
+#[cfg(feature = "generation")]
+use std::time::Instant;
 
 use anyhow::anyhow;
+use ethereum_types::{H256, U256};
 use hashbrown::HashMap;
 use itertools::{zip_eq, Itertools};
 use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie};
@@ -19,14 +24,16 @@ use plonky2::iop::witness::{PartialWitness, WitnessWrite};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::{
     CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget,
+    VerifierOnlyCircuitData,
 };
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut};
 use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
 use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
-use plonky2::recursion::dummy_circuit::cyclic_base_proof;
+use plonky2::recursion::dummy_circuit::{cyclic_base_proof, dummy_circuit, dummy_proof};
 use plonky2::util::serialization::{
-    Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
+    Buffer, GateSerializer, IoError, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
 use plonky2_util::log2_ceil;
 use starky::config::StarkConfig;
@@ -35,16 +42,23 @@ use starky::lookup::{get_grand_product_challenge_set_target, GrandProductChallen
 use starky::proof::StarkProofWithMetadata;
 use starky::stark::Stark;
 
-use crate::all_stark::{all_cross_table_lookups, AllStark, Table, NUM_TABLES};
+use crate::all_stark::{all_cross_table_lookups, AllStark, AllStarkConfig, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
+#[cfg(feature = "generation")]
 use crate::generation::{GenerationInputs, TrimmedGenerationInputs};
 use crate::get_challenges::observe_public_values_target;
+#[cfg(feature = "generation")]
+use crate::proof::AllProof;
 use crate::proof::{
-    AllProof, BlockHashesTarget, BlockMetadataTarget, ExtraBlockData, ExtraBlockDataTarget,
-    FinalPublicValues, MemCapTarget, PublicValues, PublicValuesTarget, RegistersDataTarget,
-    TrieRoots, TrieRootsTarget, DEFAULT_CAP_LEN, TARGET_HASH_SIZE,
+    BlockHashesTarget, BlockMetadataTarget, ExtraBlockData, ExtraBlockDataTarget,
+    FinalPublicValues, MemCapTarget, PublicValues, PublicValuesError, PublicValuesTarget,
+    RegistersDataTarget, TrieRoots, TrieRootsTarget, TARGET_HASH_SIZE,
+};
+#[cfg(feature = "generation")]
+use crate::prover::{
+    check_abort_signal, prove_with_metrics, GenerationSegmentData, ProvingMetrics,
+    SegmentDataIterator,
 };
-use crate::prover::{check_abort_signal, prove, GenerationSegmentData, SegmentDataIterator};
 use crate::recursive_verifier::{
     add_common_recursion_gates, add_virtual_public_values, get_memory_extra_looking_sum_circuit,
     recursive_stark_circuit, set_public_value_targets, PlonkWrapperCircuit, PublicInputs,
@@ -69,6 +83,11 @@ where
     pub public_values: PublicValues,
 }
 
+/// The common and verifier-only data of the final circuit of a shrinking
+/// chain, which is all the root circuit needs to know about the chain.
+type FinalCircuitData<F, C, const D: usize> =
+    (CommonCircuitData<F, D>, VerifierOnlyCircuitData<C, D>);
+
 /// Contains all recursive circuits used in the system. For each STARK and each
 /// initial `degree_bits`, this contains a chain of recursive circuits for
 /// shrinking that STARK from `degree_bits` to a constant
@@ -114,8 +133,14 @@ where
     proof_with_pis: [ProofWithPublicInputsTarget<D>; NUM_TABLES],
     /// For each table, various inner circuits may be used depending on the
     /// initial table size. This target holds the index of the circuit
-    /// (within `final_circuits()`) that was used.
+    /// (among the final circuits of the table chains) that was used.
     index_verifier_data: [Target; NUM_TABLES],
+    /// For each table, whether it is in use in the segment. Tables which are
+    /// not [optional](Table::is_optional) are always in use.
+    table_in_use: [BoolTarget; NUM_TABLES],
+    /// For each optional table, the dummy proof verified in place of its
+    /// recursive proof when it is not in use.
+    table_dummy_proofs: [Option<DummyProofData<F, C, D>>; NUM_TABLES],
     /// Public inputs containing public values.
     public_values: PublicValuesTarget,
     /// Public inputs used for cyclic verification. These aren't actually used
@@ -124,6 +149,18 @@ where
     cyclic_vk: VerifierCircuitTarget,
 }
 
+/// A dummy proof matching the common data of the recursive proofs of an
+/// optional table.
+#[derive(Eq, PartialEq, Debug)]
+struct DummyProofData<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    common: CommonCircuitData<F, D>,
+    proof: ProofWithPublicInputs<F, C, D>,
+}
+
 impl<F, C, const D: usize> RootCircuitData<F, C, D>
 where
     F: RichField + Extendable<D>,
@@ -142,6 +179,16 @@ where
         for index in self.index_verifier_data {
             buffer.write_target(index)?;
         }
+        for in_use in self.table_in_use {
+            buffer.write_target_bool(in_use)?;
+        }
+        for dummy in &self.table_dummy_proofs {
+            buffer.write_bool(dummy.is_some())?;
+            if let Some(dummy) = dummy {
+                buffer.write_common_circuit_data(&dummy.common, gate_serializer)?;
+                buffer.write_proof_with_public_inputs(&dummy.proof)?;
+            }
+        }
         self.public_values.to_buffer(buffer)?;
         buffer.write_target_verifier_circuit(&self.cyclic_vk)?;
         Ok(())
@@ -161,6 +208,21 @@ where
         for _ in 0..NUM_TABLES {
             index_verifier_data.push(buffer.read_target()?);
         }
+        let mut table_in_use = Vec::with_capacity(NUM_TABLES);
+        for _ in 0..NUM_TABLES {
+            table_in_use.push(buffer.read_target_bool()?);
+        }
+        let mut table_dummy_proofs = Vec::with_capacity(NUM_TABLES);
+        for _ in 0..NUM_TABLES {
+            let dummy = if buffer.read_bool()? {
+                let common = buffer.read_common_circuit_data(gate_serializer)?;
+                let proof = buffer.read_proof_with_public_inputs(&common)?;
+                Some(DummyProofData { common, proof })
+            } else {
+                None
+            };
+            table_dummy_proofs.push(dummy);
+        }
         let public_values = PublicValuesTarget::from_buffer(buffer)?;
         let cyclic_vk = buffer.read_target_verifier_circuit()?;
 
@@ -168,10 +230,49 @@ where
             circuit,
             proof_with_pis: proof_with_pis.try_into().unwrap(),
             index_verifier_data: index_verifier_data.try_into().unwrap(),
+            table_in_use: table_in_use.try_into().unwrap(),
+            table_dummy_proofs: table_dummy_proofs.try_into().unwrap(),
             public_values,
             cyclic_vk,
         })
     }
+
+    /// Sets the shrunk proof of `table`, of the circuit at `index` in its
+    /// final circuits, in the witness of the root circuit.
+    #[cfg(feature = "generation")]
+    fn set_table_proof(
+        &self,
+        inputs: &mut PartialWitness<F>,
+        table: usize,
+        index: usize,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) {
+        if Table::all()[table].is_optional() {
+            inputs.set_bool_target(self.table_in_use[table], true);
+        }
+        inputs.set_target(
+            self.index_verifier_data[table],
+            F::from_canonical_usize(index),
+        );
+        inputs.set_proof_with_pis_target(&self.proof_with_pis[table], proof);
+    }
+
+    /// Marks `table` as not in use in the witness of the root circuit, in
+    /// which case a dummy proof is verified in place of its shrunk proof.
+    #[cfg(feature = "generation")]
+    fn set_table_not_in_use(
+        &self,
+        inputs: &mut PartialWitness<F>,
+        table: usize,
+    ) -> anyhow::Result<()> {
+        let dummy = self.table_dummy_proofs[table]
+            .as_ref()
+            .ok_or_else(|| anyhow!("{:?} table is not optional", Table::all()[table]))?;
+        inputs.set_bool_target(self.table_in_use[table], false);
+        inputs.set_target(self.index_verifier_data[table], F::ZERO);
+        inputs.set_proof_with_pis_target(&self.proof_with_pis[table], &dummy.proof);
+        Ok(())
+    }
 }
 
 /// Data for the segment aggregation circuit, which is used to compress two
@@ -373,6 +474,40 @@ impl<const D: usize> AggregationChildTarget<D> {
         .map(|(&agg_pv, &base_pv)| builder.select(self.is_agg, agg_pv, base_pv))
         .collect()
     }
+
+    /// Returns the chain segment covered by a two-to-one block child, given
+    /// its public inputs. A block proof covers a segment made of its single
+    /// block.
+    fn chain_segment<F, H>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        public_inputs: &[Target],
+    ) -> ChainSegmentPublicValues<Target>
+    where
+        F: RichField + Extendable<D>,
+        H: AlgebraicHasher<F>,
+    {
+        let agg_segment = extract_chain_segment_public_values(public_inputs);
+
+        let public_values = extract_block_public_values(public_inputs);
+        let block_pv = PublicValuesTarget::from_public_inputs(public_values);
+        let header = extract_block_header_public_values(public_values);
+        let header_hash = builder.hash_n_to_hash_no_pad::<H>(header.to_vec()).elements;
+        let base_segment = ChainSegmentPublicValues {
+            is_chain: builder.one(),
+            first_block_number: block_pv.block_metadata.block_number,
+            first_parent_hash: block_pv.block_hashes.parent_hash,
+            first_header_hash: header_hash,
+            last_block_number: block_pv.block_metadata.block_number,
+            last_block_hash: block_pv.block_hashes.cur_hash,
+            last_header_hash: header_hash,
+        };
+
+        let selected = zip_eq(agg_segment.to_vec(), base_segment.to_vec())
+            .map(|(agg, base)| builder.select(self.is_agg, agg, base))
+            .collect_vec();
+        ChainSegmentPublicValues::from_slice(&selected)
+    }
 }
 
 /// Data for the block circuit, which is used to generate a final block proof,
@@ -444,6 +579,9 @@ where
     pub circuit: CircuitData<F, C, D>,
     lhs: AggregationChildTarget<D>,
     rhs: AggregationChildTarget<D>,
+    /// Whether the proof covers a chain segment, see
+    /// [`AllRecursiveCircuits::prove_chain_segment`].
+    is_chain: BoolTarget,
     cyclic_vk: VerifierCircuitTarget,
 }
 
@@ -461,6 +599,7 @@ where
         buffer.write_circuit_data(&self.circuit, gate_serializer, generator_serializer)?;
         self.lhs.to_buffer(buffer)?;
         self.rhs.to_buffer(buffer)?;
+        buffer.write_target_bool(self.is_chain)?;
         buffer.write_target_verifier_circuit(&self.cyclic_vk)?;
         Ok(())
     }
@@ -473,16 +612,181 @@ where
         let circuit = buffer.read_circuit_data(gate_serializer, generator_serializer)?;
         let lhs = AggregationChildTarget::from_buffer(buffer)?;
         let rhs = AggregationChildTarget::from_buffer(buffer)?;
+        let is_chain = buffer.read_target_bool()?;
         let cyclic_vk = buffer.read_target_verifier_circuit()?;
         Ok(Self {
             circuit,
             lhs,
             rhs,
+            is_chain,
             cyclic_vk,
         })
     }
 }
 
+/// The current version of the [`VerifierArtifact`] serialization format.
+pub const VERIFIER_ARTIFACT_VERSION: u32 = 1;
+
+/// The verifier data of the block and two-to-one block circuits, which is all
+/// that is needed to verify their proofs.
+///
+/// Unlike [`AllRecursiveCircuits`], it holds no prover data, and is small
+/// enough to be shipped to verification services.
+#[derive(Eq, PartialEq, Debug)]
+pub struct VerifierArtifact<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// The verifier data of the block circuit.
+    pub block: VerifierCircuitData<F, C, D>,
+    /// The verifier data of the two-to-one block aggregation circuit.
+    pub two_to_one_block: VerifierCircuitData<F, C, D>,
+}
+
+impl<F, C, const D: usize> VerifierArtifact<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Serializes this artifact into a sequence of bytes, starting with
+    /// [`VERIFIER_ARTIFACT_VERSION`].
+    pub fn to_bytes(&self, gate_serializer: &dyn GateSerializer<F, D>) -> IoResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        buffer.write_u32(VERIFIER_ARTIFACT_VERSION)?;
+        buffer.write_verifier_circuit_data(&self.block, gate_serializer)?;
+        buffer.write_verifier_circuit_data(&self.two_to_one_block, gate_serializer)?;
+        Ok(buffer)
+    }
+
+    /// Deserializes an artifact serialized with [`Self::to_bytes`], failing
+    /// if it has another version.
+    pub fn from_bytes(bytes: &[u8], gate_serializer: &dyn GateSerializer<F, D>) -> IoResult<Self> {
+        let mut buffer = Buffer::new(bytes);
+        if buffer.read_u32()? != VERIFIER_ARTIFACT_VERSION {
+            return Err(IoError);
+        }
+        let block = buffer.read_verifier_circuit_data(gate_serializer)?;
+        let two_to_one_block = buffer.read_verifier_circuit_data(gate_serializer)?;
+        Ok(Self {
+            block,
+            two_to_one_block,
+        })
+    }
+
+    /// Verifies a block proof, as [`AllRecursiveCircuits::verify_block`].
+    pub fn verify_block(&self, block_proof: &ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        self.block.verify(block_proof.clone())?;
+        check_cyclic_proof_verifier_data(block_proof, &self.block.verifier_only, &self.block.common)
+    }
+
+    /// Verifies a block proof, as
+    /// [`AllRecursiveCircuits::verify_block_with_expected_values`].
+    pub fn verify_block_with_expected_values(
+        &self,
+        block_proof: &ProofWithPublicInputs<F, C, D>,
+        expected: &ExpectedBlockValues,
+    ) -> Result<PublicValues, BlockVerificationError> {
+        self.verify_block(block_proof)
+            .map_err(|err| BlockVerificationError::InvalidProof(err.to_string()))?;
+        check_block_public_values(&block_proof.public_inputs, expected)
+    }
+
+    /// Verifies a block aggregation proof, as
+    /// [`AllRecursiveCircuits::verify_two_to_one_block`].
+    pub fn verify_two_to_one_block(
+        &self,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> anyhow::Result<()> {
+        self.two_to_one_block.verify(proof.clone())?;
+        check_cyclic_proof_verifier_data(
+            proof,
+            &self.two_to_one_block.verifier_only,
+            &self.two_to_one_block.common,
+        )
+    }
+}
+
+/// The values a block proof is expected to attest to, checked by
+/// [`AllRecursiveCircuits::verify_block_with_expected_values`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpectedBlockValues {
+    /// The trie roots after the execution of the block.
+    pub trie_roots_after: TrieRoots,
+    /// The number of the block.
+    pub block_number: U256,
+    /// The chain id of the block.
+    pub chain_id: U256,
+}
+
+/// An error returned when verifying a block proof against
+/// [`ExpectedBlockValues`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BlockVerificationError {
+    #[error("invalid block proof: {0}")]
+    InvalidProof(String),
+    #[error("state root mismatch: expected {expected:?}, got {actual:?}")]
+    StateRootMismatch { expected: H256, actual: H256 },
+    #[error("transactions root mismatch: expected {expected:?}, got {actual:?}")]
+    TransactionsRootMismatch { expected: H256, actual: H256 },
+    #[error("receipts root mismatch: expected {expected:?}, got {actual:?}")]
+    ReceiptsRootMismatch { expected: H256, actual: H256 },
+    #[error("block number mismatch: expected {expected}, got {actual}")]
+    BlockNumberMismatch { expected: U256, actual: U256 },
+    #[error("chain id mismatch: expected {expected}, got {actual}")]
+    ChainIdMismatch { expected: U256, actual: U256 },
+    #[error("invalid public values: {0}")]
+    InvalidPublicValues(#[from] PublicValuesError),
+}
+
+/// Decodes the public values of a block proof from its `public_inputs`, and
+/// checks them against `expected`. The proof itself is not verified.
+fn check_block_public_values<F: RichField>(
+    public_inputs: &[F],
+    expected: &ExpectedBlockValues,
+) -> Result<PublicValues, BlockVerificationError> {
+    let public_values =
+        PublicValues::from_public_inputs(extract_block_public_values(public_inputs))?;
+
+    let roots = &public_values.trie_roots_after;
+    let expected_roots = &expected.trie_roots_after;
+    if roots.state_root != expected_roots.state_root {
+        return Err(BlockVerificationError::StateRootMismatch {
+            expected: expected_roots.state_root,
+            actual: roots.state_root,
+        });
+    }
+    if roots.transactions_root != expected_roots.transactions_root {
+        return Err(BlockVerificationError::TransactionsRootMismatch {
+            expected: expected_roots.transactions_root,
+            actual: roots.transactions_root,
+        });
+    }
+    if roots.receipts_root != expected_roots.receipts_root {
+        return Err(BlockVerificationError::ReceiptsRootMismatch {
+            expected: expected_roots.receipts_root,
+            actual: roots.receipts_root,
+        });
+    }
+
+    let metadata = &public_values.block_metadata;
+    if metadata.block_number != expected.block_number {
+        return Err(BlockVerificationError::BlockNumberMismatch {
+            expected: expected.block_number,
+            actual: metadata.block_number,
+        });
+    }
+    if metadata.block_chain_id != expected.chain_id {
+        return Err(BlockVerificationError::ChainIdMismatch {
+            expected: expected.chain_id,
+            actual: metadata.block_chain_id,
+        });
+    }
+
+    Ok(public_values)
+}
+
 impl<F, C, const D: usize> AllRecursiveCircuits<F, C, D>
 where
     F: RichField + Extendable<D>,
@@ -623,95 +927,131 @@ where
     /// starting from that length, for each `degree_bits` in the range specified
     /// for this STARK module. Specifying a wide enough range allows a
     /// prover to cover all possible scenarios.
-    /// - `stark_config`: the configuration to be used for the STARK prover. It
-    ///   will usually be a fast one yielding large proofs.
+    /// - `stark_config`: the configuration to be used for the STARK prover, for
+    ///   each table. It will usually be a fast one yielding large proofs, and
+    ///   may have a higher rate for small tables.
     pub fn new(
         all_stark: &AllStark<F, D>,
         degree_bits_ranges: &[Range<usize>; NUM_TABLES],
-        stark_config: &StarkConfig,
+        stark_config: &AllStarkConfig,
     ) -> Self {
-        // Sanity check on the provided config
-        assert_eq!(DEFAULT_CAP_LEN, 1 << stark_config.fri_config.cap_height);
-
-        let arithmetic = RecursiveCircuitsForTable::new(
-            Table::Arithmetic,
-            &all_stark.arithmetic_stark,
-            degree_bits_ranges[*Table::Arithmetic].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let byte_packing = RecursiveCircuitsForTable::new(
-            Table::BytePacking,
-            &all_stark.byte_packing_stark,
-            degree_bits_ranges[*Table::BytePacking].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let cpu = RecursiveCircuitsForTable::new(
-            Table::Cpu,
-            &all_stark.cpu_stark,
-            degree_bits_ranges[*Table::Cpu].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let keccak = RecursiveCircuitsForTable::new(
-            Table::Keccak,
-            &all_stark.keccak_stark,
-            degree_bits_ranges[*Table::Keccak].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let keccak_sponge = RecursiveCircuitsForTable::new(
-            Table::KeccakSponge,
-            &all_stark.keccak_sponge_stark,
-            degree_bits_ranges[*Table::KeccakSponge].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let logic = RecursiveCircuitsForTable::new(
-            Table::Logic,
-            &all_stark.logic_stark,
-            degree_bits_ranges[*Table::Logic].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let memory = RecursiveCircuitsForTable::new(
-            Table::Memory,
-            &all_stark.memory_stark,
-            degree_bits_ranges[*Table::Memory].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let mem_before = RecursiveCircuitsForTable::new(
-            Table::MemBefore,
-            &all_stark.mem_before_stark,
-            degree_bits_ranges[Table::MemBefore as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let mem_after = RecursiveCircuitsForTable::new(
-            Table::MemAfter,
-            &all_stark.mem_after_stark,
-            degree_bits_ranges[Table::MemAfter as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
+        let by_table: [_; NUM_TABLES] = core::array::from_fn(|i| {
+            let by_stark_size = degree_bits_ranges[i]
+                .clone()
+                .map(|degree_bits| {
+                    let table_circuits = Self::create_table_circuits(
+                        all_stark,
+                        Table::all()[i],
+                        degree_bits,
+                        stark_config,
+                    );
+                    (degree_bits, table_circuits)
+                })
+                .collect();
+            RecursiveCircuitsForTable { by_stark_size }
+        });
+        let final_circuits = core::array::from_fn(|i| {
+            by_table[i]
+                .by_stark_size
+                .values()
+                .map(RecursiveCircuitsForTableSize::final_circuit_data)
+                .collect()
+        });
+
+        Self::with_tables(by_table, &final_circuits, stark_config)
+    }
+
+    /// Preprocesses all recursive circuits used by the system like
+    /// [`AllRecursiveCircuits::new`], without keeping the recursive table
+    /// circuits in memory.
+    ///
+    /// The root circuit commits to the final verifier key of every shrinking
+    /// chain, so all chains are still built, but one at a time: each is handed
+    /// over to `on_table_circuits`, e.g. to be persisted, and dropped right
+    /// after. The resulting prover state has no recursive table circuits, and
+    /// segments are proven with
+    /// [`AllRecursiveCircuits::prove_segment_after_initial_stark`], with table
+    /// circuits loaded, or rebuilt with
+    /// [`AllRecursiveCircuits::create_table_circuits`], as proofs need them.
+    pub fn new_lazy<E>(
+        all_stark: &AllStark<F, D>,
+        degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+        stark_config: &AllStarkConfig,
+        mut on_table_circuits: impl FnMut(
+            Table,
+            usize,
+            RecursiveCircuitsForTableSize<F, C, D>,
+        ) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        let mut final_circuits: [Vec<_>; NUM_TABLES] = Default::default();
+        for (i, table) in Table::all().into_iter().enumerate() {
+            for degree_bits in degree_bits_ranges[i].clone() {
+                let table_circuits =
+                    Self::create_table_circuits(all_stark, table, degree_bits, stark_config);
+                final_circuits[i].push(table_circuits.final_circuit_data());
+                on_table_circuits(table, degree_bits, table_circuits)?;
+            }
+        }
+
+        let by_table = core::array::from_fn(|_| RecursiveCircuitsForTable {
+            by_stark_size: BTreeMap::default(),
+        });
+        Ok(Self::with_tables(by_table, &final_circuits, stark_config))
+    }
 
-        let by_table = [
-            arithmetic,
-            byte_packing,
-            cpu,
-            keccak,
-            keccak_sponge,
-            logic,
-            memory,
-            mem_before,
-            mem_after,
-        ];
-        let root = Self::create_segment_circuit(&by_table, stark_config);
+    /// Builds the chain of shrinking recursion circuits of `table`, starting
+    /// from a STARK proof of size `2^degree_bits`.
+    ///
+    /// Circuit construction is deterministic, so that the chain is identical to
+    /// the one built alongside the rest of the prover state for the same
+    /// parameters.
+    pub fn create_table_circuits(
+        all_stark: &AllStark<F, D>,
+        table: Table,
+        degree_bits: usize,
+        stark_config: &AllStarkConfig,
+    ) -> RecursiveCircuitsForTableSize<F, C, D> {
+        macro_rules! table_circuits {
+            ($stark:ident) => {
+                RecursiveCircuitsForTableSize::new(
+                    table,
+                    &all_stark.$stark,
+                    degree_bits,
+                    &all_stark.cross_table_lookups,
+                    stark_config.table(table),
+                    stark_config.recursion_config(),
+                )
+            };
+        }
+
+        match table {
+            Table::Arithmetic => table_circuits!(arithmetic_stark),
+            Table::BytePacking => table_circuits!(byte_packing_stark),
+            Table::Cpu => table_circuits!(cpu_stark),
+            Table::Keccak => table_circuits!(keccak_stark),
+            Table::KeccakSponge => table_circuits!(keccak_sponge_stark),
+            Table::Logic => table_circuits!(logic_stark),
+            Table::Memory => table_circuits!(memory_stark),
+            Table::MemBefore => table_circuits!(mem_before_stark),
+            Table::MemAfter => table_circuits!(mem_after_stark),
+            Table::RangeCheck => table_circuits!(range_check_stark),
+            Table::Ec => table_circuits!(ec_stark),
+        }
+    }
+
+    /// Builds the upper circuits on top of the final circuits of the shrinking
+    /// chains of each table, ordered by initial size.
+    fn with_tables(
+        by_table: [RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
+        final_circuits: &[Vec<FinalCircuitData<F, C, D>>; NUM_TABLES],
+        stark_config: &AllStarkConfig,
+    ) -> Self {
+        let root = Self::create_segment_circuit(final_circuits, stark_config);
         let segment_aggregation = Self::create_segment_aggregation_circuit(&root);
-        let txn_aggregation =
-            Self::create_txn_aggregation_circuit(&segment_aggregation, stark_config);
+        let txn_aggregation = Self::create_txn_aggregation_circuit(
+            &segment_aggregation,
+            stark_config.table(Table::MemBefore),
+        );
         let block = Self::create_block_circuit(&txn_aggregation);
         let two_to_one_block = Self::create_two_to_one_block_circuit(&block);
         Self {
@@ -744,14 +1084,22 @@ where
         self.block.circuit.verifier_data()
     }
 
+    /// Exports the verifier data of the block and two-to-one block circuits,
+    /// to verify their proofs without the prover circuits.
+    pub fn export_verifier(&self) -> VerifierArtifact<F, C, D> {
+        VerifierArtifact {
+            block: self.block.circuit.verifier_data(),
+            two_to_one_block: self.two_to_one_block.circuit.verifier_data(),
+        }
+    }
+
     fn create_segment_circuit(
-        by_table: &[RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
-        stark_config: &StarkConfig,
+        final_circuits: &[Vec<FinalCircuitData<F, C, D>>; NUM_TABLES],
+        stark_config: &AllStarkConfig,
     ) -> RootCircuitData<F, C, D> {
-        let inner_common_data: [_; NUM_TABLES] =
-            core::array::from_fn(|i| &by_table[i].final_circuits()[0].common);
+        let inner_common_data: [_; NUM_TABLES] = core::array::from_fn(|i| &final_circuits[i][0].0);
 
-        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let mut builder = CircuitBuilder::new(stark_config.recursion_config().clone());
 
         let public_values = add_virtual_public_values(&mut builder);
 
@@ -760,15 +1108,30 @@ where
         let pis: [_; NUM_TABLES] = core::array::from_fn(|i| {
             PublicInputs::<Target, <C::Hasher as AlgebraicHasher<F>>::AlgebraicPermutation>::from_vec(
                 &recursive_proofs[i].public_inputs,
-                stark_config,
+                stark_config.table(Table::all()[i]),
             )
         });
         let index_verifier_data = core::array::from_fn(|_i| builder.add_virtual_target());
+        let table_in_use: [BoolTarget; NUM_TABLES] =
+            core::array::from_fn(|i| match Table::all()[i].is_optional() {
+                true => builder.add_virtual_bool_target_safe(),
+                false => builder._true(),
+            });
 
+        // The trace caps of the tables which are not in use are observed as
+        // zeros, and their proofs are left out of the challenger state chain and
+        // of the CTL checks, as their recursive proofs are not verified.
         let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(&mut builder);
-        for pi in &pis {
+        for (i, pi) in pis.iter().enumerate() {
             for h in &pi.trace_cap {
-                challenger.observe_elements(h);
+                let h = match Table::all()[i].is_optional() {
+                    true => h
+                        .iter()
+                        .map(|&t| builder.mul(table_in_use[i].target, t))
+                        .collect(),
+                    false => h.clone(),
+                };
+                challenger.observe_elements(&h);
             }
         }
 
@@ -777,43 +1140,56 @@ where
         let ctl_challenges = get_grand_product_challenge_set_target(
             &mut builder,
             &mut challenger,
-            stark_config.num_challenges,
+            stark_config.num_challenges(),
         );
+        // Connects `x` and `y` if the `i`-th table is in use.
+        let connect_if_in_use = |builder: &mut CircuitBuilder<F, D>, i: usize, x, y| {
+            match Table::all()[i].is_optional() {
+                true => builder.conditional_assert_eq(table_in_use[i].target, x, y),
+                false => builder.connect(x, y),
+            }
+        };
+
         // Check that the correct CTL challenges are used in every proof.
-        for pi in &pis {
-            for i in 0..stark_config.num_challenges {
-                builder.connect(
-                    ctl_challenges.challenges[i].beta,
-                    pi.ctl_challenges.challenges[i].beta,
+        for (i, pi) in pis.iter().enumerate() {
+            for c in 0..stark_config.num_challenges() {
+                connect_if_in_use(
+                    &mut builder,
+                    i,
+                    ctl_challenges.challenges[c].beta,
+                    pi.ctl_challenges.challenges[c].beta,
                 );
-                builder.connect(
-                    ctl_challenges.challenges[i].gamma,
-                    pi.ctl_challenges.challenges[i].gamma,
+                connect_if_in_use(
+                    &mut builder,
+                    i,
+                    ctl_challenges.challenges[c].gamma,
+                    pi.ctl_challenges.challenges[c].gamma,
                 );
             }
         }
 
-        let state = challenger.compact(&mut builder);
-        for (&before, &s) in zip_eq(state.as_ref(), pis[0].challenger_state_before.as_ref()) {
-            builder.connect(before, s);
-        }
-        // Check that the challenger state is consistent between proofs.
-        for i in 1..NUM_TABLES {
-            for (&before, &after) in zip_eq(
-                pis[i].challenger_state_before.as_ref(),
-                pis[i - 1].challenger_state_after.as_ref(),
-            ) {
-                builder.connect(before, after);
+        // Check that the challenger state is consistent between proofs, skipping
+        // the tables which are not in use.
+        let mut state = challenger.compact(&mut builder).as_ref().to_vec();
+        for (i, pi) in pis.iter().enumerate() {
+            for (&before, &s) in zip_eq(pi.challenger_state_before.as_ref(), &state) {
+                connect_if_in_use(&mut builder, i, before, s);
             }
+            state = match Table::all()[i].is_optional() {
+                true => zip_eq(pi.challenger_state_after.as_ref(), &state)
+                    .map(|(&after, &s)| builder.select(table_in_use[i], after, s))
+                    .collect(),
+                false => pi.challenger_state_after.as_ref().to_vec(),
+            };
         }
 
         // Extra sums to add to the looked last value.
         // Only necessary for the Memory values.
         let mut extra_looking_sums =
-            vec![vec![builder.zero(); stark_config.num_challenges]; NUM_TABLES];
+            vec![vec![builder.zero(); stark_config.num_challenges()]; NUM_TABLES];
 
         // Memory
-        extra_looking_sums[*Table::Memory] = (0..stark_config.num_challenges)
+        extra_looking_sums[*Table::Memory] = (0..stark_config.num_challenges())
             .map(|c| {
                 get_memory_extra_looking_sum_circuit(
                     &mut builder,
@@ -824,25 +1200,29 @@ where
             .collect_vec();
 
         // Verify the CTL checks.
+        let ctl_zs_first = core::array::from_fn(|i| match Table::all()[i].is_optional() {
+            true => pis[i]
+                .ctl_zs_first
+                .iter()
+                .map(|&z| builder.mul(table_in_use[i].target, z))
+                .collect(),
+            false => pis[i].ctl_zs_first.clone(),
+        });
         verify_cross_table_lookups_circuit::<F, D, NUM_TABLES>(
             &mut builder,
             all_cross_table_lookups(),
-            pis.map(|p| p.ctl_zs_first),
+            ctl_zs_first,
             Some(&extra_looking_sums),
-            stark_config,
+            stark_config.ctl_config(),
         );
 
-        for (i, table_circuits) in by_table.iter().enumerate() {
-            let final_circuits = table_circuits.final_circuits();
-            for final_circuit in &final_circuits {
-                assert_eq!(
-                    &final_circuit.common, inner_common_data[i],
-                    "common_data mismatch"
-                );
+        for (i, table_final_circuits) in final_circuits.iter().enumerate() {
+            for (common, _) in table_final_circuits {
+                assert_eq!(common, inner_common_data[i], "common_data mismatch");
             }
-            let mut possible_vks = final_circuits
-                .into_iter()
-                .map(|c| builder.constant_verifier_data(&c.verifier_only))
+            let mut possible_vks = table_final_circuits
+                .iter()
+                .map(|(_, verifier_only)| builder.constant_verifier_data(verifier_only))
                 .collect_vec();
             // random_access_verifier_data expects a vector whose length is a power of two.
             // To satisfy this, we will just add some duplicates of the first VK.
@@ -852,12 +1232,31 @@ where
             let inner_verifier_data =
                 builder.random_access_verifier_data(index_verifier_data[i], possible_vks);
 
-            builder.verify_proof::<C>(
-                &recursive_proofs[i],
-                &inner_verifier_data,
-                inner_common_data[i],
-            );
+            if Table::all()[i].is_optional() {
+                builder
+                    .conditionally_verify_proof_or_dummy::<C>(
+                        table_in_use[i],
+                        &recursive_proofs[i],
+                        &inner_verifier_data,
+                        inner_common_data[i],
+                    )
+                    .expect("Failed to build the conditional verification of an optional table");
+            } else {
+                builder.verify_proof::<C>(
+                    &recursive_proofs[i],
+                    &inner_verifier_data,
+                    inner_common_data[i],
+                );
+            }
         }
+        let table_dummy_proofs = core::array::from_fn(|i| {
+            Table::all()[i].is_optional().then(|| {
+                let common = inner_common_data[i].clone();
+                let proof = dummy_proof::<F, C, D>(&dummy_circuit(&common), Default::default())
+                    .expect("Failed to generate the dummy proof of an optional table");
+                DummyProofData { common, proof }
+            })
+        });
 
         let merkle_before =
             MemCapTarget::from_public_inputs(&recursive_proofs[*Table::MemBefore].public_inputs);
@@ -885,6 +1284,8 @@ where
             circuit: builder.build::<C>(),
             proof_with_pis: recursive_proofs,
             index_verifier_data,
+            table_in_use,
+            table_dummy_proofs,
             public_values,
             cyclic_vk,
         }
@@ -1231,7 +1632,7 @@ where
             ..agg.circuit.common.clone()
         };
 
-        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut builder = CircuitBuilder::<F, D>::new(agg.circuit.common.config.clone());
         let public_values = add_virtual_public_values(&mut builder);
         let has_parent_block = builder.add_virtual_bool_target_safe();
         let parent_block_proof = builder.add_virtual_proof_with_pis(&expected_common_data);
@@ -1438,13 +1839,18 @@ where
         let mut builder = CircuitBuilder::<F, D>::new(block_circuit.circuit.common.config.clone());
 
         let mix_hash = builder.add_virtual_hash_public_input();
+        let segment = ChainSegmentPublicValues::from_slice(
+            &builder.add_virtual_public_input_arr::<CHAIN_SEGMENT_PUBLIC_VALUES_LEN>(),
+        );
+        let is_chain = BoolTarget::new_unsafe(segment.is_chain);
+        builder.assert_bool(is_chain);
 
         // We need to pad by PIS to match the count of PIS of the `base_proof`.
         let mut padding = block_circuit.circuit.common.num_public_inputs;
         // The number of PIS that will be added *after* padding by
         // [`add_verifier_data_public_inputs()`].
         padding -= verification_key_len(&block_circuit.circuit);
-        // Account for `mix_pv_hash`.
+        // Account for `mix_pv_hash` and the chain segment.
         padding -= builder.num_public_inputs();
 
         let zero = builder.zero();
@@ -1489,15 +1895,64 @@ where
 
         builder.connect_hashes(mix_hash, mix_hash_virtual);
 
+        let lhs_segment = lhs.chain_segment::<F, C::InnerHasher>(&mut builder, &lhs_public_inputs);
+        let rhs_segment = rhs.chain_segment::<F, C::InnerHasher>(&mut builder, &rhs_public_inputs);
+        Self::connect_chain_segments(&mut builder, is_chain, &segment, &lhs_segment, &rhs_segment);
+
         let circuit = builder.build::<C>();
         TwoToOneBlockCircuitData {
             circuit,
             lhs,
             rhs,
+            is_chain,
             cyclic_vk,
         }
     }
 
+    /// Sets the chain segment covered by a two-to-one block proof from the ones
+    /// of its children: it starts with the first block of `lhs` and ends with
+    /// the last block of `rhs`.
+    ///
+    /// If `is_chain`, both children must cover chain segments, and the first
+    /// block of `rhs` must be the child of the last block of `lhs`.
+    fn connect_chain_segments(
+        builder: &mut CircuitBuilder<F, D>,
+        is_chain: BoolTarget,
+        segment: &ChainSegmentPublicValues<Target>,
+        lhs: &ChainSegmentPublicValues<Target>,
+        rhs: &ChainSegmentPublicValues<Target>,
+    ) {
+        let one = builder.one();
+        builder.conditional_assert_eq(is_chain.target, lhs.is_chain, one);
+        builder.conditional_assert_eq(is_chain.target, rhs.is_chain, one);
+        for (&parent_limb, &hash_limb) in zip_eq(&rhs.first_parent_hash, &lhs.last_block_hash) {
+            builder.conditional_assert_eq(is_chain.target, parent_limb, hash_limb);
+        }
+        let next_block_number = builder.add(lhs.last_block_number, one);
+        builder.conditional_assert_eq(is_chain.target, rhs.first_block_number, next_block_number);
+
+        builder.connect(segment.first_block_number, lhs.first_block_number);
+        builder.connect(segment.last_block_number, rhs.last_block_number);
+        for (&x, &y) in zip_eq(
+            segment
+                .first_parent_hash
+                .iter()
+                .chain(&segment.first_header_hash),
+            lhs.first_parent_hash.iter().chain(&lhs.first_header_hash),
+        ) {
+            builder.connect(x, y);
+        }
+        for (&x, &y) in zip_eq(
+            segment
+                .last_block_hash
+                .iter()
+                .chain(&segment.last_header_hash),
+            rhs.last_block_hash.iter().chain(&rhs.last_header_hash),
+        ) {
+            builder.connect(x, y);
+        }
+    }
+
     /// Connect the 256 block hashes between two blocks
     fn connect_block_hashes(
         builder: &mut CircuitBuilder<F, D>,
@@ -1643,28 +2098,62 @@ where
     /// for a verifier to assert correctness of the computation,
     /// but the public values are output for the prover convenience, as these
     /// are necessary during proof aggregation.
+    #[cfg(feature = "generation")]
     pub fn prove_segment(
         &self,
         all_stark: &AllStark<F, D>,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
+        generation_inputs: TrimmedGenerationInputs,
+        segment_data: &mut GenerationSegmentData,
+        timing: &mut TimingTree,
+        abort_signal: Option<Arc<AtomicBool>>,
+    ) -> anyhow::Result<ProverOutputData<F, C, D>> {
+        self.prove_segment_with_metrics(
+            all_stark,
+            config,
+            generation_inputs,
+            segment_data,
+            timing,
+            abort_signal,
+            &mut ProvingMetrics::default(),
+        )
+    }
+
+    /// Returns a proof for a segment, like [`Self::prove_segment`], adding
+    /// the metrics of the segment to `metrics`.
+    #[cfg(feature = "generation")]
+    pub fn prove_segment_with_metrics(
+        &self,
+        all_stark: &AllStark<F, D>,
+        config: &AllStarkConfig,
         generation_inputs: TrimmedGenerationInputs,
         segment_data: &mut GenerationSegmentData,
         timing: &mut TimingTree,
         abort_signal: Option<Arc<AtomicBool>>,
+        metrics: &mut ProvingMetrics,
     ) -> anyhow::Result<ProverOutputData<F, C, D>> {
-        let all_proof = prove::<F, C, D>(
+        let all_proof = prove_with_metrics::<F, C, D>(
             all_stark,
             config,
             generation_inputs,
             segment_data,
             timing,
             abort_signal.clone(),
+            metrics,
         )?;
+        let recursion_start = Instant::now();
         let mut root_inputs = PartialWitness::new();
 
         for table in 0..NUM_TABLES {
+            if !all_proof.table_in_use[table] {
+                self.root.set_table_not_in_use(&mut root_inputs, table)?;
+                continue;
+            }
+
             let stark_proof = &all_proof.multi_proof.stark_proofs[table];
-            let original_degree_bits = stark_proof.proof.recover_degree_bits(config);
+            let original_degree_bits = stark_proof
+                .proof
+                .recover_degree_bits(config.table(Table::all()[table]));
             let table_circuits = &self.by_table[table];
             let shrunk_proof = table_circuits
                 .by_stark_size
@@ -1682,11 +2171,8 @@ where
                 .keys()
                 .position(|&size| size == original_degree_bits)
                 .unwrap();
-            root_inputs.set_target(
-                self.root.index_verifier_data[table],
-                F::from_canonical_usize(index_verifier_data),
-            );
-            root_inputs.set_proof_with_pis_target(&self.root.proof_with_pis[table], &shrunk_proof);
+            self.root
+                .set_table_proof(&mut root_inputs, table, index_verifier_data, &shrunk_proof);
 
             check_abort_signal(abort_signal.clone())?;
         }
@@ -1706,6 +2192,7 @@ where
         })?;
 
         let root_proof = self.root.circuit.prove(root_inputs)?;
+        metrics.recursion_time += recursion_start.elapsed();
 
         Ok(ProverOutputData {
             is_dummy: false,
@@ -1716,29 +2203,56 @@ where
 
     /// Returns a proof for each segment that is part of a full transaction
     /// proof.
+    #[cfg(feature = "generation")]
     pub fn prove_all_segments(
         &self,
         all_stark: &AllStark<F, D>,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
         generation_inputs: GenerationInputs,
         max_cpu_len_log: usize,
         timing: &mut TimingTree,
         abort_signal: Option<Arc<AtomicBool>>,
     ) -> anyhow::Result<Vec<ProverOutputData<F, C, D>>> {
+        self.prove_all_segments_with_metrics(
+            all_stark,
+            config,
+            generation_inputs,
+            max_cpu_len_log,
+            timing,
+            abort_signal,
+        )
+        .map(|(proofs, _)| proofs)
+    }
+
+    /// Returns a proof for each segment that is part of a full transaction
+    /// proof, like [`Self::prove_all_segments`], along with the metrics of
+    /// their generation.
+    #[cfg(feature = "generation")]
+    pub fn prove_all_segments_with_metrics(
+        &self,
+        all_stark: &AllStark<F, D>,
+        config: &AllStarkConfig,
+        generation_inputs: GenerationInputs,
+        max_cpu_len_log: usize,
+        timing: &mut TimingTree,
+        abort_signal: Option<Arc<AtomicBool>>,
+    ) -> anyhow::Result<(Vec<ProverOutputData<F, C, D>>, ProvingMetrics)> {
         let segment_iterator =
             SegmentDataIterator::<F>::new(&generation_inputs, Some(max_cpu_len_log));
 
         let mut proofs = vec![];
+        let mut metrics = ProvingMetrics::default();
 
         for segment_run in segment_iterator {
             let (_, mut next_data) = segment_run.map_err(|e| anyhow::format_err!(e))?;
-            let proof = self.prove_segment(
+            let proof = self.prove_segment_with_metrics(
                 all_stark,
                 config,
                 generation_inputs.trim(),
                 &mut next_data,
                 timing,
                 abort_signal.clone(),
+                &mut metrics,
             )?;
             proofs.push(proof);
         }
@@ -1751,7 +2265,7 @@ where
             proofs.push(first_proof);
         }
 
-        Ok(proofs)
+        Ok((proofs, metrics))
     }
 
     /// From an initial set of STARK proofs passed with their associated
@@ -1804,6 +2318,7 @@ where
     ///     abort_signal,
     /// ).unwrap();
     /// ```
+    #[cfg(feature = "generation")]
     pub fn prove_segment_after_initial_stark(
         &self,
         all_proof: AllProof<F, C, D>,
@@ -1813,17 +2328,23 @@ where
         let mut root_inputs = PartialWitness::new();
 
         for table in 0..NUM_TABLES {
+            if !all_proof.table_in_use[table] {
+                self.root.set_table_not_in_use(&mut root_inputs, table)?;
+                continue;
+            }
+
             let (table_circuit, index_verifier_data) = &table_circuits[table];
 
             let stark_proof = &all_proof.multi_proof.stark_proofs[table];
 
             let shrunk_proof =
                 table_circuit.shrink(stark_proof, &all_proof.multi_proof.ctl_challenges)?;
-            root_inputs.set_target(
-                self.root.index_verifier_data[table],
-                F::from_canonical_u8(*index_verifier_data),
+            self.root.set_table_proof(
+                &mut root_inputs,
+                table,
+                *index_verifier_data as usize,
+                &shrunk_proof,
             );
-            root_inputs.set_proof_with_pis_target(&self.root.proof_with_pis[table], &shrunk_proof);
 
             check_abort_signal(abort_signal.clone())?;
         }
@@ -2185,8 +2706,7 @@ where
             // Initialize checkpoint block hashes.
             // These will be all zeros the initial genesis checkpoint.
             let block_hashes_keys = TrieRootsTarget::SIZE * 2 + BlockMetadataTarget::SIZE
-                ..TrieRootsTarget::SIZE * 2 + BlockMetadataTarget::SIZE + BlockHashesTarget::SIZE
-                    - 8;
+                ..TrieRootsTarget::SIZE * 2 + BlockMetadataTarget::SIZE + 2048;
 
             for i in 0..public_values.block_hashes.prev_hashes.len() - 1 {
                 let targets = h256_limbs::<F>(public_values.block_hashes.prev_hashes[i]);
@@ -2194,8 +2714,7 @@ where
                     nonzero_pis.insert(block_hashes_keys.start + 8 * (i + 1) + j, targets[j]);
                 }
             }
-            let block_hashes_current_start =
-                TrieRootsTarget::SIZE * 2 + BlockMetadataTarget::SIZE + BlockHashesTarget::SIZE - 8;
+            let block_hashes_current_start = block_hashes_keys.end;
             let cur_targets = h256_limbs::<F>(public_values.block_hashes.prev_hashes[255]);
             for i in 0..8 {
                 nonzero_pis.insert(block_hashes_current_start + i, cur_targets[i]);
@@ -2256,6 +2775,23 @@ where
         )
     }
 
+    /// Verifies a block proof, and checks that it attests to the `expected`
+    /// trie roots after the block, block number and chain id.
+    ///
+    /// # Outputs
+    ///
+    /// Returns the public values of the block, decoded from the public inputs
+    /// of the proof.
+    pub fn verify_block_with_expected_values(
+        &self,
+        block_proof: &ProofWithPublicInputs<F, C, D>,
+        expected: &ExpectedBlockValues,
+    ) -> Result<PublicValues, BlockVerificationError> {
+        self.verify_block(block_proof)
+            .map_err(|err| BlockVerificationError::InvalidProof(err.to_string()))?;
+        check_block_public_values(&block_proof.public_inputs, expected)
+    }
+
     /// Aggregates two proofs in manner similar to [`prove_aggregation`].
     ///
     /// # Arguments
@@ -2274,8 +2810,38 @@ where
         lhs_is_agg: bool,
         rhs: &ProofWithPublicInputs<F, C, D>,
         rhs_is_agg: bool,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        self.prove_two_to_one_block_inner(lhs, lhs_is_agg, rhs, rhs_is_agg, false)
+    }
+
+    /// Aggregates two proofs like [`Self::prove_two_to_one_block`], further
+    /// checking that they cover consecutive blocks: the first block of `rhs`
+    /// must have the block number following the one of the last block of
+    /// `lhs`, and the hash of the latter as parent hash.
+    ///
+    /// Aggregation children must themselves be chain segment proofs. The
+    /// first and last blocks of the resulting chain segment can be read with
+    /// [`extract_chain_segment_public_values`].
+    pub fn prove_chain_segment(
+        &self,
+        lhs: &ProofWithPublicInputs<F, C, D>,
+        lhs_is_agg: bool,
+        rhs: &ProofWithPublicInputs<F, C, D>,
+        rhs_is_agg: bool,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        self.prove_two_to_one_block_inner(lhs, lhs_is_agg, rhs, rhs_is_agg, true)
+    }
+
+    fn prove_two_to_one_block_inner(
+        &self,
+        lhs: &ProofWithPublicInputs<F, C, D>,
+        lhs_is_agg: bool,
+        rhs: &ProofWithPublicInputs<F, C, D>,
+        rhs_is_agg: bool,
+        is_chain: bool,
     ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
         let mut witness = PartialWitness::new();
+        witness.set_bool_target(self.two_to_one_block.is_chain, is_chain);
 
         Self::set_dummy_if_necessary(
             &self.two_to_one_block.lhs,
@@ -2435,46 +3001,6 @@ where
         }
         Ok(Self { by_stark_size })
     }
-
-    fn new<S: Stark<F, D>>(
-        table: Table,
-        stark: &S,
-        degree_bits_range: Range<usize>,
-        all_ctls: &[CrossTableLookup<F>],
-        stark_config: &StarkConfig,
-    ) -> Self {
-        let by_stark_size = degree_bits_range
-            .map(|degree_bits| {
-                (
-                    degree_bits,
-                    RecursiveCircuitsForTableSize::new::<S>(
-                        table,
-                        stark,
-                        degree_bits,
-                        all_ctls,
-                        stark_config,
-                    ),
-                )
-            })
-            .collect();
-        Self { by_stark_size }
-    }
-
-    /// For each initial `degree_bits`, get the final circuit at the end of that
-    /// shrinking chain. Each of these final circuits should have degree
-    /// `THRESHOLD_DEGREE_BITS`.
-    fn final_circuits(&self) -> Vec<&CircuitData<F, C, D>> {
-        self.by_stark_size
-            .values()
-            .map(|chain| {
-                chain
-                    .shrinking_wrappers
-                    .last()
-                    .map(|wrapper| &wrapper.circuit)
-                    .unwrap_or(&chain.initial_wrapper.circuit)
-            })
-            .collect()
-    }
 }
 
 /// A chain of shrinking wrapper circuits, ending with a final circuit with
@@ -2564,14 +3090,16 @@ where
         degree_bits: usize,
         all_ctls: &[CrossTableLookup<F>],
         stark_config: &StarkConfig,
+        recursion_config: &CircuitConfig,
     ) -> Self {
+        let shrinking_config = shrinking_config(recursion_config);
         let initial_wrapper = recursive_stark_circuit(
             table,
             stark,
             degree_bits,
             all_ctls,
             stark_config,
-            &shrinking_config(),
+            &shrinking_config,
             THRESHOLD_DEGREE_BITS,
         );
         let mut shrinking_wrappers = vec![];
@@ -2588,12 +3116,17 @@ where
                 break;
             }
 
-            let mut builder = CircuitBuilder::new(shrinking_config());
+            let mut builder = CircuitBuilder::new(shrinking_config.clone());
             let proof_with_pis_target = builder.add_virtual_proof_with_pis(&last.common);
             let last_vk = builder.constant_verifier_data(&last.verifier_only);
             builder.verify_proof::<C>(&proof_with_pis_target, &last_vk, &last.common);
             builder.register_public_inputs(&proof_with_pis_target.public_inputs); // carry PIs forward
             add_common_recursion_gates(&mut builder);
+            // Recursion configurations with fewer queries than the standard one
+            // may shrink past the threshold, where the chains must all end.
+            while log2_ceil(builder.num_gates()) < THRESHOLD_DEGREE_BITS {
+                builder.add_gate(NoopGate, vec![]);
+            }
             let circuit = builder.build::<C>();
 
             assert!(
@@ -2614,6 +3147,17 @@ where
         }
     }
 
+    /// Returns the common and verifier-only data of the final circuit of this
+    /// chain, of degree `THRESHOLD_DEGREE_BITS`.
+    fn final_circuit_data(&self) -> FinalCircuitData<F, C, D> {
+        let circuit = self
+            .shrinking_wrappers
+            .last()
+            .map(|wrapper| &wrapper.circuit)
+            .unwrap_or(&self.initial_wrapper.circuit);
+        (circuit.common.clone(), circuit.verifier_only.clone())
+    }
+
     pub fn shrink(
         &self,
         stark_proof_with_metadata: &StarkProofWithMetadata<F, C, D>,
@@ -2633,10 +3177,10 @@ where
 /// circuits, we use a few more gates for a constant inner VK and for public
 /// inputs. This pushes us over the threshold to 2^13. As long as we're at 2^13
 /// gates, we might as well use a narrower witness.
-fn shrinking_config() -> CircuitConfig {
+fn shrinking_config(recursion_config: &CircuitConfig) -> CircuitConfig {
     CircuitConfig {
         num_routed_wires: 40,
-        ..CircuitConfig::standard_recursion_config()
+        ..recursion_config.clone()
     }
 }
 
@@ -2684,6 +3228,220 @@ pub fn extract_block_public_values<T>(public_inputs: &[T]) -> &[T; PublicValuesT
         .expect("Public inputs vector was malformed.")
 }
 
+/// The number of public inputs describing the chain segment covered by a
+/// two-to-one block aggregation proof.
+pub const CHAIN_SEGMENT_PUBLIC_VALUES_LEN: usize = 3 + 2 * TARGET_HASH_SIZE + 2 * NUM_HASH_OUT_ELTS;
+
+/// The chain segment covered by a two-to-one block aggregation proof, i.e. its
+/// first and last blocks.
+///
+/// Hashes are made of `u32` limbs, from the least significant one. Header
+/// hashes are the hashes of the public values returned by
+/// [`extract_block_header_public_values`] for the block.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct ChainSegmentPublicValues<T> {
+    /// Whether the blocks are consecutive, each being the parent of the next
+    /// one. This is only guaranteed for proofs generated with
+    /// [`AllRecursiveCircuits::prove_chain_segment`] all the way down.
+    pub is_chain: T,
+    pub first_block_number: T,
+    /// The hash of the parent of the first block.
+    pub first_parent_hash: [T; TARGET_HASH_SIZE],
+    pub first_header_hash: [T; NUM_HASH_OUT_ELTS],
+    pub last_block_number: T,
+    pub last_block_hash: [T; TARGET_HASH_SIZE],
+    pub last_header_hash: [T; NUM_HASH_OUT_ELTS],
+}
+
+impl<T: Copy> ChainSegmentPublicValues<T> {
+    fn from_slice(values: &[T]) -> Self {
+        const FIRST_BLOCK_NUMBER: usize = 1;
+        const FIRST_PARENT_HASH: usize = FIRST_BLOCK_NUMBER + 1;
+        const FIRST_HEADER_HASH: usize = FIRST_PARENT_HASH + TARGET_HASH_SIZE;
+        const LAST_BLOCK_NUMBER: usize = FIRST_HEADER_HASH + NUM_HASH_OUT_ELTS;
+        const LAST_BLOCK_HASH: usize = LAST_BLOCK_NUMBER + 1;
+        const LAST_HEADER_HASH: usize = LAST_BLOCK_HASH + TARGET_HASH_SIZE;
+
+        let array_at = |start: usize, len: usize| &values[start..start + len];
+        Self {
+            is_chain: values[0],
+            first_block_number: values[FIRST_BLOCK_NUMBER],
+            first_parent_hash: array_at(FIRST_PARENT_HASH, TARGET_HASH_SIZE)
+                .try_into()
+                .unwrap(),
+            first_header_hash: array_at(FIRST_HEADER_HASH, NUM_HASH_OUT_ELTS)
+                .try_into()
+                .unwrap(),
+            last_block_number: values[LAST_BLOCK_NUMBER],
+            last_block_hash: array_at(LAST_BLOCK_HASH, TARGET_HASH_SIZE)
+                .try_into()
+                .unwrap(),
+            last_header_hash: array_at(LAST_HEADER_HASH, NUM_HASH_OUT_ELTS)
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    fn to_vec(self) -> Vec<T> {
+        let mut values = vec![self.is_chain, self.first_block_number];
+        values.extend(self.first_parent_hash);
+        values.extend(self.first_header_hash);
+        values.push(self.last_block_number);
+        values.extend(self.last_block_hash);
+        values.extend(self.last_header_hash);
+        values
+    }
+}
+
+/// Extracts the chain segment covered by a two-to-one block aggregation proof
+/// from its public inputs. It is an error to call this on a slice for a base
+/// proof.
+pub fn extract_chain_segment_public_values<T: Copy>(
+    public_inputs: &[T],
+) -> ChainSegmentPublicValues<T> {
+    const SEGMENT_INDEX_START: usize = NUM_HASH_OUT_ELTS;
+    ChainSegmentPublicValues::from_slice(
+        &public_inputs[SEGMENT_INDEX_START..SEGMENT_INDEX_START + CHAIN_SEGMENT_PUBLIC_VALUES_LEN],
+    )
+}
+
+/// The number of public values returned by
+/// [`extract_block_header_public_values`].
+pub const BLOCK_HEADER_PUBLIC_VALUES_LEN: usize = 6 + 7 * TARGET_HASH_SIZE;
+
+/// Extracts the public values identifying a block from its public values.
+///
+/// These are, in order, the block number and timestamp, the block hash, the
+/// state, transactions and receipts trie roots after the block, the
+/// checkpoint state trie root, the blob gas used and excess blob gas, the
+/// parent beacon block root and the withdrawals root. Hashes are made of `u32`
+/// limbs, from the least significant one, and the blob gas values of two `u32`
+/// limbs, from the least significant one.
+pub fn extract_block_header_public_values<T: Copy>(
+    public_values: &[T; PublicValuesTarget::SIZE],
+) -> [T; BLOCK_HEADER_PUBLIC_VALUES_LEN] {
+    // The layout of the public values is read from `PublicValuesTarget`, by
+    // extracting it from virtual targets standing for their own index.
+    let indices = (0..PublicValuesTarget::SIZE)
+        .map(|index| Target::VirtualTarget { index })
+        .collect::<Vec<_>>();
+    let PublicValuesTarget {
+        trie_roots_after,
+        block_metadata,
+        block_hashes,
+        extra_block_data,
+        ..
+    } = PublicValuesTarget::from_public_inputs(&indices);
+    let value = |target| match target {
+        Target::VirtualTarget { index } => public_values[index],
+        Target::Wire(_) => unreachable!("the layout is extracted from virtual targets"),
+    };
+
+    [block_metadata.block_number, block_metadata.block_timestamp]
+        .into_iter()
+        .chain(block_hashes.cur_hash)
+        .chain(trie_roots_after.state_root)
+        .chain(trie_roots_after.transactions_root)
+        .chain(trie_roots_after.receipts_root)
+        .chain(extra_block_data.checkpoint_state_trie_root)
+        .chain(block_metadata.block_blob_gas_used)
+        .chain(block_metadata.block_excess_blob_gas)
+        .chain(block_metadata.parent_beacon_block_root)
+        .chain(block_metadata.withdrawals_root)
+        .map(value)
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("the header has a fixed length"))
+}
+
+/// The targets of a block proof, verified within an external circuit by
+/// [`verify_block_proof_circuit`].
+#[derive(Eq, PartialEq, Debug)]
+pub struct BlockProofTarget<const D: usize> {
+    /// The block proof, along with its public inputs.
+    pub proof_with_pis: ProofWithPublicInputsTarget<D>,
+    /// The public values of the block, read from the public inputs of the
+    /// proof.
+    pub public_values: PublicValuesTarget,
+}
+
+impl<const D: usize> BlockProofTarget<D> {
+    /// Sets the block proof verified by the external circuit.
+    pub fn set_proof<F, C>(
+        &self,
+        witness: &mut PartialWitness<F>,
+        block_proof: &ProofWithPublicInputs<F, C, D>,
+    ) where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        witness.set_proof_with_pis_target(&self.proof_with_pis, block_proof);
+    }
+}
+
+/// Adds the targets of a proof of the block circuit with the given verifier
+/// data, e.g. [`VerifierArtifact::block`], to an external circuit.
+///
+/// The proof is only constrained once passed to
+/// [`verify_block_proof_circuit`].
+pub fn add_virtual_block_proof<F, C, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    block_verifier_data: &VerifierCircuitData<F, C, D>,
+) -> BlockProofTarget<D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let proof_with_pis = builder.add_virtual_proof_with_pis(&block_verifier_data.common);
+    let public_values = PublicValuesTarget::from_public_inputs(&proof_with_pis.public_inputs);
+    BlockProofTarget {
+        proof_with_pis,
+        public_values,
+    }
+}
+
+/// Verifies a block proof added by [`add_virtual_block_proof`] within an
+/// external circuit, as [`VerifierArtifact::verify_block`] does natively.
+///
+/// Block proofs being cyclic, this also checks that the verifier data they
+/// carry in their public inputs, against which their parent block proof was
+/// verified, is the one of the block circuit.
+pub fn verify_block_proof_circuit<F, C, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    block_proof: &BlockProofTarget<D>,
+    block_verifier_data: &VerifierCircuitData<F, C, D>,
+) where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let verifier_data = builder.constant_verifier_data(&block_verifier_data.verifier_only);
+    builder.verify_proof::<C>(
+        &block_proof.proof_with_pis,
+        &verifier_data,
+        &block_verifier_data.common,
+    );
+
+    // The verifier data is appended to the public inputs as done by
+    // `CircuitBuilder::add_verifier_data_public_inputs`.
+    let cyclic_vk = verifier_data.circuit_digest.elements.into_iter().chain(
+        verifier_data
+            .constants_sigmas_cap
+            .0
+            .iter()
+            .flat_map(|hash| hash.elements),
+    );
+    let public_inputs = &block_proof.proof_with_pis.public_inputs;
+    let cyclic_vk_len = NUM_HASH_OUT_ELTS * (1 + verifier_data.constants_sigmas_cap.0.len());
+    for (expected, &actual) in zip_eq(
+        cyclic_vk,
+        &public_inputs[public_inputs.len() - cyclic_vk_len..],
+    ) {
+        builder.connect(expected, actual);
+    }
+}
+
 /// Computes the length added to the public inputs vector by
 /// [`CircuitBuilder::add_verifier_data_public_inputs`].
 pub const fn verification_key_len<F, C, const D: usize>(circuit: &CircuitData<F, C, D>) -> usize
@@ -2695,3 +3453,148 @@ where
     circuit.verifier_only.circuit_digest.elements.len()
         + (1 << circuit.common.config.fri_config.cap_height) * NUM_HASH_OUT_ELTS
 }
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::Witness;
+
+    use super::*;
+    use crate::proof::BlockMetadata;
+    use crate::util::get_h256;
+
+    type F = GoldilocksField;
+    const D: usize = 2;
+
+    /// Encodes `public_values` as the public inputs of a proof, as the circuits
+    /// expose them.
+    fn public_inputs(public_values: &PublicValues) -> Vec<F> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder.add_virtual_targets(PublicValuesTarget::SIZE);
+        let mut witness = PartialWitness::new();
+        set_public_value_targets(
+            &mut witness,
+            &PublicValuesTarget::from_public_inputs(&targets),
+            public_values,
+        )
+        .unwrap();
+        targets
+            .iter()
+            .map(|&target| witness.try_get_target(target).unwrap_or(F::ZERO))
+            .collect()
+    }
+
+    fn block_public_values() -> PublicValues {
+        PublicValues {
+            trie_roots_after: TrieRoots {
+                state_root: H256::repeat_byte(0x11),
+                transactions_root: H256::repeat_byte(0x22),
+                receipts_root: H256::repeat_byte(0x33),
+            },
+            block_metadata: BlockMetadata {
+                block_number: 7.into(),
+                block_chain_id: 1.into(),
+                block_blob_gas_used: ((3u64 << 32) + 5).into(),
+                block_excess_blob_gas: ((4u64 << 32) + 6).into(),
+                parent_beacon_block_root: H256::repeat_byte(0x44),
+                withdrawals_root: H256::repeat_byte(0x55),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exposes_blob_fields_and_withdrawals_root() {
+        let public_values = block_public_values();
+        let public_inputs = public_inputs(&public_values);
+
+        let decoded = PublicValues::from_public_inputs(&public_inputs).unwrap();
+        assert_eq!(decoded.block_metadata, public_values.block_metadata);
+
+        let header =
+            extract_block_header_public_values(extract_block_public_values(&public_inputs));
+        assert_eq!(header[0], F::from_canonical_u32(7));
+        assert_eq!(header[42..46], [5, 3, 6, 4].map(F::from_canonical_u32));
+        assert_eq!(get_h256(&header[46..54]), H256::repeat_byte(0x44));
+        assert_eq!(get_h256(&header[54..62]), H256::repeat_byte(0x55));
+    }
+
+    #[test]
+    fn checks_expected_block_values() {
+        let public_values = block_public_values();
+        let public_inputs = public_inputs(&public_values);
+        let expected = ExpectedBlockValues {
+            trie_roots_after: public_values.trie_roots_after.clone(),
+            block_number: 7.into(),
+            chain_id: 1.into(),
+        };
+        let checked = check_block_public_values(&public_inputs, &expected).unwrap();
+        assert_eq!(checked.trie_roots_after, public_values.trie_roots_after);
+        assert_eq!(checked.block_metadata, public_values.block_metadata);
+
+        let mismatch = |expected: ExpectedBlockValues| {
+            check_block_public_values(&public_inputs, &expected).unwrap_err()
+        };
+        assert_eq!(
+            mismatch(ExpectedBlockValues {
+                trie_roots_after: TrieRoots {
+                    state_root: H256::zero(),
+                    ..expected.trie_roots_after.clone()
+                },
+                ..expected.clone()
+            }),
+            BlockVerificationError::StateRootMismatch {
+                expected: H256::zero(),
+                actual: H256::repeat_byte(0x11),
+            }
+        );
+        assert_eq!(
+            mismatch(ExpectedBlockValues {
+                trie_roots_after: TrieRoots {
+                    transactions_root: H256::zero(),
+                    ..expected.trie_roots_after.clone()
+                },
+                ..expected.clone()
+            }),
+            BlockVerificationError::TransactionsRootMismatch {
+                expected: H256::zero(),
+                actual: H256::repeat_byte(0x22),
+            }
+        );
+        assert_eq!(
+            mismatch(ExpectedBlockValues {
+                trie_roots_after: TrieRoots {
+                    receipts_root: H256::zero(),
+                    ..expected.trie_roots_after.clone()
+                },
+                ..expected.clone()
+            }),
+            BlockVerificationError::ReceiptsRootMismatch {
+                expected: H256::zero(),
+                actual: H256::repeat_byte(0x33),
+            }
+        );
+        assert_eq!(
+            mismatch(ExpectedBlockValues {
+                block_number: 8.into(),
+                ..expected.clone()
+            }),
+            BlockVerificationError::BlockNumberMismatch {
+                expected: 8.into(),
+                actual: 7.into(),
+            }
+        );
+        assert_eq!(
+            mismatch(ExpectedBlockValues {
+                chain_id: 2.into(),
+                ..expected
+            }),
+            BlockVerificationError::ChainIdMismatch {
+                expected: 2.into(),
+                actual: 1.into(),
+            }
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Typed reasons for which the kernel rejects a transaction.
+//!
+//! Invalid transactions are not aborting the execution: the kernel skips them,
+//! charging no gas and emitting a failed receipt. The prover observes the
+//! kernel entry points handling each rejection reason, so that the outcome of
+//! such transactions can be reported by [`simulate`](crate::simulate), as
+//! [`TxnStatus::Rejected`](crate::simulation::TxnStatus::Rejected).
+
+#[cfg(feature = "generation")]
+use std::collections::HashMap;
+
+use ethereum_types::{H256, U256};
+#[cfg(feature = "generation")]
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "generation")]
+use crate::cpu::kernel::aggregator::KERNEL;
+
+/// The reason why a transaction has been rejected by the kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum InvalidTxnReason {
+    /// The gas limit of the transaction is lower than its intrinsic gas.
+    IntrinsicGasExceedsGasLimit,
+    /// The gas limit of the transaction exceeds the block gas limit.
+    GasLimitExceedsBlockGasLimit,
+    /// The nonce of the transaction does not match the sender nonce.
+    NonceMismatch,
+    /// The sender nonce is already at its maximum value, see EIP-2681.
+    NonceOverflow,
+    /// The sender has deployed code, see EIP-3607.
+    SenderNotEoa,
+    /// The sender balance does not cover the upfront gas cost and value.
+    InsufficientBalance,
+    /// The chain ID of the transaction does not match the block chain ID.
+    ChainIdMismatch,
+    /// The max fee per gas is lower than the base fee or the max priority
+    /// fee per gas.
+    FeeCapTooLow,
+}
+
+#[cfg(feature = "generation")]
+impl InvalidTxnReason {
+    pub(crate) const ALL: [Self; 8] = [
+        Self::IntrinsicGasExceedsGasLimit,
+        Self::GasLimitExceedsBlockGasLimit,
+        Self::NonceMismatch,
+        Self::NonceOverflow,
+        Self::SenderNotEoa,
+        Self::InsufficientBalance,
+        Self::ChainIdMismatch,
+        Self::FeeCapTooLow,
+    ];
+
+    /// The kernel label handling this rejection reason.
+    pub(crate) const fn label(&self) -> &'static str {
+        match self {
+            Self::IntrinsicGasExceedsGasLimit => "invalid_txn_intrinsic_gas",
+            Self::GasLimitExceedsBlockGasLimit => "invalid_txn_block_gas_limit",
+            Self::NonceMismatch => "invalid_txn_nonce_mismatch",
+            Self::NonceOverflow => "invalid_txn_nonce_overflow",
+            Self::SenderNotEoa => "invalid_txn_sender_not_eoa",
+            Self::InsufficientBalance => "invalid_txn_insufficient_balance",
+            Self::ChainIdMismatch => "invalid_txn_chain_id_mismatch",
+            Self::FeeCapTooLow => "invalid_txn_fee_cap",
+        }
+    }
+
+    /// Returns the rejection reason handled at the given kernel offset, if
+    /// any.
+    pub(crate) fn from_kernel_offset(offset: usize) -> Option<Self> {
+        static OFFSETS: Lazy<HashMap<usize, InvalidTxnReason>> = Lazy::new(|| {
+            InvalidTxnReason::ALL
+                .into_iter()
+                .map(|reason| (KERNEL.global_labels[reason.label()], reason))
+                .collect()
+        });
+
+        OFFSETS.get(&offset).copied()
+    }
+}
+
+impl std::fmt::Display for InvalidTxnReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            Self::IntrinsicGasExceedsGasLimit => "intrinsic gas exceeds gas limit",
+            Self::GasLimitExceedsBlockGasLimit => "gas limit exceeds block gas limit",
+            Self::NonceMismatch => "nonce mismatch",
+            Self::NonceOverflow => "sender nonce overflow",
+            Self::SenderNotEoa => "sender is not an EOA",
+            Self::InsufficientBalance => "insufficient sender balance",
+            Self::ChainIdMismatch => "chain ID mismatch",
+            Self::FeeCapTooLow => "max fee per gas too low",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+/// A transaction rejected by the kernel.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InvalidTxn {
+    /// The index of the transaction within its block.
+    pub txn_number: U256,
+    /// The hash of the transaction.
+    pub txn_hash: H256,
+    /// The reason why the transaction has been rejected.
+    pub reason: InvalidTxnReason,
+}
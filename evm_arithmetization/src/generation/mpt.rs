@@ -3,31 +3,23 @@ use std::collections::HashMap;
 
 use bytes::Bytes;
 use ethereum_types::{Address, BigEndianHash, H256, U256};
-use keccak_hash::keccak;
 use mpt_trie::nibbles::{Nibbles, NibblesIntern};
 use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
 use rlp::{Decodable, DecoderError, Encodable, PayloadInfo, Rlp, RlpStream};
 use rlp_derive::{RlpDecodable, RlpEncodable};
 use serde::{Deserialize, Serialize};
+pub use zk_evm_common::{AccountRlp, LegacyReceiptRlp, LogRlp};
 
 use super::linked_list::empty_list_mem;
 use super::prover_input::{ACCOUNTS_LINKED_LIST_NODE_SIZE, STORAGE_LINKED_LIST_NODE_SIZE};
 use super::TrimmedTrieInputs;
 use crate::cpu::kernel::constants::trie_type::PartialTrieType;
-use crate::generation::TrieInputs;
 use crate::memory::segments::Segment;
 use crate::util::h2u;
 use crate::witness::errors::{ProgramError, ProverInputError};
+use crate::witness::memory::{InitialSegments, MemorySegmentState};
 use crate::Node;
 
-#[derive(RlpEncodable, RlpDecodable, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct AccountRlp {
-    pub nonce: U256,
-    pub balance: U256,
-    pub storage_root: H256,
-    pub code_hash: H256,
-}
-
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TrieRootPtrs {
     pub state_root_ptr: Option<usize>,
@@ -35,43 +27,6 @@ pub struct TrieRootPtrs {
     pub receipt_root_ptr: usize,
 }
 
-impl Default for AccountRlp {
-    fn default() -> Self {
-        Self {
-            nonce: U256::zero(),
-            balance: U256::zero(),
-            storage_root: HashedPartialTrie::from(Node::Empty).hash(),
-            code_hash: keccak([]),
-        }
-    }
-}
-
-#[derive(RlpEncodable, RlpDecodable, Debug, Clone)]
-pub struct LogRlp {
-    pub address: Address,
-    pub topics: Vec<H256>,
-    pub data: Bytes,
-}
-
-#[derive(RlpEncodable, RlpDecodable, Debug, Clone)]
-pub struct LegacyReceiptRlp {
-    pub status: bool,
-    pub cum_gas_used: U256,
-    pub bloom: Bytes,
-    pub logs: Vec<LogRlp>,
-}
-
-impl LegacyReceiptRlp {
-    // RLP encode the receipt and prepend the tx type.
-    pub fn encode(&self, tx_type: u8) -> Vec<u8> {
-        let mut bytes = rlp::encode(self).to_vec();
-        if tx_type != 0 {
-            bytes.insert(0, tx_type);
-        }
-        bytes
-    }
-}
-
 /// Decodes a transaction receipt from an RLP string, outputting a tuple
 /// consisting of:
 ///   - the receipt's [`PayloadInfo`],
@@ -82,6 +37,8 @@ pub fn decode_receipt(rlp: &[u8]) -> Result<(PayloadInfo, usize, LegacyReceiptRl
         1 => 1,
         2 => 2,
         3 => 3,
+        #[cfg(feature = "op_deposit")]
+        0x7e => 0x7e,
         _ => 0,
     };
 
@@ -501,6 +458,23 @@ where
     }
 }
 
+/// Returns whether the path of `key` in `trie` ends in a hash node, in which
+/// case the value at `key` cannot be known from `trie`.
+pub(crate) fn is_hashed_out(trie: &HashedPartialTrie, mut key: Nibbles) -> bool {
+    match trie.deref() {
+        Node::Hash(_) => true,
+        Node::Empty | Node::Leaf { .. } => false,
+        Node::Branch { children, .. } => {
+            !key.is_empty() && is_hashed_out(&children[key.pop_next_nibble_front() as usize], key)
+        }
+        Node::Extension { nibbles, child } => {
+            nibbles.count <= key.count
+                && key.pop_nibbles_front(nibbles.count) == *nibbles
+                && is_hashed_out(child, key)
+        }
+    }
+}
+
 /// A type alias used to gather:
 ///     - the trie root pointers for all tries
 ///     - the vector of state trie leaves
@@ -514,7 +488,7 @@ type TriePtrsLinkedLists = (
 );
 
 pub(crate) fn load_linked_lists_and_txn_and_receipt_mpts(
-    trie_inputs: &TrieInputs,
+    trie_inputs: &TrimmedTrieInputs,
 ) -> Result<TriePtrsLinkedLists, ProgramError> {
     let mut state_leaves =
         empty_list_mem::<ACCOUNTS_LINKED_LIST_NODE_SIZE>(Segment::AccountsLinkedList).to_vec();
@@ -561,6 +535,33 @@ pub(crate) fn load_linked_lists_and_txn_and_receipt_mpts(
     ))
 }
 
+/// Loads the preinitialized segments at the start of a batch from its input
+/// tries, along with the pointers to the roots of its tries.
+pub(crate) fn load_preinitialized_segments(
+    trie_inputs: &TrimmedTrieInputs,
+) -> Result<(TrieRootPtrs, InitialSegments), ProgramError> {
+    let (trie_root_ptrs, state_leaves, storage_leaves, trie_data) =
+        load_linked_lists_and_txn_and_receipt_mpts(trie_inputs)?;
+
+    let segments = HashMap::from([
+        (
+            Segment::AccountsLinkedList,
+            MemorySegmentState {
+                content: state_leaves,
+            },
+        ),
+        (
+            Segment::StorageLinkedList,
+            MemorySegmentState {
+                content: storage_leaves,
+            },
+        ),
+        (Segment::TrieData, MemorySegmentState { content: trie_data }),
+    ]);
+
+    Ok((trie_root_ptrs, segments))
+}
+
 pub(crate) fn load_state_mpt(
     trie_inputs: &TrimmedTrieInputs,
     trie_data: &mut Vec<Option<U256>>,
@@ -683,3 +684,35 @@ pub mod transaction_testing {
         pub s: U256,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hashed_out_keys() {
+        let key = |byte| Nibbles::from_h256_be(H256::repeat_byte(byte));
+        let mut trie = HashedPartialTrie::default();
+        trie.insert(key(0x11), vec![1]).unwrap();
+        trie.insert(key(0x12), vec![2]).unwrap();
+        trie.insert(key(0x21), vec![3]).unwrap();
+        assert!(!is_hashed_out(&trie, key(0x33)));
+
+        // Hash out the subtrie of the keys starting with 0x1.
+        let Node::Branch { children, value } = trie.deref() else {
+            panic!("expected a branch at the root");
+        };
+        let mut children = children.clone();
+        children[1] = Node::Hash(children[1].hash()).into();
+        let trie = HashedPartialTrie::new(Node::Branch {
+            children,
+            value: value.clone(),
+        });
+
+        assert!(is_hashed_out(&trie, key(0x11)));
+        assert!(is_hashed_out(&trie, key(0x13)));
+        assert!(!is_hashed_out(&trie, key(0x21)));
+        assert!(!is_hashed_out(&trie, key(0x22)));
+        assert!(!is_hashed_out(&trie, key(0x33)));
+    }
+}
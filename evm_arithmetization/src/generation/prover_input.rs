@@ -6,12 +6,15 @@ use std::str::FromStr;
 use anyhow::{bail, Error, Result};
 use ethereum_types::{BigEndianHash, H256, U256, U512};
 use itertools::Itertools;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
 use num_bigint::BigUint;
 use plonky2::field::types::Field;
-use serde::{Deserialize, Serialize};
 
 use super::linked_list::LinkedList;
-use super::mpt::load_state_mpt;
+use super::mpt::{is_hashed_out, load_state_mpt, AccountRlp};
+use crate::cpu::kernel::ast::ProverInputFn;
 use crate::cpu::kernel::cancun_constants::KZG_VERSIONED_HASH;
 use crate::cpu::kernel::constants::cancun_constants::{
     BLOB_BASE_FEE_UPDATE_FRACTION, G2_TRUSTED_SETUP_POINT, MIN_BASE_FEE_PER_BLOB_GAS,
@@ -22,7 +25,8 @@ use crate::cpu::kernel::interpreter::simulate_cpu_and_get_user_jumps;
 use crate::curve_pairings::{bls381, CurveAff, CyclicGroup};
 use crate::extension_tower::{FieldExt, Fp12, Fp2, BLS381, BLS_BASE, BLS_SCALAR, BN254, BN_BASE};
 use crate::generation::prover_input::EvmField::{
-    Bls381Base, Bls381Scalar, Bn254Base, Bn254Scalar, Secp256k1Base, Secp256k1Scalar,
+    Bls381Base, Bls381Scalar, Bn254Base, Bn254Scalar, P256Base, P256Scalar, Secp256k1Base,
+    Secp256k1Scalar,
 };
 use crate::generation::prover_input::FieldOp::{Inverse, Sqrt};
 use crate::generation::state::GenerationState;
@@ -30,28 +34,16 @@ use crate::memory::segments::Segment;
 use crate::memory::segments::Segment::BnPairing;
 use crate::util::{biguint_to_mem_vec, mem_vec_to_biguint, sha2, u256_to_u8, u256_to_usize};
 use crate::witness::errors::ProverInputError::*;
-use crate::witness::errors::{ProgramError, ProverInputError};
+use crate::witness::errors::{self, ProgramError, ProverInputError};
 use crate::witness::memory::MemoryAddress;
-use crate::witness::operation::CONTEXT_SCALING_FACTOR;
+use crate::witness::memory::CONTEXT_SCALING_FACTOR;
 use crate::witness::util::{current_context_peek, stack_peek};
 
-/// Prover input function represented as a scoped function name.
-/// Example: `PROVER_INPUT(ff::bn254_base::inverse)` is represented as
-/// `ProverInputFn([ff, bn254_base, inverse])`.
-#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
-pub struct ProverInputFn(Vec<String>);
-
 pub const ADDRESSES_ACCESS_LIST_LEN: usize = 2;
 pub const STORAGE_KEYS_ACCESS_LIST_LEN: usize = 4;
 pub const ACCOUNTS_LINKED_LIST_NODE_SIZE: usize = 4;
 pub const STORAGE_LINKED_LIST_NODE_SIZE: usize = 5;
 
-impl From<Vec<String>> for ProverInputFn {
-    fn from(v: Vec<String>) -> Self {
-        Self(v)
-    }
-}
-
 impl<F: Field> GenerationState<F> {
     pub(crate) fn prover_input(&mut self, input_fn: &ProverInputFn) -> Result<U256, ProgramError> {
         match input_fn.0[0].as_str() {
@@ -71,6 +63,7 @@ impl<F: Field> GenerationState<F> {
             "access_lists" => self.run_access_lists(input_fn),
             "linked_list" => self.run_linked_list(input_fn),
             "ger" => self.run_global_exit_roots(),
+            "burn_addr" => self.run_burn_addr(),
             "kzg_point_eval" => self.run_kzg_point_eval(),
             "kzg_point_eval_2" => self.run_kzg_point_eval_2(),
             _ => Err(ProgramError::ProverInputError(InvalidFunction)),
@@ -80,6 +73,7 @@ impl<F: Field> GenerationState<F> {
     fn run_end_of_txns(&mut self) -> Result<U256, ProgramError> {
         // Reset the jumpdest table before the next transaction.
         self.jumpdest_table = None;
+        self.jumpdest_table_simulated = false;
         let end = self.next_txn_index == self.inputs.txn_hashes.len();
         if end {
             Ok(U256::one())
@@ -304,11 +298,13 @@ impl<F: Field> GenerationState<F> {
         }
     }
 
-    /// Generate either the next used jump address, the proof for the last
-    /// jump address, or a non-jumpdest proof.
+    /// Generate either the next used jump address, a context in which it has
+    /// already been verified, the proof for the last jump address, or a
+    /// non-jumpdest proof.
     fn run_jumpdest_table(&mut self, input_fn: &ProverInputFn) -> Result<U256, ProgramError> {
         match input_fn.0[1].as_str() {
             "next_address" => self.run_next_jumpdest_table_address(),
+            "verified_context" => self.run_verified_jumpdest_context(),
             "next_proof" => self.run_next_jumpdest_table_proof(),
             "non_jumpdest_proof" => self.run_next_non_jumpdest_proof(),
             _ => Err(ProgramError::ProverInputError(InvalidInput)),
@@ -378,12 +374,31 @@ impl<F: Field> GenerationState<F> {
             .ok_or(ProgramError::ProverInputError(OutOfGerData))
     }
 
+    /// Returns the burn address of the chain, or `U256::MAX` if the base fees
+    /// are burnt.
+    fn run_burn_addr(&mut self) -> Result<U256, ProgramError> {
+        Ok(self
+            .inputs
+            .burn_addr
+            .map_or(U256::MAX, |addr| U256::from_big_endian(&addr.0)))
+    }
+
     /// Returns the next used jump address.
     fn run_next_jumpdest_table_address(&mut self) -> Result<U256, ProgramError> {
         let context = u256_to_usize(stack_peek(self, 0)? >> CONTEXT_SCALING_FACTOR)?;
 
-        if self.jumpdest_table.is_none() {
-            self.generate_jumpdest_table()?;
+        let has_context_table = self
+            .jumpdest_table
+            .as_ref()
+            .is_some_and(|jumpdest_table| jumpdest_table.contains_key(&context));
+        if !has_context_table {
+            if let Some(proofs) = self.precomputed_jumpdest_proofs(context)? {
+                self.jumpdest_table
+                    .get_or_insert_with(HashMap::new)
+                    .insert(context, proofs);
+            } else if !self.jumpdest_table_simulated {
+                self.generate_jumpdest_table()?;
+            }
         }
 
         let Some(jumpdest_table) = &mut self.jumpdest_table else {
@@ -402,6 +417,43 @@ impl<F: Field> GenerationState<F> {
         }
     }
 
+    /// Returns `ctx + 1` for another context `ctx` running the same code, in
+    /// which the jump address on the top of the stack has already been
+    /// verified, or zero if there is none. In the former case the kernel skips
+    /// the proof of the address, so it is discarded.
+    fn run_verified_jumpdest_context(&mut self) -> Result<U256, ProgramError> {
+        let address = u256_to_usize(stack_peek(self, 0)?)?;
+        let context = u256_to_usize(stack_peek(self, 1)? >> CONTEXT_SCALING_FACTOR)?;
+        let code_hash = self.get_code_hash(context);
+        if code_hash.is_zero() {
+            return Ok(U256::zero());
+        }
+
+        self.jumpdest_contexts
+            .entry(code_hash)
+            .or_default()
+            .insert(context);
+        let verified_context = self.jumpdest_contexts[&code_hash]
+            .iter()
+            .copied()
+            .find(|&ctx| {
+                ctx != context
+                    && self.memory.get_with_init(MemoryAddress::new(
+                        ctx,
+                        Segment::JumpdestBits,
+                        address,
+                    )) == U256::one()
+            });
+
+        match verified_context {
+            Some(verified_context) => {
+                self.run_next_jumpdest_table_proof()?;
+                Ok((verified_context + 1).into())
+            }
+            None => Ok(U256::zero()),
+        }
+    }
+
     /// Returns the proof for the last jump address.
     fn run_next_jumpdest_table_proof(&mut self) -> Result<U256, ProgramError> {
         let context = u256_to_usize(stack_peek(self, 1)? >> CONTEXT_SCALING_FACTOR)?;
@@ -549,15 +601,26 @@ impl<F: Field> GenerationState<F> {
                 Segment::StorageLinkedList,
             )?;
 
-        if let Some(([.., pred_ptr], _, _)) = storage_linked_list.tuple_windows().find(
-            |&(_, [prev_addr, prev_key, ..], [next_addr, next_key, ..])| {
-                let prev_is_less_or_equal = (prev_addr < addr || prev_addr == U256::MAX)
-                    || (prev_addr == addr && prev_key <= key);
-                let next_is_strictly_larger =
-                    next_addr > addr || (next_addr == addr && next_key > key);
-                prev_is_less_or_equal && next_is_strictly_larger
-            },
-        ) {
+        if let Some(([.., pred_ptr], [pred_addr, pred_key, ..], _)) =
+            storage_linked_list.tuple_windows().find(
+                |&(_, [prev_addr, prev_key, ..], [next_addr, next_key, ..])| {
+                    let prev_is_less_or_equal = (prev_addr < addr || prev_addr == U256::MAX)
+                        || (prev_addr == addr && prev_key <= key);
+                    let next_is_strictly_larger =
+                        next_addr > addr || (next_addr == addr && next_key > key);
+                    prev_is_less_or_equal && next_is_strictly_larger
+                },
+            )
+        {
+            // A slot missing from the list is read as zero, which is only correct if
+            // the input storage trie proves its absence.
+            if (pred_addr, pred_key) != (addr, key)
+                && let Some(unhydrated) = self.unhydrated_slot(addr, key)
+            {
+                return Err(ProgramError::ProverInputError(UnhydratedStorage(
+                    unhydrated,
+                )));
+            }
             Ok((pred_ptr - U256::from(Segment::StorageLinkedList as usize))
                 / U256::from(STORAGE_LINKED_LIST_NODE_SIZE))
         } else {
@@ -565,6 +628,36 @@ impl<F: Field> GenerationState<F> {
         }
     }
 
+    /// Returns the slot `key` of the account `addr`, both hashed, if it lies
+    /// under a hash node of the input tries.
+    fn unhydrated_slot(&self, addr: U256, key: U256) -> Option<errors::UnhydratedStorage> {
+        let hashed_address = H256::from_uint(&addr);
+        let hashed_slot = H256::from_uint(&key);
+        let tries = &self.inputs.trimmed_tries;
+
+        let hashed_out = match tries
+            .storage_tries
+            .iter()
+            .find(|(address, _)| *address == hashed_address)
+        {
+            Some((_, storage_trie)) => {
+                is_hashed_out(storage_trie, Nibbles::from_h256_be(hashed_slot))
+            }
+            // Accounts without a storage trie in the inputs are only hydrated if
+            // their storage is empty.
+            None => tries
+                .state_trie
+                .get(Nibbles::from_h256_be(hashed_address))
+                .and_then(|account| rlp::decode::<AccountRlp>(account).ok())
+                .is_some_and(|account| account.storage_root != HashedPartialTrie::default().hash()),
+        };
+
+        hashed_out.then_some(errors::UnhydratedStorage {
+            hashed_address,
+            hashed_slot,
+        })
+    }
+
     /// Returns a pointer `ptr` to a node of the form [next_addr, ..]  in the
     /// list such that `next_addr = addr` and `addr` is the top of the stack.
     /// If the element is not in the list, loops forever.
@@ -651,8 +744,9 @@ impl<F: Field> GenerationState<F> {
         let proof_hi = stack_peek(self, 5)?;
         let proof_lo = stack_peek(self, 6)?;
 
-        // Validate scalars
-        if z > BLS_SCALAR || y > BLS_SCALAR {
+        // Validate scalars, which must be canonical elements of the BLS12-381 scalar
+        // field.
+        if z >= BLS_SCALAR || y >= BLS_SCALAR {
             return Ok(U256::zero());
         }
 
@@ -797,11 +891,42 @@ impl<F: Field> GenerationState<F> {
     fn generate_jumpdest_table(&mut self) -> Result<(), ProgramError> {
         // Simulate the user's code and (unnecessarily) part of the kernel code,
         // skipping the validate table call
-        self.jumpdest_table = simulate_cpu_and_get_user_jumps("terminate_common", self);
+        let simulated_table = simulate_cpu_and_get_user_jumps("terminate_common", self);
+
+        // Contexts already served from precomputed analyses keep their proofs,
+        // and the simulated table is kept until the end of the transaction.
+        let jumpdest_table = self.jumpdest_table.get_or_insert_with(HashMap::new);
+        for (context, proofs) in simulated_table {
+            jumpdest_table.entry(context).or_insert(proofs);
+        }
+        self.jumpdest_table_simulated = true;
 
         Ok(())
     }
 
+    /// Returns the proofs and addresses of the precomputed JUMPDEST analysis of
+    /// the code of `context`, in the format of `get_proofs_and_jumpdests`, if
+    /// the inputs have one for its code hash.
+    fn precomputed_jumpdest_proofs(
+        &self,
+        context: usize,
+    ) -> Result<Option<Vec<usize>>, ProgramError> {
+        if self.inputs.jumpdest_tables.is_empty() {
+            return Ok(None);
+        }
+
+        let code = self.get_code(context)?;
+        let Some(jumpdests) = self.inputs.jumpdest_tables.get(&keccak(&code)) else {
+            return Ok(None);
+        };
+        Ok(Some(match jumpdests.last() {
+            Some(&largest_address) => {
+                get_proofs_and_jumpdests(&code, largest_address, jumpdests.clone())
+            }
+            None => vec![],
+        }))
+    }
+
     /// Given a HashMap containing the contexts and the jumpdest addresses,
     /// compute their respective proofs, by calling
     /// `get_proofs_and_jumpdests`
@@ -809,6 +934,7 @@ impl<F: Field> GenerationState<F> {
         &mut self,
         jumpdest_table: HashMap<usize, BTreeSet<usize>>,
     ) {
+        self.jumpdest_table_simulated = true;
         self.jumpdest_table = Some(HashMap::from_iter(jumpdest_table.into_iter().map(
             |(ctx, jumpdest_table)| {
                 let code = self.get_code(ctx).unwrap();
@@ -839,6 +965,14 @@ impl<F: Field> GenerationState<F> {
         Ok(code)
     }
 
+    fn get_code_hash(&self, context: usize) -> H256 {
+        H256::from_uint(&self.memory.get_with_init(MemoryAddress::new(
+            context,
+            Segment::ContextMetadata,
+            ContextMetadata::CodeHash.unscale(),
+        )))
+    }
+
     fn get_code_len(&self, context: usize) -> Result<usize, ProgramError> {
         let code_len = u256_to_usize(self.memory.get_with_init(MemoryAddress::new(
             context,
@@ -984,6 +1118,8 @@ enum EvmField {
     Bn254Scalar,
     Secp256k1Base,
     Secp256k1Scalar,
+    P256Base,
+    P256Scalar,
 }
 
 enum FieldOp {
@@ -1002,6 +1138,8 @@ impl FromStr for EvmField {
             "bn254_scalar" => Bn254Scalar,
             "secp256k1_base" => Secp256k1Base,
             "secp256k1_scalar" => Secp256k1Scalar,
+            "p256_base" => P256Base,
+            "p256_scalar" => P256Scalar,
             _ => bail!("Unrecognized field."),
         })
     }
@@ -1036,6 +1174,16 @@ impl EvmField {
                     .unwrap()
                     .into()
             }
+            EvmField::P256Base => {
+                U256::from_str("0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff")
+                    .unwrap()
+                    .into()
+            }
+            EvmField::P256Scalar => {
+                U256::from_str("0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")
+                    .unwrap()
+                    .into()
+            }
         }
     }
 
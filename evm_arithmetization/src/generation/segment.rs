@@ -0,0 +1,533 @@
+use anyhow::anyhow;
+use ethereum_types::{BigEndianHash, H256, U256};
+use keccak_hash::keccak;
+use log::log_enabled;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+use GlobalMetadata::{
+    ReceiptTrieRootDigestAfter, ReceiptTrieRootDigestBefore, StateTrieRootDigestAfter,
+    StateTrieRootDigestBefore, TransactionTrieRootDigestAfter, TransactionTrieRootDigestBefore,
+};
+
+use super::state::{GenerationState, State};
+use super::trie_extractor::{get_receipt_trie, get_state_trie, get_txn_trie};
+use super::{
+    cpu_cycle_limit, GenerationInputs, TrieInputs, TrimmedGenerationInputs, TrimmedTrieInputs,
+};
+use crate::all_stark::{AllStark, AllStarkConfig, Table, NUM_TABLES};
+use crate::cpu::columns::CpuColumnsView;
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::memory::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES};
+use crate::proof::{ExtraBlockData, MemCap, PublicValues, RegistersData, TrieRoots};
+use crate::prover::GenerationSegmentData;
+use crate::util::{h2u, u256_to_usize};
+use crate::witness::memory::{MemoryAddress, MemoryChannel, MemoryState};
+use crate::witness::state::RegistersState;
+use crate::witness::util::mem_write_log;
+
+impl TrieInputs {
+    pub(crate) fn trim(&self) -> TrimmedTrieInputs {
+        TrimmedTrieInputs {
+            state_trie: self.state_trie.clone(),
+            transactions_trie: self.transactions_trie.clone(),
+            receipts_trie: self.receipts_trie.clone(),
+            storage_tries: self.storage_tries.clone(),
+        }
+    }
+}
+
+impl GenerationInputs {
+    /// Outputs a trimmed version of the `GenerationInputs`, that do not contain
+    /// the fields that have already been processed during pre-initialization,
+    /// namely: the signed transactions, and the withdrawals.
+    pub(crate) fn trim(&self) -> TrimmedGenerationInputs {
+        let txn_hashes = self
+            .signed_txns
+            .iter()
+            .map(|tx_bytes| keccak(&tx_bytes[..]))
+            .collect();
+
+        TrimmedGenerationInputs {
+            trimmed_tries: self.tries.trim(),
+            txn_number_before: self.txn_number_before,
+            gas_used_before: self.gas_used_before,
+            gas_used_after: self.gas_used_after,
+            txn_hashes,
+            trie_roots_before: TrieRoots {
+                state_root: self.tries.state_trie.hash(),
+                transactions_root: self.tries.transactions_trie.hash(),
+                receipts_root: self.tries.receipts_trie.hash(),
+            },
+            trie_roots_after: self.trie_roots_after.clone(),
+            checkpoint_state_trie_root: self.checkpoint_state_trie_root,
+            burn_addr: self.burn_addr,
+            contract_code: self.contract_code.clone(),
+            jumpdest_tables: self.jumpdest_tables.clone(),
+            block_metadata: self.block_metadata.clone(),
+            block_hashes: self.block_hashes.clone(),
+        }
+    }
+}
+
+fn apply_metadata_and_tries_memops<F: RichField + Extendable<D>, const D: usize>(
+    state: &mut GenerationState<F>,
+    inputs: &TrimmedGenerationInputs,
+    registers_before: &RegistersData,
+    registers_after: &RegistersData,
+) {
+    let metadata = &inputs.block_metadata;
+    let trie_roots_after = &inputs.trie_roots_after;
+    let fields = [
+        (
+            GlobalMetadata::BlockBeneficiary,
+            U256::from_big_endian(&metadata.block_beneficiary.0),
+        ),
+        (GlobalMetadata::BlockTimestamp, metadata.block_timestamp),
+        (GlobalMetadata::BlockNumber, metadata.block_number),
+        (GlobalMetadata::BlockDifficulty, metadata.block_difficulty),
+        (
+            GlobalMetadata::BlockRandom,
+            metadata.block_random.into_uint(),
+        ),
+        (GlobalMetadata::BlockGasLimit, metadata.block_gaslimit),
+        (GlobalMetadata::BlockChainId, metadata.block_chain_id),
+        (
+            GlobalMetadata::BlockHardfork,
+            metadata.block_hardfork.id().into(),
+        ),
+        (GlobalMetadata::BlockBaseFee, metadata.block_base_fee),
+        (
+            GlobalMetadata::BlockCurrentHash,
+            h2u(inputs.block_hashes.cur_hash),
+        ),
+        (
+            GlobalMetadata::BlockParentHash,
+            h2u(inputs.block_hashes.parent_hash()),
+        ),
+        (
+            GlobalMetadata::BlockAncestorsHash,
+            h2u(inputs.block_hashes.ancestors_hash()),
+        ),
+        (GlobalMetadata::BlockGasUsed, metadata.block_gas_used),
+        (
+            GlobalMetadata::BlockBlobGasUsed,
+            metadata.block_blob_gas_used,
+        ),
+        (
+            GlobalMetadata::BlockExcessBlobGas,
+            metadata.block_excess_blob_gas,
+        ),
+        (
+            GlobalMetadata::ParentBeaconBlockRoot,
+            h2u(metadata.parent_beacon_block_root),
+        ),
+        (
+            GlobalMetadata::BlockWithdrawalsRoot,
+            h2u(metadata.withdrawals_root),
+        ),
+        (GlobalMetadata::BlockGasUsedBefore, inputs.gas_used_before),
+        (GlobalMetadata::BlockGasUsedAfter, inputs.gas_used_after),
+        (GlobalMetadata::TxnNumberBefore, inputs.txn_number_before),
+        (
+            GlobalMetadata::TxnNumberAfter,
+            inputs.txn_number_before + inputs.txn_hashes.len(),
+        ),
+        (
+            GlobalMetadata::StateTrieRootDigestBefore,
+            h2u(inputs.trie_roots_before.state_root),
+        ),
+        (
+            GlobalMetadata::TransactionTrieRootDigestBefore,
+            h2u(inputs.trie_roots_before.transactions_root),
+        ),
+        (
+            GlobalMetadata::ReceiptTrieRootDigestBefore,
+            h2u(inputs.trie_roots_before.receipts_root),
+        ),
+        (
+            GlobalMetadata::StateTrieRootDigestAfter,
+            h2u(trie_roots_after.state_root),
+        ),
+        (
+            GlobalMetadata::TransactionTrieRootDigestAfter,
+            h2u(trie_roots_after.transactions_root),
+        ),
+        (
+            GlobalMetadata::ReceiptTrieRootDigestAfter,
+            h2u(trie_roots_after.receipts_root),
+        ),
+        (GlobalMetadata::KernelHash, h2u(KERNEL.code_hash)),
+        (GlobalMetadata::KernelLen, KERNEL.code.len().into()),
+    ];
+
+    let channel = MemoryChannel::GeneralPurpose(0);
+    let mut ops = fields
+        .map(|(field, val)| {
+            mem_write_log(
+                channel,
+                // These fields are already scaled by their segment, and are in context 0 (kernel).
+                MemoryAddress::new_bundle(U256::from(field as usize)).unwrap(),
+                state,
+                val,
+            )
+        })
+        .to_vec();
+
+    // Write the block's final block bloom filter.
+    ops.extend((0..8).map(|i| {
+        mem_write_log(
+            channel,
+            MemoryAddress::new(0, Segment::GlobalBlockBloom, i),
+            state,
+            metadata.block_bloom[i],
+        )
+    }));
+
+    // Write previous block hashes.
+    ops.extend(
+        (0..256)
+            .map(|i| {
+                mem_write_log(
+                    channel,
+                    MemoryAddress::new(0, Segment::BlockHashes, i),
+                    state,
+                    h2u(inputs.block_hashes.prev_hashes[i]),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    // Write initial registers.
+    let registers_before = [
+        registers_before.program_counter,
+        registers_before.is_kernel,
+        registers_before.stack_len,
+        registers_before.stack_top,
+        registers_before.context,
+        registers_before.gas_used,
+    ];
+    ops.extend((0..registers_before.len()).map(|i| {
+        mem_write_log(
+            channel,
+            MemoryAddress::new(0, Segment::RegistersStates, i),
+            state,
+            registers_before[i],
+        )
+    }));
+
+    let length = registers_before.len();
+
+    // Write final registers.
+    let registers_after = [
+        registers_after.program_counter,
+        registers_after.is_kernel,
+        registers_after.stack_len,
+        registers_after.stack_top,
+        registers_after.context,
+        registers_after.gas_used,
+    ];
+    ops.extend((0..registers_before.len()).map(|i| {
+        mem_write_log(
+            channel,
+            MemoryAddress::new(0, Segment::RegistersStates, length + i),
+            state,
+            registers_after[i],
+        )
+    }));
+
+    state.memory.apply_ops(&ops);
+    state.traces.memory_ops.extend(ops);
+}
+
+pub(crate) fn debug_inputs(inputs: &GenerationInputs) {
+    log::debug!("Input signed_txns: {:?}", &inputs.signed_txns);
+    log::debug!("Input state_trie: {:?}", &inputs.tries.state_trie);
+    log::debug!(
+        "Input transactions_trie: {:?}",
+        &inputs.tries.transactions_trie
+    );
+    log::debug!("Input receipts_trie: {:?}", &inputs.tries.receipts_trie);
+    log::debug!("Input storage_tries: {:?}", &inputs.tries.storage_tries);
+    log::debug!("Input contract_code: {:?}", &inputs.contract_code);
+}
+
+fn initialize_kernel_code_and_shift_table(memory: &mut MemoryState) {
+    let mut code_addr = MemoryAddress::new(0, Segment::Code, 0);
+    for &byte in &KERNEL.code {
+        memory.set(code_addr, U256::from(byte));
+        code_addr.increment();
+    }
+
+    let mut shift_addr = MemoryAddress::new(0, Segment::ShiftTable, 0);
+    let mut shift_val = U256::one();
+    for _ in 0..256 {
+        memory.set(shift_addr, shift_val);
+        shift_addr.increment();
+        shift_val <<= 1;
+    }
+}
+
+/// Returns the memory addresses and values that should comprise the state at
+/// the start of the segment's execution.
+/// Ignores zero values in non-preinitialized segments.
+fn get_all_memory_address_and_values(memory_before: &MemoryState) -> Vec<(MemoryAddress, U256)> {
+    let mut res = vec![];
+    for (ctx_idx, ctx) in memory_before.contexts.iter().enumerate() {
+        for (segment_idx, segment) in ctx.segments.iter().enumerate() {
+            for (virt, value) in segment.content.iter().enumerate() {
+                if let &Some(val) = value {
+                    // We skip zero values in non-preinitialized segments.
+                    if !val.is_zero() || PREINITIALIZED_SEGMENTS_INDICES.contains(&segment_idx) {
+                        res.push((
+                            MemoryAddress {
+                                context: ctx_idx,
+                                segment: segment_idx,
+                                virt,
+                            },
+                            val,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    res
+}
+
+type TablesWithPVsAndFinalMem<F> = ([Vec<PolynomialValues<F>>; NUM_TABLES], PublicValues);
+pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    inputs: &TrimmedGenerationInputs,
+    config: &AllStarkConfig,
+    segment_data: &mut GenerationSegmentData,
+    timing: &mut TimingTree,
+) -> anyhow::Result<TablesWithPVsAndFinalMem<F>> {
+    let mut tables: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+    let (public_values, _) = generate_traces_streaming(
+        all_stark,
+        inputs,
+        config,
+        segment_data,
+        timing,
+        |table, trace, _| {
+            tables[*table] = trace;
+            Ok(())
+        },
+    )?;
+    Ok((tables, public_values))
+}
+
+/// Executes a segment, then generates the trace of each table in turn and
+/// hands it to `sink` as soon as it is generated.
+///
+/// Unlike [`generate_traces`], this lets the caller commit to each trace
+/// before the next one is materialized, which bounds the memory used by the
+/// traces of large segments. It also returns, for each table, whether the
+/// segment produced any of its rows.
+pub fn generate_traces_streaming<F: RichField + Extendable<D>, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    inputs: &TrimmedGenerationInputs,
+    config: &AllStarkConfig,
+    segment_data: &mut GenerationSegmentData,
+    timing: &mut TimingTree,
+    sink: impl FnMut(Table, Vec<PolynomialValues<F>>, &mut TimingTree) -> anyhow::Result<()>,
+) -> anyhow::Result<(PublicValues, [bool; NUM_TABLES])> {
+    let memory = segment_data.decode_memory(inputs)?;
+    initialize_kernel_code_and_shift_table(memory);
+
+    // Retrieve initial memory addresses and values.
+    let actual_mem_before = get_all_memory_address_and_values(memory);
+    let preinitialized_segments = memory.preinitialized_segments.clone();
+
+    let mut state =
+        GenerationState::<F>::new_with_segment_data(inputs, segment_data, preinitialized_segments)
+            .map_err(|err| anyhow!("Failed to parse all the initial prover inputs: {:?}", err))?;
+
+    // Initialize the state with the one at the end of the
+    // previous segment execution, if any.
+    let GenerationSegmentData {
+        max_cpu_len_log,
+        cycle_limit,
+        registers_before,
+        registers_after,
+        ..
+    } = segment_data;
+
+    for &(address, val) in &actual_mem_before {
+        state.memory.set(address, val);
+    }
+
+    let registers_before: RegistersData = RegistersData::from(*registers_before);
+    let registers_after: RegistersData = RegistersData::from(*registers_after);
+    apply_metadata_and_tries_memops(&mut state, inputs, &registers_before, &registers_after);
+
+    let cpu_res = timed!(
+        timing,
+        "simulate CPU",
+        simulate_cpu(
+            &mut state,
+            cycle_limit.or(max_cpu_len_log.map(cpu_cycle_limit))
+        )
+    );
+    if cpu_res.is_err() {
+        output_debug_tries(&state)?;
+        cpu_res?;
+    };
+
+    let trace_lengths = state.traces.get_lengths();
+    let table_in_use = state.traces.table_in_use();
+
+    let read_metadata = |field| state.memory.read_global_metadata(field);
+    let trie_roots_before = TrieRoots {
+        state_root: H256::from_uint(&read_metadata(StateTrieRootDigestBefore)),
+        transactions_root: H256::from_uint(&read_metadata(TransactionTrieRootDigestBefore)),
+        receipts_root: H256::from_uint(&read_metadata(ReceiptTrieRootDigestBefore)),
+    };
+    let trie_roots_after = TrieRoots {
+        state_root: H256::from_uint(&read_metadata(StateTrieRootDigestAfter)),
+        transactions_root: H256::from_uint(&read_metadata(TransactionTrieRootDigestAfter)),
+        receipts_root: H256::from_uint(&read_metadata(ReceiptTrieRootDigestAfter)),
+    };
+
+    let gas_used_after = read_metadata(GlobalMetadata::BlockGasUsedAfter);
+    let txn_number_after = read_metadata(GlobalMetadata::TxnNumberAfter);
+
+    let extra_block_data = ExtraBlockData {
+        checkpoint_state_trie_root: inputs.checkpoint_state_trie_root,
+        txn_number_before: inputs.txn_number_before,
+        txn_number_after,
+        gas_used_before: inputs.gas_used_before,
+        gas_used_after,
+    };
+
+    // `mem_before` and `mem_after` are initialized with an empty cap.
+    // They will be set to the caps of `MemBefore` and `MemAfter`
+    // respectively, while proving.
+    let public_values = PublicValues {
+        trie_roots_before,
+        trie_roots_after,
+        block_metadata: inputs.block_metadata.clone(),
+        block_hashes: inputs.block_hashes.clone(),
+        extra_block_data,
+        registers_before,
+        registers_after,
+        mem_before: MemCap::default(),
+        mem_after: MemCap::default(),
+    };
+
+    // Free the rest of the state, most notably the memory of the segment,
+    // before generating the traces.
+    let GenerationState {
+        traces,
+        stale_contexts,
+        ..
+    } = { state };
+
+    timed!(
+        timing,
+        "convert trace data to tables",
+        traces.for_each_table(
+            all_stark,
+            &actual_mem_before,
+            stale_contexts,
+            trace_lengths,
+            config,
+            timing,
+            sink
+        )
+    )?;
+    Ok((public_values, table_in_use))
+}
+
+fn simulate_cpu<F: Field>(
+    state: &mut GenerationState<F>,
+    cycle_limit: Option<usize>,
+) -> anyhow::Result<(RegistersState, Option<MemoryState>)> {
+    let (final_registers, mem_after) = state.run_cpu(cycle_limit)?;
+
+    let pc = state.registers.program_counter;
+    // Setting the values of padding rows.
+    let mut row = CpuColumnsView::<F>::default();
+    row.clock = F::from_canonical_usize(state.traces.clock() + 1);
+    row.context = F::from_canonical_usize(state.registers.context);
+    row.program_counter = F::from_canonical_usize(pc);
+    row.is_kernel_mode = F::ONE;
+    row.gas = F::from_canonical_u64(state.registers.gas_used);
+    row.stack_len = F::from_canonical_usize(state.registers.stack_len);
+
+    loop {
+        // Padding to a power of 2.
+        state.push_cpu(row);
+        row.clock += F::ONE;
+        if state.traces.clock().is_power_of_two() {
+            break;
+        }
+    }
+
+    log::info!("CPU trace padded to {} cycles", state.traces.clock());
+
+    Ok((final_registers, mem_after))
+}
+
+/// Outputs the tries that have been obtained post transaction execution, as
+/// they are represented in the prover's memory.
+/// This will do nothing if the CPU execution failed outside of the final trie
+/// root checks.
+pub(crate) fn output_debug_tries<F: RichField>(state: &GenerationState<F>) -> anyhow::Result<()> {
+    if !log_enabled!(log::Level::Debug) {
+        return Ok(());
+    }
+
+    // Retrieve previous PC (before jumping to KernelPanic), to see if we reached
+    // `perform_final_checks`. We will output debugging information on the final
+    // tries only if we got a root mismatch.
+    let previous_pc = state.get_registers().program_counter;
+
+    let label = KERNEL.offset_name(previous_pc);
+
+    if label.contains("check_state_trie")
+        || label.contains("check_txn_trie")
+        || label.contains("check_receipt_trie")
+    {
+        let state_trie_ptr = u256_to_usize(
+            state
+                .memory
+                .read_global_metadata(GlobalMetadata::StateTrieRoot),
+        )
+        .map_err(|_| anyhow!("State trie pointer is too large to fit in a usize."))?;
+        log::debug!(
+            "Computed state trie: {:?}",
+            get_state_trie::<HashedPartialTrie>(&state.memory, state_trie_ptr)
+        );
+
+        let txn_trie_ptr = u256_to_usize(
+            state
+                .memory
+                .read_global_metadata(GlobalMetadata::TransactionTrieRoot),
+        )
+        .map_err(|_| anyhow!("Transactions trie pointer is too large to fit in a usize."))?;
+        log::debug!(
+            "Computed transactions trie: {:?}",
+            get_txn_trie::<HashedPartialTrie>(&state.memory, txn_trie_ptr)
+        );
+
+        let receipt_trie_ptr = u256_to_usize(
+            state
+                .memory
+                .read_global_metadata(GlobalMetadata::ReceiptTrieRoot),
+        )
+        .map_err(|_| anyhow!("Receipts trie pointer is too large to fit in a usize."))?;
+        log::debug!(
+            "Computed receipts trie: {:?}",
+            get_receipt_trie::<HashedPartialTrie>(&state.memory, receipt_trie_ptr)
+        );
+    }
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::mem::size_of;
 
 use anyhow::{anyhow, bail};
@@ -9,12 +9,14 @@ use log::Level;
 use plonky2::field::types::Field;
 
 use super::mpt::TrieRootPtrs;
-use super::{TrieInputs, TrimmedGenerationInputs, NUM_EXTRA_CYCLES_AFTER};
+use super::{TrimmedGenerationInputs, NUM_EXTRA_CYCLES_AFTER};
 use crate::byte_packing::byte_packing_stark::BytePackingOp;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
 use crate::cpu::stack::MAX_USER_STACK_SIZE;
-use crate::generation::mpt::load_linked_lists_and_txn_and_receipt_mpts;
+use crate::ec::ec_stark::EcOp;
+use crate::generation::invalid_txn::{InvalidTxn, InvalidTxnReason};
+use crate::generation::mpt::load_preinitialized_segments;
 use crate::generation::rlp::all_rlp_prover_inputs_reversed;
 use crate::generation::CpuColumnsView;
 use crate::generation::GenerationInputs;
@@ -23,10 +25,10 @@ use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeOp;
 use crate::memory::segments::Segment;
 use crate::prover::GenerationSegmentData;
 use crate::util::u256_to_usize;
-use crate::witness::errors::ProgramError;
+use crate::witness::errors::{ProgramError, ProverInputError};
 use crate::witness::memory::MemoryChannel::GeneralPurpose;
 use crate::witness::memory::MemoryOpKind;
-use crate::witness::memory::{MemoryAddress, MemoryOp, MemoryState};
+use crate::witness::memory::{MemoryAddress, MemoryOp, MemorySegmentState, MemoryState};
 use crate::witness::operation::{generate_exception, Operation};
 use crate::witness::state::RegistersState;
 use crate::witness::traces::{TraceCheckpoint, Traces};
@@ -148,6 +150,10 @@ pub(crate) trait State<F: Field> {
             .push(op);
     }
 
+    fn push_ec(&mut self, op: EcOp) {
+        self.get_mut_generation_state().traces.ec_ops.push(op);
+    }
+
     /// Returns the content of a the `KernelGeneral` segment of a `State`.
     fn mem_get_kernel_content(&self) -> Vec<Option<U256>>;
 
@@ -166,20 +172,18 @@ pub(crate) trait State<F: Field> {
         None
     }
 
-    /// Simulates the CPU. It only generates the traces if the `State` is a
+    /// Simulates the CPU, until it halts or reaches the end of the segment at
+    /// `cycle_limit`. It only generates the traces if the `State` is a
     /// `GenerationState`.
     fn run_cpu(
         &mut self,
-        max_cpu_len_log: Option<usize>,
+        cycle_limit: Option<usize>,
     ) -> anyhow::Result<(RegistersState, Option<MemoryState>)>
     where
         Self: Transition<F>,
     {
         let halt_offsets = self.get_halt_offsets();
 
-        let cycle_limit =
-            max_cpu_len_log.map(|max_len_log| (1 << max_len_log) - NUM_EXTRA_CYCLES_AFTER);
-
         let mut final_registers = RegistersState::default();
         let mut running = true;
         let mut final_clock = 0;
@@ -274,6 +278,15 @@ pub(crate) trait State<F: Field> {
             Err(e) => {
                 if self.get_registers().is_kernel {
                     let offset_name = KERNEL.offset_name(self.get_registers().program_counter);
+                    // Keep missing trie preimages as typed errors, so that callers can report
+                    // them without digging through the kernel state.
+                    if let ProgramError::ProverInputError(ProverInputError::UnhydratedStorage(
+                        unhydrated,
+                    )) = e
+                    {
+                        return Err(anyhow::Error::new(unhydrated)
+                            .context(format!("Kernel exception at pc={}", offset_name)));
+                    }
                     bail!(
                         "{:?} in kernel at pc={}, stack={:?}, memory={:?}",
                         e,
@@ -366,40 +379,37 @@ pub struct GenerationState<F: Field> {
     /// the code (not necessarily pointing to an opcode) such that for every
     /// j in [i, i+32] it holds that code[j] < 0x7f - j + i.
     pub(crate) jumpdest_table: Option<HashMap<usize, Vec<usize>>>,
+
+    /// Whether the user's code has already been simulated to fill
+    /// `jumpdest_table` during the current transaction. Contexts missing from
+    /// the table after the simulation have no jump destinations.
+    pub(crate) jumpdest_table_simulated: bool,
+
+    /// The contexts which went through jumpdest analysis, grouped by code
+    /// hash, used to find a context in which a jump destination has already
+    /// been verified.
+    pub(crate) jumpdest_contexts: HashMap<H256, BTreeSet<usize>>,
+
+    /// The transactions rejected by the kernel so far.
+    pub(crate) invalid_txns: Vec<InvalidTxn>,
 }
 
 impl<F: Field> GenerationState<F> {
-    fn preinitialize_linked_lists_and_txn_and_receipt_mpts(
-        &mut self,
-        trie_inputs: &TrieInputs,
-    ) -> TrieRootPtrs {
-        let (trie_roots_ptrs, state_leaves, storage_leaves, trie_data) =
-            load_linked_lists_and_txn_and_receipt_mpts(trie_inputs)
-                .expect("Invalid MPT data for preinitialization");
-
-        self.memory.insert_preinitialized_segment(
-            Segment::AccountsLinkedList,
-            crate::witness::memory::MemorySegmentState {
-                content: state_leaves,
-            },
-        );
-        self.memory.insert_preinitialized_segment(
-            Segment::StorageLinkedList,
-            crate::witness::memory::MemorySegmentState {
-                content: storage_leaves,
-            },
-        );
-        self.memory.insert_preinitialized_segment(
-            Segment::TrieData,
-            crate::witness::memory::MemorySegmentState { content: trie_data },
-        );
+    fn preinitialize_linked_lists_and_txn_and_receipt_mpts(&mut self) -> TrieRootPtrs {
+        let (trie_roots_ptrs, segments) = load_preinitialized_segments(&self.inputs.trimmed_tries)
+            .expect("Invalid MPT data for preinitialization");
+
+        for (segment, values) in segments {
+            self.memory.insert_preinitialized_segment(segment, values);
+        }
 
         trie_roots_ptrs
     }
 
     pub(crate) fn new(inputs: &GenerationInputs, kernel_code: &[u8]) -> Result<Self, ProgramError> {
         let rlp_prover_inputs = all_rlp_prover_inputs_reversed(&inputs.signed_txns);
-        let withdrawal_prover_inputs = all_withdrawals_prover_inputs_reversed(&inputs.withdrawals);
+        let withdrawal_prover_inputs =
+            all_withdrawals_prover_inputs_reversed(&inputs.withdrawals, &inputs.block_rewards);
         let ger_prover_inputs = all_ger_prover_inputs_reversed(&inputs.global_exit_roots);
         let bignum_modmul_result_limbs = Vec::new();
 
@@ -421,9 +431,11 @@ impl<F: Field> GenerationState<F> {
                 receipt_root_ptr: 0,
             },
             jumpdest_table: None,
+            jumpdest_table_simulated: false,
+            jumpdest_contexts: HashMap::new(),
+            invalid_txns: Vec::new(),
         };
-        let trie_root_ptrs =
-            state.preinitialize_linked_lists_and_txn_and_receipt_mpts(&inputs.tries);
+        let trie_root_ptrs = state.preinitialize_linked_lists_and_txn_and_receipt_mpts();
 
         state.trie_root_ptrs = trie_root_ptrs;
         Ok(state)
@@ -432,13 +444,14 @@ impl<F: Field> GenerationState<F> {
     pub(crate) fn new_with_segment_data(
         trimmed_inputs: &TrimmedGenerationInputs,
         segment_data: &GenerationSegmentData,
+        preinitialized_segments: HashMap<Segment, MemorySegmentState>,
     ) -> Result<Self, ProgramError> {
         let mut state = Self {
             inputs: trimmed_inputs.clone(),
             ..Default::default()
         };
 
-        state.memory.preinitialized_segments = segment_data.memory.preinitialized_segments.clone();
+        state.memory.preinitialized_segments = preinitialized_segments;
 
         state.set_segment_data(segment_data);
 
@@ -462,11 +475,38 @@ impl<F: Field> GenerationState<F> {
             let tip_u256 = stack_peek(self, 0)?;
             let tip_h256 = H256::from_uint(&tip_u256);
             self.observe_contract(tip_h256)?;
+        } else if let Some(reason) = InvalidTxnReason::from_kernel_offset(dst) {
+            self.observe_invalid_txn(reason);
         }
 
         Ok(())
     }
 
+    /// Records that the transaction currently being processed has been
+    /// rejected by the kernel for the given reason.
+    pub(crate) fn observe_invalid_txn(&mut self, reason: InvalidTxnReason) {
+        // `next_txn_index` has already been incremented when fetching the
+        // current transaction.
+        let txn_index = self.next_txn_index.saturating_sub(1);
+        let invalid_txn = InvalidTxn {
+            txn_number: self.inputs.txn_number_before + txn_index,
+            txn_hash: self
+                .inputs
+                .txn_hashes
+                .get(txn_index)
+                .copied()
+                .unwrap_or_default(),
+            reason,
+        };
+        log::info!(
+            "Transaction {} ({:?}) is invalid: {}",
+            invalid_txn.txn_number,
+            invalid_txn.txn_hash,
+            reason
+        );
+        self.invalid_txns.push(invalid_txn);
+    }
+
     /// Observe the given address, so that we will be able to recognize the
     /// associated state key. This is just for debugging purposes.
     pub(crate) fn observe_address(&mut self, address: Address) {
@@ -532,6 +572,9 @@ impl<F: Field> GenerationState<F> {
                 receipt_root_ptr: 0,
             },
             jumpdest_table: None,
+            jumpdest_table_simulated: false,
+            jumpdest_contexts: HashMap::new(),
+            invalid_txns: self.invalid_txns.clone(),
         }
     }
 
@@ -548,6 +591,9 @@ impl<F: Field> GenerationState<F> {
             .clone_from(&segment_data.extra_data.trie_root_ptrs);
         self.jumpdest_table
             .clone_from(&segment_data.extra_data.jumpdest_table);
+        self.jumpdest_table_simulated = segment_data.extra_data.jumpdest_table_simulated;
+        self.jumpdest_contexts
+            .clone_from(&segment_data.extra_data.jumpdest_contexts);
         self.next_txn_index = segment_data.extra_data.next_txn_index;
         self.registers = RegistersState {
             program_counter: self.registers.program_counter,
@@ -727,9 +773,16 @@ pub(crate) struct GenerationStateCheckpoint {
 
 /// Withdrawals prover input array is of the form `[addr0, amount0, ..., addrN,
 /// amountN, U256::MAX, U256::MAX]`. Returns the reversed array.
-pub(crate) fn all_withdrawals_prover_inputs_reversed(withdrawals: &[(Address, U256)]) -> Vec<U256> {
+///
+/// The block rewards are credited by the kernel like withdrawals, and thus
+/// follow them in the array.
+pub(crate) fn all_withdrawals_prover_inputs_reversed(
+    withdrawals: &[(Address, U256)],
+    block_rewards: &[(Address, U256)],
+) -> Vec<U256> {
     let mut withdrawal_prover_inputs = withdrawals
         .iter()
+        .chain(block_rewards)
         .flat_map(|w| [U256::from((w.0).0.as_slice()), w.1])
         .collect::<Vec<_>>();
     withdrawal_prover_inputs.push(U256::MAX);
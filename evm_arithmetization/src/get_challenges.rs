@@ -1,15 +1,31 @@
 use ethereum_types::{BigEndianHash, H256, U256};
 use plonky2::field::extension::Extendable;
 use plonky2::hash::hash_types::RichField;
+use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
-use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
-use starky::config::StarkConfig;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use starky::lookup::get_grand_product_challenge_set;
 
+use crate::all_stark::{AllStarkConfig, Table};
 use crate::proof::*;
 use crate::util::{h256_limbs, u256_limbs, u256_to_u32, u256_to_u64};
 use crate::witness::errors::ProgramError;
 
+/// Observes the trace cap of a table, or as many zeros if the table is not in
+/// use, so that the transcript does not depend on the traces of the tables
+/// absent from the segment.
+pub(crate) fn observe_trace_cap<F: RichField, H: Hasher<F>>(
+    challenger: &mut Challenger<F, H>,
+    trace_cap: &MerkleCap<F, H>,
+    in_use: bool,
+) {
+    if in_use {
+        challenger.observe_cap(trace_cap);
+    } else {
+        challenger.observe_elements(&vec![F::ZERO; trace_cap.flatten().len()]);
+    }
+}
+
 fn observe_root<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     challenger: &mut Challenger<F, C::Hasher>,
     root: H256,
@@ -57,10 +73,14 @@ fn observe_block_metadata<
     );
     challenger.observe_element(u256_to_u32(block_metadata.block_timestamp)?);
     challenger.observe_element(u256_to_u32(block_metadata.block_number)?);
-    challenger.observe_element(u256_to_u32(block_metadata.block_difficulty)?);
+    let difficulty = u256_to_u64(block_metadata.block_difficulty)?;
+    challenger.observe_element(difficulty.0);
+    challenger.observe_element(difficulty.1);
     challenger.observe_elements(&h256_limbs::<F>(block_metadata.block_random));
     challenger.observe_element(u256_to_u32(block_metadata.block_gaslimit)?);
-    challenger.observe_element(u256_to_u32(block_metadata.block_chain_id)?);
+    let chain_id = u256_to_u64(block_metadata.block_chain_id)?;
+    challenger.observe_element(chain_id.0);
+    challenger.observe_element(chain_id.1);
     let basefee = u256_to_u64(block_metadata.block_base_fee)?;
     challenger.observe_element(basefee.0);
     challenger.observe_element(basefee.1);
@@ -72,9 +92,11 @@ fn observe_block_metadata<
     challenger.observe_element(excess_blob_gas.0);
     challenger.observe_element(excess_blob_gas.1);
     challenger.observe_elements(&h256_limbs::<F>(block_metadata.parent_beacon_block_root));
+    challenger.observe_elements(&h256_limbs::<F>(block_metadata.withdrawals_root));
     for i in 0..8 {
         challenger.observe_elements(&u256_limbs(block_metadata.block_bloom[i]));
     }
+    challenger.observe_element(F::from_canonical_u64(block_metadata.block_hardfork.id()));
 
     Ok(())
 }
@@ -92,16 +114,18 @@ fn observe_block_metadata_target<
     challenger.observe_elements(&block_metadata.block_beneficiary);
     challenger.observe_element(block_metadata.block_timestamp);
     challenger.observe_element(block_metadata.block_number);
-    challenger.observe_element(block_metadata.block_difficulty);
+    challenger.observe_elements(&block_metadata.block_difficulty);
     challenger.observe_elements(&block_metadata.block_random);
     challenger.observe_element(block_metadata.block_gaslimit);
-    challenger.observe_element(block_metadata.block_chain_id);
+    challenger.observe_elements(&block_metadata.block_chain_id);
     challenger.observe_elements(&block_metadata.block_base_fee);
     challenger.observe_element(block_metadata.block_gas_used);
     challenger.observe_elements(&block_metadata.block_blob_gas_used);
     challenger.observe_elements(&block_metadata.block_excess_blob_gas);
     challenger.observe_elements(&block_metadata.parent_beacon_block_root);
+    challenger.observe_elements(&block_metadata.withdrawals_root);
     challenger.observe_elements(&block_metadata.block_bloom);
+    challenger.observe_element(block_metadata.block_hardfork);
 }
 
 fn observe_extra_block_data<
@@ -150,6 +174,8 @@ fn observe_block_hashes<
         challenger.observe_elements(&h256_limbs::<F>(block_hashes.prev_hashes[i]));
     }
     challenger.observe_elements(&h256_limbs::<F>(block_hashes.cur_hash));
+    challenger.observe_elements(&h256_limbs::<F>(block_hashes.parent_hash()));
+    challenger.observe_elements(&h256_limbs::<F>(block_hashes.ancestors_hash()));
 }
 
 fn observe_block_hashes_target<
@@ -164,6 +190,8 @@ fn observe_block_hashes_target<
 {
     challenger.observe_elements(&block_hashes.prev_hashes);
     challenger.observe_elements(&block_hashes.cur_hash);
+    challenger.observe_elements(&block_hashes.parent_hash);
+    challenger.observe_elements(&block_hashes.ancestors_hash);
 }
 
 pub(crate) fn observe_public_values<
@@ -202,29 +230,38 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> A
     /// Computes all Fiat-Shamir challenges used in the STARK proof.
     pub(crate) fn get_challenges(
         &self,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
     ) -> Result<AllProofChallenges<F, D>, ProgramError> {
         let mut challenger = Challenger::<F, C::Hasher>::new();
 
         let stark_proofs = &self.multi_proof.stark_proofs;
 
-        for proof in stark_proofs {
-            challenger.observe_cap(&proof.proof.trace_cap);
+        for (proof, &in_use) in stark_proofs.iter().zip(&self.table_in_use) {
+            observe_trace_cap(&mut challenger, &proof.proof.trace_cap, in_use);
         }
 
         observe_public_values::<F, C, D>(&mut challenger, &self.public_values)?;
 
         let ctl_challenges =
-            get_grand_product_challenge_set(&mut challenger, config.num_challenges);
+            get_grand_product_challenge_set(&mut challenger, config.num_challenges());
 
         Ok(AllProofChallenges {
             stark_challenges: core::array::from_fn(|i| {
+                // The proofs of tables which are not in use are not bound to the
+                // transcript of the segment.
+                let mut fork;
+                let challenger = if self.table_in_use[i] {
+                    &mut challenger
+                } else {
+                    fork = challenger.clone();
+                    &mut fork
+                };
                 challenger.compact();
                 stark_proofs[i].proof.get_challenges(
-                    &mut challenger,
+                    challenger,
                     Some(&ctl_challenges),
                     true,
-                    config,
+                    config.table(Table::all()[i]),
                 )
             }),
             ctl_challenges,
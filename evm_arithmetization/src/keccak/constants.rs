@@ -1,3 +1,4 @@
+#[cfg(feature = "generation")]
 const RC: [u64; 24] = [
     0x0000000000000001,
     0x0000000000008082,
@@ -152,6 +153,7 @@ pub(crate) const fn rc_value_bit(round: usize, bit_index: usize) -> u8 {
     RC_BITS[round][bit_index]
 }
 
+#[cfg(feature = "generation")]
 pub(crate) const fn rc_value(round: usize) -> u64 {
     RC[round]
 }
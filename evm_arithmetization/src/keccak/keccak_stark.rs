@@ -3,17 +3,21 @@ use core::marker::PhantomData;
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::plonk_common::reduce_with_powers_ext_circuit;
+#[cfg(feature = "generation")]
 use plonky2::timed;
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::evaluation_frame::StarkEvaluationFrame;
 use starky::lookup::{Column, Filter};
 use starky::stark::Stark;
+#[cfg(feature = "generation")]
 use starky::util::trace_rows_to_poly_values;
 
 use super::columns::reg_input_limb;
@@ -22,7 +26,9 @@ use crate::keccak::columns::{
     reg_a, reg_a_prime, reg_a_prime_prime, reg_a_prime_prime_0_0_bit, reg_a_prime_prime_prime,
     reg_b, reg_c, reg_c_prime, reg_output_limb, reg_step, NUM_COLUMNS, TIMESTAMP,
 };
-use crate::keccak::constants::{rc_value, rc_value_bit};
+#[cfg(feature = "generation")]
+use crate::keccak::constants::rc_value;
+use crate::keccak::constants::rc_value_bit;
 use crate::keccak::logic::{
     andn, andn_gen, andn_gen_circuit, xor, xor3_gen, xor3_gen_circuit, xor_gen, xor_gen_circuit,
 };
@@ -63,6 +69,7 @@ pub(crate) struct KeccakStark<F, const D: usize> {
     pub(crate) f: PhantomData<F>,
 }
 
+#[cfg(feature = "generation")]
 impl<F: RichField + Extendable<D>, const D: usize> KeccakStark<F, D> {
     /// Generate the rows of the trace. Note that this does not generate the
     /// permuted columns used in our lookup arguments, as those are computed
@@ -610,7 +617,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for KeccakStark<F
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "generation"))]
 mod tests {
     use anyhow::Result;
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
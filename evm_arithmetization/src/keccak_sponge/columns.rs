@@ -8,6 +8,7 @@ use crate::util::indices_arr;
 /// bytes.
 pub(crate) const KECCAK_WIDTH_BYTES: usize = 200;
 /// Total number of 32-bit limbs in the sponge.
+#[cfg(any(feature = "generation", test))]
 pub(crate) const KECCAK_WIDTH_U32S: usize = KECCAK_WIDTH_BYTES / 4;
 /// Number of non-digest bytes.
 pub(crate) const KECCAK_WIDTH_MINUS_DIGEST_U32S: usize =
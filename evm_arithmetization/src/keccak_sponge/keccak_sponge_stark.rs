@@ -3,15 +3,20 @@ use core::iter::{self, repeat};
 use core::marker::PhantomData;
 use core::mem::size_of;
 
+#[cfg(any(feature = "generation", test))]
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use plonky2::timed;
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
+#[cfg(feature = "generation")]
 use plonky2::util::transpose;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::evaluation_frame::StarkEvaluationFrame;
@@ -19,8 +24,10 @@ use starky::lookup::{Column, Filter, Lookup};
 use starky::stark::Stark;
 
 use crate::all_stark::EvmStarkFrame;
+#[cfg(any(feature = "generation", test))]
 use crate::cpu::kernel::keccak_util::keccakf_u32s;
 use crate::keccak_sponge::columns::*;
+#[cfg(any(feature = "generation", test))]
 use crate::witness::memory::MemoryAddress;
 
 /// Strict upper bound for the individual bytes range-check.
@@ -229,6 +236,7 @@ pub(crate) fn ctl_looking_keccak_filter<F: Field>() -> Filter<F> {
 }
 
 /// Information about a Keccak sponge operation needed for witness generation.
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Debug)]
 pub(crate) struct KeccakSpongeOp {
     /// The base address at which inputs are read.
@@ -250,6 +258,7 @@ pub(crate) struct KeccakSpongeStark<F, const D: usize> {
 
 impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
     /// Generates the trace polynomial values for the `KeccakSponge`STARK.
+    #[cfg(feature = "generation")]
     pub(crate) fn generate_trace(
         &self,
         operations: Vec<KeccakSpongeOp>,
@@ -273,6 +282,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
 
     /// Generates the trace rows given the vector of `KeccakSponge` operations.
     /// The trace is padded to a power of two with all-zero rows.
+    #[cfg(feature = "generation")]
     fn generate_trace_rows(
         &self,
         operations: Vec<KeccakSpongeOp>,
@@ -300,6 +310,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
     /// Performs a Keccak sponge permutation and fills the STARK's rows
     /// accordingly. The number of rows is the number of input chunks of
     /// size `KECCAK_RATE_BYTES`.
+    #[cfg(any(feature = "generation", test))]
     fn generate_rows_for_op(&self, op: KeccakSpongeOp) -> Vec<[F; NUM_KECCAK_SPONGE_COLUMNS]> {
         let mut rows = Vec::with_capacity(op.input.len() / KECCAK_RATE_BYTES + 1);
 
@@ -357,6 +368,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
 
     /// Generates a row where all bytes are input bytes, not padding bytes.
     /// This includes updating the state sponge with a single absorption.
+    #[cfg(any(feature = "generation", test))]
     fn generate_full_input_row(
         &self,
         op: &KeccakSpongeOp,
@@ -379,6 +391,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
     /// On top of computing one absorption and padding the input,
     /// we indicate all the padding input bytes by setting the
     /// corresponding indices in `row.is_padding_byte` to 1.
+    #[cfg(any(feature = "generation", test))]
     fn generate_final_row(
         &self,
         op: &KeccakSpongeOp,
@@ -416,6 +429,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
     /// absorption. Given a state S = R || C and a block input B,
     /// - R is updated with R XOR B,
     /// - S is replaced by keccakf_u32s(S).
+    #[cfg(any(feature = "generation", test))]
     fn generate_common_fields(
         row: &mut KeccakSpongeColumnsView<F>,
         op: &KeccakSpongeOp,
@@ -493,6 +507,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
             })
     }
 
+    #[cfg(feature = "generation")]
     fn generate_padding_row(&self) -> [F; NUM_KECCAK_SPONGE_COLUMNS] {
         // The default instance has is_full_input_block = is_final_block = 0,
         // indicating that it's a dummy/padding row.
@@ -500,6 +515,7 @@ impl<F: RichField + Extendable<D>, const D: usize> KeccakSpongeStark<F, D> {
     }
 
     /// Expects input in *column*-major layout
+    #[cfg(feature = "generation")]
     fn generate_range_checks(&self, cols: &mut [Vec<F>]) {
         debug_assert!(cols.len() == NUM_KECCAK_SPONGE_COLUMNS);
 
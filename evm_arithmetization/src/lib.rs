@@ -23,7 +23,9 @@
 //! type C = PoseidonGoldilocksConfig;
 //!
 //! let all_stark = AllStark::<F, D>::default();
-//! let config = StarkConfig::standard_fast_config();
+//! // Prove the small Logic table with a higher rate, and the others with the
+//! // standard configuration.
+//! let config = AllStarkConfig::standard_fast_config().with_fri_params(Table::Logic, 3, 4);
 //!
 //! // Generate all the recursive circuits needed to generate succinct proofs for blocks.
 //! // The ranges correspond to the supported table sizes for each individual STARK component.
@@ -65,7 +67,7 @@
 //! public inputs, as such:
 //!
 //! ```ignore
-//! let public_values = PublicValues::from_public_inputs(&proof.public_inputs);
+//! let public_values = PublicValues::from_public_inputs(&proof.public_inputs)?;
 //! ```
 //!
 //! ## Aggregation proofs
@@ -187,23 +189,35 @@
 pub mod arithmetic;
 pub mod byte_packing;
 pub mod cpu;
+pub mod ec;
 pub mod keccak;
 pub mod keccak_sponge;
 pub mod logic;
 pub mod memory;
 pub mod memory_continuation;
+pub mod range_check;
 
 // Proving system components
 pub mod all_stark;
+#[cfg(feature = "generation")]
+pub mod constraint_checker;
+#[cfg(all(feature = "generation", any(debug_assertions, feature = "ctl_diagnostics")))]
+mod ctl_diagnostics;
 pub mod fixed_recursive_verifier;
 mod get_challenges;
 pub mod proof;
+#[cfg(feature = "generation")]
 pub mod prover;
 pub mod recursive_verifier;
+pub mod security;
 pub mod verifier;
 
 // Witness generation
 pub mod generation;
+#[cfg(feature = "generation")]
+pub mod simulation;
+#[cfg(feature = "generation")]
+pub mod synthetic;
 pub mod witness;
 
 // Utility modules
@@ -212,6 +226,7 @@ pub mod extension_tower;
 pub mod testing_utils;
 pub mod util;
 
+#[cfg(feature = "generation")]
 use generation::TrimmedGenerationInputs;
 use mpt_trie::partial_trie::HashedPartialTrie;
 
@@ -221,12 +236,18 @@ pub type Node = mpt_trie::partial_trie::Node<HashedPartialTrie>;
 /// A type alias for `u64` of a block height.
 pub type BlockHeight = u64;
 
-pub use all_stark::AllStark;
+pub use all_stark::{AllStark, AllStarkConfig};
 pub use fixed_recursive_verifier::AllRecursiveCircuits;
 pub use generation::GenerationInputs;
+#[cfg(feature = "generation")]
 use prover::{GenerationSegmentData, SegmentError};
+pub use security::SecurityPreset;
+#[cfg(feature = "generation")]
+pub use simulation::simulate;
 pub use starky::config::StarkConfig;
+pub use witness::errors::UnhydratedStorage;
 
 /// Returned type from a `SegmentDataIterator`, needed to prove all segments in
 /// a transaction batch.
+#[cfg(feature = "generation")]
 pub type AllData = Result<(TrimmedGenerationInputs, GenerationSegmentData), SegmentError>;
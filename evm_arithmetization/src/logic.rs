@@ -1,20 +1,25 @@
 use core::borrow::Borrow;
 use core::marker::PhantomData;
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
 use itertools::izip;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use plonky2::timed;
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::evaluation_frame::StarkEvaluationFrame;
 use starky::lookup::{Column, Filter};
 use starky::stark::Stark;
+#[cfg(feature = "generation")]
 use starky::util::trace_rows_to_poly_values;
 
 use crate::all_stark::EvmStarkFrame;
@@ -118,6 +123,7 @@ pub(crate) struct LogicStark<F, const D: usize> {
 }
 
 /// Logic operations.
+#[cfg(any(feature = "generation", test))]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub(crate) enum Op {
     And,
@@ -125,6 +131,7 @@ pub(crate) enum Op {
     Xor,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl Op {
     /// Returns the output of the current Logic operation.
     pub(crate) fn result(&self, a: U256, b: U256) -> U256 {
@@ -138,6 +145,7 @@ impl Op {
 
 /// A logic operation over `U256`` words. It contains an operator,
 /// either `AND`, `OR` or `XOR`, two inputs and its expected result.
+#[cfg(any(feature = "generation", test))]
 #[derive(Debug)]
 pub(crate) struct Operation {
     operator: Op,
@@ -146,6 +154,7 @@ pub(crate) struct Operation {
     pub(crate) result: U256,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl Operation {
     /// Computes the expected result of an operator with the two provided
     /// inputs, and returns the associated logic `Operation`.
@@ -187,6 +196,7 @@ impl Operation {
     }
 }
 
+#[cfg(feature = "generation")]
 impl<F: RichField, const D: usize> LogicStark<F, D> {
     /// Generates the trace polynomials for `LogicStark`.
     pub(crate) fn generate_trace(
@@ -82,11 +82,6 @@ pub(crate) struct MemoryColumnsView<T> {
 
     /// We use a range check to enforce the ordering.
     pub range_check: T,
-    /// The counter column (used for the range check) starts from 0 and
-    /// increments.
-    pub counter: T,
-    /// The frequencies column used in logUp.
-    pub frequencies: T,
 }
 
 /// Total number of columns in `MemoryStark`.
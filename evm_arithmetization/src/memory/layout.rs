@@ -0,0 +1,205 @@
+//! Introspection of the memory layout of the kernel.
+//!
+//! Lists the memory segments along with their address, value range,
+//! preinitialization status and the [`GlobalMetadata`] fields describing their
+//! contents. The kernel debugger heads the content of each segment with its
+//! layout in its context dumps.
+//!
+//! ```
+//! use evm_arithmetization::memory::layout::MemoryLayout;
+//!
+//! let layout = MemoryLayout::current();
+//! assert_eq!(layout.segments[0].name, "SEGMENT_CODE");
+//! println!("{layout}");
+//! ```
+use core::fmt;
+
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::memory::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES};
+
+/// The layout of a memory segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentLayout {
+    /// The index of the segment, i.e. its unscaled address component.
+    pub index: usize,
+    /// The name of the segment in the kernel assembly.
+    pub name: &'static str,
+    /// The scaled address of the segment, as bundled in kernel addresses.
+    pub address: u64,
+    /// The number of bits of the values of the segment.
+    pub bit_range: usize,
+    /// Whether the segment is initialized with non-zero values.
+    pub preinitialized: bool,
+    /// The kernel names of the global metadata fields describing the segment.
+    pub metadata: Vec<&'static str>,
+}
+
+/// The memory segments of the kernel, in address order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryLayout {
+    pub segments: Vec<SegmentLayout>,
+}
+
+impl MemoryLayout {
+    /// The memory layout of the current kernel.
+    pub fn current() -> Self {
+        Self {
+            segments: Segment::all()
+                .iter()
+                .map(|segment| SegmentLayout {
+                    index: segment.unscale(),
+                    name: segment.var_name(),
+                    address: *segment as u64,
+                    bit_range: segment.bit_range(),
+                    preinitialized: PREINITIALIZED_SEGMENTS_INDICES.contains(&segment.unscale()),
+                    metadata: segment_metadata(*segment)
+                        .iter()
+                        .map(GlobalMetadata::var_name)
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The global metadata fields holding the length or the bounds of a segment.
+pub(crate) const fn segment_metadata(segment: Segment) -> &'static [GlobalMetadata] {
+    match segment {
+        Segment::Code => &[GlobalMetadata::KernelHash, GlobalMetadata::KernelLen],
+        Segment::RlpRaw => &[GlobalMetadata::RlpDataSize],
+        Segment::TrieData => &[GlobalMetadata::TrieDataSize],
+        Segment::AccessedAddresses => &[GlobalMetadata::AccessedAddressesLen],
+        Segment::AccessedStorageKeys => &[GlobalMetadata::AccessedStorageKeysLen],
+        Segment::SelfDestructList => &[GlobalMetadata::SelfDestructListLen],
+        Segment::Logs => &[GlobalMetadata::LogsLen],
+        Segment::LogsData => &[GlobalMetadata::LogsDataLen, GlobalMetadata::LogsPayloadLen],
+        Segment::Journal => &[
+            GlobalMetadata::JournalLen,
+            GlobalMetadata::CurrentCheckpoint,
+        ],
+        Segment::JournalData => &[GlobalMetadata::JournalDataLen],
+        Segment::TouchedAddresses => &[GlobalMetadata::TouchedAddressesLen],
+        Segment::AccountsLinkedList => &[
+            GlobalMetadata::AccountsLinkedListNextAvailable,
+            GlobalMetadata::InitialAccountsLinkedListLen,
+        ],
+        Segment::StorageLinkedList => &[
+            GlobalMetadata::StorageLinkedListNextAvailable,
+            GlobalMetadata::InitialStorageLinkedListLen,
+        ],
+        Segment::TransientStorage => &[GlobalMetadata::TransientStorageLen],
+        Segment::CreatedContracts => &[GlobalMetadata::CreatedContractsLen],
+        Segment::TxnBlobVersionedHashes => &[GlobalMetadata::BlobVersionedHashesLen],
+        _ => &[],
+    }
+}
+
+impl fmt::Display for MemoryLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .segments
+            .iter()
+            .map(|segment| segment.name.len())
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:>5}  {:<name_width$}  {:>18}  {:>4}  {:>7}  metadata",
+            "index", "segment", "address", "bits", "preinit"
+        )?;
+        for segment in &self.segments {
+            writeln!(
+                f,
+                "{:>5}  {:<name_width$}  {:#018x}  {:>4}  {:>7}  {}",
+                segment.index,
+                segment.name,
+                segment.address,
+                segment.bit_range,
+                if segment.preinitialized { "yes" } else { "no" },
+                segment.metadata.join(", "),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reordering the segments changes the addresses the kernel and the
+    /// circuits rely on, hence breaks all existing proofs and circuits.
+    #[test]
+    fn layout_is_stable() {
+        let expected = [
+            "SEGMENT_CODE",
+            "SEGMENT_STACK",
+            "SEGMENT_MAIN_MEMORY",
+            "SEGMENT_CALLDATA",
+            "SEGMENT_RETURNDATA",
+            "SEGMENT_GLOBAL_METADATA",
+            "SEGMENT_CONTEXT_METADATA",
+            "SEGMENT_KERNEL_GENERAL",
+            "SEGMENT_KERNEL_ACCOUNT_CODE",
+            "SEGMENT_NORMALIZED_TXN",
+            "SEGMENT_TXN_DATA",
+            "SEGMENT_RLP_RAW",
+            "SEGMENT_TRIE_DATA",
+            "SEGMENT_SHIFT_TABLE",
+            "SEGMENT_JUMPDEST_BITS",
+            "SEGMENT_ECDSA_TABLE",
+            "SEGMENT_BN_WNAF_A",
+            "SEGMENT_BN_WNAF_B",
+            "SEGMENT_BN_TABLE_Q",
+            "SEGMENT_BN_PAIRING",
+            "SEGMENT_ACCESSED_ADDRESSES",
+            "SEGMENT_ACCESSED_STORAGE_KEYS",
+            "SEGMENT_SELFDESTRUCT_LIST",
+            "SEGMENT_TXN_BLOOM",
+            "SEGMENT_GLOBAL_BLOCK_BLOOM",
+            "SEGMENT_LOGS",
+            "SEGMENT_LOGS_DATA",
+            "SEGMENT_JOURNAL",
+            "SEGMENT_JOURNAL_DATA",
+            "SEGMENT_JOURNAL_CHECKPOINTS",
+            "SEGMENT_TOUCHED_ADDRESSES",
+            "SEGMENT_CONTEXT_CHECKPOINTS",
+            "SEGMENT_BLOCK_HASHES",
+            "SEGMENT_REGISTERS_STATES",
+            "SEGMENT_ACCOUNTS_LINKED_LIST",
+            "SEGMENT_STORAGE_LINKED_LIST",
+            "SEGMENT_TRANSIENT_STORAGE",
+            "SEGMENT_CREATED_CONTRACTS",
+            "SEGMENT_TXN_BLOB_VERSIONED_HASHES",
+        ];
+
+        let layout = MemoryLayout::current();
+        assert_eq!(
+            layout
+                .segments
+                .iter()
+                .map(|segment| segment.name)
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for (i, segment) in layout.segments.iter().enumerate() {
+            assert_eq!(segment.index, i);
+            assert_eq!(segment.address, (i as u64) << 32);
+        }
+        assert_eq!(
+            layout
+                .segments
+                .iter()
+                .filter(|segment| segment.preinitialized)
+                .map(|segment| segment.name)
+                .collect::<Vec<_>>(),
+            [
+                "SEGMENT_CODE",
+                "SEGMENT_TRIE_DATA",
+                "SEGMENT_ACCOUNTS_LINKED_LIST",
+                "SEGMENT_STORAGE_LINKED_LIST"
+            ]
+        );
+    }
+}
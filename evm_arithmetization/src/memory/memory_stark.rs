@@ -1,17 +1,25 @@
 use core::marker::PhantomData;
 use std::borrow::Borrow;
 
+#[cfg(feature = "generation")]
 use ethereum_types::U256;
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
+#[cfg(feature = "generation")]
+use plonky2::field::types::PrimeField64;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use plonky2::timed;
+#[cfg(feature = "generation")]
 use plonky2::util::timing::TimingTree;
+#[cfg(feature = "generation")]
 use plonky2::util::transpose;
+#[cfg(feature = "generation")]
 use plonky2_maybe_rayon::*;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::cross_table_lookup::TableWithColumns;
@@ -20,11 +28,17 @@ use starky::lookup::{Column, Filter, Lookup};
 use starky::stark::Stark;
 
 use super::columns::{MemoryColumnsView, MEMORY_COL_MAP};
-use super::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES};
+use super::segments::Segment;
+#[cfg(feature = "generation")]
+use super::segments::PREINITIALIZED_SEGMENTS_INDICES;
 use crate::all_stark::{EvmStarkFrame, Table};
 use crate::memory::columns::NUM_COLUMNS;
 use crate::memory::VALUE_LIMBS;
+#[cfg(feature = "generation")]
+use crate::range_check::range_check_stark::{RangeCheckMultiplicities, RANGE_MAX};
+#[cfg(feature = "generation")]
 use crate::witness::memory::MemoryOpKind::{self, Read};
+#[cfg(feature = "generation")]
 use crate::witness::memory::{MemoryAddress, MemoryOp};
 
 /// Creates the vector of `Columns` corresponding to:
@@ -97,16 +111,59 @@ pub(crate) fn ctl_filter_mem_after<F: Field>() -> Filter<F> {
 }
 
 #[derive(Copy, Clone, Default)]
+/// Returns the `TableWithColumns` range-checking the ordering of the rows,
+/// looking into the 16-bit range-check table.
+///
+/// The first lookup checks the `range_check` column on all rows. The second
+/// checks that the first virtual address accessed after a change of context or
+/// segment is range-checkable.
+pub(crate) fn ctl_looking_range_checks<F: Field>() -> Vec<TableWithColumns<F>> {
+    vec![
+        TableWithColumns::new(
+            *Table::Memory,
+            vec![Column::single(MEMORY_COL_MAP.range_check)],
+            Filter::new_simple(Column::constant(F::ONE)),
+        ),
+        TableWithColumns::new(
+            *Table::Memory,
+            vec![Column::single_next_row(MEMORY_COL_MAP.addr_virtual)],
+            Filter::new_simple(Column::sum([
+                MEMORY_COL_MAP.context_first_change,
+                MEMORY_COL_MAP.segment_first_change,
+            ])),
+        ),
+    ]
+}
+
+/// Records the lookups of [`ctl_looking_range_checks`] in the range-check
+/// table.
+#[cfg(feature = "generation")]
+pub(crate) fn count_range_checks<F: PrimeField64>(
+    trace: &[PolynomialValues<F>],
+    multiplicities: &mut RangeCheckMultiplicities,
+) {
+    let num_rows = trace[0].len();
+    for i in 0..num_rows {
+        multiplicities.add(trace[MEMORY_COL_MAP.range_check].values[i]);
+        if trace[MEMORY_COL_MAP.context_first_change].values[i].is_one()
+            || trace[MEMORY_COL_MAP.segment_first_change].values[i].is_one()
+        {
+            multiplicities.add(trace[MEMORY_COL_MAP.addr_virtual].values[(i + 1) % num_rows]);
+        }
+    }
+}
+
 pub(crate) struct MemoryStark<F, const D: usize> {
     pub(crate) f: PhantomData<F>,
 }
 
+#[cfg(feature = "generation")]
 impl MemoryOp {
     /// Generate a row for a given memory operation. Note that this does not
     /// generate columns which depend on the next operation, such as
     /// `context_first_change`; those are generated later. It also does not
-    /// generate columns such as `counter`, which are generated later, after the
-    /// trace has been transposed into column-major form.
+    /// generate columns such as `is_stale`, which are generated later, after
+    /// the trace has been transposed into column-major form.
     fn into_row<F: Field>(self) -> MemoryColumnsView<F> {
         let mut row = MemoryColumnsView::default();
         row.filter = F::from_bool(self.filter);
@@ -131,6 +188,7 @@ impl MemoryOp {
 
 /// Generates the `*_first_change` columns and the `range_check` column in the
 /// trace.
+#[cfg(feature = "generation")]
 pub(crate) fn generate_first_change_flags_and_rc<F: RichField>(
     trace_rows: &mut [MemoryColumnsView<F>],
 ) {
@@ -180,7 +238,7 @@ pub(crate) fn generate_first_change_flags_and_rc<F: RichField>(
         };
 
         assert!(
-            row.range_check.to_canonical_u64() < num_ops as u64,
+            row.range_check.to_canonical_u64() < RANGE_MAX as u64,
             "Range check of {} is too large. Bug in fill_gaps?",
             row.range_check
         );
@@ -200,9 +258,11 @@ pub(crate) fn generate_first_change_flags_and_rc<F: RichField>(
     }
 }
 
+#[cfg(feature = "generation")]
 impl<F: RichField + Extendable<D>, const D: usize> MemoryStark<F, D> {
-    /// Generate most of the trace rows. Excludes a few columns like `counter`,
-    /// which are generated later, after transposing to column-major form.
+    /// Generate most of the trace rows. Excludes a few columns like
+    /// `is_stale`, which are generated later, after transposing to
+    /// column-major form.
     fn generate_trace_row_major(
         &self,
         mut memory_ops: Vec<MemoryOp>,
@@ -229,30 +289,12 @@ impl<F: RichField + Extendable<D>, const D: usize> MemoryStark<F, D> {
         (trace_rows, unpadded_length)
     }
 
-    /// Generates the `counter`, `range_check` and `frequencies` columns, given
-    /// a trace in column-major form.
-    /// Also generates the `state_contexts`, `state_contexts_frequencies`,
-    /// `maybe_in_mem_after` and `mem_after_filter` columns.
+    /// Generates the `state_contexts`, `state_contexts_frequencies`,
+    /// `maybe_in_mem_after` and `mem_after_filter` columns, given a trace in
+    /// column-major form.
     fn generate_trace_col_major(trace_col_vecs: &mut [Vec<F>]) {
         let height = trace_col_vecs[0].len();
-        trace_col_vecs[MEMORY_COL_MAP.counter] =
-            (0..height).map(|i| F::from_canonical_usize(i)).collect();
-
         for i in 0..height {
-            let x_rc = trace_col_vecs[MEMORY_COL_MAP.range_check][i].to_canonical_u64() as usize;
-            trace_col_vecs[MEMORY_COL_MAP.frequencies][x_rc] += F::ONE;
-            if (trace_col_vecs[MEMORY_COL_MAP.context_first_change][i] == F::ONE)
-                || (trace_col_vecs[MEMORY_COL_MAP.segment_first_change][i] == F::ONE)
-            {
-                if i < trace_col_vecs[MEMORY_COL_MAP.addr_virtual].len() - 1 {
-                    let x_val = trace_col_vecs[MEMORY_COL_MAP.addr_virtual][i + 1]
-                        .to_canonical_u64() as usize;
-                    trace_col_vecs[MEMORY_COL_MAP.frequencies][x_val] += F::ONE;
-                } else {
-                    trace_col_vecs[MEMORY_COL_MAP.frequencies][0] += F::ONE;
-                }
-            }
-
             let addr_ctx = trace_col_vecs[MEMORY_COL_MAP.addr_context][i];
             let addr_ctx_usize = addr_ctx.to_canonical_u64() as usize;
             if addr_ctx + F::ONE == trace_col_vecs[MEMORY_COL_MAP.stale_contexts][addr_ctx_usize] {
@@ -286,13 +328,12 @@ impl<F: RichField + Extendable<D>, const D: usize> MemoryStark<F, D> {
     ///
     /// This method adds some dummy operations to ensure that none of these
     /// range checks will be too large, i.e. that they will all be smaller
-    /// than the number of rows, allowing them to be checked easily with a
-    /// single lookup.
+    /// than `RANGE_MAX`, allowing them to be looked up in the range-check
+    /// table.
     ///
-    /// For example, say there are 32 memory operations, and a particular
-    /// address is accessed at timestamps 20 and 100. 80 would fail the
-    /// range check, so this method would add two dummy reads to the same
-    /// address, say at timestamps 50 and 80.
+    /// For example, say a particular address is accessed at timestamps 20 and
+    /// 100000. The difference would fail the range check, so this method
+    /// would add a dummy read to the same address at timestamp 65555.
     fn fill_gaps(memory_ops: &mut Vec<MemoryOp>) {
         // First, insert padding row at address (0, 0, 0) if the first row doesn't
         // have a first virtual address at 0.
@@ -313,7 +354,7 @@ impl<F: RichField + Extendable<D>, const D: usize> MemoryStark<F, D> {
                 },
             );
         }
-        let max_rc = memory_ops.len().next_power_of_two() - 1;
+        let max_rc = RANGE_MAX - 1;
         for (mut curr, mut next) in memory_ops.clone().into_iter().tuple_windows() {
             if curr.address.context != next.address.context
                 || curr.address.segment != next.address.segment
@@ -614,14 +655,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for MemoryStark<F
         // Validate timestamp_inv. Since it's used as a CTL filter, its value must be
         // checked.
         yield_constr.constraint(timestamp * (timestamp * timestamp_inv - P::ONES));
-
-        // Check the range column: First value must be 0,
-        // and intermediate rows must increment by 1.
-        let rc1 = lv.counter;
-        let rc2 = nv.counter;
-        yield_constr.constraint_first_row(rc1);
-        let incr = rc2 - rc1;
-        yield_constr.constraint_transition(incr - P::Scalar::ONES);
     }
 
     fn eval_ext_circuit(
@@ -840,15 +873,6 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for MemoryStark<F
         let timestamp_inv_constraint =
             builder.mul_sub_extension(timestamp, timestamp_prod, timestamp);
         yield_constr.constraint(builder, timestamp_inv_constraint);
-
-        // Check the range column: First value must be 0,
-        // and intermediate rows must increment by 1.
-        let rc1 = lv.counter;
-        let rc2 = nv.counter;
-        yield_constr.constraint_first_row(builder, rc1);
-        let incr = builder.sub_extension(rc2, rc1);
-        let t = builder.sub_extension(incr, one);
-        yield_constr.constraint_transition(builder, t);
     }
 
     fn constraint_degree(&self) -> usize {
@@ -856,32 +880,15 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for MemoryStark<F
     }
 
     fn lookups(&self) -> Vec<Lookup<F>> {
-        vec![
-            Lookup {
-                columns: vec![
-                    Column::single(MEMORY_COL_MAP.range_check),
-                    Column::single_next_row(MEMORY_COL_MAP.addr_virtual),
-                ],
-                table_column: Column::single(MEMORY_COL_MAP.counter),
-                frequencies_column: Column::single(MEMORY_COL_MAP.frequencies),
-                filter_columns: vec![
-                    Default::default(),
-                    Filter::new_simple(Column::sum([
-                        MEMORY_COL_MAP.context_first_change,
-                        MEMORY_COL_MAP.segment_first_change,
-                    ])),
-                ],
-            },
-            Lookup {
-                columns: vec![Column::linear_combination_with_constant(
-                    vec![(MEMORY_COL_MAP.addr_context, F::ONE)],
-                    F::ONE,
-                )],
-                table_column: Column::single(MEMORY_COL_MAP.stale_contexts),
-                frequencies_column: Column::single(MEMORY_COL_MAP.stale_context_frequencies),
-                filter_columns: vec![Filter::new_simple(Column::single(MEMORY_COL_MAP.is_stale))],
-            },
-        ]
+        vec![Lookup {
+            columns: vec![Column::linear_combination_with_constant(
+                vec![(MEMORY_COL_MAP.addr_context, F::ONE)],
+                F::ONE,
+            )],
+            table_column: Column::single(MEMORY_COL_MAP.stale_contexts),
+            frequencies_column: Column::single(MEMORY_COL_MAP.stale_context_frequencies),
+            filter_columns: vec![Filter::new_simple(Column::single(MEMORY_COL_MAP.is_stale))],
+        }]
     }
 
     fn requires_ctls(&self) -> bool {
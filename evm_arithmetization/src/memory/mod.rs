@@ -4,6 +4,7 @@
 //! each memory operation.
 
 pub mod columns;
+pub mod layout;
 pub mod memory_stark;
 pub mod segments;
 
@@ -3,16 +3,19 @@
 //! This is used to ensure a continuation of the memory when proving
 //! multiple segments of a single full transaction proof.
 //! As such, `ContinuationMemoryStark` doesn't have any constraints.
+#[cfg(feature = "generation")]
 use std::cmp::max;
 use std::marker::PhantomData;
 
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use plonky2::util::transpose;
 use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use starky::evaluation_frame::StarkEvaluationFrame;
@@ -21,7 +24,9 @@ use starky::stark::Stark;
 
 use super::columns::{value_limb, ADDR_CONTEXT, ADDR_SEGMENT, ADDR_VIRTUAL, FILTER, NUM_COLUMNS};
 use crate::all_stark::EvmStarkFrame;
+#[cfg(feature = "generation")]
 use crate::generation::MemBeforeValues;
+#[cfg(feature = "generation")]
 use crate::memory::VALUE_LIMBS;
 
 /// Creates the vector of `Columns` corresponding to:
@@ -50,6 +55,7 @@ pub(crate) fn ctl_data_memory<F: Field>() -> Vec<Column<F>> {
 }
 
 /// Convert `mem_before_values` to a vector of memory trace rows
+#[cfg(feature = "generation")]
 pub(crate) fn mem_before_values_to_rows<F: Field>(
     mem_before_values: &MemBeforeValues,
 ) -> Vec<Vec<F>> {
@@ -75,6 +81,7 @@ pub(crate) struct MemoryContinuationStark<F, const D: usize> {
     f: PhantomData<F>,
 }
 
+#[cfg(feature = "generation")]
 impl<F: RichField + Extendable<D>, const D: usize> MemoryContinuationStark<F, D> {
     pub(crate) fn generate_trace(
         &self,
@@ -1,4 +1,5 @@
 use ethereum_types::{Address, H256, U256};
+use keccak_hash::keccak;
 use plonky2::field::extension::Extendable;
 use plonky2::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS};
 use plonky2::iop::target::{BoolTarget, Target};
@@ -6,11 +7,11 @@ use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
 use serde::{Deserialize, Serialize};
-use starky::config::StarkConfig;
 use starky::lookup::GrandProductChallengeSet;
 use starky::proof::{MultiProof, StarkProofChallenges};
+pub use zk_evm_common::Hardfork;
 
-use crate::all_stark::NUM_TABLES;
+use crate::all_stark::{AllStarkConfig, Table, NUM_TABLES};
 use crate::util::{get_h160, get_h256, get_u256, h2u};
 use crate::witness::state::RegistersState;
 
@@ -19,6 +20,14 @@ pub(crate) const DEFAULT_CAP_HEIGHT: usize = 4;
 /// Number of elements contained in a Merkle cap with default height.
 pub(crate) const DEFAULT_CAP_LEN: usize = 1 << DEFAULT_CAP_HEIGHT;
 
+/// An error returned when decoding public values from the public inputs of a
+/// proof.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PublicValuesError {
+    #[error("unsupported hardfork id {0}")]
+    UnsupportedHardfork(u64),
+}
+
 /// A STARK proof for each table, plus some metadata used to create recursive
 /// wrapper proofs.
 #[derive(Debug, Clone)]
@@ -28,12 +37,21 @@ pub struct AllProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, co
     pub multi_proof: MultiProof<F, C, D, NUM_TABLES>,
     /// Public memory values used for the recursive proofs.
     pub public_values: PublicValues,
+    /// Whether each table is in use in the segment. The proofs of
+    /// [optional](Table::is_optional) tables which are not in use are not
+    /// bound to the Fiat-Shamir transcript, and are neither verified nor
+    /// recursively wrapped.
+    pub table_in_use: [bool; NUM_TABLES],
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> AllProof<F, C, D> {
     /// Returns the degree (i.e. the trace length) of each STARK.
-    pub fn degree_bits(&self, config: &StarkConfig) -> [usize; NUM_TABLES] {
-        self.multi_proof.recover_degree_bits(config)
+    pub fn degree_bits(&self, config: &AllStarkConfig) -> [usize; NUM_TABLES] {
+        core::array::from_fn(|i| {
+            self.multi_proof.stark_proofs[i]
+                .proof
+                .recover_degree_bits(config.table(Table::all()[i]))
+        })
     }
 }
 
@@ -72,7 +90,7 @@ impl PublicValues {
     /// Public values are always the first public inputs added to the circuit,
     /// so we can start extracting at index 0.
     /// `len_mem_cap` is the length of the `MemBefore` and `MemAfter` caps.
-    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Self {
+    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Result<Self, PublicValuesError> {
         assert!(pis.len() >= PublicValuesTarget::SIZE);
 
         let mut offset = 0;
@@ -83,7 +101,7 @@ impl PublicValues {
             TrieRoots::from_public_inputs(&pis[offset..offset + TrieRootsTarget::SIZE]);
         offset += TrieRootsTarget::SIZE;
         let block_metadata =
-            BlockMetadata::from_public_inputs(&pis[offset..offset + BlockMetadataTarget::SIZE]);
+            BlockMetadata::from_public_inputs(&pis[offset..offset + BlockMetadataTarget::SIZE])?;
         offset += BlockMetadataTarget::SIZE;
         let block_hashes =
             BlockHashes::from_public_inputs(&pis[offset..offset + BlockHashesTarget::SIZE]);
@@ -101,7 +119,7 @@ impl PublicValues {
         offset += MemCapTarget::SIZE;
         let mem_after = MemCap::from_public_inputs(&pis[offset..offset + MemCapTarget::SIZE]);
 
-        Self {
+        Ok(Self {
             trie_roots_before,
             trie_roots_after,
             block_metadata,
@@ -111,7 +129,7 @@ impl PublicValues {
             registers_after,
             mem_before,
             mem_after,
-        }
+        })
     }
 }
 
@@ -134,7 +152,7 @@ impl FinalPublicValues {
     /// Extracts final public values from the given public inputs of a proof.
     /// Public values are always the first public inputs added to the circuit,
     /// so we can start extracting at index 0.
-    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Self {
+    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Result<Self, PublicValuesError> {
         assert!(
             PublicValuesTarget::SIZE - 2 * RegistersDataTarget::SIZE - 2 * MemCapTarget::SIZE
                 <= pis.len()
@@ -148,7 +166,7 @@ impl FinalPublicValues {
             TrieRoots::from_public_inputs(&pis[offset..offset + TrieRootsTarget::SIZE]);
         offset += TrieRootsTarget::SIZE;
         let block_metadata =
-            BlockMetadata::from_public_inputs(&pis[offset..offset + BlockMetadataTarget::SIZE]);
+            BlockMetadata::from_public_inputs(&pis[offset..offset + BlockMetadataTarget::SIZE])?;
         offset += BlockMetadataTarget::SIZE;
         let block_hashes =
             BlockHashes::from_public_inputs(&pis[offset..offset + BlockHashesTarget::SIZE]);
@@ -156,13 +174,13 @@ impl FinalPublicValues {
         let extra_block_data =
             ExtraBlockData::from_public_inputs(&pis[offset..offset + ExtraBlockDataTarget::SIZE]);
 
-        Self {
+        Ok(Self {
             trie_roots_before,
             trie_roots_after,
             block_metadata,
             block_hashes,
             extra_block_data,
-        }
+        })
     }
 }
 
@@ -246,6 +264,22 @@ impl BlockHashes {
             cur_hash,
         }
     }
+
+    /// The hash of the parent block, i.e. the last of `prev_hashes`.
+    pub fn parent_hash(&self) -> H256 {
+        self.prev_hashes.last().copied().unwrap_or_default()
+    }
+
+    /// The commitment to the 256 previous block hashes, i.e. the keccak hash
+    /// of their concatenation, from the oldest one.
+    pub fn ancestors_hash(&self) -> H256 {
+        keccak(
+            self.prev_hashes
+                .iter()
+                .flat_map(|hash| hash.0)
+                .collect::<Vec<_>>(),
+        )
+    }
 }
 
 /// Metadata contained in a block header. Those are identical between
@@ -258,12 +292,13 @@ pub struct BlockMetadata {
     pub block_timestamp: U256,
     /// The index of this block.
     pub block_number: U256,
-    /// The difficulty (before PoS transition) of this block.
+    /// The difficulty (before PoS transition) of this block. It must fit in a
+    /// `u64`.
     pub block_difficulty: U256,
     pub block_random: H256,
     /// The gas limit of this block. It must fit in a `u32`.
     pub block_gaslimit: U256,
-    /// The chain id of this block.
+    /// The chain id of this block. It must fit in a `u64`.
     pub block_chain_id: U256,
     /// The base fee of this block.
     pub block_base_fee: U256,
@@ -275,34 +310,50 @@ pub struct BlockMetadata {
     pub block_excess_blob_gas: U256,
     /// The hash tree root of the parent beacon block.
     pub parent_beacon_block_root: H256,
+    /// The root of the withdrawals trie of this block, as committed to in its
+    /// header. The kernel does not recompute it, but exposing it lets a
+    /// verifier bind the proof to the full header.
+    #[serde(default)]
+    pub withdrawals_root: H256,
     /// The block bloom of this block, represented as the consecutive
     /// 32-byte chunks of a block's final bloom filter string.
     pub block_bloom: [U256; 8],
+    /// The hardfork whose rules this block follows.
+    #[serde(default)]
+    pub block_hardfork: Hardfork,
 }
 
 impl BlockMetadata {
-    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Self {
+    /// Extracts the block metadata from the given public inputs, failing if
+    /// they do not hold a supported hardfork.
+    pub fn from_public_inputs<F: RichField>(pis: &[F]) -> Result<Self, PublicValuesError> {
         assert!(pis.len() == BlockMetadataTarget::SIZE);
 
         let block_beneficiary = get_h160(&pis[0..5]);
         let block_timestamp = pis[5].to_canonical_u64().into();
         let block_number = pis[6].to_canonical_u64().into();
-        let block_difficulty = pis[7].to_canonical_u64().into();
-        let block_random = get_h256(&pis[8..16]);
-        let block_gaslimit = pis[16].to_canonical_u64().into();
-        let block_chain_id = pis[17].to_canonical_u64().into();
-        let block_base_fee =
+        let block_difficulty =
+            (pis[7].to_canonical_u64() + (pis[8].to_canonical_u64() << 32)).into();
+        let block_random = get_h256(&pis[9..17]);
+        let block_gaslimit = pis[17].to_canonical_u64().into();
+        let block_chain_id =
             (pis[18].to_canonical_u64() + (pis[19].to_canonical_u64() << 32)).into();
-        let block_gas_used = pis[20].to_canonical_u64().into();
+        let block_base_fee =
+            (pis[20].to_canonical_u64() + (pis[21].to_canonical_u64() << 32)).into();
+        let block_gas_used = pis[22].to_canonical_u64().into();
         let block_blob_gas_used =
-            (pis[21].to_canonical_u64() + (pis[22].to_canonical_u64() << 32)).into();
-        let block_excess_blob_gas =
             (pis[23].to_canonical_u64() + (pis[24].to_canonical_u64() << 32)).into();
-        let parent_beacon_block_root = get_h256(&pis[25..33]);
+        let block_excess_blob_gas =
+            (pis[25].to_canonical_u64() + (pis[26].to_canonical_u64() << 32)).into();
+        let parent_beacon_block_root = get_h256(&pis[27..35]);
+        let withdrawals_root = get_h256(&pis[35..43]);
         let block_bloom =
-            core::array::from_fn(|i| h2u(get_h256(&pis[33 + 8 * i..33 + 8 * (i + 1)])));
+            core::array::from_fn(|i| h2u(get_h256(&pis[43 + 8 * i..43 + 8 * (i + 1)])));
+        let block_hardfork_id = pis[107].to_canonical_u64();
+        let block_hardfork = Hardfork::from_id(block_hardfork_id)
+            .ok_or(PublicValuesError::UnsupportedHardfork(block_hardfork_id))?;
 
-        Self {
+        Ok(Self {
             block_beneficiary,
             block_timestamp,
             block_number,
@@ -315,8 +366,10 @@ impl BlockMetadata {
             block_blob_gas_used,
             block_excess_blob_gas,
             parent_beacon_block_root,
+            withdrawals_root,
             block_bloom,
-        }
+            block_hardfork,
+        })
     }
 }
 
@@ -459,11 +512,14 @@ pub struct PublicValuesTarget {
 }
 
 impl PublicValuesTarget {
-    pub(crate) const SIZE: usize = TrieRootsTarget::SIZE * 2
+    /// Number of `Target`s required for the public values, i.e. the number of
+    /// leading public inputs of a proof they are extracted from.
+    pub const SIZE: usize = TrieRootsTarget::SIZE * 2
         + BlockMetadataTarget::SIZE
         + BlockHashesTarget::SIZE
         + ExtraBlockDataTarget::SIZE
-        + DEFAULT_CAP_HEIGHT * NUM_HASH_OUT_ELTS * 2;
+        + RegistersDataTarget::SIZE * 2
+        + MemCapTarget::SIZE * 2;
     /// Serializes public value targets.
     pub(crate) fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
         let TrieRootsTarget {
@@ -499,29 +555,37 @@ impl PublicValuesTarget {
             block_blob_gas_used,
             block_excess_blob_gas,
             parent_beacon_block_root,
+            withdrawals_root,
             block_bloom,
+            block_hardfork,
         } = self.block_metadata;
 
         buffer.write_target_array(&block_beneficiary)?;
         buffer.write_target(block_timestamp)?;
         buffer.write_target(block_number)?;
-        buffer.write_target(block_difficulty)?;
+        buffer.write_target_array(&block_difficulty)?;
         buffer.write_target_array(&block_random)?;
         buffer.write_target(block_gaslimit)?;
-        buffer.write_target(block_chain_id)?;
+        buffer.write_target_array(&block_chain_id)?;
         buffer.write_target_array(&block_base_fee)?;
         buffer.write_target(block_gas_used)?;
         buffer.write_target_array(&block_blob_gas_used)?;
         buffer.write_target_array(&block_excess_blob_gas)?;
         buffer.write_target_array(&parent_beacon_block_root)?;
+        buffer.write_target_array(&withdrawals_root)?;
         buffer.write_target_array(&block_bloom)?;
+        buffer.write_target(block_hardfork)?;
 
         let BlockHashesTarget {
             prev_hashes,
             cur_hash,
+            parent_hash,
+            ancestors_hash,
         } = self.block_hashes;
         buffer.write_target_array(&prev_hashes)?;
         buffer.write_target_array(&cur_hash)?;
+        buffer.write_target_array(&parent_hash)?;
+        buffer.write_target_array(&ancestors_hash)?;
 
         let ExtraBlockDataTarget {
             checkpoint_state_trie_root,
@@ -588,21 +652,25 @@ impl PublicValuesTarget {
             block_beneficiary: buffer.read_target_array()?,
             block_timestamp: buffer.read_target()?,
             block_number: buffer.read_target()?,
-            block_difficulty: buffer.read_target()?,
+            block_difficulty: buffer.read_target_array()?,
             block_random: buffer.read_target_array()?,
             block_gaslimit: buffer.read_target()?,
-            block_chain_id: buffer.read_target()?,
+            block_chain_id: buffer.read_target_array()?,
             block_base_fee: buffer.read_target_array()?,
             block_gas_used: buffer.read_target()?,
             block_blob_gas_used: buffer.read_target_array()?,
             block_excess_blob_gas: buffer.read_target_array()?,
             parent_beacon_block_root: buffer.read_target_array()?,
+            withdrawals_root: buffer.read_target_array()?,
             block_bloom: buffer.read_target_array()?,
+            block_hardfork: buffer.read_target()?,
         };
 
         let block_hashes = BlockHashesTarget {
             prev_hashes: buffer.read_target_array()?,
             cur_hash: buffer.read_target_array()?,
+            parent_hash: buffer.read_target_array()?,
+            ancestors_hash: buffer.read_target_array()?,
         };
 
         let extra_block_data = ExtraBlockDataTarget {
@@ -763,11 +831,11 @@ impl PublicValuesTarget {
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct TrieRootsTarget {
     /// Targets for the state trie hash.
-    pub(crate) state_root: [Target; TARGET_HASH_SIZE],
+    pub state_root: [Target; TARGET_HASH_SIZE],
     /// Targets for the transactions trie hash.
-    pub(crate) transactions_root: [Target; TARGET_HASH_SIZE],
+    pub transactions_root: [Target; TARGET_HASH_SIZE],
     /// Targets for the receipts trie hash.
-    pub(crate) receipts_root: [Target; TARGET_HASH_SIZE],
+    pub receipts_root: [Target; TARGET_HASH_SIZE],
 }
 
 /// Number of `Target`s required for hashes.
@@ -862,36 +930,40 @@ impl TrieRootsTarget {
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct BlockMetadataTarget {
     /// `Target`s for the address of this block's producer.
-    pub(crate) block_beneficiary: [Target; 5],
+    pub block_beneficiary: [Target; 5],
     /// `Target` for the timestamp of this block.
-    pub(crate) block_timestamp: Target,
+    pub block_timestamp: Target,
     /// `Target` for the index of this block.
-    pub(crate) block_number: Target,
-    /// `Target` for the difficulty (before PoS transition) of this block.
-    pub(crate) block_difficulty: Target,
+    pub block_number: Target,
+    /// `Target`s for the difficulty (before PoS transition) of this block.
+    pub block_difficulty: [Target; 2],
     /// `Target`s for the `mix_hash` value of this block.
-    pub(crate) block_random: [Target; 8],
+    pub block_random: [Target; 8],
     /// `Target` for the gas limit of this block.
-    pub(crate) block_gaslimit: Target,
-    /// `Target` for the chain id of this block.
-    pub(crate) block_chain_id: Target,
+    pub block_gaslimit: Target,
+    /// `Target`s for the chain id of this block.
+    pub block_chain_id: [Target; 2],
     /// `Target`s for the base fee of this block.
-    pub(crate) block_base_fee: [Target; 2],
+    pub block_base_fee: [Target; 2],
     /// `Target` for the gas used of this block.
-    pub(crate) block_gas_used: Target,
+    pub block_gas_used: Target,
     /// `Target`s for the total blob gas used of this block.
-    pub(crate) block_blob_gas_used: [Target; 2],
+    pub block_blob_gas_used: [Target; 2],
     /// `Target`s for the excess blob gas of this block.
-    pub(crate) block_excess_blob_gas: [Target; 2],
-    /// `Target`s for the block bloom of this block.
+    pub block_excess_blob_gas: [Target; 2],
     /// `Target`s for the parent beacon block root.
-    pub(crate) parent_beacon_block_root: [Target; 8],
-    pub(crate) block_bloom: [Target; 64],
+    pub parent_beacon_block_root: [Target; 8],
+    /// `Target`s for the withdrawals root of this block.
+    pub withdrawals_root: [Target; 8],
+    /// `Target`s for the block bloom of this block.
+    pub block_bloom: [Target; 64],
+    /// `Target` for the hardfork of this block.
+    pub block_hardfork: Target,
 }
 
 impl BlockMetadataTarget {
     /// Number of `Target`s required for the block metadata.
-    pub(crate) const SIZE: usize = 97;
+    pub(crate) const SIZE: usize = 108;
 
     /// Extracts block metadata `Target`s from the provided public input
     /// `Target`s. The provided `pis` should start with the block metadata.
@@ -899,16 +971,18 @@ impl BlockMetadataTarget {
         let block_beneficiary = pis[0..5].try_into().unwrap();
         let block_timestamp = pis[5];
         let block_number = pis[6];
-        let block_difficulty = pis[7];
-        let block_random = pis[8..16].try_into().unwrap();
-        let block_gaslimit = pis[16];
-        let block_chain_id = pis[17];
-        let block_base_fee = pis[18..20].try_into().unwrap();
-        let block_gas_used = pis[20];
-        let block_blob_gas_used = pis[21..23].try_into().unwrap();
-        let block_excess_blob_gas = pis[23..25].try_into().unwrap();
-        let parent_beacon_block_root = pis[25..33].try_into().unwrap();
-        let block_bloom = pis[33..97].try_into().unwrap();
+        let block_difficulty = pis[7..9].try_into().unwrap();
+        let block_random = pis[9..17].try_into().unwrap();
+        let block_gaslimit = pis[17];
+        let block_chain_id = pis[18..20].try_into().unwrap();
+        let block_base_fee = pis[20..22].try_into().unwrap();
+        let block_gas_used = pis[22];
+        let block_blob_gas_used = pis[23..25].try_into().unwrap();
+        let block_excess_blob_gas = pis[25..27].try_into().unwrap();
+        let parent_beacon_block_root = pis[27..35].try_into().unwrap();
+        let withdrawals_root = pis[35..43].try_into().unwrap();
+        let block_bloom = pis[43..107].try_into().unwrap();
+        let block_hardfork = pis[107];
 
         Self {
             block_beneficiary,
@@ -923,7 +997,9 @@ impl BlockMetadataTarget {
             block_blob_gas_used,
             block_excess_blob_gas,
             parent_beacon_block_root,
+            withdrawals_root,
             block_bloom,
+            block_hardfork,
         }
     }
 
@@ -945,12 +1021,16 @@ impl BlockMetadataTarget {
             }),
             block_timestamp: builder.select(condition, bm0.block_timestamp, bm1.block_timestamp),
             block_number: builder.select(condition, bm0.block_number, bm1.block_number),
-            block_difficulty: builder.select(condition, bm0.block_difficulty, bm1.block_difficulty),
+            block_difficulty: core::array::from_fn(|i| {
+                builder.select(condition, bm0.block_difficulty[i], bm1.block_difficulty[i])
+            }),
             block_random: core::array::from_fn(|i| {
                 builder.select(condition, bm0.block_random[i], bm1.block_random[i])
             }),
             block_gaslimit: builder.select(condition, bm0.block_gaslimit, bm1.block_gaslimit),
-            block_chain_id: builder.select(condition, bm0.block_chain_id, bm1.block_chain_id),
+            block_chain_id: core::array::from_fn(|i| {
+                builder.select(condition, bm0.block_chain_id[i], bm1.block_chain_id[i])
+            }),
             block_base_fee: core::array::from_fn(|i| {
                 builder.select(condition, bm0.block_base_fee[i], bm1.block_base_fee[i])
             }),
@@ -976,9 +1056,13 @@ impl BlockMetadataTarget {
                     bm1.parent_beacon_block_root[i],
                 )
             }),
+            withdrawals_root: core::array::from_fn(|i| {
+                builder.select(condition, bm0.withdrawals_root[i], bm1.withdrawals_root[i])
+            }),
             block_bloom: core::array::from_fn(|i| {
                 builder.select(condition, bm0.block_bloom[i], bm1.block_bloom[i])
             }),
+            block_hardfork: builder.select(condition, bm0.block_hardfork, bm1.block_hardfork),
         }
     }
 
@@ -993,12 +1077,16 @@ impl BlockMetadataTarget {
         }
         builder.connect(bm0.block_timestamp, bm1.block_timestamp);
         builder.connect(bm0.block_number, bm1.block_number);
-        builder.connect(bm0.block_difficulty, bm1.block_difficulty);
+        for i in 0..2 {
+            builder.connect(bm0.block_difficulty[i], bm1.block_difficulty[i])
+        }
         for i in 0..8 {
             builder.connect(bm0.block_random[i], bm1.block_random[i]);
         }
         builder.connect(bm0.block_gaslimit, bm1.block_gaslimit);
-        builder.connect(bm0.block_chain_id, bm1.block_chain_id);
+        for i in 0..2 {
+            builder.connect(bm0.block_chain_id[i], bm1.block_chain_id[i])
+        }
         for i in 0..2 {
             builder.connect(bm0.block_base_fee[i], bm1.block_base_fee[i])
         }
@@ -1015,9 +1103,13 @@ impl BlockMetadataTarget {
                 bm1.parent_beacon_block_root[i],
             )
         }
+        for i in 0..8 {
+            builder.connect(bm0.withdrawals_root[i], bm1.withdrawals_root[i])
+        }
         for i in 0..64 {
             builder.connect(bm0.block_bloom[i], bm1.block_bloom[i])
         }
+        builder.connect(bm0.block_hardfork, bm1.block_hardfork);
     }
 
     /// If `condition`, asserts that `bm0 == bm1`.
@@ -1036,7 +1128,13 @@ impl BlockMetadataTarget {
         }
         builder.conditional_assert_eq(condition.target, bm0.block_timestamp, bm1.block_timestamp);
         builder.conditional_assert_eq(condition.target, bm0.block_number, bm1.block_number);
-        builder.conditional_assert_eq(condition.target, bm0.block_difficulty, bm1.block_difficulty);
+        for i in 0..2 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.block_difficulty[i],
+                bm1.block_difficulty[i],
+            )
+        }
         for i in 0..8 {
             builder.conditional_assert_eq(
                 condition.target,
@@ -1045,7 +1143,13 @@ impl BlockMetadataTarget {
             );
         }
         builder.conditional_assert_eq(condition.target, bm0.block_gaslimit, bm1.block_gaslimit);
-        builder.conditional_assert_eq(condition.target, bm0.block_chain_id, bm1.block_chain_id);
+        for i in 0..2 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.block_chain_id[i],
+                bm1.block_chain_id[i],
+            )
+        }
         for i in 0..2 {
             builder.conditional_assert_eq(
                 condition.target,
@@ -1054,9 +1158,38 @@ impl BlockMetadataTarget {
             )
         }
         builder.conditional_assert_eq(condition.target, bm0.block_gas_used, bm1.block_gas_used);
+        for i in 0..2 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.block_blob_gas_used[i],
+                bm1.block_blob_gas_used[i],
+            )
+        }
+        for i in 0..2 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.block_excess_blob_gas[i],
+                bm1.block_excess_blob_gas[i],
+            )
+        }
+        for i in 0..8 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.parent_beacon_block_root[i],
+                bm1.parent_beacon_block_root[i],
+            )
+        }
+        for i in 0..8 {
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.withdrawals_root[i],
+                bm1.withdrawals_root[i],
+            )
+        }
         for i in 0..64 {
             builder.conditional_assert_eq(condition.target, bm0.block_bloom[i], bm1.block_bloom[i])
         }
+        builder.conditional_assert_eq(condition.target, bm0.block_hardfork, bm1.block_hardfork);
     }
 }
 
@@ -1068,27 +1201,38 @@ impl BlockMetadataTarget {
 ///
 /// When the block number is less than 256, dummy values, i.e.
 /// `H256::default()`, should be used for the additional block hashes.
+///
+/// The parent hash and the ancestors hash are derived from the previous
+/// hashes (see [`BlockHashes::parent_hash`] and
+/// [`BlockHashes::ancestors_hash`]), which the kernel checks at the start of
+/// the block. They let verifiers chain block proofs by block hash, and
+/// commit to the previous hashes without reading all of them.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct BlockHashesTarget {
     /// `Target`s for the previous 256 hashes to the current block. The leftmost
     /// hash, i.e. `prev_hashes[0..8]`, is the oldest, and the rightmost,
     /// i.e. `prev_hashes[255 * 7..255 * 8]` is the hash of the parent block.
-    pub(crate) prev_hashes: [Target; 2048],
+    pub prev_hashes: [Target; 2048],
     // `Target` for the hash of the current block.
-    pub(crate) cur_hash: [Target; 8],
+    pub cur_hash: [Target; 8],
+    /// `Target`s for the hash of the parent block.
+    pub parent_hash: [Target; 8],
+    /// `Target`s for the keccak hash of the previous 256 hashes.
+    pub ancestors_hash: [Target; 8],
 }
 
 impl BlockHashesTarget {
-    /// Number of `Target`s required for previous and current block hashes.
-    pub(crate) const SIZE: usize = 2056;
+    /// Number of `Target`s required for the block hashes.
+    pub(crate) const SIZE: usize = 2072;
 
-    /// Extracts the previous and current block hash `Target`s from the public
-    /// input `Target`s. The provided `pis` should start with the block
-    /// hashes.
+    /// Extracts the block hash `Target`s from the public input `Target`s. The
+    /// provided `pis` should start with the block hashes.
     pub(crate) fn from_public_inputs(pis: &[Target]) -> Self {
         Self {
             prev_hashes: pis[0..2048].try_into().unwrap(),
             cur_hash: pis[2048..2056].try_into().unwrap(),
+            parent_hash: pis[2056..2064].try_into().unwrap(),
+            ancestors_hash: pis[2064..2072].try_into().unwrap(),
         }
     }
 
@@ -1107,6 +1251,12 @@ impl BlockHashesTarget {
             cur_hash: core::array::from_fn(|i| {
                 builder.select(condition, bm0.cur_hash[i], bm1.cur_hash[i])
             }),
+            parent_hash: core::array::from_fn(|i| {
+                builder.select(condition, bm0.parent_hash[i], bm1.parent_hash[i])
+            }),
+            ancestors_hash: core::array::from_fn(|i| {
+                builder.select(condition, bm0.ancestors_hash[i], bm1.ancestors_hash[i])
+            }),
         }
     }
 
@@ -1121,6 +1271,8 @@ impl BlockHashesTarget {
         }
         for i in 0..8 {
             builder.connect(bm0.cur_hash[i], bm1.cur_hash[i]);
+            builder.connect(bm0.parent_hash[i], bm1.parent_hash[i]);
+            builder.connect(bm0.ancestors_hash[i], bm1.ancestors_hash[i]);
         }
     }
 
@@ -1136,6 +1288,12 @@ impl BlockHashesTarget {
         }
         for i in 0..8 {
             builder.conditional_assert_eq(condition.target, bm0.cur_hash[i], bm1.cur_hash[i]);
+            builder.conditional_assert_eq(condition.target, bm0.parent_hash[i], bm1.parent_hash[i]);
+            builder.conditional_assert_eq(
+                condition.target,
+                bm0.ancestors_hash[i],
+                bm1.ancestors_hash[i],
+            );
         }
     }
 }
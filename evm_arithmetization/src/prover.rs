@@ -1,8 +1,11 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
-use itertools::Itertools;
 use once_cell::sync::Lazy;
 use plonky2::field::extension::Extendable;
 use plonky2::field::polynomial::PolynomialValues;
@@ -10,7 +13,7 @@ use plonky2::fri::oracle::PolynomialBatch;
 use plonky2::hash::hash_types::RichField;
 use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::iop::challenger::Challenger;
-use plonky2::plonk::config::{GenericConfig, GenericHashOut};
+use plonky2::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
 use serde::{Deserialize, Serialize};
@@ -21,19 +24,28 @@ use starky::proof::{MultiProof, StarkProofWithMetadata};
 use starky::prover::prove_with_commitment;
 use starky::stark::Stark;
 
-use crate::all_stark::{AllStark, Table, NUM_TABLES};
+use crate::all_stark::{AllStark, AllStarkConfig, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::interpreter::{set_registers_and_run, ExtraSegmentData, Interpreter};
+use crate::generation::mpt::load_preinitialized_segments;
 use crate::generation::state::State;
-use crate::generation::{debug_inputs, generate_traces, GenerationInputs, TrimmedGenerationInputs};
-use crate::get_challenges::observe_public_values;
-use crate::proof::{AllProof, MemCap, PublicValues, DEFAULT_CAP_LEN};
-use crate::witness::memory::MemoryState;
-use crate::witness::state::RegistersState;
+use crate::generation::{
+    debug_inputs, generate_traces_streaming, GenerationInputs, TrimmedGenerationInputs,
+};
+use crate::get_challenges::{observe_public_values, observe_trace_cap};
+use crate::proof::{AllProof, MemCap, PublicValues};
+use crate::witness::memory::{CompactMemoryState, InitialSegments, MemoryState};
+use crate::witness::state::{CompactRegistersState, RegistersState};
 use crate::AllData;
 
 /// Structure holding the data needed to initialize a segment.
+///
+/// It is (de)serialized through [`CompactSegmentData`], as segment data is
+/// shipped to workers for each segment of each batch. The memory snapshot is
+/// then only decoded when generating the traces of the segment, once the
+/// initial memory of the batch has been rebuilt from its inputs.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(into = "CompactSegmentData", from = "CompactSegmentData")]
 pub struct GenerationSegmentData {
     /// Indicates the position of this segment in a sequence of
     /// executions for a larger payload.
@@ -43,11 +55,15 @@ pub struct GenerationSegmentData {
     /// Registers at the end of the segment execution.
     pub(crate) registers_after: RegistersState,
     /// Memory at the start of the segment execution.
-    pub(crate) memory: MemoryState,
+    pub(crate) memory: SegmentMemory,
     /// Extra data required to initialize a segment.
     pub(crate) extra_data: ExtraSegmentData,
     /// Log of the maximal cpu length.
     pub(crate) max_cpu_len_log: Option<usize>,
+    /// The clock at which the execution of the segment stops, as decided
+    /// while splitting the payload. If `None`, it is derived from
+    /// `max_cpu_len_log`.
+    pub(crate) cycle_limit: Option<usize>,
 }
 
 impl GenerationSegmentData {
@@ -55,12 +71,297 @@ impl GenerationSegmentData {
     pub fn segment_index(&self) -> usize {
         self.segment_index
     }
+
+    /// Reports how much of the memory snapshot of this segment is actually
+    /// shipped once delta-encoded against the initial memory of the batch.
+    pub fn memory_stats(&self) -> SegmentMemoryStats {
+        let compact = CompactMemoryState::from(&self.memory);
+        SegmentMemoryStats {
+            set_cells: compact.num_set_cells(),
+            encoded_cells: compact.num_values(),
+        }
+    }
+
+    /// Returns the memory at the start of the segment execution, decoding it
+    /// first if this segment data was deserialized. `inputs` are the inputs of
+    /// the batch of this segment, from which the initial memory of the batch
+    /// is rebuilt.
+    pub(crate) fn decode_memory(
+        &mut self,
+        inputs: &TrimmedGenerationInputs,
+    ) -> Result<&mut MemoryState> {
+        if let SegmentMemory::Encoded(compact) = &self.memory {
+            let (_, initial_segments) = load_preinitialized_segments(&inputs.trimmed_tries)
+                .map_err(|err| {
+                    anyhow!(
+                        "Failed to rebuild the initial memory of the batch: {:?}",
+                        err
+                    )
+                })?;
+            let memory = MemoryState::try_from((compact, &initial_segments))?;
+            self.memory = SegmentMemory::Decoded {
+                memory,
+                initial_segments: Arc::new(initial_segments),
+            };
+        }
+
+        match &mut self.memory {
+            SegmentMemory::Decoded { memory, .. } => Ok(memory),
+            SegmentMemory::Encoded(_) => unreachable!("The memory was just decoded"),
+        }
+    }
+}
+
+/// The memory of a segment at the start of its execution.
+#[derive(Clone, Debug)]
+pub(crate) enum SegmentMemory {
+    /// A memory snapshot, along with the preinitialized segments at the start
+    /// of the batch, which are shared by all its segments and against which
+    /// the snapshot is serialized.
+    Decoded {
+        memory: MemoryState,
+        initial_segments: Arc<InitialSegments>,
+    },
+    /// A deserialized memory snapshot, which is delta-encoded against the
+    /// initial memory of the batch.
+    Encoded(CompactMemoryState),
+}
+
+impl Default for SegmentMemory {
+    fn default() -> Self {
+        Self::Decoded {
+            memory: MemoryState::default(),
+            initial_segments: Arc::default(),
+        }
+    }
+}
+
+impl From<&SegmentMemory> for CompactMemoryState {
+    fn from(memory: &SegmentMemory) -> Self {
+        match memory {
+            SegmentMemory::Decoded {
+                memory,
+                initial_segments,
+            } => CompactMemoryState::encode(memory, initial_segments),
+            SegmentMemory::Encoded(compact) => compact.clone(),
+        }
+    }
+}
+
+/// Memory size statistics of a [`GenerationSegmentData`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SegmentMemoryStats {
+    /// The number of set memory cells in the snapshot.
+    pub set_cells: usize,
+    /// The number of memory values in the serialized snapshot.
+    pub encoded_cells: usize,
+}
+
+/// Profile of the kernel computing the final state trie of a batch from the
+/// state linked lists, which only reinserts the slots and accounts modified
+/// during the batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalTriesStats {
+    /// The number of CPU cycles spent computing the final state trie, before
+    /// hashing it.
+    pub cycles: usize,
+    /// The number of unchanged storage slots which were not reinserted.
+    pub skipped_slots: usize,
+    /// The number of unchanged accounts which were not reinserted.
+    pub skipped_accounts: usize,
+}
+
+/// The instructions executed in a segment, attributed to the kernel routines
+/// they belong to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleProfile {
+    /// The number of instructions executed at each kernel offset.
+    kernel_cycles: HashMap<usize, usize>,
+    /// The number of instructions of user code.
+    user_cycles: usize,
+}
+
+impl CycleProfile {
+    pub(crate) fn record(&mut self, is_kernel: bool, program_counter: usize) {
+        if is_kernel {
+            *self.kernel_cycles.entry(program_counter).or_default() += 1;
+        } else {
+            self.user_cycles += 1;
+        }
+    }
+
+    /// Returns the total number of instructions of the profile.
+    pub fn total_cycles(&self) -> usize {
+        self.kernel_cycles.values().sum::<usize>() + self.user_cycles
+    }
+
+    /// Returns the number of instructions executed in each kernel routine,
+    /// identified by the global label starting it.
+    pub fn by_routine(&self) -> BTreeMap<String, usize> {
+        let mut routines = BTreeMap::new();
+        for (&offset, &cycles) in &self.kernel_cycles {
+            let routine = KERNEL
+                .routine_label(offset)
+                .map_or_else(|| offset.to_string(), str::to_string);
+            *routines.entry(routine).or_default() += cycles;
+        }
+        routines
+    }
+
+    /// Returns the profile in the folded stacks format of flamegraph tools,
+    /// e.g. `inferno-flamegraph` or `flamegraph.pl`, with one line per kernel
+    /// routine below a `kernel` frame, and a single `user` frame.
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for (routine, cycles) in self.by_routine() {
+            folded.push_str(&format!("kernel;{routine} {cycles}\n"));
+        }
+        if self.user_cycles > 0 {
+            folded.push_str(&format!("user {}\n", self.user_cycles));
+        }
+        folded
+    }
+}
+
+/// Metrics collected while proving one or more segments.
+///
+/// Times are wall-clock times, summed over the segments.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvingMetrics {
+    /// The number of segments proven.
+    pub segments: usize,
+    /// The number of rows of the trace of each table, in the order of
+    /// [`Table`].
+    pub table_rows: [usize; NUM_TABLES],
+    /// The time spent committing to the trace of each table.
+    pub commit_times: [Duration; NUM_TABLES],
+    /// The time spent proving each table once its trace is committed to, i.e.
+    /// computing its quotient polynomials and FRI openings.
+    pub prove_times: [Duration; NUM_TABLES],
+    /// The time spent recursively shrinking the table proofs and proving the
+    /// root circuit, when proving segments with
+    /// [`AllRecursiveCircuits`](crate::AllRecursiveCircuits).
+    pub recursion_time: Duration,
+    /// The peak resident set size of the process, in bytes. It is only
+    /// measured on Linux.
+    pub peak_rss: Option<u64>,
+}
+
+impl ProvingMetrics {
+    /// Adds the metrics of `other`, e.g. those of another segment, to these.
+    pub fn merge(&mut self, other: &Self) {
+        self.segments += other.segments;
+        for table in 0..NUM_TABLES {
+            self.table_rows[table] += other.table_rows[table];
+            self.commit_times[table] += other.commit_times[table];
+            self.prove_times[table] += other.prove_times[table];
+        }
+        self.recursion_time += other.recursion_time;
+        self.peak_rss = self.peak_rss.max(other.peak_rss);
+    }
+
+    /// Returns the total time spent committing to traces.
+    pub fn total_commit_time(&self) -> Duration {
+        self.commit_times.iter().sum()
+    }
+
+    /// Returns the total time spent proving tables once their traces are
+    /// committed to.
+    pub fn total_prove_time(&self) -> Duration {
+        self.prove_times.iter().sum()
+    }
+
+    fn record_peak_rss(&mut self) {
+        self.peak_rss = self.peak_rss.max(peak_rss());
+    }
+}
+
+impl fmt::Display for ProvingMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} segments: commit {:?}, prove {:?}, recursion {:?}, peak RSS {}",
+            self.segments,
+            self.total_commit_time(),
+            self.total_prove_time(),
+            self.recursion_time,
+            self.peak_rss
+                .map_or_else(|| "unknown".to_string(), |rss| format!("{rss} bytes")),
+        )?;
+        for table in Table::all() {
+            writeln!(
+                f,
+                "  {:?}: {} rows, commit {:?}, prove {:?}",
+                table, self.table_rows[*table], self.commit_times[*table], self.prove_times[*table],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the peak resident set size of the current process, in bytes, read
+/// from `/proc/self/status`.
+fn peak_rss() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// The serialized form of a [`GenerationSegmentData`], with a delta-encoded
+/// memory snapshot and bit-packed registers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CompactSegmentData {
+    segment_index: usize,
+    registers_before: CompactRegistersState,
+    registers_after: CompactRegistersState,
+    memory: CompactMemoryState,
+    extra_data: ExtraSegmentData,
+    max_cpu_len_log: Option<usize>,
+    cycle_limit: Option<usize>,
+}
+
+impl From<GenerationSegmentData> for CompactSegmentData {
+    fn from(data: GenerationSegmentData) -> Self {
+        Self {
+            segment_index: data.segment_index,
+            registers_before: data.registers_before.into(),
+            registers_after: data.registers_after.into(),
+            memory: (&data.memory).into(),
+            extra_data: data.extra_data,
+            max_cpu_len_log: data.max_cpu_len_log,
+            cycle_limit: data.cycle_limit,
+        }
+    }
+}
+
+impl From<CompactSegmentData> for GenerationSegmentData {
+    fn from(data: CompactSegmentData) -> Self {
+        Self {
+            segment_index: data.segment_index,
+            registers_before: data.registers_before.into(),
+            registers_after: data.registers_after.into(),
+            memory: SegmentMemory::Encoded(data.memory),
+            extra_data: data.extra_data,
+            max_cpu_len_log: data.max_cpu_len_log,
+            cycle_limit: data.cycle_limit,
+        }
+    }
 }
 
 /// Generate traces, then create all STARK proofs.
 pub fn prove<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
-    config: &StarkConfig,
+    config: &AllStarkConfig,
     inputs: TrimmedGenerationInputs,
     segment_data: &mut GenerationSegmentData,
     timing: &mut TimingTree,
@@ -70,92 +371,177 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    // Sanity check on the provided config
-    assert_eq!(DEFAULT_CAP_LEN, 1 << config.fri_config.cap_height);
+    prove_with_metrics(
+        all_stark,
+        config,
+        inputs,
+        segment_data,
+        timing,
+        abort_signal,
+        &mut ProvingMetrics::default(),
+    )
+}
 
+/// Generate traces, then create all STARK proofs, like [`prove`], adding the
+/// metrics of the segment to `metrics`.
+pub fn prove_with_metrics<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &AllStarkConfig,
+    inputs: TrimmedGenerationInputs,
+    segment_data: &mut GenerationSegmentData,
+    timing: &mut TimingTree,
+    abort_signal: Option<Arc<AtomicBool>>,
+    metrics: &mut ProvingMetrics,
+) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
     timed!(timing, "build kernel", Lazy::force(&KERNEL));
 
-    let (traces, mut public_values) = timed!(
+    // Each trace is committed to as soon as it is generated, rather than once
+    // all of them are, so that the operations of the remaining tables and the
+    // memory of the segment are freed before the bulk of the commitments are
+    // computed.
+    let mut traces: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+    let mut trace_commitments = Vec::with_capacity(NUM_TABLES);
+    let (mut public_values, table_in_use) = timed!(
         timing,
-        "generate all traces",
-        generate_traces(all_stark, &inputs, config, segment_data, timing)?
+        "generate and commit all traces",
+        generate_traces_streaming(
+            all_stark,
+            &inputs,
+            config,
+            segment_data,
+            timing,
+            |table, trace, timing| {
+                check_abort_signal(abort_signal.clone())?;
+                let start = Instant::now();
+                trace_commitments.push(commit_trace::<F, C, D>(
+                    config.table(table),
+                    table,
+                    &trace,
+                    timing,
+                ));
+                metrics.commit_times[*table] += start.elapsed();
+                metrics.table_rows[*table] += trace.first().map_or(0, |column| column.len());
+                traces[*table] = trace;
+                Ok(())
+            }
+        )?
     );
 
     check_abort_signal(abort_signal.clone())?;
 
-    let proof = prove_with_traces(
+    let proof = prove_with_trace_commitments(
         all_stark,
         config,
         traces,
+        trace_commitments,
+        table_in_use,
         &mut public_values,
         timing,
         abort_signal,
+        &mut metrics.prove_times,
     )?;
+    metrics.segments += 1;
+    metrics.record_peak_rss();
 
     Ok(proof)
 }
 
-/// Compute all STARK proofs.
-pub(crate) fn prove_with_traces<F, C, const D: usize>(
-    all_stark: &AllStark<F, D>,
+/// Computes the polynomial commitment of the trace of `table`.
+fn commit_trace<F, C, const D: usize>(
     config: &StarkConfig,
+    table: Table,
+    trace: &[PolynomialValues<F>],
+    timing: &mut TimingTree,
+) -> PolynomialBatch<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    timed!(
+        timing,
+        &format!("compute trace commitment for {:?}", table),
+        PolynomialBatch::<F, C, D>::from_values(
+            trace.to_vec(),
+            config.fri_config.rate_bits,
+            false,
+            config.fri_config.cap_height,
+            timing,
+            None,
+        )
+    )
+}
+
+/// Compute all STARK proofs, given the traces of all tables and their
+/// commitments, in the order of [`Table`].
+///
+/// The proofs of the [optional](Table::is_optional) tables which are not in
+/// use are not bound to the transcript of the segment: verifiers skip them,
+/// and the root circuit verifies a dummy proof in place of their recursive
+/// wrapper.
+fn prove_with_trace_commitments<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &AllStarkConfig,
     trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
+    trace_commitments: Vec<PolynomialBatch<F, C, D>>,
+    table_in_use: [bool; NUM_TABLES],
     public_values: &mut PublicValues,
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
+    prove_times: &mut [Duration; NUM_TABLES],
 ) -> Result<AllProof<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let rate_bits = config.fri_config.rate_bits;
-    let cap_height = config.fri_config.cap_height;
-
-    // For each STARK, we compute the polynomial commitments for the polynomials
-    // interpolating its trace.
-    let trace_commitments = timed!(
-        timing,
-        "compute all trace commitments",
-        trace_poly_values
-            .iter()
-            .zip_eq(Table::all())
-            .map(|(trace, table)| {
-                timed!(
-                    timing,
-                    &format!("compute trace commitment for {:?}", table),
-                    PolynomialBatch::<F, C, D>::from_values(
-                        trace.clone(),
-                        rate_bits,
-                        false,
-                        cap_height,
-                        timing,
-                        None,
-                    )
-                )
-            })
-            .collect::<Vec<_>>()
-    );
-
     // Get the Merkle caps for all trace commitments and observe them.
     let trace_caps = trace_commitments
         .iter()
         .map(|c| c.merkle_tree.cap.clone())
         .collect::<Vec<_>>();
     let mut challenger = Challenger::<F, C::Hasher>::new();
-    for cap in &trace_caps {
-        challenger.observe_cap(cap);
+    for (cap, &in_use) in trace_caps.iter().zip(&table_in_use) {
+        observe_trace_cap(&mut challenger, cap, in_use);
     }
 
     observe_public_values::<F, C, D>(&mut challenger, public_values)
         .map_err(|_| anyhow::Error::msg("Invalid conversion of public values."))?;
 
+    // This is an expensive check, hence is only run when `debug_assertions` or
+    // the `ctl_diagnostics` feature are enabled.
+    #[cfg(any(debug_assertions, feature = "ctl_diagnostics"))]
+    {
+        use hashbrown::HashMap;
+
+        use crate::ctl_diagnostics::check_ctls;
+        use crate::verifier::debug_utils::get_memory_extra_looking_values;
+
+        let mut extra_values = HashMap::new();
+        extra_values.insert(
+            *Table::Memory,
+            get_memory_extra_looking_values(public_values),
+        );
+        timed!(
+            timing,
+            "check CTLs",
+            check_ctls(
+                &trace_poly_values,
+                &all_stark.cross_table_lookups,
+                &extra_values,
+            )?
+        );
+    }
+
     // For each STARK, compute its cross-table lookup Z polynomials and get the
     // associated `CtlData`.
     let (ctl_challenges, ctl_data_per_table) = timed!(
         timing,
         "compute CTL data",
         get_ctl_data::<F, C, D, NUM_TABLES>(
-            config,
+            config.ctl_config(),
             &trace_poly_values,
             &all_stark.cross_table_lookups,
             &mut challenger,
@@ -172,10 +558,12 @@ where
             &trace_poly_values,
             trace_commitments,
             ctl_data_per_table,
+            &table_in_use,
             &mut challenger,
             &ctl_challenges,
             timing,
             abort_signal,
+            prove_times,
         )?
     );
     public_values.mem_before = MemCap {
@@ -207,33 +595,13 @@ where
             .collect::<Vec<_>>(),
     };
 
-    // This is an expensive check, hence is only run when `debug_assertions` are
-    // enabled.
-    #[cfg(debug_assertions)]
-    {
-        use hashbrown::HashMap;
-        use starky::cross_table_lookup::debug_utils::check_ctls;
-
-        use crate::verifier::debug_utils::get_memory_extra_looking_values;
-
-        let mut extra_values = HashMap::new();
-        extra_values.insert(
-            *Table::Memory,
-            get_memory_extra_looking_values(public_values),
-        );
-        check_ctls(
-            &trace_poly_values,
-            &all_stark.cross_table_lookups,
-            &extra_values,
-        );
-    }
-
     Ok(AllProof {
         multi_proof: MultiProof {
             stark_proofs,
             ctl_challenges,
         },
         public_values: public_values.clone(),
+        table_in_use,
     })
 }
 
@@ -251,28 +619,32 @@ type ProofWithMemCaps<F, C, H, const D: usize> = (
 /// - `trace_commitments` are the trace polynomials commitments for each STARK.
 /// - `ctl_data_per_table` group all the cross-table lookup data for each STARK.
 ///
-/// Each STARK uses its associated data to generate a proof.
+/// Each STARK uses its associated data to generate a proof, and the time it
+/// takes is added to `prove_times`.
 fn prove_with_commitments<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
-    config: &StarkConfig,
+    config: &AllStarkConfig,
     trace_poly_values: &[Vec<PolynomialValues<F>>; NUM_TABLES],
     trace_commitments: Vec<PolynomialBatch<F, C, D>>,
     ctl_data_per_table: [CtlData<F>; NUM_TABLES],
+    table_in_use: &[bool; NUM_TABLES],
     challenger: &mut Challenger<F, C::Hasher>,
     ctl_challenges: &GrandProductChallengeSet<F>,
     timing: &mut TimingTree,
     abort_signal: Option<Arc<AtomicBool>>,
+    prove_times: &mut [Duration; NUM_TABLES],
 ) -> Result<ProofWithMemCaps<F, C, C::Hasher, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
+    let mut fork = Challenger::new();
     let (arithmetic_proof, _) = timed!(
         timing,
         "prove Arithmetic STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::Arithmetic], || prove_single_table(
             &all_stark.arithmetic_stark,
-            config,
+            config.table(Table::Arithmetic),
             &trace_poly_values[Table::Arithmetic as usize],
             &trace_commitments[Table::Arithmetic as usize],
             &ctl_data_per_table[Table::Arithmetic as usize],
@@ -280,29 +652,32 @@ where
             challenger,
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (byte_packing_proof, _) = timed!(
         timing,
         "prove byte packing STARK",
-        prove_single_table(
-            &all_stark.byte_packing_stark,
-            config,
-            &trace_poly_values[Table::BytePacking as usize],
-            &trace_commitments[Table::BytePacking as usize],
-            &ctl_data_per_table[Table::BytePacking as usize],
-            ctl_challenges,
-            challenger,
-            timing,
-            abort_signal.clone(),
+        add_elapsed(
+            &mut prove_times[*Table::BytePacking],
+            || prove_single_table(
+                &all_stark.byte_packing_stark,
+                config.table(Table::BytePacking),
+                &trace_poly_values[Table::BytePacking as usize],
+                &trace_commitments[Table::BytePacking as usize],
+                &ctl_data_per_table[Table::BytePacking as usize],
+                ctl_challenges,
+                table_challenger(challenger, &mut fork, table_in_use[*Table::BytePacking]),
+                timing,
+                abort_signal.clone(),
+            )
         )?
     );
     let (cpu_proof, _) = timed!(
         timing,
         "prove CPU STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::Cpu], || prove_single_table(
             &all_stark.cpu_stark,
-            config,
+            config.table(Table::Cpu),
             &trace_poly_values[Table::Cpu as usize],
             &trace_commitments[Table::Cpu as usize],
             &ctl_data_per_table[Table::Cpu as usize],
@@ -310,59 +685,61 @@ where
             challenger,
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (keccak_proof, _) = timed!(
         timing,
         "prove Keccak STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::Keccak], || prove_single_table(
             &all_stark.keccak_stark,
-            config,
+            config.table(Table::Keccak),
             &trace_poly_values[Table::Keccak as usize],
             &trace_commitments[Table::Keccak as usize],
             &ctl_data_per_table[Table::Keccak as usize],
             ctl_challenges,
-            challenger,
+            table_challenger(challenger, &mut fork, table_in_use[*Table::Keccak]),
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (keccak_sponge_proof, _) = timed!(
         timing,
         "prove Keccak sponge STARK",
-        prove_single_table(
-            &all_stark.keccak_sponge_stark,
-            config,
-            &trace_poly_values[Table::KeccakSponge as usize],
-            &trace_commitments[Table::KeccakSponge as usize],
-            &ctl_data_per_table[Table::KeccakSponge as usize],
-            ctl_challenges,
-            challenger,
-            timing,
-            abort_signal.clone(),
-        )?
+        add_elapsed(&mut prove_times[*Table::KeccakSponge], || {
+            prove_single_table(
+                &all_stark.keccak_sponge_stark,
+                config.table(Table::KeccakSponge),
+                &trace_poly_values[Table::KeccakSponge as usize],
+                &trace_commitments[Table::KeccakSponge as usize],
+                &ctl_data_per_table[Table::KeccakSponge as usize],
+                ctl_challenges,
+                table_challenger(challenger, &mut fork, table_in_use[*Table::KeccakSponge]),
+                timing,
+                abort_signal.clone(),
+            )
+        })?
     );
     let (logic_proof, _) = timed!(
         timing,
         "prove logic STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::Logic], || prove_single_table(
             &all_stark.logic_stark,
-            config,
+            config.table(Table::Logic),
             &trace_poly_values[Table::Logic as usize],
             &trace_commitments[Table::Logic as usize],
             &ctl_data_per_table[Table::Logic as usize],
             ctl_challenges,
-            challenger,
+            table_challenger(challenger, &mut fork, table_in_use[*Table::Logic]),
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (memory_proof, _) = timed!(
         timing,
         "prove memory STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::Memory], || prove_single_table(
             &all_stark.memory_stark,
-            config,
+            config.table(Table::Memory),
             &trace_poly_values[Table::Memory as usize],
             &trace_commitments[Table::Memory as usize],
             &ctl_data_per_table[Table::Memory as usize],
@@ -370,14 +747,14 @@ where
             challenger,
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (mem_before_proof, mem_before_cap) = timed!(
         timing,
         "prove mem_before STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::MemBefore], || prove_single_table(
             &all_stark.mem_before_stark,
-            config,
+            config.table(Table::MemBefore),
             &trace_poly_values[Table::MemBefore as usize],
             &trace_commitments[Table::MemBefore as usize],
             &ctl_data_per_table[Table::MemBefore as usize],
@@ -385,22 +762,52 @@ where
             challenger,
             timing,
             abort_signal.clone(),
-        )?
+        ))?
     );
     let (mem_after_proof, mem_after_cap) = timed!(
         timing,
         "prove mem_after STARK",
-        prove_single_table(
+        add_elapsed(&mut prove_times[*Table::MemAfter], || prove_single_table(
             &all_stark.mem_after_stark,
-            config,
+            config.table(Table::MemAfter),
             &trace_poly_values[Table::MemAfter as usize],
             &trace_commitments[Table::MemAfter as usize],
             &ctl_data_per_table[Table::MemAfter as usize],
             ctl_challenges,
             challenger,
             timing,
+            abort_signal.clone(),
+        ))?
+    );
+    let (range_check_proof, _) = timed!(
+        timing,
+        "prove range-check STARK",
+        add_elapsed(&mut prove_times[*Table::RangeCheck], || prove_single_table(
+            &all_stark.range_check_stark,
+            config.table(Table::RangeCheck),
+            &trace_poly_values[Table::RangeCheck as usize],
+            &trace_commitments[Table::RangeCheck as usize],
+            &ctl_data_per_table[Table::RangeCheck as usize],
+            ctl_challenges,
+            challenger,
+            timing,
+            abort_signal.clone(),
+        ))?
+    );
+    let (ec_proof, _) = timed!(
+        timing,
+        "prove EC STARK",
+        add_elapsed(&mut prove_times[*Table::Ec], || prove_single_table(
+            &all_stark.ec_stark,
+            config.table(Table::Ec),
+            &trace_poly_values[Table::Ec as usize],
+            &trace_commitments[Table::Ec as usize],
+            &ctl_data_per_table[Table::Ec as usize],
+            ctl_challenges,
+            table_challenger(challenger, &mut fork, table_in_use[*Table::Ec]),
+            timing,
             abort_signal,
-        )?
+        ))?
     );
 
     Ok((
@@ -414,12 +821,38 @@ where
             memory_proof,
             mem_before_proof,
             mem_after_proof,
+            range_check_proof,
+            ec_proof,
         ],
         mem_before_cap,
         mem_after_cap,
     ))
 }
 
+/// Runs `f`, adding the time it takes to `elapsed`.
+fn add_elapsed<T>(elapsed: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let output = f();
+    *elapsed += start.elapsed();
+    output
+}
+
+/// Returns the challenger to prove a table with: tables which are not in use
+/// are proven with `fork`, a copy of `challenger`, so that the transcript of
+/// the segment is left unchanged.
+fn table_challenger<'a, F: RichField, H: Hasher<F>>(
+    challenger: &'a mut Challenger<F, H>,
+    fork: &'a mut Challenger<F, H>,
+    in_use: bool,
+) -> &'a mut Challenger<F, H> {
+    if in_use {
+        challenger
+    } else {
+        fork.clone_from(challenger);
+        fork
+    }
+}
+
 type ProofSingleWithCap<F, C, H, const D: usize> =
     (StarkProofWithMetadata<F, C, D>, MerkleCap<F, H>);
 
@@ -490,20 +923,25 @@ fn build_segment_data<F: RichField>(
     registers_after: Option<RegistersState>,
     memory: Option<MemoryState>,
     interpreter: &Interpreter<F>,
+    initial_segments: &Arc<InitialSegments>,
 ) -> GenerationSegmentData {
     GenerationSegmentData {
         segment_index,
         registers_before: registers_before.unwrap_or(RegistersState::new()),
         registers_after: registers_after.unwrap_or(RegistersState::new()),
-        memory: memory.unwrap_or(MemoryState {
-            preinitialized_segments: interpreter
-                .generation_state
-                .memory
-                .preinitialized_segments
-                .clone(),
-            ..Default::default()
-        }),
+        memory: SegmentMemory::Decoded {
+            memory: memory.unwrap_or(MemoryState {
+                preinitialized_segments: interpreter
+                    .generation_state
+                    .memory
+                    .preinitialized_segments
+                    .clone(),
+                ..Default::default()
+            }),
+            initial_segments: initial_segments.clone(),
+        },
         max_cpu_len_log: interpreter.get_max_cpu_len_log(),
+        cycle_limit: None,
         extra_data: ExtraSegmentData {
             bignum_modmul_result_limbs: interpreter
                 .generation_state
@@ -517,6 +955,8 @@ fn build_segment_data<F: RichField>(
             ger_prover_inputs: interpreter.generation_state.ger_prover_inputs.clone(),
             trie_root_ptrs: interpreter.generation_state.trie_root_ptrs.clone(),
             jumpdest_table: interpreter.generation_state.jumpdest_table.clone(),
+            jumpdest_table_simulated: interpreter.generation_state.jumpdest_table_simulated,
+            jumpdest_contexts: interpreter.generation_state.jumpdest_contexts.clone(),
             next_txn_index: interpreter.generation_state.next_txn_index,
         },
     }
@@ -524,7 +964,14 @@ fn build_segment_data<F: RichField>(
 
 pub struct SegmentDataIterator<F: RichField> {
     interpreter: Interpreter<F>,
+    /// The preinitialized segments at the start of the batch.
+    initial_segments: Arc<InitialSegments>,
     partial_next_data: Option<GenerationSegmentData>,
+    /// The total number of CPU cycles of the segments generated so far.
+    cycles: usize,
+    /// The cycle profiles of the segments generated so far, if profiling is
+    /// enabled.
+    cycle_profiles: Vec<CycleProfile>,
 }
 
 pub type SegmentRunResult = Option<Box<(GenerationSegmentData, Option<GenerationSegmentData>)>>;
@@ -544,12 +991,48 @@ impl<F: RichField> SegmentDataIterator<F> {
             max_cpu_len_log,
         );
 
+        let initial_segments = Arc::new(
+            interpreter
+                .generation_state
+                .memory
+                .preinitialized_segments
+                .clone(),
+        );
+
         Self {
             interpreter,
+            initial_segments,
             partial_next_data: None,
+            cycles: 0,
+            cycle_profiles: vec![],
         }
     }
 
+    /// Enables the profiling of the instructions of each segment, by kernel
+    /// routine.
+    pub fn with_cycle_profiling(mut self) -> Self {
+        self.interpreter.cycle_profile = Some(CycleProfile::default());
+        self
+    }
+
+    /// Returns the cycle profiles of the segments generated so far, empty
+    /// unless profiling is enabled.
+    pub fn cycle_profiles(&self) -> &[CycleProfile] {
+        &self.cycle_profiles
+    }
+
+    /// Returns the total number of CPU cycles of the segments generated so
+    /// far.
+    pub const fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    /// Returns the profile of the computation of the final state trie, once
+    /// the last segment has been generated.
+    pub const fn final_tries_stats(&self) -> FinalTriesStats {
+        self.interpreter.final_tries_stats
+    }
+
     /// Returns the data for the current segment, as well as the data -- except
     /// registers_after -- for the next segment.
     fn generate_next_segment(
@@ -565,10 +1048,20 @@ impl<F: RichField> SegmentDataIterator<F> {
             self.interpreter
                 .get_mut_generation_state()
                 .set_segment_data(&partial);
-            self.interpreter.generation_state.memory = partial.memory.clone();
+            let SegmentMemory::Decoded { memory, .. } = &partial.memory else {
+                unreachable!("The segment data of the next segment is built decoded");
+            };
+            self.interpreter.generation_state.memory = memory.clone();
             partial
         } else {
-            build_segment_data(0, None, None, None, &self.interpreter)
+            build_segment_data(
+                0,
+                None,
+                None,
+                None,
+                &self.interpreter,
+                &self.initial_segments,
+            )
         };
 
         let segment_index = segment_data.segment_index;
@@ -577,15 +1070,21 @@ impl<F: RichField> SegmentDataIterator<F> {
         // next segment.
         let run = set_registers_and_run(segment_data.registers_after, &mut self.interpreter);
         if let Ok((updated_registers, mem_after)) = run {
+            self.cycles += self.interpreter.get_clock();
+            if let Some(profile) = self.interpreter.cycle_profile.as_mut() {
+                self.cycle_profiles.push(std::mem::take(profile));
+            }
             let partial_segment_data = Some(build_segment_data(
                 segment_index + 1,
                 Some(updated_registers),
                 Some(updated_registers),
                 mem_after,
                 &self.interpreter,
+                &self.initial_segments,
             ));
 
             segment_data.registers_after = updated_registers;
+            segment_data.cycle_limit = Some(self.interpreter.segment_end_clock);
             Ok(Some(Box::new((segment_data, partial_segment_data))))
         } else {
             let inputs = &self.interpreter.get_generation_state().inputs;
@@ -635,6 +1134,60 @@ impl<F: RichField> Iterator for SegmentDataIterator<F> {
     }
 }
 
+impl<F: RichField> SegmentDataIterator<F> {
+    /// Moves the segmentation to a dedicated thread, which runs ahead of the
+    /// consumer by up to `depth` segments, so that the generation of the data
+    /// of the next segments overlaps with the proving of the current ones.
+    ///
+    /// The segments are yielded in the same order, and the segmentation stops
+    /// after the first error, or once the returned iterator is dropped.
+    pub fn in_background(self, depth: usize) -> BackgroundSegmentDataIterator {
+        let (sender, receiver) = mpsc::sync_channel(depth);
+        let handle = thread::Builder::new()
+            .name("segmentation".to_string())
+            .spawn(move || {
+                for data in self {
+                    let is_err = data.is_err();
+                    if sender.send(data).is_err() || is_err {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn the segmentation thread");
+
+        BackgroundSegmentDataIterator {
+            receiver,
+            handle: Some(handle),
+        }
+    }
+}
+
+/// A [`SegmentDataIterator`] running on a dedicated thread, built with
+/// [`SegmentDataIterator::in_background`].
+pub struct BackgroundSegmentDataIterator {
+    receiver: mpsc::Receiver<AllData>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Iterator for BackgroundSegmentDataIterator {
+    type Item = AllData;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(data) => Some(data),
+            // The sender is dropped once the segmentation is over, or if it panicked.
+            Err(_) => {
+                if let Some(handle) = self.handle.take() {
+                    if let Err(panic) = handle.join() {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
 /// A utility module designed to test witness generation externally.
 pub mod testing {
     use super::*;
@@ -662,12 +1215,37 @@ pub mod testing {
 
     pub fn prove_all_segments<F, C, const D: usize>(
         all_stark: &AllStark<F, D>,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
         inputs: GenerationInputs,
         max_cpu_len_log: usize,
         timing: &mut TimingTree,
         abort_signal: Option<Arc<AtomicBool>>,
     ) -> Result<Vec<AllProof<F, C, D>>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        prove_all_segments_with_metrics(
+            all_stark,
+            config,
+            inputs,
+            max_cpu_len_log,
+            timing,
+            abort_signal,
+        )
+        .map(|(proofs, _)| proofs)
+    }
+
+    /// Proves all the segments of `inputs` like [`prove_all_segments`], also
+    /// returning the metrics of their generation.
+    pub fn prove_all_segments_with_metrics<F, C, const D: usize>(
+        all_stark: &AllStark<F, D>,
+        config: &AllStarkConfig,
+        inputs: GenerationInputs,
+        max_cpu_len_log: usize,
+        timing: &mut TimingTree,
+        abort_signal: Option<Arc<AtomicBool>>,
+    ) -> Result<(Vec<AllProof<F, C, D>>, ProvingMetrics)>
     where
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
@@ -675,21 +1253,23 @@ pub mod testing {
         let segment_data_iterator = SegmentDataIterator::<F>::new(&inputs, Some(max_cpu_len_log));
         let inputs = inputs.trim();
         let mut proofs = vec![];
+        let mut metrics = ProvingMetrics::default();
 
         for segment_run in segment_data_iterator {
             let (_, mut next_data) = segment_run.map_err(|e| anyhow::format_err!(e))?;
-            let proof = prove(
+            let proof = prove_with_metrics(
                 all_stark,
                 config,
                 inputs.clone(),
                 &mut next_data,
                 timing,
                 abort_signal.clone(),
+                &mut metrics,
             )?;
             proofs.push(proof);
         }
 
-        Ok(proofs)
+        Ok((proofs, metrics))
     }
 
     pub fn simulate_execution_all_segments<F>(
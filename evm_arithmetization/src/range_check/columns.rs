@@ -0,0 +1,9 @@
+//! Columns for the range-check table, holding each value of the range once,
+//! in increasing order.
+
+/// The value of the range of this row.
+pub(crate) const VALUE: usize = 0;
+/// The number of lookups of `VALUE` by the other tables.
+pub(crate) const MULTIPLICITY: usize = VALUE + 1;
+
+pub(crate) const NUM_COLUMNS: usize = MULTIPLICITY + 1;
@@ -0,0 +1,5 @@
+//! The RangeCheck STARK is a lookup table of all the values of `0..2^16`,
+//! shared by the tables range-checking their columns through CTLs.
+
+pub mod columns;
+pub mod range_check_stark;
@@ -0,0 +1,220 @@
+//! `RangeCheckStark` holds all the values of `0..RANGE_MAX`, along with the
+//! number of times each of them is looked up by the other tables.
+//!
+//! Tables range-check their columns by looking them up in this table through a
+//! single CTL, whose looked filter is the multiplicity of each value, instead
+//! of each holding its own counter and frequencies columns.
+use std::marker::PhantomData;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+#[cfg(feature = "generation")]
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+#[cfg(any(feature = "generation", test))]
+use plonky2::field::types::PrimeField64;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use starky::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use starky::evaluation_frame::StarkEvaluationFrame;
+use starky::lookup::{Column, Filter, Lookup};
+use starky::stark::Stark;
+
+use super::columns::{MULTIPLICITY, NUM_COLUMNS, VALUE};
+use crate::all_stark::EvmStarkFrame;
+
+/// Strict upper bound of the range-checked values.
+pub(crate) const RANGE_MAX: usize = 1 << 16;
+
+/// Returns the column of the values looked up by the other tables.
+pub(crate) fn ctl_looked_data<F: Field>() -> Vec<Column<F>> {
+    vec![Column::single(VALUE)]
+}
+
+/// CTL filter of the range-check table, counting each value as many times as
+/// it is looked up.
+pub(crate) fn ctl_looked_filter<F: Field>() -> Filter<F> {
+    Filter::new_simple(Column::single(MULTIPLICITY))
+}
+
+/// The number of lookups of each value of the range, gathered from the traces
+/// of the looking tables.
+#[cfg(any(feature = "generation", test))]
+#[derive(Clone, Debug)]
+pub(crate) struct RangeCheckMultiplicities(Vec<u64>);
+
+#[cfg(any(feature = "generation", test))]
+impl Default for RangeCheckMultiplicities {
+    fn default() -> Self {
+        Self(vec![0; RANGE_MAX])
+    }
+}
+
+#[cfg(any(feature = "generation", test))]
+impl RangeCheckMultiplicities {
+    /// Records a lookup of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in the range.
+    pub(crate) fn add<F: PrimeField64>(&mut self, value: F) {
+        let x = value.to_canonical_u64() as usize;
+        assert!(
+            x < RANGE_MAX,
+            "range-checked value {} exceeds the max range value {}",
+            x,
+            RANGE_MAX
+        );
+        self.0[x] += 1;
+    }
+
+    /// Records a lookup of each of `values`.
+    pub(crate) fn extend<F: PrimeField64>(&mut self, values: impl IntoIterator<Item = F>) {
+        for value in values {
+            self.add(value);
+        }
+    }
+
+    /// Returns the number of lookups of each value of the range.
+    pub(crate) fn counts(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+/// Structure representing the `RangeCheck` STARK.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct RangeCheckStark<F, const D: usize> {
+    f: PhantomData<F>,
+}
+
+#[cfg(feature = "generation")]
+impl<F: RichField + Extendable<D>, const D: usize> RangeCheckStark<F, D> {
+    /// Generates the trace of the range, which always has `RANGE_MAX` rows.
+    pub(crate) fn generate_trace(
+        &self,
+        multiplicities: &RangeCheckMultiplicities,
+    ) -> Vec<PolynomialValues<F>> {
+        let values = (0..RANGE_MAX).map(F::from_canonical_usize).collect();
+        let multiplicities = multiplicities
+            .counts()
+            .iter()
+            .map(|&count| F::from_canonical_u64(count))
+            .collect();
+
+        vec![
+            PolynomialValues::new(values),
+            PolynomialValues::new(multiplicities),
+        ]
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for RangeCheckStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize> = EvmStarkFrame<P, FE, NUM_COLUMNS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = EvmStarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, NUM_COLUMNS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let local_values = vars.get_local_values();
+        let next_values = vars.get_next_values();
+
+        // The values start from 0, increment by 1 and end at `RANGE_MAX - 1`,
+        // so that the table has exactly `RANGE_MAX` rows.
+        let value = local_values[VALUE];
+        let next_value = next_values[VALUE];
+        yield_constr.constraint_first_row(value);
+        yield_constr.constraint_transition(next_value - value - P::ONES);
+        let range_max = P::Scalar::from_canonical_u64((RANGE_MAX - 1) as u64);
+        yield_constr.constraint_last_row(value - range_max);
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let local_values = vars.get_local_values();
+        let next_values = vars.get_next_values();
+        let one = builder.one_extension();
+
+        // The values start from 0, increment by 1 and end at `RANGE_MAX - 1`,
+        // so that the table has exactly `RANGE_MAX` rows.
+        let value = local_values[VALUE];
+        let next_value = next_values[VALUE];
+        yield_constr.constraint_first_row(builder, value);
+        let incr = builder.sub_extension(next_value, value);
+        let t = builder.sub_extension(incr, one);
+        yield_constr.constraint_transition(builder, t);
+        let range_max =
+            builder.constant_extension(F::Extension::from_canonical_usize(RANGE_MAX - 1));
+        let t = builder.sub_extension(value, range_max);
+        yield_constr.constraint_last_row(builder, t);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+
+    fn lookups(&self) -> Vec<Lookup<F>> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use starky::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    use super::*;
+
+    #[test]
+    fn test_stark_degree() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = RangeCheckStark<F, D>;
+
+        let stark = S::default();
+        test_stark_low_degree(stark)
+    }
+
+    #[test]
+    fn test_stark_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = RangeCheckStark<F, D>;
+
+        let stark = S::default();
+        test_stark_circuit_constraints::<F, C, S, D>(stark)
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the max range value")]
+    fn rejects_out_of_range_values() {
+        let mut multiplicities = RangeCheckMultiplicities::default();
+        multiplicities.extend([
+            GoldilocksField::ZERO,
+            GoldilocksField::from_canonical_usize(5),
+        ]);
+        assert_eq!(multiplicities.counts()[5], 1);
+        multiplicities.add(GoldilocksField::from_canonical_usize(RANGE_MAX));
+    }
+}
@@ -345,17 +345,13 @@ pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>,
             GlobalMetadata::BlockNumber,
             public_values.block_metadata.block_number,
         ),
-        (
-            GlobalMetadata::BlockDifficulty,
-            public_values.block_metadata.block_difficulty,
-        ),
         (
             GlobalMetadata::BlockGasLimit,
             public_values.block_metadata.block_gaslimit,
         ),
         (
-            GlobalMetadata::BlockChainId,
-            public_values.block_metadata.block_chain_id,
+            GlobalMetadata::BlockHardfork,
+            public_values.block_metadata.block_hardfork,
         ),
         (
             GlobalMetadata::BlockGasUsed,
@@ -379,18 +375,27 @@ pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>,
         ),
     ];
 
-    // This contains the `block_beneficiary`, `block_random`, `block_base_fee`,
-    // `block_blob_gas_used`, `block_excess_blob_gas`, `parent_beacon_block_root`
-    // as well as `cur_hash`.
-    let block_fields_arrays: [(GlobalMetadata, &[Target]); 7] = [
+    // This contains the `block_beneficiary`, `block_difficulty`, `block_random`,
+    // `block_chain_id`, `block_base_fee`, `block_blob_gas_used`,
+    // `block_excess_blob_gas`, `parent_beacon_block_root`, `withdrawals_root` as
+    // well as `cur_hash`, `parent_hash` and `ancestors_hash`.
+    let block_fields_arrays: [(GlobalMetadata, &[Target]); 12] = [
         (
             GlobalMetadata::BlockBeneficiary,
             &public_values.block_metadata.block_beneficiary,
         ),
+        (
+            GlobalMetadata::BlockDifficulty,
+            &public_values.block_metadata.block_difficulty,
+        ),
         (
             GlobalMetadata::BlockRandom,
             &public_values.block_metadata.block_random,
         ),
+        (
+            GlobalMetadata::BlockChainId,
+            &public_values.block_metadata.block_chain_id,
+        ),
         (
             GlobalMetadata::BlockBaseFee,
             &public_values.block_metadata.block_base_fee,
@@ -407,10 +412,22 @@ pub(crate) fn get_memory_extra_looking_sum_circuit<F: RichField + Extendable<D>,
             GlobalMetadata::ParentBeaconBlockRoot,
             &public_values.block_metadata.parent_beacon_block_root,
         ),
+        (
+            GlobalMetadata::BlockWithdrawalsRoot,
+            &public_values.block_metadata.withdrawals_root,
+        ),
         (
             GlobalMetadata::BlockCurrentHash,
             &public_values.block_hashes.cur_hash,
         ),
+        (
+            GlobalMetadata::BlockParentHash,
+            &public_values.block_hashes.parent_hash,
+        ),
+        (
+            GlobalMetadata::BlockAncestorsHash,
+            &public_values.block_hashes.ancestors_hash,
+        ),
     ];
 
     let metadata_segment =
@@ -661,16 +678,18 @@ pub(crate) fn add_virtual_block_metadata<F: RichField + Extendable<D>, const D:
     let block_beneficiary = builder.add_virtual_public_input_arr();
     let block_timestamp = builder.add_virtual_public_input();
     let block_number = builder.add_virtual_public_input();
-    let block_difficulty = builder.add_virtual_public_input();
+    let block_difficulty = builder.add_virtual_public_input_arr();
     let block_random = builder.add_virtual_public_input_arr();
     let block_gaslimit = builder.add_virtual_public_input();
-    let block_chain_id = builder.add_virtual_public_input();
+    let block_chain_id = builder.add_virtual_public_input_arr();
     let block_base_fee = builder.add_virtual_public_input_arr();
     let block_gas_used = builder.add_virtual_public_input();
     let block_blob_gas_used = builder.add_virtual_public_input_arr();
     let block_excess_blob_gas = builder.add_virtual_public_input_arr();
     let parent_beacon_block_root = builder.add_virtual_public_input_arr();
+    let withdrawals_root = builder.add_virtual_public_input_arr();
     let block_bloom = builder.add_virtual_public_input_arr();
+    let block_hardfork = builder.add_virtual_public_input();
     BlockMetadataTarget {
         block_beneficiary,
         block_timestamp,
@@ -684,7 +703,9 @@ pub(crate) fn add_virtual_block_metadata<F: RichField + Extendable<D>, const D:
         block_blob_gas_used,
         block_excess_blob_gas,
         parent_beacon_block_root,
+        withdrawals_root,
         block_bloom,
+        block_hardfork,
     }
 }
 
@@ -693,9 +714,13 @@ pub(crate) fn add_virtual_block_hashes<F: RichField + Extendable<D>, const D: us
 ) -> BlockHashesTarget {
     let prev_hashes = builder.add_virtual_public_input_arr();
     let cur_hash = builder.add_virtual_public_input_arr();
+    let parent_hash = builder.add_virtual_public_input_arr();
+    let ancestors_hash = builder.add_virtual_public_input_arr();
     BlockHashesTarget {
         prev_hashes,
         cur_hash,
+        parent_hash,
+        ancestors_hash,
     }
 }
 pub(crate) fn add_virtual_extra_block_data<F: RichField + Extendable<D>, const D: usize>(
@@ -883,10 +908,9 @@ where
         block_metadata_target.block_number,
         u256_to_u32(block_metadata.block_number)?,
     );
-    witness.set_target(
-        block_metadata_target.block_difficulty,
-        u256_to_u32(block_metadata.block_difficulty)?,
-    );
+    let difficulty = u256_to_u64(block_metadata.block_difficulty)?;
+    witness.set_target(block_metadata_target.block_difficulty[0], difficulty.0);
+    witness.set_target(block_metadata_target.block_difficulty[1], difficulty.1);
     witness.set_target_arr(
         &block_metadata_target.block_random,
         &h256_limbs(block_metadata.block_random),
@@ -895,10 +919,9 @@ where
         block_metadata_target.block_gaslimit,
         u256_to_u32(block_metadata.block_gaslimit)?,
     );
-    witness.set_target(
-        block_metadata_target.block_chain_id,
-        u256_to_u32(block_metadata.block_chain_id)?,
-    );
+    let chain_id = u256_to_u64(block_metadata.block_chain_id)?;
+    witness.set_target(block_metadata_target.block_chain_id[0], chain_id.0);
+    witness.set_target(block_metadata_target.block_chain_id[1], chain_id.1);
     // Basefee fits in 2 limbs
     let basefee = u256_to_u64(block_metadata.block_base_fee)?;
     witness.set_target(block_metadata_target.block_base_fee[0], basefee.0);
@@ -932,12 +955,20 @@ where
         &block_metadata_target.parent_beacon_block_root,
         &h256_limbs(block_metadata.parent_beacon_block_root),
     );
+    witness.set_target_arr(
+        &block_metadata_target.withdrawals_root,
+        &h256_limbs(block_metadata.withdrawals_root),
+    );
 
     let mut block_bloom_limbs = [F::ZERO; 64];
     for (i, limbs) in block_bloom_limbs.chunks_exact_mut(8).enumerate() {
         limbs.copy_from_slice(&u256_limbs(block_metadata.block_bloom[i]));
     }
     witness.set_target_arr(&block_metadata_target.block_bloom, &block_bloom_limbs);
+    witness.set_target(
+        block_metadata_target.block_hardfork,
+        F::from_canonical_u64(block_metadata.block_hardfork.id()),
+    );
 
     Ok(())
 }
@@ -959,6 +990,14 @@ pub(crate) fn set_block_hashes_target<F, W, const D: usize>(
     }
     let cur_block_hash_limbs: [F; 8] = h256_limbs::<F>(block_hashes.cur_hash);
     witness.set_target_arr(&block_hashes_target.cur_hash, &cur_block_hash_limbs);
+    witness.set_target_arr(
+        &block_hashes_target.parent_hash,
+        &h256_limbs::<F>(block_hashes.parent_hash()),
+    );
+    witness.set_target_arr(
+        &block_hashes_target.ancestors_hash,
+        &h256_limbs::<F>(block_hashes.ancestors_hash()),
+    );
 }
 
 pub(crate) fn set_extra_public_values_target<F, W, const D: usize>(
@@ -0,0 +1,207 @@
+//! Named FRI security presets for the STARK proofs of the zkEVM.
+//!
+//! The conjectured soundness of a FRI proof is `rate_bits * num_query_rounds +
+//! proof_of_work_bits`. Each preset picks the number of query rounds reaching
+//! its target at the rate of [`StarkConfig::standard_fast_config`], so that
+//! deployments trade proving time for soundness explicitly rather than by
+//! editing constants.
+//!
+//! The recursion circuits follow the same target with
+//! [`SecurityPreset::recursion_config`]. Their recursive verifiers are sized
+//! for at most the queries of [`CircuitConfig::standard_recursion_config`], to
+//! which the shrinking and aggregation thresholds are tuned, so higher targets
+//! raise their rate instead. The soundness of a block proof is the smallest of
+//! both, as reported by [`SecurityReport`].
+use core::fmt;
+use core::str::FromStr;
+
+use plonky2::fri::FriConfig;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use starky::config::StarkConfig;
+
+use crate::all_stark::AllStarkConfig;
+
+/// A target conjectured soundness for the STARK proofs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SecurityPreset {
+    Bits80,
+    /// The soundness of [`StarkConfig::standard_fast_config`].
+    #[default]
+    Bits100,
+    Bits128,
+}
+
+impl SecurityPreset {
+    pub const ALL: [Self; 3] = [Self::Bits80, Self::Bits100, Self::Bits128];
+
+    /// The proof-of-work bits of all presets.
+    const PROOF_OF_WORK_BITS: u32 = 16;
+
+    pub const fn target_bits(self) -> usize {
+        match self {
+            Self::Bits80 => 80,
+            Self::Bits100 => 100,
+            Self::Bits128 => 128,
+        }
+    }
+
+    /// The STARK configuration of this preset. [`Self::Bits100`] yields
+    /// [`StarkConfig::standard_fast_config`].
+    pub fn stark_config(self) -> StarkConfig {
+        let mut config = StarkConfig::standard_fast_config();
+        let rate_bits = config.fri_config.rate_bits;
+        let grinding_bits = Self::PROOF_OF_WORK_BITS as usize;
+
+        config.security_bits = self.target_bits();
+        config.fri_config.proof_of_work_bits = Self::PROOF_OF_WORK_BITS;
+        config.fri_config.num_query_rounds =
+            (self.target_bits() - grinding_bits).div_ceil(rate_bits);
+        config
+    }
+
+    /// The configuration of the recursion circuits of this preset.
+    /// [`Self::Bits100`] yields [`CircuitConfig::standard_recursion_config`].
+    pub fn recursion_config(self) -> CircuitConfig {
+        let mut config = CircuitConfig::standard_recursion_config();
+        let max_query_rounds = config.fri_config.num_query_rounds;
+        let grinding_bits = Self::PROOF_OF_WORK_BITS as usize;
+        let query_bits = self.target_bits() - grinding_bits;
+
+        config.security_bits = self.target_bits();
+        config.fri_config.proof_of_work_bits = Self::PROOF_OF_WORK_BITS;
+        config.fri_config.rate_bits = config
+            .fri_config
+            .rate_bits
+            .max(query_bits.div_ceil(max_query_rounds));
+        config.fri_config.num_query_rounds = query_bits.div_ceil(config.fri_config.rate_bits);
+        config
+    }
+
+    /// The configuration of the STARK proofs of all tables and of the
+    /// recursion circuits of this preset.
+    pub fn all_stark_config(self) -> AllStarkConfig {
+        AllStarkConfig::uniform(self.stark_config()).with_recursion_config(self.recursion_config())
+    }
+
+    /// The soundness and cost of this preset.
+    pub fn report(self) -> SecurityReport {
+        let fri_config = self.stark_config().fri_config;
+        SecurityReport {
+            preset: self,
+            num_query_rounds: fri_config.num_query_rounds,
+            proof_of_work_bits: fri_config.proof_of_work_bits,
+            stark_bits: conjectured_security_bits(&fri_config),
+            recursion_bits: conjectured_security_bits(&self.recursion_config().fri_config),
+            relative_query_work: fri_config.num_query_rounds as f64
+                / StarkConfig::standard_fast_config()
+                    .fri_config
+                    .num_query_rounds as f64,
+        }
+    }
+}
+
+/// Returns the conjectured soundness, in bits, of proofs with the given FRI
+/// configuration.
+pub const fn conjectured_security_bits(fri_config: &FriConfig) -> usize {
+    fri_config.rate_bits * fri_config.num_query_rounds + fri_config.proof_of_work_bits as usize
+}
+
+impl fmt::Display for SecurityPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.target_bits())
+    }
+}
+
+impl FromStr for SecurityPreset {
+    type Err = String;
+
+    /// Parses the target bits of a preset, e.g. `128`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.to_string() == s)
+            .ok_or_else(|| format!("unknown security preset {s}, expected one of 80, 100 or 128"))
+    }
+}
+
+/// The soundness of the proofs of a [`SecurityPreset`], along with the cost of
+/// its FRI queries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecurityReport {
+    pub preset: SecurityPreset,
+    pub num_query_rounds: usize,
+    pub proof_of_work_bits: u32,
+    /// The conjectured soundness of the STARK proofs.
+    pub stark_bits: usize,
+    /// The conjectured soundness of the recursion circuits.
+    pub recursion_bits: usize,
+    /// The FRI query work of the STARK proofs, relative to the default
+    /// preset. STARK proof sizes and recursive verification costs scale with
+    /// it.
+    pub relative_query_work: f64,
+}
+
+impl SecurityReport {
+    /// The conjectured soundness of the block proofs.
+    pub fn block_proof_bits(&self) -> usize {
+        self.stark_bits.min(self.recursion_bits)
+    }
+}
+
+impl fmt::Display for SecurityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-bit preset: STARK proofs {} bits ({} queries, {} proof-of-work bits, {:.2}x query \
+             work), recursion {} bits, block proofs {} bits",
+            self.preset,
+            self.stark_bits,
+            self.num_query_rounds,
+            self.proof_of_work_bits,
+            self.relative_query_work,
+            self.recursion_bits,
+            self.block_proof_bits(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_reach_their_target() {
+        assert_eq!(
+            format!("{:?}", SecurityPreset::Bits100.stark_config()),
+            format!("{:?}", StarkConfig::standard_fast_config())
+        );
+        assert_eq!(
+            format!("{:?}", SecurityPreset::Bits100.recursion_config()),
+            format!("{:?}", CircuitConfig::standard_recursion_config())
+        );
+
+        let standard_queries = CircuitConfig::standard_recursion_config()
+            .fri_config
+            .num_query_rounds;
+        for preset in SecurityPreset::ALL {
+            let report = preset.report();
+            assert!(report.stark_bits >= preset.target_bits());
+            assert!(report.recursion_bits >= preset.target_bits());
+            assert!(preset.recursion_config().fri_config.num_query_rounds <= standard_queries);
+            assert_eq!(preset.to_string().parse(), Ok(preset));
+        }
+        assert_eq!(SecurityPreset::Bits80.report().num_query_rounds, 64);
+        assert_eq!(SecurityPreset::Bits128.report().num_query_rounds, 112);
+
+        let recursion_80 = SecurityPreset::Bits80.recursion_config().fri_config;
+        assert_eq!(
+            (recursion_80.rate_bits, recursion_80.num_query_rounds),
+            (3, 22)
+        );
+        let recursion_128 = SecurityPreset::Bits128.recursion_config().fri_config;
+        assert_eq!(
+            (recursion_128.rate_bits, recursion_128.num_query_rounds),
+            (4, 28)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Standalone simulation of the execution of a block or batch.
+//!
+//! [`simulate`] runs the kernel in the interpreter, without generating any
+//! trace or proof, and reports the outcome of the execution: the gas used,
+//! the status and logs of each transaction, and the accounts it modified.
+//!
+//! Unlike proving, the simulation does not check the final values of
+//! [`GenerationInputs`], i.e. `gas_used_after` and `trie_roots_after`, which
+//! are instead computed and returned. This makes it suitable for pre-flight
+//! validation of witnesses, and for building the expected values of tests.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use ethereum_types::{H256, U256};
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use mpt_trie::trie_ops::ValOrHash;
+use plonky2::hash::hash_types::RichField;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::generation::invalid_txn::InvalidTxnReason;
+use crate::generation::mpt::{decode_receipt, AccountRlp, LogRlp};
+use crate::generation::trie_extractor::{get_receipt_trie, get_state_trie, get_txn_trie};
+use crate::generation::GenerationInputs;
+use crate::proof::TrieRoots;
+use crate::util::{h2u, u256_to_usize};
+use crate::witness::memory::MemoryState;
+use crate::witness::util::stack_peek;
+
+/// The outcome of a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxnStatus {
+    Success,
+    /// The transaction was executed, but reverted.
+    Reverted,
+    /// The transaction was skipped by the kernel, without charging any gas.
+    Rejected(InvalidTxnReason),
+}
+
+/// The simulated execution of a transaction.
+#[derive(Clone, Debug)]
+pub struct TxnSimulation {
+    /// The index of the transaction within its block.
+    pub txn_number: U256,
+    pub txn_hash: H256,
+    pub status: TxnStatus,
+    pub gas_used: U256,
+    pub logs: Vec<LogRlp>,
+}
+
+/// The change of an account through the execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountDiff {
+    Created(AccountRlp),
+    Modified {
+        before: AccountRlp,
+        after: AccountRlp,
+    },
+    Deleted(AccountRlp),
+}
+
+/// The outcome of the simulated execution of a block or batch.
+#[derive(Clone, Debug)]
+pub struct SimulationResult {
+    /// The gas used by the transactions of the batch.
+    pub gas_used: U256,
+    /// The cumulative gas used in the block after the batch, to be set as
+    /// `gas_used_after`.
+    pub gas_used_after: U256,
+    /// The roots of the tries after the batch, to be set as
+    /// `trie_roots_after`.
+    pub trie_roots_after: TrieRoots,
+    /// The transactions of the batch, in order.
+    pub txns: Vec<TxnSimulation>,
+    /// The accounts changed by the batch, by hashed address.
+    pub state_diff: BTreeMap<H256, AccountDiff>,
+}
+
+/// Simulates the execution of the block or batch of `inputs` in the kernel
+/// interpreter, without generating any trace or proof.
+pub fn simulate<F: RichField>(inputs: &GenerationInputs) -> Result<SimulationResult> {
+    let mut interpreter: Interpreter<F> =
+        Interpreter::new_with_generation_inputs(KERNEL.global_labels["init"], vec![], inputs, None);
+
+    // Stop right before the kernel checks the final values against the inputs.
+    let final_checks = KERNEL.global_labels["perform_final_checks"];
+    interpreter.halt_offsets.push(final_checks);
+    interpreter.run()?;
+    if interpreter.generation_state.registers.program_counter != final_checks {
+        return Err(anyhow!("the kernel halted before the end of the batch"));
+    }
+
+    // stack: cum_gas, txn_counter, num_nibbles, txn_nb
+    let state = &interpreter.generation_state;
+    let gas_used_after = stack_peek(state, 0).map_err(|e| anyhow!("{e:?}"))?;
+    let txn_number_after = stack_peek(state, 3).map_err(|e| anyhow!("{e:?}"))?;
+    let txn_ptr = trie_ptr(&state.memory, GlobalMetadata::TransactionTrieRoot)?;
+    let txn_trie: HashedPartialTrie = get_txn_trie(&state.memory, txn_ptr)
+        .map_err(|e| anyhow!("couldn't read the final transactions trie: {e:?}"))?;
+    let receipt_ptr = trie_ptr(&state.memory, GlobalMetadata::ReceiptTrieRoot)?;
+    let receipt_trie: HashedPartialTrie = get_receipt_trie(&state.memory, receipt_ptr)
+        .map_err(|e| anyhow!("couldn't read the final receipts trie: {e:?}"))?;
+
+    // Let the final checks of the transactions and receipts pass, and stop
+    // once the final state trie is built.
+    interpreter.set_global_metadata_multi_fields(&[
+        (GlobalMetadata::BlockGasUsedAfter, gas_used_after),
+        (GlobalMetadata::TxnNumberAfter, txn_number_after),
+        (
+            GlobalMetadata::TransactionTrieRootDigestAfter,
+            h2u(txn_trie.hash()),
+        ),
+        (
+            GlobalMetadata::ReceiptTrieRootDigestAfter,
+            h2u(receipt_trie.hash()),
+        ),
+    ]);
+    interpreter
+        .halt_offsets
+        .retain(|&offset| offset != final_checks);
+    let final_state_trie = KERNEL.global_labels["hash_final_state_trie"];
+    interpreter.halt_offsets.push(final_state_trie);
+    interpreter.run()?;
+    if interpreter.generation_state.registers.program_counter != final_state_trie {
+        return Err(anyhow!(
+            "the kernel halted before building the final state trie"
+        ));
+    }
+
+    let memory = &interpreter.generation_state.memory;
+    let state_ptr = trie_ptr(memory, GlobalMetadata::StateTrieRoot)?;
+    let state_trie: HashedPartialTrie = get_state_trie(memory, state_ptr)
+        .map_err(|e| anyhow!("couldn't read the final state trie: {e:?}"))?;
+
+    let rejected: HashMap<U256, InvalidTxnReason> = interpreter
+        .generation_state
+        .invalid_txns
+        .iter()
+        .map(|txn| (txn.txn_number, txn.reason))
+        .collect();
+    let mut cum_gas_used = inputs.gas_used_before;
+    let mut txns = vec![];
+    for (i, txn_number) in (inputs.txn_number_before.as_usize()..txn_number_after.as_usize())
+        .map(U256::from)
+        .enumerate()
+    {
+        let key = Nibbles::from_bytes_be(&rlp::encode(&txn_number))
+            .map_err(|_| anyhow!("invalid key of transaction {txn_number}"))?;
+        let (_, _, receipt) = receipt_trie
+            .get(key)
+            .ok_or_else(|| anyhow!("no receipt for transaction {txn_number}"))
+            .and_then(|rlp| {
+                decode_receipt(rlp).map_err(|_| anyhow!("invalid receipt of {txn_number}"))
+            })?;
+
+        let status = match rejected.get(&txn_number) {
+            Some(&reason) => TxnStatus::Rejected(reason),
+            None if receipt.status => TxnStatus::Success,
+            None => TxnStatus::Reverted,
+        };
+        txns.push(TxnSimulation {
+            txn_number,
+            txn_hash: inputs.signed_txns.get(i).map(keccak).unwrap_or_default(),
+            status,
+            gas_used: receipt.cum_gas_used - cum_gas_used,
+            logs: receipt.logs,
+        });
+        cum_gas_used = receipt.cum_gas_used;
+    }
+
+    Ok(SimulationResult {
+        gas_used: gas_used_after - inputs.gas_used_before,
+        gas_used_after,
+        trie_roots_after: TrieRoots {
+            state_root: state_trie.hash(),
+            transactions_root: txn_trie.hash(),
+            receipts_root: receipt_trie.hash(),
+        },
+        txns,
+        state_diff: state_diff(&inputs.tries.state_trie, &state_trie),
+    })
+}
+
+/// Reads the pointer to the root of a trie from the global metadata.
+fn trie_ptr(memory: &MemoryState, field: GlobalMetadata) -> Result<usize> {
+    u256_to_usize(memory.read_global_metadata(field))
+        .map_err(|_| anyhow!("{field:?} pointer is too large to fit in a usize."))
+}
+
+/// Compares the accounts of the state tries before and after the execution.
+///
+/// The final state trie is built from the accounts the kernel loaded from the
+/// initial one, so an account of the initial trie missing from the final one
+/// has been deleted.
+fn state_diff(
+    before: &HashedPartialTrie,
+    after: &HashedPartialTrie,
+) -> BTreeMap<H256, AccountDiff> {
+    let accounts = |trie: &HashedPartialTrie| -> HashMap<H256, AccountRlp> {
+        trie.items()
+            .filter_map(|(key, value)| match value {
+                ValOrHash::Val(rlp) => Some((H256::from(key), rlp::decode(&rlp).ok()?)),
+                ValOrHash::Hash(_) => None,
+            })
+            .collect()
+    };
+    let before = accounts(before);
+    let mut after = accounts(after);
+
+    let mut diff: BTreeMap<_, _> = before
+        .into_iter()
+        .filter_map(|(key, before)| match after.remove(&key) {
+            None => Some((key, AccountDiff::Deleted(before))),
+            Some(after) if after != before => Some((key, AccountDiff::Modified { before, after })),
+            Some(_) => None,
+        })
+        .collect();
+    diff.extend(
+        after
+            .into_iter()
+            .map(|(key, after)| (key, AccountDiff::Created(after))),
+    );
+    diff
+}
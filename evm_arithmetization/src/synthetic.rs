@@ -0,0 +1,197 @@
+//! Synthetic blocks, to warm up, benchmark and compare provers without
+//! touching real chain data.
+//!
+//! A synthetic block is made of a single transaction running a loop until it
+//! runs out of gas. Its gas limit is large enough for the execution to outlast
+//! any realistic segment, so that the first segment of a synthetic block fills
+//! its CPU table up to the requested height. The body of the loop selects the
+//! other tables growing along with the CPU one.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Result};
+use ethereum_types::{BigEndianHash, H256, U256};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie};
+use plonky2::hash::hash_types::RichField;
+use serde::{Deserialize, Serialize};
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::generation::mpt::AccountRlp;
+use crate::generation::{GenerationInputs, TrieInputs, TrimmedGenerationInputs};
+use crate::proof::{BlockHashes, BlockMetadata};
+use crate::prover::{GenerationSegmentData, SegmentDataIterator};
+use crate::testing_utils::preinitialized_state_and_storage_tries;
+
+/// The address of the contract running the loop of synthetic blocks.
+const SYNTHETIC_CONTRACT: [u8; 20] = hex!("5757575757575757575757575757575757575757");
+
+/// The sender of the transaction of synthetic blocks, of private key 1.
+const SYNTHETIC_SENDER: [u8; 20] = hex!("7e5f4552091a69125d5dfcb7b8c2659029395bdf");
+
+/// The legacy transaction of synthetic blocks, calling [`SYNTHETIC_CONTRACT`]
+/// on chain 1 with a gas limit of 10^9 and a gas price of 10, and signed by
+/// [`SYNTHETIC_SENDER`].
+const SYNTHETIC_TXN: [u8; 99] = hex!("f861800a843b9aca00945757575757575757575757575757575757575757808025a01ff2d2305f1b5905429eb5a47779d0e9d613f7ea3533cff32d860a7ad4320f47a06b0fb57c46f12be6e124f80c774b11f44eb0d974155bfe4e30d183e9d5aa069a");
+
+/// The body of the loop of a synthetic block, which selects the tables
+/// growing along with the CPU one.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+pub enum SyntheticLoop {
+    /// Only jumps back to the start of the loop.
+    #[default]
+    Jump,
+    /// Modular multiplications, growing the arithmetic table.
+    Arithmetic,
+    /// Hashes of 64 bytes of memory, growing the Keccak tables.
+    Keccak,
+    /// Increments of a word in memory, growing the memory table.
+    Memory,
+}
+
+impl SyntheticLoop {
+    pub const ALL: [Self; 4] = [Self::Jump, Self::Arithmetic, Self::Keccak, Self::Memory];
+
+    /// Returns the code of the contract running this loop, whose only
+    /// `JUMPDEST` is at offset 0.
+    fn code(self) -> Vec<u8> {
+        let body: &[u8] = match self {
+            Self::Jump => &[],
+            // POP(MULMOD(3, 5, 7))
+            Self::Arithmetic => &hex!("6007600560030950"),
+            // POP(KECCAK256(0, 64))
+            Self::Keccak => &hex!("604060002050"),
+            // MSTORE(0, ADD(MLOAD(0), 1))
+            Self::Memory => &hex!("600051600101600052"),
+        };
+        // JUMPDEST, body, JUMP(0)
+        let mut code = vec![0x5b];
+        code.extend_from_slice(body);
+        code.extend_from_slice(&hex!("600056"));
+        code
+    }
+}
+
+impl fmt::Display for SyntheticLoop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Jump => "jump",
+            Self::Arithmetic => "arithmetic",
+            Self::Keccak => "keccak",
+            Self::Memory => "memory",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for SyntheticLoop {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|body| body.to_string() == s)
+            .ok_or_else(|| anyhow!("unknown synthetic loop {s:?}"))
+    }
+}
+
+/// Builds the `GenerationInputs` of the synthetic block running `body`.
+///
+/// Only the first segments of the block are meant to be generated: as the
+/// transaction does not end in practice, the final values of the inputs are
+/// left to their defaults.
+pub fn synthetic_block(body: SyntheticLoop) -> Result<GenerationInputs> {
+    let (mut state_trie, mut storage_tries) = preinitialized_state_and_storage_tries()?;
+    let checkpoint_state_trie_root = state_trie.hash();
+
+    let code = body.code();
+    let code_hash = keccak(&code);
+    let sender = AccountRlp {
+        // The upfront cost of the transaction is 10^10 wei.
+        balance: U256::exp10(18),
+        ..Default::default()
+    };
+    let contract = AccountRlp {
+        nonce: 1.into(),
+        code_hash,
+        ..Default::default()
+    };
+    for (address, account) in [(SYNTHETIC_SENDER, sender), (SYNTHETIC_CONTRACT, contract)] {
+        let hashed_address = keccak(address);
+        state_trie.insert(
+            Nibbles::from_h256_be(hashed_address),
+            rlp::encode(&account).to_vec(),
+        )?;
+        storage_tries.push((hashed_address, HashedPartialTrie::from(Node::Empty)));
+    }
+
+    Ok(GenerationInputs {
+        signed_txns: vec![SYNTHETIC_TXN.to_vec()],
+        tries: TrieInputs {
+            state_trie,
+            storage_tries,
+            ..Default::default()
+        },
+        checkpoint_state_trie_root,
+        contract_code: HashMap::from([(keccak([]), vec![]), (code_hash, code)]),
+        // Spares the simulation of the whole transaction.
+        jumpdest_tables: HashMap::from([(code_hash, BTreeSet::from([0]))]),
+        block_metadata: BlockMetadata {
+            block_timestamp: 0x03e8.into(),
+            block_number: 1.into(),
+            block_difficulty: 0x020000.into(),
+            block_random: H256::from_uint(&0x020000.into()),
+            block_gaslimit: 0xff112233u32.into(),
+            block_chain_id: 1.into(),
+            block_base_fee: 0xa.into(),
+            ..Default::default()
+        },
+        block_hashes: BlockHashes {
+            prev_hashes: vec![H256::default(); 256],
+            cur_hash: H256::default(),
+        },
+        ..Default::default()
+    })
+}
+
+/// Generates the first segment of the synthetic block running `body`, whose
+/// CPU table has `2^max_cpu_len_log` rows.
+pub fn synthetic_segment<F: RichField>(
+    body: SyntheticLoop,
+    max_cpu_len_log: usize,
+) -> Result<(TrimmedGenerationInputs, GenerationSegmentData)> {
+    let inputs = synthetic_block(body)?;
+    let (inputs, segment) = SegmentDataIterator::<F>::new(&inputs, Some(max_cpu_len_log))
+        .next()
+        .ok_or_else(|| anyhow!("the synthetic block has no segment"))??;
+
+    // The segment is cut at the cycle limit, unless the block ended before.
+    ensure!(
+        segment.registers_after.program_counter != KERNEL.global_labels["halt"],
+        "the synthetic block is too short to fill 2^{max_cpu_len_log} cycles"
+    );
+    Ok((inputs, segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+    use super::*;
+
+    #[test]
+    fn synthetic_segments_fill_the_cpu_table() -> Result<()> {
+        for body in SyntheticLoop::ALL {
+            assert_eq!(body.to_string().parse::<SyntheticLoop>()?, body);
+            synthetic_segment::<F>(body, 16)?;
+        }
+
+        Ok(())
+    }
+}
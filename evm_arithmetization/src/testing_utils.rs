@@ -9,12 +9,22 @@ use mpt_trie::{
     nibbles::Nibbles,
     partial_trie::{HashedPartialTrie, Node, PartialTrie},
 };
+use zk_evm_common::AccountRlp;
 
 pub use crate::cpu::kernel::cancun_constants::*;
 pub use crate::cpu::kernel::constants::global_exit_root::{
     GLOBAL_EXIT_ROOT_ACCOUNT, GLOBAL_EXIT_ROOT_ADDRESS_HASHED, GLOBAL_EXIT_ROOT_STORAGE_POS,
 };
-use crate::{generation::mpt::AccountRlp, util::h2u};
+use crate::util::h2u;
+
+#[cfg(feature = "testing")]
+mod block_builder;
+#[cfg(feature = "testing")]
+mod bytecode_harness;
+#[cfg(feature = "testing")]
+pub use block_builder::{TestBlockBuilder, TEST_BENEFICIARY};
+#[cfg(feature = "testing")]
+pub use bytecode_harness::{BytecodeHarness, HarnessAccount, HARNESS_CONTRACT};
 
 pub const EMPTY_NODE_HASH: H256 = H256(hex!(
     "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
@@ -0,0 +1,215 @@
+//! A builder for the `GenerationInputs` of test blocks.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::{Address, BigEndianHash, H256, U256};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, Node, PartialTrie};
+use zk_evm_common::AccountRlp;
+
+use super::{
+    beacon_roots_account_nibbles, beacon_roots_contract_from_storage, ger_account_nibbles,
+    preinitialized_state_and_storage_tries, update_beacon_roots_account_storage,
+    GLOBAL_EXIT_ROOT_ACCOUNT,
+};
+use crate::generation::{GenerationInputs, TrieInputs};
+use crate::proof::{BlockHashes, BlockMetadata, Hardfork, TrieRoots};
+
+/// The default beneficiary of test blocks.
+pub const TEST_BENEFICIARY: [u8; 20] = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
+
+/// Builds the `GenerationInputs` of a test block without transactions, on top
+/// of the preinitialized state containing the beacon roots and global exit
+/// roots contracts.
+///
+/// The block timestamp, prevrandao and parent beacon block root, which
+/// determine the beacon roots contract update, can all be injected so that
+/// tests can construct varied but deterministic scenarios.
+#[derive(Clone, Debug)]
+pub struct TestBlockBuilder {
+    block_metadata: BlockMetadata,
+    block_hashes: BlockHashes,
+    /// Whether the beacon roots contract update of this block has already been
+    /// applied to the initial state, e.g. when proving a second payload of the
+    /// same block.
+    beacon_roots_update_applied: bool,
+    /// The ommers `(beneficiary, number)` included by the block.
+    ommers: Vec<(Address, U256)>,
+}
+
+impl Default for TestBlockBuilder {
+    fn default() -> Self {
+        Self {
+            block_metadata: BlockMetadata {
+                block_beneficiary: Address::from(TEST_BENEFICIARY),
+                block_timestamp: 0x03e8.into(),
+                block_number: 1.into(),
+                block_difficulty: 0x020000.into(),
+                block_random: H256::from_uint(&0x020000.into()),
+                block_gaslimit: 0xff112233u32.into(),
+                block_chain_id: 1.into(),
+                block_base_fee: 0xa.into(),
+                ..Default::default()
+            },
+            block_hashes: BlockHashes::default(),
+            beacon_roots_update_applied: false,
+            ommers: Vec::new(),
+        }
+    }
+}
+
+impl TestBlockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the block timestamp.
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.block_metadata.block_timestamp = timestamp.into();
+        self
+    }
+
+    /// Sets the block prevrandao.
+    pub fn prev_randao(mut self, prev_randao: H256) -> Self {
+        self.block_metadata.block_random = prev_randao;
+        self
+    }
+
+    /// Sets the parent beacon block root.
+    pub fn parent_beacon_block_root(mut self, root: H256) -> Self {
+        self.block_metadata.parent_beacon_block_root = root;
+        self
+    }
+
+    /// Sets the block number.
+    pub fn block_number(mut self, block_number: u64) -> Self {
+        self.block_metadata.block_number = block_number.into();
+        self
+    }
+
+    /// Sets the hashes of the block and of its ancestors, the last of which
+    /// is its parent.
+    pub fn block_hashes(mut self, block_hashes: BlockHashes) -> Self {
+        self.block_hashes = block_hashes;
+        self
+    }
+
+    /// Sets the block chain ID.
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.block_metadata.block_chain_id = chain_id.into();
+        self
+    }
+
+    /// Sets the hardfork whose rules the block follows. The beacon roots
+    /// contract is only updated from Cancun on.
+    pub const fn hardfork(mut self, hardfork: Hardfork) -> Self {
+        self.block_metadata.block_hardfork = hardfork;
+        self
+    }
+
+    /// Sets whether the beacon roots contract update of this block has
+    /// already been applied to the initial state.
+    pub const fn beacon_roots_update_applied(mut self, applied: bool) -> Self {
+        self.beacon_roots_update_applied = applied;
+        self
+    }
+
+    /// Sets the ommers `(beneficiary, number)` included by the block, whose
+    /// miners are rewarded along with the miner of the block before Paris.
+    pub fn ommers(mut self, ommers: Vec<(Address, U256)>) -> Self {
+        self.ommers = ommers;
+        self
+    }
+
+    /// Returns the metadata of the block being built.
+    pub const fn block_metadata(&self) -> &BlockMetadata {
+        &self.block_metadata
+    }
+
+    /// Builds the `GenerationInputs` of the block.
+    pub fn build(self) -> anyhow::Result<GenerationInputs> {
+        let Self {
+            block_metadata,
+            block_hashes,
+            beacon_roots_update_applied,
+            ommers,
+        } = self;
+
+        let (mut state_trie_before, mut storage_tries) = preinitialized_state_and_storage_tries()?;
+        let checkpoint_state_trie_root = state_trie_before.hash();
+        let mut beacon_roots_account_storage = storage_tries[0].1.clone();
+
+        if block_metadata.block_hardfork >= Hardfork::Cancun {
+            update_beacon_roots_account_storage(
+                &mut beacon_roots_account_storage,
+                block_metadata.block_timestamp,
+                block_metadata.parent_beacon_block_root,
+            )?;
+        }
+        let updated_beacon_roots_account =
+            beacon_roots_contract_from_storage(&beacon_roots_account_storage);
+
+        if beacon_roots_update_applied {
+            state_trie_before.insert(
+                beacon_roots_account_nibbles(),
+                rlp::encode(&updated_beacon_roots_account).to_vec(),
+            )?;
+            storage_tries[0].1 = beacon_roots_account_storage;
+        }
+
+        let tries_before = TrieInputs {
+            state_trie: state_trie_before,
+            storage_tries,
+            ..Default::default()
+        };
+
+        let mut state_trie_after = HashedPartialTrie::from(Node::Empty);
+        state_trie_after.insert(
+            beacon_roots_account_nibbles(),
+            rlp::encode(&updated_beacon_roots_account).to_vec(),
+        )?;
+        state_trie_after.insert(
+            ger_account_nibbles(),
+            rlp::encode(&GLOBAL_EXIT_ROOT_ACCOUNT).to_vec(),
+        )?;
+
+        // The rewarded miners don't exist before the block.
+        let block_rewards = block_metadata.block_hardfork.block_rewards(
+            block_metadata.block_beneficiary,
+            block_metadata.block_number,
+            &ommers,
+        );
+        let mut balances = BTreeMap::<Address, U256>::new();
+        for &(address, amount) in &block_rewards {
+            *balances.entry(address).or_default() += amount;
+        }
+        for (address, balance) in balances {
+            let account = AccountRlp {
+                balance,
+                ..Default::default()
+            };
+            state_trie_after.insert(
+                Nibbles::from_h256_be(keccak(address)),
+                rlp::encode(&account).to_vec(),
+            )?;
+        }
+
+        let trie_roots_after = TrieRoots {
+            state_root: state_trie_after.hash(),
+            transactions_root: tries_before.transactions_trie.hash(),
+            receipts_root: tries_before.receipts_trie.hash(),
+        };
+
+        Ok(GenerationInputs {
+            tries: tries_before,
+            trie_roots_after,
+            checkpoint_state_trie_root,
+            block_metadata,
+            block_hashes,
+            block_rewards,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,460 @@
+//! A harness proving the execution of a bytecode snippet, without assembling
+//! the witness of a full block.
+
+use std::collections::{BTreeMap, HashMap};
+
+use ethereum_types::{Address, H256, U256};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::PartialTrie;
+use num::Zero;
+use num_bigint::BigUint;
+use plonky2::field::extension::Extendable;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::util::timing::TimingTree;
+
+use super::{
+    create_account_storage, eth_to_wei, preinitialized_state_and_storage_tries, TestBlockBuilder,
+};
+use crate::generation::mpt::transaction_testing::{
+    AddressOption, BlobTransactionRlp, FeeMarketTransactionRlp, LegacyTransactionRlp,
+};
+use crate::generation::mpt::AccountRlp;
+use crate::generation::{GenerationInputs, TrieInputs};
+use crate::proof::{AllProof, BlockHashes};
+use crate::prover::testing::prove_all_segments;
+use crate::simulation::simulate;
+use crate::util::{biguint_to_u256, u256_to_biguint};
+use crate::{AllStark, AllStarkConfig};
+
+/// The address the snippet of a [`BytecodeHarness`] is deployed at.
+pub const HARNESS_CONTRACT: [u8; 20] = hex!("c0dec0dec0dec0dec0dec0dec0dec0dec0dec0de");
+
+/// The private key of the account sending the transaction calling the snippet.
+const SENDER_KEY: U256 = U256([1, 0, 0, 0]);
+
+/// An account of the initial state of a [`BytecodeHarness`].
+#[derive(Clone, Debug, Default)]
+pub struct HarnessAccount {
+    pub nonce: U256,
+    pub balance: U256,
+    pub code: Vec<u8>,
+    /// The non-zero `(slot, value)` pairs of the account storage.
+    pub storage: Vec<(U256, U256)>,
+}
+
+/// Builds and proves a block made of a single transaction calling a bytecode
+/// snippet, deployed at [`HARNESS_CONTRACT`] on top of a sketch of the
+/// initial state.
+///
+/// The transaction is a legacy transaction from
+/// [`sender`](BytecodeHarness::sender), paying the base fee as gas price
+/// unless [`gas_price`](BytecodeHarness::gas_price) is set, or a typed one
+/// when [`fee_caps`](BytecodeHarness::fee_caps) or
+/// [`blobs`](BytecodeHarness::blobs) are. The
+/// gas used and trie roots after the block are obtained by simulating it, so
+/// that the proof attests to whatever the snippet does, including reverting.
+#[derive(Clone, Debug)]
+pub struct BytecodeHarness {
+    contract: HarnessAccount,
+    calldata: Vec<u8>,
+    value: U256,
+    gas_limit: U256,
+    gas_price: Option<U256>,
+    fee_caps: Option<(U256, U256)>,
+    blobs: Option<(usize, U256)>,
+    chain_id: Option<U256>,
+    accounts: Vec<(Address, HarnessAccount)>,
+    block: TestBlockBuilder,
+}
+
+impl BytecodeHarness {
+    pub fn new(code: Vec<u8>) -> Self {
+        Self {
+            contract: HarnessAccount {
+                nonce: 1.into(),
+                code,
+                ..Default::default()
+            },
+            calldata: vec![],
+            value: U256::zero(),
+            gas_limit: 10_000_000.into(),
+            gas_price: None,
+            fee_caps: None,
+            blobs: None,
+            chain_id: None,
+            accounts: vec![],
+            block: TestBlockBuilder::new(),
+        }
+    }
+
+    /// Sets the calldata of the call to the snippet.
+    pub fn calldata(mut self, calldata: Vec<u8>) -> Self {
+        self.calldata = calldata;
+        self
+    }
+
+    /// Sets the value transferred to the snippet.
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Sets the gas limit of the transaction.
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit.into();
+        self
+    }
+
+    /// Sets the gas price of the transaction, the base fee by default.
+    pub fn gas_price(mut self, gas_price: U256) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
+    /// Sends an [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559)
+    /// transaction with these fee caps, instead of a legacy one.
+    pub fn fee_caps(mut self, max_priority_fee_per_gas: U256, max_fee_per_gas: U256) -> Self {
+        self.fee_caps = Some((max_priority_fee_per_gas, max_fee_per_gas));
+        self
+    }
+
+    /// Sends an [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844) blob
+    /// transaction carrying `blobs` blobs, instead of a legacy one. Its fee
+    /// caps are the ones set with [`fee_caps`](Self::fee_caps), or the gas
+    /// price.
+    pub fn blobs(mut self, blobs: usize, max_fee_per_blob_gas: U256) -> Self {
+        self.blobs = Some((blobs, max_fee_per_blob_gas));
+        self
+    }
+
+    /// Sets the chain ID the transaction is signed for, the one of the block
+    /// by default.
+    pub fn chain_id(mut self, chain_id: U256) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Sets the balance of the snippet account.
+    pub fn balance(mut self, balance: U256) -> Self {
+        self.contract.balance = balance;
+        self
+    }
+
+    /// Sets a storage slot of the snippet account.
+    pub fn storage(mut self, slot: U256, value: U256) -> Self {
+        self.contract.storage.push((slot, value));
+        self
+    }
+
+    /// Adds an account to the initial state, e.g. a contract called by the
+    /// snippet. It replaces the sender or the snippet account if it has the
+    /// same address.
+    pub fn account(mut self, address: Address, account: HarnessAccount) -> Self {
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Sets the block the transaction is included in.
+    pub fn block(mut self, block: TestBlockBuilder) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Returns the address sending the transaction, i.e. the `ORIGIN` and
+    /// `CALLER` of the snippet.
+    pub fn sender() -> Address {
+        let (x, y) = secp256k1_mul(&u256_to_biguint(SENDER_KEY), &secp256k1_generator())
+            .expect("the sender key is a non-zero scalar");
+        let mut public_key = [0; 64];
+        biguint_to_u256(x).to_big_endian(&mut public_key[..32]);
+        biguint_to_u256(y).to_big_endian(&mut public_key[32..]);
+        Address::from_slice(&keccak(public_key).0[12..])
+    }
+
+    /// Builds the `GenerationInputs` of the block.
+    pub fn build(&self) -> anyhow::Result<GenerationInputs> {
+        let mut block_metadata = self.block.block_metadata().clone();
+
+        let sender = HarnessAccount {
+            balance: eth_to_wei(1_000_000.into()),
+            ..Default::default()
+        };
+        let accounts: BTreeMap<_, _> = [
+            (Self::sender(), &sender),
+            (Address::from(HARNESS_CONTRACT), &self.contract),
+        ]
+        .into_iter()
+        .chain(
+            self.accounts
+                .iter()
+                .map(|(address, account)| (*address, account)),
+        )
+        .collect();
+
+        let (mut state_trie, mut storage_tries) = preinitialized_state_and_storage_tries()?;
+        let mut contract_code = HashMap::from([(keccak([]), vec![])]);
+        for (address, account) in accounts {
+            let storage_trie = create_account_storage(&account.storage)?;
+            let code_hash = keccak(&account.code);
+            let account_rlp = AccountRlp {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root: storage_trie.hash(),
+                code_hash,
+            };
+            let hashed_address = keccak(address.0);
+            state_trie.insert(
+                Nibbles::from_h256_be(hashed_address),
+                rlp::encode(&account_rlp).to_vec(),
+            )?;
+            storage_tries.push((hashed_address, storage_trie));
+            contract_code.insert(code_hash, account.code.clone());
+        }
+
+        let signed_txn = self.signed_txn(
+            self.chain_id.unwrap_or(block_metadata.block_chain_id),
+            self.gas_price.unwrap_or(block_metadata.block_base_fee),
+        );
+        let checkpoint_state_trie_root = state_trie.hash();
+        let mut inputs = GenerationInputs {
+            signed_txns: vec![signed_txn],
+            tries: TrieInputs {
+                state_trie,
+                storage_tries,
+                ..Default::default()
+            },
+            checkpoint_state_trie_root,
+            contract_code,
+            block_metadata: block_metadata.clone(),
+            block_hashes: BlockHashes {
+                prev_hashes: vec![H256::default(); 256],
+                cur_hash: H256::default(),
+            },
+            ..Default::default()
+        };
+
+        let simulation = simulate::<GoldilocksField>(&inputs)?;
+        block_metadata.block_gas_used = simulation.gas_used_after;
+        inputs.block_metadata = block_metadata;
+        inputs.gas_used_after = simulation.gas_used_after;
+        inputs.trie_roots_after = simulation.trie_roots_after;
+
+        Ok(inputs)
+    }
+
+    /// Builds the block, and proves all its segments.
+    pub fn prove<F, C, const D: usize>(
+        &self,
+        all_stark: &AllStark<F, D>,
+        config: &AllStarkConfig,
+        max_cpu_len_log: usize,
+        timing: &mut TimingTree,
+    ) -> anyhow::Result<Vec<AllProof<F, C, D>>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        prove_all_segments::<F, C, D>(
+            all_stark,
+            config,
+            self.build()?,
+            max_cpu_len_log,
+            timing,
+            None,
+        )
+    }
+
+    /// Returns the transaction calling the snippet, signed with
+    /// [`SENDER_KEY`].
+    fn signed_txn(&self, chain_id: U256, gas_price: U256) -> Vec<u8> {
+        let (max_priority_fee_per_gas, max_fee_per_gas) =
+            self.fee_caps.unwrap_or((gas_price, gas_price));
+        match (self.fee_caps, self.blobs) {
+            (None, None) => self.signed_legacy_txn(chain_id, gas_price),
+            (Some(_), None) => {
+                let mut txn = FeeMarketTransactionRlp {
+                    chain_id: chain_id.as_u64(),
+                    nonce: U256::zero(),
+                    max_priority_fee_per_gas,
+                    max_fee_per_gas,
+                    gas: self.gas_limit,
+                    to: AddressOption(Some(Address::from(HARNESS_CONTRACT))),
+                    value: self.value,
+                    data: self.calldata.clone().into(),
+                    access_list: vec![],
+                    y_parity: U256::zero(),
+                    r: U256::zero(),
+                    s: U256::zero(),
+                };
+                (txn.y_parity, txn.r, txn.s) = sign_typed_txn(0x02, &rlp::encode(&txn));
+                [&[0x02][..], &rlp::encode(&txn)[..]].concat()
+            }
+            (_, Some((blobs, max_fee_per_blob_gas))) => {
+                let mut txn = BlobTransactionRlp {
+                    chain_id: chain_id.as_u64(),
+                    nonce: U256::zero(),
+                    max_priority_fee_per_gas,
+                    max_fee_per_gas,
+                    gas: self.gas_limit,
+                    to: Address::from(HARNESS_CONTRACT),
+                    value: self.value,
+                    data: self.calldata.clone().into(),
+                    access_list: vec![],
+                    max_fee_per_blob_gas,
+                    // Versioned hashes start with the version of KZG commitments.
+                    blob_versioned_hashes: (0..blobs)
+                        .map(|i| {
+                            let mut hash = H256::from_low_u64_be(i as u64);
+                            hash.0[0] = 0x01;
+                            hash
+                        })
+                        .collect(),
+                    y_parity: U256::zero(),
+                    r: U256::zero(),
+                    s: U256::zero(),
+                };
+                (txn.y_parity, txn.r, txn.s) = sign_typed_txn(0x03, &rlp::encode(&txn));
+                [&[0x03][..], &rlp::encode(&txn)[..]].concat()
+            }
+        }
+    }
+
+    /// Returns the EIP-155 transaction calling the snippet, signed with
+    /// [`SENDER_KEY`].
+    fn signed_legacy_txn(&self, chain_id: U256, gas_price: U256) -> Vec<u8> {
+        let mut txn = LegacyTransactionRlp {
+            nonce: U256::zero(),
+            gas_price,
+            gas: self.gas_limit,
+            to: AddressOption(Some(Address::from(HARNESS_CONTRACT))),
+            value: self.value,
+            data: self.calldata.clone().into(),
+            v: chain_id,
+            r: U256::zero(),
+            s: U256::zero(),
+        };
+
+        let (r, s, recovery_id) = sign(keccak(rlp::encode(&txn)), SENDER_KEY);
+        txn.v = chain_id * 2 + 35 + recovery_id;
+        txn.r = r;
+        txn.s = s;
+        rlp::encode(&txn).to_vec()
+    }
+}
+
+/// Signs the RLP list `encoded` of a typed transaction, whose signature
+/// fields come last, with [`SENDER_KEY`], returning `(y_parity, r, s)`.
+///
+/// The signed payload is the type byte, followed by the RLP list of the
+/// fields but the signature.
+fn sign_typed_txn(txn_type: u8, encoded: &[u8]) -> (U256, U256, U256) {
+    let rlp = rlp::Rlp::new(encoded);
+    let fields = rlp.item_count().expect("a transaction is an RLP list") - 3;
+    let mut payload = rlp::RlpStream::new_list(fields);
+    for i in 0..fields {
+        payload.append_raw(rlp.at(i).expect("the field exists").as_raw(), 1);
+    }
+
+    let (r, s, recovery_id) = sign(
+        keccak([&[txn_type][..], &payload.out()[..]].concat()),
+        SENDER_KEY,
+    );
+    (recovery_id, r, s)
+}
+
+/// A secp256k1 point in affine coordinates, `None` being the point at
+/// infinity.
+type Point = Option<(BigUint, BigUint)>;
+
+fn secp256k1_base() -> BigUint {
+    BigUint::from_bytes_be(&hex!(
+        "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"
+    ))
+}
+
+fn secp256k1_order() -> BigUint {
+    BigUint::from_bytes_be(&hex!(
+        "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"
+    ))
+}
+
+fn secp256k1_generator() -> Point {
+    Some((
+        BigUint::from_bytes_be(&hex!(
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        )),
+        BigUint::from_bytes_be(&hex!(
+            "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"
+        )),
+    ))
+}
+
+fn inverse(x: &BigUint, p: &BigUint) -> BigUint {
+    x.modpow(&(p - 2u32), p)
+}
+
+fn secp256k1_add(a: &Point, b: &Point) -> Point {
+    let (Some((x1, y1)), Some((x2, y2))) = (a, b) else {
+        return a.clone().or_else(|| b.clone());
+    };
+    let p = secp256k1_base();
+
+    let slope = if x1 == x2 {
+        if ((y1 + y2) % &p).is_zero() {
+            return None;
+        }
+        // Doubling: the slope is the one of the tangent, `3 x^2 / 2 y`.
+        x1 * x1 * 3u32 % &p * inverse(&((y1 + y1) % &p), &p) % &p
+    } else {
+        (y2 + &p - y1) % &p * inverse(&((x2 + &p - x1) % &p), &p) % &p
+    };
+    let x3 = (&slope * &slope + &p + &p - x1 - x2) % &p;
+    let y3 = (slope * ((x1 + &p - &x3) % &p) % &p + &p - y1) % &p;
+    Some((x3, y3))
+}
+
+fn secp256k1_mul(scalar: &BigUint, point: &Point) -> Point {
+    (0..scalar.bits()).rev().fold(None, |acc, i| {
+        let acc = secp256k1_add(&acc, &acc);
+        match scalar.bit(i) {
+            true => secp256k1_add(&acc, point),
+            false => acc,
+        }
+    })
+}
+
+/// Signs `hash` with `key`, returning the low-s signature `(r, s)` along with
+/// its recovery id. The nonce is derived deterministically from the key and
+/// the hash.
+fn sign(hash: H256, key: U256) -> (U256, U256, U256) {
+    let n = secp256k1_order();
+    let z = BigUint::from_bytes_be(hash.as_bytes());
+    let d = u256_to_biguint(key);
+    let mut key_bytes = [0; 32];
+    key.to_big_endian(&mut key_bytes);
+
+    for counter in 0u8.. {
+        let seed = keccak([&key_bytes[..], hash.as_bytes(), &[counter]].concat());
+        let k = BigUint::from_bytes_be(seed.as_bytes()) % &n;
+        let Some((x, y)) = secp256k1_mul(&k, &secp256k1_generator()) else {
+            continue;
+        };
+        let r = x % &n;
+        let mut s = inverse(&k, &n) * (&z + &r * &d) % &n;
+        if r.is_zero() || s.is_zero() {
+            continue;
+        }
+
+        let mut recovery_id = u64::from(y.bit(0));
+        if s > &n >> 1 {
+            s = &n - s;
+            recovery_id ^= 1;
+        }
+        return (biguint_to_u256(r), biguint_to_u256(s), recovery_id.into());
+    }
+    unreachable!("a valid nonce is found with overwhelming probability")
+}
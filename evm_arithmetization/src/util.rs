@@ -6,6 +6,7 @@ use plonky2::field::packed::PackedField;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generation")]
 use sha2::{Digest, Sha256};
 
 use crate::witness::errors::ProgramError;
@@ -60,18 +61,21 @@ pub(crate) fn u256_to_u64<F: Field>(u256: U256) -> Result<(F, F), ProgramError>
 
 /// Safe alternative to `U256::as_usize()`, which errors in case of overflow
 /// instead of panicking.
+#[cfg(feature = "generation")]
 pub(crate) fn u256_to_usize(u256: U256) -> Result<usize, ProgramError> {
     u256.try_into().map_err(|_| ProgramError::IntegerTooLarge)
 }
 
 /// Converts a `U256` to a `u8`, erroring in case of overflow instead of
 /// panicking.
+#[cfg(feature = "generation")]
 pub(crate) fn u256_to_u8(u256: U256) -> Result<u8, ProgramError> {
     u256.try_into().map_err(|_| ProgramError::IntegerTooLarge)
 }
 
 /// Converts a `U256` to a `bool`, erroring in case of overflow instead of
 /// panicking.
+#[cfg(feature = "generation")]
 pub(crate) fn u256_to_bool(u256: U256) -> Result<bool, ProgramError> {
     if u256 == U256::zero() {
         Ok(false)
@@ -84,6 +88,7 @@ pub(crate) fn u256_to_bool(u256: U256) -> Result<bool, ProgramError> {
 
 /// Converts a `U256` to a `H160`, erroring in case of overflow instead of
 /// panicking.
+#[cfg(feature = "generation")]
 pub(crate) fn u256_to_h160(u256: U256) -> Result<H160, ProgramError> {
     if u256.bits() / 8 > 20 {
         return Err(ProgramError::IntegerTooLarge);
@@ -181,6 +186,7 @@ pub(crate) fn biguint_to_u256(x: BigUint) -> U256 {
     U256::from_little_endian(&bytes)
 }
 
+#[cfg(feature = "generation")]
 pub(crate) fn mem_vec_to_biguint(x: &[U256]) -> BigUint {
     BigUint::from_slice(
         &x.iter()
@@ -197,6 +203,7 @@ pub(crate) fn mem_vec_to_biguint(x: &[U256]) -> BigUint {
     )
 }
 
+#[cfg(feature = "generation")]
 pub(crate) fn biguint_to_mem_vec(x: BigUint) -> Vec<U256> {
     let num_limbs = ((x.bits() + 127) / 128) as usize;
 
@@ -249,6 +256,7 @@ pub(crate) fn get_u256<F: RichField>(slice: &[F; 8]) -> U256 {
 }
 
 /// Standard Sha2 implementation.
+#[cfg(feature = "generation")]
 pub(crate) fn sha2(input: Vec<u8>) -> U256 {
     let mut hasher = Sha256::new();
     hasher.update(input);
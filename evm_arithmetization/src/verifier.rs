@@ -9,13 +9,12 @@ use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::plonk::config::{GenericConfig, GenericHashOut};
 use plonky2::util::timing::TimingTree;
 use plonky2::util::transpose;
-use starky::config::StarkConfig;
 use starky::cross_table_lookup::{get_ctl_vars_from_proofs, verify_cross_table_lookups};
 use starky::lookup::GrandProductChallenge;
 use starky::stark::Stark;
 use starky::verifier::verify_stark_proof_with_challenges;
 
-use crate::all_stark::{AllStark, Table, NUM_TABLES};
+use crate::all_stark::{AllStark, AllStarkConfig, Table, NUM_TABLES};
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
 use crate::memory::segments::Segment;
@@ -95,8 +94,9 @@ fn verify_initial_memory<
     const D: usize,
 >(
     public_values: &PublicValues,
-    config: &StarkConfig,
+    config: &AllStarkConfig,
 ) -> Result<()> {
+    let config = config.table(Table::MemBefore);
     for (hash1, hash2) in initial_memory_merkle_cap::<F, C, D>(
         config.fri_config.rate_bits,
         config.fri_config.cap_height,
@@ -119,7 +119,7 @@ fn verify_initial_memory<
 fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     all_stark: &AllStark<F, D>,
     all_proof: AllProof<F, C, D>,
-    config: &StarkConfig,
+    config: &AllStarkConfig,
     is_initial: bool,
 ) -> Result<()> {
     let AllProofChallenges {
@@ -141,6 +141,8 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
         memory_stark,
         mem_before_stark,
         mem_after_stark,
+        range_check_stark,
+        ec_stark,
         cross_table_lookups,
     } = all_stark;
 
@@ -154,62 +156,79 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
 
     let stark_proofs = &all_proof.multi_proof.stark_proofs;
 
+    // Only optional tables may be absent, in which case their proofs are not
+    // verified, and they don't contribute to the cross-table lookups.
+    ensure!(
+        Table::all()
+            .iter()
+            .all(|table| table.is_optional() || all_proof.table_in_use[**table]),
+        "A required table is marked as not in use."
+    );
+
     verify_stark_proof_with_challenges(
         arithmetic_stark,
         &stark_proofs[Table::Arithmetic as usize].proof,
         &stark_challenges[Table::Arithmetic as usize],
         Some(&ctl_vars_per_table[Table::Arithmetic as usize]),
         &[],
-        config,
+        config.table(Table::Arithmetic),
     )?;
 
-    verify_stark_proof_with_challenges(
-        byte_packing_stark,
-        &stark_proofs[Table::BytePacking as usize].proof,
-        &stark_challenges[Table::BytePacking as usize],
-        Some(&ctl_vars_per_table[Table::BytePacking as usize]),
-        &[],
-        config,
-    )?;
+    if all_proof.table_in_use[*Table::BytePacking] {
+        verify_stark_proof_with_challenges(
+            byte_packing_stark,
+            &stark_proofs[Table::BytePacking as usize].proof,
+            &stark_challenges[Table::BytePacking as usize],
+            Some(&ctl_vars_per_table[Table::BytePacking as usize]),
+            &[],
+            config.table(Table::BytePacking),
+        )?;
+    }
     verify_stark_proof_with_challenges(
         cpu_stark,
         &stark_proofs[Table::Cpu as usize].proof,
         &stark_challenges[Table::Cpu as usize],
         Some(&ctl_vars_per_table[Table::Cpu as usize]),
         &[],
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        keccak_stark,
-        &stark_proofs[Table::Keccak as usize].proof,
-        &stark_challenges[Table::Keccak as usize],
-        Some(&ctl_vars_per_table[Table::Keccak as usize]),
-        &[],
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        keccak_sponge_stark,
-        &stark_proofs[Table::KeccakSponge as usize].proof,
-        &stark_challenges[Table::KeccakSponge as usize],
-        Some(&ctl_vars_per_table[Table::KeccakSponge as usize]),
-        &[],
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        logic_stark,
-        &stark_proofs[Table::Logic as usize].proof,
-        &stark_challenges[Table::Logic as usize],
-        Some(&ctl_vars_per_table[Table::Logic as usize]),
-        &[],
-        config,
+        config.table(Table::Cpu),
     )?;
+    if all_proof.table_in_use[*Table::Keccak] {
+        verify_stark_proof_with_challenges(
+            keccak_stark,
+            &stark_proofs[Table::Keccak as usize].proof,
+            &stark_challenges[Table::Keccak as usize],
+            Some(&ctl_vars_per_table[Table::Keccak as usize]),
+            &[],
+            config.table(Table::Keccak),
+        )?;
+    }
+    if all_proof.table_in_use[*Table::KeccakSponge] {
+        verify_stark_proof_with_challenges(
+            keccak_sponge_stark,
+            &stark_proofs[Table::KeccakSponge as usize].proof,
+            &stark_challenges[Table::KeccakSponge as usize],
+            Some(&ctl_vars_per_table[Table::KeccakSponge as usize]),
+            &[],
+            config.table(Table::KeccakSponge),
+        )?;
+    }
+    if all_proof.table_in_use[*Table::Logic] {
+        verify_stark_proof_with_challenges(
+            logic_stark,
+            &stark_proofs[Table::Logic as usize].proof,
+            &stark_challenges[Table::Logic as usize],
+            Some(&ctl_vars_per_table[Table::Logic as usize]),
+            &[],
+            config.table(Table::Logic),
+        )?;
+    }
     verify_stark_proof_with_challenges(
         memory_stark,
         &stark_proofs[Table::Memory as usize].proof,
         &stark_challenges[Table::Memory as usize],
         Some(&ctl_vars_per_table[Table::Memory as usize]),
         &[],
-        config,
+        config.table(Table::Memory),
     )?;
     verify_stark_proof_with_challenges(
         mem_before_stark,
@@ -217,7 +236,7 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
         &stark_challenges[Table::MemBefore as usize],
         Some(&ctl_vars_per_table[Table::MemBefore as usize]),
         &[],
-        config,
+        config.table(Table::MemBefore),
     )?;
     verify_stark_proof_with_challenges(
         mem_after_stark,
@@ -225,8 +244,26 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
         &stark_challenges[Table::MemAfter as usize],
         Some(&ctl_vars_per_table[Table::MemAfter as usize]),
         &[],
-        config,
+        config.table(Table::MemAfter),
+    )?;
+    verify_stark_proof_with_challenges(
+        range_check_stark,
+        &stark_proofs[Table::RangeCheck as usize].proof,
+        &stark_challenges[Table::RangeCheck as usize],
+        Some(&ctl_vars_per_table[Table::RangeCheck as usize]),
+        &[],
+        config.table(Table::RangeCheck),
     )?;
+    if all_proof.table_in_use[*Table::Ec] {
+        verify_stark_proof_with_challenges(
+            ec_stark,
+            &stark_proofs[Table::Ec as usize].proof,
+            &stark_challenges[Table::Ec as usize],
+            Some(&ctl_vars_per_table[Table::Ec as usize]),
+            &[],
+            config.table(Table::Ec),
+        )?;
+    }
 
     let public_values = all_proof.public_values;
 
@@ -237,21 +274,24 @@ fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
 
     // Extra sums to add to the looked last value.
     // Only necessary for the Memory values.
-    let mut extra_looking_sums = vec![vec![F::ZERO; config.num_challenges]; NUM_TABLES];
+    let mut extra_looking_sums = vec![vec![F::ZERO; config.num_challenges()]; NUM_TABLES];
 
     // Memory
-    extra_looking_sums[Table::Memory as usize] = (0..config.num_challenges)
+    extra_looking_sums[Table::Memory as usize] = (0..config.num_challenges())
         .map(|i| get_memory_extra_looking_sum(&public_values, ctl_challenges.challenges[i]))
         .collect_vec();
 
     verify_cross_table_lookups::<F, D, NUM_TABLES>(
         cross_table_lookups,
-        all_proof
-            .multi_proof
-            .stark_proofs
-            .map(|p| p.proof.openings.ctl_zs_first.unwrap()),
+        core::array::from_fn(|i| {
+            let ctl_zs_first = stark_proofs[i].proof.openings.ctl_zs_first.clone().unwrap();
+            match all_proof.table_in_use[i] {
+                true => ctl_zs_first,
+                false => vec![F::ZERO; ctl_zs_first.len()],
+            }
+        }),
         Some(&extra_looking_sums),
-        config,
+        config.ctl_config(),
     )
 }
 
@@ -298,6 +338,10 @@ where
             GlobalMetadata::BlockChainId,
             public_values.block_metadata.block_chain_id,
         ),
+        (
+            GlobalMetadata::BlockHardfork,
+            public_values.block_metadata.block_hardfork.id().into(),
+        ),
         (
             GlobalMetadata::BlockBaseFee,
             public_values.block_metadata.block_base_fee,
@@ -306,10 +350,22 @@ where
             GlobalMetadata::ParentBeaconBlockRoot,
             h2u(public_values.block_metadata.parent_beacon_block_root),
         ),
+        (
+            GlobalMetadata::BlockWithdrawalsRoot,
+            h2u(public_values.block_metadata.withdrawals_root),
+        ),
         (
             GlobalMetadata::BlockCurrentHash,
             h2u(public_values.block_hashes.cur_hash),
         ),
+        (
+            GlobalMetadata::BlockParentHash,
+            h2u(public_values.block_hashes.parent_hash()),
+        ),
+        (
+            GlobalMetadata::BlockAncestorsHash,
+            h2u(public_values.block_hashes.ancestors_hash()),
+        ),
         (
             GlobalMetadata::BlockGasUsed,
             public_values.block_metadata.block_gas_used,
@@ -455,7 +511,7 @@ pub mod testing {
     >(
         all_stark: &AllStark<F, D>,
         all_proofs: &[AllProof<F, C, D>],
-        config: &StarkConfig,
+        config: &AllStarkConfig,
     ) -> Result<()> {
         assert!(!all_proofs.is_empty());
 
@@ -469,7 +525,7 @@ pub mod testing {
     }
 }
 
-#[cfg(debug_assertions)]
+#[cfg(all(feature = "generation", any(debug_assertions, feature = "ctl_diagnostics")))]
 pub(crate) mod debug_utils {
     use super::*;
 
@@ -511,6 +567,10 @@ pub(crate) mod debug_utils {
                 GlobalMetadata::BlockChainId,
                 public_values.block_metadata.block_chain_id,
             ),
+            (
+                GlobalMetadata::BlockHardfork,
+                public_values.block_metadata.block_hardfork.id().into(),
+            ),
             (
                 GlobalMetadata::BlockBaseFee,
                 public_values.block_metadata.block_base_fee,
@@ -519,6 +579,14 @@ pub(crate) mod debug_utils {
                 GlobalMetadata::BlockCurrentHash,
                 h2u(public_values.block_hashes.cur_hash),
             ),
+            (
+                GlobalMetadata::BlockParentHash,
+                h2u(public_values.block_hashes.parent_hash()),
+            ),
+            (
+                GlobalMetadata::BlockAncestorsHash,
+                h2u(public_values.block_hashes.ancestors_hash()),
+            ),
             (
                 GlobalMetadata::BlockGasUsed,
                 public_values.block_metadata.block_gas_used,
@@ -535,6 +603,10 @@ pub(crate) mod debug_utils {
                 GlobalMetadata::ParentBeaconBlockRoot,
                 h2u(public_values.block_metadata.parent_beacon_block_root),
             ),
+            (
+                GlobalMetadata::BlockWithdrawalsRoot,
+                h2u(public_values.block_metadata.withdrawals_root),
+            ),
             (
                 GlobalMetadata::TxnNumberBefore,
                 public_values.extra_block_data.txn_number_before,
@@ -1,4 +1,6 @@
-use ethereum_types::U256;
+use std::fmt;
+
+use ethereum_types::{H256, U256};
 
 #[derive(Debug)]
 pub enum ProgramError {
@@ -16,6 +18,7 @@ pub enum ProgramError {
     IntegerTooLarge,
     ProverInputError(ProverInputError),
     UnknownContractCode,
+    InvalidEcOperation,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -41,4 +44,30 @@ pub enum ProverInputError {
     InvalidJumpdestSimulation,
     KzgEvalFailure(String),
     Unimplemented,
+    UnhydratedStorage(UnhydratedStorage),
+}
+
+/// A storage slot accessed by the kernel which is absent from the storage
+/// linked list because it lies under a hash node of its account's input
+/// storage trie. Its value cannot be known without the preimage of that node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnhydratedStorage {
+    /// The hashed address of the account, i.e. its key in the state trie.
+    pub hashed_address: H256,
+    /// The hashed slot, i.e. its key in the account's storage trie.
+    pub hashed_slot: H256,
 }
+
+impl fmt::Display for UnhydratedStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "storage slot with hashed key {:?} of the account with hashed address {:?} lies \
+             under a hash node of the input storage trie: the trace must include the trie \
+             nodes on the path to this slot",
+            self.hashed_slot, self.hashed_address
+        )
+    }
+}
+
+impl std::error::Error for UnhydratedStorage {}
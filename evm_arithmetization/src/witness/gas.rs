@@ -52,5 +52,8 @@ pub(crate) const fn gas_to_charge(op: Operation) -> u64 {
         ExitKernel => KERNEL_ONLY_INSTR,
         MloadGeneral => KERNEL_ONLY_INSTR,
         MstoreGeneral => KERNEL_ONLY_INSTR,
+        EcAdd(_) => KERNEL_ONLY_INSTR,
+        EcDouble(_) => KERNEL_ONLY_INSTR,
+        EcMul(_) => KERNEL_ONLY_INSTR,
     }
 }
@@ -1,11 +1,17 @@
+#[cfg(any(feature = "generation", test))]
 use std::collections::HashMap;
 
+#[cfg(any(feature = "generation", test))]
 use ethereum_types::U256;
+#[cfg(any(feature = "generation", test))]
 use serde::{Deserialize, Serialize};
+#[cfg(any(feature = "generation", test))]
 use serde_big_array::BigArray;
 
+#[cfg(feature = "generation")]
 use crate::cpu::membus::{NUM_CHANNELS, NUM_GP_CHANNELS};
 
+#[cfg(feature = "generation")]
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum MemoryChannel {
     Code,
@@ -13,15 +19,28 @@ pub(crate) enum MemoryChannel {
     PartialChannel,
 }
 
+#[cfg(feature = "generation")]
 use MemoryChannel::{Code, GeneralPurpose, PartialChannel};
 
-use super::operation::CONTEXT_SCALING_FACTOR;
+#[cfg(feature = "generation")]
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
-use crate::memory::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES, SEGMENT_SCALING_FACTOR};
+#[cfg(feature = "generation")]
+use crate::memory::segments::SEGMENT_SCALING_FACTOR;
+#[cfg(any(feature = "generation", test))]
+use crate::memory::segments::{Segment, PREINITIALIZED_SEGMENTS_INDICES};
+#[cfg(feature = "generation")]
 use crate::witness::errors::MemoryError::SegmentTooLarge;
+#[cfg(feature = "generation")]
 use crate::witness::errors::ProgramError;
+#[cfg(feature = "generation")]
 use crate::witness::errors::ProgramError::MemoryError;
 
+// Contexts in the kernel are shifted by 2^64, so that they can be combined with
+// the segment and virtual address components in a single U256 word.
+#[cfg(feature = "generation")]
+pub(crate) const CONTEXT_SCALING_FACTOR: usize = 64;
+
+#[cfg(feature = "generation")]
 impl MemoryChannel {
     pub(crate) fn index(&self) -> usize {
         match *self {
@@ -43,6 +62,7 @@ pub struct MemoryAddress {
 }
 
 impl MemoryAddress {
+    #[cfg(any(feature = "generation", test))]
     pub(crate) const fn new(context: usize, segment: Segment, virt: usize) -> Self {
         Self {
             context,
@@ -55,6 +75,7 @@ impl MemoryAddress {
     /// Creates a new `MemoryAddress` from a bundled address fitting a `U256`.
     /// It will recover the virtual offset as the lowest 32-bit limb, the
     /// segment as the next limb, and the context as the next one.
+    #[cfg(feature = "generation")]
     pub(crate) fn new_bundle(addr: U256) -> Result<Self, ProgramError> {
         let virt = addr.low_u32() as usize;
         let segment = (addr >> SEGMENT_SCALING_FACTOR).low_u32() as usize;
@@ -69,17 +90,20 @@ impl MemoryAddress {
         Ok(Self::new(context, Segment::all()[segment], virt))
     }
 
+    #[cfg(feature = "generation")]
     pub(crate) fn increment(&mut self) {
         self.virt = self.virt.saturating_add(1);
     }
 }
 
+#[cfg(feature = "generation")]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum MemoryOpKind {
     Read,
     Write,
 }
 
+#[cfg(feature = "generation")]
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct MemoryOp {
     /// true if this is an actual memory operation, or false if it's a padding
@@ -91,6 +115,7 @@ pub(crate) struct MemoryOp {
     pub value: U256,
 }
 
+#[cfg(feature = "generation")]
 pub(crate) static DUMMY_MEMOP: MemoryOp = MemoryOp {
     filter: false,
     timestamp: 0,
@@ -103,6 +128,7 @@ pub(crate) static DUMMY_MEMOP: MemoryOp = MemoryOp {
     value: U256::zero(),
 };
 
+#[cfg(feature = "generation")]
 impl MemoryOp {
     pub(crate) fn new(
         channel: MemoryChannel,
@@ -147,12 +173,14 @@ impl MemoryOp {
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct MemoryState {
     pub(crate) contexts: Vec<MemoryContextState>,
     pub(crate) preinitialized_segments: HashMap<Segment, MemorySegmentState>,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl MemoryState {
     pub(crate) fn new(kernel_code: &[u8]) -> Self {
         let code_u256s = kernel_code.iter().map(|&x| Some(x.into())).collect();
@@ -161,6 +189,7 @@ impl MemoryState {
         result
     }
 
+    #[cfg(feature = "generation")]
     pub(crate) fn apply_ops(&mut self, ops: &[MemoryOp]) {
         for &op in ops {
             let MemoryOp {
@@ -201,6 +230,7 @@ impl MemoryState {
     /// Returns the memory values associated with a preinitialized segment. We
     /// need a specific behaviour here, since the values can be stored either in
     /// `preinitialized_segments` or in the memory itself.
+    #[cfg(feature = "generation")]
     pub(crate) fn get_preinit_memory(&self, segment: Segment) -> Vec<Option<U256>> {
         assert!(PREINITIALIZED_SEGMENTS_INDICES.contains(&segment.unscale()));
         let len = self
@@ -221,6 +251,7 @@ impl MemoryState {
     /// be stored in memory yet. If the value in memory is not set and the
     /// address is part of the preinitialized segment, then we return the
     /// preinitialized value instead.
+    #[cfg(feature = "generation")]
     pub(crate) fn get_with_init(&self, address: MemoryAddress) -> U256 {
         match self.get(address) {
             Some(val) => val,
@@ -263,12 +294,14 @@ impl MemoryState {
     }
 
     // These fields are already scaled by their respective segment.
+    #[cfg(feature = "generation")]
     pub(crate) fn read_global_metadata(&self, field: GlobalMetadata) -> U256 {
         self.get_with_init(MemoryAddress::new_bundle(U256::from(field as usize)).unwrap())
     }
 
     /// Inserts a segment and its preinitialized values in
     /// `preinitialized_segments`.
+    #[cfg(feature = "generation")]
     pub(crate) fn insert_preinitialized_segment(
         &mut self,
         segment: Segment,
@@ -280,6 +313,7 @@ impl MemoryState {
 
     /// Returns a boolean which indicates whether a segment (given as a usize)
     /// is part of the `preinitialize_segments`.
+    #[cfg(feature = "generation")]
     pub(crate) fn is_preinitialized_segment(&self, segment: usize) -> bool {
         if let Some(seg) = Segment::all().get(segment) {
             self.preinitialized_segments.contains_key(seg)
@@ -289,6 +323,7 @@ impl MemoryState {
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 impl Default for MemoryState {
     fn default() -> Self {
         Self {
@@ -299,6 +334,7 @@ impl Default for MemoryState {
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct MemoryContextState {
     #[serde(with = "BigArray")]
@@ -306,6 +342,7 @@ pub(crate) struct MemoryContextState {
     pub(crate) segments: [MemorySegmentState; Segment::COUNT],
 }
 
+#[cfg(any(feature = "generation", test))]
 impl Default for MemoryContextState {
     fn default() -> Self {
         Self {
@@ -314,11 +351,13 @@ impl Default for MemoryContextState {
     }
 }
 
+#[cfg(any(feature = "generation", test))]
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub(crate) struct MemorySegmentState {
     pub(crate) content: Vec<Option<U256>>,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl MemorySegmentState {
     pub(crate) fn get(&self, virtual_addr: usize) -> U256 {
         self.content
@@ -335,6 +374,7 @@ impl MemorySegmentState {
         self.content[virtual_addr] = Some(value);
     }
 
+    #[cfg(feature = "generation")]
     pub(crate) fn content(&self) -> Vec<U256> {
         self.content
             .iter()
@@ -342,3 +382,340 @@ impl MemorySegmentState {
             .collect()
     }
 }
+
+/// The preinitialized segments at the start of a batch, which are shared by
+/// the memory snapshots of all its segments.
+#[cfg(any(feature = "generation", test))]
+pub(crate) type InitialSegments = HashMap<Segment, MemorySegmentState>;
+
+/// A compact serialized form of a [`MemoryState`].
+///
+/// The preinitialized segments hold the initial memory of the batch, which
+/// can be rebuilt from the inputs of the batch. They are therefore
+/// delta-encoded against their content at the start of the batch, so that
+/// only the cells written since are shipped with each segment. Since reading a
+/// preinitialized cell copies its value into the kernel context, the cells of
+/// the kernel context are in turn delta-encoded against the preinitialized
+/// segments. Sparse contents are encoded as bitmaps.
+#[cfg(any(feature = "generation", test))]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct CompactMemoryState {
+    preinitialized_segments: Vec<(Segment, CompactSegmentState)>,
+    /// The non-empty segments of each context, along with their index.
+    contexts: Vec<Vec<(usize, CompactSegmentState)>>,
+}
+
+/// An error raised when decoding a malformed [`CompactMemoryState`].
+#[cfg(any(feature = "generation", test))]
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CompactMemoryError {
+    #[error("invalid segment index {0}")]
+    InvalidSegment(usize),
+    #[error("segment {segment} is not preinitialized")]
+    NotPreinitialized { segment: usize },
+    #[error("bitmaps of {words} words cannot hold {len} cells")]
+    TruncatedBitmap { len: usize, words: usize },
+    #[error("missing the value of cell {virt}")]
+    MissingValue { virt: usize },
+    #[error("{0} values are not assigned to any cell")]
+    UnusedValues(usize),
+}
+
+/// The delta-encoded content of a memory segment.
+#[cfg(any(feature = "generation", test))]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct CompactSegmentState {
+    len: usize,
+    /// Bitmap of the cells that are set.
+    set: Vec<u64>,
+    /// Bitmap of the set cells whose value differs from the initial memory.
+    changed: Vec<u64>,
+    /// The values of the changed cells, in order.
+    values: Vec<U256>,
+}
+
+fn get_bit(bitmap: &[u64], i: usize) -> bool {
+    (bitmap[i / 64] >> (i % 64)) & 1 == 1
+}
+
+#[cfg(any(feature = "generation", test))]
+fn set_bit(bitmap: &mut [u64], i: usize) {
+    bitmap[i / 64] |= 1 << (i % 64);
+}
+
+#[cfg(any(feature = "generation", test))]
+impl CompactSegmentState {
+    fn encode(content: &[Option<U256>], base: Option<&MemorySegmentState>) -> Self {
+        let words = content.len().div_ceil(64);
+        let mut result = Self {
+            len: content.len(),
+            set: vec![0; words],
+            changed: vec![0; words],
+            values: vec![],
+        };
+
+        for (i, value) in content.iter().enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+            set_bit(&mut result.set, i);
+            if base.and_then(|base| base.content.get(i).copied().flatten()) != Some(*value) {
+                set_bit(&mut result.changed, i);
+                result.values.push(*value);
+            }
+        }
+
+        result
+    }
+
+    fn decode(
+        &self,
+        base: Option<&MemorySegmentState>,
+    ) -> Result<MemorySegmentState, CompactMemoryError> {
+        let words = self.set.len().min(self.changed.len());
+        if words < self.len.div_ceil(64) {
+            return Err(CompactMemoryError::TruncatedBitmap {
+                len: self.len,
+                words,
+            });
+        }
+
+        let mut values = self.values.iter();
+        let content = (0..self.len)
+            .map(|i| {
+                Ok(if !get_bit(&self.set, i) {
+                    None
+                } else if get_bit(&self.changed, i) {
+                    Some(
+                        *values
+                            .next()
+                            .ok_or(CompactMemoryError::MissingValue { virt: i })?,
+                    )
+                } else {
+                    base.and_then(|base| base.content.get(i).copied().flatten())
+                })
+            })
+            .collect::<Result<_, CompactMemoryError>>()?;
+
+        match values.len() {
+            0 => Ok(MemorySegmentState { content }),
+            unused => Err(CompactMemoryError::UnusedValues(unused)),
+        }
+    }
+
+    fn num_set_cells(&self) -> usize {
+        self.set.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+/// Returns the initial content of segment `segment` in context `ctx`, against
+/// which its cells are delta-encoded. Only the kernel context reads from the
+/// preinitialized segments.
+#[cfg(any(feature = "generation", test))]
+fn preinitialized_base(
+    preinitialized_segments: &HashMap<Segment, MemorySegmentState>,
+    ctx: usize,
+    segment: usize,
+) -> Option<&MemorySegmentState> {
+    if ctx != 0 {
+        return None;
+    }
+    Segment::all()
+        .get(segment)
+        .and_then(|segment| preinitialized_segments.get(segment))
+}
+
+#[cfg(any(feature = "generation", test))]
+impl CompactMemoryState {
+    /// Encodes `memory`, whose preinitialized segments were `initial` at the
+    /// start of the batch.
+    pub(crate) fn encode(memory: &MemoryState, initial: &InitialSegments) -> Self {
+        let preinitialized_segments = memory
+            .preinitialized_segments
+            .iter()
+            .map(|(&segment, state)| {
+                (
+                    segment,
+                    CompactSegmentState::encode(&state.content, initial.get(&segment)),
+                )
+            })
+            .collect();
+
+        let contexts = memory
+            .contexts
+            .iter()
+            .enumerate()
+            .map(|(ctx, context)| {
+                context
+                    .segments
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, state)| !state.content.is_empty())
+                    .map(|(i, state)| {
+                        let base = preinitialized_base(&memory.preinitialized_segments, ctx, i);
+                        (i, CompactSegmentState::encode(&state.content, base))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            preinitialized_segments,
+            contexts,
+        }
+    }
+
+    /// Returns the number of values actually stored in this compact form.
+    pub(crate) fn num_values(&self) -> usize {
+        self.segment_states().map(|state| state.values.len()).sum()
+    }
+
+    /// Returns the number of set cells across all segments.
+    pub(crate) fn num_set_cells(&self) -> usize {
+        self.segment_states()
+            .map(CompactSegmentState::num_set_cells)
+            .sum()
+    }
+
+    fn segment_states(&self) -> impl Iterator<Item = &CompactSegmentState> {
+        self.preinitialized_segments
+            .iter()
+            .map(|(_, state)| state)
+            .chain(self.contexts.iter().flatten().map(|(_, state)| state))
+    }
+}
+
+/// Decodes a [`CompactMemoryState`], given the preinitialized segments at the
+/// start of its batch.
+#[cfg(any(feature = "generation", test))]
+impl TryFrom<(&CompactMemoryState, &InitialSegments)> for MemoryState {
+    type Error = CompactMemoryError;
+
+    fn try_from(
+        (compact, initial): (&CompactMemoryState, &InitialSegments),
+    ) -> Result<Self, Self::Error> {
+        let preinitialized_segments = compact
+            .preinitialized_segments
+            .iter()
+            .map(|(segment, state)| {
+                if !PREINITIALIZED_SEGMENTS_INDICES.contains(&segment.unscale()) {
+                    return Err(CompactMemoryError::NotPreinitialized {
+                        segment: segment.unscale(),
+                    });
+                }
+                Ok((*segment, state.decode(initial.get(segment))?))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let contexts = compact
+            .contexts
+            .iter()
+            .enumerate()
+            .map(|(ctx, segments)| {
+                let mut context = MemoryContextState::default();
+                for (i, state) in segments {
+                    let segment = context
+                        .segments
+                        .get_mut(*i)
+                        .ok_or(CompactMemoryError::InvalidSegment(*i))?;
+                    let base = preinitialized_base(&preinitialized_segments, ctx, *i);
+                    *segment = state.decode(base)?;
+                }
+                Ok(context)
+            })
+            .collect::<Result<_, CompactMemoryError>>()?;
+
+        Ok(Self {
+            contexts,
+            preinitialized_segments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_with_preinitialized_trie_data() -> (MemoryState, InitialSegments) {
+        let mut memory = MemoryState::new(&[1, 2, 3]);
+        let trie_data = MemorySegmentState {
+            content: vec![Some(7.into()), None, Some(9.into()), Some(0.into())],
+        };
+        let initial = HashMap::from([(Segment::TrieData, trie_data.clone())]);
+
+        // The state trie was appended to the preinitialized trie data during the
+        // batch.
+        let mut preinitialized = trie_data.clone();
+        preinitialized.set(4, 11.into());
+        memory
+            .preinitialized_segments
+            .insert(Segment::TrieData, preinitialized);
+
+        // Context 0 holds a copy of the preinitialized cells it read, one of
+        // which was then overwritten.
+        let mut copied = trie_data;
+        copied.content[2] = Some(10.into());
+        memory.contexts[0].segments[Segment::TrieData.unscale()] = copied;
+        memory.contexts.push(MemoryContextState::default());
+        memory.contexts[1].segments[Segment::Stack.unscale()].content = vec![None, Some(5.into())];
+
+        (memory, initial)
+    }
+
+    #[test]
+    fn compact_memory_roundtrip() {
+        let (memory, initial) = memory_with_preinitialized_trie_data();
+
+        let compact = CompactMemoryState::encode(&memory, &initial);
+        // 1 appended preinitialized value, 3 code bytes, 1 changed value and 1
+        // stack value.
+        assert_eq!(compact.num_values(), 6);
+        assert_eq!(compact.num_set_cells(), 11);
+
+        let decoded = MemoryState::try_from((&compact, &initial)).unwrap();
+        assert_eq!(decoded.contexts.len(), memory.contexts.len());
+        for (decoded, original) in decoded.contexts.iter().zip(&memory.contexts) {
+            for (decoded, original) in decoded.segments.iter().zip(&original.segments) {
+                assert_eq!(decoded.content, original.content);
+            }
+        }
+        assert_eq!(
+            decoded.preinitialized_segments[&Segment::TrieData].content,
+            memory.preinitialized_segments[&Segment::TrieData].content
+        );
+    }
+
+    #[test]
+    fn malformed_compact_memory_is_rejected() {
+        let (memory, initial) = memory_with_preinitialized_trie_data();
+        let compact = CompactMemoryState::encode(&memory, &initial);
+
+        let mut invalid_segment = compact.clone();
+        invalid_segment.contexts[1][0].0 = Segment::COUNT;
+        assert!(matches!(
+            MemoryState::try_from((&invalid_segment, &initial)),
+            Err(CompactMemoryError::InvalidSegment(_))
+        ));
+
+        let mut truncated = compact.clone();
+        truncated.contexts[1][0].1.set.clear();
+        assert!(matches!(
+            MemoryState::try_from((&truncated, &initial)),
+            Err(CompactMemoryError::TruncatedBitmap { .. })
+        ));
+
+        let mut missing_value = compact.clone();
+        missing_value.contexts[1][0].1.values.clear();
+        assert!(matches!(
+            MemoryState::try_from((&missing_value, &initial)),
+            Err(CompactMemoryError::MissingValue { virt: 1 })
+        ));
+
+        let mut unused_value = compact;
+        unused_value.contexts[1][0].1.values.push(U256::one());
+        assert!(matches!(
+            MemoryState::try_from((&unused_value, &initial)),
+            Err(CompactMemoryError::UnusedValues(1))
+        ));
+    }
+}
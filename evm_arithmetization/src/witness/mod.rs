@@ -1,8 +1,13 @@
 pub(crate) mod errors;
+#[cfg(feature = "generation")]
 pub(crate) mod gas;
 pub(crate) mod memory;
+#[cfg(feature = "generation")]
 pub(crate) mod operation;
 pub(crate) mod state;
+#[cfg(feature = "generation")]
 pub(crate) mod traces;
+#[cfg(feature = "generation")]
 pub mod transition;
+#[cfg(feature = "generation")]
 pub(crate) mod util;
@@ -14,17 +14,21 @@ use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::assembler::BYTES_PER_OFFSET;
 use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
 use crate::cpu::simple_logic::eq_iszero::generate_pinv_diff;
 use crate::cpu::stack::MAX_USER_STACK_SIZE;
+use crate::ec::ec_stark::{Curve, EcOpKind};
 use crate::extension_tower::BN_BASE;
 use crate::memory::segments::Segment;
 use crate::util::u256_to_usize;
 use crate::witness::errors::MemoryError::VirtTooLarge;
 use crate::witness::errors::ProgramError;
-use crate::witness::memory::{MemoryAddress, MemoryChannel, MemoryOp, MemoryOpKind};
+use crate::witness::memory::{
+    MemoryAddress, MemoryChannel, MemoryOp, MemoryOpKind, CONTEXT_SCALING_FACTOR,
+};
 use crate::witness::operation::MemoryChannel::GeneralPurpose;
 use crate::witness::util::{
-    keccak_sponge_log, mem_read_gp_with_log_and_fill, mem_write_gp_log_and_fill,
+    ec_log, keccak_sponge_log, mem_read_gp_with_log_and_fill, mem_write_gp_log_and_fill,
     stack_pop_with_log_and_fill,
 };
 use crate::{arithmetic, logic};
@@ -56,12 +60,11 @@ pub(crate) enum Operation {
     ExitKernel,
     MloadGeneral,
     MstoreGeneral,
+    EcAdd(Curve),
+    EcDouble(Curve),
+    EcMul(Curve),
 }
 
-// Contexts in the kernel are shifted by 2^64, so that they can be combined with
-// the segment and virtual address components in a single U256 word.
-pub(crate) const CONTEXT_SCALING_FACTOR: usize = 64;
-
 /// Adds a CPU row filled with the two inputs and the output of a logic
 /// operation. Generates a new logic operation and adds it to the vector of
 /// operation in `LogicStark`. Adds three memory read operations to
@@ -371,6 +374,33 @@ pub(crate) fn generate_push<F: Field, T: Transition<F>>(
         // The call to `U256::from_big_endian()` would panic.
         return Err(ProgramError::IntegerTooLarge);
     }
+    if num_bytes == 0 {
+        // `PUSH0` reads whether it is enabled, i.e. whether the block follows
+        // Shanghai or a later hardfork. The read is only carried out in user mode.
+        let (push0_enabled, push0_enabled_log) = mem_read_gp_with_log_and_fill(
+            1,
+            MemoryAddress::new(
+                0,
+                Segment::GlobalMetadata,
+                GlobalMetadata::Push0Enabled.unscale(),
+            ),
+            generation_state,
+            &mut row,
+        );
+        if generation_state.registers.is_kernel {
+            // Don't actually do the read, just set the address, etc.
+            let channel = &mut row.mem_channels[1];
+            channel.used = F::ZERO;
+            channel.value[0] = F::ONE;
+        } else {
+            if push0_enabled.is_zero() {
+                return Err(ProgramError::InvalidOpcode);
+            }
+            state.push_memory(push0_enabled_log);
+        }
+    }
+
+    let generation_state = state.get_mut_generation_state();
     let initial_offset = generation_state.registers.program_counter + 1;
 
     let base_address = MemoryAddress::new(code_context, Segment::Code, initial_offset);
@@ -763,6 +793,25 @@ pub(crate) fn generate_exit_kernel<F: Field, T: Transition<F>>(
     Ok(())
 }
 
+/// Adds a CPU row popping the base address of the inputs of a point operation
+/// of the given `kind` on `curve`. The inputs are read and the result written
+/// by the `EcStark`.
+pub(crate) fn generate_ec_op<F: Field, T: Transition<F>>(
+    curve: Curve,
+    kind: EcOpKind,
+    state: &mut T,
+    mut row: CpuColumnsView<F>,
+) -> Result<(), ProgramError> {
+    let generation_state = state.get_mut_generation_state();
+    let [(addr, _)] = stack_pop_with_log_and_fill::<1, _>(generation_state, &mut row)?;
+    let base_address = MemoryAddress::new_bundle(addr)?;
+
+    ec_log(state, curve, base_address, kind)?;
+    state.push_cpu(row);
+
+    Ok(())
+}
+
 pub(crate) fn generate_mload_general<F: Field, T: Transition<F>>(
     state: &mut T,
     mut row: CpuColumnsView<F>,
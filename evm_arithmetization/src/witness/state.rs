@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::cpu::kernel::aggregator::KERNEL;
 
+#[cfg(feature = "generation")]
 pub(crate) const KERNEL_CONTEXT: usize = 0;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
@@ -20,6 +21,62 @@ pub struct RegistersState {
     pub gas_used: u64,
 }
 
+/// A compact serialized form of [`RegistersState`], packing its boolean flags
+/// into a single bitmap.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(crate) struct CompactRegistersState {
+    program_counter: usize,
+    flags: u8,
+    stack_len: usize,
+    stack_top: U256,
+    context: usize,
+    gas_used: u64,
+}
+
+const IS_KERNEL_FLAG: u8 = 1;
+const IS_STACK_TOP_READ_FLAG: u8 = 1 << 1;
+const CHECK_OVERFLOW_FLAG: u8 = 1 << 2;
+
+impl From<RegistersState> for CompactRegistersState {
+    fn from(registers: RegistersState) -> Self {
+        let mut flags = 0;
+        if registers.is_kernel {
+            flags |= IS_KERNEL_FLAG;
+        }
+        if registers.is_stack_top_read {
+            flags |= IS_STACK_TOP_READ_FLAG;
+        }
+        if registers.check_overflow {
+            flags |= CHECK_OVERFLOW_FLAG;
+        }
+
+        Self {
+            program_counter: registers.program_counter,
+            flags,
+            stack_len: registers.stack_len,
+            stack_top: registers.stack_top,
+            context: registers.context,
+            gas_used: registers.gas_used,
+        }
+    }
+}
+
+impl From<CompactRegistersState> for RegistersState {
+    fn from(compact: CompactRegistersState) -> Self {
+        Self {
+            program_counter: compact.program_counter,
+            is_kernel: compact.flags & IS_KERNEL_FLAG != 0,
+            stack_len: compact.stack_len,
+            stack_top: compact.stack_top,
+            is_stack_top_read: compact.flags & IS_STACK_TOP_READ_FLAG != 0,
+            check_overflow: compact.flags & CHECK_OVERFLOW_FLAG != 0,
+            context: compact.context,
+            gas_used: compact.gas_used,
+        }
+    }
+}
+
+#[cfg(feature = "generation")]
 impl RegistersState {
     /// Returns the KERNEL context in kernel mode, and the
     /// current context otherwise.
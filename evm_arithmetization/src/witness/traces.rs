@@ -1,18 +1,22 @@
+use anyhow::Result;
 use plonky2::field::extension::Extendable;
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::hash::hash_types::RichField;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
-use starky::config::StarkConfig;
 use starky::util::trace_rows_to_poly_values;
 
-use crate::all_stark::{AllStark, NUM_TABLES};
+use crate::all_stark::{AllStark, AllStarkConfig, Table, NUM_TABLES};
+use crate::arithmetic::arithmetic_stark;
 use crate::arithmetic::{BinaryOperator, Operation};
-use crate::byte_packing::byte_packing_stark::BytePackingOp;
+use crate::byte_packing::byte_packing_stark::{self, BytePackingOp};
 use crate::cpu::columns::CpuColumnsView;
+use crate::ec::ec_stark::EcOp;
 use crate::generation::MemBeforeValues;
 use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeOp;
+use crate::memory::memory_stark;
 use crate::memory_continuation::memory_continuation_stark::mem_before_values_to_rows;
+use crate::range_check::range_check_stark::RangeCheckMultiplicities;
 use crate::witness::memory::MemoryOp;
 use crate::{arithmetic, keccak, keccak_sponge, logic};
 
@@ -25,6 +29,7 @@ pub(crate) struct TraceCheckpoint {
     pub(self) keccak_sponge_len: usize,
     pub(self) logic_len: usize,
     pub(self) memory_len: usize,
+    pub(self) ec_len: usize,
 }
 
 #[derive(Debug)]
@@ -36,6 +41,7 @@ pub(crate) struct Traces<T: Copy> {
     pub(crate) memory_ops: Vec<MemoryOp>,
     pub(crate) keccak_inputs: Vec<([u64; keccak::keccak_stark::NUM_INPUTS], usize)>,
     pub(crate) keccak_sponge_ops: Vec<KeccakSpongeOp>,
+    pub(crate) ec_ops: Vec<EcOp>,
 }
 
 impl<T: Copy> Traces<T> {
@@ -48,6 +54,7 @@ impl<T: Copy> Traces<T> {
             memory_ops: vec![],
             keccak_inputs: vec![],
             keccak_sponge_ops: vec![],
+            ec_ops: vec![],
         }
     }
 
@@ -84,6 +91,7 @@ impl<T: Copy> Traces<T> {
             // This is technically a lower-bound, as we may fill gaps,
             // but this gives a relatively good estimate.
             memory_len: self.memory_ops.len(),
+            ec_len: self.ec_ops.len(),
         }
     }
 
@@ -97,6 +105,7 @@ impl<T: Copy> Traces<T> {
             keccak_sponge_len: self.keccak_sponge_ops.len(),
             logic_len: self.logic_ops.len(),
             memory_len: self.memory_ops.len(),
+            ec_len: self.ec_ops.len(),
         }
     }
 
@@ -109,29 +118,51 @@ impl<T: Copy> Traces<T> {
             .truncate(checkpoint.keccak_sponge_len);
         self.logic_ops.truncate(checkpoint.logic_len);
         self.memory_ops.truncate(checkpoint.memory_len);
+        self.ec_ops.truncate(checkpoint.ec_len);
     }
 
     pub(crate) fn mem_ops_since(&self, checkpoint: TraceCheckpoint) -> &[MemoryOp] {
         &self.memory_ops[checkpoint.memory_len..]
     }
 
+    /// Returns, for each table, whether the segment produced any of its rows.
+    /// Tables which are not [optional](Table::is_optional) are always in use.
+    pub(crate) fn table_in_use(&self) -> [bool; NUM_TABLES] {
+        let mut table_in_use = [true; NUM_TABLES];
+        table_in_use[*Table::BytePacking] = !self.byte_packing_ops.is_empty();
+        table_in_use[*Table::Keccak] = !self.keccak_inputs.is_empty();
+        table_in_use[*Table::KeccakSponge] = !self.keccak_sponge_ops.is_empty();
+        table_in_use[*Table::Logic] = !self.logic_ops.is_empty();
+        table_in_use[*Table::Ec] = !self.ec_ops.is_empty();
+        table_in_use
+    }
+
     pub(crate) fn clock(&self) -> usize {
         self.cpu.len()
     }
 
-    pub(crate) fn into_tables<const D: usize>(
+    /// Generates the trace of each table in turn, in the order of [`Table`],
+    /// and hands it to `sink` as soon as it is generated.
+    ///
+    /// The operations of a table are freed once its trace is generated, so that
+    /// `sink` can commit to each trace before the next one is materialized,
+    /// instead of holding the traces of all tables in memory at once. The
+    /// lookups of each trace in the range-check table are counted before it is
+    /// handed over, so that the range-check trace comes after all of them.
+    pub(crate) fn for_each_table<const D: usize>(
         self,
         all_stark: &AllStark<T, D>,
         mem_before_values: &MemBeforeValues,
         stale_contexts: Vec<usize>,
         mut trace_lengths: TraceCheckpoint,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
         timing: &mut TimingTree,
-    ) -> [Vec<PolynomialValues<T>>; NUM_TABLES]
+        mut sink: impl FnMut(Table, Vec<PolynomialValues<T>>, &mut TimingTree) -> Result<()>,
+    ) -> Result<()>
     where
         T: RichField + Extendable<D>,
     {
-        let cap_elements = config.fri_config.num_cap_elements();
+        let cap_elements = |table| config.table(table).fri_config.num_cap_elements();
         let Traces {
             arithmetic_ops,
             byte_packing_ops,
@@ -140,43 +171,61 @@ impl<T: Copy> Traces<T> {
             memory_ops,
             keccak_inputs,
             keccak_sponge_ops,
+            ec_ops,
         } = self;
+        let mut range_checks = RangeCheckMultiplicities::default();
 
         let arithmetic_trace = timed!(
             timing,
             "generate arithmetic trace",
-            all_stark.arithmetic_stark.generate_trace(arithmetic_ops)
+            all_stark
+                .arithmetic_stark
+                .generate_trace(arithmetic_ops, cap_elements(Table::Arithmetic))
         );
+        arithmetic_stark::count_range_checks(&arithmetic_trace, &mut range_checks);
+        sink(Table::Arithmetic, arithmetic_trace, timing)?;
         let byte_packing_trace = timed!(
             timing,
             "generate byte packing trace",
-            all_stark
-                .byte_packing_stark
-                .generate_trace(byte_packing_ops, cap_elements, timing)
+            all_stark.byte_packing_stark.generate_trace(
+                byte_packing_ops,
+                cap_elements(Table::BytePacking),
+                timing
+            )
         );
+        byte_packing_stark::count_range_checks(&byte_packing_trace, &mut range_checks);
+        sink(Table::BytePacking, byte_packing_trace, timing)?;
         let cpu_rows = cpu.into_iter().map(|x| x.into()).collect();
         let cpu_trace = trace_rows_to_poly_values(cpu_rows);
+        sink(Table::Cpu, cpu_trace, timing)?;
         let keccak_trace = timed!(
             timing,
             "generate Keccak trace",
-            all_stark
-                .keccak_stark
-                .generate_trace(keccak_inputs, cap_elements, timing)
+            all_stark.keccak_stark.generate_trace(
+                keccak_inputs,
+                cap_elements(Table::Keccak),
+                timing
+            )
         );
+        sink(Table::Keccak, keccak_trace, timing)?;
         let keccak_sponge_trace = timed!(
             timing,
             "generate Keccak sponge trace",
-            all_stark
-                .keccak_sponge_stark
-                .generate_trace(keccak_sponge_ops, cap_elements, timing)
+            all_stark.keccak_sponge_stark.generate_trace(
+                keccak_sponge_ops,
+                cap_elements(Table::KeccakSponge),
+                timing
+            )
         );
+        sink(Table::KeccakSponge, keccak_sponge_trace, timing)?;
         let logic_trace = timed!(
             timing,
             "generate logic trace",
             all_stark
                 .logic_stark
-                .generate_trace(logic_ops, cap_elements, timing)
+                .generate_trace(logic_ops, cap_elements(Table::Logic), timing)
         );
+        sink(Table::Logic, logic_trace, timing)?;
         let (memory_trace, final_values, unpadded_memory_length) = timed!(
             timing,
             "generate memory trace",
@@ -188,6 +237,8 @@ impl<T: Copy> Traces<T> {
             )
         );
         trace_lengths.memory_len = unpadded_memory_length;
+        memory_stark::count_range_checks(&memory_trace, &mut range_checks);
+        sink(Table::Memory, memory_trace, timing)?;
 
         let mem_before_trace = timed!(
             timing,
@@ -196,13 +247,7 @@ impl<T: Copy> Traces<T> {
                 .mem_before_stark
                 .generate_trace(mem_before_values_to_rows(mem_before_values))
         );
-        let mem_after_trace = timed!(
-            timing,
-            "generate mem_after trace",
-            all_stark
-                .mem_after_stark
-                .generate_trace(final_values.clone())
-        );
+        sink(Table::MemBefore, mem_before_trace, timing)?;
 
         log::info!(
             "Trace lengths (before padding): {:?}, mem_before_len: {}, mem_after_len: {}",
@@ -211,17 +256,28 @@ impl<T: Copy> Traces<T> {
             final_values.len()
         );
 
-        [
-            arithmetic_trace,
-            byte_packing_trace,
-            cpu_trace,
-            keccak_trace,
-            keccak_sponge_trace,
-            logic_trace,
-            memory_trace,
-            mem_before_trace,
-            mem_after_trace,
-        ]
+        let mem_after_trace = timed!(
+            timing,
+            "generate mem_after trace",
+            all_stark.mem_after_stark.generate_trace(final_values)
+        );
+        sink(Table::MemAfter, mem_after_trace, timing)?;
+
+        let range_check_trace = timed!(
+            timing,
+            "generate range-check trace",
+            all_stark.range_check_stark.generate_trace(&range_checks)
+        );
+        sink(Table::RangeCheck, range_check_trace, timing)?;
+
+        let ec_trace = timed!(
+            timing,
+            "generate EC trace",
+            all_stark
+                .ec_stark
+                .generate_trace(ec_ops, cap_elements(Table::Ec), timing)
+        );
+        sink(Table::Ec, ec_trace, timing)
     }
 }
 
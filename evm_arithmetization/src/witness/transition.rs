@@ -10,6 +10,8 @@ use crate::cpu::membus::NUM_GP_CHANNELS;
 use crate::cpu::stack::{
     EQ_STACK_BEHAVIOR, IS_ZERO_STACK_BEHAVIOR, JUMPI_OP, JUMP_OP, MIGHT_OVERFLOW, STACK_BEHAVIORS,
 };
+use crate::cpu::syscalls_exceptions::EXC_STOP_CODE;
+use crate::ec::ec_stark::{Curve, EcOpKind};
 use crate::generation::state::State;
 use crate::memory::segments::Segment;
 use crate::witness::errors::ProgramError;
@@ -20,8 +22,6 @@ use crate::witness::state::RegistersState;
 use crate::witness::util::mem_read_code_with_log_and_fill;
 use crate::{arithmetic, logic};
 
-pub(crate) const EXC_STOP_CODE: u8 = 6;
-
 pub(crate) fn read_code_memory<F: Field, T: Transition<F>>(
     state: &mut T,
     row: &mut CpuColumnsView<F>,
@@ -90,6 +90,12 @@ pub(crate) fn decode(registers: RegistersState, opcode: u8) -> Result<Operation,
         (0x1d, _) => Ok(Operation::Syscall(opcode, 2, false)), // SAR
         (0x20, _) => Ok(Operation::Syscall(opcode, 2, false)), // KECCAK256
         (0x21, true) => Ok(Operation::KeccakGeneral),
+        (0x28, true) => Ok(Operation::EcAdd(Curve::Secp256k1)),
+        (0x29, true) => Ok(Operation::EcDouble(Curve::Secp256k1)),
+        (0x2a, true) => Ok(Operation::EcAdd(Curve::Bn254)),
+        (0x2b, true) => Ok(Operation::EcDouble(Curve::Bn254)),
+        (0x2c, true) => Ok(Operation::EcMul(Curve::Secp256k1)),
+        (0x2e, true) => Ok(Operation::EcMul(Curve::Bn254)),
         (0x30, _) => Ok(Operation::Syscall(opcode, 0, true)), // ADDRESS
         (0x31, _) => Ok(Operation::Syscall(opcode, 1, false)), // BALANCE
         (0x32, _) => Ok(Operation::Syscall(opcode, 0, true)), // ORIGIN
@@ -148,6 +154,12 @@ pub(crate) fn decode(registers: RegistersState, opcode: u8) -> Result<Operation,
             Err(ProgramError::KernelPanic)
         }
         (0xc0..=0xdf, true) => Ok(Operation::Mstore32Bytes(opcode - 0xc0 + 1)),
+        #[cfg(feature = "eof")]
+        (0xe0, _) => Ok(Operation::Syscall(opcode, 0, false)), // RJUMP
+        #[cfg(feature = "eof")]
+        (0xe1, _) => Ok(Operation::Syscall(opcode, 1, false)), // RJUMPI
+        #[cfg(feature = "eof")]
+        (0xe2, _) => Ok(Operation::Syscall(opcode, 1, false)), // RJUMPV
         (0xee, true) => Ok(Operation::ProverInput),
         (0xf0, _) => Ok(Operation::Syscall(opcode, 3, false)), // CREATE
         (0xf1, _) => Ok(Operation::Syscall(opcode, 7, false)), // CALL
@@ -194,6 +206,7 @@ pub(crate) fn fill_op_flag<F: Field>(op: Operation, row: &mut CpuColumnsView<F>)
         Operation::Mload32Bytes | Operation::Mstore32Bytes(_) => &mut flags.m_op_32bytes,
         Operation::ExitKernel => &mut flags.exit_kernel,
         Operation::MloadGeneral | Operation::MstoreGeneral => &mut flags.m_op_general,
+        Operation::EcAdd(_) | Operation::EcDouble(_) | Operation::EcMul(_) => &mut flags.ec_op,
     } = F::ONE;
 }
 
@@ -225,6 +238,7 @@ pub(crate) const fn get_op_special_length(op: Operation) -> Option<usize> {
         Operation::Mload32Bytes | Operation::Mstore32Bytes(_) => STACK_BEHAVIORS.m_op_32bytes,
         Operation::ExitKernel => STACK_BEHAVIORS.exit_kernel,
         Operation::MloadGeneral | Operation::MstoreGeneral => STACK_BEHAVIORS.m_op_general,
+        Operation::EcAdd(_) | Operation::EcDouble(_) | Operation::EcMul(_) => STACK_BEHAVIORS.ec_op,
     };
     if let Some(behavior) = behavior_opt {
         if behavior.num_pops > 0 && !behavior.pushes {
@@ -264,6 +278,7 @@ pub(crate) const fn might_overflow_op(op: Operation) -> bool {
         Operation::Mload32Bytes | Operation::Mstore32Bytes(_) => MIGHT_OVERFLOW.m_op_32bytes,
         Operation::ExitKernel => MIGHT_OVERFLOW.exit_kernel,
         Operation::MloadGeneral | Operation::MstoreGeneral => MIGHT_OVERFLOW.m_op_general,
+        Operation::EcAdd(_) | Operation::EcDouble(_) | Operation::EcMul(_) => MIGHT_OVERFLOW.ec_op,
     }
 }
 
@@ -517,6 +532,9 @@ where
             Operation::ExitKernel => generate_exit_kernel(self, row),
             Operation::MloadGeneral => generate_mload_general(self, row),
             Operation::MstoreGeneral => generate_mstore_general(self, row),
+            Operation::EcAdd(curve) => generate_ec_op(curve, EcOpKind::Add, self, row),
+            Operation::EcDouble(curve) => generate_ec_op(curve, EcOpKind::Double, self, row),
+            Operation::EcMul(curve) => generate_ec_op(curve, EcOpKind::Mul, self, row),
         }
     }
 
@@ -8,6 +8,7 @@ use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::keccak_util::keccakf_u8s;
 use crate::cpu::membus::NUM_CHANNELS;
 use crate::cpu::stack::MAX_USER_STACK_SIZE;
+use crate::ec::ec_stark::{Curve, EcOp, EcOpKind};
 use crate::generation::state::GenerationState;
 use crate::keccak_sponge::columns::{KECCAK_RATE_BYTES, KECCAK_WIDTH_BYTES};
 use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeOp;
@@ -403,3 +404,71 @@ pub(crate) fn byte_unpacking_log<F: Field, T: Transition<F>>(
         bytes,
     });
 }
+
+/// Adds a point addition, doubling or scalar multiplication on `curve` on
+/// the inputs stored at `base_address`: the reads of the input coordinates
+/// and scalar, the writes of the result right after them, and all the field
+/// operations of the formulas.
+pub(crate) fn ec_log<F: Field, T: Transition<F>>(
+    state: &mut T,
+    curve: Curve,
+    base_address: MemoryAddress,
+    kind: EcOpKind,
+) -> Result<(), ProgramError> {
+    let clock = state.get_clock();
+    let timestamp = clock * NUM_CHANNELS + 1;
+
+    let num_inputs = match kind {
+        EcOpKind::Add => 4,
+        EcOpKind::Double => 2,
+        EcOpKind::Mul => 3,
+    };
+    let mut address = base_address;
+    let mut inputs = Vec::with_capacity(num_inputs);
+    for _ in 0..num_inputs {
+        let val = state.get_generation_state().memory.get_with_init(address);
+        state.push_memory(MemoryOp::new(
+            MemoryChannel::Code,
+            clock,
+            address,
+            MemoryOpKind::Read,
+            val,
+        ));
+        inputs.push(val);
+        address.increment();
+    }
+
+    let point = (inputs[0], inputs[1]);
+    let ops = match kind {
+        EcOpKind::Add => vec![EcOp::add(
+            curve,
+            base_address,
+            timestamp,
+            point,
+            (inputs[2], inputs[3]),
+        )?],
+        EcOpKind::Double => vec![EcOp::double(curve, base_address, timestamp, point)?],
+        EcOpKind::Mul => EcOp::mul(curve, base_address, timestamp, point, inputs[2])?,
+    };
+
+    let (x, y) = ops[ops.len() - 1].output();
+    for val in [x, y] {
+        state.push_memory(MemoryOp::new(
+            MemoryChannel::Code,
+            clock,
+            address,
+            MemoryOpKind::Write,
+            val,
+        ));
+        address.increment();
+    }
+
+    for op in ops {
+        for arithmetic_op in op.arithmetic_ops() {
+            state.push_arithmetic(arithmetic_op);
+        }
+        state.push_ec(op);
+    }
+
+    Ok(())
+}
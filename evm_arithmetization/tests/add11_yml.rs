@@ -13,7 +13,7 @@ use evm_arithmetization::testing_utils::{
     GLOBAL_EXIT_ROOT_ACCOUNT,
 };
 use evm_arithmetization::verifier::testing::verify_all_proofs;
-use evm_arithmetization::StarkConfig;
+use evm_arithmetization::AllStarkConfig;
 use evm_arithmetization::{AllStark, GenerationInputs, Node};
 use hex_literal::hex;
 use keccak_hash::keccak;
@@ -188,10 +188,13 @@ fn get_generation_inputs() -> GenerationInputs {
     GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: tries_before,
         trie_roots_after,
         contract_code,
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         checkpoint_state_trie_root: state_trie_before.hash(),
         txn_number_before: 0.into(),
@@ -209,7 +212,7 @@ fn add11_yml() -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
     let inputs = get_generation_inputs();
 
     let max_cpu_len_log = 20;
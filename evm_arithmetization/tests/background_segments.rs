@@ -0,0 +1,51 @@
+use evm_arithmetization::prover::{SegmentDataIterator, SegmentMemoryStats};
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::AllData;
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+type F = GoldilocksField;
+
+/// Small segments, so that the block is split in several of them.
+const MAX_CPU_LEN_LOG: usize = 9;
+
+/// Returns the index and memory size of each segment of a payload.
+fn segment_summaries(
+    segments: impl Iterator<Item = AllData>,
+) -> anyhow::Result<Vec<(usize, SegmentMemoryStats)>> {
+    segments
+        .map(|segment_run| {
+            let (_, segment_data) = segment_run?;
+            Ok((segment_data.segment_index(), segment_data.memory_stats()))
+        })
+        .collect()
+}
+
+/// Test that the segments generated on a background thread are the same, and
+/// in the same order, as those generated on the current thread, and that the
+/// segmentation stops once the consumer is dropped.
+#[test]
+fn test_background_segmentation() -> anyhow::Result<()> {
+    init_logger();
+
+    let inputs = TestBlockBuilder::new().build()?;
+    let expected = segment_summaries(SegmentDataIterator::<F>::new(
+        &inputs,
+        Some(MAX_CPU_LEN_LOG),
+    ))?;
+    assert!(expected.len() > 1);
+
+    for depth in [0, 1, 2] {
+        let segments =
+            SegmentDataIterator::<F>::new(&inputs, Some(MAX_CPU_LEN_LOG)).in_background(depth);
+        assert_eq!(segment_summaries(segments)?, expected, "depth {depth}");
+    }
+
+    // Dropping the consumer early must not block on the remaining segments.
+    let mut segments =
+        SegmentDataIterator::<F>::new(&inputs, Some(MAX_CPU_LEN_LOG)).in_background(1);
+    let (_, first) = segments.next().expect("a block has at least one segment")?;
+    assert_eq!(first.segment_index(), 0);
+    drop(segments);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use ethereum_types::{Address, U256};
+use evm_arithmetization::proof::Hardfork;
+use evm_arithmetization::prover::testing::prove_all_segments;
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder, TEST_BENEFICIARY};
+use evm_arithmetization::verifier::testing::verify_all_proofs;
+use evm_arithmetization::{AllStark, AllStarkConfig};
+use hex_literal::hex;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+/// Prove a London block including one ommer, whose miner and the miner of the
+/// block are both credited their rewards.
+#[test]
+fn test_london_block_rewards() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+
+    let uncle_miner = Address::from(hex!("00000000000000000000000000000000000000aa"));
+    let inputs = TestBlockBuilder::new()
+        .hardfork(Hardfork::London)
+        .ommers(vec![(uncle_miner, 0.into())])
+        .build()?;
+
+    let eth = U256::exp10(18);
+    assert_eq!(
+        inputs.block_rewards,
+        vec![
+            (Address::from(TEST_BENEFICIARY), eth * 2 + eth / 16),
+            (uncle_miner, eth * 7 / 4),
+        ]
+    );
+
+    let mut timing = TimingTree::new("prove", log::Level::Debug);
+    let proofs = prove_all_segments::<F, C, D>(&all_stark, &config, inputs, 20, &mut timing, None)?;
+    timing.filter(std::time::Duration::from_millis(100)).print();
+
+    verify_all_proofs(&all_stark, &proofs, &config)
+}
+
+/// From Paris on, blocks don't reward their miner.
+#[test]
+fn test_paris_block_has_no_rewards() -> anyhow::Result<()> {
+    let inputs = TestBlockBuilder::new().hardfork(Hardfork::Paris).build()?;
+    assert!(inputs.block_rewards.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use ethereum_types::{Address, U256};
+use evm_arithmetization::proof::Hardfork;
+use evm_arithmetization::testing_utils::{init_logger, BytecodeHarness, TestBlockBuilder};
+use evm_arithmetization::verifier::testing::verify_all_proofs;
+use evm_arithmetization::{AllStark, AllStarkConfig};
+use hex_literal::hex;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::KeccakGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = KeccakGoldilocksConfig;
+
+#[test]
+fn test_harness_sender() {
+    // The address of the private key `1`.
+    assert_eq!(
+        BytecodeHarness::sender(),
+        Address::from_str("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf").unwrap()
+    );
+}
+
+/// Test a snippet storing its first calldata word.
+#[test]
+fn test_harness_sstore_calldata() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+
+    // PUSH1 0, CALLDATALOAD, PUSH1 1, SSTORE, STOP
+    let code = hex!("600035600155").to_vec();
+    let harness = BytecodeHarness::new(code)
+        .calldata(hex!("000000000000000000000000000000000000000000000000000000000000002a").to_vec())
+        .storage(U256::one(), U256::from(7));
+
+    let mut timing = TimingTree::new("prove", log::Level::Debug);
+    let proofs = harness.prove::<F, C, D>(&all_stark, &config, 20, &mut timing)?;
+    timing.filter(Duration::from_millis(100)).print();
+
+    verify_all_proofs(&all_stark, &proofs, &config)
+}
+
+/// Test that `PUSH0` is an invalid opcode before Shanghai, in which case the
+/// call consumes all its gas, and a valid one from Shanghai on.
+#[test]
+fn test_harness_push0_before_shanghai() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+
+    // PUSH0, PUSH1 1, SSTORE, STOP
+    let code = hex!("5f60015500").to_vec();
+    let gas_limit = 100_000;
+    let harness = |hardfork| {
+        BytecodeHarness::new(code.clone())
+            .gas_limit(gas_limit)
+            .storage(U256::one(), U256::from(7))
+            .block(TestBlockBuilder::new().hardfork(hardfork))
+    };
+
+    let paris = harness(Hardfork::Paris);
+    assert_eq!(paris.build()?.gas_used_after, gas_limit.into());
+    let shanghai = harness(Hardfork::Shanghai);
+    assert!(shanghai.build()?.gas_used_after < gas_limit.into());
+
+    let mut timing = TimingTree::new("prove", log::Level::Debug);
+    let proofs = paris.prove::<F, C, D>(&all_stark, &config, 20, &mut timing)?;
+    timing.filter(Duration::from_millis(100)).print();
+
+    verify_all_proofs(&all_stark, &proofs, &config)
+}
@@ -12,7 +12,7 @@ use evm_arithmetization::testing_utils::{
     update_beacon_roots_account_storage, GLOBAL_EXIT_ROOT_ACCOUNT,
 };
 use evm_arithmetization::verifier::testing::verify_all_proofs;
-use evm_arithmetization::{AllStark, Node, StarkConfig};
+use evm_arithmetization::{AllStark, AllStarkConfig, Node};
 use hex_literal::hex;
 use keccak_hash::keccak;
 use mpt_trie::nibbles::Nibbles;
@@ -53,7 +53,7 @@ fn test_erc721() -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
 
     let beneficiary = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
     let owner = hex!("5B38Da6a701c568545dCfcB03FcB875f56beddC4");
@@ -185,11 +185,14 @@ fn test_erc721() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: tries_before,
         trie_roots_after,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: Default::default(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
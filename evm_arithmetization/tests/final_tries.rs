@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use ethereum_types::{H160, H256, U256};
+use evm_arithmetization::generation::mpt::AccountRlp;
+use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use evm_arithmetization::prover::{FinalTriesStats, SegmentDataIterator};
+use evm_arithmetization::testing_utils::{
+    beacon_roots_account_nibbles, beacon_roots_contract_from_storage, create_account_storage,
+    ger_account_nibbles, init_logger, preinitialized_state_and_storage_tries,
+    update_beacon_roots_account_storage, GLOBAL_EXIT_ROOT_ACCOUNT,
+};
+use evm_arithmetization::Node;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+type F = GoldilocksField;
+
+const NUM_HEAVY_SLOTS: usize = 128;
+
+/// Execute 0 txns on a state with an untouched account holding many storage
+/// slots, and check that the kernel computes the final state trie without
+/// reinserting its slots.
+#[test]
+fn test_final_tries_skip_unchanged_storage() -> anyhow::Result<()> {
+    init_logger();
+
+    let block_metadata = BlockMetadata {
+        block_timestamp: 1.into(),
+        ..BlockMetadata::default()
+    };
+
+    let (mut state_trie_before, mut storage_tries) = preinitialized_state_and_storage_tries()?;
+
+    // The slot of the timestamp is overwritten by the block, the other one is
+    // left untouched.
+    let mut beacon_roots_account_storage = create_account_storage(&[
+        (block_metadata.block_timestamp, 7.into()),
+        (12345.into(), 1.into()),
+    ])?;
+    state_trie_before.insert(
+        beacon_roots_account_nibbles(),
+        rlp::encode(&beacon_roots_contract_from_storage(
+            &beacon_roots_account_storage,
+        ))
+        .to_vec(),
+    )?;
+    storage_tries[0].1 = beacon_roots_account_storage.clone();
+
+    let heavy_addr = H160::repeat_byte(0x42);
+    let heavy_state_key = keccak(heavy_addr);
+    let heavy_storage = create_account_storage(
+        &(1..=NUM_HEAVY_SLOTS)
+            .map(|i| (U256::from(i), U256::from(i) << 128))
+            .collect::<Vec<_>>(),
+    )?;
+    let heavy_account = AccountRlp {
+        nonce: 1.into(),
+        balance: 1_000_000.into(),
+        storage_root: heavy_storage.hash(),
+        ..AccountRlp::default()
+    };
+    let heavy_nibbles = Nibbles::from_bytes_be(heavy_state_key.as_bytes()).unwrap();
+    state_trie_before.insert(heavy_nibbles, rlp::encode(&heavy_account).to_vec())?;
+    storage_tries.push((heavy_state_key, heavy_storage));
+
+    let transactions_trie = HashedPartialTrie::from(Node::Empty);
+    let receipts_trie = HashedPartialTrie::from(Node::Empty);
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    let state_trie_after = {
+        let mut trie = HashedPartialTrie::from(Node::Empty);
+        update_beacon_roots_account_storage(
+            &mut beacon_roots_account_storage,
+            block_metadata.block_timestamp,
+            block_metadata.parent_beacon_block_root,
+        )?;
+        trie.insert(
+            beacon_roots_account_nibbles(),
+            rlp::encode(&beacon_roots_contract_from_storage(
+                &beacon_roots_account_storage,
+            ))
+            .to_vec(),
+        )?;
+        trie.insert(
+            ger_account_nibbles(),
+            rlp::encode(&GLOBAL_EXIT_ROOT_ACCOUNT).to_vec(),
+        )?;
+        trie.insert(heavy_nibbles, rlp::encode(&heavy_account).to_vec())?;
+
+        trie
+    };
+
+    let trie_roots_after = TrieRoots {
+        state_root: state_trie_after.hash(),
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+
+    let inputs = GenerationInputs {
+        signed_txns: vec![],
+        withdrawals: vec![],
+        block_rewards: vec![],
+        global_exit_roots: vec![],
+        burn_addr: None,
+        tries: TrieInputs {
+            state_trie: state_trie_before,
+            transactions_trie,
+            receipts_trie,
+            storage_tries,
+        },
+        trie_roots_after,
+        contract_code,
+        checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: vec![H256::default(); 256],
+            cur_hash: H256::default(),
+        },
+    };
+
+    // The kernel checks the final state trie against `trie_roots_after`.
+    let mut segment_data_iterator = SegmentDataIterator::<F>::new(&inputs, Some(20));
+    for segment in segment_data_iterator.by_ref() {
+        segment?;
+    }
+
+    let stats = segment_data_iterator.final_tries_stats();
+    log::info!("{:?}", stats);
+    assert!(stats.cycles > 0);
+    // The heavy account and the global exit root account are untouched, and
+    // so are all of the slots of the former and one of the beacon roots
+    // account.
+    assert_eq!(
+        stats,
+        FinalTriesStats {
+            cycles: stats.cycles,
+            skipped_slots: NUM_HEAVY_SLOTS + 1,
+            skipped_accounts: 2,
+        }
+    );
+
+    Ok(())
+}
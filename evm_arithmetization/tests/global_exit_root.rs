@@ -11,7 +11,7 @@ use evm_arithmetization::testing_utils::{
     update_beacon_roots_account_storage, update_ger_account_storage,
 };
 use evm_arithmetization::verifier::testing::verify_all_proofs;
-use evm_arithmetization::{AllStark, Node, StarkConfig};
+use evm_arithmetization::{AllStark, AllStarkConfig, Node};
 use keccak_hash::keccak;
 use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
 use plonky2::field::goldilocks_field::GoldilocksField;
@@ -29,7 +29,7 @@ fn test_global_exit_root() -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
 
     let block_metadata = BlockMetadata {
         block_timestamp: 1.into(),
@@ -79,7 +79,9 @@ fn test_global_exit_root() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots,
+        burn_addr: None,
         tries: TrieInputs {
             state_trie: state_trie_before,
             transactions_trie,
@@ -89,6 +91,7 @@ fn test_global_exit_root() -> anyhow::Result<()> {
         trie_roots_after,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use ethereum_types::{Address, BigEndianHash, H256};
+use evm_arithmetization::generation::invalid_txn::InvalidTxnReason;
+use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp};
+use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use evm_arithmetization::simulation::TxnStatus;
+use evm_arithmetization::testing_utils::{
+    beacon_roots_account_nibbles, beacon_roots_contract_from_storage, eth_to_wei,
+    ger_account_nibbles, init_logger, preinitialized_state_and_storage_tries,
+    update_beacon_roots_account_storage, BytecodeHarness, HarnessAccount, GLOBAL_EXIT_ROOT_ACCOUNT,
+};
+use evm_arithmetization::{simulate, Node};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+type F = GoldilocksField;
+
+/// Test that a transfer whose nonce does not match the sender nonce is skipped
+/// by the kernel, and reported with the corresponding rejection reason.
+#[test]
+fn test_nonce_mismatch() -> anyhow::Result<()> {
+    init_logger();
+
+    let beneficiary = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
+    let sender = hex!("2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+
+    let sender_state_key = keccak(sender);
+    let sender_nibbles = Nibbles::from_bytes_be(sender_state_key.as_bytes()).unwrap();
+
+    // The transaction below has nonce 5.
+    let sender_account = AccountRlp {
+        nonce: 6.into(),
+        balance: eth_to_wei(100_000.into()),
+        storage_root: HashedPartialTrie::from(Node::Empty).hash(),
+        code_hash: keccak([]),
+    };
+
+    let (mut state_trie_before, storage_tries) = preinitialized_state_and_storage_tries()?;
+    let mut beacon_roots_account_storage = storage_tries[0].1.clone();
+    state_trie_before.insert(sender_nibbles, rlp::encode(&sender_account).to_vec())?;
+
+    let tries_before = TrieInputs {
+        state_trie: state_trie_before,
+        transactions_trie: HashedPartialTrie::from(Node::Empty),
+        receipts_trie: HashedPartialTrie::from(Node::Empty),
+        storage_tries,
+    };
+
+    // Generated using a little py-evm script.
+    let txn = hex!("f861050a8255f094a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0648242421ba02c89eb757d9deeb1f5b3859a9d4d679951ef610ac47ad4608dc142beb1b7e313a05af7e9fbab825455d36c36c7f4cfcafbeafa9a77bdff936b52afb36d4fe4bcdd");
+
+    let block_metadata = BlockMetadata {
+        block_beneficiary: Address::from(beneficiary),
+        block_timestamp: 0x03e8.into(),
+        block_number: 1.into(),
+        block_difficulty: 0x020000.into(),
+        block_random: H256::from_uint(&0x020000.into()),
+        block_gaslimit: 0xff112233u32.into(),
+        block_chain_id: 1.into(),
+        block_base_fee: 0xa.into(),
+        block_gas_used: 0.into(),
+        ..Default::default()
+    };
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    // The sender account is left untouched.
+    let expected_state_trie_after: HashedPartialTrie = {
+        let mut state_trie_after = HashedPartialTrie::from(Node::Empty);
+
+        update_beacon_roots_account_storage(
+            &mut beacon_roots_account_storage,
+            block_metadata.block_timestamp,
+            block_metadata.parent_beacon_block_root,
+        )?;
+        let beacon_roots_account =
+            beacon_roots_contract_from_storage(&beacon_roots_account_storage);
+
+        state_trie_after.insert(sender_nibbles, rlp::encode(&sender_account).to_vec())?;
+        state_trie_after.insert(
+            beacon_roots_account_nibbles(),
+            rlp::encode(&beacon_roots_account).to_vec(),
+        )?;
+        state_trie_after.insert(
+            ger_account_nibbles(),
+            rlp::encode(&GLOBAL_EXIT_ROOT_ACCOUNT).to_vec(),
+        )?;
+
+        state_trie_after
+    };
+
+    let receipt_0 = LegacyReceiptRlp {
+        status: false,
+        cum_gas_used: 0.into(),
+        bloom: vec![0; 256].into(),
+        logs: vec![],
+    };
+    let mut receipts_trie = HashedPartialTrie::from(Node::Empty);
+    receipts_trie.insert(
+        Nibbles::from_str("0x80").unwrap(),
+        rlp::encode(&receipt_0).to_vec(),
+    )?;
+    let transactions_trie: HashedPartialTrie = Node::Leaf {
+        nibbles: Nibbles::from_str("0x80").unwrap(),
+        value: txn.to_vec(),
+    }
+    .into();
+
+    let trie_roots_after = TrieRoots {
+        state_root: expected_state_trie_after.hash(),
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+
+    let inputs = GenerationInputs {
+        signed_txns: vec![txn.to_vec()],
+        withdrawals: vec![],
+        block_rewards: vec![],
+        global_exit_roots: vec![],
+        burn_addr: None,
+        tries: tries_before,
+        trie_roots_after,
+        contract_code,
+        checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: vec![H256::default(); 256],
+            cur_hash: H256::default(),
+        },
+    };
+
+    let result = simulate::<F>(&inputs)?;
+    assert_eq!(result.trie_roots_after, trie_roots_after);
+    assert_eq!(result.gas_used_after, 0.into());
+    assert_eq!(result.txns.len(), 1);
+    assert_eq!(result.txns[0].txn_number, 0.into());
+    assert_eq!(result.txns[0].txn_hash, keccak(txn));
+    assert_eq!(
+        result.txns[0].status,
+        TxnStatus::Rejected(InvalidTxnReason::NonceMismatch)
+    );
+
+    Ok(())
+}
+
+/// Test that transactions failing each of the other pre-checks of the kernel
+/// are skipped, and reported with the corresponding rejection reason.
+#[test]
+fn test_rejection_reasons() -> anyhow::Result<()> {
+    init_logger();
+
+    let sender = |account: HarnessAccount| {
+        BytecodeHarness::new(vec![]).account(
+            BytecodeHarness::sender(),
+            HarnessAccount {
+                balance: eth_to_wei(1_000_000.into()),
+                ..account
+            },
+        )
+    };
+    let cases = [
+        (
+            BytecodeHarness::new(vec![]).gas_limit(20_000),
+            InvalidTxnReason::IntrinsicGasExceedsGasLimit,
+        ),
+        (
+            BytecodeHarness::new(vec![]).gas_limit(0x1_0000_0000),
+            InvalidTxnReason::GasLimitExceedsBlockGasLimit,
+        ),
+        (
+            sender(HarnessAccount {
+                nonce: u64::MAX.into(),
+                ..Default::default()
+            }),
+            InvalidTxnReason::NonceOverflow,
+        ),
+        (
+            sender(HarnessAccount {
+                code: vec![0x00],
+                ..Default::default()
+            }),
+            InvalidTxnReason::SenderNotEoa,
+        ),
+        (
+            BytecodeHarness::new(vec![]).account(
+                BytecodeHarness::sender(),
+                HarnessAccount {
+                    balance: 1.into(),
+                    ..Default::default()
+                },
+            ),
+            InvalidTxnReason::InsufficientBalance,
+        ),
+        (
+            BytecodeHarness::new(vec![]).chain_id(2.into()),
+            InvalidTxnReason::ChainIdMismatch,
+        ),
+        (
+            // The base fee of the block is 10.
+            BytecodeHarness::new(vec![]).gas_price(9.into()),
+            InvalidTxnReason::FeeCapTooLow,
+        ),
+    ];
+
+    for (harness, reason) in cases {
+        let result = simulate::<F>(&harness.build()?)?;
+        assert_eq!(result.gas_used, 0.into(), "{reason}");
+        assert_eq!(result.txns.len(), 1, "{reason}");
+        assert_eq!(result.txns[0].status, TxnStatus::Rejected(reason));
+    }
+
+    Ok(())
+}
+
+/// Test that the sender must afford the fee caps of a transaction, and not
+/// only the fees it actually pays.
+#[test]
+fn test_balance_covers_fee_caps() -> anyhow::Result<()> {
+    init_logger();
+
+    // The base fee of the block is 10 and its blob base fee is 1, so that
+    // these transactions pay at most 10 * 100_000 + 131_072 = 1_131_072 wei.
+    let fee_market = BytecodeHarness::new(vec![])
+        .gas_limit(100_000)
+        .fee_caps(0.into(), 1_000.into());
+    let blob = BytecodeHarness::new(vec![])
+        .gas_limit(100_000)
+        .fee_caps(0.into(), 10.into())
+        .blobs(1, 1_000.into());
+    let with_balance = |harness: &BytecodeHarness, balance: u64| {
+        harness.clone().account(
+            BytecodeHarness::sender(),
+            HarnessAccount {
+                balance: balance.into(),
+                ..Default::default()
+            },
+        )
+    };
+
+    let cases = [
+        // 1_000 * 100_000
+        (with_balance(&fee_market, 99_999_999), false),
+        (with_balance(&fee_market, 100_000_000), true),
+        // 10 * 100_000 + 1_000 * 131_072
+        (with_balance(&blob, 132_071_999), false),
+        (with_balance(&blob, 132_072_000), true),
+    ];
+
+    for (harness, accepted) in cases {
+        let result = simulate::<F>(&harness.build()?)?;
+        assert_eq!(result.txns.len(), 1);
+        match accepted {
+            true => assert_eq!(result.txns[0].status, TxnStatus::Success),
+            false => {
+                assert_eq!(result.gas_used, 0.into());
+                assert_eq!(
+                    result.txns[0].status,
+                    TxnStatus::Rejected(InvalidTxnReason::InsufficientBalance)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::convert::Infallible;
+
+use evm_arithmetization::all_stark::Table;
+use evm_arithmetization::prover::testing::prove_all_segments;
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::{AllRecursiveCircuits, AllStark, AllStarkConfig};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+/// All the tables, in the order of their proofs.
+const TABLES: [Table; 11] = [
+    Table::Arithmetic,
+    Table::BytePacking,
+    Table::Cpu,
+    Table::Keccak,
+    Table::KeccakSponge,
+    Table::Logic,
+    Table::Memory,
+    Table::MemBefore,
+    Table::MemAfter,
+    Table::RangeCheck,
+    Table::Ec,
+];
+
+/// Test that a prover state built lazily hands over each table circuit chain
+/// once, in the order of the tables, that these chains are the ones rebuilt
+/// on demand, and that they prove segments the root circuit accepts.
+#[ignore]
+#[test]
+fn test_lazy_circuits() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+    let inputs = TestBlockBuilder::new().build()?;
+    let timing = &mut TimingTree::new("prove", log::Level::Debug);
+
+    let proofs = prove_all_segments::<F, C, D>(&all_stark, &config, inputs, 20, timing, None)?;
+    let proof = proofs[0].clone();
+    let degree_bits = proof.degree_bits(&config);
+    let ranges = core::array::from_fn(|i| degree_bits[i]..degree_bits[i] + 1);
+
+    // An error of the callback stops the construction.
+    let mut calls = 0;
+    let result =
+        AllRecursiveCircuits::<F, C, D>::new_lazy(&all_stark, &ranges, &config, |_, _, _| {
+            calls += 1;
+            Err("stop")
+        });
+    assert_eq!(result.err(), Some("stop"));
+    assert_eq!(calls, 1);
+
+    let mut built = vec![];
+    let all_circuits = AllRecursiveCircuits::<F, C, D>::new_lazy(
+        &all_stark,
+        &ranges,
+        &config,
+        |table, degree_bits, table_circuits| {
+            built.push((table, degree_bits, table_circuits));
+            Ok::<_, Infallible>(())
+        },
+    )?;
+    let order = built
+        .iter()
+        .map(|(table, degree_bits, _)| (*table, *degree_bits))
+        .collect::<Vec<_>>();
+    let expected_order = TABLES
+        .iter()
+        .zip(degree_bits)
+        .map(|(table, degree_bits)| (*table, degree_bits))
+        .collect::<Vec<_>>();
+    assert_eq!(order, expected_order);
+
+    let (table, degree_bits, table_circuits) = &built[0];
+    assert_eq!(
+        table_circuits,
+        &AllRecursiveCircuits::create_table_circuits(&all_stark, *table, *degree_bits, &config)
+    );
+
+    let mut built = built.into_iter();
+    let table_circuits = core::array::from_fn(|_| {
+        let (_, _, table_circuits) = built.next().unwrap();
+        (table_circuits, 0)
+    });
+    let (root_proof, _) =
+        all_circuits.prove_segment_after_initial_stark(proof, &table_circuits, None)?;
+    all_circuits.verify_root(root_proof)
+}
@@ -17,7 +17,7 @@ use evm_arithmetization::testing_utils::{
     GLOBAL_EXIT_ROOT_ACCOUNT,
 };
 use evm_arithmetization::verifier::testing::verify_all_proofs;
-use evm_arithmetization::{AllStark, Node, StarkConfig};
+use evm_arithmetization::{AllStark, AllStarkConfig, Node};
 use hex_literal::hex;
 use keccak_hash::keccak;
 use mpt_trie::nibbles::Nibbles;
@@ -36,7 +36,7 @@ fn test_log_opcodes() -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
 
     let beneficiary = hex!("2adc25665018aa1fe0e6bc666dac8fc2697ff9ba");
     let sender = hex!("af1276cbb260bb13deddb4209ae99ae6e497f446");
@@ -240,11 +240,14 @@ fn test_log_opcodes() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: tries_before,
         trie_roots_after,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
@@ -0,0 +1,201 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use evm_arithmetization::all_stark::Table;
+use evm_arithmetization::fixed_recursive_verifier::ProverOutputData;
+use evm_arithmetization::generation::TrimmedGenerationInputs;
+use evm_arithmetization::proof::AllProof;
+use evm_arithmetization::prover::{prove, GenerationSegmentData, SegmentDataIterator};
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::verifier::testing::verify_all_proofs;
+use evm_arithmetization::{AllRecursiveCircuits, AllStark, AllStarkConfig};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+/// Small segments, so that some of them have no Keccak, logic nor byte
+/// packing rows.
+const MAX_CPU_LEN_LOG: usize = 9;
+
+/// All the tables, in the order of their proofs.
+const TABLES: [Table; 11] = [
+    Table::Arithmetic,
+    Table::BytePacking,
+    Table::Cpu,
+    Table::Keccak,
+    Table::KeccakSponge,
+    Table::Logic,
+    Table::Memory,
+    Table::MemBefore,
+    Table::MemAfter,
+    Table::RangeCheck,
+    Table::Ec,
+];
+
+/// The tables which a segment proof may omit.
+const OPTIONAL_TABLES: [Table; 4] = [
+    Table::BytePacking,
+    Table::Keccak,
+    Table::KeccakSponge,
+    Table::Logic,
+];
+
+/// The segments of an empty block, proven natively up to the first one for
+/// which `stop` holds, along with the data to prove them again.
+struct ProvenSegments {
+    inputs: TrimmedGenerationInputs,
+    segment_data: Vec<GenerationSegmentData>,
+    proofs: Vec<AllProof<F, C, D>>,
+}
+
+fn prove_segments_until(
+    all_stark: &AllStark<F, D>,
+    config: &AllStarkConfig,
+    stop: impl Fn(&AllProof<F, C, D>) -> bool,
+) -> anyhow::Result<ProvenSegments> {
+    let inputs = TestBlockBuilder::new().build()?;
+    let timing = &mut TimingTree::new("prove", log::Level::Debug);
+
+    let mut segments = ProvenSegments {
+        inputs: inputs.trim(),
+        segment_data: vec![],
+        proofs: vec![],
+    };
+    for segment_run in SegmentDataIterator::<F>::new(&inputs, Some(MAX_CPU_LEN_LOG)) {
+        let (_, segment_data) = segment_run?;
+        segments.segment_data.push(segment_data.clone());
+        let proof = prove(
+            all_stark,
+            config,
+            segments.inputs.clone(),
+            &mut segment_data.clone(),
+            timing,
+            None,
+        )?;
+        let done = stop(&proof);
+        segments.proofs.push(proof);
+        if done {
+            return Ok(segments);
+        }
+    }
+
+    anyhow::bail!("No segment of the block satisfies the condition")
+}
+
+/// Returns the recursive circuits for the sizes of the tables of `proofs`.
+fn circuits_for(
+    all_stark: &AllStark<F, D>,
+    config: &AllStarkConfig,
+    proofs: &[AllProof<F, C, D>],
+) -> AllRecursiveCircuits<F, C, D> {
+    let degree_bits = proofs
+        .iter()
+        .map(|proof| proof.degree_bits(config))
+        .collect::<Vec<_>>();
+    let ranges = core::array::from_fn(|i| {
+        let min = degree_bits.iter().map(|bits| bits[i]).min().unwrap();
+        let max = degree_bits.iter().map(|bits| bits[i]).max().unwrap();
+        min..max + 1
+    });
+    AllRecursiveCircuits::new(all_stark, &ranges, config)
+}
+
+/// Test that a segment without Keccak, logic nor byte packing rows, and whose
+/// proof hence omits these tables, is verified natively, by the root circuit
+/// and once aggregated with the previous segment.
+#[ignore]
+#[test]
+fn test_segment_without_optional_tables() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+
+    let segments = prove_segments_until(&all_stark, &config, |proof| {
+        OPTIONAL_TABLES
+            .iter()
+            .all(|table| !proof.table_in_use[**table])
+    })?;
+    // The first segment hashes the initial tries, and hence uses the Keccak
+    // tables.
+    assert!(segments.proofs.len() > 1);
+    verify_all_proofs(&all_stark, &segments.proofs, &config)?;
+
+    let all_circuits = circuits_for(
+        &all_stark,
+        &config,
+        &segments.proofs[segments.proofs.len() - 2..],
+    );
+    let timing = &mut TimingTree::new("prove root", log::Level::Debug);
+    let [previous, segment]: [ProverOutputData<F, C, D>; 2] = segments.segment_data
+        [segments.segment_data.len() - 2..]
+        .iter()
+        .map(|segment_data| {
+            all_circuits.prove_segment(
+                &all_stark,
+                &config,
+                segments.inputs.clone(),
+                &mut segment_data.clone(),
+                timing,
+                None,
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .try_into()
+        .unwrap();
+    all_circuits.verify_root(segment.proof_with_pis.clone())?;
+
+    let agg_proof = all_circuits.prove_segment_aggregation(false, &previous, false, &segment)?;
+    all_circuits.verify_segment_aggregation(&agg_proof.proof_with_pis)
+}
+
+/// Test that a segment proof marking a table as not in use while the other
+/// tables still look rows up in it is rejected, natively and by the root
+/// circuit.
+#[ignore]
+#[test]
+fn test_unused_table_with_ctl_contributions() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+
+    let segments = prove_segments_until(&all_stark, &config, |proof| {
+        proof.table_in_use[*Table::Logic]
+    })?;
+    let mut proofs = segments.proofs;
+    let proof = proofs.last_mut().unwrap();
+    proof.table_in_use[*Table::Logic] = false;
+    let proof = proof.clone();
+
+    assert!(verify_all_proofs(&all_stark, &proofs, &config).is_err());
+
+    let all_circuits = circuits_for(&all_stark, &config, std::slice::from_ref(&proof));
+    let degree_bits = proof.degree_bits(&config);
+    let table_circuits = core::array::from_fn(|i| {
+        let circuits = AllRecursiveCircuits::<F, C, D>::create_table_circuits(
+            &all_stark,
+            TABLES[i],
+            degree_bits[i],
+            &config,
+        );
+        (circuits, 0)
+    });
+
+    // An unsatisfiable witness either makes the prover fail or panic.
+    let rejected = match catch_unwind(AssertUnwindSafe(|| {
+        all_circuits.prove_segment_after_initial_stark(proof, &table_circuits, None)
+    })) {
+        Ok(Ok((root_proof, _))) => all_circuits.verify_root(root_proof).is_err(),
+        Ok(Err(_)) | Err(_) => true,
+    };
+    assert!(
+        rejected,
+        "The root circuit accepted an unused table with CTL contributions"
+    );
+
+    Ok(())
+}
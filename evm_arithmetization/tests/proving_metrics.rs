@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use evm_arithmetization::all_stark::Table;
+use evm_arithmetization::prover::testing::prove_all_segments_with_metrics;
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::verifier::testing::verify_all_proofs;
+use evm_arithmetization::{AllStark, AllStarkConfig};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+/// Test that the metrics collected while proving all the segments of a block
+/// account for each segment, and for the traces of its tables.
+#[test]
+fn test_proving_metrics() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+    let inputs = TestBlockBuilder::new().build()?;
+    let timing = &mut TimingTree::new("prove", log::Level::Debug);
+
+    let (proofs, metrics) =
+        prove_all_segments_with_metrics::<F, C, D>(&all_stark, &config, inputs, 20, timing, None)?;
+    verify_all_proofs(&all_stark, &proofs, &config)?;
+
+    assert_eq!(metrics.segments, proofs.len());
+
+    // The CPU trace is padded to a power of two, which is the degree of its
+    // proof.
+    let cpu_rows = proofs
+        .iter()
+        .map(|proof| 1 << proof.degree_bits(&config)[Table::Cpu as usize])
+        .sum::<usize>();
+    assert_eq!(metrics.table_rows[Table::Cpu as usize], cpu_rows);
+
+    assert!(metrics.total_commit_time() > Duration::ZERO);
+    assert!(metrics.total_prove_time() > Duration::ZERO);
+    // The table proofs aren't recursively shrunk.
+    assert_eq!(metrics.recursion_time, Duration::ZERO);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use ethereum_types::{Address, BigEndianHash, H256, U256};
+use evm_arithmetization::generation::mpt::AccountRlp;
+use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use evm_arithmetization::prover::testing::simulate_execution;
+use evm_arithmetization::simulation::{AccountDiff, TxnStatus};
+use evm_arithmetization::testing_utils::{
+    eth_to_wei, init_logger, preinitialized_state_and_storage_tries,
+};
+use evm_arithmetization::{simulate, Node};
+use hex_literal::hex;
+use keccak_hash::keccak;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use plonky2::field::goldilocks_field::GoldilocksField;
+
+type F = GoldilocksField;
+
+/// Simulate a simple token transfer to a new address, without providing the
+/// final values of the execution, and check that the computed ones are
+/// accepted by the kernel.
+#[test]
+fn test_simulate_simple_transfer() -> anyhow::Result<()> {
+    init_logger();
+
+    let beneficiary = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
+    let sender = hex!("2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+    let to = hex!("a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0");
+
+    let sender_state_key = keccak(sender);
+    let sender_nibbles = Nibbles::from_bytes_be(sender_state_key.as_bytes()).unwrap();
+
+    let sender_account_before = AccountRlp {
+        nonce: 5.into(),
+        balance: eth_to_wei(100_000.into()),
+        storage_root: HashedPartialTrie::from(Node::Empty).hash(),
+        code_hash: keccak([]),
+    };
+
+    let (mut state_trie_before, storage_tries) = preinitialized_state_and_storage_tries()?;
+    state_trie_before.insert(sender_nibbles, rlp::encode(&sender_account_before).to_vec())?;
+
+    // Generated using a little py-evm script.
+    let txn = hex!("f861050a8255f094a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0648242421ba02c89eb757d9deeb1f5b3859a9d4d679951ef610ac47ad4608dc142beb1b7e313a05af7e9fbab825455d36c36c7f4cfcafbeafa9a77bdff936b52afb36d4fe4bcdd");
+    let value = U256::from(100u32);
+    let gas_used = 21_000 + 2 * 16;
+
+    let block_metadata = BlockMetadata {
+        block_beneficiary: Address::from(beneficiary),
+        block_timestamp: 0x03e8.into(),
+        block_number: 1.into(),
+        block_difficulty: 0x020000.into(),
+        block_random: H256::from_uint(&0x020000.into()),
+        block_gaslimit: 0xff112233u32.into(),
+        block_chain_id: 1.into(),
+        block_base_fee: 0xa.into(),
+        block_gas_used: gas_used.into(),
+        ..Default::default()
+    };
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    // The final values are left to their defaults.
+    let mut inputs = GenerationInputs {
+        signed_txns: vec![txn.to_vec()],
+        withdrawals: vec![],
+        block_rewards: vec![],
+        global_exit_roots: vec![],
+        burn_addr: None,
+        tries: TrieInputs {
+            state_trie: state_trie_before,
+            transactions_trie: HashedPartialTrie::from(Node::Empty),
+            receipts_trie: HashedPartialTrie::from(Node::Empty),
+            storage_tries,
+        },
+        trie_roots_after: TrieRoots::default(),
+        contract_code,
+        checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: vec![H256::default(); 256],
+            cur_hash: H256::default(),
+        },
+    };
+
+    let result = simulate::<F>(&inputs)?;
+    assert_eq!(result.gas_used, gas_used.into());
+    assert_eq!(result.gas_used_after, gas_used.into());
+
+    assert_eq!(result.txns.len(), 1);
+    let txn_result = &result.txns[0];
+    assert_eq!(txn_result.txn_hash, keccak(txn));
+    assert_eq!(txn_result.status, TxnStatus::Success);
+    assert_eq!(txn_result.gas_used, gas_used.into());
+    assert!(txn_result.logs.is_empty());
+
+    assert_eq!(
+        result.state_diff[&sender_state_key],
+        AccountDiff::Modified {
+            before: sender_account_before,
+            after: AccountRlp {
+                balance: sender_account_before.balance - value - gas_used * 10,
+                nonce: sender_account_before.nonce + 1,
+                ..sender_account_before
+            },
+        }
+    );
+    assert_eq!(
+        result.state_diff[&keccak(to)],
+        AccountDiff::Created(AccountRlp {
+            balance: value,
+            ..AccountRlp::default()
+        })
+    );
+
+    // The computed final values pass the checks of the kernel.
+    inputs.trie_roots_after = result.trie_roots_after;
+    inputs.gas_used_after = result.gas_used_after;
+    simulate_execution::<F>(inputs)
+}
@@ -0,0 +1,84 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use evm_arithmetization::all_stark::Table;
+use evm_arithmetization::prover::testing::prove_all_segments;
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::verifier::testing::verify_all_proofs;
+use evm_arithmetization::{AllRecursiveCircuits, AllStark, AllStarkConfig};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+
+/// Proves some tables of an empty block with their own FRI parameters.
+fn non_uniform_config() -> AllStarkConfig {
+    AllStarkConfig::standard_fast_config()
+        .with_fri_params(Table::Logic, 2, 2)
+        .with_fri_params(Table::KeccakSponge, 2, 3)
+}
+
+/// Test that proofs generated with per-table FRI parameters are verified with
+/// these parameters, and only with them.
+#[test]
+fn test_per_table_fri_params() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = non_uniform_config();
+    let inputs = TestBlockBuilder::new().build()?;
+    let timing = &mut TimingTree::new("prove", log::Level::Debug);
+
+    let proofs = prove_all_segments::<F, C, D>(&all_stark, &config, inputs, 20, timing, None)?;
+    verify_all_proofs(&all_stark, &proofs, &config)?;
+
+    // The proofs don't have the shape of those of the uniform configuration,
+    // which either makes the verifier fail or panic.
+    let uniform = AllStarkConfig::standard_fast_config();
+    let rejected = match catch_unwind(AssertUnwindSafe(|| {
+        verify_all_proofs(&all_stark, &proofs, &uniform)
+    })) {
+        Ok(Ok(())) => false,
+        Ok(Err(_)) | Err(_) => true,
+    };
+    assert!(
+        rejected,
+        "The proofs were verified with the wrong configuration"
+    );
+
+    Ok(())
+}
+
+/// Test that the recursive circuits verify proofs generated with per-table
+/// FRI parameters.
+#[ignore]
+#[test]
+fn test_per_table_fri_params_recursion() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = non_uniform_config();
+    let inputs = TestBlockBuilder::new().build()?;
+    let timing = &mut TimingTree::new("prove", log::Level::Debug);
+
+    let proofs =
+        prove_all_segments::<F, C, D>(&all_stark, &config, inputs.clone(), 20, timing, None)?;
+    let degree_bits = proofs
+        .iter()
+        .map(|proof| proof.degree_bits(&config))
+        .collect::<Vec<_>>();
+    let ranges = core::array::from_fn(|i| {
+        let min = degree_bits.iter().map(|bits| bits[i]).min().unwrap();
+        let max = degree_bits.iter().map(|bits| bits[i]).max().unwrap();
+        min..max + 1
+    });
+    let all_circuits = AllRecursiveCircuits::<F, C, D>::new(&all_stark, &ranges, &config);
+
+    let segment_proofs =
+        all_circuits.prove_all_segments(&all_stark, &config, inputs, 20, timing, None)?;
+    segment_proofs
+        .into_iter()
+        .try_for_each(|segment| all_circuits.verify_root(segment.proof_with_pis))
+}
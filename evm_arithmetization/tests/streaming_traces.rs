@@ -0,0 +1,89 @@
+use evm_arithmetization::all_stark::Table;
+use evm_arithmetization::generation::{generate_traces, generate_traces_streaming};
+use evm_arithmetization::prover::SegmentDataIterator;
+use evm_arithmetization::testing_utils::{init_logger, TestBlockBuilder};
+use evm_arithmetization::{AllStark, AllStarkConfig};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::util::timing::TimingTree;
+
+type F = GoldilocksField;
+const D: usize = 2;
+
+/// All the tables, in the order of their proofs.
+const TABLES: [Table; 11] = [
+    Table::Arithmetic,
+    Table::BytePacking,
+    Table::Cpu,
+    Table::Keccak,
+    Table::KeccakSponge,
+    Table::Logic,
+    Table::Memory,
+    Table::MemBefore,
+    Table::MemAfter,
+    Table::RangeCheck,
+    Table::Ec,
+];
+
+/// Test that the trace of each table is handed over once, in the order of the
+/// tables, and that an error of the sink stops the generation of the
+/// remaining traces.
+#[test]
+fn test_streamed_traces() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+    let inputs = TestBlockBuilder::new().build()?;
+    let (_, segment_data) = SegmentDataIterator::<F>::new(&inputs, Some(20))
+        .next()
+        .expect("a block has at least one segment")?;
+    let inputs = inputs.trim();
+    let timing = &mut TimingTree::default();
+
+    let (tables, public_values) = generate_traces(
+        &all_stark,
+        &inputs,
+        &config,
+        &mut segment_data.clone(),
+        timing,
+    )?;
+
+    let mut streamed = vec![];
+    let (streamed_public_values, _) = generate_traces_streaming(
+        &all_stark,
+        &inputs,
+        &config,
+        &mut segment_data.clone(),
+        timing,
+        |table, trace, _| {
+            streamed.push((table, trace));
+            Ok(())
+        },
+    )?;
+    assert_eq!(streamed_public_values, public_values);
+    assert_eq!(
+        streamed.iter().map(|(table, _)| *table).collect::<Vec<_>>(),
+        TABLES
+    );
+    for ((_, streamed_trace), trace) in streamed.iter().zip(&tables) {
+        assert_eq!(streamed_trace, trace);
+    }
+
+    let mut sunk = vec![];
+    let res = generate_traces_streaming(
+        &all_stark,
+        &inputs,
+        &config,
+        &mut segment_data.clone(),
+        timing,
+        |table, _, _| {
+            sunk.push(table);
+            anyhow::ensure!(table != Table::Cpu, "no room for the CPU trace");
+            Ok(())
+        },
+    );
+    assert!(res.is_err());
+    assert_eq!(sunk, TABLES[..3]);
+
+    Ok(())
+}
@@ -1,22 +1,27 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
 use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
-use ethereum_types::{Address, BigEndianHash, H256};
+use ethereum_types::H256;
 use evm_arithmetization::fixed_recursive_verifier::{
-    extract_block_public_values, extract_two_to_one_block_hash,
-};
-use evm_arithmetization::generation::{GenerationInputs, TrieInputs};
-use evm_arithmetization::proof::{BlockMetadata, PublicValues, TrieRoots};
-use evm_arithmetization::testing_utils::{
-    beacon_roots_account_nibbles, beacon_roots_contract_from_storage, ger_account_nibbles,
-    preinitialized_state_and_storage_tries, update_beacon_roots_account_storage,
-    GLOBAL_EXIT_ROOT_ACCOUNT,
+    add_virtual_block_proof, extract_block_public_values, extract_chain_segment_public_values,
+    extract_two_to_one_block_hash, verify_block_proof_circuit, BlockVerificationError,
+    ExpectedBlockValues, VerifierArtifact,
 };
-use evm_arithmetization::{AllRecursiveCircuits, AllStark, Node, StarkConfig};
-use hex_literal::hex;
-use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
+use evm_arithmetization::generation::GenerationInputs;
+use evm_arithmetization::proof::{BlockHashes, PublicValues};
+use evm_arithmetization::prover::testing::prove_all_segments;
+use evm_arithmetization::testing_utils::TestBlockBuilder;
+use evm_arithmetization::{AllRecursiveCircuits, AllStark, AllStarkConfig};
+use keccak_hash::keccak;
 use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::Field;
 use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{Hasher, PoseidonGoldilocksConfig};
 use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
 use plonky2::util::timing::TimingTree;
 
 type F = GoldilocksField;
@@ -27,90 +32,49 @@ fn init_logger() {
     let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
 }
 
-/// Get `GenerationInputs` for a dummy payload, where the block has the given
-/// timestamp.
-fn dummy_payload(timestamp: u64, is_first_payload: bool) -> anyhow::Result<GenerationInputs> {
-    let beneficiary = hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
-
-    let block_metadata = BlockMetadata {
-        block_beneficiary: Address::from(beneficiary),
-        block_timestamp: timestamp.into(),
-        block_number: 1.into(),
-        block_difficulty: 0x020000.into(),
-        block_random: H256::from_uint(&0x020000.into()),
-        block_gaslimit: 0xff112233u32.into(),
-        block_chain_id: 1.into(),
-        block_base_fee: 0xa.into(),
-        ..Default::default()
-    };
-
-    let (mut state_trie_before, mut storage_tries) = preinitialized_state_and_storage_tries()?;
-    let checkpoint_state_trie_root = state_trie_before.hash();
-    let mut beacon_roots_account_storage = storage_tries[0].1.clone();
-
-    update_beacon_roots_account_storage(
-        &mut beacon_roots_account_storage,
-        block_metadata.block_timestamp,
-        block_metadata.parent_beacon_block_root,
-    )?;
-    let updated_beacon_roots_account =
-        beacon_roots_contract_from_storage(&beacon_roots_account_storage);
-
-    if !is_first_payload {
-        // This isn't the first dummy payload being processed. We need to update the
-        // initial state trie to account for the update on the beacon roots contract.
-        state_trie_before.insert(
-            beacon_roots_account_nibbles(),
-            rlp::encode(&updated_beacon_roots_account).to_vec(),
-        )?;
-        storage_tries[0].1 = beacon_roots_account_storage;
-    }
-
-    let tries_before = TrieInputs {
-        state_trie: state_trie_before,
-        storage_tries,
-        ..Default::default()
-    };
-
-    let expected_state_trie_after: HashedPartialTrie = {
-        let mut state_trie_after = HashedPartialTrie::from(Node::Empty);
-        state_trie_after.insert(
-            beacon_roots_account_nibbles(),
-            rlp::encode(&updated_beacon_roots_account).to_vec(),
-        )?;
-        state_trie_after.insert(
-            ger_account_nibbles(),
-            rlp::encode(&GLOBAL_EXIT_ROOT_ACCOUNT).to_vec(),
-        )?;
-
-        state_trie_after
-    };
-
-    let trie_roots_after = TrieRoots {
-        state_root: expected_state_trie_after.hash(),
-        transactions_root: tries_before.transactions_trie.hash(),
-        receipts_root: tries_before.receipts_trie.hash(),
-    };
+/// A distinct, non-zero hash for the test block with the given number.
+fn test_block_hash(block_number: u64) -> H256 {
+    keccak(block_number.to_be_bytes())
+}
 
-    let inputs = GenerationInputs {
-        tries: tries_before.clone(),
-        trie_roots_after,
-        checkpoint_state_trie_root,
-        block_metadata,
-        ..Default::default()
-    };
+/// The `BlockHashes` of a child of the block with the given hashes: its
+/// ancestors are the ones of the parent shifted by one to the left, followed
+/// by the parent itself.
+fn child_block_hashes(parent: &BlockHashes, cur_hash: H256) -> BlockHashes {
+    let mut prev_hashes = parent.prev_hashes[1..].to_vec();
+    prev_hashes.push(parent.cur_hash);
+    BlockHashes {
+        prev_hashes,
+        cur_hash,
+    }
+}
 
-    Ok(inputs)
+/// Get `GenerationInputs` for a dummy payload, where the block has the given
+/// timestamp, number and hashes.
+fn dummy_payload(
+    timestamp: u64,
+    block_number: u64,
+    block_hashes: BlockHashes,
+    is_first_payload: bool,
+) -> anyhow::Result<GenerationInputs> {
+    TestBlockBuilder::new()
+        .timestamp(timestamp)
+        .block_number(block_number)
+        .block_hashes(block_hashes)
+        .beacon_roots_update_applied(!is_first_payload)
+        .build()
 }
 
 fn get_test_block_proof(
     timestamp: u64,
+    block_number: u64,
+    block_hashes: BlockHashes,
     all_circuits: &AllRecursiveCircuits<GoldilocksField, PoseidonGoldilocksConfig, 2>,
     all_stark: &AllStark<GoldilocksField, 2>,
-    config: &StarkConfig,
+    config: &AllStarkConfig,
 ) -> anyhow::Result<ProofWithPublicInputs<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
-    let dummy0 = dummy_payload(timestamp, true)?;
-    let dummy1 = dummy_payload(timestamp, false)?;
+    let dummy0 = dummy_payload(timestamp, block_number, block_hashes.clone(), true)?;
+    let dummy1 = dummy_payload(timestamp, block_number, block_hashes, false)?;
 
     let timing = &mut TimingTree::new(&format!("Blockproof {timestamp}"), log::Level::Info);
     let dummy0_proof0 =
@@ -139,7 +103,7 @@ fn get_test_block_proof(
     all_circuits.verify_txn_aggregation(&agg_proof0)?;
 
     // Test retrieved public values from the proof public inputs.
-    let retrieved_public_values0 = PublicValues::from_public_inputs(&agg_proof0.public_inputs);
+    let retrieved_public_values0 = PublicValues::from_public_inputs(&agg_proof0.public_inputs)?;
     assert_eq!(retrieved_public_values0, pv0);
     assert_eq!(
         pv0.trie_roots_before.state_root,
@@ -152,7 +116,7 @@ fn get_test_block_proof(
         pv0.clone(),
     )?;
 
-    let pv_block = PublicValues::from_public_inputs(&block_proof0.public_inputs);
+    let pv_block = PublicValues::from_public_inputs(&block_proof0.public_inputs)?;
     assert_eq!(block_public_values, pv_block.into());
 
     Ok(block_proof0)
@@ -165,11 +129,11 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
     let some_timestamps = [127, 42, 65, 43];
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
     let all_circuits = AllRecursiveCircuits::<F, C, D>::new(
         &all_stark,
         &[
-            16..17,
+            9..17,
             9..15,
             12..18,
             14..15,
@@ -178,13 +142,24 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
             17..20,
             16..17,
             7..8,
+            16..17,
+            4..5,
         ],
         &config,
     );
 
     let unrelated_block_proofs = some_timestamps
         .iter()
-        .map(|&ts| get_test_block_proof(ts, &all_circuits, &all_stark, &config))
+        .map(|&ts| {
+            get_test_block_proof(
+                ts,
+                1,
+                BlockHashes::default(),
+                &all_circuits,
+                &all_stark,
+                &config,
+            )
+        })
         .collect::<anyhow::Result<Vec<ProofWithPublicInputs<F, C, D>>>>()?;
 
     unrelated_block_proofs
@@ -193,6 +168,42 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
 
     let bp = unrelated_block_proofs;
 
+    {
+        // Check a block proof against the values it is expected to attest to.
+        let pv =
+            PublicValues::from_public_inputs(extract_block_public_values(&bp[0].public_inputs))?;
+        let expected = ExpectedBlockValues {
+            trie_roots_after: pv.trie_roots_after.clone(),
+            block_number: 1.into(),
+            chain_id: pv.block_metadata.block_chain_id,
+        };
+        let checked = all_circuits.verify_block_with_expected_values(&bp[0], &expected)?;
+        assert_eq!(checked, pv);
+
+        let wrong_number = ExpectedBlockValues {
+            block_number: 2.into(),
+            ..expected.clone()
+        };
+        assert_eq!(
+            all_circuits
+                .verify_block_with_expected_values(&bp[0], &wrong_number)
+                .unwrap_err(),
+            BlockVerificationError::BlockNumberMismatch {
+                expected: 2.into(),
+                actual: 1.into(),
+            }
+        );
+
+        // A proof whose public inputs were tampered with is rejected, even if
+        // they match the expected values.
+        let mut tampered = bp[0].clone();
+        tampered.public_inputs[0] += F::ONE;
+        assert!(matches!(
+            all_circuits.verify_block_with_expected_values(&tampered, &expected),
+            Err(BlockVerificationError::InvalidProof(_))
+        ));
+    }
+
     {
         // Aggregate the same proof twice
         let aggproof_42_42 = all_circuits.prove_two_to_one_block(&bp[0], false, &bp[0], false)?;
@@ -218,6 +229,54 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
             all_circuits.prove_two_to_one_block(&aggproof01, true, &aggproof23, true)?;
         all_circuits.verify_two_to_one_block(&aggproof0123)?;
 
+        {
+            // Verify with the exported verifier data only.
+            let gate_serializer = DefaultGateSerializer;
+            let artifact = all_circuits.export_verifier();
+            let bytes = artifact.to_bytes(&gate_serializer).unwrap();
+            let artifact =
+                VerifierArtifact::<F, C, D>::from_bytes(&bytes, &gate_serializer).unwrap();
+            artifact.verify_block(&bp[0])?;
+            artifact.verify_two_to_one_block(&aggproof0123)?;
+
+            // Verify a block proof within an external circuit, exposing its
+            // block number.
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let block_proof = add_virtual_block_proof(&mut builder, &artifact.block);
+            verify_block_proof_circuit(&mut builder, &block_proof, &artifact.block);
+            builder.register_public_input(block_proof.public_values.block_metadata.block_number);
+            let circuit = builder.build::<C>();
+
+            let mut witness = PartialWitness::new();
+            block_proof.set_proof(&mut witness, &bp[0]);
+            let proof = circuit.prove(witness)?;
+            let block_number = PublicValues::from_public_inputs(&bp[0].public_inputs)?
+                .block_metadata
+                .block_number;
+            assert_eq!(
+                proof.public_inputs,
+                [F::from_canonical_u64(block_number.as_u64())]
+            );
+            circuit.verify(proof)?;
+
+            // A block proof whose public inputs were tampered with is rejected.
+            let mut tampered = bp[0].clone();
+            tampered.public_inputs[0] += F::ONE;
+            let rejected = match catch_unwind(AssertUnwindSafe(|| {
+                let mut witness = PartialWitness::new();
+                block_proof.set_proof(&mut witness, &tampered);
+                circuit.prove(witness)
+            })) {
+                Ok(Ok(proof)) => circuit.verify(proof).is_err(),
+                Ok(Err(_)) | Err(_) => true,
+            };
+            assert!(
+                rejected,
+                "The external circuit accepted a tampered block proof"
+            );
+        }
+
         {
             // Compute Merkle root from public inputs of block proofs.
             // Leaves
@@ -245,6 +304,53 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
         }
     }
 
+    {
+        // Chain segment: block 2 is a child of block 1, which is a child of the
+        // genesis block.
+        let genesis = BlockHashes {
+            cur_hash: test_block_hash(0),
+            ..Default::default()
+        };
+        let block1 = child_block_hashes(&genesis, test_block_hash(1));
+        let block2 = child_block_hashes(&block1, test_block_hash(2));
+        let bp1 = get_test_block_proof(128, 1, block1.clone(), &all_circuits, &all_stark, &config)?;
+        let bp2 = get_test_block_proof(129, 2, block2, &all_circuits, &all_stark, &config)?;
+
+        let segment = all_circuits.prove_chain_segment(&bp1, false, &bp2, false)?;
+        all_circuits.verify_two_to_one_block(&segment)?;
+
+        let segment_values = extract_chain_segment_public_values(&segment.public_inputs);
+        assert_eq!(segment_values.is_chain, F::ONE);
+        assert_eq!(segment_values.first_block_number, F::ONE);
+        assert_eq!(segment_values.last_block_number, F::TWO);
+
+        // A block 2 whose parent isn't block 1, but another child of the
+        // genesis block, doesn't extend the chain segment of block 1.
+        let sibling1 = child_block_hashes(&genesis, keccak(b"sibling of block 1"));
+        let orphan2 = get_test_block_proof(
+            129,
+            2,
+            child_block_hashes(&sibling1, test_block_hash(2)),
+            &all_circuits,
+            &all_stark,
+            &config,
+        )?;
+        // Neither does a child of block 1 which doesn't directly follow it.
+        let block3 = child_block_hashes(&block1, test_block_hash(3));
+        let bp3 = get_test_block_proof(130, 3, block3, &all_circuits, &all_stark, &config)?;
+
+        for rhs in [&orphan2, &bp3] {
+            // An unsatisfiable witness either makes the prover fail or panic.
+            let rejected = match catch_unwind(AssertUnwindSafe(|| {
+                all_circuits.prove_chain_segment(&bp1, false, rhs, false)
+            })) {
+                Ok(Ok(segment)) => all_circuits.verify_two_to_one_block(&segment).is_err(),
+                Ok(Err(_)) | Err(_) => true,
+            };
+            assert!(rejected, "A chain segment was built from unchained blocks");
+        }
+    }
+
     {
         // Foldleft
         //
@@ -291,3 +397,78 @@ fn test_two_to_one_block_aggregation() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[ignore]
+#[test]
+fn test_segment_aggregation_rejects_non_contiguous_segments() -> anyhow::Result<()> {
+    init_logger();
+    // Small segments, so that the block is split into several of them.
+    let max_cpu_len_log = 14;
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = AllStarkConfig::standard_fast_config();
+    let inputs = dummy_payload(42, 1, BlockHashes::default(), true)?;
+
+    // Build the recursive circuits for the table sizes of the segments.
+    let timing = &mut TimingTree::new("Segments", log::Level::Info);
+    let stark_proofs = prove_all_segments::<F, C, D>(
+        &all_stark,
+        &config,
+        inputs.clone(),
+        max_cpu_len_log,
+        timing,
+        None,
+    )?;
+    assert!(stark_proofs.len() >= 3);
+    let degree_bits = stark_proofs
+        .iter()
+        .map(|proof| proof.degree_bits(&config))
+        .collect::<Vec<_>>();
+    let ranges = core::array::from_fn(|i| {
+        let min = degree_bits.iter().map(|bits| bits[i]).min().unwrap();
+        let max = degree_bits.iter().map(|bits| bits[i]).max().unwrap();
+        min..max + 1
+    });
+    let all_circuits = AllRecursiveCircuits::<F, C, D>::new(&all_stark, &ranges, &config);
+
+    let segment_proofs = all_circuits.prove_all_segments(
+        &all_stark,
+        &config,
+        inputs,
+        max_cpu_len_log,
+        timing,
+        None,
+    )?;
+
+    // Contiguous segments are aggregated.
+    let agg_proof = all_circuits.prove_segment_aggregation(
+        false,
+        &segment_proofs[0],
+        false,
+        &segment_proofs[1],
+    )?;
+    all_circuits.verify_segment_aggregation(&agg_proof.proof_with_pis)?;
+
+    // Segments in the wrong order, or with a gap between them, are not: the
+    // registers and memory after the left segment don't match the ones before
+    // the right one.
+    for (lhs, rhs) in [(1, 0), (0, 2)] {
+        // An unsatisfiable witness either makes the prover fail or panic.
+        let rejected = match catch_unwind(AssertUnwindSafe(|| {
+            all_circuits.prove_segment_aggregation(
+                false,
+                &segment_proofs[lhs],
+                false,
+                &segment_proofs[rhs],
+            )
+        })) {
+            Ok(Ok(agg_proof)) => all_circuits
+                .verify_segment_aggregation(&agg_proof.proof_with_pis)
+                .is_err(),
+            Ok(Err(_)) | Err(_) => true,
+        };
+        assert!(rejected, "Segments {lhs} and {rhs} were aggregated");
+    }
+
+    Ok(())
+}
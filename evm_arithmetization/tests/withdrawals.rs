@@ -12,7 +12,7 @@ use evm_arithmetization::testing_utils::{
     GLOBAL_EXIT_ROOT_ACCOUNT,
 };
 use evm_arithmetization::verifier::testing::verify_all_proofs;
-use evm_arithmetization::{AllStark, Node, StarkConfig};
+use evm_arithmetization::{AllStark, AllStarkConfig, Node};
 use keccak_hash::keccak;
 use mpt_trie::nibbles::Nibbles;
 use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie};
@@ -31,7 +31,7 @@ fn test_withdrawals() -> anyhow::Result<()> {
     init_logger();
 
     let all_stark = AllStark::<F, D>::default();
-    let config = StarkConfig::standard_fast_config();
+    let config = AllStarkConfig::standard_fast_config();
 
     let block_metadata = BlockMetadata {
         block_timestamp: 1.into(),
@@ -87,7 +87,9 @@ fn test_withdrawals() -> anyhow::Result<()> {
     let inputs = GenerationInputs {
         signed_txns: vec![],
         withdrawals,
+        block_rewards: vec![],
         global_exit_roots: vec![],
+        burn_addr: None,
         tries: TrieInputs {
             state_trie: state_trie_before,
             transactions_trie,
@@ -97,6 +99,7 @@ fn test_withdrawals() -> anyhow::Result<()> {
         trie_roots_after,
         contract_code,
         checkpoint_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        jumpdest_tables: HashMap::new(),
         block_metadata,
         txn_number_before: 0.into(),
         gas_used_before: 0.into(),
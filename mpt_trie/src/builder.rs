@@ -70,6 +70,12 @@ impl<T: PartialTrie> PartialTrieBuilder<T> {
         construct_partial_trie(self.root, &self.nodes)
     }
 
+    /// Builds the partial trie rooted at another node of the builder, e.g. a
+    /// storage trie whose nodes were inserted along with the state trie.
+    pub fn build_subtrie(&self, root: H256) -> T {
+        construct_partial_trie(root, &self.nodes)
+    }
+
     fn insert_short_node_variants(&mut self, bytes: Vec<Vec<u8>>) {
         let is_leaf = is_leaf_node(&bytes);
         let mut nibbles = Nibbles::from_bytes_be(&bytes[0][..]).unwrap();
@@ -20,13 +20,18 @@ pub mod builder;
 pub mod nibbles;
 pub mod partial_trie;
 pub mod special_query;
+pub mod trie_diff;
 mod trie_hashing;
 pub mod trie_ops;
+pub mod trie_proofs;
 pub mod trie_subsets;
+pub mod trie_witness;
 pub mod utils;
 
 #[cfg(feature = "trie_debug")]
 pub mod debug_tools;
 
+pub use trie_diff::diff;
+
 #[cfg(test)]
 pub(crate) mod testing_utils;
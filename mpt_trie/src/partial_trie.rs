@@ -2,7 +2,7 @@
 
 use std::{
     fmt::Debug,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, RangeBounds},
     sync::Arc,
 };
 
@@ -104,6 +104,16 @@ pub trait PartialTrie:
     /// `Leaf` and `Hash` node.
     fn values(&self) -> impl Iterator<Item = ValOrHash>;
 
+    /// Returns an iterator over the key/value pairs of the trie whose keys lie
+    /// in `range`, in lexicographic order.
+    ///
+    /// Keys are compared nibble by nibble, a key coming before the keys it is a
+    /// prefix of, unlike the [`Ord`] of [`Nibbles`]. If the iterator reaches a
+    /// `Hash` node which may hide keys in the range, it returns it as its last
+    /// item, at the path of the node.
+    fn range(&self, range: impl RangeBounds<Nibbles>)
+        -> impl Iterator<Item = (Nibbles, ValOrHash)>;
+
     /// Returns `true` if the trie contains an element with the given key.
     fn contains<K>(&self, k: K) -> bool
     where
@@ -267,6 +277,13 @@ impl PartialTrie for StandardTrie {
         self.0.trie_values()
     }
 
+    fn range(
+        &self,
+        range: impl RangeBounds<Nibbles>,
+    ) -> impl Iterator<Item = (Nibbles, ValOrHash)> {
+        self.0.trie_range(range)
+    }
+
     fn contains<K>(&self, k: K) -> bool
     where
         K: Into<Nibbles>,
@@ -308,7 +325,14 @@ where
 /// A partial trie that lazily caches hashes for each node as needed.
 /// If you are doing frequent hashing of node, you probably want to use this
 /// `Trie` variant.
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+///
+/// Mutations only mark the nodes on the modified paths as dirty: the
+/// operations of [`PartialTrie`] rebuild the nodes along the path to the key,
+/// and mutable access to a node (through [`DerefMut`]) clears its cached hash.
+/// As the children of a node can only be reached mutably through it, hashing
+/// the trie afterwards only recomputes the nodes along the modified paths, and
+/// reuses the cached hashes of every other subtree.
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct HashedPartialTrie {
     pub(crate) node: Node<HashedPartialTrie>,
     pub(crate) hash: Arc<RwLock<Option<H256>>>,
@@ -336,12 +360,7 @@ impl_from_for_trie_type!(HashedPartialTrie);
 impl HashedPartialTrie {
     /// Lazily get calculates the hash for the node,
     pub(crate) fn get_hash(&self) -> H256 {
-        let hash = *self.hash.read();
-
-        match hash {
-            Some(h) => h,
-            None => hash_trie(self),
-        }
+        (&self.hash_intern()).into()
     }
 
     pub(crate) fn set_hash(&self, v: Option<H256>) {
@@ -420,6 +439,13 @@ impl PartialTrie for HashedPartialTrie {
         self.node.trie_values()
     }
 
+    fn range(
+        &self,
+        range: impl RangeBounds<Nibbles>,
+    ) -> impl Iterator<Item = (Nibbles, ValOrHash)> {
+        self.node.trie_range(range)
+    }
+
     fn contains<K>(&self, k: K) -> bool
     where
         K: Into<Nibbles>,
@@ -445,6 +471,18 @@ impl TrieNodeIntern for HashedPartialTrie {
     }
 }
 
+// Copies don't share their cache, so that mutating one doesn't invalidate, or
+// worse, overwrite the cached hash of the other.
+impl Clone for HashedPartialTrie {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node.clone(),
+            hash: Arc::new(RwLock::new(*self.hash.read())),
+            strategy: self.strategy,
+        }
+    }
+}
+
 impl Deref for HashedPartialTrie {
     type Target = Node<HashedPartialTrie>;
 
@@ -454,7 +492,10 @@ impl Deref for HashedPartialTrie {
 }
 
 impl DerefMut for HashedPartialTrie {
+    /// The node may be modified through the returned reference, so this marks
+    /// it as dirty.
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_hash(None);
         &mut self.node
     }
 }
@@ -0,0 +1,276 @@
+//! Logic for calculating the changes between two [`PartialTrie`]s.
+//!
+//! [`diff`] lists every key which was inserted, removed or modified between
+//! two tries, e.g. to find where a computed post-state departs from the
+//! expected one. This complements `debug_tools::diff::create_diff_between_tries`
+//! of the `trie_debug` feature, which only reports the highest point where
+//! the structure of the tries diverges.
+//!
+//! Subtrees with the same hash are skipped, so diffing two versions of a
+//! [`HashedPartialTrie`](crate::partial_trie::HashedPartialTrie) only visits
+//! the paths where they differ. The keys below a [`Hash`](Node::Hash) node of
+//! either trie can't be compared, so the differing hash nodes are reported as
+//! a frontier instead.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::H256;
+
+use crate::{
+    nibbles::{Nibble, Nibbles},
+    partial_trie::{Node, PartialTrie},
+    trie_ops::ValOrHash,
+};
+
+/// The changes turning a trie into another.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TrieChanges {
+    /// The keys only present in the second trie, with their values.
+    pub inserted: BTreeMap<Nibbles, Vec<u8>>,
+    /// The keys only present in the first trie, with their values.
+    pub removed: BTreeMap<Nibbles, Vec<u8>>,
+    /// The keys present in both tries with different values, with their
+    /// values in the first and in the second trie.
+    pub modified: BTreeMap<Nibbles, (Vec<u8>, Vec<u8>)>,
+    /// The hash nodes below which the tries differ in unknown ways.
+    pub hash_frontiers: Vec<HashFrontier>,
+}
+
+impl TrieChanges {
+    /// Returns whether the tries were found to be identical.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.hash_frontiers.is_empty()
+    }
+}
+
+/// A [`Hash`](Node::Hash) node of either trie, below which the keys of the two
+/// tries can't be compared.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct HashFrontier {
+    /// The path to the hash node.
+    pub path: Nibbles,
+    /// The hash of the node of the first trie, if it is a hash node.
+    pub a: Option<H256>,
+    /// The hash of the node of the second trie, if it is a hash node.
+    pub b: Option<H256>,
+}
+
+/// Calculates the changes turning `a` into `b`.
+///
+/// The keys below the [`HashFrontier`]s are left out of the changes, as they
+/// are only known to one of the tries, if any. A hash node may be reported
+/// even if it hashes out the same subtree as the other trie holds, if the
+/// structure of the tries around it diverges.
+pub fn diff<N: PartialTrie>(a: &N, b: &N) -> TrieChanges {
+    let mut changes = TrieChanges::default();
+    diff_nodes(Nibbles::default(), a, b, &mut changes);
+
+    changes
+}
+
+fn diff_nodes<N: PartialTrie>(path: Nibbles, a: &N, b: &N, changes: &mut TrieChanges) {
+    if a.hash() == b.hash() {
+        return;
+    }
+
+    match (&**a, &**b) {
+        (
+            Node::Branch {
+                children: a_children,
+                value: a_value,
+            },
+            Node::Branch {
+                children: b_children,
+                value: b_value,
+            },
+        ) => {
+            let value = |v: &Vec<u8>| Some(v.clone()).filter(|v| !v.is_empty());
+            changes.record(path, value(a_value), value(b_value));
+
+            for (i, (a_child, b_child)) in a_children.iter().zip(b_children.iter()).enumerate() {
+                diff_nodes::<N>(path.merge_nibble(i as Nibble), a_child, b_child, changes);
+            }
+        }
+        (
+            Node::Extension {
+                nibbles: a_nibbles,
+                child: a_child,
+            },
+            Node::Extension {
+                nibbles: b_nibbles,
+                child: b_child,
+            },
+        ) if a_nibbles == b_nibbles => {
+            diff_nodes::<N>(path.merge_nibbles(a_nibbles), a_child, b_child, changes)
+        }
+        // The structure of the tries diverges, so compare the items below the
+        // node instead.
+        (a, b) => diff_items(path, a, b, changes),
+    }
+}
+
+fn diff_items<N: PartialTrie>(path: Nibbles, a: &Node<N>, b: &Node<N>, changes: &mut TrieChanges) {
+    let mut frontiers = BTreeMap::<Nibbles, (Option<H256>, Option<H256>)>::new();
+    let mut items = |node: &Node<N>, is_a: bool| {
+        let mut vals = BTreeMap::new();
+        for (k, v) in node.trie_items() {
+            let k = path.merge_nibbles(&k);
+            match v {
+                ValOrHash::Val(v) => {
+                    vals.insert(k, v);
+                }
+                ValOrHash::Hash(h) => {
+                    let frontier = frontiers.entry(k).or_default();
+                    match is_a {
+                        true => frontier.0 = Some(h),
+                        false => frontier.1 = Some(h),
+                    }
+                }
+            }
+        }
+        vals
+    };
+    let a_vals = items(a, true);
+    let mut b_vals = items(b, false);
+
+    frontiers.retain(|_, (a, b)| a != b);
+    let is_hidden = |k: &Nibbles| {
+        frontiers
+            .keys()
+            .any(|path| path.count <= k.count && k.get_next_nibbles(path.count) == *path)
+    };
+
+    for (k, a_val) in a_vals {
+        let b_val = b_vals.remove(&k);
+        if b_val.is_some() || !is_hidden(&k) {
+            changes.record(k, Some(a_val), b_val);
+        }
+    }
+    for (k, b_val) in b_vals {
+        if !is_hidden(&k) {
+            changes.record(k, None, Some(b_val));
+        }
+    }
+
+    changes
+        .hash_frontiers
+        .extend(
+            frontiers
+                .into_iter()
+                .map(|(path, (a, b))| HashFrontier { path, a, b }),
+        );
+}
+
+impl TrieChanges {
+    fn record(&mut self, k: Nibbles, a: Option<Vec<u8>>, b: Option<Vec<u8>>) {
+        match (a, b) {
+            (Some(a), Some(b)) if a != b => {
+                self.modified.insert(k, (a, b));
+            }
+            (Some(a), None) => {
+                self.removed.insert(k, a);
+            }
+            (None, Some(b)) => {
+                self.inserted.insert(k, b);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{diff, HashFrontier};
+    use crate::{
+        nibbles::Nibbles,
+        partial_trie::{HashedPartialTrie, Node, PartialTrie},
+        testing_utils::{common_setup, entry_with_value, large_entry},
+        trie_ops::TrieOpResult,
+        utils::TryFromIterator,
+    };
+
+    #[test]
+    fn reports_inserted_removed_and_modified_keys() -> TrieOpResult<()> {
+        common_setup();
+
+        let a = HashedPartialTrie::try_from_iter([
+            entry_with_value(0x1234, 1),
+            entry_with_value(0x1235, 2),
+            entry_with_value(0x5678, 3),
+        ])?;
+        let mut b = a.clone();
+        assert!(diff(&a, &b).is_empty());
+
+        b.insert(0x1236, vec![4])?;
+        b.insert(0x5678, vec![5])?;
+        b.delete(0x1235)?;
+
+        let changes = diff(&a, &b);
+        assert_eq!(
+            changes.inserted,
+            BTreeMap::from([(Nibbles::from(0x1236_u64), vec![4])])
+        );
+        assert_eq!(
+            changes.removed,
+            BTreeMap::from([(Nibbles::from(0x1235_u64), vec![2])])
+        );
+        assert_eq!(
+            changes.modified,
+            BTreeMap::from([(Nibbles::from(0x5678_u64), (vec![3], vec![5]))])
+        );
+        assert!(changes.hash_frontiers.is_empty());
+
+        let reversed = diff(&b, &a);
+        assert_eq!(reversed.inserted, changes.removed);
+        assert_eq!(reversed.removed, changes.inserted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stops_at_differing_hash_nodes() -> TrieOpResult<()> {
+        common_setup();
+
+        let mut a = HashedPartialTrie::try_from_iter([
+            large_entry(0x12),
+            large_entry(0x13),
+            large_entry(0x24),
+        ])?;
+        let mut b = a.clone();
+        b.insert(0x12, vec![3; 33])?;
+        b.insert(0x24, vec![4; 33])?;
+
+        // Hash out the subtree of the first key in `a`.
+        let hash = match &*a {
+            Node::Branch { children, .. } => children[1].hash(),
+            _ => unreachable!(),
+        };
+        match &mut *a {
+            Node::Branch { children, .. } => children[1] = Node::Hash(hash).into(),
+            _ => unreachable!(),
+        }
+
+        let changes = diff(&a, &b);
+        assert!(changes.inserted.is_empty());
+        assert!(changes.removed.is_empty());
+        assert_eq!(
+            changes.modified,
+            BTreeMap::from([(Nibbles::from(0x24_u64), (large_entry(0x24).1, vec![4; 33]))])
+        );
+        assert_eq!(
+            changes.hash_frontiers,
+            [HashFrontier {
+                path: Nibbles::from(0x1_u64),
+                a: Some(hash),
+                b: None,
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -36,9 +36,22 @@ pub(crate) fn hash_trie<N: PartialTrie + TrieNodeIntern>(node: &Node<N>) -> H256
 pub(crate) fn rlp_encode_and_hash_node<N: PartialTrie + TrieNodeIntern>(
     node: &Node<N>,
 ) -> EncodedNode {
+    match rlp_encode_node(node) {
+        Ok(bytes) => hash_bytes_if_large_enough(bytes),
+        Err(h) => EncodedNode::Hashed(h.0),
+    }
+}
+
+/// RLP encodes a node, referencing its children by their [`EncodedNode`].
+///
+/// The encoding of a hash node is unknown, so its hash is returned as an error
+/// instead.
+pub(crate) fn rlp_encode_node<N: PartialTrie + TrieNodeIntern>(
+    node: &Node<N>,
+) -> Result<Bytes, H256> {
     let res = match node {
-        Node::Empty => EncodedNode::Raw(Bytes::from_static(&rlp::NULL_RLP)),
-        Node::Hash(h) => EncodedNode::Hashed(h.0),
+        Node::Empty => Bytes::from_static(&rlp::NULL_RLP),
+        Node::Hash(h) => return Err(*h),
         Node::Branch { children, value } => {
             let mut stream = RlpStream::new_list(17);
 
@@ -51,7 +64,7 @@ pub(crate) fn rlp_encode_and_hash_node<N: PartialTrie + TrieNodeIntern>(
                 true => stream.append_empty_data(),
             };
 
-            hash_bytes_if_large_enough(stream.out().into())
+            stream.out().into()
         }
         Node::Extension { nibbles, child } => {
             let mut stream = RlpStream::new_list(2);
@@ -59,7 +72,7 @@ pub(crate) fn rlp_encode_and_hash_node<N: PartialTrie + TrieNodeIntern>(
             stream.append(&nibbles.to_hex_prefix_encoding(false));
             append_to_stream(&mut stream, child.hash_intern());
 
-            hash_bytes_if_large_enough(stream.out().into())
+            stream.out().into()
         }
         Node::Leaf { nibbles, value } => {
             let hex_prefix_k = nibbles.to_hex_prefix_encoding(true);
@@ -68,11 +81,11 @@ pub(crate) fn rlp_encode_and_hash_node<N: PartialTrie + TrieNodeIntern>(
             stream.append(&hex_prefix_k);
             stream.append(value);
 
-            hash_bytes_if_large_enough(stream.out().into())
+            stream.out().into()
         }
     };
 
-    res
+    Ok(res)
 }
 
 fn hash_bytes_if_large_enough(bytes: Bytes) -> EncodedNode {
@@ -456,4 +469,62 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn inserting_keeps_hashes_of_untouched_subtrees_cached() -> TrieOpResult<()> {
+        let entries = [
+            large_entry(0x1),
+            large_entry(0x2),
+            large_entry(0x3),
+            large_entry(0x4),
+        ];
+        let mut trie = HashedPartialTrie::try_from_iter(entries.clone())?;
+        trie.hash();
+
+        let (k, v) = large_entry(0x5);
+        trie.insert(k, v)?;
+
+        let children = get_branch_children_expected(&mut trie);
+        assert!((1..=4).all(|i| children[i].hash.read().is_some()));
+        assert!(children[5].hash.read().is_none());
+        assert_eq!(
+            trie.hash(),
+            HashedPartialTrie::try_from_iter(entries.into_iter().chain(once(large_entry(0x5))))?
+                .hash()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutating_a_node_in_place_invalidates_its_path() -> TrieOpResult<()> {
+        let mut trie = HashedPartialTrie::try_from_iter([
+            large_entry(0x12),
+            large_entry(0x13),
+            large_entry(0x24),
+        ])?;
+        let orig = trie.clone();
+        let orig_hash = orig.hash();
+
+        let children = get_branch_children_expected(&mut trie);
+        let grandchildren = get_branch_children_expected(&mut **Arc::make_mut(&mut children[1]));
+        grandchildren[2] = Node::Leaf {
+            nibbles: Nibbles::default(),
+            value: vec![3; 33],
+        }
+        .into();
+        assert!(children[2].hash.read().is_some());
+
+        let expected = HashedPartialTrie::try_from_iter([
+            (Nibbles::from(0x12_u64), vec![3; 33]),
+            large_entry(0x13),
+            large_entry(0x24),
+        ])?;
+        assert_eq!(trie.hash(), expected.hash());
+        // The copy made before the mutation is unaffected.
+        assert_eq!(orig.hash(), orig_hash);
+        assert_ne!(orig_hash, expected.hash());
+
+        Ok(())
+    }
 }
@@ -1,7 +1,12 @@
 //! Defines various operations for
 //! [`PartialTrie`].
 
-use std::{fmt::Display, mem::size_of};
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    mem::size_of,
+    ops::{Bound, RangeBounds},
+};
 
 use enum_as_inner::EnumAsInner;
 use ethereum_types::{H256, U128, U256, U512};
@@ -303,6 +308,108 @@ impl<N: PartialTrie> Iterator for PartialTrieIter<N> {
     }
 }
 
+#[derive(Clone, Debug)]
+/// An iterator over the leafs of the trie whose keys lie in a range, in
+/// lexicographic order, which stops at the first hash node that may hide keys
+/// in the range.
+pub struct PartialTrieRangeIter<N> {
+    trie_stack: Vec<(Nibbles, WrappedNode<N>)>,
+    start: Bound<Nibbles>,
+    end: Bound<Nibbles>,
+}
+
+impl<N> PartialTrieRangeIter<N> {
+    /// Returns whether some keys starting with `prefix` may lie in the range.
+    fn overlaps_keys_under(&self, prefix: &Nibbles) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) | Bound::Excluded(start) => {
+                cmp_keys(prefix, start).is_ge()
+                    || (prefix.count <= start.count
+                        && prefix.nibbles_are_identical_up_to_smallest_count(start))
+            }
+            Bound::Unbounded => true,
+        };
+        let before_end = match &self.end {
+            Bound::Included(end) => cmp_keys(prefix, end).is_le(),
+            Bound::Excluded(end) => cmp_keys(prefix, end).is_lt(),
+            Bound::Unbounded => true,
+        };
+
+        after_start && before_end
+    }
+
+    fn contains(&self, k: &Nibbles) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) => cmp_keys(k, start).is_ge(),
+            Bound::Excluded(start) => cmp_keys(k, start).is_gt(),
+            Bound::Unbounded => true,
+        };
+        let before_end = match &self.end {
+            Bound::Included(end) => cmp_keys(k, end).is_le(),
+            Bound::Excluded(end) => cmp_keys(k, end).is_lt(),
+            Bound::Unbounded => true,
+        };
+
+        after_start && before_end
+    }
+}
+
+impl<N: PartialTrie> Iterator for PartialTrieRangeIter<N> {
+    type Item = (Nibbles, ValOrHash);
+
+    fn next(&mut self) -> Option<(Nibbles, ValOrHash)> {
+        while let Some((path, node)) = self.trie_stack.pop() {
+            if !self.overlaps_keys_under(&path) {
+                continue;
+            }
+
+            let node: &Node<N> = &node;
+            match node {
+                Node::Empty => (),
+                Node::Hash(h) => {
+                    // The keys after the hash node can't be returned in order.
+                    self.trie_stack.clear();
+                    return Some((path, ValOrHash::Hash(*h)));
+                }
+                Node::Branch { children, value } => {
+                    self.trie_stack.extend(
+                        children
+                            .iter()
+                            .enumerate()
+                            .rev()
+                            .map(|(i, child)| (path.merge_nibble(i as Nibble), child.clone())),
+                    );
+                    if !value.is_empty() && self.contains(&path) {
+                        return Some((path, ValOrHash::Val(value.clone())));
+                    }
+                }
+                Node::Extension { nibbles, child } => {
+                    self.trie_stack
+                        .push((path.merge_nibbles(nibbles), child.clone()));
+                }
+                Node::Leaf { nibbles, value } => {
+                    let k = path.merge_nibbles(nibbles);
+                    if self.contains(&k) {
+                        return Some((k, ValOrHash::Val(value.clone())));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Compares two keys nibble by nibble, a key coming before the keys it is a
+/// prefix of. Note that this differs from the [`Ord`] of [`Nibbles`], which
+/// compares their lengths first.
+fn cmp_keys(a: &Nibbles, b: &Nibbles) -> Ordering {
+    (0..a.count.min(b.count))
+        .map(|i| a.get_nibble(i).cmp(&b.get_nibble(i)))
+        .find(|ord| ord.is_ne())
+        .unwrap_or_else(|| a.count.cmp(&b.count))
+}
+
 impl<T: PartialTrie> Node<T> {
     pub(crate) fn trie_insert<K, V>(&mut self, k: K, v: V) -> TrieOpResult<()>
     where
@@ -412,6 +519,14 @@ impl<T: PartialTrie> Node<T> {
         }
     }
 
+    pub(crate) fn trie_range(&self, range: impl RangeBounds<Nibbles>) -> PartialTrieRangeIter<T> {
+        PartialTrieRangeIter {
+            trie_stack: vec![(Nibbles::default(), self.clone().into())],
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+
     pub(crate) fn trie_keys(&self) -> impl Iterator<Item = Nibbles> {
         self.trie_items().map(|(k, _)| k)
     }
@@ -889,8 +1004,9 @@ fn create_node_if_ins_val_not_hash<N, F: FnOnce(Vec<u8>) -> WrappedNode<N>>(
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashSet, iter::once};
+    use std::{collections::HashSet, iter::once, ops::Bound};
 
+    use ethereum_types::H256;
     use log::debug;
 
     use super::ValOrHash;
@@ -1247,4 +1363,70 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn range_returns_entries_in_range_in_order() -> TrieOpResult<()> {
+        common_setup();
+
+        let mut entries: Vec<_> =
+            generate_n_random_fixed_trie_value_entries(COW_TEST_TRIE_SIZE, 9).collect();
+        let trie = HashedPartialTrie::try_from_iter(entries.iter().cloned())?;
+
+        // The keys all have the same length, so their `Ord` is lexicographic.
+        entries.sort();
+        let (start, end) = (entries[100].0, entries[200].0);
+        let collect_range = |range: (Bound<Nibbles>, Bound<Nibbles>)| {
+            trie.range(range)
+                .map(|(k, v)| (k, unwrap_iter_item_to_val(v)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(collect_range((Bound::Unbounded, Bound::Unbounded)), entries);
+        assert_eq!(
+            collect_range((Bound::Included(start), Bound::Excluded(end))),
+            entries[100..200]
+        );
+        assert_eq!(
+            collect_range((Bound::Excluded(start), Bound::Included(end))),
+            entries[101..=200]
+        );
+        // Prefixes of the keys bound the range like the keys they are a prefix of.
+        assert_eq!(
+            collect_range((
+                Bound::Included(start.truncate_n_nibbles_back(1)),
+                Bound::Unbounded
+            )),
+            entries[100..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn range_stops_at_hash_nodes() -> TrieOpResult<()> {
+        common_setup();
+
+        let mut trie =
+            HashedPartialTrie::try_from_iter([entry(0x12), entry(0x13), entry(0x24), entry(0x35)])?;
+        match &mut *trie {
+            Node::Branch { children, .. } => children[2] = Node::Hash(H256::zero()).into(),
+            _ => unreachable!(),
+        }
+
+        assert_eq!(
+            trie.range(..).collect::<Vec<_>>(),
+            [
+                (Nibbles::from(0x12_u64), ValOrHash::Val(vec![2])),
+                (Nibbles::from(0x13_u64), ValOrHash::Val(vec![2])),
+                (Nibbles::from(0x2_u64), ValOrHash::Hash(H256::zero())),
+            ]
+        );
+        // Ranges which can't hold any key of the hash node skip it.
+        assert_eq!(
+            trie.range(Nibbles::from(0x3_u64)..).collect::<Vec<_>>(),
+            [(Nibbles::from(0x35_u64), ValOrHash::Val(vec![2]))]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,278 @@
+//! Merkle proofs of the presence or the absence of keys in a
+//! [`HashedPartialTrie`].
+//!
+//! The proofs follow the format of the `eth_getProof` RPC method: the RLP
+//! encodings of the nodes on the path to the key, starting from the root.
+//! Nodes whose encoding is shorter than 32 bytes are embedded in their parent,
+//! so only appear as part of it. A proof of absence consists of the nodes on
+//! the path up to where the key diverges from the trie.
+
+use std::collections::HashMap;
+
+use ethereum_types::H256;
+use keccak_hash::keccak;
+use rlp::{DecoderError, Rlp};
+use thiserror::Error;
+
+use crate::{
+    nibbles::{FromHexPrefixError, Nibbles},
+    partial_trie::{HashedPartialTrie, Node},
+    trie_hashing::rlp_encode_node,
+    utils::bytes_to_h256,
+};
+
+/// An error that occurs when creating or verifying a Merkle proof.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum MerkleProofError {
+    /// The path to the key goes through a hash node, so the nodes of the proof
+    /// are unknown.
+    #[error("Found a `Hash` node on the path to the key, so it can't be proven! (hash: {0})")]
+    HashNodeOnPath(H256),
+
+    /// A node referenced on the path to the key is missing from the proof.
+    #[error("The proof is missing a node on the path to the key! (hash: {0})")]
+    MissingNode(H256),
+
+    /// A node of the proof isn't a valid trie node.
+    #[error("The proof contains an invalid node: {0}")]
+    InvalidNode(#[from] DecoderError),
+
+    /// The key piece of a node of the proof isn't valid hex prefix encoding.
+    #[error("The proof contains a node with an invalid key piece: {0}")]
+    InvalidNodeKey(#[from] FromHexPrefixError),
+}
+
+impl HashedPartialTrie {
+    /// Creates a Merkle proof of the presence or the absence of `k` in the
+    /// trie.
+    ///
+    /// Fails if the path to the key goes through a hash node.
+    pub fn prove<K: Into<Nibbles>>(&self, k: K) -> Result<Vec<Vec<u8>>, MerkleProofError> {
+        let mut proof = Vec::new();
+        prove_intern(self, k.into(), true, &mut proof)?;
+
+        Ok(proof)
+    }
+
+    /// Verifies a Merkle proof of `k` against the trie root hash `root`, as
+    /// created by [`HashedPartialTrie::prove`] or returned by `eth_getProof`.
+    ///
+    /// Returns the value of the key, or `None` if the proof shows that the key
+    /// is absent. The order of the nodes of the proof doesn't matter, and the
+    /// nodes which aren't on the path to the key are ignored.
+    pub fn verify_proof<K: Into<Nibbles>>(
+        root: H256,
+        k: K,
+        proof: &[impl AsRef<[u8]>],
+    ) -> Result<Option<Vec<u8>>, MerkleProofError> {
+        let nodes: HashMap<_, _> = proof
+            .iter()
+            .map(|node| (bytes_to_h256(&keccak(node.as_ref()).0), node.as_ref()))
+            .collect();
+        let resolve = |hash: H256| {
+            nodes
+                .get(&hash)
+                .map(|node| Rlp::new(node))
+                .ok_or(MerkleProofError::MissingNode(hash))
+        };
+
+        let mut key = k.into();
+        let mut node = resolve(root)?;
+        loop {
+            // Only the root of an empty trie is an empty node.
+            if node.is_data() && node.is_empty() {
+                return Ok(None);
+            }
+
+            let child = match node.item_count()? {
+                17 => {
+                    if key.is_empty() {
+                        let value = node.at(16)?.data()?.to_vec();
+                        return Ok(Some(value).filter(|v| !v.is_empty()));
+                    }
+                    node.at(key.pop_next_nibble_front() as usize)?
+                }
+                2 => {
+                    let (nibbles, is_leaf) =
+                        decode_key_piece::<MerkleProofError>(node.at(0)?.data()?)?;
+                    if is_leaf {
+                        let value = node.at(1)?.data()?.to_vec();
+                        return Ok((key == nibbles).then_some(value));
+                    }
+                    if key.count < nibbles.count || key.get_next_nibbles(nibbles.count) != nibbles {
+                        return Ok(None);
+                    }
+                    key.truncate_n_nibbles_front_mut(nibbles.count);
+                    node.at(1)?
+                }
+                _ => return Err(DecoderError::RlpIncorrectListLen.into()),
+            };
+
+            // Children are either embedded in their parent, or referenced by
+            // their hash.
+            node = match child.is_list() {
+                true => child,
+                false => match child.data()? {
+                    [] => return Ok(None),
+                    hash if hash.len() == 32 => resolve(H256::from_slice(hash))?,
+                    _ => return Err(DecoderError::RlpInvalidLength.into()),
+                },
+            };
+        }
+    }
+}
+
+fn prove_intern(
+    node: &HashedPartialTrie,
+    mut key: Nibbles,
+    is_root: bool,
+    proof: &mut Vec<Vec<u8>>,
+) -> Result<(), MerkleProofError> {
+    let encoded = rlp_encode_node(node).map_err(MerkleProofError::HashNodeOnPath)?;
+    // Nodes shorter than a hash are embedded in their parent.
+    if is_root || encoded.len() >= 32 {
+        proof.push(encoded.to_vec());
+    }
+
+    match &**node {
+        Node::Branch { children, .. } if !key.is_empty() => {
+            let child = &children[key.pop_next_nibble_front() as usize];
+            prove_intern(child, key, false, proof)
+        }
+        Node::Extension { nibbles, child }
+            if key.count >= nibbles.count && key.get_next_nibbles(nibbles.count) == *nibbles =>
+        {
+            key.truncate_n_nibbles_front_mut(nibbles.count);
+            prove_intern(child, key, false, proof)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Decodes the hex prefix encoded key piece of a leaf or an extension, and
+/// returns whether it belongs to a leaf.
+pub(crate) fn decode_key_piece<E>(bytes: &[u8]) -> Result<(Nibbles, bool), E>
+where
+    E: From<DecoderError> + From<FromHexPrefixError>,
+{
+    let Some((&first, rest)) = bytes.split_first() else {
+        return Err(DecoderError::RlpIsTooShort.into());
+    };
+    if rest.len() > 32 {
+        return Err(FromHexPrefixError::TooLong(hex::encode(bytes), bytes.len()).into());
+    }
+
+    // The high nibble of the first byte holds the flags (0b10 for leaves, 0b01
+    // for odd lengths), and the low one the first nibble of odd length keys.
+    let flags = first >> 4;
+    if flags > 0b11 {
+        return Err(FromHexPrefixError::InvalidFlags(flags).into());
+    }
+
+    let mut nibbles = Nibbles::default();
+    if flags & 0b01 != 0 {
+        nibbles.push_nibble_back(first & 0xf);
+    }
+    for b in rest {
+        nibbles.push_nibble_back(b >> 4);
+        nibbles.push_nibble_back(b & 0xf);
+    }
+
+    Ok((nibbles, flags & 0b10 != 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use eth_trie::{EthTrie, MemoryDB, Trie};
+    use ethereum_types::H256;
+
+    use super::MerkleProofError;
+    use crate::{
+        nibbles::Nibbles,
+        partial_trie::{HashedPartialTrie, Node, PartialTrie},
+        testing_utils::{
+            common_setup, generate_n_random_fixed_even_nibble_padded_trie_value_entries,
+            large_entry,
+        },
+        utils::TryFromIterator,
+    };
+
+    const NUM_ENTRIES_FOR_PROOF_TEST: usize = 500;
+
+    #[test]
+    fn proofs_verify_against_eth_trie() {
+        common_setup();
+
+        let entries: Vec<_> = generate_n_random_fixed_even_nibble_padded_trie_value_entries(
+            NUM_ENTRIES_FOR_PROOF_TEST,
+            3,
+        )
+        .collect();
+        let trie = HashedPartialTrie::try_from_iter(entries.iter().cloned()).unwrap();
+        let mut truth_trie = EthTrie::new(Arc::new(MemoryDB::new(true)));
+        for (k, v) in &entries {
+            truth_trie.insert(&k.bytes_be(), v).unwrap();
+        }
+        let truth_root = truth_trie.root_hash().unwrap();
+        assert_eq!(trie.hash().0, truth_root.0);
+
+        let absent = Nibbles::from_bytes_be(&[0xff; 32]).unwrap();
+        let keys = entries.iter().map(|(k, _)| *k).chain([absent]);
+        for k in keys {
+            let expected = trie.get(k).map(<[u8]>::to_vec);
+
+            let proof = trie.prove(k).unwrap();
+            assert_eq!(
+                HashedPartialTrie::verify_proof(trie.hash(), k, &proof).unwrap(),
+                expected
+            );
+            assert_eq!(
+                truth_trie
+                    .verify_proof(truth_root, &k.bytes_be(), proof)
+                    .unwrap(),
+                expected
+            );
+
+            let truth_proof = truth_trie.get_proof(&k.bytes_be()).unwrap();
+            assert_eq!(
+                HashedPartialTrie::verify_proof(trie.hash(), k, &truth_proof).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn proofs_stop_at_hash_nodes() {
+        common_setup();
+
+        let mut trie =
+            HashedPartialTrie::try_from_iter([large_entry(0x12), large_entry(0x24)]).unwrap();
+        let root = trie.hash();
+        let proof = trie.prove(0x24).unwrap();
+
+        let hash = match &mut *trie {
+            Node::Branch { children, .. } => {
+                let hash = children[1].hash();
+                children[1] = Node::Hash(hash).into();
+                hash
+            }
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            trie.prove(0x12),
+            Err(MerkleProofError::HashNodeOnPath(hash))
+        );
+        assert_eq!(trie.prove(0x24).unwrap(), proof);
+
+        assert_eq!(
+            HashedPartialTrie::verify_proof(root, 0x12, &proof),
+            Err(MerkleProofError::MissingNode(hash))
+        );
+        assert_eq!(
+            HashedPartialTrie::verify_proof(H256::zero(), 0x24, &proof),
+            Err(MerkleProofError::MissingNode(H256::zero()))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Conversion of a [`HashedPartialTrie`] to and from the node list encoding of
+//! stateless witnesses.
+//!
+//! A witness is the RLP list of the encodings of the nodes of the trie, as
+//! served by the `GetNodeData` message of eth/63, ordered depth-first starting
+//! from the root. Like in the tries themselves, nodes whose encoding is shorter
+//! than 32 bytes are embedded in their parent rather than listed, and the
+//! hashed out parts of the trie only appear as the hashes referenced by their
+//! parent. If the root itself is hashed out, the witness only holds its hash.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use ethereum_types::H256;
+use keccak_hash::keccak;
+use rlp::{DecoderError, Rlp, RlpStream};
+use thiserror::Error;
+
+use crate::{
+    nibbles::FromHexPrefixError,
+    partial_trie::{HashedPartialTrie, Node, PartialTrie},
+    trie_hashing::rlp_encode_node,
+    trie_proofs::decode_key_piece,
+    utils::bytes_to_h256,
+};
+
+/// An error that occurs when decoding a witness.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum WitnessError {
+    /// The witness holds no node.
+    #[error("The witness is missing the root node!")]
+    MissingRoot,
+
+    /// A node of the witness isn't a valid trie node.
+    #[error("The witness contains an invalid node: {0}")]
+    InvalidNode(#[from] DecoderError),
+
+    /// The key piece of a node of the witness isn't valid hex prefix encoding.
+    #[error("The witness contains a node with an invalid key piece: {0}")]
+    InvalidNodeKey(#[from] FromHexPrefixError),
+}
+
+impl HashedPartialTrie {
+    /// Encodes the trie as a witness.
+    pub fn to_witness(&self) -> Vec<u8> {
+        let mut nodes = Vec::new();
+        match rlp_encode_node(self) {
+            Ok(_) => collect_nodes(self, true, &mut nodes),
+            Err(hash) => nodes.push(rlp::encode(&hash.as_bytes()).into()),
+        }
+
+        let mut stream = RlpStream::new_list(nodes.len());
+        for node in &nodes {
+            stream.append_raw(node, 1);
+        }
+
+        stream.out().to_vec()
+    }
+
+    /// Decodes a trie from a witness.
+    ///
+    /// The references to nodes missing from the witness are decoded as
+    /// [`Hash`](Node::Hash) nodes, and the nodes of the witness which aren't
+    /// referenced are ignored.
+    pub fn from_witness(witness: &[u8]) -> Result<Self, WitnessError> {
+        let witness = Rlp::new(witness);
+        if witness.item_count()? == 0 {
+            return Err(WitnessError::MissingRoot);
+        }
+        let root = witness.at(0)?;
+        if root.is_data() && root.data()?.len() == 32 {
+            return Ok(Self::new(Node::Hash(H256::from_slice(root.data()?))));
+        }
+
+        let nodes: HashMap<_, _> = witness
+            .iter()
+            .map(|node| (bytes_to_h256(&keccak(node.as_raw()).0), node))
+            .collect();
+
+        Ok(Self::new(decode_node(root, &nodes)?))
+    }
+}
+
+/// Collects the encodings of the nodes which aren't embedded in their parent,
+/// depth-first.
+fn collect_nodes(node: &HashedPartialTrie, is_root: bool, nodes: &mut Vec<Bytes>) {
+    // Hashed out nodes are only referenced by their parent.
+    let Ok(encoded) = rlp_encode_node(node) else {
+        return;
+    };
+    if is_root || encoded.len() >= 32 {
+        nodes.push(encoded);
+    }
+
+    match &**node {
+        Node::Branch { children, .. } => {
+            for child in children.iter() {
+                collect_nodes(child, false, nodes);
+            }
+        }
+        Node::Extension { child, .. } => collect_nodes(child, false, nodes),
+        Node::Empty | Node::Hash(_) | Node::Leaf { .. } => (),
+    }
+}
+
+fn decode_node(
+    node: Rlp,
+    nodes: &HashMap<H256, Rlp>,
+) -> Result<Node<HashedPartialTrie>, WitnessError> {
+    // Only the root of an empty trie is an empty node.
+    if node.is_data() && node.is_empty() {
+        return Ok(Node::Empty);
+    }
+
+    match node.item_count()? {
+        17 => {
+            let children = (0..16)
+                .map(|i| Ok(Arc::new(Box::new(decode_child(node.at(i)?, nodes)?))))
+                .collect::<Result<Vec<_>, WitnessError>>()?;
+
+            Ok(Node::Branch {
+                children: children.try_into().unwrap(),
+                value: node.at(16)?.data()?.to_vec(),
+            })
+        }
+        2 => {
+            let (nibbles, is_leaf) = decode_key_piece::<WitnessError>(node.at(0)?.data()?)?;
+            match is_leaf {
+                true => Ok(Node::Leaf {
+                    nibbles,
+                    value: node.at(1)?.data()?.to_vec(),
+                }),
+                false => Ok(Node::Extension {
+                    nibbles,
+                    child: Arc::new(Box::new(decode_child(node.at(1)?, nodes)?)),
+                }),
+            }
+        }
+        _ => Err(DecoderError::RlpIncorrectListLen.into()),
+    }
+}
+
+/// Decodes a child node, which is either embedded in its parent or referenced
+/// by its hash.
+fn decode_child(child: Rlp, nodes: &HashMap<H256, Rlp>) -> Result<HashedPartialTrie, WitnessError> {
+    if child.is_list() {
+        return Ok(HashedPartialTrie::new(decode_node(child, nodes)?));
+    }
+
+    let node = match child.data()? {
+        [] => Node::Empty,
+        hash if hash.len() == 32 => {
+            let hash = H256::from_slice(hash);
+            match nodes.get(&hash) {
+                Some(node) => decode_node(node.clone(), nodes)?,
+                None => Node::Hash(hash),
+            }
+        }
+        _ => return Err(DecoderError::RlpInvalidLength.into()),
+    };
+
+    Ok(HashedPartialTrie::new(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WitnessError;
+    use crate::{
+        partial_trie::{HashedPartialTrie, Node, PartialTrie},
+        testing_utils::{
+            common_setup, entry_with_value, generate_n_random_variable_trie_value_entries,
+            large_entry,
+        },
+        trie_subsets::create_trie_subset,
+        utils::TryFromIterator,
+    };
+
+    const NUM_ENTRIES_FOR_WITNESS_TEST: usize = 500;
+
+    #[test]
+    fn witness_round_trip() {
+        common_setup();
+
+        let entries: Vec<_> =
+            generate_n_random_variable_trie_value_entries(NUM_ENTRIES_FOR_WITNESS_TEST, 5)
+                .collect();
+        let trie = HashedPartialTrie::try_from_iter(entries.iter().cloned()).unwrap();
+        let decoded = HashedPartialTrie::from_witness(&trie.to_witness()).unwrap();
+        assert_eq!(decoded, trie);
+        assert_eq!(decoded.hash(), trie.hash());
+
+        // Hashed out parts of the trie stay hashed out.
+        let subset = create_trie_subset(&trie, entries.iter().take(10).map(|(k, _)| *k)).unwrap();
+        let decoded = HashedPartialTrie::from_witness(&subset.to_witness()).unwrap();
+        assert_eq!(decoded, subset);
+        assert_eq!(decoded.hash(), trie.hash());
+    }
+
+    #[test]
+    fn small_tries_round_trip() {
+        common_setup();
+
+        let empty = HashedPartialTrie::default();
+        let small = HashedPartialTrie::try_from_iter([entry_with_value(0x1234, 1)]).unwrap();
+        let hashed = HashedPartialTrie::new(Node::Hash(
+            HashedPartialTrie::try_from_iter([large_entry(0x12), large_entry(0x24)])
+                .unwrap()
+                .hash(),
+        ));
+        for trie in [empty, small, hashed] {
+            let decoded = HashedPartialTrie::from_witness(&trie.to_witness()).unwrap();
+            assert_eq!(decoded, trie);
+        }
+
+        assert_eq!(
+            HashedPartialTrie::from_witness(&rlp::EMPTY_LIST_RLP),
+            Err(WitnessError::MissingRoot)
+        );
+    }
+}
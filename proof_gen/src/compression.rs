@@ -0,0 +1,167 @@
+//! This module compresses block proofs into much smaller proofs, for users
+//! who ship them over the network or store many of them.
+//!
+//! The block proof is first shrunk by a recursive circuit with the standard
+//! configuration, whose proof is then verified by a last circuit with an
+//! aggressive FRI rate. For the same conjectured security, the proofs of the
+//! latter need far fewer FRI queries than block proofs. They are moreover
+//! serialized in the compressed format of plonky2, which deduplicates the
+//! Merkle paths of the queries.
+//!
+//! Compressed proofs expose the public values of the block as their public
+//! inputs, and are verified against the verifier data of the last circuit, see
+//! [`BlockProofCompressor::verifier_data`].
+
+use evm_arithmetization::fixed_recursive_verifier::{
+    add_virtual_block_proof, extract_block_public_values, verify_block_proof_circuit,
+};
+use evm_arithmetization::proof::{PublicValues, PublicValuesError};
+use plonky2::{
+    fri::{reduction_strategies::FriReductionStrategy, FriConfig},
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData},
+        proof::{CompressedProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    proof_gen::ProofGenResult,
+    proof_types::GeneratedBlockProof,
+    types::{Config, Field, VerifierData, EXTENSION_DEGREE},
+};
+
+/// The circuit configuration of the last compression circuit.
+///
+/// Its rate of `2^-7`, with 12 FRI queries, matches the conjectured security
+/// of the standard recursion configuration, whose rate of `2^-3` takes 28
+/// queries.
+pub fn compression_config() -> CircuitConfig {
+    CircuitConfig {
+        fri_config: FriConfig {
+            rate_bits: 7,
+            cap_height: 0,
+            proof_of_work_bits: 16,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+            num_query_rounds: 12,
+        },
+        ..CircuitConfig::standard_recursion_config()
+    }
+}
+
+/// A block proof compressed by a [`BlockProofCompressor`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CompressedBlockProof {
+    /// Associated block height.
+    pub b_height: u64,
+    /// The compressed proof, whose public inputs are the public values of the
+    /// block.
+    #[serde(with = "crate::proof_types::hasher_tagged")]
+    pub intern: CompressedProofWithPublicInputs<Field, Config, EXTENSION_DEGREE>,
+}
+
+impl CompressedBlockProof {
+    /// Returns the public values of the block.
+    pub fn public_values(&self) -> Result<PublicValues, PublicValuesError> {
+        PublicValues::from_public_inputs(&self.intern.public_inputs)
+    }
+}
+
+/// The circuits compressing block proofs.
+pub struct BlockProofCompressor {
+    shrink: CircuitData<Field, Config, EXTENSION_DEGREE>,
+    shrink_target: ProofWithPublicInputsTarget<EXTENSION_DEGREE>,
+    compress: CircuitData<Field, Config, EXTENSION_DEGREE>,
+    compress_target: ProofWithPublicInputsTarget<EXTENSION_DEGREE>,
+}
+
+impl BlockProofCompressor {
+    /// Builds the compression circuits of the proofs of the `block` circuit.
+    pub fn new(block: &VerifierData) -> Self {
+        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let block_proof = add_virtual_block_proof(&mut builder, block);
+        verify_block_proof_circuit(&mut builder, &block_proof, block);
+        builder.register_public_inputs(extract_block_public_values(
+            &block_proof.proof_with_pis.public_inputs,
+        ));
+        let shrink = builder.build::<Config>();
+        let shrink_target = block_proof.proof_with_pis;
+
+        let mut builder = CircuitBuilder::new(compression_config());
+        let compress_target = builder.add_virtual_proof_with_pis(&shrink.common);
+        let shrink_vk = builder.constant_verifier_data(&shrink.verifier_only);
+        builder.verify_proof::<Config>(&compress_target, &shrink_vk, &shrink.common);
+        builder.register_public_inputs(&compress_target.public_inputs);
+        let compress = builder.build::<Config>();
+
+        Self {
+            shrink,
+            shrink_target,
+            compress,
+            compress_target,
+        }
+    }
+
+    /// The verifier data of the last compression circuit, against which
+    /// compressed proofs are verified.
+    pub fn verifier_data(&self) -> VerifierData {
+        self.compress.verifier_data()
+    }
+
+    /// Compresses a block proof.
+    pub fn compress(
+        &self,
+        block_proof: &GeneratedBlockProof,
+    ) -> ProofGenResult<CompressedBlockProof> {
+        let mut inputs = PartialWitness::new();
+        inputs.set_proof_with_pis_target(&self.shrink_target, &block_proof.intern);
+        let shrunk = self.shrink.prove(inputs).map_err(|err| err.to_string())?;
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_proof_with_pis_target(&self.compress_target, &shrunk);
+        let compressed = self
+            .compress
+            .prove(inputs)
+            .map_err(|err| err.to_string())?
+            .compress(
+                &self.compress.verifier_only.circuit_digest,
+                &self.compress.common,
+            )
+            .map_err(|err| err.to_string())?;
+
+        Ok(CompressedBlockProof {
+            b_height: block_proof.b_height,
+            intern: compressed,
+        })
+    }
+}
+
+/// Verifies a compressed block proof against the verifier data of the last
+/// compression circuit.
+pub fn verify_compressed_proof(
+    verifier: &VerifierData,
+    proof: &CompressedBlockProof,
+) -> ProofGenResult<()> {
+    verifier
+        .verify_compressed(proof.intern.clone())
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use evm_arithmetization::security::conjectured_security_bits;
+
+    use super::*;
+
+    #[test]
+    fn compression_keeps_security() {
+        let standard = CircuitConfig::standard_recursion_config();
+        assert_eq!(
+            conjectured_security_bits(&compression_config().fri_config),
+            conjectured_security_bits(&standard.fri_config)
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use core::ops::Range;
 
 /// Default range to be used for the `ArithmeticStark` table.
-pub(crate) const DEFAULT_ARITHMETIC_RANGE: Range<usize> = 16..28;
+pub(crate) const DEFAULT_ARITHMETIC_RANGE: Range<usize> = 9..28;
 /// Default range to be used for the `BytePackingStark` table.
 pub(crate) const DEFAULT_BYTE_PACKING_RANGE: Range<usize> = 9..28;
 /// Default range to be used for the `CpuStark` table.
@@ -22,3 +22,8 @@ pub(crate) const DEFAULT_MEMORY_RANGE: Range<usize> = 17..30;
 pub(crate) const DEFAULT_MEMORY_BEFORE_RANGE: Range<usize> = 8..20;
 /// Default range to be used for the `MemoryAfterStark` table.
 pub(crate) const DEFAULT_MEMORY_AFTER_RANGE: Range<usize> = 16..30;
+/// Default range to be used for the `RangeCheckStark` table, which always has
+/// 2^16 rows.
+pub(crate) const DEFAULT_RANGE_CHECK_RANGE: Range<usize> = 16..17;
+/// Default range to be used for the `EcStark` table.
+pub(crate) const DEFAULT_EC_RANGE: Range<usize> = 4..20;
@@ -133,13 +133,34 @@
 //!     // Have the verifier attest validity of the proof.
 //!     assert!(verifier_state.verify(block_proof.intern).is_ok());
 //! ```
+//!
+//! ## Wrapping block proofs
+//!
+//! Block proofs can be wrapped into Groth16 proofs over BN254, which are cheap
+//! to verify on-chain, with the [`wrapper`] module. The [`solidity`] module
+//! then generates the contract verifying them, and encodes its calldata.
+//!
+//! ## Compressing block proofs
+//!
+//! Block proofs can be compressed with the [`compression`] module, by an extra
+//! recursive circuit with an aggressive FRI rate, for users who ship them over
+//! the network or store many of them.
 
+#[cfg(feature = "prover")]
+pub mod compression;
 pub(crate) mod constants;
+pub mod light_client;
+#[cfg(feature = "poseidon2")]
+pub mod poseidon2;
 pub mod proof_gen;
 pub mod proof_types;
 pub mod prover_state;
+#[cfg(feature = "prover")]
+pub mod solidity;
 pub mod types;
 pub mod verifier_state;
+#[cfg(feature = "prover")]
+pub mod wrapper;
 
 // Re-exports
 
@@ -0,0 +1,387 @@
+//! This module defines the [`LightClientUpdate`], a compact package built from
+//! a range of consecutive block proofs that can be consumed by an off-chain
+//! light client.
+//!
+//! An update contains the header of the last block of the range along with
+//! its block proof, which commits to the hashes of the 256 blocks preceding
+//! it. The update carries the RLP-encoded headers of the other blocks of the
+//! range, whose hashes are checked against these commitments, hence a range
+//! spans at most 257 blocks. This binds the fields of the earlier headers,
+//! including their state roots, to the proof.
+
+use ethereum_types::{H256, U256};
+use evm_arithmetization::{
+    fixed_recursive_verifier::extract_block_public_values, proof::PublicValues,
+};
+use keccak_hash::keccak;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    proof_gen::{ProofGenError, ProofGenResult},
+    proof_types::GeneratedBlockProof,
+    types::PlonkyProofIntern,
+    verifier_state::VerifierState,
+};
+
+/// The current version of the [`LightClientUpdate`] serialization format.
+pub const LIGHT_CLIENT_UPDATE_VERSION: u16 = 3;
+
+/// The maximum number of blocks preceding the last header of an update, i.e.
+/// the number of previous block hashes committed to by a block proof.
+pub const MAX_ANCESTOR_HASHES: usize = 256;
+
+/// The subset of a block header tracked by a light client.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LightClientHeader {
+    /// The block number.
+    pub number: U256,
+    /// The block hash.
+    pub hash: H256,
+    /// The hash of the parent block.
+    pub parent_hash: H256,
+    /// The block timestamp.
+    pub timestamp: U256,
+    /// The state trie root after the execution of the block.
+    pub state_root: H256,
+    /// The transactions trie root of the block.
+    pub transactions_root: H256,
+    /// The receipts trie root of the block.
+    pub receipts_root: H256,
+}
+
+impl LightClientHeader {
+    /// Decodes the fields tracked by a light client from an RLP-encoded block
+    /// header, whose hash is the hash of the encoding.
+    pub fn from_rlp(header: &[u8]) -> ProofGenResult<Self> {
+        let rlp = Rlp::new(header);
+        let field = |i| {
+            rlp.at(i)
+                .map_err(|e| ProofGenError(format!("invalid header: {e}")))
+        };
+        let hash = |i| {
+            field(i)?
+                .as_val::<H256>()
+                .map_err(|e| ProofGenError(e.to_string()))
+        };
+        let uint = |i| {
+            field(i)?
+                .as_val::<U256>()
+                .map_err(|e| ProofGenError(e.to_string()))
+        };
+
+        Ok(Self {
+            number: uint(8)?,
+            hash: keccak(header),
+            parent_hash: hash(0)?,
+            timestamp: uint(11)?,
+            state_root: hash(3)?,
+            transactions_root: hash(4)?,
+            receipts_root: hash(5)?,
+        })
+    }
+}
+
+impl From<&PublicValues> for LightClientHeader {
+    fn from(p_vals: &PublicValues) -> Self {
+        Self {
+            number: p_vals.block_metadata.block_number,
+            hash: p_vals.block_hashes.cur_hash,
+            parent_hash: p_vals
+                .block_hashes
+                .prev_hashes
+                .last()
+                .copied()
+                .unwrap_or_default(),
+            timestamp: p_vals.block_metadata.block_timestamp,
+            state_root: p_vals.trie_roots_after.state_root,
+            transactions_root: p_vals.trie_roots_after.transactions_root,
+            receipts_root: p_vals.trie_roots_after.receipts_root,
+        }
+    }
+}
+
+/// A versioned package attesting to a block header and the headers of the
+/// blocks preceding it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LightClientUpdate {
+    /// The serialization version of this update.
+    pub version: u16,
+    /// The state trie root of the checkpoint block the proofs are anchored to.
+    pub checkpoint_state_trie_root: H256,
+    /// The RLP-encoded headers of the blocks preceding `header` covered by
+    /// this update, in ascending order. There are at most
+    /// [`MAX_ANCESTOR_HASHES`] of them.
+    pub ancestor_headers: Vec<Vec<u8>>,
+    /// The header of the last block covered by this update.
+    pub header: LightClientHeader,
+    /// The serialized block proof of `header`.
+    pub proof: Vec<u8>,
+}
+
+/// Decodes the public values of a block proof.
+fn block_public_values(proof: &PlonkyProofIntern) -> ProofGenResult<PublicValues> {
+    PublicValues::from_public_inputs(extract_block_public_values(&proof.public_inputs))
+        .map_err(|err| err.to_string().into())
+}
+
+impl LightClientUpdate {
+    /// Builds an update from a range of at most 257 consecutive block proofs,
+    /// sorted in ascending block order, along with the RLP-encoded headers of
+    /// their blocks.
+    pub fn from_block_proofs(
+        proofs: &[GeneratedBlockProof],
+        headers: Vec<Vec<u8>>,
+    ) -> ProofGenResult<Self> {
+        if headers.len() != proofs.len() {
+            return Err(format!(
+                "got {} headers for {} block proofs",
+                headers.len(),
+                proofs.len()
+            )
+            .into());
+        }
+        let last = proofs
+            .last()
+            .ok_or_else(|| ProofGenError("no block proof to export".to_string()))?;
+
+        let p_vals = proofs
+            .iter()
+            .map(|proof| block_public_values(&proof.intern))
+            .collect::<ProofGenResult<Vec<_>>>()?;
+        for (p_vals, header) in p_vals.iter().zip(&headers) {
+            if LightClientHeader::from_rlp(header)? != LightClientHeader::from(p_vals) {
+                return Err(format!(
+                    "header of block {} does not match its proof",
+                    p_vals.block_metadata.block_number
+                )
+                .into());
+            }
+        }
+
+        let mut ancestor_headers = headers;
+        ancestor_headers.pop();
+        let update = Self {
+            version: LIGHT_CLIENT_UPDATE_VERSION,
+            checkpoint_state_trie_root: p_vals[0].extra_block_data.checkpoint_state_trie_root,
+            ancestor_headers,
+            header: LightClientHeader::from(&p_vals[p_vals.len() - 1]),
+            proof: last.intern.to_bytes(),
+        };
+        update.check_headers(&p_vals[p_vals.len() - 1])?;
+
+        Ok(update)
+    }
+
+    /// Checks the header and ancestor headers against the public values of
+    /// the proof of the update, returning all the headers covered by the
+    /// update in ascending order.
+    fn check_headers(&self, p_vals: &PublicValues) -> ProofGenResult<Vec<LightClientHeader>> {
+        if self.ancestor_headers.len() > MAX_ANCESTOR_HASHES {
+            return Err(format!(
+                "light client update spans {} blocks, but a block proof only commits to the \
+                 hashes of the {MAX_ANCESTOR_HASHES} blocks preceding it",
+                self.ancestor_headers.len() + 1
+            )
+            .into());
+        }
+
+        if LightClientHeader::from(p_vals) != self.header {
+            return Err("header does not match the proof public values"
+                .to_string()
+                .into());
+        }
+        if p_vals.extra_block_data.checkpoint_state_trie_root != self.checkpoint_state_trie_root {
+            return Err(
+                "checkpoint state trie root does not match the proof public values"
+                    .to_string()
+                    .into(),
+            );
+        }
+
+        let mut headers = self
+            .ancestor_headers
+            .iter()
+            .map(|header| LightClientHeader::from_rlp(header))
+            .collect::<ProofGenResult<Vec<_>>>()?;
+        // The proof commits to the hashes of the 256 blocks preceding the header.
+        let prev_hashes = &p_vals.block_hashes.prev_hashes;
+        let committed_hashes = &prev_hashes[prev_hashes.len() - headers.len()..];
+        if headers
+            .iter()
+            .zip(committed_hashes)
+            .any(|(header, hash)| header.hash != *hash)
+        {
+            return Err("ancestor headers do not match the proof public values"
+                .to_string()
+                .into());
+        }
+
+        headers.push(self.header.clone());
+        for (parent, child) in headers.iter().zip(headers.iter().skip(1)) {
+            if child.number != parent.number + 1 || child.parent_hash != parent.hash {
+                return Err(format!(
+                    "header {} does not extend header {}",
+                    child.number, parent.number
+                )
+                .into());
+            }
+        }
+
+        Ok(headers)
+    }
+
+    /// Verifies the update: its version, the consistency of its header and
+    /// ancestor headers with the public values of the proof, and the proof
+    /// itself.
+    ///
+    /// Returns the headers of all the blocks covered by the update, in
+    /// ascending order.
+    pub fn verify(&self, verifier: &VerifierState) -> ProofGenResult<Vec<LightClientHeader>> {
+        if self.version != LIGHT_CLIENT_UPDATE_VERSION {
+            return Err(format!(
+                "unsupported light client update version {} (expected {})",
+                self.version, LIGHT_CLIENT_UPDATE_VERSION
+            )
+            .into());
+        }
+
+        let proof: PlonkyProofIntern =
+            ProofWithPublicInputs::from_bytes(self.proof.clone(), &verifier.state.common)
+                .map_err(|err| err.to_string())?;
+        let headers = self.check_headers(&block_public_values(&proof)?)?;
+
+        verifier.verify(&proof)?;
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{Address, Bloom};
+    use rlp::RlpStream;
+
+    use super::*;
+
+    /// RLP-encodes a pre-London block header.
+    fn encode_header(number: u64, parent_hash: H256, state_root: H256) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(15);
+        stream
+            .append(&parent_hash)
+            .append(&keccak(rlp::EMPTY_LIST_RLP))
+            .append(&Address::zero())
+            .append(&state_root)
+            .append(&H256::from_low_u64_be(number + 1000))
+            .append(&H256::from_low_u64_be(number + 2000))
+            .append(&Bloom::zero())
+            .append(&U256::from(1))
+            .append(&U256::from(number))
+            .append(&U256::from(30_000_000))
+            .append(&U256::zero())
+            .append(&U256::from(1_700_000_000 + 12 * number))
+            .append_empty_data()
+            .append(&H256::zero())
+            .append(&[0u8; 8].as_slice());
+        stream.out().to_vec()
+    }
+
+    /// Builds a chain of `len` RLP-encoded headers, the public values of a
+    /// proof of the last one, and an update covering the whole chain.
+    fn chain(len: u64) -> (Vec<Vec<u8>>, PublicValues, LightClientUpdate) {
+        let mut headers: Vec<Vec<u8>> = vec![];
+        for number in 100..100 + len {
+            let parent_hash = headers.last().map(keccak).unwrap_or_default();
+            headers.push(encode_header(
+                number,
+                parent_hash,
+                H256::from_low_u64_be(number),
+            ));
+        }
+
+        let tip = LightClientHeader::from_rlp(headers.last().unwrap()).unwrap();
+        let mut p_vals = PublicValues::default();
+        p_vals.block_metadata.block_number = tip.number;
+        p_vals.block_metadata.block_timestamp = tip.timestamp;
+        p_vals.block_hashes.cur_hash = tip.hash;
+        let prev_hashes = &mut p_vals.block_hashes.prev_hashes;
+        let ancestors = headers.len() - 1;
+        for (slot, header) in prev_hashes[256 - ancestors..].iter_mut().zip(&headers) {
+            *slot = keccak(header);
+        }
+        p_vals.trie_roots_after.state_root = tip.state_root;
+        p_vals.trie_roots_after.transactions_root = tip.transactions_root;
+        p_vals.trie_roots_after.receipts_root = tip.receipts_root;
+        p_vals.extra_block_data.checkpoint_state_trie_root = H256::repeat_byte(0xcc);
+
+        let update = LightClientUpdate {
+            version: LIGHT_CLIENT_UPDATE_VERSION,
+            checkpoint_state_trie_root: H256::repeat_byte(0xcc),
+            ancestor_headers: headers[..ancestors].to_vec(),
+            header: tip,
+            proof: vec![],
+        };
+
+        (headers, p_vals, update)
+    }
+
+    #[test]
+    fn decodes_header_fields() {
+        let parent_hash = H256::repeat_byte(0x11);
+        let rlp = encode_header(42, parent_hash, H256::repeat_byte(0x22));
+        let header = LightClientHeader::from_rlp(&rlp).unwrap();
+
+        assert_eq!(header.number, 42.into());
+        assert_eq!(header.hash, keccak(&rlp));
+        assert_eq!(header.parent_hash, parent_hash);
+        assert_eq!(header.timestamp, (1_700_000_000 + 12 * 42).into());
+        assert_eq!(header.state_root, H256::repeat_byte(0x22));
+        assert_eq!(header.transactions_root, H256::from_low_u64_be(1042));
+        assert_eq!(header.receipts_root, H256::from_low_u64_be(2042));
+
+        assert!(LightClientHeader::from_rlp(&rlp[..rlp.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn returns_the_state_root_of_every_block() {
+        let (headers, p_vals, update) = chain(5);
+        let verified = update.check_headers(&p_vals).unwrap();
+
+        assert_eq!(verified.len(), headers.len());
+        for (header, number) in verified.iter().zip(100..) {
+            assert_eq!(header.number, number.into());
+            assert_eq!(header.state_root, H256::from_low_u64_be(number));
+        }
+    }
+
+    #[test]
+    fn rejects_unbound_ancestor_headers() {
+        let (_, p_vals, mut update) = chain(5);
+        update.ancestor_headers[1] = encode_header(
+            101,
+            keccak(&update.ancestor_headers[0]),
+            H256::repeat_byte(0xff),
+        );
+        assert!(update.check_headers(&p_vals).is_err());
+
+        let (_, p_vals, mut update) = chain(5);
+        update.ancestor_headers.swap(0, 1);
+        assert!(update.check_headers(&p_vals).is_err());
+
+        let (_, p_vals, mut update) = chain(5);
+        update.checkpoint_state_trie_root = H256::zero();
+        assert!(update.check_headers(&p_vals).is_err());
+    }
+
+    #[test]
+    fn rejects_ranges_longer_than_the_committed_hashes() {
+        let (_, p_vals, update) = chain(MAX_ANCESTOR_HASHES as u64 + 1);
+        assert!(update.check_headers(&p_vals).is_ok());
+
+        let (_, p_vals, mut update) = chain(MAX_ANCESTOR_HASHES as u64 + 1);
+        update
+            .ancestor_headers
+            .insert(0, encode_header(99, H256::zero(), H256::zero()));
+        assert!(update.check_headers(&p_vals).is_err());
+    }
+}
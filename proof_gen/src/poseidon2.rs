@@ -0,0 +1,978 @@
+//! The Poseidon2 permutation over the Goldilocks field, and the plonky2 hasher
+//! and configuration built on it, selected by the `poseidon2` feature.
+//!
+//! The instance is the width 12 one of the
+//! [reference implementation](https://github.com/HorizenLabs/poseidon2), with
+//! the `x^7` S-box, 8 full rounds and 22 partial rounds. Its round constants
+//! are generated as by the parameter script of the reference, and its internal
+//! matrix is copied from the reference instance.
+//!
+//! The pinned plonky2 revision provides no Poseidon2 gate, so permutations in
+//! circuits are computed by the [`Poseidon2Gate`] of this module. As
+//! [`DefaultGateSerializer`] and [`DefaultGeneratorSerializer`] do not know of
+//! it, circuits built with [`Poseidon2GoldilocksConfig`] must be serialized
+//! with [`Poseidon2GateSerializer`] and [`Poseidon2GeneratorSerializer`].
+
+use std::marker::PhantomData;
+
+use plonky2::{
+    field::{
+        extension::{quadratic::QuadraticExtension, Extendable},
+        goldilocks_field::GoldilocksField,
+        types::{Field, Field64},
+    },
+    gates::{
+        gate::{Gate, GateRef},
+        util::StridedConstraintConsumer,
+    },
+    hash::{
+        hash_types::{HashOut, RichField},
+        hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation},
+    },
+    iop::{
+        ext_target::ExtensionTarget,
+        generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef},
+        target::{BoolTarget, Target},
+        wire::Wire,
+        witness::{PartitionWitness, Witness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::CommonCircuitData,
+        config::{AlgebraicHasher, GenericConfig, Hasher},
+        vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase},
+    },
+    util::serialization::{
+        Buffer, DefaultGateSerializer, DefaultGeneratorSerializer, GateSerializer, IoResult, Read,
+        WitnessGeneratorSerializer, Write,
+    },
+};
+use serde::Serialize;
+
+/// The width of the permutation.
+pub const WIDTH: usize = 12;
+/// The number of elements absorbed and squeezed by each permutation.
+const RATE: usize = 8;
+/// The number of full rounds before, and after, the partial rounds.
+const HALF_N_FULL_ROUNDS: usize = 4;
+const N_PARTIAL_ROUNDS: usize = 22;
+
+/// The length of the Grain LFSR generating the round constants.
+const GRAIN_LEN: usize = 80;
+/// The bits of the Grain LFSR whose sum is shifted in, from the oldest.
+const GRAIN_TAPS: [usize; 6] = [0, 13, 23, 38, 51, 62];
+
+/// The Grain LFSR, in self-shrinking mode, from which the reference derives
+/// the round constants in its `poseidon2_rust_params.sage` script.
+struct Grain {
+    state: [bool; GRAIN_LEN],
+    /// The index of the oldest bit of the state.
+    head: usize,
+}
+
+impl Grain {
+    /// Seeds the LFSR with the parameters of the instance, i.e. a prime field,
+    /// the `x^alpha` S-box, 64-bit elements, the width and the numbers of full
+    /// and partial rounds, padded with ones, then discards its first 160 bits.
+    const fn new() -> Self {
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (64, 12),
+            (WIDTH, 12),
+            (2 * HALF_N_FULL_ROUNDS, 10),
+            (N_PARTIAL_ROUNDS, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        let mut grain = Self {
+            state: [false; GRAIN_LEN],
+            head: 0,
+        };
+        let mut len = 0;
+        let mut i = 0;
+        while i < fields.len() {
+            let (value, mut bits) = fields[i];
+            while bits > 0 {
+                bits -= 1;
+                grain.state[len] = (value >> bits) & 1 == 1;
+                len += 1;
+            }
+            i += 1;
+        }
+        let mut i = 0;
+        while i < 2 * GRAIN_LEN {
+            grain.shift();
+            i += 1;
+        }
+        grain
+    }
+
+    /// Shifts the register, returning the bit shifted in.
+    const fn shift(&mut self) -> bool {
+        let mut bit = false;
+        let mut i = 0;
+        while i < GRAIN_TAPS.len() {
+            bit ^= self.state[(self.head + GRAIN_TAPS[i]) % GRAIN_LEN];
+            i += 1;
+        }
+        self.state[self.head] = bit;
+        self.head = (self.head + 1) % GRAIN_LEN;
+        bit
+    }
+
+    /// Returns the next output bit: of each pair of bits shifted in, the
+    /// second is output if the first is set, and discarded otherwise.
+    const fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.shift();
+            let bit = self.shift();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Returns the next field element, drawing 64-bit integers until one is
+    /// canonical.
+    const fn next_element(&mut self) -> u64 {
+        loop {
+            let mut x = 0;
+            let mut i = 0;
+            while i < 64 {
+                x = (x << 1) | self.next_bit() as u64;
+                i += 1;
+            }
+            if x < GoldilocksField::ORDER {
+                return x;
+            }
+        }
+    }
+
+    const fn next_elements(&mut self) -> [u64; WIDTH] {
+        let mut elements = [0; WIDTH];
+        let mut i = 0;
+        while i < WIDTH {
+            elements[i] = self.next_element();
+            i += 1;
+        }
+        elements
+    }
+}
+
+/// The round constants of the first half of the full rounds, of the partial
+/// rounds and of the second half of the full rounds.
+type RoundConstants = (
+    [[u64; WIDTH]; HALF_N_FULL_ROUNDS],
+    [u64; N_PARTIAL_ROUNDS],
+    [[u64; WIDTH]; HALF_N_FULL_ROUNDS],
+);
+
+/// Draws the round constants from the [`Grain`] LFSR in the order of the
+/// rounds, as the reference does: `WIDTH` of them for each full round, and a
+/// single one for each partial round.
+const fn round_constants() -> RoundConstants {
+    let mut grain = Grain::new();
+    let mut constants: RoundConstants = (
+        [[0; WIDTH]; HALF_N_FULL_ROUNDS],
+        [0; N_PARTIAL_ROUNDS],
+        [[0; WIDTH]; HALF_N_FULL_ROUNDS],
+    );
+    let mut round = 0;
+    while round < HALF_N_FULL_ROUNDS {
+        constants.0[round] = grain.next_elements();
+        round += 1;
+    }
+    let mut round = 0;
+    while round < N_PARTIAL_ROUNDS {
+        constants.1[round] = grain.next_element();
+        round += 1;
+    }
+    let mut round = 0;
+    while round < HALF_N_FULL_ROUNDS {
+        constants.2[round] = grain.next_elements();
+        round += 1;
+    }
+    constants
+}
+
+const ROUND_CONSTANTS: RoundConstants = round_constants();
+
+/// The round constants of the first half of the full rounds.
+const INITIAL_ROUND_CONSTANTS: [[u64; WIDTH]; HALF_N_FULL_ROUNDS] = ROUND_CONSTANTS.0;
+
+/// The round constants of the partial rounds, added to the first element of
+/// the state only.
+const PARTIAL_ROUND_CONSTANTS: [u64; N_PARTIAL_ROUNDS] = ROUND_CONSTANTS.1;
+
+/// The round constants of the second half of the full rounds.
+const TERMINAL_ROUND_CONSTANTS: [[u64; WIDTH]; HALF_N_FULL_ROUNDS] = ROUND_CONSTANTS.2;
+
+/// The diagonal of the internal matrix, minus the identity, copied from
+/// `MAT_DIAG12_M_1` in `src/poseidon2/poseidon2_instance_goldilocks.rs` of the
+/// reference.
+const INTERNAL_DIAG_M_1: [u64; WIDTH] = [
+    0xc3b6c08e23ba9300,
+    0xd84b5de94a324fb6,
+    0x0d0c371c5b35b84f,
+    0x7964f570e7188037,
+    0x5daf18bbd996604b,
+    0x6743bc47b9595257,
+    0x5528b9362c59bb70,
+    0xac45e25b7127b68b,
+    0xa2077d7dfbb606b5,
+    0xf3faac6faee378ae,
+    0x0c6388b51545e883,
+    0xd27dbb6944917b60,
+];
+
+/// The `x^7` S-box.
+fn sbox<T: Field>(x: T) -> T {
+    let x2 = x.square();
+    let x4 = x2.square();
+    x4 * x2 * x
+}
+
+/// Multiplies the state by the external matrix: each chunk of 4 elements is
+/// multiplied by the 4x4 MDS matrix of the reference, then the sum of the
+/// elements at the same position of every chunk is added to each of them.
+fn external_linear_layer<T: Field>(state: &mut [T; WIDTH]) {
+    for chunk in state.chunks_exact_mut(4) {
+        let t0 = chunk[0] + chunk[1];
+        let t1 = chunk[2] + chunk[3];
+        let t2 = chunk[1].double() + t1;
+        let t3 = chunk[3].double() + t0;
+        let t4 = t1.double().double() + t3;
+        let t5 = t0.double().double() + t2;
+        chunk[0] = t3 + t5;
+        chunk[1] = t5;
+        chunk[2] = t2 + t4;
+        chunk[3] = t4;
+    }
+    let sums: [T; 4] =
+        std::array::from_fn(|i| (i..WIDTH).step_by(4).fold(T::ZERO, |acc, j| acc + state[j]));
+    for (i, x) in state.iter_mut().enumerate() {
+        *x += sums[i % 4];
+    }
+}
+
+/// Multiplies the state by the internal matrix, i.e. the all-ones matrix plus
+/// the diagonal [`INTERNAL_DIAG_M_1`].
+fn internal_linear_layer<T: Field>(state: &mut [T; WIDTH]) {
+    let sum = state.iter().fold(T::ZERO, |acc, &x| acc + x);
+    for (x, &diag) in state.iter_mut().zip(&INTERNAL_DIAG_M_1) {
+        *x = *x * T::from_canonical_u64(diag) + sum;
+    }
+}
+
+fn add_round_constants<T: Field>(state: &mut [T; WIDTH], round_constants: &[u64; WIDTH]) {
+    for (x, &c) in state.iter_mut().zip(round_constants) {
+        *x += T::from_canonical_u64(c);
+    }
+}
+
+/// The Poseidon2 permutation.
+pub fn permute<T: Field>(mut state: [T; WIDTH]) -> [T; WIDTH] {
+    external_linear_layer(&mut state);
+    for round_constants in &INITIAL_ROUND_CONSTANTS {
+        add_round_constants(&mut state, round_constants);
+        state = state.map(sbox);
+        external_linear_layer(&mut state);
+    }
+    for &round_constant in &PARTIAL_ROUND_CONSTANTS {
+        state[0] = sbox(state[0] + T::from_canonical_u64(round_constant));
+        internal_linear_layer(&mut state);
+    }
+    for round_constants in &TERMINAL_ROUND_CONSTANTS {
+        add_round_constants(&mut state, round_constants);
+        state = state.map(sbox);
+        external_linear_layer(&mut state);
+    }
+    state
+}
+
+/// The types whose states can be permuted natively.
+///
+/// States of targets must be permuted in circuits, with
+/// [`AlgebraicHasher::permute_swapped`].
+pub trait Permuter: Sized {
+    fn permute(state: [Self; WIDTH]) -> [Self; WIDTH];
+}
+
+impl Permuter for GoldilocksField {
+    fn permute(state: [Self; WIDTH]) -> [Self; WIDTH] {
+        permute(state)
+    }
+}
+
+impl Permuter for Target {
+    fn permute(_state: [Self; WIDTH]) -> [Self; WIDTH] {
+        panic!("Call `permute_swapped()` instead of `permute()`");
+    }
+}
+
+/// The state of a [`Poseidon2Hash`] sponge.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct Poseidon2Permutation<T> {
+    state: [T; WIDTH],
+}
+
+impl<T> AsRef<[T]> for Poseidon2Permutation<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.state
+    }
+}
+
+impl<T: Copy + std::fmt::Debug + Default + Eq + Permuter + Send + Sync> PlonkyPermutation<T>
+    for Poseidon2Permutation<T>
+{
+    const RATE: usize = RATE;
+    const WIDTH: usize = WIDTH;
+
+    fn new<I: IntoIterator<Item = T>>(elts: I) -> Self {
+        let mut perm = Self::default();
+        perm.set_from_iter(elts, 0);
+        perm
+    }
+
+    fn set_elt(&mut self, elt: T, idx: usize) {
+        self.state[idx] = elt;
+    }
+
+    fn set_from_slice(&mut self, elts: &[T], start_idx: usize) {
+        self.state[start_idx..start_idx + elts.len()].copy_from_slice(elts);
+    }
+
+    fn set_from_iter<I: IntoIterator<Item = T>>(&mut self, elts: I, start_idx: usize) {
+        for (s, e) in self.state[start_idx..].iter_mut().zip(elts) {
+            *s = e;
+        }
+    }
+
+    fn permute(&mut self) {
+        self.state = T::permute(self.state);
+    }
+
+    fn squeeze(&self) -> &[T] {
+        &self.state[..RATE]
+    }
+}
+
+/// Poseidon2 hash function over the Goldilocks field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Poseidon2Hash;
+
+impl Hasher<GoldilocksField> for Poseidon2Hash {
+    const HASH_SIZE: usize = 4 * 8;
+    type Hash = HashOut<GoldilocksField>;
+    type Permutation = Poseidon2Permutation<GoldilocksField>;
+
+    fn hash_no_pad(input: &[GoldilocksField]) -> Self::Hash {
+        hash_n_to_hash_no_pad::<GoldilocksField, Self::Permutation>(input)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        compress::<GoldilocksField, Self::Permutation>(left, right)
+    }
+}
+
+impl AlgebraicHasher<GoldilocksField> for Poseidon2Hash {
+    type AlgebraicPermutation = Poseidon2Permutation<Target>;
+
+    fn permute_swapped<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<GoldilocksField, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        GoldilocksField: RichField + Extendable<D>,
+    {
+        let gate = builder.add_gate(Poseidon2Gate::<GoldilocksField, D>::new(), vec![]);
+
+        builder.connect(
+            swap.target,
+            Target::wire(gate, Poseidon2Gate::<GoldilocksField, D>::WIRE_SWAP),
+        );
+        for (i, &input) in inputs.as_ref().iter().enumerate() {
+            builder.connect(
+                input,
+                Target::wire(gate, Poseidon2Gate::<GoldilocksField, D>::wire_input(i)),
+            );
+        }
+
+        Self::AlgebraicPermutation::new(
+            (0..WIDTH)
+                .map(|i| Target::wire(gate, Poseidon2Gate::<GoldilocksField, D>::wire_output(i))),
+        )
+    }
+}
+
+/// Configuration using Poseidon2 over the Goldilocks field.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct Poseidon2GoldilocksConfig;
+
+impl GenericConfig<2> for Poseidon2GoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = Poseidon2Hash;
+    type InnerHasher = Poseidon2Hash;
+}
+
+/// Evaluates a Poseidon2 permutation of the state in a single row, optionally
+/// swapping its first two chunks of 4 elements beforehand, for Merkle proofs.
+///
+/// Its layout follows the one of plonky2's Poseidon gate: the inputs of the
+/// S-boxes are stored in wires for every round but the first one, so that the
+/// constraints are of degree 7.
+#[derive(Debug, Default)]
+pub struct Poseidon2Gate<F: RichField + Extendable<D>, const D: usize>(PhantomData<F>);
+
+impl<F: RichField + Extendable<D>, const D: usize> Poseidon2Gate<F, D> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    /// The wire of the `i`-th element of the input state.
+    pub const fn wire_input(i: usize) -> usize {
+        i
+    }
+
+    /// The wire of the `i`-th element of the output state.
+    pub const fn wire_output(i: usize) -> usize {
+        WIDTH + i
+    }
+
+    /// The wire of the boolean swapping the first two chunks of the input
+    /// state.
+    pub const WIRE_SWAP: usize = 2 * WIDTH;
+
+    const START_DELTA: usize = 2 * WIDTH + 1;
+
+    /// The wire of `swap * (input[i + 4] - input[i])`.
+    const fn wire_delta(i: usize) -> usize {
+        Self::START_DELTA + i
+    }
+
+    const START_INITIAL_SBOX: usize = Self::START_DELTA + 4;
+
+    /// The wire of the input of the `i`-th S-box of the given initial full
+    /// round, which cannot be the first one.
+    const fn wire_initial_sbox(round: usize, i: usize) -> usize {
+        debug_assert!(round != 0);
+        Self::START_INITIAL_SBOX + WIDTH * (round - 1) + i
+    }
+
+    const START_PARTIAL_SBOX: usize = Self::START_INITIAL_SBOX + WIDTH * (HALF_N_FULL_ROUNDS - 1);
+
+    /// The wire of the input of the S-box of the given partial round.
+    const fn wire_partial_sbox(round: usize) -> usize {
+        Self::START_PARTIAL_SBOX + round
+    }
+
+    const START_TERMINAL_SBOX: usize = Self::START_PARTIAL_SBOX + N_PARTIAL_ROUNDS;
+
+    /// The wire of the input of the `i`-th S-box of the given terminal full
+    /// round.
+    const fn wire_terminal_sbox(round: usize, i: usize) -> usize {
+        Self::START_TERMINAL_SBOX + WIDTH * round + i
+    }
+
+    const NUM_WIRES: usize = Self::START_TERMINAL_SBOX + WIDTH * HALF_N_FULL_ROUNDS;
+
+    /// The constraints of the gate, given the values of its wires.
+    fn eval<T: Field>(wire: impl Fn(usize) -> T) -> Vec<T> {
+        let mut constraints = Vec::with_capacity(Self::NUM_CONSTRAINTS);
+
+        // Assert that `swap` is binary, and that each delta wire is set properly.
+        let swap = wire(Self::WIRE_SWAP);
+        constraints.push(swap * (swap - T::ONE));
+        let mut state = std::array::from_fn(|i| wire(Self::wire_input(i)));
+        for i in 0..4 {
+            let (lhs, rhs) = (state[i], state[i + 4]);
+            let delta = wire(Self::wire_delta(i));
+            constraints.push(swap * (rhs - lhs) - delta);
+            state[i] = lhs + delta;
+            state[i + 4] = rhs - delta;
+        }
+
+        external_linear_layer(&mut state);
+        for (round, round_constants) in INITIAL_ROUND_CONSTANTS.iter().enumerate() {
+            add_round_constants(&mut state, round_constants);
+            if round != 0 {
+                for (i, x) in state.iter_mut().enumerate() {
+                    let sbox_in = wire(Self::wire_initial_sbox(round, i));
+                    constraints.push(*x - sbox_in);
+                    *x = sbox_in;
+                }
+            }
+            state = state.map(sbox);
+            external_linear_layer(&mut state);
+        }
+        for (round, &round_constant) in PARTIAL_ROUND_CONSTANTS.iter().enumerate() {
+            let sbox_in = wire(Self::wire_partial_sbox(round));
+            constraints.push(state[0] + T::from_canonical_u64(round_constant) - sbox_in);
+            state[0] = sbox(sbox_in);
+            internal_linear_layer(&mut state);
+        }
+        for (round, round_constants) in TERMINAL_ROUND_CONSTANTS.iter().enumerate() {
+            add_round_constants(&mut state, round_constants);
+            for (i, x) in state.iter_mut().enumerate() {
+                let sbox_in = wire(Self::wire_terminal_sbox(round, i));
+                constraints.push(*x - sbox_in);
+                *x = sbox(sbox_in);
+            }
+            external_linear_layer(&mut state);
+        }
+
+        for (i, x) in state.into_iter().enumerate() {
+            constraints.push(x - wire(Self::wire_output(i)));
+        }
+        constraints
+    }
+
+    const NUM_CONSTRAINTS: usize = 1
+        + 4
+        + WIDTH * (HALF_N_FULL_ROUNDS - 1)
+        + N_PARTIAL_ROUNDS
+        + WIDTH * HALF_N_FULL_ROUNDS
+        + WIDTH;
+}
+
+/// The circuit version of [`external_linear_layer`].
+fn external_linear_layer_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &mut [ExtensionTarget<D>; WIDTH],
+) {
+    let four = F::from_canonical_u64(4);
+    for chunk in state.chunks_exact_mut(4) {
+        let t0 = builder.add_extension(chunk[0], chunk[1]);
+        let t1 = builder.add_extension(chunk[2], chunk[3]);
+        let t2 = builder.mul_const_add_extension(F::TWO, chunk[1], t1);
+        let t3 = builder.mul_const_add_extension(F::TWO, chunk[3], t0);
+        let t4 = builder.mul_const_add_extension(four, t1, t3);
+        let t5 = builder.mul_const_add_extension(four, t0, t2);
+        chunk[0] = builder.add_extension(t3, t5);
+        chunk[1] = t5;
+        chunk[2] = builder.add_extension(t2, t4);
+        chunk[3] = t4;
+    }
+    let sums: [_; 4] = std::array::from_fn(|i| {
+        builder.add_many_extension((i..WIDTH).step_by(4).map(|j| state[j]))
+    });
+    for (i, x) in state.iter_mut().enumerate() {
+        *x = builder.add_extension(*x, sums[i % 4]);
+    }
+}
+
+/// The circuit version of [`internal_linear_layer`].
+fn internal_linear_layer_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    state: &mut [ExtensionTarget<D>; WIDTH],
+) {
+    let sum = builder.add_many_extension(state.iter().copied());
+    for (x, &diag) in state.iter_mut().zip(&INTERNAL_DIAG_M_1) {
+        *x = builder.mul_const_add_extension(F::from_canonical_u64(diag), *x, sum);
+    }
+}
+
+/// The circuit version of [`sbox`].
+fn sbox_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    let x2 = builder.mul_extension(x, x);
+    let x4 = builder.mul_extension(x2, x2);
+    let x6 = builder.mul_extension(x4, x2);
+    builder.mul_extension(x6, x)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for Poseidon2Gate<F, D> {
+    fn id(&self) -> String {
+        format!("Poseidon2Gate<WIDTH={WIDTH}>")
+    }
+
+    fn serialize(
+        &self,
+        _dst: &mut Vec<u8>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn deserialize(_src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Ok(Self::new())
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        Self::eval(|i| vars.local_wires[i])
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        yield_constr.many(Self::eval(|i| vars.local_wires[i]));
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let wire = |i: usize| vars.local_wires[i];
+        let mut constraints = Vec::with_capacity(Self::NUM_CONSTRAINTS);
+
+        let swap = wire(Self::WIRE_SWAP);
+        constraints.push(builder.mul_sub_extension(swap, swap, swap));
+        let mut state = std::array::from_fn(|i| wire(Self::wire_input(i)));
+        for i in 0..4 {
+            let (lhs, rhs) = (state[i], state[i + 4]);
+            let delta = wire(Self::wire_delta(i));
+            let diff = builder.sub_extension(rhs, lhs);
+            constraints.push(builder.mul_sub_extension(swap, diff, delta));
+            state[i] = builder.add_extension(lhs, delta);
+            state[i + 4] = builder.sub_extension(rhs, delta);
+        }
+
+        external_linear_layer_circuit(builder, &mut state);
+        for (round, round_constants) in INITIAL_ROUND_CONSTANTS.iter().enumerate() {
+            for (i, x) in state.iter_mut().enumerate() {
+                let mut sbox_in =
+                    builder.add_const_extension(*x, F::from_canonical_u64(round_constants[i]));
+                if round != 0 {
+                    let wire_in = wire(Self::wire_initial_sbox(round, i));
+                    constraints.push(builder.sub_extension(sbox_in, wire_in));
+                    sbox_in = wire_in;
+                }
+                *x = sbox_circuit(builder, sbox_in);
+            }
+            external_linear_layer_circuit(builder, &mut state);
+        }
+        for (round, &round_constant) in PARTIAL_ROUND_CONSTANTS.iter().enumerate() {
+            let sbox_in =
+                builder.add_const_extension(state[0], F::from_canonical_u64(round_constant));
+            let wire_in = wire(Self::wire_partial_sbox(round));
+            constraints.push(builder.sub_extension(sbox_in, wire_in));
+            state[0] = sbox_circuit(builder, wire_in);
+            internal_linear_layer_circuit(builder, &mut state);
+        }
+        for (round, round_constants) in TERMINAL_ROUND_CONSTANTS.iter().enumerate() {
+            for (i, x) in state.iter_mut().enumerate() {
+                let sbox_in =
+                    builder.add_const_extension(*x, F::from_canonical_u64(round_constants[i]));
+                let wire_in = wire(Self::wire_terminal_sbox(round, i));
+                constraints.push(builder.sub_extension(sbox_in, wire_in));
+                *x = sbox_circuit(builder, wire_in);
+            }
+            external_linear_layer_circuit(builder, &mut state);
+        }
+
+        for (i, x) in state.into_iter().enumerate() {
+            constraints.push(builder.sub_extension(x, wire(Self::wire_output(i))));
+        }
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        let gen = Poseidon2Generator::<F, D> {
+            row,
+            _phantom: PhantomData,
+        };
+        vec![WitnessGeneratorRef::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        Self::NUM_WIRES
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        7
+    }
+
+    fn num_constraints(&self) -> usize {
+        Self::NUM_CONSTRAINTS
+    }
+}
+
+/// Fills the wires of a [`Poseidon2Gate`] from its inputs.
+#[derive(Debug, Default)]
+pub struct Poseidon2Generator<F: RichField + Extendable<D>, const D: usize> {
+    row: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for Poseidon2Generator<F, D>
+{
+    fn id(&self) -> String {
+        "Poseidon2Generator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        (0..WIDTH)
+            .map(Poseidon2Gate::<F, D>::wire_input)
+            .chain([Poseidon2Gate::<F, D>::WIRE_SWAP])
+            .map(|column| Target::wire(self.row, column))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let swap = witness.get_wire(wire(Poseidon2Gate::<F, D>::WIRE_SWAP));
+        debug_assert!(swap == F::ZERO || swap == F::ONE);
+        let mut state: [F; WIDTH] =
+            std::array::from_fn(|i| witness.get_wire(wire(Poseidon2Gate::<F, D>::wire_input(i))));
+        for i in 0..4 {
+            let delta = swap * (state[i + 4] - state[i]);
+            out_buffer.set_wire(wire(Poseidon2Gate::<F, D>::wire_delta(i)), delta);
+            state[i] += delta;
+            state[i + 4] -= delta;
+        }
+
+        external_linear_layer(&mut state);
+        for (round, round_constants) in INITIAL_ROUND_CONSTANTS.iter().enumerate() {
+            add_round_constants(&mut state, round_constants);
+            if round != 0 {
+                for (i, &x) in state.iter().enumerate() {
+                    out_buffer
+                        .set_wire(wire(Poseidon2Gate::<F, D>::wire_initial_sbox(round, i)), x);
+                }
+            }
+            state = state.map(sbox);
+            external_linear_layer(&mut state);
+        }
+        for (round, &round_constant) in PARTIAL_ROUND_CONSTANTS.iter().enumerate() {
+            state[0] += F::from_canonical_u64(round_constant);
+            out_buffer.set_wire(
+                wire(Poseidon2Gate::<F, D>::wire_partial_sbox(round)),
+                state[0],
+            );
+            state[0] = sbox(state[0]);
+            internal_linear_layer(&mut state);
+        }
+        for (round, round_constants) in TERMINAL_ROUND_CONSTANTS.iter().enumerate() {
+            add_round_constants(&mut state, round_constants);
+            for (i, &x) in state.iter().enumerate() {
+                out_buffer.set_wire(wire(Poseidon2Gate::<F, D>::wire_terminal_sbox(round, i)), x);
+            }
+            state = state.map(sbox);
+            external_linear_layer(&mut state);
+        }
+
+        for (i, x) in state.into_iter().enumerate() {
+            out_buffer.set_wire(wire(Poseidon2Gate::<F, D>::wire_output(i)), x);
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        Ok(Self {
+            row,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// The tag of [`Poseidon2Gate`]s and [`Poseidon2Generator`]s in serialized
+/// circuits, which the default serializers never write.
+const POSEIDON2_TAG: u32 = u32::MAX;
+
+/// Serializes the gates of circuits built with [`Poseidon2GoldilocksConfig`],
+/// deferring to [`DefaultGateSerializer`] for all but [`Poseidon2Gate`]s.
+#[derive(Debug, Default)]
+pub struct Poseidon2GateSerializer;
+
+impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D>
+    for Poseidon2GateSerializer
+{
+    fn read_gate(
+        &self,
+        buf: &mut Buffer,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<GateRef<F, D>> {
+        match buf.read_u32()? {
+            POSEIDON2_TAG => Ok(GateRef::new(Poseidon2Gate::<F, D>::deserialize(
+                buf,
+                common_data,
+            )?)),
+            _ => DefaultGateSerializer.read_gate(buf, common_data),
+        }
+    }
+
+    fn write_gate(
+        &self,
+        buf: &mut Vec<u8>,
+        gate: &GateRef<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        if gate.0.id() == Poseidon2Gate::<F, D>::new().id() {
+            buf.write_u32(POSEIDON2_TAG)?;
+            gate.0.serialize(buf, common_data)
+        } else {
+            buf.write_u32(0)?;
+            DefaultGateSerializer.write_gate(buf, gate, common_data)
+        }
+    }
+}
+
+/// Serializes the witness generators of circuits built with
+/// [`Poseidon2GoldilocksConfig`], deferring to [`DefaultGeneratorSerializer`]
+/// for all but [`Poseidon2Generator`]s.
+#[derive(Default)]
+pub struct Poseidon2GeneratorSerializer<C: GenericConfig<D>, const D: usize> {
+    default: DefaultGeneratorSerializer<C, D>,
+}
+
+impl<F, C, const D: usize> WitnessGeneratorSerializer<F, D> for Poseidon2GeneratorSerializer<C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    fn read_generator(
+        &self,
+        buf: &mut Buffer,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<WitnessGeneratorRef<F, D>> {
+        match buf.read_u32()? {
+            POSEIDON2_TAG => Ok(WitnessGeneratorRef::new(
+                Poseidon2Generator::<F, D>::deserialize(buf, common_data)?.adapter(),
+            )),
+            _ => self.default.read_generator(buf, common_data),
+        }
+    }
+
+    fn write_generator(
+        &self,
+        buf: &mut Vec<u8>,
+        generator: &WitnessGeneratorRef<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        if generator.0.id() == Poseidon2Generator::<F, D>::default().id() {
+            buf.write_u32(POSEIDON2_TAG)?;
+            generator.0.serialize(buf, common_data)
+        } else {
+            buf.write_u32(0)?;
+            self.default.write_generator(buf, generator, common_data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, circuit_data::CircuitData},
+    };
+
+    use super::*;
+
+    type F = GoldilocksField;
+    type C = Poseidon2GoldilocksConfig;
+    const D: usize = 2;
+
+    #[test]
+    fn permutation_matches_reference() {
+        // The known answer of the Goldilocks width 12 instance in the `kats`
+        // test of `src/poseidon2/poseidon2.rs` in the reference, permuting
+        // `[0, 1, ..., 11]`.
+        let expected = [
+            0x01eaef96bdf1c0c1,
+            0x1f0d2cc525b2540c,
+            0x6282c1dfe1e0358d,
+            0xe780d721f698e1e6,
+            0x280c0b6f753d833b,
+            0x1b942dd5023156ab,
+            0x43f0df3fcccb8398,
+            0xe8e8190585489025,
+            0x56bdbf72f77ada22,
+            0x7911c32bf9dcd705,
+            0xec467926508fbe67,
+            0x6a50450ddf85a6ed,
+        ]
+        .map(F::from_canonical_u64);
+        assert_eq!(
+            permute(std::array::from_fn(F::from_canonical_usize)),
+            expected
+        );
+    }
+
+    /// Builds a circuit permuting its public inputs with a [`Poseidon2Gate`],
+    /// swapped or not.
+    fn permutation_circuit(swap: bool) -> (CircuitData<F, C, D>, [Target; WIDTH]) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_target_arr::<WIDTH>();
+        let swap = builder.constant_bool(swap);
+        let outputs =
+            Poseidon2Hash::permute_swapped(Poseidon2Permutation::new(inputs), swap, &mut builder);
+        builder.register_public_inputs(outputs.as_ref());
+        (builder.build::<C>(), inputs)
+    }
+
+    #[test]
+    fn gate_matches_permutation() {
+        let inputs: [F; WIDTH] = std::array::from_fn(|i| F::from_canonical_usize(i * i + 1));
+        for swap in [false, true] {
+            let (data, targets) = permutation_circuit(swap);
+            let mut pw = PartialWitness::new();
+            for (&target, &input) in targets.iter().zip(&inputs) {
+                pw.set_target(target, input);
+            }
+            let proof = data.prove(pw).unwrap();
+
+            let mut swapped = inputs;
+            if swap {
+                swapped[..8].rotate_left(4);
+            }
+            assert_eq!(proof.public_inputs, permute(swapped));
+            data.verify(proof).unwrap();
+        }
+    }
+
+    #[test]
+    fn verifies_proofs_recursively() {
+        let (inner, targets) = permutation_circuit(true);
+        let mut pw = PartialWitness::new();
+        for &target in &targets {
+            pw.set_target(target, F::ONE);
+        }
+        let inner_proof = inner.prove(pw).unwrap();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_target = builder.add_virtual_proof_with_pis(&inner.common);
+        let vd_target = builder.constant_verifier_data(&inner.verifier_only);
+        builder.verify_proof::<C>(&proof_target, &vd_target, &inner.common);
+        let outer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_target, &inner_proof);
+        outer.verify(outer.prove(pw).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn serializes_circuits() {
+        let (data, _) = permutation_circuit(false);
+        let gate_serializer = Poseidon2GateSerializer;
+        let generator_serializer = Poseidon2GeneratorSerializer::<C, D>::default();
+
+        let bytes = data
+            .to_bytes(&gate_serializer, &generator_serializer)
+            .unwrap();
+        let deserialized =
+            CircuitData::<F, C, D>::from_bytes(&bytes, &gate_serializer, &generator_serializer)
+                .unwrap();
+        assert_eq!(deserialized, data);
+    }
+}
@@ -1,19 +1,24 @@
 //! This module defines the proof generation methods corresponding to the three
 //! types of proofs the zkEVM internally handles.
 
+#[cfg(feature = "prover")]
 use std::sync::{atomic::AtomicBool, Arc};
 
+#[cfg(feature = "prover")]
 use evm_arithmetization::{
     fixed_recursive_verifier::ProverOutputData, generation::TrimmedGenerationInputs,
-    prover::GenerationSegmentData, AllStark, StarkConfig,
+    prover::GenerationSegmentData, AllStark, AllStarkConfig,
 };
 use hashbrown::HashMap;
+#[cfg(feature = "prover")]
+use plonky2::util::timing::TimingTree;
 use plonky2::{
     gates::noop::NoopGate,
     plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
-    util::timing::TimingTree,
 };
 
+use crate::types::{Field, PlonkyProofIntern, EXTENSION_DEGREE};
+#[cfg(feature = "prover")]
 use crate::{
     proof_types::{
         AggregatableBlockProof, BatchAggregatableProof, GeneratedAggBlockProof,
@@ -21,7 +26,6 @@ use crate::{
         SegmentAggregatableProof,
     },
     prover_state::ProverState,
-    types::{Field, PlonkyProofIntern, EXTENSION_DEGREE},
 };
 
 /// A type alias for `Result<T, ProofGenError>`.
@@ -48,6 +52,7 @@ impl From<String> for ProofGenError {
 }
 
 /// Generates a transaction proof from some IR data.
+#[cfg(feature = "prover")]
 pub fn generate_segment_proof(
     p_state: &ProverState,
     gen_inputs: TrimmedGenerationInputs,
@@ -58,7 +63,7 @@ pub fn generate_segment_proof(
         .state
         .prove_segment(
             &AllStark::default(),
-            &StarkConfig::standard_fast_config(),
+            &AllStarkConfig::standard_fast_config(),
             gen_inputs,
             segment_data,
             &mut TimingTree::default(),
@@ -78,6 +83,7 @@ pub fn generate_segment_proof(
 /// If a transaction only contains a single segment, this function must still be
 /// called to generate a `GeneratedSegmentAggProof`. In that case, you can set
 /// `has_dummy` to `true`, and provide an arbitrary proof for the right child.
+#[cfg(feature = "prover")]
 pub fn generate_segment_agg_proof(
     p_state: &ProverState,
     lhs_child: &SegmentAggregatableProof,
@@ -120,6 +126,7 @@ pub fn generate_segment_agg_proof(
 /// Generates a transaction aggregation proof from two child proofs.
 ///
 /// Note that the child proofs may be either transaction or aggregation proofs.
+#[cfg(feature = "prover")]
 pub fn generate_transaction_agg_proof(
     p_state: &ProverState,
     lhs_child: &BatchAggregatableProof,
@@ -147,6 +154,7 @@ pub fn generate_transaction_agg_proof(
 ///
 /// It takes an optional argument, `prev_opt_parent_b_proof`, that can be set to
 /// `None` on checkpoint heights.
+#[cfg(feature = "prover")]
 pub fn generate_block_proof(
     p_state: &ProverState,
     prev_opt_parent_b_proof: Option<&GeneratedBlockProof>,
@@ -177,6 +185,7 @@ pub fn generate_block_proof(
 /// Generates an aggregation block proof from two child proofs.
 ///
 /// Note that the child proofs may be either block or aggregation proofs.
+#[cfg(feature = "prover")]
 pub fn generate_agg_block_proof(
     p_state: &ProverState,
     lhs_child: &AggregatableBlockProof,
@@ -195,6 +204,30 @@ pub fn generate_agg_block_proof(
     Ok(GeneratedAggBlockProof { intern })
 }
 
+/// Generates an aggregation block proof from two child proofs covering
+/// consecutive blocks, the first block of `rhs_child` being the child of the
+/// last block of `lhs_child`.
+///
+/// Aggregation children must themselves be chain segment proofs.
+#[cfg(feature = "prover")]
+pub fn generate_chain_segment_proof(
+    p_state: &ProverState,
+    lhs_child: &AggregatableBlockProof,
+    rhs_child: &AggregatableBlockProof,
+) -> ProofGenResult<GeneratedAggBlockProof> {
+    let intern = p_state
+        .state
+        .prove_chain_segment(
+            lhs_child.intern(),
+            lhs_child.is_agg(),
+            rhs_child.intern(),
+            rhs_child.is_agg(),
+        )
+        .map_err(|err| err.to_string())?;
+
+    Ok(GeneratedAggBlockProof { intern })
+}
+
 /// Generates a dummy proof for a dummy circuit doing nothing.
 /// This is useful for testing purposes only.
 pub fn dummy_proof() -> ProofGenResult<PlonkyProofIntern> {
@@ -11,6 +11,64 @@ use serde::{Deserialize, Serialize};
 
 use crate::types::{Hash, Hasher, PlonkyProofIntern};
 
+/// (De)serializes proofs along with the name of the hash of the recursive
+/// layers they were generated with, so that proofs of builds with another
+/// [`Config`](crate::types::Config) fail to deserialize instead of to verify.
+///
+/// Proofs of the default Poseidon hash are left untagged, so that their
+/// serialization is unchanged.
+pub(crate) mod hasher_tagged {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::types::HASHER_NAME;
+
+    /// The hash of the proofs serialized without a tag.
+    const UNTAGGED_HASHER_NAME: &str = "poseidon";
+
+    #[derive(Serialize)]
+    struct Tagged<'a, T> {
+        hasher: &'a str,
+        proof: &'a T,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MaybeTagged<T> {
+        Tagged { hasher: String, proof: T },
+        Untagged(T),
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        proof: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if HASHER_NAME == UNTAGGED_HASHER_NAME {
+            return proof.serialize(serializer);
+        }
+        Tagged {
+            hasher: HASHER_NAME,
+            proof,
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let (hasher, proof) = match MaybeTagged::deserialize(deserializer)? {
+            MaybeTagged::Tagged { hasher, proof } => (hasher, proof),
+            MaybeTagged::Untagged(proof) => (UNTAGGED_HASHER_NAME.to_string(), proof),
+        };
+        if hasher != HASHER_NAME {
+            return Err(D::Error::custom(format!(
+                "the proof was generated with the {hasher} hash, but this build uses \
+                 {HASHER_NAME}"
+            )));
+        }
+        Ok(proof)
+    }
+}
+
 /// A transaction proof along with its public values, for proper connection with
 /// contiguous proofs.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -18,6 +76,7 @@ pub struct GeneratedSegmentProof {
     /// Public values of this transaction proof.
     pub p_vals: PublicValues,
     /// Underlying plonky2 proof.
+    #[serde(with = "hasher_tagged")]
     pub intern: PlonkyProofIntern,
 }
 
@@ -31,6 +90,7 @@ pub struct GeneratedSegmentAggProof {
     /// Public values of this aggregation proof.
     pub p_vals: PublicValues,
     /// Underlying plonky2 proof.
+    #[serde(with = "hasher_tagged")]
     pub intern: PlonkyProofIntern,
 }
 
@@ -44,6 +104,7 @@ pub struct GeneratedTxnAggProof {
     /// Public values of this transaction aggregation proof.
     pub p_vals: PublicValues,
     /// Underlying plonky2 proof.
+    #[serde(with = "hasher_tagged")]
     pub intern: PlonkyProofIntern,
 }
 
@@ -54,6 +115,7 @@ pub struct GeneratedBlockProof {
     /// Associated block height.
     pub b_height: BlockHeight,
     /// Underlying plonky2 proof.
+    #[serde(with = "hasher_tagged")]
     pub intern: PlonkyProofIntern,
 }
 
@@ -65,6 +127,7 @@ pub struct GeneratedBlockProof {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GeneratedAggBlockProof {
     /// Underlying plonky2 proof.
+    #[serde(with = "hasher_tagged")]
     pub intern: PlonkyProofIntern,
 }
 
@@ -223,3 +286,39 @@ impl From<GeneratedAggBlockProof> for AggregatableBlockProof {
         Self::Agg(v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::hasher_tagged;
+    use crate::types::HASHER_NAME;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Tagged {
+        #[serde(with = "hasher_tagged")]
+        proof: u64,
+    }
+
+    #[test]
+    fn round_trips_proofs() {
+        let json = serde_json::to_string(&Tagged { proof: 42 }).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Tagged>(&json).unwrap(),
+            Tagged { proof: 42 }
+        );
+    }
+
+    #[test]
+    fn rejects_proofs_of_other_hashes() {
+        let json = r#"{"proof":{"hasher":"keccak","proof":42}}"#;
+        let err = serde_json::from_str::<Tagged>(json).unwrap_err();
+        assert!(err.to_string().contains("generated with the keccak hash"));
+    }
+
+    #[test]
+    fn reads_untagged_proofs_as_poseidon_ones() {
+        let untagged = serde_json::from_str::<Tagged>(r#"{"proof":42}"#);
+        assert_eq!(untagged.is_ok(), HASHER_NAME == "poseidon");
+    }
+}
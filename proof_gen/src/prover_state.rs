@@ -5,7 +5,7 @@
 
 use std::ops::Range;
 
-use evm_arithmetization::{AllStark, StarkConfig};
+use evm_arithmetization::{AllStark, AllStarkConfig};
 use log::info;
 use paste::paste;
 
@@ -31,6 +31,8 @@ pub struct ProverStateBuilder {
     pub(crate) memory_circuit_size: Range<usize>,
     pub(crate) memory_before_circuit_size: Range<usize>,
     pub(crate) memory_after_circuit_size: Range<usize>,
+    pub(crate) range_check_circuit_size: Range<usize>,
+    pub(crate) ec_circuit_size: Range<usize>,
 }
 
 impl Default for ProverStateBuilder {
@@ -52,6 +54,8 @@ impl Default for ProverStateBuilder {
             memory_circuit_size: DEFAULT_MEMORY_RANGE,
             memory_before_circuit_size: DEFAULT_MEMORY_BEFORE_RANGE,
             memory_after_circuit_size: DEFAULT_MEMORY_AFTER_RANGE,
+            range_check_circuit_size: DEFAULT_RANGE_CHECK_RANGE,
+            ec_circuit_size: DEFAULT_EC_RANGE,
         }
     }
 }
@@ -79,6 +83,8 @@ impl ProverStateBuilder {
     define_set_circuit_size_method!(memory);
     define_set_circuit_size_method!(memory_before);
     define_set_circuit_size_method!(memory_after);
+    define_set_circuit_size_method!(range_check);
+    define_set_circuit_size_method!(ec);
 
     // TODO: Consider adding async version?
     /// Instantiate the prover state from the builder. Note that this is a very
@@ -98,8 +104,10 @@ impl ProverStateBuilder {
                 self.memory_circuit_size,
                 self.memory_before_circuit_size,
                 self.memory_after_circuit_size,
+                self.range_check_circuit_size,
+                self.ec_circuit_size,
             ],
-            &StarkConfig::standard_fast_config(),
+            &AllStarkConfig::standard_fast_config(),
         );
 
         info!("Finished initializing Plonky2 aggregation prover state!");
@@ -0,0 +1,565 @@
+//! This module generates the Solidity contract verifying
+//! [wrapped](crate::wrapper) block proofs on-chain, and encodes the calldata
+//! of its calls.
+//!
+//! The contract checks the Groth16 proof of a block against the hash of its
+//! public values and its [`BlockHeader`], given in calldata. The other public
+//! values of the block can be checked against the hash off-chain.
+
+use std::fmt::Write as _;
+
+use ethereum_types::{H256, U256};
+use evm_arithmetization::{
+    fixed_recursive_verifier::BLOCK_HEADER_PUBLIC_VALUES_LEN, proof::PublicValues,
+};
+use keccak_hash::keccak;
+use plonky2::{field::types::PrimeField64, hash::hash_types::NUM_HASH_OUT_ELTS};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    proof_gen::{ProofGenError, ProofGenResult},
+    wrapper::WrappedBlockProof,
+};
+
+/// The template of the verifier contract.
+const TEMPLATE: &str = include_str!("../templates/BlockProofVerifier.sol");
+
+/// The number of public inputs of the Groth16 circuit, which are those of the
+/// last wrapping circuit.
+pub const NUM_PUBLIC_INPUTS: usize = NUM_HASH_OUT_ELTS + BLOCK_HEADER_PUBLIC_VALUES_LEN;
+
+/// The signature of the function of the verifier contract verifying block
+/// proofs.
+pub const VERIFY_BLOCK_PROOF_SIGNATURE: &str = "verifyBlockProof((uint64,uint64,bytes32,bytes32,\
+                                                bytes32,bytes32,bytes32,uint64,uint64,bytes32,\
+                                                bytes32),uint64[4],uint256[8])";
+
+/// A point of the G1 group of BN254, in affine coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct G1Point {
+    pub x: U256,
+    pub y: U256,
+}
+
+/// A point of the G2 group of BN254, in affine coordinates.
+///
+/// Coordinates are elements of the quadratic extension of the base field,
+/// given as `[c1, c0]` as expected by the pairing precompile of
+/// [EIP-197](https://eips.ethereum.org/EIPS/eip-197).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct G2Point {
+    pub x: [U256; 2],
+    pub y: [U256; 2],
+}
+
+/// The verifying key of the Groth16 circuit verifying wrapped block proofs,
+/// as exported by the external Groth16 prover.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Groth16VerifyingKey {
+    pub alpha: G1Point,
+    pub beta: G2Point,
+    pub gamma: G2Point,
+    pub delta: G2Point,
+    /// The points of the public inputs, starting with the constant one.
+    pub ic: Vec<G1Point>,
+}
+
+/// The public values identifying a block, given in calldata to the verifier
+/// contract.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlockHeader {
+    /// The block number.
+    pub number: U256,
+    /// The block timestamp.
+    pub timestamp: U256,
+    /// The block hash.
+    pub hash: H256,
+    /// The state trie root after the execution of the block.
+    pub state_root: H256,
+    /// The transactions trie root of the block.
+    pub transactions_root: H256,
+    /// The receipts trie root of the block.
+    pub receipts_root: H256,
+    /// The state trie root of the checkpoint block.
+    pub checkpoint_state_root: H256,
+    /// The blob gas used by the block.
+    pub blob_gas_used: U256,
+    /// The excess blob gas of the block.
+    pub excess_blob_gas: U256,
+    /// The hash tree root of the parent beacon block.
+    pub parent_beacon_block_root: H256,
+    /// The withdrawals trie root of the block.
+    pub withdrawals_root: H256,
+}
+
+impl From<&PublicValues> for BlockHeader {
+    fn from(p_vals: &PublicValues) -> Self {
+        Self {
+            number: p_vals.block_metadata.block_number,
+            timestamp: p_vals.block_metadata.block_timestamp,
+            hash: p_vals.block_hashes.cur_hash,
+            state_root: p_vals.trie_roots_after.state_root,
+            transactions_root: p_vals.trie_roots_after.transactions_root,
+            receipts_root: p_vals.trie_roots_after.receipts_root,
+            checkpoint_state_root: p_vals.extra_block_data.checkpoint_state_trie_root,
+            blob_gas_used: p_vals.block_metadata.block_blob_gas_used,
+            excess_blob_gas: p_vals.block_metadata.block_excess_blob_gas,
+            parent_beacon_block_root: p_vals.block_metadata.parent_beacon_block_root,
+            withdrawals_root: p_vals.block_metadata.withdrawals_root,
+        }
+    }
+}
+
+/// Generates the source of the verifier contract of the Groth16 circuit of
+/// verifying key `vk`.
+pub fn generate_verifier_contract(vk: &Groth16VerifyingKey) -> ProofGenResult<String> {
+    if vk.ic.len() != NUM_PUBLIC_INPUTS + 1 {
+        return Err(ProofGenError(format!(
+            "expected a verifying key with {} public inputs, got {}",
+            NUM_PUBLIC_INPUTS,
+            vk.ic.len().saturating_sub(1)
+        )));
+    }
+
+    let mut constants = String::new();
+    let mut constant = |name: &str, value: U256| {
+        writeln!(constants, "    uint256 constant {name} = {value};").expect("infallible")
+    };
+    constant("ALPHA_X", vk.alpha.x);
+    constant("ALPHA_Y", vk.alpha.y);
+    for (name, point) in [("BETA", vk.beta), ("GAMMA", vk.gamma), ("DELTA", vk.delta)] {
+        constant(&format!("{name}_X1"), point.x[0]);
+        constant(&format!("{name}_X0"), point.x[1]);
+        constant(&format!("{name}_Y1"), point.y[0]);
+        constant(&format!("{name}_Y0"), point.y[1]);
+    }
+    for (i, point) in vk.ic.iter().enumerate() {
+        constant(&format!("IC_{i}_X"), point.x);
+        constant(&format!("IC_{i}_Y"), point.y);
+    }
+
+    let mut linear_combination = String::new();
+    for i in 1..vk.ic.len() {
+        writeln!(
+            linear_combination,
+            "        (px, py) = ecMul(IC_{i}_X, IC_{i}_Y, inputs[{}]);\n        \
+             (x, y) = ecAdd(x, y, px, py);",
+            i - 1
+        )
+        .expect("infallible");
+    }
+
+    Ok(TEMPLATE
+        .replace("{{NUM_PUBLIC_INPUTS}}", &NUM_PUBLIC_INPUTS.to_string())
+        .replace("{{VERIFYING_KEY}}", &constants)
+        .replace("{{LINEAR_COMBINATION}}", &linear_combination))
+}
+
+/// Encodes the call to the verifier contract verifying the wrapped `proof` of
+/// the block of `header`.
+///
+/// The Groth16 proof must be the 256-byte uncompressed encoding of its points
+/// `A`, `B` and `C`, with coordinates in big-endian and those of `B` given as
+/// `(c1, c0)`.
+pub fn encode_verify_block_proof(
+    header: &BlockHeader,
+    proof: &WrappedBlockProof,
+) -> ProofGenResult<Vec<u8>> {
+    if proof.groth16_proof.len() != 8 * 32 {
+        return Err(ProofGenError(format!(
+            "expected a Groth16 proof of 256 bytes, got {}",
+            proof.groth16_proof.len()
+        )));
+    }
+
+    let mut calldata = keccak(VERIFY_BLOCK_PROOF_SIGNATURE)[..4].to_vec();
+    let mut push_word = |word: U256| {
+        let mut bytes = [0; 32];
+        word.to_big_endian(&mut bytes);
+        calldata.extend_from_slice(&bytes);
+    };
+    push_word(header.number);
+    push_word(header.timestamp);
+    for hash in [
+        header.hash,
+        header.state_root,
+        header.transactions_root,
+        header.receipts_root,
+        header.checkpoint_state_root,
+    ] {
+        push_word(U256::from_big_endian(hash.as_bytes()));
+    }
+    push_word(header.blob_gas_used);
+    push_word(header.excess_blob_gas);
+    for hash in [header.parent_beacon_block_root, header.withdrawals_root] {
+        push_word(U256::from_big_endian(hash.as_bytes()));
+    }
+    for element in proof.public_values_hash.elements {
+        push_word(element.to_canonical_u64().into());
+    }
+    // The proof is already made of 32-byte words.
+    calldata.extend_from_slice(&proof.groth16_proof);
+    Ok(calldata)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::Address;
+    use evm_arithmetization::testing_utils::{BytecodeHarness, HarnessAccount};
+    use plonky2::{field::types::Field as _, hash::hash_types::HashOut};
+
+    use super::*;
+    use crate::types::Field;
+
+    fn verifying_key(num_public_inputs: usize) -> Groth16VerifyingKey {
+        Groth16VerifyingKey {
+            ic: (0..=num_public_inputs)
+                .map(|i| G1Point {
+                    x: i.into(),
+                    y: (2 * i).into(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn contract_holds_verifying_key() {
+        let contract = generate_verifier_contract(&verifying_key(NUM_PUBLIC_INPUTS)).unwrap();
+        assert!(contract.contains("uint256 constant NUM_PUBLIC_INPUTS = 66;"));
+        assert!(contract.contains("uint256 constant IC_66_Y = 132;"));
+        assert!(contract.contains("ecMul(IC_66_X, IC_66_Y, inputs[65]);"));
+        assert!(!contract.contains("{{"));
+
+        assert!(generate_verifier_contract(&verifying_key(NUM_PUBLIC_INPUTS - 1)).is_err());
+    }
+
+    #[test]
+    fn calldata_layout() {
+        let header = BlockHeader {
+            number: 7.into(),
+            hash: H256::repeat_byte(0x11),
+            checkpoint_state_root: H256::repeat_byte(0x22),
+            withdrawals_root: H256::repeat_byte(0x33),
+            ..Default::default()
+        };
+        let proof = WrappedBlockProof {
+            b_height: 7,
+            public_values_hash: HashOut {
+                elements: [Field::ONE, Field::TWO, Field::ZERO, Field::NEG_ONE],
+            },
+            groth16_proof: (0..=255).collect(),
+        };
+        let calldata = encode_verify_block_proof(&header, &proof).unwrap();
+
+        let word = |i: usize| &calldata[4 + 32 * i..4 + 32 * (i + 1)];
+        assert_eq!(calldata.len(), 4 + 32 * 23);
+        assert_eq!(calldata[..4], keccak(VERIFY_BLOCK_PROOF_SIGNATURE)[..4]);
+        assert_eq!(U256::from_big_endian(word(0)), 7.into());
+        assert_eq!(word(2), H256::repeat_byte(0x11).as_bytes());
+        assert_eq!(word(6), H256::repeat_byte(0x22).as_bytes());
+        assert_eq!(word(10), H256::repeat_byte(0x33).as_bytes());
+        assert_eq!(
+            U256::from_big_endian(word(14)),
+            Field::NEG_ONE.to_canonical_u64().into()
+        );
+        assert_eq!(word(15), &proof.groth16_proof[..32]);
+
+        let truncated = WrappedBlockProof {
+            groth16_proof: vec![0; 255],
+            ..proof
+        };
+        assert!(encode_verify_block_proof(&header, &truncated).is_err());
+    }
+
+    /// Compiles the verifier contract of verifying key `vk` with `solc`,
+    /// which must be on the `PATH`, returning its runtime bytecode.
+    fn compile_contract(name: &str, vk: &Groth16VerifyingKey) -> Vec<u8> {
+        let dir = std::env::temp_dir().join(format!(
+            "block_proof_verifier_{name}_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("BlockProofVerifier.sol");
+        std::fs::write(&source, generate_verifier_contract(vk).unwrap()).unwrap();
+
+        let output = std::process::Command::new("solc")
+            .args(["--optimize", "--combined-json", "bin-runtime"])
+            .arg(&source)
+            .output()
+            .expect("solc should be installed");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let (_, contract) = json["contracts"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .find(|(name, _)| name.ends_with(":BlockProofVerifier"))
+            .unwrap();
+        let code = contract["bin-runtime"].as_str().unwrap();
+        (0..code.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&code[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn contract_compiles() {
+        assert!(!compile_contract("compiles", &verifying_key(NUM_PUBLIC_INPUTS)).is_empty());
+    }
+
+    /// Arithmetic over the G1 group of BN254, in affine coordinates, `None`
+    /// being the point at infinity.
+    mod g1 {
+        use ethereum_types::{U256, U512};
+
+        pub(super) type Point = Option<(U256, U256)>;
+
+        pub(super) const GENERATOR: Point = Some((U256([1, 0, 0, 0]), U256([2, 0, 0, 0])));
+
+        /// The modulus of the base field.
+        const Q: U256 = U256([
+            0x3c208c16d87cfd47,
+            0x97816a916871ca8d,
+            0xb85045b68181585d,
+            0x30644e72e131a029,
+        ]);
+
+        fn mul_mod(a: U256, b: U256) -> U256 {
+            U256::try_from(a.full_mul(b) % U512::from(Q)).expect("reduced modulo Q")
+        }
+
+        fn sub_mod(a: U256, b: U256) -> U256 {
+            if a >= b {
+                a - b
+            } else {
+                Q - (b - a)
+            }
+        }
+
+        fn inv(a: U256) -> U256 {
+            let exponent = Q - 2;
+            (0..256).rev().fold(U256::one(), |result, i| {
+                let result = mul_mod(result, result);
+                if exponent.bit(i) {
+                    mul_mod(result, a)
+                } else {
+                    result
+                }
+            })
+        }
+
+        pub(super) fn neg(p: Point) -> Point {
+            p.map(|(x, y)| (x, sub_mod(U256::zero(), y)))
+        }
+
+        pub(super) fn add(p: Point, q: Point) -> Point {
+            let ((x1, y1), (x2, y2)) = match (p, q) {
+                (None, _) => return q,
+                (_, None) => return p,
+                (Some(p), Some(q)) => (p, q),
+            };
+            let lambda = if x1 != x2 {
+                mul_mod(sub_mod(y2, y1), inv(sub_mod(x2, x1)))
+            } else if y1 == y2 && !y1.is_zero() {
+                mul_mod(
+                    mul_mod(3.into(), mul_mod(x1, x1)),
+                    inv(mul_mod(2.into(), y1)),
+                )
+            } else {
+                return None;
+            };
+            let x3 = sub_mod(sub_mod(mul_mod(lambda, lambda), x1), x2);
+            let y3 = sub_mod(mul_mod(lambda, sub_mod(x1, x3)), y1);
+            Some((x3, y3))
+        }
+
+        pub(super) fn mul(p: Point, scalar: U256) -> Point {
+            (0..256).rev().fold(None, |result, i| {
+                let result = add(result, result);
+                if scalar.bit(i) {
+                    add(result, p)
+                } else {
+                    result
+                }
+            })
+        }
+    }
+
+    /// Returns a verifying key, and a proof passing the pairing check of the
+    /// verifier contract for `inputs`, built from the generators of BN254
+    /// rather than by a Groth16 prover.
+    ///
+    /// With `A = alpha = g1`, `B = beta = gamma = delta = g2` and all the
+    /// points of the public inputs equal to `g1`, the check reduces to
+    /// `vk_x + C = 0`, where `vk_x = (1 + sum(inputs)) * g1`.
+    fn groth16_instance(inputs: &[u64]) -> (Groth16VerifyingKey, Vec<u8>) {
+        let g1 = G1Point {
+            x: 1.into(),
+            y: 2.into(),
+        };
+        let g2 = G2Point {
+            x: [
+                U256::from_str_radix(
+                    "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+                    16,
+                )
+                .unwrap(),
+                U256::from_str_radix(
+                    "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+                    16,
+                )
+                .unwrap(),
+            ],
+            y: [
+                U256::from_str_radix(
+                    "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+                    16,
+                )
+                .unwrap(),
+                U256::from_str_radix(
+                    "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+                    16,
+                )
+                .unwrap(),
+            ],
+        };
+        let vk = Groth16VerifyingKey {
+            alpha: g1,
+            beta: g2,
+            gamma: g2,
+            delta: g2,
+            ic: vec![g1; inputs.len() + 1],
+        };
+
+        let scalar = inputs.iter().fold(U256::one(), |sum, &input| sum + input);
+        let (c_x, c_y) = g1::neg(g1::mul(g1::GENERATOR, scalar)).unwrap();
+        let proof = [g1.x, g1.y, g2.x[0], g2.x[1], g2.y[0], g2.y[1], c_x, c_y]
+            .into_iter()
+            .flat_map(|word| {
+                let mut bytes = [0; 32];
+                word.to_big_endian(&mut bytes);
+                bytes
+            })
+            .collect();
+        (vk, proof)
+    }
+
+    /// Returns the public inputs of the Groth16 circuit for the block of
+    /// `header` and the hash of its public values, laid out as in
+    /// [`extract_block_header_public_values`](evm_arithmetization::fixed_recursive_verifier::extract_block_header_public_values).
+    fn public_inputs(header: &BlockHeader, public_values_hash: &HashOut<Field>) -> Vec<u64> {
+        let limbs = |word: U256, len: usize| {
+            (0..len).map(move |i| (word >> (32 * i)).low_u64() & 0xffff_ffff)
+        };
+        let hash = |hash: H256| limbs(U256::from_big_endian(hash.as_bytes()), 8);
+        public_values_hash
+            .elements
+            .iter()
+            .map(|element| element.to_canonical_u64())
+            .chain([header.number.low_u64(), header.timestamp.low_u64()])
+            .chain(hash(header.hash))
+            .chain(hash(header.state_root))
+            .chain(hash(header.transactions_root))
+            .chain(hash(header.receipts_root))
+            .chain(hash(header.checkpoint_state_root))
+            .chain(limbs(header.blob_gas_used, 2))
+            .chain(limbs(header.excess_blob_gas, 2))
+            .chain(hash(header.parent_beacon_block_root))
+            .chain(hash(header.withdrawals_root))
+            .collect()
+    }
+
+    /// Calls the verifier contract of runtime bytecode `code` with `calldata`
+    /// in a transaction executed by the kernel, returning its output.
+    fn call_verifier(code: &[u8], calldata: Vec<u8>) -> bool {
+        const VERIFIER: [u8; 20] = [0xfe; 20];
+        // Forwards the calldata to the verifier, then logs its 32-byte output
+        // followed by the success flag of the call.
+        let snippet = [0x36, 0x60, 0x00, 0x60, 0x00, 0x37]
+            .into_iter()
+            .chain([0x60, 0x20, 0x60, 0x00, 0x36, 0x60, 0x00, 0x60, 0x00, 0x73])
+            .chain(VERIFIER)
+            .chain([0x5a, 0xf1, 0x60, 0x20, 0x52])
+            .chain([0x60, 0x40, 0x60, 0x00, 0xa0, 0x00])
+            .collect();
+        let inputs = BytecodeHarness::new(snippet)
+            .calldata(calldata)
+            .account(
+                Address::from(VERIFIER),
+                HarnessAccount {
+                    nonce: 1.into(),
+                    code: code.to_vec(),
+                    ..Default::default()
+                },
+            )
+            .build()
+            .unwrap();
+
+        let simulation = evm_arithmetization::simulate::<Field>(&inputs).unwrap();
+        let log = &simulation.txns[0].logs[0];
+        assert_eq!(
+            U256::from_big_endian(&log.data[32..]),
+            U256::one(),
+            "the call to the verifier failed"
+        );
+        U256::from_big_endian(&log.data[..32]) == U256::one()
+    }
+
+    /// Runs the verifier contract, compiled with `solc`, which must be on the
+    /// `PATH`. The tests needing `solc` are ignored by default, and run by CI.
+    #[test]
+    #[ignore]
+    fn contract_verifies_proofs() {
+        let header = BlockHeader {
+            number: 19_000_000.into(),
+            timestamp: 1_700_000_000.into(),
+            hash: H256::repeat_byte(0x11),
+            state_root: H256::repeat_byte(0x22),
+            transactions_root: H256::repeat_byte(0x33),
+            receipts_root: H256::repeat_byte(0x44),
+            checkpoint_state_root: H256::repeat_byte(0x55),
+            blob_gas_used: ((3u64 << 32) | 5).into(),
+            excess_blob_gas: ((7u64 << 32) | 11).into(),
+            parent_beacon_block_root: H256::repeat_byte(0x66),
+            withdrawals_root: H256::repeat_byte(0x77),
+        };
+        let public_values_hash = HashOut {
+            elements: [Field::ONE, Field::TWO, Field::ZERO, Field::NEG_ONE],
+        };
+        let (vk, groth16_proof) = groth16_instance(&public_inputs(&header, &public_values_hash));
+        let code = compile_contract("verifies", &vk);
+        let proof = WrappedBlockProof {
+            b_height: 19_000_000,
+            public_values_hash,
+            groth16_proof,
+        };
+        let verify = |header: &BlockHeader, proof: &WrappedBlockProof| {
+            call_verifier(&code, encode_verify_block_proof(header, proof).unwrap())
+        };
+
+        assert!(verify(&header, &proof));
+
+        // The proof holds neither for another block, nor for other public
+        // values of the same block.
+        let other_header = BlockHeader {
+            excess_blob_gas: (7u64 << 32).into(),
+            ..header.clone()
+        };
+        assert!(!verify(&other_header, &proof));
+        let other_proof = WrappedBlockProof {
+            public_values_hash: HashOut {
+                elements: [Field::ONE, Field::TWO, Field::ONE, Field::NEG_ONE],
+            },
+            ..proof.clone()
+        };
+        assert!(!verify(&header, &other_proof));
+    }
+}
@@ -3,22 +3,47 @@
 
 use plonky2::{
     field::goldilocks_field::GoldilocksField,
-    hash::poseidon::PoseidonHash,
-    plonk::{self, config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+    plonk::{self, config::GenericConfig, proof::ProofWithPublicInputs},
+};
+#[cfg(not(feature = "poseidon2"))]
+use plonky2::{
+    plonk::config::PoseidonGoldilocksConfig,
+    util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer},
+};
+
+#[cfg(feature = "poseidon2")]
+use crate::poseidon2::{
+    Poseidon2GateSerializer, Poseidon2GeneratorSerializer, Poseidon2GoldilocksConfig,
 };
 
 /// The base field on which statements are being proven.
 pub type Field = GoldilocksField;
+
 /// The recursive circuit configuration to be used to shrink and aggregate
 /// proofs.
+///
+/// This is the single point selecting the hash of the recursive layers, which
+/// is Poseidon2 with the `poseidon2` feature and Poseidon otherwise: proofs
+/// and circuits built with different configurations are incompatible.
+#[cfg(not(feature = "poseidon2"))]
 pub type Config = PoseidonGoldilocksConfig;
+#[cfg(feature = "poseidon2")]
+pub type Config = Poseidon2GoldilocksConfig;
 /// The extension degree of the field used in the proof system.
 pub const EXTENSION_DEGREE: usize = 2;
 
+/// The name of the hash of the recursive layers, recorded along serialized
+/// proofs and circuits to detect incompatible ones.
+pub const HASHER_NAME: &str = if cfg!(feature = "poseidon2") {
+    "poseidon2"
+} else {
+    "poseidon"
+};
+
 /// A type alias for proofs generated by the zkEVM.
 pub type PlonkyProofIntern = ProofWithPublicInputs<Field, Config, EXTENSION_DEGREE>;
 
-pub type Hasher = PoseidonHash;
+pub type Hasher = <Config as GenericConfig<EXTENSION_DEGREE>>::Hasher;
 
 pub type Hash = <Hasher as plonk::config::Hasher<Field>>::Hash;
 
@@ -30,6 +55,15 @@ pub type AllRecursiveCircuits = evm_arithmetization::fixed_recursive_verifier::A
     EXTENSION_DEGREE,
 >;
 
+/// A type alias for the verifier data of the block and block aggregation
+/// circuits, exported from [`AllRecursiveCircuits`] to verify their proofs
+/// without the prover circuits.
+pub type VerifierArtifact = evm_arithmetization::fixed_recursive_verifier::VerifierArtifact<
+    Field,
+    Config,
+    EXTENSION_DEGREE,
+>;
+
 /// A type alias for the verifier data necessary to verify succinct block
 /// proofs.
 /// While the prover state [`AllRecursiveCircuits`] can also verify proofs, this
@@ -37,3 +71,26 @@ pub type AllRecursiveCircuits = evm_arithmetization::fixed_recursive_verifier::A
 /// regardless of the underlying hardware.
 pub type VerifierData =
     plonky2::plonk::circuit_data::VerifierCircuitData<Field, Config, EXTENSION_DEGREE>;
+
+/// The serializers of the gates and witness generators of the circuits built
+/// with [`Config`].
+#[cfg(not(feature = "poseidon2"))]
+pub fn circuit_serializers() -> (
+    DefaultGateSerializer,
+    DefaultGeneratorSerializer<Config, EXTENSION_DEGREE>,
+) {
+    (DefaultGateSerializer, DefaultGeneratorSerializer::default())
+}
+
+/// The serializers of the gates and witness generators of the circuits built
+/// with [`Config`].
+#[cfg(feature = "poseidon2")]
+pub fn circuit_serializers() -> (
+    Poseidon2GateSerializer,
+    Poseidon2GeneratorSerializer<Config, EXTENSION_DEGREE>,
+) {
+    (
+        Poseidon2GateSerializer,
+        Poseidon2GeneratorSerializer::default(),
+    )
+}
@@ -8,7 +8,7 @@ use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
 
 use crate::proof_gen::ProofGenResult;
 use crate::prover_state::ProverStateBuilder;
-use crate::types::PlonkyProofIntern;
+use crate::types::{PlonkyProofIntern, VerifierArtifact};
 use crate::{prover_state::ProverState, types::VerifierData};
 
 /// Plonky2 verifier state.
@@ -51,6 +51,15 @@ impl<T: Borrow<ProverState>> From<T> for VerifierState {
     }
 }
 
+/// Extracts the verifier state from an exported [`VerifierArtifact`].
+impl From<VerifierArtifact> for VerifierState {
+    fn from(artifact: VerifierArtifact) -> Self {
+        VerifierState {
+            state: artifact.block,
+        }
+    }
+}
+
 impl VerifierState {
     /// Verifies a `block_proof`.
     pub fn verify(&self, block_proof: &PlonkyProofIntern) -> ProofGenResult<()> {
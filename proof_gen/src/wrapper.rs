@@ -0,0 +1,523 @@
+//! This module wraps block proofs into Groth16 proofs over BN254, which are
+//! cheap to verify on-chain.
+//!
+//! The wrapping goes in two steps. First, the block proof is recursively
+//! verified by a chain of plonky2 circuits of decreasing size, the first of
+//! which only exposes the hash of the public values of the block, followed by
+//! the public values identifying the block (see
+//! [`extract_block_header_public_values`]). Then, the
+//! last proof of this chain is verified in a Groth16 circuit over BN254. This
+//! last step is delegated to an external prover, such as one built on
+//! [gnark-plonky2-verifier], which reads the plonky2 proof and circuit data
+//! serialized as JSON.
+//!
+//! [gnark-plonky2-verifier]: https://github.com/succinctlabs/gnark-plonky2-verifier
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use ethereum_types::{U256, U512};
+use evm_arithmetization::fixed_recursive_verifier::{
+    extract_block_header_public_values, extract_block_public_values,
+};
+use plonky2::{
+    hash::hash_types::HashOut,
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData},
+        proof::ProofWithPublicInputsTarget,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    proof_gen::{ProofGenError, ProofGenResult},
+    proof_types::GeneratedBlockProof,
+    types::{Config, Field, Hasher, PlonkyProofIntern, VerifierData, EXTENSION_DEGREE},
+};
+
+/// The file the external Groth16 prover writes its proof to.
+pub const GROTH16_PROOF_FILE: &str = "groth16_proof.json";
+
+/// The modulus of the base field of BN254.
+const BN254_BASE_MODULUS: U256 = U256([
+    0x3c208c16d87cfd47,
+    0x97816a916871ca8d,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+]);
+
+/// A block proof wrapped into a Groth16 proof over BN254.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WrappedBlockProof {
+    /// Associated block height.
+    pub b_height: u64,
+    /// The hash of the public values of the block, which is the public input
+    /// of the wrapped proof.
+    pub public_values_hash: HashOut<Field>,
+    /// The Groth16 proof, as output by the external prover.
+    pub groth16_proof: Vec<u8>,
+}
+
+/// A plonky2 circuit recursively verifying the proofs of the previous one.
+struct WrapperLayer {
+    circuit: CircuitData<Field, Config, EXTENSION_DEGREE>,
+    proof_with_pis_target: ProofWithPublicInputsTarget<EXTENSION_DEGREE>,
+}
+
+/// The chain of plonky2 circuits shrinking block proofs before their
+/// Groth16 wrapping.
+pub struct BlockProofWrapper {
+    layers: Vec<WrapperLayer>,
+}
+
+impl BlockProofWrapper {
+    /// Builds the wrapping circuits of the proofs of the `block` circuit.
+    pub fn new(block: &VerifierData) -> Self {
+        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let proof_with_pis_target = builder.add_virtual_proof_with_pis(&block.common);
+        let block_vk = builder.constant_verifier_data(&block.verifier_only);
+        builder.verify_proof::<Config>(&proof_with_pis_target, &block_vk, &block.common);
+
+        // Block proofs are cyclic, and end with the verifier data they were
+        // verified against, which must be the one of the block circuit.
+        let public_inputs = &proof_with_pis_target.public_inputs;
+        let vk_len = (1 + block.common.config.fri_config.num_cap_elements()) * 4;
+        let cyclic_vk = &public_inputs[public_inputs.len() - vk_len..];
+        let expected_vk = block_vk.circuit_digest.elements.into_iter().chain(
+            block_vk
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .flat_map(|hash| hash.elements),
+        );
+        for (&limb, expected) in cyclic_vk.iter().zip(expected_vk) {
+            builder.connect(limb, expected);
+        }
+
+        let public_values = extract_block_public_values(public_inputs);
+        let public_values_hash = builder.hash_n_to_hash_no_pad::<Hasher>(public_values.to_vec());
+        builder.register_public_inputs(&public_values_hash.elements);
+        builder.register_public_inputs(&extract_block_header_public_values(public_values));
+
+        let mut layers = vec![WrapperLayer {
+            circuit: builder.build::<Config>(),
+            proof_with_pis_target,
+        }];
+
+        // Shrinking recursion loop, until the circuit size stalls.
+        loop {
+            let last = &layers.last().expect("there is a first layer").circuit;
+            let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+            let proof_with_pis_target = builder.add_virtual_proof_with_pis(&last.common);
+            let last_vk = builder.constant_verifier_data(&last.verifier_only);
+            builder.verify_proof::<Config>(&proof_with_pis_target, &last_vk, &last.common);
+            // Carry the public inputs forward.
+            builder.register_public_inputs(&proof_with_pis_target.public_inputs);
+            let circuit = builder.build::<Config>();
+            if circuit.common.degree_bits() >= last.common.degree_bits() {
+                break;
+            }
+            layers.push(WrapperLayer {
+                circuit,
+                proof_with_pis_target,
+            });
+        }
+
+        Self { layers }
+    }
+
+    /// The verifier data of the last wrapping circuit, verified by the
+    /// Groth16 circuit.
+    pub fn verifier_data(&self) -> VerifierData {
+        self.last_circuit().verifier_data()
+    }
+
+    fn last_circuit(&self) -> &CircuitData<Field, Config, EXTENSION_DEGREE> {
+        &self.layers.last().expect("there is a first layer").circuit
+    }
+
+    /// Shrinks a block proof through the wrapping circuits.
+    pub fn shrink(&self, block_proof: &PlonkyProofIntern) -> ProofGenResult<PlonkyProofIntern> {
+        let mut proof = block_proof.clone();
+        for layer in &self.layers {
+            let mut inputs = PartialWitness::new();
+            inputs.set_proof_with_pis_target(&layer.proof_with_pis_target, &proof);
+            proof = layer.circuit.prove(inputs).map_err(|err| err.to_string())?;
+        }
+        self.last_circuit()
+            .verify(proof.clone())
+            .map_err(|err| err.to_string())?;
+        Ok(proof)
+    }
+}
+
+/// An external Groth16 prover of plonky2 proofs.
+///
+/// The prover is run as `<command> <dir>`, where `<dir>` holds the
+/// `common_circuit_data.json`, `verifier_only_circuit_data.json` and
+/// `proof_with_public_inputs.json` files of the proof to wrap. It must write
+/// its proof to [`GROTH16_PROOF_FILE`] in the same directory, in the encoding
+/// expected by [`encode_verify_block_proof`].
+///
+/// A Groth16 proof only verifies against the verifying key of the circuit it
+/// was generated with, which differs between builds of the prover. The
+/// prover can be pinned to the build the verifier contract was generated
+/// for with [`Self::pinned_to`].
+///
+/// [`encode_verify_block_proof`]: crate::solidity::encode_verify_block_proof
+#[derive(Clone, Debug)]
+pub struct Groth16Prover {
+    command: PathBuf,
+    version: Option<String>,
+}
+
+impl Groth16Prover {
+    pub fn new(command: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+            version: None,
+        }
+    }
+
+    /// Only proves with the prover if it reports `version` when run as
+    /// `<command> --version`.
+    pub fn pinned_to(self, version: impl Into<String>) -> Self {
+        Self {
+            version: Some(version.into()),
+            ..self
+        }
+    }
+
+    fn check_version(&self) -> ProofGenResult<()> {
+        let Some(expected) = &self.version else {
+            return Ok(());
+        };
+        let output = Command::new(&self.command)
+            .arg("--version")
+            .output()
+            .map_err(|err| ProofGenError(format!("couldn't run {:?}: {err}", self.command)))?;
+        let actual = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || actual.trim() != expected {
+            return Err(ProofGenError(format!(
+                "Groth16 prover {:?} reports version {:?}, expected {expected:?}",
+                self.command,
+                actual.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Proves, in `dir`, the plonky2 `proof` of the circuit of `verifier`.
+    pub fn prove(
+        &self,
+        dir: &Path,
+        verifier: &VerifierData,
+        proof: &PlonkyProofIntern,
+    ) -> ProofGenResult<Vec<u8>> {
+        self.check_version()?;
+        let write = |name: &str, json: serde_json::Result<Vec<u8>>| {
+            let json = json.map_err(|err| err.to_string())?;
+            std::fs::write(dir.join(name), json)
+                .map_err(|err| ProofGenError(format!("couldn't write {name}: {err}")))
+        };
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        write(
+            "common_circuit_data.json",
+            serde_json::to_vec(&verifier.common),
+        )?;
+        write(
+            "verifier_only_circuit_data.json",
+            serde_json::to_vec(&verifier.verifier_only),
+        )?;
+        write("proof_with_public_inputs.json", serde_json::to_vec(proof))?;
+
+        let status = Command::new(&self.command)
+            .arg(dir)
+            .status()
+            .map_err(|err| ProofGenError(format!("couldn't run {:?}: {err}", self.command)))?;
+        if !status.success() {
+            return Err(ProofGenError(format!(
+                "Groth16 prover {:?} failed with {status}",
+                self.command
+            )));
+        }
+        let groth16_proof = std::fs::read(dir.join(GROTH16_PROOF_FILE))
+            .map_err(|err| ProofGenError(format!("couldn't read the Groth16 proof: {err}")))?;
+        check_groth16_proof(&groth16_proof)?;
+        Ok(groth16_proof)
+    }
+}
+
+/// Checks that `proof` is a Groth16 proof in the encoding expected by
+/// [`encode_verify_block_proof`](crate::solidity::encode_verify_block_proof):
+/// all its coordinates are elements of the base field, and its points `A` and
+/// `C` are on BN254, the point at infinity excluded.
+///
+/// This rejects malformed outputs of the external prover, but not proofs
+/// which are well-formed and don't verify.
+fn check_groth16_proof(proof: &[u8]) -> ProofGenResult<()> {
+    if proof.len() != 8 * 32 {
+        return Err(ProofGenError(format!(
+            "expected a Groth16 proof of 256 bytes, got {}",
+            proof.len()
+        )));
+    }
+    let words = proof
+        .chunks(32)
+        .map(U256::from_big_endian)
+        .collect::<Vec<_>>();
+    if words.iter().any(|word| *word >= BN254_BASE_MODULUS) {
+        return Err(ProofGenError(
+            "the Groth16 proof has coordinates outside of the base field".to_string(),
+        ));
+    }
+
+    let mul_mod = |a: U256, b: U256| {
+        U256::try_from(a.full_mul(b) % U512::from(BN254_BASE_MODULUS))
+            .expect("reduced modulo the base field")
+    };
+    // The curve equation is `y^2 = x^3 + 3`, which `(0, 0)`, standing for the
+    // point at infinity, doesn't satisfy.
+    for (name, x, y) in [("A", words[0], words[1]), ("C", words[6], words[7])] {
+        if mul_mod(y, y) != (mul_mod(mul_mod(x, x), x) + 3) % BN254_BASE_MODULUS {
+            return Err(ProofGenError(format!(
+                "point {name} of the Groth16 proof is not on BN254"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Wraps a block proof into a Groth16 proof over BN254.
+pub fn wrap_proof(
+    wrapper: &BlockProofWrapper,
+    groth16: &Groth16Prover,
+    block_proof: &GeneratedBlockProof,
+) -> ProofGenResult<WrappedBlockProof> {
+    let shrunk = wrapper.shrink(&block_proof.intern)?;
+    let public_values_hash = HashOut {
+        elements: shrunk.public_inputs[..4]
+            .try_into()
+            .expect("wrapped proofs expose a hash"),
+    };
+
+    let dir = std::env::temp_dir().join(format!(
+        "groth16_b{}_{}",
+        block_proof.b_height,
+        std::process::id()
+    ));
+    let groth16_proof = groth16.prove(&dir, &wrapper.verifier_data(), &shrunk);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    Ok(WrappedBlockProof {
+        b_height: block_proof.b_height,
+        public_values_hash,
+        groth16_proof: groth16_proof?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use evm_arithmetization::testing_utils::TestBlockBuilder;
+    use evm_arithmetization::{AllStark, AllStarkConfig};
+    use plonky2::field::types::Field as _;
+    use plonky2::plonk::config::Hasher as _;
+    use plonky2::util::timing::TimingTree;
+
+    use super::*;
+    use crate::prover_state::{ProverState, ProverStateBuilder};
+
+    /// Proves block 1, made of two empty payloads, with `p_state`.
+    fn prove_empty_block(p_state: &ProverState) -> GeneratedBlockProof {
+        let all_stark = AllStark::default();
+        let config = AllStarkConfig::standard_fast_config();
+        let timing = &mut TimingTree::default();
+
+        let mut payload_proofs = vec![];
+        for beacon_roots_update_applied in [false, true] {
+            let inputs = TestBlockBuilder::new()
+                .block_number(1)
+                .beacon_roots_update_applied(beacon_roots_update_applied)
+                .build()
+                .unwrap();
+            let segments = p_state
+                .state
+                .prove_all_segments(&all_stark, &config, inputs, 20, timing, None)
+                .unwrap();
+            payload_proofs.push(
+                p_state
+                    .state
+                    .prove_segment_aggregation(false, &segments[0], false, &segments[1])
+                    .unwrap(),
+            );
+        }
+
+        let (txn_agg_proof, p_vals) = p_state
+            .state
+            .prove_transaction_aggregation(
+                false,
+                &payload_proofs[0].proof_with_pis,
+                payload_proofs[0].public_values.clone(),
+                false,
+                &payload_proofs[1].proof_with_pis,
+                payload_proofs[1].public_values.clone(),
+            )
+            .unwrap();
+        let (intern, _) = p_state
+            .state
+            .prove_block(None, &txn_agg_proof, p_vals)
+            .unwrap();
+
+        GeneratedBlockProof {
+            b_height: 1,
+            intern,
+        }
+    }
+
+    /// A well-formed Groth16 proof, made of the generators of BN254, which
+    /// doesn't verify against any actual verifying key.
+    #[cfg(unix)]
+    fn well_formed_groth16_proof() -> Vec<u8> {
+        [
+            "01",
+            "02",
+            "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+            "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+            "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+            "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+            "01",
+            "02",
+        ]
+        .into_iter()
+        .flat_map(|word| {
+            let mut bytes = [0; 32];
+            U256::from_str_radix(word, 16)
+                .unwrap()
+                .to_big_endian(&mut bytes);
+            bytes
+        })
+        .collect()
+    }
+
+    /// Writes an executable stub of a Groth16 prover to `dir`, which reports
+    /// `version`, checks that its inputs were written and outputs `proof`.
+    #[cfg(unix)]
+    fn stub_groth16_prover(dir: &Path, version: &str, proof: &[u8]) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::create_dir_all(dir).unwrap();
+        let proof_path = dir.join("proof.bin");
+        std::fs::write(&proof_path, proof).unwrap();
+        let path = dir.join("groth16_prover.sh");
+        let script = format!(
+            "#!/bin/sh\nset -e\n\
+             if [ \"$1\" = --version ]; then echo {version}; exit; fi\n\
+             for f in common_circuit_data verifier_only_circuit_data proof_with_public_inputs; \
+             do test -s \"$1/$f.json\"; done\n\
+             cp {proof_path:?} \"$1/{GROTH16_PROOF_FILE}\"\n"
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// Test that the outputs of the Groth16 prover are checked, as well as its
+    /// version when pinned.
+    #[cfg(unix)]
+    #[test]
+    fn checks_groth16_prover_outputs() {
+        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let target = builder.add_virtual_target();
+        builder.register_public_input(target);
+        let circuit = builder.build::<Config>();
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(target, Field::ONE);
+        let proof = circuit.prove(inputs).unwrap();
+        let verifier = circuit.verifier_data();
+
+        let dir = std::env::temp_dir().join(format!("groth16_checks_{}", std::process::id()));
+        let prove = |prover: &Groth16Prover| prover.prove(&dir.join("run"), &verifier, &proof);
+
+        let valid = well_formed_groth16_proof();
+        let prover = Groth16Prover::new(stub_groth16_prover(&dir.join("valid"), "v1", &valid));
+        assert_eq!(prove(&prover).unwrap(), valid);
+        assert_eq!(prove(&prover.clone().pinned_to("v1")).unwrap(), valid);
+        assert!(prove(&prover.pinned_to("v2")).is_err());
+
+        // Proofs of the point at infinity, with coordinates out of the base
+        // field or of the wrong length are rejected.
+        let mut out_of_field = valid.clone();
+        out_of_field[7 * 32..].copy_from_slice(&[0xff; 32]);
+        for (name, invalid) in [
+            ("zero", vec![0; 256]),
+            ("out_of_field", out_of_field),
+            ("truncated", valid[..7 * 32].to_vec()),
+        ] {
+            let prover = Groth16Prover::new(stub_groth16_prover(&dir.join(name), "v1", &invalid));
+            assert!(prove(&prover).is_err(), "{name}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Test that a block proof is shrunk and handed over to the Groth16 prover,
+    /// the wrapped proof committing to the public values of the block.
+    ///
+    /// The Groth16 prover is the one at `$GROTH16_PROVER` if set, pinned to
+    /// `$GROTH16_PROVER_VERSION` if set, and a stub outputting a well-formed
+    /// proof otherwise.
+    #[cfg(unix)]
+    #[ignore]
+    #[test]
+    fn wraps_block_proofs() {
+        let p_state = ProverStateBuilder::default()
+            .set_arithmetic_circuit_size(9..17)
+            .set_byte_packing_circuit_size(9..15)
+            .set_cpu_circuit_size(12..18)
+            .set_keccak_circuit_size(14..15)
+            .set_keccak_sponge_circuit_size(9..10)
+            .set_logic_circuit_size(12..13)
+            .set_memory_circuit_size(17..20)
+            .set_memory_before_circuit_size(16..17)
+            .set_memory_after_circuit_size(7..8)
+            .set_range_check_circuit_size(16..17)
+            .set_ec_circuit_size(4..5)
+            .build();
+        let block_proof = prove_empty_block(&p_state);
+        p_state.state.verify_block(&block_proof.intern).unwrap();
+
+        let wrapper = BlockProofWrapper::new(&p_state.state.final_verifier_data());
+        let public_values_hash = Hasher::hash_no_pad(extract_block_public_values(
+            &block_proof.intern.public_inputs,
+        ));
+        let shrunk = wrapper.shrink(&block_proof.intern).unwrap();
+        assert_eq!(shrunk.public_inputs[..4], public_values_hash.elements);
+
+        let dir = std::env::temp_dir().join(format!("groth16_test_{}", std::process::id()));
+        let groth16_prover = match std::env::var_os("GROTH16_PROVER") {
+            Some(command) => {
+                let prover = Groth16Prover::new(command);
+                match std::env::var("GROTH16_PROVER_VERSION") {
+                    Ok(version) => prover.pinned_to(version),
+                    Err(_) => prover,
+                }
+            }
+            None => Groth16Prover::new(stub_groth16_prover(
+                &dir,
+                "stub",
+                &well_formed_groth16_proof(),
+            )),
+        };
+        let wrapped = wrap_proof(&wrapper, &groth16_prover, &block_proof).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(wrapped.b_height, 1);
+        assert_eq!(wrapped.groth16_proof.len(), 8 * 32);
+        assert_eq!(wrapped.public_values_hash, public_values_hash);
+
+        // A failing Groth16 prover fails the wrap.
+        let failing_prover = Groth16Prover::new("false");
+        assert!(wrap_proof(&wrapper, &failing_prover, &block_proof).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+pragma solidity ^0.8.19;
+
+/// @title Verifier of wrapped block proofs.
+/// @notice Generated by `proof_gen::solidity` for a given set of block
+/// circuits: do not edit.
+/// @dev Checks Groth16 proofs over BN254 of the last wrapping circuit, whose
+/// public inputs are the hash of the public values of the block, followed by
+/// the public values in `BlockHeader`. Hashes are split into 32-bit limbs,
+/// from the least significant one.
+contract BlockProofVerifier {
+    /// @notice The public values identifying a block.
+    struct BlockHeader {
+        uint64 number;
+        uint64 timestamp;
+        bytes32 hash;
+        bytes32 stateRoot;
+        bytes32 transactionsRoot;
+        bytes32 receiptsRoot;
+        bytes32 checkpointStateRoot;
+        uint64 blobGasUsed;
+        uint64 excessBlobGas;
+        bytes32 parentBeaconBlockRoot;
+        bytes32 withdrawalsRoot;
+    }
+
+    /// The modulus of the base field of BN254.
+    uint256 constant Q = 21888242871839275222246405745257298275088548364400416034343698204186575808583;
+    /// The modulus of the Goldilocks field, in which public inputs lie.
+    uint256 constant GOLDILOCKS = 0xffffffff00000001;
+    uint256 constant NUM_PUBLIC_INPUTS = {{NUM_PUBLIC_INPUTS}};
+
+    // The verifying key of the Groth16 circuit. Coordinates over the quadratic
+    // extension are given as (c1, c0), as expected by the pairing precompile.
+{{VERIFYING_KEY}}
+    /// @notice Verifies the proof of the block identified by `header`, whose
+    /// public values hash to `publicValuesHash`.
+    /// @param proof The Groth16 proof, as `[A.x, A.y, B.x.c1, B.x.c0, B.y.c1,
+    /// B.y.c0, C.x, C.y]`.
+    function verifyBlockProof(
+        BlockHeader calldata header,
+        uint64[4] calldata publicValuesHash,
+        uint256[8] calldata proof
+    ) external view returns (bool) {
+        uint256[NUM_PUBLIC_INPUTS] memory inputs;
+        for (uint256 i = 0; i < 4; i++) {
+            inputs[i] = publicValuesHash[i];
+        }
+        inputs[4] = header.number;
+        inputs[5] = header.timestamp;
+        writeLimbs(inputs, 6, header.hash);
+        writeLimbs(inputs, 14, header.stateRoot);
+        writeLimbs(inputs, 22, header.transactionsRoot);
+        writeLimbs(inputs, 30, header.receiptsRoot);
+        writeLimbs(inputs, 38, header.checkpointStateRoot);
+        inputs[46] = header.blobGasUsed & 0xffffffff;
+        inputs[47] = header.blobGasUsed >> 32;
+        inputs[48] = header.excessBlobGas & 0xffffffff;
+        inputs[49] = header.excessBlobGas >> 32;
+        writeLimbs(inputs, 50, header.parentBeaconBlockRoot);
+        writeLimbs(inputs, 58, header.withdrawalsRoot);
+        return verifyProof(proof, inputs);
+    }
+
+    /// @notice Verifies a Groth16 proof against the public inputs of the last
+    /// wrapping circuit.
+    function verifyProof(
+        uint256[8] calldata proof,
+        uint256[NUM_PUBLIC_INPUTS] memory inputs
+    ) public view returns (bool) {
+        for (uint256 i = 0; i < NUM_PUBLIC_INPUTS; i++) {
+            if (inputs[i] >= GOLDILOCKS) {
+                return false;
+            }
+        }
+
+        uint256 x = IC_0_X;
+        uint256 y = IC_0_Y;
+        uint256 px;
+        uint256 py;
+{{LINEAR_COMBINATION}}
+        // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+        uint256[24] memory pairing = [
+            proof[0], (Q - proof[1]) % Q, proof[2], proof[3], proof[4], proof[5],
+            ALPHA_X, ALPHA_Y, BETA_X1, BETA_X0, BETA_Y1, BETA_Y0,
+            x, y, GAMMA_X1, GAMMA_X0, GAMMA_Y1, GAMMA_Y0,
+            proof[6], proof[7], DELTA_X1, DELTA_X0, DELTA_Y1, DELTA_Y0
+        ];
+        uint256[1] memory out;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x08, pairing, 0x300, out, 0x20)
+        }
+        return success && out[0] == 1;
+    }
+
+    function writeLimbs(
+        uint256[NUM_PUBLIC_INPUTS] memory inputs,
+        uint256 start,
+        bytes32 hash
+    ) internal pure {
+        for (uint256 i = 0; i < 8; i++) {
+            inputs[start + i] = (uint256(hash) >> (32 * i)) & 0xffffffff;
+        }
+    }
+
+    function ecAdd(uint256 ax, uint256 ay, uint256 bx, uint256 by)
+        internal
+        view
+        returns (uint256, uint256)
+    {
+        uint256[4] memory input = [ax, ay, bx, by];
+        uint256[2] memory result;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x06, input, 0x80, result, 0x40)
+        }
+        require(success, "ecAdd failed");
+        return (result[0], result[1]);
+    }
+
+    function ecMul(uint256 px, uint256 py, uint256 scalar)
+        internal
+        view
+        returns (uint256, uint256)
+    {
+        uint256[3] memory input = [px, py, scalar];
+        uint256[2] memory result;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x07, input, 0x60, result, 0x40)
+        }
+        require(success, "ecMul failed");
+        return (result[0], result[1]);
+    }
+}
@@ -0,0 +1,109 @@
+//! Conversions from [`alloy`] primitives to the [`ethereum_types`] ones used
+//! internally by the decoder.
+//!
+//! The [`BlockTrace`](crate::BlockTrace) and
+//! [`OtherBlockData`](crate::OtherBlockData) taken by the decoder are built on
+//! [`alloy`], while the tries and the
+//! [`GenerationInputs`](evm_arithmetization::GenerationInputs) it outputs are
+//! built on [`ethereum_types`]. Their fields are converted where they are
+//! read, with [`IntoEthereumTypes`].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use ethereum_types::{Address, H256, U256};
+
+/// A value which converts to the [`ethereum_types`] equivalent `T`.
+///
+/// This is implemented for the [`ethereum_types`] primitives themselves, their
+/// [`alloy`] equivalents, and the containers thereof.
+pub(crate) trait IntoEthereumTypes<T> {
+    /// Converts the value.
+    fn into_ethereum_types(self) -> T;
+}
+
+macro_rules! identity {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoEthereumTypes<$ty> for $ty {
+                fn into_ethereum_types(self) -> $ty {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+identity!(Address, H256, U256);
+
+impl IntoEthereumTypes<Address> for alloy::primitives::Address {
+    fn into_ethereum_types(self) -> Address {
+        Address(self.0 .0)
+    }
+}
+
+impl IntoEthereumTypes<H256> for alloy::primitives::B256 {
+    fn into_ethereum_types(self) -> H256 {
+        H256(self.0)
+    }
+}
+
+impl IntoEthereumTypes<U256> for alloy::primitives::U256 {
+    fn into_ethereum_types(self) -> U256 {
+        U256(self.into_limbs())
+    }
+}
+
+impl<T, U: IntoEthereumTypes<T>> IntoEthereumTypes<Option<T>> for Option<U> {
+    fn into_ethereum_types(self) -> Option<T> {
+        self.map(U::into_ethereum_types)
+    }
+}
+
+impl<T, U: IntoEthereumTypes<T>> IntoEthereumTypes<Vec<T>> for Vec<U> {
+    fn into_ethereum_types(self) -> Vec<T> {
+        self.into_iter().map(U::into_ethereum_types).collect()
+    }
+}
+
+impl<A, B, T: IntoEthereumTypes<A>, U: IntoEthereumTypes<B>> IntoEthereumTypes<(A, B)> for (T, U) {
+    fn into_ethereum_types(self) -> (A, B) {
+        (self.0.into_ethereum_types(), self.1.into_ethereum_types())
+    }
+}
+
+impl<K, V, L, W> IntoEthereumTypes<HashMap<K, V>> for HashMap<L, W>
+where
+    K: Eq + Hash,
+    L: IntoEthereumTypes<K>,
+    W: IntoEthereumTypes<V>,
+{
+    fn into_ethereum_types(self) -> HashMap<K, V> {
+        self.into_iter()
+            .map(|(k, v)| (k.into_ethereum_types(), v.into_ethereum_types()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256};
+
+    use super::*;
+    use crate::typed_mpt::TrieKey;
+
+    #[test]
+    fn converts_from_alloy() {
+        let address = address!("0102030405060708090a0b0c0d0e0f1011121314");
+        let ours = Address::from_slice(address.as_slice());
+        assert_eq!(address.into_ethereum_types(), ours);
+        assert_eq!(TrieKey::from_address(address), TrieKey::from_address(ours));
+
+        let hash = b256!("00000000000000000000000000000000000000000000000000000000000000ff");
+        assert_eq!(hash.into_ethereum_types(), H256::from_low_u64_be(0xff));
+
+        let withdrawals = vec![(address, alloy::primitives::U256::from(7))];
+        let converted: Vec<(Address, U256)> = withdrawals.into_ethereum_types();
+        assert_eq!(converted, [(ours, U256::from(7))]);
+    }
+}
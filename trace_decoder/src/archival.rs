@@ -0,0 +1,203 @@
+//! Revival of expired accounts from archival proofs.
+//!
+//! On chains with state expiry, accounts which have not been touched for a
+//! while are removed from the state trie, and transactions accessing them
+//! must revive them. Such traces carry, for each revived account, a proof of
+//! the account against the state root of the epoch in which it was last live,
+//! along with the updates it went through since.
+//!
+//! The decoder checks the proofs, replays the updates and inserts the revived
+//! accounts into the state trie pre-image, which then has the standard form.
+//! Note that the epoch roots are trusted: they are not committed to by the
+//! proofs of the block.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Context as _};
+use ethereum_types::{Address, H256, U256};
+use mpt_trie::builder::PartialTrieBuilder;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie as _};
+use serde::{Deserialize, Serialize};
+use zk_evm_common::AccountRlp;
+
+use crate::typed_mpt::{StateTrie, TrieKey};
+use crate::SeparateTriePreImages;
+
+/// Separate state/storage pre-images, along with the expired accounts
+/// revived by the block.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchivalPreImages {
+    /// The pre-images of the live state.
+    pub live: SeparateTriePreImages,
+    /// The accounts revived by the block, absent from the live state.
+    pub revivals: Vec<AccountRevival>,
+}
+
+/// An expired account, proven against the state root of an older epoch.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccountRevival {
+    /// The address of the revived account.
+    pub address: Address,
+    /// The state root of the epoch in which the account was last live.
+    pub epoch_root: H256,
+    /// The RLP-encoded nodes of the proof of the account against
+    /// `epoch_root`, as returned by `eth_getProof`.
+    pub proof: Vec<ProofNode>,
+    /// The updates the account went through since `epoch_root`, oldest
+    /// first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<AccountUpdate>,
+}
+
+/// An RLP-encoded trie node.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ProofNode(#[serde(with = "crate::hex")] pub Vec<u8>);
+
+/// The fields of an account changed by an update. Fields left to `None` are
+/// unchanged.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct AccountUpdate {
+    /// The new nonce of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U256>,
+    /// The new balance of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// The new storage root of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_root: Option<H256>,
+    /// The new code hash of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<H256>,
+}
+
+impl AccountRevival {
+    /// Checks the proof of the account and returns its revived state.
+    fn account(&self) -> anyhow::Result<AccountRlp> {
+        for ProofNode(node) in &self.proof {
+            // The trie builder expects well-formed nodes.
+            ensure!(
+                matches!(
+                    rlp::Rlp::new(node).prototype(),
+                    Ok(rlp::Prototype::List(2 | 17))
+                ),
+                "invalid node {} in the proof of {:x}",
+                hex::encode(node),
+                self.address
+            );
+        }
+        let mut builder =
+            PartialTrieBuilder::<HashedPartialTrie>::new(self.epoch_root, HashMap::new());
+        builder.insert_proof(self.proof.iter().map(|it| it.0.clone()).collect());
+        let trie = builder.build();
+
+        let key = TrieKey::from_address(self.address).into_nibbles();
+        let Some(bytes) = trie.get(key) else {
+            bail!(
+                "proof of {:x} does not include the account at epoch root {:x}",
+                self.address,
+                self.epoch_root
+            )
+        };
+        let mut account = rlp::decode::<AccountRlp>(bytes)
+            .with_context(|| format!("invalid account {:x} in proof", self.address))?;
+
+        for update in &self.updates {
+            let AccountUpdate {
+                nonce,
+                balance,
+                storage_root,
+                code_hash,
+            } = *update;
+            account.nonce = nonce.unwrap_or(account.nonce);
+            account.balance = balance.unwrap_or(account.balance);
+            account.storage_root = storage_root.unwrap_or(account.storage_root);
+            account.code_hash = code_hash.unwrap_or(account.code_hash);
+        }
+        Ok(account)
+    }
+}
+
+/// Inserts the `revivals` into the live `state` trie.
+pub(crate) fn revive(state: &mut StateTrie, revivals: &[AccountRevival]) -> anyhow::Result<()> {
+    for revival in revivals {
+        let key = TrieKey::from_address(revival.address);
+        ensure!(
+            !state.contains(key),
+            "revived account {:x} is live in the state pre-image",
+            revival.address
+        );
+        let account = revival.account()?;
+        log::debug!(
+            "reviving account {:x} from epoch root {:x}",
+            revival.address,
+            revival.epoch_root
+        );
+        state.insert_by_key(key, account).with_context(|| {
+            format!(
+                "couldn't insert revived account {:x} into the state pre-image",
+                revival.address
+            )
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use keccak_hash::keccak;
+    use mpt_trie::partial_trie::OnOrphanedHashNode;
+
+    use super::*;
+
+    #[test]
+    fn revive_from_single_leaf_proof() {
+        let address = Address::repeat_byte(0x42);
+        let account = AccountRlp {
+            nonce: 1.into(),
+            balance: 2.into(),
+            ..Default::default()
+        };
+        // An epoch state trie holding only the revived account.
+        let leaf = rlp::encode_list::<Vec<u8>, _>(&[
+            TrieKey::from_address(address)
+                .into_nibbles()
+                .to_hex_prefix_encoding(true)
+                .to_vec(),
+            rlp::encode(&account).to_vec(),
+        ])
+        .to_vec();
+        let revival = AccountRevival {
+            address,
+            epoch_root: keccak(&leaf),
+            proof: vec![ProofNode(leaf)],
+            updates: vec![AccountUpdate {
+                balance: Some(3.into()),
+                ..Default::default()
+            }],
+        };
+
+        let mut state = StateTrie::new(OnOrphanedHashNode::Reject);
+        revive(&mut state, &[revival.clone()]).unwrap();
+        assert_eq!(
+            state.get_by_address(address),
+            Some(AccountRlp {
+                balance: 3.into(),
+                ..account
+            })
+        );
+        // The account is now live.
+        assert!(revive(&mut state, &[revival.clone()]).is_err());
+
+        let wrong_root = AccountRevival {
+            epoch_root: H256::repeat_byte(1),
+            ..revival
+        };
+        assert!(revive(
+            &mut StateTrie::new(OnOrphanedHashNode::Reject),
+            &[wrong_root]
+        )
+        .is_err());
+    }
+}
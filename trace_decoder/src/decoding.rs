@@ -1,13 +1,17 @@
 use std::{cmp::min, collections::HashMap, ops::Range};
 
-use anyhow::{anyhow, Context as _};
-use ethereum_types::{Address, BigEndianHash, H256, U256, U512};
+use anyhow::anyhow;
+use ethereum_types::{Address, BigEndianHash, H256, U256};
+#[cfg(feature = "cdk_erigon")]
+use evm_arithmetization::testing_utils::{
+    GLOBAL_EXIT_ROOT_ADDRESS_HASHED, GLOBAL_EXIT_ROOT_STORAGE_POS,
+};
 use evm_arithmetization::{
     generation::{
         mpt::{decode_receipt, AccountRlp},
         GenerationInputs, TrieInputs,
     },
-    proof::{BlockMetadata, ExtraBlockData, TrieRoots},
+    proof::{BlockMetadata, ExtraBlockData, Hardfork, TrieRoots},
     testing_utils::{BEACON_ROOTS_CONTRACT_ADDRESS_HASHED, HISTORY_BUFFER_LENGTH},
 };
 use mpt_trie::{
@@ -16,14 +20,16 @@ use mpt_trie::{
     special_query::path_for_query,
     utils::{IntoTrieKey as _, TriePath},
 };
+use plonky2_maybe_rayon::*;
 
 use crate::{
+    error::{DecodeError, TrieType},
     hash,
     processed_block_trace::{
-        NodesUsedByTxn, ProcessedBlockTrace, ProcessedTxnInfo, StateWrite, TxnMetaState,
+        Hash2Code, NodesUsedByTxn, ProcessedBlockTrace, ProcessedTxnInfo, StateWrite, TxnMetaState,
     },
     typed_mpt::{ReceiptTrie, StateTrie, StorageTrie, TransactionTrie, TrieKey},
-    OtherBlockData, PartialTriePreImages,
+    IntoEthereumTypes, OtherBlockData, PartialTriePreImages,
 };
 
 /// The current state of all tries as we process txn deltas. These are mutated
@@ -50,10 +56,12 @@ pub fn into_txn_proof_gen_ir(
         tries: PartialTriePreImages { state, storage },
         txn_info,
         withdrawals,
+        block_rewards,
+        codes,
     }: ProcessedBlockTrace,
     other_data: OtherBlockData,
     batch_size: usize,
-) -> anyhow::Result<Vec<GenerationInputs>> {
+) -> Result<Vec<GenerationInputs>, DecodeError> {
     let mut curr_block_tries = PartialTrieState {
         state: state.clone(),
         storage: storage.iter().map(|(k, v)| (*k, v.clone())).collect(),
@@ -61,7 +69,7 @@ pub fn into_txn_proof_gen_ir(
     };
 
     let mut extra_data = ExtraBlockData {
-        checkpoint_state_trie_root: other_data.checkpoint_state_trie_root,
+        checkpoint_state_trie_root: other_data.checkpoint_state_trie_root.into_ethereum_types(),
         txn_number_before: U256::zero(),
         txn_number_after: U256::zero(),
         gas_used_before: U256::zero(),
@@ -72,8 +80,22 @@ pub fn into_txn_proof_gen_ir(
         .iter()
         .map(|tx_info| tx_info.meta.len())
         .sum::<usize>();
+    let num_txs_without_bytes = txn_info
+        .iter()
+        .flat_map(|tx_info| &tx_info.meta)
+        .filter(|meta| meta.txn_bytes.is_none())
+        .count();
+
+    let at_block = |e: DecodeError| {
+        e.at_block(
+            other_data.b_data.b_meta.block_number,
+            other_data.b_data.b_meta.block_chain_id,
+        )
+    };
 
-    let mut txn_gen_inputs = txn_info
+    // The deltas of the batches are applied in order, each batch keeping a
+    // copy-on-write snapshot of the tries at its start.
+    let (mut txn_gen_inputs, batch_tries): (Vec<_>, Vec<_>) = txn_info
         .into_iter()
         .enumerate()
         .map(|(txn_idx, txn_info)| {
@@ -88,25 +110,72 @@ pub fn into_txn_proof_gen_ir(
                 &mut curr_block_tries,
                 &mut extra_data,
                 &other_data,
+                &codes,
             )
-            .context(format!(
-                "at transaction range {}..{}",
-                txn_range.start, txn_range.end
-            ))
+            .map_err(|e| e.at_batch(txn_range))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(at_block)?
+        .into_iter()
+        .unzip();
+
+    // Extracting the minimal tries of a batch only reads its snapshot, so all
+    // the batches are done in parallel.
+    txn_gen_inputs
+        .par_iter_mut()
+        .zip(batch_tries.into_par_iter())
+        .try_for_each(|(gen_inputs, batch_tries)| {
+            let txn_range = batch_tries.txn_range.clone();
+            gen_inputs.tries = batch_tries
+                .into_minimal_tries()
+                .map_err(|e| e.at_batch(txn_range))?;
+            Ok::<_, DecodeError>(())
         })
-        .collect::<anyhow::Result<Vec<_>>>()
-        .context(format!(
-            "at block num {} with chain id {}",
-            other_data.b_data.b_meta.block_number, other_data.b_data.b_meta.block_chain_id
-        ))?;
-
-    if !withdrawals.is_empty() {
-        add_withdrawals_to_txns(&mut txn_gen_inputs, &mut curr_block_tries, withdrawals)?;
+        .map_err(at_block)?;
+
+    if let Some(expected) = other_data.b_data.transactions_root {
+        check_transactions_root(
+            &curr_block_tries.txn,
+            expected.into_ethereum_types(),
+            num_txs,
+            num_txs_without_bytes,
+        )
+        .map_err(at_block)?;
+    }
+
+    if !withdrawals.is_empty() || !block_rewards.is_empty() {
+        add_withdrawals_to_txns(
+            &mut txn_gen_inputs,
+            &mut curr_block_tries,
+            withdrawals,
+            block_rewards,
+        )?;
     }
 
     Ok(txn_gen_inputs)
 }
 
+/// Checks the transaction trie built from the raw transactions of the trace
+/// against the transactions root of the block header.
+fn check_transactions_root(
+    txn_trie: &TransactionTrie,
+    expected: H256,
+    num_txs: usize,
+    num_txs_without_bytes: usize,
+) -> Result<(), DecodeError> {
+    let actual = txn_trie.root();
+    if actual == expected {
+        return Ok(());
+    }
+
+    Err(DecodeError::TransactionsRoot {
+        expected,
+        actual,
+        num_txs,
+        num_txs_without_bytes,
+    })
+}
+
 /// Cancun HF specific: At the start of a block, prior txn execution, we
 /// need to update the storage of the beacon block root contract.
 // See <https://eips.ethereum.org/EIPS/eip-4788>.
@@ -115,47 +184,91 @@ fn update_beacon_block_root_contract_storage(
     delta_out: &mut TrieDeltaApplicationOutput,
     nodes_used: &mut NodesUsedByTxn,
     block_data: &BlockMetadata,
-) -> anyhow::Result<()> {
+) -> Result<(), DecodeError> {
     const HISTORY_BUFFER_LENGTH_MOD: U256 = U256([HISTORY_BUFFER_LENGTH.1, 0, 0, 0]);
     const ADDRESS: H256 = H256(BEACON_ROOTS_CONTRACT_ADDRESS_HASHED);
 
     let timestamp_idx = block_data.block_timestamp % HISTORY_BUFFER_LENGTH_MOD;
-    let timestamp = rlp::encode(&block_data.block_timestamp).to_vec();
+    let timestamp = block_data.block_timestamp;
 
     let root_idx = timestamp_idx + HISTORY_BUFFER_LENGTH_MOD;
-    let calldata = rlp::encode(&U256::from_big_endian(
-        &block_data.parent_beacon_block_root.0,
-    ))
-    .to_vec();
+    let calldata = U256::from_big_endian(&block_data.parent_beacon_block_root.0);
+
+    update_system_contract_storage(
+        trie_state,
+        delta_out,
+        nodes_used,
+        ADDRESS,
+        [(timestamp_idx, timestamp), (root_idx, calldata)],
+    )
+}
 
-    let storage_trie = trie_state
-        .storage
-        .get_mut(&ADDRESS)
-        .context(format!("missing account storage trie {:x}", ADDRESS))?;
+/// cdk-erigon specific: At the start of a block, prior txn execution, the
+/// timestamp of each global exit root is written to the storage of the global
+/// exit root manager, in a slot derived from the root.
+// See <https://github.com/0xPolygonHermez/cdk-erigon/blob/zkevm/zk/utils/global_exit_root.go>.
+#[cfg(feature = "cdk_erigon")]
+fn update_global_exit_root_storage(
+    trie_state: &mut PartialTrieState,
+    delta_out: &mut TrieDeltaApplicationOutput,
+    nodes_used: &mut NodesUsedByTxn,
+    global_exit_roots: &[(alloy::primitives::U256, alloy::primitives::B256)],
+) -> Result<(), DecodeError> {
+    if global_exit_roots.is_empty() {
+        return Ok(());
+    }
 
-    let slots_nibbles = nodes_used.storage_accesses.entry(ADDRESS).or_default();
+    let storage_pos = H256::from_low_u64_be(GLOBAL_EXIT_ROOT_STORAGE_POS.1);
+    update_system_contract_storage(
+        trie_state,
+        delta_out,
+        nodes_used,
+        H256(GLOBAL_EXIT_ROOT_ADDRESS_HASHED),
+        global_exit_roots.iter().map(|(timestamp, root)| {
+            let slot = hash([root.as_slice(), storage_pos.as_bytes()].concat());
+            (slot.into_uint(), timestamp.into_ethereum_types())
+        }),
+    )
+}
 
-    for (ix, val) in [(timestamp_idx, timestamp), (root_idx, calldata)] {
-        // TODO(0xaatif): https://github.com/0xPolygonZero/zk_evm/issues/275
-        //                document this
-        let slot = TrieKey::from_nibbles(Nibbles::from_h256_be(hash(
-            Nibbles::from_h256_be(H256::from_uint(&ix)).bytes_be(),
-        )));
+/// Writes the values of `slots` to the storage of the system contract at the
+/// hashed address `address`.
+fn update_system_contract_storage(
+    trie_state: &mut PartialTrieState,
+    delta_out: &mut TrieDeltaApplicationOutput,
+    nodes_used: &mut NodesUsedByTxn,
+    address: H256,
+    slots: impl IntoIterator<Item = (U256, U256)>,
+) -> Result<(), DecodeError> {
+    let storage_trie =
+        trie_state
+            .storage
+            .get_mut(&address)
+            .ok_or(DecodeError::MissingStorageTrie {
+                hashed_address: address,
+            })?;
+
+    let slots_nibbles = nodes_used.storage_accesses.entry(address).or_default();
+
+    for (ix, val) in slots {
+        let slot = TrieKey::from_slot(ix);
 
         slots_nibbles.push(slot);
 
         // If we are writing a zero, then we actually need to perform a delete.
-        match val == ZERO_STORAGE_SLOT_VAL_RLPED {
+        match val.is_zero() {
             false => {
-                storage_trie.insert(slot, val.clone()).context(format!(
-                    "at slot {:?} with value {}",
-                    slot,
-                    U512::from_big_endian(val.as_slice())
-                ))?;
+                storage_trie
+                    .set_slot(ix, val)
+                    .map_err(|e| DecodeError::StorageWrite {
+                        hashed_address: address,
+                        hashed_slot: slot.into_hash_left_padded(),
+                        source: e.into(),
+                    })?;
 
                 delta_out
                     .additional_storage_trie_paths_to_not_hash
-                    .entry(ADDRESS)
+                    .entry(address)
                     .or_default()
                     .push(slot);
             }
@@ -168,7 +281,7 @@ fn update_beacon_block_root_contract_storage(
                 {
                     delta_out
                         .additional_storage_trie_paths_to_not_hash
-                        .entry(ADDRESS)
+                        .entry(address)
                         .or_default()
                         .push(remaining_slot_key);
                 }
@@ -176,14 +289,17 @@ fn update_beacon_block_root_contract_storage(
         }
     }
 
-    let addr_nibbles = TrieKey::from_hash(ADDRESS);
+    let addr_nibbles = TrieKey::from_hash(address);
     delta_out
         .additional_state_trie_paths_to_not_hash
         .push(addr_nibbles);
-    let mut account = trie_state
-        .state
-        .get_by_key(addr_nibbles)
-        .context(format!("missing account storage trie {:x}", ADDRESS))?;
+    let mut account =
+        trie_state
+            .state
+            .get_by_key(addr_nibbles)
+            .ok_or(DecodeError::MissingAccount {
+                hashed_address: address,
+            })?;
 
     account.storage_root = storage_trie.root();
 
@@ -201,7 +317,7 @@ fn update_txn_and_receipt_tries(
     trie_state: &mut PartialTrieState,
     meta: &TxnMetaState,
     txn_idx: usize,
-) -> anyhow::Result<()> {
+) -> Result<(), DecodeError> {
     if let Some(bytes) = &meta.txn_bytes {
         trie_state.txn.insert(txn_idx, bytes.clone())?;
         trie_state
@@ -241,7 +357,7 @@ fn create_minimal_partial_tries_needed_by_txn(
     nodes_used_by_txn: &NodesUsedByTxn,
     txn_range: Range<usize>,
     delta_application_out: TrieDeltaApplicationOutput,
-) -> anyhow::Result<TrieInputs> {
+) -> Result<TrieInputs, DecodeError> {
     let state_trie = create_minimal_state_partial_trie(
         &curr_block_tries.state,
         nodes_used_by_txn.state_accesses.iter().cloned(),
@@ -284,28 +400,30 @@ fn apply_deltas_to_trie_state(
     trie_state: &mut PartialTrieState,
     deltas: &NodesUsedByTxn,
     meta: &[TxnMetaState],
-) -> anyhow::Result<TrieDeltaApplicationOutput> {
+) -> Result<TrieDeltaApplicationOutput, DecodeError> {
     let mut out = TrieDeltaApplicationOutput::default();
 
     for (hashed_acc_addr, storage_writes) in deltas.storage_writes.iter() {
-        let storage_trie = trie_state
-            .storage
-            .get_mut(hashed_acc_addr)
-            .context(format!(
-                "missing account storage trie {:x}",
-                hashed_acc_addr
-            ))?;
-
-        for (key, val) in storage_writes {
-            let slot = TrieKey::from_hash(hash(key.into_nibbles().bytes_be()));
+        let storage_trie =
+            trie_state
+                .storage
+                .get_mut(hashed_acc_addr)
+                .ok_or(DecodeError::MissingStorageTrie {
+                    hashed_address: *hashed_acc_addr,
+                })?;
+
+        for (&ix, val) in storage_writes {
+            let slot = TrieKey::from_slot(ix);
             // If we are writing a zero, then we actually need to perform a delete.
-            match val == &ZERO_STORAGE_SLOT_VAL_RLPED {
+            match val.is_zero() {
                 false => {
-                    storage_trie.insert(slot, val.clone()).context(format!(
-                        "at slot {:?} with value {}",
-                        slot,
-                        U512::from_big_endian(val.as_slice())
-                    ))?;
+                    storage_trie
+                        .set_slot(ix, *val)
+                        .map_err(|e| DecodeError::StorageWrite {
+                            hashed_address: *hashed_acc_addr,
+                            hashed_slot: slot.into_hash_left_padded(),
+                            source: e.into(),
+                        })?;
                 }
                 true => {
                     if let Some(remaining_slot_key) =
@@ -402,7 +520,7 @@ fn get_trie_trace(trie: &HashedPartialTrie, k: &Nibbles) -> TriePath {
 fn delete_node_and_report_remaining_key_if_branch_collapsed(
     trie: &mut HashedPartialTrie,
     delete_k: &TrieKey,
-) -> anyhow::Result<Option<TrieKey>> {
+) -> Result<Option<TrieKey>, DecodeError> {
     let old_trace = get_trie_trace(trie, &delete_k.into_nibbles());
     trie.delete(delete_k.into_nibbles())?;
     let new_trace = get_trie_trace(trie, &delete_k.into_nibbles());
@@ -437,12 +555,14 @@ fn node_deletion_resulted_in_a_branch_collapse(
     branch_collapse_occurred.then(|| new_path.iter().into_key())
 }
 
-/// The withdrawals are always in the final ir payload.
+/// The withdrawals, and the block rewards of pre-Merge blocks, are always in
+/// the final ir payload.
 fn add_withdrawals_to_txns(
     txn_ir: &mut [GenerationInputs],
     final_trie_state: &mut PartialTrieState,
     mut withdrawals: Vec<(Address, U256)>,
-) -> anyhow::Result<()> {
+    block_rewards: Vec<(Address, U256)>,
+) -> Result<(), DecodeError> {
     // Scale withdrawals amounts.
     for (_addr, amt) in withdrawals.iter_mut() {
         *amt = eth_to_gwei(*amt)
@@ -460,22 +580,30 @@ fn add_withdrawals_to_txns(
 
     if last_inputs.signed_txns.is_empty() {
         // This is a dummy payload, hence it does not contain yet
-        // state accesses to the withdrawal addresses.
-        let withdrawal_addrs = withdrawals_with_hashed_addrs_iter().map(|(_, h_addr, _)| h_addr);
-
-        let additional_paths = if last_inputs.txn_number_before == 0.into() {
-            // We need to include the beacon roots contract as this payload is at the
-            // start of the block execution.
-            vec![TrieKey::from_hash(H256(
-                BEACON_ROOTS_CONTRACT_ADDRESS_HASHED,
-            ))]
-        } else {
-            vec![]
-        };
+        // state accesses to the withdrawal and rewarded addresses.
+        let accessed_addrs = withdrawals_with_hashed_addrs_iter()
+            .map(|(_, h_addr, _)| h_addr)
+            .chain(block_rewards.iter().map(|(addr, _)| hash(addr.as_bytes())));
+
+        let mut additional_paths = vec![];
+        if last_inputs.txn_number_before == 0.into() {
+            // We need to include the system contracts updated at the start of the
+            // block execution, as this payload is at the start of it.
+            let hardfork = last_inputs.block_metadata.block_hardfork;
+            if hardfork >= Hardfork::Cancun {
+                additional_paths.push(TrieKey::from_hash(H256(
+                    BEACON_ROOTS_CONTRACT_ADDRESS_HASHED,
+                )));
+            }
+            #[cfg(feature = "cdk_erigon")]
+            if !last_inputs.global_exit_roots.is_empty() {
+                additional_paths.push(TrieKey::from_hash(H256(GLOBAL_EXIT_ROOT_ADDRESS_HASHED)));
+            }
+        }
 
         last_inputs.tries.state_trie = create_minimal_state_partial_trie(
             &final_trie_state.state,
-            withdrawal_addrs,
+            accessed_addrs,
             additional_paths,
         )?
         .as_hashed_partial_trie()
@@ -486,8 +614,10 @@ fn add_withdrawals_to_txns(
         withdrawals_with_hashed_addrs_iter(),
         &mut final_trie_state.state,
     )?;
+    update_trie_state_from_block_rewards(&block_rewards, &mut final_trie_state.state)?;
 
     last_inputs.withdrawals = withdrawals;
+    last_inputs.block_rewards = block_rewards;
     last_inputs.trie_roots_after.state_root = final_trie_state.state.root();
 
     Ok(())
@@ -498,11 +628,15 @@ fn add_withdrawals_to_txns(
 fn update_trie_state_from_withdrawals<'a>(
     withdrawals: impl IntoIterator<Item = (Address, H256, U256)> + 'a,
     state: &mut StateTrie,
-) -> anyhow::Result<()> {
-    for (addr, h_addr, amt) in withdrawals {
-        let mut acc_data = state.get_by_address(addr).context(format!(
-            "No account present at {addr:x} (hashed: {h_addr:x}) to withdraw {amt} Gwei from!"
-        ))?;
+) -> Result<(), DecodeError> {
+    for (addr, _, amt) in withdrawals {
+        let mut acc_data =
+            state
+                .get_by_address(addr)
+                .ok_or(DecodeError::MissingWithdrawalAccount {
+                    address: addr,
+                    amount: amt,
+                })?;
 
         acc_data.balance += amt;
 
@@ -516,7 +650,44 @@ fn update_trie_state_from_withdrawals<'a>(
     Ok(())
 }
 
+/// Block rewards are credited to the miners after the withdrawals, creating
+/// their accounts if needed.
+fn update_trie_state_from_block_rewards(
+    block_rewards: &[(Address, U256)],
+    state: &mut StateTrie,
+) -> Result<(), DecodeError> {
+    for &(addr, amt) in block_rewards {
+        let mut acc_data = state.get_by_address(addr).unwrap_or_default();
+        acc_data.balance += amt;
+        state.insert_by_address(addr, acc_data)?;
+    }
+
+    Ok(())
+}
+
+/// The tries accessed by a batch, from which its minimal tries are extracted.
+struct BatchTries {
+    tries_at_start_of_txn: PartialTrieState,
+    nodes_used_by_txn: NodesUsedByTxn,
+    txn_range: Range<usize>,
+    delta_out: TrieDeltaApplicationOutput,
+}
+
+impl BatchTries {
+    fn into_minimal_tries(self) -> Result<TrieInputs, DecodeError> {
+        create_minimal_partial_tries_needed_by_txn(
+            &self.tries_at_start_of_txn,
+            &self.nodes_used_by_txn,
+            self.txn_range,
+            self.delta_out,
+        )
+    }
+}
+
 /// Processes a single transaction in the trace.
+///
+/// The tries of the returned inputs are left empty, to be extracted from the
+/// returned [`BatchTries`].
 fn process_txn_info(
     txn_range: Range<usize>,
     is_initial_payload: bool,
@@ -524,7 +695,8 @@ fn process_txn_info(
     curr_block_tries: &mut PartialTrieState,
     extra_data: &mut ExtraBlockData,
     other_data: &OtherBlockData,
-) -> anyhow::Result<GenerationInputs> {
+    codes: &Hash2Code,
+) -> Result<(GenerationInputs, BatchTries), DecodeError> {
     log::trace!(
         "Generating proof IR for txn {} through {}...",
         txn_range.start,
@@ -563,11 +735,21 @@ fn process_txn_info(
 
     let nodes_used_by_txn = if is_initial_payload {
         let mut nodes_used = txn_info.nodes_used_by_txn;
-        update_beacon_block_root_contract_storage(
+        let b_meta = &other_data.b_data.b_meta;
+        if b_meta.block_hardfork >= Hardfork::Cancun {
+            update_beacon_block_root_contract_storage(
+                curr_block_tries,
+                &mut delta_out,
+                &mut nodes_used,
+                b_meta,
+            )?;
+        }
+        #[cfg(feature = "cdk_erigon")]
+        update_global_exit_root_storage(
             curr_block_tries,
             &mut delta_out,
             &mut nodes_used,
-            &other_data.b_data.b_meta,
+            &other_data.b_data.global_exit_roots,
         )?;
 
         nodes_used
@@ -575,13 +757,6 @@ fn process_txn_info(
         txn_info.nodes_used_by_txn
     };
 
-    let tries = create_minimal_partial_tries_needed_by_txn(
-        &tries_at_start_of_txn,
-        &nodes_used_by_txn,
-        txn_range,
-        delta_out,
-    )?;
-
     let gen_inputs = GenerationInputs {
         txn_number_before: extra_data.txn_number_before,
         gas_used_before: extra_data.gas_used_before,
@@ -594,7 +769,8 @@ fn process_txn_info(
         withdrawals: Vec::default(), /* Only ever set in a dummy txn at the end of
                                       * the block (see `[add_withdrawals_to_txns]`
                                       * for more info). */
-        tries,
+        block_rewards: Vec::default(),
+        tries: TrieInputs::default(),
         trie_roots_after: TrieRoots {
             state_root: curr_block_tries.state.root(),
             transactions_root: curr_block_tries.txn.root(),
@@ -604,11 +780,20 @@ fn process_txn_info(
         contract_code: txn_info
             .contract_code_accessed
             .into_iter()
-            .map(|code| (hash(&code), code))
-            .collect(),
+            .map(|code_hash| Ok((code_hash, codes.get(code_hash)?.to_vec())))
+            .collect::<Result<_, DecodeError>>()?,
+        jumpdest_tables: Default::default(),
         block_metadata: other_data.b_data.b_meta.clone(),
         block_hashes: other_data.b_data.b_hashes.clone(),
-        global_exit_roots: vec![],
+        global_exit_roots: match is_initial_payload {
+            true => other_data
+                .b_data
+                .global_exit_roots
+                .clone()
+                .into_ethereum_types(),
+            false => vec![],
+        },
+        burn_addr: other_data.b_data.burn_addr.into_ethereum_types(),
     };
 
     // After processing a transaction, we update the remaining accumulators
@@ -616,7 +801,15 @@ fn process_txn_info(
     extra_data.txn_number_before = extra_data.txn_number_after;
     extra_data.gas_used_before = extra_data.gas_used_after;
 
-    Ok(gen_inputs)
+    Ok((
+        gen_inputs,
+        BatchTries {
+            tries_at_start_of_txn,
+            nodes_used_by_txn,
+            txn_range,
+            delta_out,
+        },
+    ))
 }
 
 impl StateWrite {
@@ -625,13 +818,16 @@ impl StateWrite {
         state_node: &mut AccountRlp,
         h_addr: &H256,
         acc_storage_tries: &HashMap<H256, StorageTrie>,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), DecodeError> {
         let storage_root_hash_change = match self.storage_trie_change {
             false => None,
             true => {
-                let storage_trie = acc_storage_tries
-                    .get(h_addr)
-                    .context(format!("missing account storage trie {:x}", h_addr))?;
+                let storage_trie =
+                    acc_storage_tries
+                        .get(h_addr)
+                        .ok_or(DecodeError::MissingStorageTrie {
+                            hashed_address: *h_addr,
+                        })?;
 
                 Some(storage_trie.root())
             }
@@ -650,16 +846,18 @@ fn create_minimal_state_partial_trie(
     state_trie: &StateTrie,
     state_accesses: impl IntoIterator<Item = H256>,
     additional_state_trie_paths_to_not_hash: impl IntoIterator<Item = TrieKey>,
-) -> anyhow::Result<StateTrie> {
-    create_trie_subset_wrapped(
-        state_trie.as_hashed_partial_trie(),
-        state_accesses
-            .into_iter()
-            .map(TrieKey::from_hash)
-            .chain(additional_state_trie_paths_to_not_hash),
-        TrieType::State,
-    )
-    .map(StateTrie::from_hashed_partial_trie_unchecked)
+) -> Result<StateTrie, DecodeError> {
+    state_trie
+        .trim_to(
+            state_accesses
+                .into_iter()
+                .map(TrieKey::from_hash)
+                .chain(additional_state_trie_paths_to_not_hash),
+        )
+        .map_err(|source| DecodeError::TrieSubset {
+            trie: TrieType::State,
+            source,
+        })
 }
 
 // TODO!!!: We really need to be appending the empty storage tries to the base
@@ -668,7 +866,7 @@ fn create_minimal_storage_partial_tries<'a>(
     storage_tries: &HashMap<H256, StorageTrie>,
     accesses_per_account: impl IntoIterator<Item = (&'a H256, &'a Vec<TrieKey>)>,
     additional_storage_trie_paths_to_not_hash: &HashMap<H256, Vec<TrieKey>>,
-) -> anyhow::Result<Vec<(H256, HashedPartialTrie)>> {
+) -> Result<Vec<(H256, HashedPartialTrie)>, DecodeError> {
     accesses_per_account
         .into_iter()
         .map(|(h_addr, mem_accesses)| {
@@ -698,29 +896,18 @@ fn create_trie_subset_wrapped(
     trie: &HashedPartialTrie,
     accesses: impl IntoIterator<Item = TrieKey>,
     trie_type: TrieType,
-) -> anyhow::Result<HashedPartialTrie> {
+) -> Result<HashedPartialTrie, DecodeError> {
     mpt_trie::trie_subsets::create_trie_subset(
         trie,
         accesses.into_iter().map(TrieKey::into_nibbles),
     )
-    .context(format!("missing keys when creating {}", trie_type))
+    .map_err(|source| DecodeError::TrieSubset {
+        trie: trie_type,
+        source,
+    })
 }
 
 fn eth_to_gwei(eth: U256) -> U256 {
     // 1 ether = 10^9 gwei.
     eth * U256::from(10).pow(9.into())
 }
-
-// This is just `rlp(0)`.
-const ZERO_STORAGE_SLOT_VAL_RLPED: [u8; 1] = [128];
-
-/// Aid for error context.
-/// Covers all Ethereum trie types (see <https://ethereum.github.io/yellowpaper/paper.pdf> for details).
-#[derive(Debug, strum::Display)]
-#[allow(missing_docs)]
-enum TrieType {
-    State,
-    Storage,
-    Receipt,
-    Txn,
-}
@@ -0,0 +1,212 @@
+//! Errors of the decoder, locating the transactions, accounts and slots at
+//! fault.
+
+use std::ops::Range;
+
+use ethereum_types::{Address, H256, U256};
+use mpt_trie::trie_ops::TrieOpError;
+use mpt_trie::trie_subsets::SubsetTrieError;
+
+/// An error decoding a [`BlockTrace`](crate::BlockTrace) into
+/// [`GenerationInputs`](evm_arithmetization::GenerationInputs).
+///
+/// Errors are nested in the variants locating them, from the block down to the
+/// transaction and the account, which are reported as their [`source`] chain.
+///
+/// [`source`]: std::error::Error::source
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// An error in a block.
+    #[error("at block num {block_number} with chain id {chain_id}")]
+    AtBlock {
+        /// The number of the block.
+        block_number: U256,
+        /// The chain id of the block.
+        chain_id: U256,
+        /// The error.
+        #[source]
+        source: Box<DecodeError>,
+    },
+    /// An error in a batch of transactions.
+    #[error("at transaction range {}..{}", txn_range.start, txn_range.end)]
+    AtBatch {
+        /// The indices of the transactions of the batch in the block.
+        txn_range: Range<usize>,
+        /// The error.
+        #[source]
+        source: Box<DecodeError>,
+    },
+    /// An error in a transaction.
+    #[error("at transaction {txn_ix}")]
+    AtTxn {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The error.
+        #[source]
+        source: Box<DecodeError>,
+    },
+    /// An error in the trace of an account.
+    #[error("at account {address:x}")]
+    AtAccount {
+        /// The account.
+        address: Address,
+        /// The error.
+        #[source]
+        source: Box<DecodeError>,
+    },
+    /// An account is missing from the state trie.
+    #[error("missing account {hashed_address:x} in the state trie")]
+    MissingAccount {
+        /// The hash of the address of the account.
+        hashed_address: H256,
+    },
+    /// A withdrawal is made to an account missing from the state trie.
+    #[error("no account present at {address:x} to withdraw {amount} Gwei to")]
+    MissingWithdrawalAccount {
+        /// The account.
+        address: Address,
+        /// The amount of the withdrawal, in Gwei.
+        amount: U256,
+    },
+    /// The storage trie of an account is missing.
+    #[error("missing account storage trie {hashed_address:x}")]
+    MissingStorageTrie {
+        /// The hash of the address of the account.
+        hashed_address: H256,
+    },
+    /// A storage slot could not be written.
+    #[error("couldn't write slot {hashed_slot:x} of account {hashed_address:x}")]
+    StorageWrite {
+        /// The hash of the address of the account.
+        hashed_address: H256,
+        /// The hash of the slot.
+        hashed_slot: H256,
+        /// The error of the storage trie.
+        #[source]
+        source: TrieOpError,
+    },
+    /// A code read by a transaction is missing from the trace.
+    #[error("no code for hash {code_hash:x}")]
+    MissingCode {
+        /// The hash of the code.
+        code_hash: H256,
+    },
+    /// A transaction deletes a contract it didn't create, which
+    /// [EIP-6780](https://eips.ethereum.org/EIPS/eip-6780) rules out.
+    #[error("self-destruct of a contract which the transaction didn't create")]
+    IllegalSelfDestruct,
+    /// The minimal trie of a batch could not be extracted from a trie,
+    /// because some of the keys accessed by the batch are hashed out.
+    #[error("missing keys when creating {trie}")]
+    TrieSubset {
+        /// The trie.
+        trie: TrieType,
+        /// The error of the trie.
+        #[source]
+        source: SubsetTrieError,
+    },
+    /// The transaction trie of the trace is not the one of the block.
+    #[error(
+        "transactions root mismatch: built {actual:x} from {num_txs} transactions, \
+         but the block header has {expected:x} ({})",
+        match num_txs_without_bytes {
+            0 => String::from("the raw transactions of the trace do not match the block"),
+            n => format!("{n} transactions of the trace have no raw bytes"),
+        }
+    )]
+    TransactionsRoot {
+        /// The transactions root of the block header.
+        expected: H256,
+        /// The root of the transaction trie of the trace.
+        actual: H256,
+        /// The number of transactions of the trace.
+        num_txs: usize,
+        /// The number of transactions of the trace without raw bytes.
+        num_txs_without_bytes: usize,
+    },
+    /// A trie operation failed.
+    #[error(transparent)]
+    Trie(#[from] TrieOpError),
+    /// Any other error, e.g. malformed pre-images or transactions.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[cfg(any(feature = "generation", test))]
+impl DecodeError {
+    pub(crate) fn at_block(self, block_number: U256, chain_id: U256) -> Self {
+        Self::AtBlock {
+            block_number,
+            chain_id,
+            source: Box::new(self),
+        }
+    }
+
+    pub(crate) fn at_batch(self, txn_range: Range<usize>) -> Self {
+        Self::AtBatch {
+            txn_range,
+            source: Box::new(self),
+        }
+    }
+
+    pub(crate) fn at_txn(self, txn_ix: usize) -> Self {
+        Self::AtTxn {
+            txn_ix,
+            source: Box::new(self),
+        }
+    }
+
+    pub(crate) fn at_account(self, address: Address) -> Self {
+        Self::AtAccount {
+            address,
+            source: Box::new(self),
+        }
+    }
+}
+
+impl From<crate::typed_mpt::Error> for DecodeError {
+    fn from(e: crate::typed_mpt::Error) -> Self {
+        Self::Trie(e.into())
+    }
+}
+
+/// The Ethereum tries.
+///
+/// See <https://ethereum.github.io/yellowpaper/paper.pdf> for details.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum TrieType {
+    /// The state trie.
+    State,
+    /// A storage trie.
+    Storage,
+    /// The receipt trie.
+    Receipt,
+    /// The transaction trie.
+    Txn,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_errors() {
+        let address = Address::repeat_byte(1);
+        let e = DecodeError::MissingCode {
+            code_hash: H256::zero(),
+        }
+        .at_account(address)
+        .at_txn(3)
+        .at_batch(2..4)
+        .at_block(10.into(), 1.into());
+        assert_eq!(
+            format!("{:#}", anyhow::Error::new(e)),
+            format!(
+                "at block num 10 with chain id 1: at transaction range 2..4: at transaction 3: \
+                 at account {address:x}: no code for hash {:x}",
+                H256::zero()
+            )
+        );
+    }
+}
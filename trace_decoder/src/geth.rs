@@ -0,0 +1,412 @@
+//! Converter from the outputs of unmodified [Geth](https://geth.ethereum.org/)
+//! nodes into a [`BlockTrace`].
+//!
+//! Geth does not emit witnesses, but the same data can be recovered from two
+//! standard RPC methods:
+//! - the [`prestateTracer`](https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers#prestate-tracer)
+//!   of `debug_traceBlockByNumber`, in both default and diff mode, which gives
+//!   the accounts and storage slots read and written by each transaction.
+//! - `eth_getProof` at the parent block, for all the accounts and slots touched
+//!   by the block, whose Merkle proofs make up the state and storage
+//!   pre-images.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use mpt_trie::builder::PartialTrieBuilder;
+use mpt_trie::partial_trie::HashedPartialTrie;
+use serde::{Deserialize, Serialize};
+use zk_evm_common::Hardfork;
+
+use crate::{
+    BlockTrace, BlockTraceTriePreImages, ContractCodeUsage, IntoEthereumTypes,
+    SeparateStorageTriesPreImage, SeparateTriePreImage, SeparateTriePreImages, TxnInfo, TxnMeta,
+    TxnTrace,
+};
+
+/// Hex-encoded bytes, as found in Geth RPC responses.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Bytes(#[serde(with = "crate::hex")] pub Vec<u8>);
+
+/// The state of an account reported by the `prestateTracer`.
+///
+/// In default mode, it is the state of the account before the transaction. In
+/// diff mode, only the fields modified by the transaction are reported.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccountState {
+    /// The balance of the account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// The nonce of the account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// The code of the account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// The storage slots of the account, along with their values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+/// The output of the `prestateTracer` in diff mode.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DiffMode {
+    /// The state of the modified accounts before the transaction.
+    pub pre: HashMap<Address, AccountState>,
+    /// The state of the modified accounts after the transaction. Accounts
+    /// deleted by the transaction are absent.
+    pub post: HashMap<Address, AccountState>,
+}
+
+/// A Merkle proof of a storage slot, from an `eth_getProof` response.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageProof {
+    /// The nodes of the storage trie from its root to the slot.
+    pub proof: Vec<Bytes>,
+}
+
+/// An `eth_getProof` response.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    /// The address of the account.
+    pub address: Address,
+    /// The nodes of the state trie from its root to the account.
+    pub account_proof: Vec<Bytes>,
+    /// The root of the storage trie of the account.
+    pub storage_hash: B256,
+    /// The proofs of the requested storage slots.
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/// The Geth outputs describing a transaction.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GethTxn {
+    /// The encoded transaction and receipt.
+    pub meta: TxnMeta,
+    /// The output of the `prestateTracer` in default mode.
+    pub prestate: HashMap<Address, AccountState>,
+    /// The output of the `prestateTracer` in diff mode.
+    pub diff: DiffMode,
+    /// The storage keys of the access list of the transaction.
+    #[serde(default)]
+    pub access_list: HashMap<Address, HashSet<B256>>,
+    /// The address of the contract whose creation by the transaction
+    /// reverted, which the traces omit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverted_creation: Option<Address>,
+}
+
+/// Converts the traces of the transactions of a block, along with the
+/// `eth_getProof` responses of the accounts they touch, into a [`BlockTrace`].
+///
+/// `proofs` must be taken at the parent block, whose state root is
+/// `parent_state_root`. `next_proofs` are the proofs of the same accounts
+/// taken at the block itself. They provide the nodes of the state trie that
+/// deleting accounts or slots collapses, see [`state_pre_images`].
+///
+/// `hardfork` is the one the block belongs to, which determines the accounts
+/// a `SELFDESTRUCT` deletes, see [`txn_traces`].
+pub fn block_trace(
+    parent_state_root: B256,
+    txns: Vec<GethTxn>,
+    proofs: &[AccountProof],
+    next_proofs: &[AccountProof],
+    hardfork: Hardfork,
+) -> BlockTrace {
+    let mut code_db = HashMap::new();
+    let txn_info = txns
+        .into_iter()
+        .map(|txn| {
+            let (code, mut traces) =
+                txn_traces(&txn.prestate, &txn.diff, &txn.access_list, hardfork);
+            code_db.extend(code);
+            if let Some(address) = txn.reverted_creation {
+                traces.insert(address, TxnTrace::default());
+            }
+            TxnInfo {
+                traces,
+                meta: txn.meta,
+            }
+        })
+        .collect();
+
+    BlockTrace {
+        trie_pre_images: state_pre_images(parent_state_root, proofs, next_proofs),
+        code_db: Some(code_db).filter(|it| !it.is_empty()),
+        txn_info,
+        jumpdest_tables: Default::default(),
+    }
+}
+
+/// Converts the `prestateTracer` traces of a transaction into the traces of
+/// the accounts it touches, along with the codes it reads or deploys keyed by
+/// their hash.
+///
+/// Accounts missing from the post state are flagged as self-destructed
+/// according to the rules of `hardfork`: from Cancun on, only those created by
+/// the transaction can be.
+pub fn txn_traces(
+    prestate: &HashMap<Address, AccountState>,
+    diff: &DiffMode,
+    access_list: &HashMap<Address, HashSet<B256>>,
+    hardfork: Hardfork,
+) -> (HashMap<B256, Vec<u8>>, HashMap<Address, TxnTrace>) {
+    let addresses = prestate
+        .keys()
+        .chain(diff.post.keys())
+        .chain(diff.pre.keys())
+        .chain(access_list.keys())
+        .collect::<HashSet<_>>();
+
+    let mut code_db = HashMap::new();
+    let traces = addresses
+        .into_iter()
+        .map(|address| {
+            let read = prestate.get(address);
+            let pre = diff.pre.get(address);
+            let post = diff.post.get(address);
+
+            let (storage_read, storage_written) =
+                storage_accesses(access_list.get(address), read, pre, post);
+            let code_usage = code_usage(read, post, &mut code_db);
+            let nonce = post
+                .and_then(|it| it.nonce.map(U256::from))
+                .or_else(|| match code_usage {
+                    // Created contracts start with a nonce of 1.
+                    Some(ContractCodeUsage::Write(_)) => Some(U256::from(1)),
+                    _ => None,
+                });
+
+            let trace = TxnTrace {
+                balance: post.and_then(|it| it.balance),
+                nonce,
+                storage_read,
+                storage_written,
+                code_usage,
+                self_destructed: self_destructed(pre, post, hardfork),
+            };
+            (*address, trace)
+        })
+        .collect();
+
+    (code_db, traces)
+}
+
+/// Builds the state and storage pre-images out of the `eth_getProof`
+/// responses of the accounts touched by a block.
+///
+/// When the block deletes an account or a slot, its sibling in the trie may
+/// be collapsed into its parent node, which the proofs at the parent block do
+/// not cover. The short node variants of the proofs at the block itself,
+/// `next_proofs`, provide these nodes.
+pub fn state_pre_images(
+    parent_state_root: B256,
+    proofs: &[AccountProof],
+    next_proofs: &[AccountProof],
+) -> BlockTraceTriePreImages {
+    let nodes =
+        |proof: &[Bytes]| -> Vec<Vec<u8>> { proof.iter().map(|node| node.0.clone()).collect() };
+
+    let mut state = PartialTrieBuilder::<HashedPartialTrie>::new(
+        parent_state_root.into_ethereum_types(),
+        HashMap::new(),
+    );
+    let mut storage = HashMap::<B256, PartialTrieBuilder<HashedPartialTrie>>::new();
+    for proof in proofs {
+        state.insert_proof(nodes(&proof.account_proof));
+        let storage_trie = storage.entry(keccak256(proof.address)).or_insert_with(|| {
+            PartialTrieBuilder::new(proof.storage_hash.into_ethereum_types(), HashMap::new())
+        });
+        for storage_proof in &proof.storage_proof {
+            storage_trie.insert_proof(nodes(&storage_proof.proof));
+        }
+    }
+    for proof in next_proofs {
+        state.insert_short_node_variants_from_proof(nodes(&proof.account_proof));
+        if let Some(storage_trie) = storage.get_mut(&keccak256(proof.address)) {
+            for storage_proof in &proof.storage_proof {
+                storage_trie.insert_short_node_variants_from_proof(nodes(&storage_proof.proof));
+            }
+        }
+    }
+
+    BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+        state: SeparateTriePreImage::Direct(state.build()),
+        storage: SeparateStorageTriesPreImage::MultipleTries(
+            storage
+                .into_iter()
+                .map(|(hashed_address, trie)| {
+                    (hashed_address, SeparateTriePreImage::Direct(trie.build()))
+                })
+                .collect(),
+        ),
+    })
+}
+
+/// Returns the slots read by a transaction, including the ones of its access
+/// list, and the slots it writes along with their new values, deleted slots
+/// being written zero.
+fn storage_accesses(
+    access_list: Option<&HashSet<B256>>,
+    read: Option<&AccountState>,
+    pre: Option<&AccountState>,
+    post: Option<&AccountState>,
+) -> (Option<Vec<B256>>, Option<HashMap<B256, U256>>) {
+    let storage_read = access_list
+        .into_iter()
+        .flatten()
+        .chain(read.into_iter().flat_map(|it| it.storage.keys()))
+        .copied()
+        .collect::<HashSet<_>>();
+
+    let mut storage_written = post
+        .into_iter()
+        .flat_map(|it| &it.storage)
+        .map(|(slot, value)| (*slot, U256::from_be_bytes(value.0)))
+        .collect::<HashMap<_, _>>();
+    for slot in pre.into_iter().flat_map(|it| it.storage.keys()) {
+        storage_written.entry(*slot).or_insert(U256::ZERO);
+    }
+
+    (
+        Some(storage_read.into_iter().collect::<Vec<_>>()).filter(|it| !it.is_empty()),
+        Some(storage_written).filter(|it| !it.is_empty()),
+    )
+}
+
+/// Returns how a transaction uses the code of an account: deploying it if it
+/// changes, or reading it otherwise.
+fn code_usage(
+    read: Option<&AccountState>,
+    post: Option<&AccountState>,
+    code_db: &mut HashMap<B256, Vec<u8>>,
+) -> Option<ContractCodeUsage> {
+    match (
+        post.and_then(|it| it.code.as_ref()),
+        read.and_then(|it| it.code.as_ref()),
+    ) {
+        (Some(Bytes(deployed)), _) => {
+            code_db.insert(keccak256(deployed), deployed.clone());
+            Some(ContractCodeUsage::Write(deployed.clone()))
+        }
+        (_, Some(Bytes(read))) => {
+            let code_hash = keccak256(read);
+            code_db.insert(code_hash, read.clone());
+            Some(ContractCodeUsage::Read(code_hash))
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether an account is deleted by a transaction, wrapped in an
+/// `Option` so that the most common case is skipped when serializing.
+fn self_destructed(
+    pre: Option<&AccountState>,
+    post: Option<&AccountState>,
+    hardfork: Hardfork,
+) -> Option<bool> {
+    match (pre, post) {
+        // EIP-6780: accounts can only be deleted in the transaction creating
+        // them, i.e. when they had neither code nor storage before it.
+        (Some(pre), None) if pre.code.is_none() && pre.storage.is_empty() => Some(true),
+        // Before Cancun, contracts can be deleted at any time, their code
+        // being then reported as removed.
+        (Some(pre), None) if pre.code.is_some() && !hardfork.restricts_selfdestruct() => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txn_traces_from_prestate() {
+        let sender = Address::repeat_byte(1);
+        let contract = Address::repeat_byte(2);
+        let slot = B256::with_last_byte(1);
+        let code = Bytes(vec![0x60, 0x00]);
+
+        let prestate = HashMap::from([
+            (
+                sender,
+                AccountState {
+                    balance: Some(U256::from(100)),
+                    nonce: Some(0),
+                    ..Default::default()
+                },
+            ),
+            (
+                contract,
+                AccountState {
+                    code: Some(code.clone()),
+                    storage: BTreeMap::from([(slot, B256::with_last_byte(7))]),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let diff = DiffMode {
+            pre: HashMap::from([(
+                contract,
+                AccountState {
+                    storage: BTreeMap::from([(slot, B256::with_last_byte(7))]),
+                    ..Default::default()
+                },
+            )]),
+            post: HashMap::from([(
+                sender,
+                AccountState {
+                    balance: Some(U256::from(90)),
+                    nonce: Some(1),
+                    ..Default::default()
+                },
+            )]),
+        };
+
+        let (code_db, traces) = txn_traces(&prestate, &diff, &HashMap::new(), Hardfork::Cancun);
+        assert_eq!(code_db, HashMap::from([(keccak256(&code.0), code.0)]));
+
+        let sender_trace = &traces[&sender];
+        assert_eq!(sender_trace.balance, Some(U256::from(90)));
+        assert_eq!(sender_trace.nonce, Some(U256::from(1)));
+
+        // The slot is absent from the post state, i.e. it was cleared.
+        let contract_trace = &traces[&contract];
+        assert_eq!(contract_trace.storage_read, Some(vec![slot]));
+        assert_eq!(
+            contract_trace.storage_written,
+            Some(HashMap::from([(slot, U256::ZERO)]))
+        );
+        assert!(matches!(
+            contract_trace.code_usage,
+            Some(ContractCodeUsage::Read(_))
+        ));
+        assert_eq!(contract_trace.self_destructed, None);
+    }
+
+    #[test]
+    fn self_destructs_per_hardfork() {
+        let contract = Address::repeat_byte(2);
+        let diff = DiffMode {
+            pre: HashMap::from([(
+                contract,
+                AccountState {
+                    balance: Some(U256::from(10)),
+                    code: Some(Bytes(vec![0x60, 0x00, 0xff])),
+                    ..Default::default()
+                },
+            )]),
+            post: HashMap::new(),
+        };
+
+        let self_destructed = |hardfork| {
+            let (_, traces) = txn_traces(&HashMap::new(), &diff, &HashMap::new(), hardfork);
+            traces[&contract].self_destructed
+        };
+        assert_eq!(self_destructed(Hardfork::Shanghai), Some(true));
+        // EIP-6780: the contract predates the transaction, so only its balance
+        // is sent away.
+        assert_eq!(self_destructed(Hardfork::Cancun), None);
+    }
+}
@@ -73,20 +73,32 @@
 /// 1. Ethereum nodes emit a bunch of binary [`wire::Instruction`]s, which are
 ///    parsed in [`wire`].
 /// 2. They are passed to one of two "frontends", depending on the node
-///    - [`type2`], which contains an [`smt_trie`].
+///    - `type2`, which contains an `smt_trie`, and isn't wired to the backend
+///      yet.
 ///    - [`type1`], which contains an [`mpt_trie`].
-/// 3. The frontend ([`type1::Frontend`] or [`type2::Frontend`]) is passed to
-///    the "backend", which lowers to [`evm_arithmetization::GenerationInputs`].
+/// 3. The frontend ([`type1::Frontend`] or `type2::Frontend`) is passed to the
+///    "backend", which lowers to [`evm_arithmetization::GenerationInputs`].
 ///
 /// Deviations from the specification are signalled with `BUG(spec)` in the
 /// code.
 const _DEVELOPER_DOCS: () = ();
 
+mod alloy_compat;
+#[cfg(feature = "state_expiry")]
+mod archival;
 /// Defines the main functions used to generate the IR.
+#[cfg(feature = "generation")]
 mod decoding;
+mod error;
+pub mod geth;
+pub mod nethermind;
 /// Defines functions that processes a [BlockTrace] so that it is easier to turn
 /// the block transactions into IRs.
 mod processed_block_trace;
+#[cfg(feature = "generation")]
+mod resolve;
+mod sanitize;
+mod state_access;
 mod type1;
 // TODO(0xaatif): https://github.com/0xPolygonZero/zk_evm/issues/275
 //                add backend/prod support for type 2
@@ -94,19 +106,40 @@ mod type1;
 #[allow(dead_code)]
 mod type2;
 mod typed_mpt;
+#[cfg(feature = "generation")]
+mod validate;
 mod wire;
 
 use std::collections::HashMap;
-
-use ethereum_types::{Address, U256};
-use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
-use evm_arithmetization::GenerationInputs;
+#[cfg(feature = "generation")]
+use std::ops::Range;
+
+#[cfg(feature = "generation")]
+use alloy::consensus::{ReceiptEnvelope, TxEnvelope};
+use alloy::primitives::{Address, B256, U256};
+use alloy_compat::IntoEthereumTypes;
+pub use error::{DecodeError, TrieType};
+#[cfg(feature = "generation")]
+use evm_arithmetization::{
+    proof::{BlockHashes, BlockMetadata, TrieRoots},
+    GenerationInputs,
+};
 use keccak_hash::keccak as hash;
 use keccak_hash::H256;
 use mpt_trie::partial_trie::{HashedPartialTrie, OnOrphanedHashNode};
-use processed_block_trace::ProcessedTxnInfo;
+use processed_block_trace::ProcessedBlockTracePreImages;
+#[cfg(feature = "generation")]
+pub use resolve::hydrate_pre_images;
+pub use sanitize::Sanitation;
 use serde::{Deserialize, Serialize};
+pub use state_access::{PreImageState, StateAccess};
 use typed_mpt::{StateTrie, StorageTrie, TrieKey};
+#[cfg(feature = "generation")]
+pub use validate::{validate, Discrepancy};
+use zk_evm_common::JumpDestTables;
+
+#[cfg(feature = "state_expiry")]
+pub use crate::archival::{AccountRevival, AccountUpdate, ArchivalPreImages, ProofNode};
 
 /// Core payload needed to generate proof for a block.
 /// Additional data retrievable from the blockchain node (using standard ETH RPC
@@ -123,11 +156,17 @@ pub struct BlockTrace {
 
     /// The code_db is a map of code hashes to the actual code. This is needed
     /// to execute transactions.
-    pub code_db: Option<HashMap<H256, Vec<u8>>>,
+    pub code_db: Option<HashMap<B256, Vec<u8>>>,
 
     /// Traces and other info per transaction. The index of the transaction
     /// within the block corresponds to the slot in this vec.
     pub txn_info: Vec<TxnInfo>,
+
+    /// Precomputed JUMPDEST analyses, mapping code hashes to the offsets of
+    /// the JUMPDEST instructions jumped to in the block. The codes without an
+    /// entry are analyzed by the prover, by simulating their execution.
+    #[serde(default)]
+    pub jumpdest_tables: JumpDestTables,
 }
 
 /// Minimal hashed out tries needed by all txns in the block.
@@ -138,6 +177,10 @@ pub enum BlockTraceTriePreImages {
     Separate(SeparateTriePreImages),
     /// The trie pre-image with combined state/storage tries.
     Combined(CombinedPreImages),
+    /// The trie pre-image with separate state/storage tries, along with
+    /// expired accounts revived from archival proofs.
+    #[cfg(feature = "state_expiry")]
+    Archival(ArchivalPreImages),
 }
 
 /// State/Storage trie pre-images that are separate.
@@ -173,7 +216,7 @@ pub struct CombinedPreImages {
 pub enum SeparateStorageTriesPreImage {
     /// Each storage trie is sent over in a hashmap with the hashed account
     /// address as a key.
-    MultipleTries(HashMap<H256, SeparateTriePreImage>),
+    MultipleTries(HashMap<B256, SeparateTriePreImage>),
 }
 
 /// Info specific to txns in the block.
@@ -230,12 +273,12 @@ pub struct TxnTrace {
     /// Note that if storage is written to, then it does not need to appear in
     /// this list (but is also fine if it does).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub storage_read: Option<Vec<H256>>,
+    pub storage_read: Option<Vec<B256>>,
 
     /// Account storage locations that were mutated by the txn along with their
     /// new value.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub storage_written: Option<HashMap<H256, U256>>,
+    pub storage_written: Option<HashMap<B256, U256>>,
 
     /// Contract code that this account has accessed or created
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -251,7 +294,7 @@ pub struct TxnTrace {
 #[serde(rename_all = "snake_case")]
 pub enum ContractCodeUsage {
     /// Contract was read.
-    Read(H256),
+    Read(B256),
 
     /// Contract was created (and these are the bytes). Note that this new
     /// contract code will not appear in the [`BlockTrace`] map.
@@ -259,16 +302,18 @@ pub enum ContractCodeUsage {
 }
 
 /// Other data that is needed for proof gen.
+#[cfg(feature = "generation")]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OtherBlockData {
     /// Data that is specific to the block.
     pub b_data: BlockLevelData,
     /// State trie root hash at the checkpoint.
-    pub checkpoint_state_trie_root: H256,
+    pub checkpoint_state_trie_root: B256,
 }
 
 /// Data that is specific to a block and is constant for all txns in a given
 /// block.
+#[cfg(feature = "generation")]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BlockLevelData {
     /// All block data excluding block hashes and withdrawals.
@@ -277,129 +322,96 @@ pub struct BlockLevelData {
     pub b_hashes: BlockHashes,
     /// Block withdrawal addresses and values.
     pub withdrawals: Vec<(Address, U256)>,
+    /// The transactions root of the block header. If provided, the
+    /// transaction trie built from the raw transactions of the trace is
+    /// checked against it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions_root: Option<B256>,
+    /// The global exit roots `(timestamp, root)` written at the start of the
+    /// block on cdk-erigon chains.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_exit_roots: Vec<(U256, B256)>,
+    /// The address receiving the base fees of the block on cdk-erigon chains,
+    /// if they are not burnt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_addr: Option<Address>,
+    /// The ommers `(beneficiary, number)` included by pre-Merge blocks, whose
+    /// miners are rewarded along with the miner of the block.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ommers: Vec<(Address, U256)>,
+}
+
+impl BlockLevelData {
+    /// The rewards credited at the end of the block to its miner and to the
+    /// miners of its ommers.
+    fn block_rewards(&self) -> Vec<(ethereum_types::Address, ethereum_types::U256)> {
+        let ommers: Vec<(ethereum_types::Address, ethereum_types::U256)> =
+            self.ommers.clone().into_ethereum_types();
+        self.b_meta.block_hardfork.block_rewards(
+            self.b_meta.block_beneficiary,
+            self.b_meta.block_number,
+            &ommers,
+        )
+    }
+}
+
+/// Options of [`entrypoint_with_options`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodingOptions {
+    /// How to handle non-canonical structures in the trie pre-images.
+    pub sanitation: Sanitation,
 }
 
 /// TODO(0xaatif): <https://github.com/0xPolygonZero/zk_evm/issues/275>
 ///                document this once we have the API finalized
+#[cfg(feature = "generation")]
 pub fn entrypoint(
     trace: BlockTrace,
     other: OtherBlockData,
     batch_size: usize,
-) -> anyhow::Result<Vec<GenerationInputs>> {
-    use anyhow::Context as _;
-    use mpt_trie::partial_trie::PartialTrie as _;
+) -> Result<Vec<GenerationInputs>, DecodeError> {
+    entrypoint_with_options(trace, other, batch_size, DecodingOptions::default())
+}
 
-    use crate::processed_block_trace::{
-        Hash2Code, ProcessedBlockTrace, ProcessedBlockTracePreImages,
-    };
-    use crate::PartialTriePreImages;
-    use crate::{
-        BlockTraceTriePreImages, CombinedPreImages, SeparateStorageTriesPreImage,
-        SeparateTriePreImage, SeparateTriePreImages,
-    };
+/// Like [`entrypoint`], with non-default [`DecodingOptions`].
+#[cfg(feature = "generation")]
+pub fn entrypoint_with_options(
+    trace: BlockTrace,
+    other: OtherBlockData,
+    batch_size: usize,
+    options: DecodingOptions,
+) -> Result<Vec<GenerationInputs>, DecodeError> {
+    use crate::processed_block_trace::{Hash2Code, ProcessedBlockTrace, ProcessedTxnInfo};
+
+    #[cfg(not(feature = "cdk_erigon"))]
+    if !other.b_data.global_exit_roots.is_empty() || other.b_data.burn_addr.is_some() {
+        return Err(anyhow::anyhow!(
+            "global exit roots and burn addresses require the `cdk_erigon` feature"
+        )
+        .into());
+    }
 
     let BlockTrace {
         trie_pre_images,
         code_db,
         txn_info,
+        jumpdest_tables,
     } = trace;
 
-    let pre_images = match trie_pre_images {
-        BlockTraceTriePreImages::Separate(SeparateTriePreImages {
-            state: SeparateTriePreImage::Direct(state),
-            storage: SeparateStorageTriesPreImage::MultipleTries(storage),
-        }) => ProcessedBlockTracePreImages {
-            tries: PartialTriePreImages {
-                state: state.items().try_fold(
-                    StateTrie::new(OnOrphanedHashNode::Reject),
-                    |mut acc, (nibbles, hash_or_val)| {
-                        let path = TrieKey::from_nibbles(nibbles);
-                        match hash_or_val {
-                            mpt_trie::trie_ops::ValOrHash::Val(bytes) => {
-                                acc.insert_by_key(
-                                    path,
-                                    rlp::decode(&bytes)
-                                        .context("invalid AccountRlp in direct state trie")?,
-                                )?;
-                            }
-                            mpt_trie::trie_ops::ValOrHash::Hash(h) => {
-                                acc.insert_hash_by_key(path, h)?;
-                            }
-                        };
-                        anyhow::Ok(acc)
-                    },
-                )?,
-                storage: storage
-                    .into_iter()
-                    .map(|(k, SeparateTriePreImage::Direct(v))| {
-                        v.items()
-                            .try_fold(
-                                StorageTrie::new(OnOrphanedHashNode::Reject),
-                                |mut acc, (nibbles, hash_or_val)| {
-                                    let path = TrieKey::from_nibbles(nibbles);
-                                    match hash_or_val {
-                                        mpt_trie::trie_ops::ValOrHash::Val(value) => {
-                                            acc.insert(path, value)?;
-                                        }
-                                        mpt_trie::trie_ops::ValOrHash::Hash(h) => {
-                                            acc.insert_hash(path, h)?;
-                                        }
-                                    };
-                                    anyhow::Ok(acc)
-                                },
-                            )
-                            .map(|v| (k, v))
-                    })
-                    .collect::<Result<_, _>>()?,
-            },
-            extra_code_hash_mappings: None,
-        },
-        BlockTraceTriePreImages::Combined(CombinedPreImages { compact }) => {
-            let instructions =
-                wire::parse(&compact).context("couldn't parse instructions from binary format")?;
-            let type1::Frontend {
-                state,
-                code,
-                storage,
-            } = type1::frontend(instructions)?;
-            ProcessedBlockTracePreImages {
-                tries: PartialTriePreImages {
-                    state,
-                    storage: storage
-                        .into_iter()
-                        .map(|(path, trie)| (path.into_hash_left_padded(), trie))
-                        .collect(),
-                },
-                extra_code_hash_mappings: match code.is_empty() {
-                    true => None,
-                    false => Some(
-                        code.into_iter()
-                            .map(|it| (crate::hash(&it), it.into_vec()))
-                            .collect(),
-                    ),
-                },
-            }
-        }
-    };
+    // Note we discard any user-provided hashes.
+    let state =
+        PreImageState::from_pre_images(process_pre_images(trie_pre_images, options)?, code_db);
 
-    let all_accounts_in_pre_images = pre_images
+    let all_accounts_in_pre_images = state
         .tries
         .state
         .iter()
         .map(|(addr, data)| (addr.into_hash_left_padded(), data))
         .collect::<Vec<_>>();
 
-    // Note we discard any user-provided hashes.
-    let mut hash2code = code_db
-        .unwrap_or_default()
-        .into_values()
-        .chain(
-            pre_images
-                .extra_code_hash_mappings
-                .unwrap_or_default()
-                .into_values(),
-        )
-        .collect::<Hash2Code>();
+    let mut deployed = Hash2Code::new();
+
+    let block_rewards = other.b_data.block_rewards();
 
     let last_tx_idx = txn_info.len().saturating_sub(1) / batch_size;
 
@@ -408,41 +420,275 @@ pub fn entrypoint(
         .enumerate()
         .map(|(i, t)| {
             let extra_state_accesses = if last_tx_idx == i {
-                // If this is the last transaction, we mark the withdrawal addresses
-                // as accessed in the state trie.
+                // If this is the last transaction, we mark the withdrawal and
+                // rewarded addresses as accessed in the state trie.
                 other
                     .b_data
                     .withdrawals
                     .iter()
-                    .map(|(addr, _)| crate::hash(addr.as_bytes()))
+                    .map(|(addr, _)| crate::hash(addr))
+                    .chain(block_rewards.iter().map(|(addr, _)| crate::hash(addr)))
                     .collect::<Vec<_>>()
             } else {
                 Vec::new()
             };
 
             TxnInfo::into_processed_txn_info(
+                i * batch_size,
                 t,
-                &pre_images.tries,
+                &state,
                 &all_accounts_in_pre_images,
                 &extra_state_accesses,
-                &mut hash2code,
+                &mut deployed,
+                other.b_data.b_meta.block_hardfork,
             )
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            e.at_block(
+                other.b_data.b_meta.block_number,
+                other.b_data.b_meta.block_chain_id,
+            )
+        })?;
 
     while txn_info.len() < 2 {
         txn_info.push(ProcessedTxnInfo::default());
     }
 
-    decoding::into_txn_proof_gen_ir(
+    let PreImageState { tries, mut codes } = state;
+    codes.extend(deployed);
+    let mut batches = decoding::into_txn_proof_gen_ir(
         ProcessedBlockTrace {
-            tries: pre_images.tries,
+            tries,
             txn_info,
-            withdrawals: other.b_data.withdrawals.clone(),
+            withdrawals: other.b_data.withdrawals.clone().into_ethereum_types(),
+            block_rewards,
+            codes,
         },
         other,
         batch_size,
-    )
+    )?;
+
+    // Each batch only carries the analyses of the codes it may run.
+    if !jumpdest_tables.is_empty() {
+        for batch in &mut batches {
+            batch.jumpdest_tables = jumpdest_tables
+                .iter()
+                .filter(|(code_hash, _)| batch.contract_code.contains_key(*code_hash))
+                .map(|(code_hash, jumpdests)| (*code_hash, jumpdests.clone()))
+                .collect();
+        }
+    }
+
+    Ok(batches)
+}
+
+/// The roots of the tries after a batch output by [`entrypoint`].
+#[cfg(feature = "generation")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchRoots {
+    /// The indices in the block of the transactions of the batch, empty for
+    /// dummy batches.
+    pub txn_range: Range<usize>,
+    /// The roots of the state, transaction and receipt tries after the batch.
+    ///
+    /// The state root after the last batch accounts for the withdrawals and
+    /// the rewards of the block.
+    pub trie_roots_after: TrieRoots,
+}
+
+/// Returns the roots of the tries after each of the `batches` output by
+/// [`entrypoint`].
+///
+/// They can be cross-checked against the intermediate roots of a node, e.g.
+/// with [`first_divergent_batch`] to find the batch at which a corrupted
+/// witness diverges from the chain.
+#[cfg(feature = "generation")]
+pub fn batch_roots(batches: &[GenerationInputs]) -> Vec<BatchRoots> {
+    batches
+        .iter()
+        .map(|batch| {
+            let start = batch.txn_number_before.as_usize();
+            BatchRoots {
+                txn_range: start..start + batch.signed_txns.len(),
+                trie_roots_after: batch.trie_roots_after.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the roots of the transaction and receipt tries of a block with the
+/// given transactions and receipts, in order.
+///
+/// Front-ends reading the transactions of a block from a node can check them
+/// against its header before building the [`BlockTrace`].
+#[cfg(feature = "generation")]
+pub fn txn_and_receipt_roots(
+    txns: &[TxEnvelope],
+    receipts: &[ReceiptEnvelope],
+) -> Result<(B256, B256), DecodeError> {
+    let mut txn_trie = typed_mpt::TransactionTrie::default();
+    for (txn_ix, txn) in txns.iter().enumerate() {
+        txn_trie.insert_txn(txn_ix, txn)?;
+    }
+    let mut receipt_trie = typed_mpt::ReceiptTrie::default();
+    for (txn_ix, receipt) in receipts.iter().enumerate() {
+        receipt_trie.insert_receipt(txn_ix, receipt)?;
+    }
+    Ok((
+        B256::from(txn_trie.root().0),
+        B256::from(receipt_trie.root().0),
+    ))
+}
+
+/// Returns the index of the first batch whose roots do not match the chain,
+/// if any.
+///
+/// Once the roots of a batch diverge, the ones of all later batches do, so
+/// the batches are bisected: `matches` is only called a logarithmic number
+/// of times, which matters when fetching the roots to compare against is
+/// costly.
+#[cfg(feature = "generation")]
+pub fn first_divergent_batch(
+    roots: &[BatchRoots],
+    matches: impl FnMut(&BatchRoots) -> bool,
+) -> Option<usize> {
+    let ix = roots.partition_point(matches);
+    (ix < roots.len()).then_some(ix)
+}
+
+/// Processes the [`BlockTraceTriePreImages`] of any format into the tries of
+/// the decoder.
+fn process_pre_images(
+    trie_pre_images: BlockTraceTriePreImages,
+    options: DecodingOptions,
+) -> anyhow::Result<ProcessedBlockTracePreImages> {
+    Ok(match trie_pre_images {
+        BlockTraceTriePreImages::Separate(separate) => separate_pre_images(separate, options)?,
+        #[cfg(feature = "state_expiry")]
+        BlockTraceTriePreImages::Archival(ArchivalPreImages { live, revivals }) => {
+            let mut pre_images = separate_pre_images(live, options)?;
+            archival::revive(&mut pre_images.tries.state, &revivals)?;
+            pre_images
+        }
+        BlockTraceTriePreImages::Combined(CombinedPreImages { compact }) => {
+            combined_pre_images(&compact, options)?
+        }
+    })
+}
+
+/// Processes the binary witness of [`CombinedPreImages`] into the tries of the
+/// decoder.
+///
+/// Only type 1 (MPT) witnesses are supported.
+fn combined_pre_images(
+    compact: &[u8],
+    options: DecodingOptions,
+) -> anyhow::Result<ProcessedBlockTracePreImages> {
+    use anyhow::Context as _;
+
+    let instructions =
+        wire::parse(compact).context("couldn't parse instructions from binary format")?;
+    let type1::Frontend {
+        state,
+        code,
+        storage,
+        non_canonical,
+    } = type1::frontend(instructions)?;
+    options.sanitation.apply(&non_canonical)?;
+    Ok(ProcessedBlockTracePreImages {
+        tries: PartialTriePreImages {
+            state,
+            storage: storage
+                .into_iter()
+                .map(|(path, trie)| (path.into_hash_left_padded(), trie))
+                .collect(),
+        },
+        extra_code_hash_mappings: match code.is_empty() {
+            true => None,
+            false => Some(
+                code.into_iter()
+                    .map(|it| (crate::hash(&it), it.into_vec()))
+                    .collect(),
+            ),
+        },
+    })
+}
+
+/// Processes [`SeparateTriePreImages`] into the tries of the decoder.
+fn separate_pre_images(
+    SeparateTriePreImages {
+        state: SeparateTriePreImage::Direct(state),
+        storage: SeparateStorageTriesPreImage::MultipleTries(storage),
+    }: SeparateTriePreImages,
+    options: DecodingOptions,
+) -> anyhow::Result<ProcessedBlockTracePreImages> {
+    use anyhow::Context as _;
+    use mpt_trie::partial_trie::PartialTrie as _;
+
+    use crate::sanitize::{find_non_canonical, TrieId};
+
+    options.sanitation.apply(
+        &storage
+            .iter()
+            .flat_map(|(k, SeparateTriePreImage::Direct(v))| {
+                find_non_canonical(TrieId::Storage(k.into_ethereum_types()), v)
+            })
+            .chain(find_non_canonical(TrieId::State, &state))
+            .collect::<Vec<_>>(),
+    )?;
+    Ok(ProcessedBlockTracePreImages {
+        tries: PartialTriePreImages {
+            state: {
+                let mut trie = StateTrie::new(OnOrphanedHashNode::Reject);
+                let mut accounts = Vec::new();
+                for (nibbles, hash_or_val) in state.items() {
+                    let path = TrieKey::from_nibbles(nibbles);
+                    match hash_or_val {
+                        // Empty values stand for absent entries.
+                        mpt_trie::trie_ops::ValOrHash::Val(bytes) if bytes.is_empty() => {}
+                        mpt_trie::trie_ops::ValOrHash::Val(bytes) => accounts.push((
+                            path,
+                            rlp::decode(&bytes)
+                                .context("invalid AccountRlp in direct state trie")?,
+                        )),
+                        mpt_trie::trie_ops::ValOrHash::Hash(h) => {
+                            trie.insert_hash_by_key(path, h)?;
+                        }
+                    };
+                }
+                // The leaves are inserted in one go, without looking up previous values.
+                trie.extend(accounts)?;
+                trie
+            },
+            storage: storage
+                .into_iter()
+                .map(|(k, SeparateTriePreImage::Direct(v))| {
+                    let mut trie = StorageTrie::new(OnOrphanedHashNode::Reject);
+                    let mut values = Vec::new();
+                    for (nibbles, hash_or_val) in v.items() {
+                        let path = TrieKey::from_nibbles(nibbles);
+                        match hash_or_val {
+                            mpt_trie::trie_ops::ValOrHash::Val(value) if value.is_empty() => {}
+                            mpt_trie::trie_ops::ValOrHash::Val(value) => values.push((path, value)),
+                            mpt_trie::trie_ops::ValOrHash::Hash(h) => {
+                                trie.insert_hash(path, h)?;
+                            }
+                        };
+                    }
+                    let len = values.len();
+                    trie.extend(values)?;
+                    // The decoder reads the values of slots as integers.
+                    anyhow::ensure!(
+                        trie.iter_slots().count() == len,
+                        "storage pre-image of {k:x} holds values which aren't integers"
+                    );
+                    anyhow::Ok((k.into_ethereum_types(), trie))
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        extra_code_hash_mappings: None,
+    })
 }
 
 #[derive(Debug, Default)]
@@ -0,0 +1,186 @@
+//! Front-end for the execution witnesses of
+//! [Nethermind](https://www.nethermind.io/) nodes, as returned by
+//! `debug_executionWitness`.
+//!
+//! Unlike the witnesses of Erigon forks, which encode the tries themselves, an
+//! execution witness is a flat pool of the RLP-encoded trie nodes touched by
+//! the block, state and storage nodes alike, along with the codes it executes
+//! and the headers of its ancestors. The tries are recovered by walking the
+//! pool from the state root of the parent block, and then from the storage
+//! root of each account found on the way. Nodes missing from the pool are
+//! hashed out.
+//!
+//! The witness carries no per-transaction data, which must be obtained
+//! separately, e.g. with the `prestateTracer`, see [`crate::geth`].
+
+use std::collections::{HashMap, HashSet};
+
+use alloy::primitives::{keccak256, B256};
+use anyhow::Context as _;
+use ethereum_types::H256;
+use mpt_trie::builder::PartialTrieBuilder;
+use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie as _};
+use mpt_trie::trie_ops::ValOrHash;
+use serde::{Deserialize, Serialize};
+use zk_evm_common::{AccountRlp, EMPTY_TRIE_HASH};
+
+use crate::geth::Bytes;
+use crate::typed_mpt::TrieKey;
+use crate::{
+    BlockTrace, BlockTraceTriePreImages, SeparateStorageTriesPreImage, SeparateTriePreImage,
+    SeparateTriePreImages, TxnInfo,
+};
+
+/// A `debug_executionWitness` response.
+///
+/// The preimages of the hashed keys of the tries, also part of the response,
+/// are not needed and thus omitted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Witness {
+    /// The RLP-encoded nodes of the state and storage tries.
+    pub state: Vec<Bytes>,
+    /// The codes executed by the block.
+    #[serde(default)]
+    pub codes: Vec<Bytes>,
+    /// The RLP-encoded headers of the ancestors of the block, the parent
+    /// being last.
+    #[serde(default)]
+    pub headers: Vec<Bytes>,
+}
+
+impl Witness {
+    /// Returns the state root of the parent block, from its header.
+    pub fn parent_state_root(&self) -> anyhow::Result<H256> {
+        let header = self
+            .headers
+            .last()
+            .context("execution witness without the parent header")?;
+        // The state root is the fourth field of a header.
+        rlp::Rlp::new(&header.0)
+            .val_at(3)
+            .context("invalid parent header in execution witness")
+    }
+}
+
+/// Converts an execution witness, along with the traces of the transactions
+/// of the block, into a [`BlockTrace`].
+pub fn block_trace(witness: &Witness, txn_info: Vec<TxnInfo>) -> anyhow::Result<BlockTrace> {
+    Ok(BlockTrace {
+        trie_pre_images: state_pre_images(witness.parent_state_root()?, witness)?,
+        code_db: Some(
+            witness
+                .codes
+                .iter()
+                .map(|Bytes(code)| (keccak256(code), code.clone()))
+                .collect::<HashMap<_, _>>(),
+        )
+        .filter(|it| !it.is_empty()),
+        txn_info,
+        jumpdest_tables: Default::default(),
+    })
+}
+
+/// Recovers the state and storage pre-images from the node pool of an
+/// execution witness.
+///
+/// A storage trie is only recovered for the accounts whose storage root is in
+/// the pool, the storage of the other ones being left untouched by the block.
+pub fn state_pre_images(
+    parent_state_root: H256,
+    witness: &Witness,
+) -> anyhow::Result<BlockTraceTriePreImages> {
+    let pool = witness
+        .state
+        .iter()
+        .map(|Bytes(node)| (crate::hash(node), node.clone()))
+        .collect::<HashMap<_, _>>();
+    let in_pool = pool.keys().copied().collect::<HashSet<_>>();
+    let nodes = PartialTrieBuilder::<HashedPartialTrie>::new(parent_state_root, pool);
+
+    let state = nodes.build_subtrie(parent_state_root);
+    let mut storage = HashMap::new();
+    for (key, value) in state.items() {
+        let ValOrHash::Val(bytes) = value else {
+            continue;
+        };
+        let hashed_address = TrieKey::from_nibbles(key).into_hash_left_padded();
+        let account = rlp::decode::<AccountRlp>(&bytes)
+            .with_context(|| format!("invalid account {hashed_address:x} in execution witness"))?;
+        if account.storage_root != EMPTY_TRIE_HASH && in_pool.contains(&account.storage_root) {
+            storage.insert(
+                B256::from(hashed_address.0),
+                SeparateTriePreImage::Direct(nodes.build_subtrie(account.storage_root)),
+            );
+        }
+    }
+
+    Ok(BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+        state: SeparateTriePreImage::Direct(state),
+        storage: SeparateStorageTriesPreImage::MultipleTries(storage),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{Address, U256};
+    use mpt_trie::nibbles::Nibbles;
+
+    use super::*;
+
+    fn leaf(key: H256, value: Vec<u8>) -> Vec<u8> {
+        rlp::encode_list::<Vec<u8>, _>(&[
+            Nibbles::from_h256_be(key)
+                .to_hex_prefix_encoding(true)
+                .to_vec(),
+            value,
+        ])
+        .to_vec()
+    }
+
+    #[test]
+    fn pre_images_from_node_pool() {
+        let address = Address::repeat_byte(1);
+        let code = vec![0x60, 0x00];
+
+        let storage_node = leaf(
+            crate::hash(H256::zero()),
+            rlp::encode(&U256::from(7)).to_vec(),
+        );
+        let account = AccountRlp {
+            nonce: 1.into(),
+            balance: 2.into(),
+            storage_root: crate::hash(&storage_node),
+            code_hash: crate::hash(&code),
+        };
+        let state_node = leaf(crate::hash(address), rlp::encode(&account).to_vec());
+        let state_root = crate::hash(&state_node);
+
+        let mut header = rlp::RlpStream::new_list(4);
+        header
+            .append(&H256::zero())
+            .append(&H256::zero())
+            .append(&Address::zero())
+            .append(&state_root);
+        let witness = Witness {
+            state: vec![Bytes(state_node), Bytes(storage_node)],
+            codes: vec![Bytes(code.clone())],
+            headers: vec![Bytes(header.out().to_vec())],
+        };
+
+        let trace = block_trace(&witness, vec![]).unwrap();
+        assert_eq!(
+            trace.code_db,
+            Some(HashMap::from([(keccak256(&code), code)]))
+        );
+        let BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+            state: SeparateTriePreImage::Direct(state),
+            storage: SeparateStorageTriesPreImage::MultipleTries(storage),
+        }) = trace.trie_pre_images
+        else {
+            panic!("expected separate pre-images");
+        };
+        assert_eq!(state.hash(), state_root);
+        let SeparateTriePreImage::Direct(storage_trie) = &storage[&keccak256(address)];
+        assert_eq!(storage_trie.hash(), account.storage_root);
+    }
+}
@@ -1,24 +1,54 @@
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::HashMap;
+#[cfg(feature = "generation")]
+use std::collections::{BTreeSet, HashSet};
 
+#[cfg(feature = "generation")]
 use anyhow::{bail, Context as _};
-use ethereum_types::{Address, H256, U256};
-use evm_arithmetization::generation::mpt::{AccountRlp, LegacyReceiptRlp};
+use ethereum_types::H256;
+#[cfg(feature = "generation")]
+use ethereum_types::{Address, U256};
+#[cfg(feature = "generation")]
 use itertools::Itertools;
-use zk_evm_common::EMPTY_TRIE_HASH;
-
+use zk_evm_common::EMPTY_CODE_HASH;
+#[cfg(any(feature = "generation", test))]
+use zk_evm_common::{AccountRlp, Hardfork};
+#[cfg(feature = "generation")]
+use zk_evm_common::{LegacyReceiptRlp, EMPTY_TRIE_HASH};
+
+use crate::error::DecodeError;
+use crate::hash;
+#[cfg(feature = "generation")]
 use crate::typed_mpt::TrieKey;
+#[cfg(feature = "generation")]
+use crate::IntoEthereumTypes;
 use crate::PartialTriePreImages;
-use crate::{hash, TxnTrace};
-use crate::{ContractCodeUsage, TxnInfo};
+#[cfg(feature = "generation")]
+use crate::StateAccess;
+#[cfg(feature = "generation")]
+use crate::{ContractCodeUsage, TxnInfo, TxnTrace};
 
+#[cfg(feature = "generation")]
 const FIRST_PRECOMPILE_ADDRESS: U256 = U256([1, 0, 0, 0]);
+#[cfg(feature = "generation")]
 const LAST_PRECOMPILE_ADDRESS: U256 = U256([10, 0, 0, 0]);
 
+/// Whether `addr` is one of the precompiles, from `ECREC` (0x01) to the KZG
+/// point evaluation precompile (0x0A).
+#[cfg(feature = "generation")]
+fn is_precompile(addr: &Address) -> bool {
+    (FIRST_PRECOMPILE_ADDRESS..=LAST_PRECOMPILE_ADDRESS).contains(&U256::from_big_endian(&addr.0))
+}
+
+#[cfg(feature = "generation")]
 #[derive(Debug)]
 pub(crate) struct ProcessedBlockTrace {
     pub tries: PartialTriePreImages,
     pub txn_info: Vec<ProcessedTxnInfo>,
     pub withdrawals: Vec<(Address, U256)>,
+    /// The block and ommer rewards of pre-Merge blocks, in Wei.
+    pub block_rewards: Vec<(Address, U256)>,
+    /// The codes of the whole block, which the batches refer to by hash.
+    pub codes: Hash2Code,
 }
 
 #[derive(Debug)]
@@ -27,10 +57,13 @@ pub(crate) struct ProcessedBlockTracePreImages {
     pub extra_code_hash_mappings: Option<HashMap<H256, Vec<u8>>>,
 }
 
+#[cfg(feature = "generation")]
 #[derive(Debug, Default)]
 pub(crate) struct ProcessedTxnInfo {
     pub nodes_used_by_txn: NodesUsedByTxn,
-    pub contract_code_accessed: HashSet<Vec<u8>>,
+    /// The hashes of the codes accessed by the batch, stored once for the
+    /// whole block in a [`Hash2Code`].
+    pub contract_code_accessed: HashSet<H256>,
     pub meta: Vec<TxnMetaState>,
 }
 
@@ -38,6 +71,10 @@ pub(crate) struct ProcessedTxnInfo {
 /// trace.
 /// If there are any txns that create contracts, then they will also
 /// get added here as we process the deltas.
+///
+/// Each code is stored once for the whole block, however many transactions
+/// access it.
+#[derive(Debug)]
 pub(crate) struct Hash2Code {
     /// Key must always be [`hash`] of value.
     inner: HashMap<H256, Vec<u8>>,
@@ -46,46 +83,96 @@ pub(crate) struct Hash2Code {
 impl Hash2Code {
     pub fn new() -> Self {
         Self {
-            inner: HashMap::new(),
+            inner: HashMap::from([(EMPTY_CODE_HASH, vec![])]),
         }
     }
-    fn get(&mut self, hash: H256) -> anyhow::Result<Vec<u8>> {
+    pub fn get(&self, hash: H256) -> Result<&[u8], DecodeError> {
         match self.inner.get(&hash) {
-            Some(code) => Ok(code.clone()),
-            None => bail!("no code for hash {}", hash),
+            Some(code) => Ok(code),
+            None => Err(DecodeError::MissingCode { code_hash: hash }),
         }
     }
-    fn insert(&mut self, code: Vec<u8>) {
-        self.inner.insert(hash(&code), code);
+    /// Returns the hash of the inserted code.
+    fn insert(&mut self, code: Vec<u8>) -> H256 {
+        let code_hash = hash(&code);
+        self.inner.entry(code_hash).or_insert(code);
+        code_hash
+    }
+}
+
+impl Extend<Vec<u8>> for Hash2Code {
+    fn extend<II: IntoIterator<Item = Vec<u8>>>(&mut self, iter: II) {
+        for code in iter {
+            self.insert(code);
+        }
+    }
+}
+
+impl IntoIterator for Hash2Code {
+    type Item = Vec<u8>;
+    type IntoIter = std::collections::hash_map::IntoValues<H256, Vec<u8>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_values()
     }
 }
 
 impl FromIterator<Vec<u8>> for Hash2Code {
     fn from_iter<II: IntoIterator<Item = Vec<u8>>>(iter: II) -> Self {
         let mut this = Self::new();
-        for code in iter {
-            this.insert(code)
-        }
+        this.extend(iter);
         this
     }
 }
 
+/// Whether a `SELFDESTRUCT` deletes an account, given its state before the
+/// block and whether an earlier transaction of the batch deployed its code.
+///
+/// From Cancun on, [EIP-6780](https://eips.ethereum.org/EIPS/eip-6780) only
+/// deletes accounts created in the same transaction. The pre-images don't tell
+/// which transaction created an account, so this rules out the deletion of
+/// contracts which existed before the block, or were deployed by an earlier
+/// transaction of the batch. A contract deployed by an earlier batch of the
+/// block isn't caught here, and makes the batch fail to prove instead.
+#[cfg(any(feature = "generation", test))]
+fn can_self_destruct(
+    hardfork: Hardfork,
+    pre_image: Option<&AccountRlp>,
+    deployed_by_earlier_txn: bool,
+) -> bool {
+    !hardfork.restricts_selfdestruct()
+        || (!deployed_by_earlier_txn
+            && pre_image.map_or(true, |account| account.code_hash == EMPTY_CODE_HASH))
+}
+
+#[cfg(feature = "generation")]
 impl TxnInfo {
+    /// Processes a batch of transactions, the first of which is at index
+    /// `first_txn_ix` in the block, which belongs to `hardfork`.
+    ///
+    /// The accounts and codes predating the block are looked up in `state`,
+    /// and the codes deployed by the block so far are collected in `deployed`.
     pub(crate) fn into_processed_txn_info(
+        first_txn_ix: usize,
         tx_infos: &[Self],
-        tries: &PartialTriePreImages,
+        state: &impl StateAccess,
         all_accounts_in_pre_image: &[(H256, AccountRlp)],
         extra_state_accesses: &[H256],
-        hash2code: &mut Hash2Code,
-    ) -> anyhow::Result<ProcessedTxnInfo> {
+        deployed: &mut Hash2Code,
+        hardfork: Hardfork,
+    ) -> Result<ProcessedTxnInfo, DecodeError> {
         let mut nodes_used_by_txn = NodesUsedByTxn::default();
-        let mut contract_code_accessed = HashSet::from([vec![]]); // we always "access" empty code
+        // we always "access" empty code
+        let mut contract_code_accessed = HashSet::from([EMPTY_CODE_HASH]);
         let mut meta = Vec::with_capacity(tx_infos.len());
 
         let all_accounts: BTreeSet<H256> =
             all_accounts_in_pre_image.iter().map(|(h, _)| *h).collect();
+        // The index of the transaction of the batch which last deployed code to
+        // an account.
+        let mut deployers = HashMap::new();
 
-        for txn in tx_infos.iter() {
+        for (i, txn) in tx_infos.iter().enumerate() {
+            let txn_ix = first_txn_ix + i;
             let mut created_accounts = BTreeSet::new();
 
             for (
@@ -100,15 +187,20 @@ impl TxnInfo {
                 },
             ) in txn.traces.iter()
             {
+                let addr: Address = (*addr).into_ethereum_types();
                 let hashed_addr = hash(addr.as_bytes());
 
                 // record storage changes
-                let storage_written = storage_written.clone().unwrap_or_default();
+                let storage_written: HashMap<H256, U256> = storage_written
+                    .clone()
+                    .unwrap_or_default()
+                    .into_ethereum_types();
 
-                let storage_read_keys = storage_read
+                let storage_read: Vec<H256> = storage_read
                     .clone()
-                    .into_iter()
-                    .flat_map(|reads| reads.into_iter());
+                    .unwrap_or_default()
+                    .into_ethereum_types();
+                let storage_read_keys = storage_read.into_iter();
 
                 let storage_written_keys = storage_written.keys();
                 let storage_access_keys = storage_read_keys.chain(storage_written_keys.copied());
@@ -130,11 +222,11 @@ impl TxnInfo {
 
                 // record state changes
                 let state_write = StateWrite {
-                    balance: *balance,
-                    nonce: *nonce,
+                    balance: balance.into_ethereum_types(),
+                    nonce: nonce.into_ethereum_types(),
                     storage_trie_change: !storage_written.is_empty(),
                     code_hash: code_usage.as_ref().map(|it| match it {
-                        ContractCodeUsage::Read(hash) => *hash,
+                        ContractCodeUsage::Read(hash) => hash.into_ethereum_types(),
                         ContractCodeUsage::Write(bytes) => hash(bytes),
                     }),
                 };
@@ -180,44 +272,48 @@ impl TxnInfo {
                 }
 
                 for (k, v) in storage_written.into_iter() {
-                    if let Some(storage) = nodes_used_by_txn.storage_writes.get_mut(&hashed_addr) {
-                        storage.insert(TrieKey::from_hash(k), rlp::encode(&v).to_vec());
-                    } else {
-                        nodes_used_by_txn.storage_writes.insert(
-                            hashed_addr,
-                            HashMap::from_iter([(TrieKey::from_hash(k), rlp::encode(&v).to_vec())]),
-                        );
-                    }
+                    nodes_used_by_txn
+                        .storage_writes
+                        .entry(hashed_addr)
+                        .or_default()
+                        .insert(k.into_uint(), v);
                 }
 
-                let is_precompile = (FIRST_PRECOMPILE_ADDRESS..LAST_PRECOMPILE_ADDRESS)
-                    .contains(&U256::from_big_endian(&addr.0));
-
                 // Trie witnesses will only include accessed precompile accounts as hash
                 // nodes if the transaction calling them reverted. If this is the case, we
                 // shouldn't include them in this transaction's `state_accesses` to allow the
                 // decoder to build a minimal state trie without hitting any hash node.
-                if !is_precompile
-                    || tries
-                        .state
-                        .get_by_key(TrieKey::from_hash(hashed_addr))
-                        .is_some()
-                {
+                if !is_precompile(&addr) || state.account(hashed_addr)?.is_some() {
                     nodes_used_by_txn.state_accesses.insert(hashed_addr);
                 }
 
                 match code_usage {
                     Some(ContractCodeUsage::Read(hash)) => {
-                        contract_code_accessed.insert(hash2code.get(*hash)?);
+                        let hash = hash.into_ethereum_types();
+                        if deployed.get(hash).is_err() && !state.has_code(hash)? {
+                            return Err(DecodeError::MissingCode { code_hash: hash }
+                                .at_account(addr)
+                                .at_txn(txn_ix));
+                        }
+                        contract_code_accessed.insert(hash);
                     }
                     Some(ContractCodeUsage::Write(code)) => {
-                        contract_code_accessed.insert(code.clone());
-                        hash2code.insert(code.to_vec());
+                        contract_code_accessed.insert(deployed.insert(code.to_vec()));
+                        deployers.insert(hashed_addr, txn_ix);
                     }
                     None => {}
                 }
 
                 if self_destructed.unwrap_or_default() {
+                    let pre_image = state.account(hashed_addr)?;
+                    let deployed_by_earlier_txn = deployers
+                        .get(&hashed_addr)
+                        .is_some_and(|&deployer| deployer < txn_ix);
+                    if !can_self_destruct(hardfork, pre_image.as_ref(), deployed_by_earlier_txn) {
+                        return Err(DecodeError::IllegalSelfDestruct
+                            .at_account(addr)
+                            .at_txn(txn_ix));
+                    }
                     nodes_used_by_txn
                         .self_destructed_accounts
                         .insert(hashed_addr);
@@ -247,14 +343,16 @@ impl TxnInfo {
                 .accts_with_unaccessed_storage
                 .extend(accounts_with_storage_but_no_storage_accesses);
 
+            let at_txn = |e| DecodeError::Other(e).at_txn(txn_ix);
             meta.push(TxnMetaState {
                 txn_bytes: match txn.meta.byte_code.is_empty() {
-                    false => Some(txn.meta.byte_code.clone()),
+                    false => Some(check_txn_bytes(txn.meta.byte_code.clone()).map_err(at_txn)?),
                     true => None,
                 },
                 receipt_node_bytes: check_receipt_bytes(
                     txn.meta.new_receipt_trie_node_byte.clone(),
-                )?,
+                )
+                .map_err(at_txn)?,
                 gas_used: txn.meta.gas_used,
                 created_accounts,
             });
@@ -268,6 +366,7 @@ impl TxnInfo {
     }
 }
 
+#[cfg(feature = "generation")]
 fn check_receipt_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
     match rlp::decode::<LegacyReceiptRlp>(&bytes) {
         Ok(_) => Ok(bytes),
@@ -277,7 +376,88 @@ fn check_receipt_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
     }
 }
 
+/// The type byte of OP Stack deposit transactions.
+#[cfg(feature = "generation")]
+const DEPOSIT_TXN_TYPE: u8 = 0x7e;
+
+/// Checks that `bytes` is a signed transaction, as inserted in the transaction
+/// trie: either an RLP list for legacy transactions, or an
+/// [EIP-2718](https://eips.ethereum.org/EIPS/eip-2718) type byte followed by an
+/// RLP list.
+#[cfg(feature = "generation")]
+fn check_txn_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let payload = match bytes.first() {
+        Some(0xc0..) => &bytes[..],
+        #[cfg(not(feature = "op_deposit"))]
+        Some(&DEPOSIT_TXN_TYPE) => bail!(
+            "deposit transaction {} requires the `op_deposit` feature",
+            hex::encode(&bytes)
+        ),
+        Some(0x00..=0x7f) => &bytes[1..],
+        _ => bail!(
+            "transaction bytes {} are neither a legacy nor a typed transaction",
+            hex::encode(&bytes)
+        ),
+    };
+    if let Err(e) = rlp::Rlp::new(payload).item_count() {
+        return Err(anyhow::Error::new(e).context(format!(
+            "invalid RLP in transaction bytes {}",
+            hex::encode(&bytes)
+        )));
+    }
+    if bytes[0] >= 0xc0 {
+        check_legacy_txn(rlp::Rlp::new(payload))
+            .with_context(|| format!("invalid legacy transaction {}", hex::encode(&bytes)))?;
+    }
+    #[cfg(feature = "op_deposit")]
+    if bytes[0] == DEPOSIT_TXN_TYPE {
+        check_deposit_txn(rlp::Rlp::new(payload))
+            .with_context(|| format!("invalid deposit transaction {}", hex::encode(&bytes)))?;
+    }
+    Ok(bytes)
+}
+
+/// Checks the signature of a legacy transaction, whose `v` is either `27` or
+/// `28`, or encodes a chain ID as per
+/// [EIP-155](https://eips.ethereum.org/EIPS/eip-155).
+///
+/// The chain ID of the block must fit in a `u64`, so larger ones are rejected.
+#[cfg(feature = "generation")]
+fn check_legacy_txn(rlp: rlp::Rlp) -> anyhow::Result<()> {
+    anyhow::ensure!(rlp.item_count()? == 9, "expected 9 fields");
+    let v = rlp.val_at::<U256>(6)?;
+    anyhow::ensure!(
+        v == U256::from(27) || v == U256::from(28) || v >= U256::from(35),
+        "v = {v} encodes neither a legacy nor an EIP-155 signature"
+    );
+    anyhow::ensure!(
+        v <= U256::from(u64::MAX) * 2 + 36,
+        "the chain ID encoded in v = {v} exceeds 64 bits"
+    );
+    Ok(())
+}
+
+/// Checks the RLP list of a deposit transaction, which is
+/// `[source_hash, from, to, mint, value, gas_limit, is_system_tx, data]`.
+///
+/// Deposits are not signed: their sender is given by the `from` field.
+#[cfg(all(feature = "generation", feature = "op_deposit"))]
+fn check_deposit_txn(rlp: rlp::Rlp) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        rlp.item_count()? == 8,
+        "expected 8 fields, without a signature"
+    );
+    anyhow::ensure!(rlp.at(1)?.data()?.len() == 20, "invalid sender");
+    // System transactions have been disabled since the Regolith upgrade.
+    anyhow::ensure!(
+        !rlp.val_at::<bool>(6)?,
+        "system transactions are not supported"
+    );
+    Ok(())
+}
+
 /// Note that "*_accesses" includes writes.
+#[cfg(feature = "generation")]
 #[derive(Debug, Default)]
 pub(crate) struct NodesUsedByTxn {
     pub state_accesses: HashSet<H256>,
@@ -285,12 +465,14 @@ pub(crate) struct NodesUsedByTxn {
 
     // Note: All entries in `storage_writes` also appear in `storage_accesses`.
     pub storage_accesses: HashMap<H256, Vec<TrieKey>>,
-    pub storage_writes: HashMap<H256, HashMap<TrieKey, Vec<u8>>>,
+    /// Hashed address -> slot -> value.
+    pub storage_writes: HashMap<H256, HashMap<U256, U256>>,
     /// Hashed address -> storage root.
     pub accts_with_unaccessed_storage: HashMap<H256, H256>,
     pub self_destructed_accounts: HashSet<H256>,
 }
 
+#[cfg(feature = "generation")]
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct StateWrite {
     pub balance: Option<U256>,
@@ -299,6 +481,7 @@ pub(crate) struct StateWrite {
     pub code_hash: Option<H256>,
 }
 
+#[cfg(feature = "generation")]
 #[derive(Debug, Default)]
 pub(crate) struct TxnMetaState {
     /// [`None`] if this is a dummy transaction inserted for padding.
@@ -307,3 +490,96 @@ pub(crate) struct TxnMetaState {
     pub gas_used: u64,
     pub created_accounts: BTreeSet<H256>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_destructs_per_hardfork() {
+        let contract = AccountRlp {
+            code_hash: hash([0xff]),
+            ..Default::default()
+        };
+        assert!(can_self_destruct(
+            Hardfork::Shanghai,
+            Some(&contract),
+            false
+        ));
+        assert!(!can_self_destruct(Hardfork::Cancun, Some(&contract), false));
+        // Accounts created by the block may be deleted by their creator.
+        assert!(can_self_destruct(Hardfork::Cancun, None, false));
+        assert!(can_self_destruct(
+            Hardfork::Cancun,
+            Some(&AccountRlp::default()),
+            false
+        ));
+        // But not by a later transaction.
+        assert!(!can_self_destruct(Hardfork::Cancun, None, true));
+        assert!(can_self_destruct(Hardfork::Shanghai, None, true));
+    }
+
+    #[test]
+    fn stores_codes_once_per_block() {
+        let code = vec![0x60, 0x00];
+        let mut hash2code = Hash2Code::new();
+        // The empty code is always known.
+        assert_eq!(hash2code.get(EMPTY_CODE_HASH).unwrap(), &[] as &[u8]);
+
+        let code_hash = hash2code.insert(code.clone());
+        assert_eq!(code_hash, hash(&code));
+        assert_eq!(hash2code.insert(code.clone()), code_hash);
+        assert_eq!(hash2code.get(code_hash).unwrap(), code.as_slice());
+        assert!(matches!(
+            hash2code.get(hash([0xff])),
+            Err(DecodeError::MissingCode { code_hash }) if code_hash == hash([0xff])
+        ));
+        assert_eq!(hash2code.into_iter().count(), 2);
+    }
+
+    #[cfg(feature = "generation")]
+    #[test]
+    fn kzg_point_evaluation_is_a_precompile() {
+        assert!(!is_precompile(&Address::zero()));
+        assert!(is_precompile(&Address::from_low_u64_be(0x01)));
+        assert!(is_precompile(&Address::from_low_u64_be(0x0a)));
+        assert!(!is_precompile(&Address::from_low_u64_be(0x0b)));
+    }
+
+    /// RLP-encodes the fields of a transaction, its signature having `v`.
+    #[cfg(feature = "generation")]
+    fn txn_fields(v: u64) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(9);
+        stream
+            .append(&1u64)
+            .append(&10u64)
+            .append(&21_000u64)
+            .append(&Address::repeat_byte(0xaa))
+            .append(&100u64)
+            .append_empty_data()
+            .append(&v)
+            .append(&U256::one())
+            .append(&U256::one());
+        stream.out().to_vec()
+    }
+
+    #[cfg(feature = "generation")]
+    #[test]
+    fn checks_txn_bytes() {
+        // Legacy transactions, with and without EIP-155 replay protection.
+        assert!(check_txn_bytes(txn_fields(27)).is_ok());
+        // v = 2 * 1 + 35, for chain ID 1.
+        assert!(check_txn_bytes(txn_fields(37)).is_ok());
+        assert!(check_txn_bytes(txn_fields(29)).is_err());
+        // Typed transactions are prefixed by their type.
+        assert!(check_txn_bytes([&[0x02][..], &txn_fields(1)].concat()).is_ok());
+
+        // Neither a list nor a type byte.
+        assert!(check_txn_bytes(vec![0x80]).is_err());
+        assert!(check_txn_bytes(vec![]).is_err());
+        // A list longer than its bytes.
+        let mut truncated = txn_fields(27);
+        truncated.pop();
+        assert!(check_txn_bytes(truncated).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! On-demand resolution of the trie nodes missing from the pre-images.
+//!
+//! Witnesses hash out the parts of the tries a block doesn't touch. If the
+//! traces of a block reach into a hashed-out part, e.g. because the witness
+//! was fetched incrementally, decoding fails. [`hydrate_pre_images`] instead
+//! fetches the missing nodes by hash beforehand, typically from a node.
+
+use std::future::Future;
+
+use alloy::primitives::B256;
+use ethereum_types::H256;
+use mpt_trie::partial_trie::{Node, OnOrphanedHashNode};
+use zk_evm_common::EMPTY_TRIE_HASH;
+
+use crate::processed_block_trace::ProcessedBlockTracePreImages;
+use crate::typed_mpt::{StorageTrie, TrieKey};
+use crate::{
+    hash, process_pre_images, BlockTrace, BlockTraceTriePreImages, DecodingOptions, OtherBlockData,
+    PartialTriePreImages, SeparateStorageTriesPreImage, SeparateTriePreImage,
+    SeparateTriePreImages,
+};
+
+/// Resolves the hashed-out nodes of the pre-images of `trace` on the paths to
+/// the accounts and slots accessed by the block, including the accounts of its
+/// withdrawals.
+///
+/// `resolve` maps the hash of a node to its RLP encoding, and is only called
+/// for the nodes the pre-images lack. An account whose storage trie is missing
+/// altogether gets one, hydrated from its storage root.
+///
+/// The returned trace has [`BlockTraceTriePreImages::Separate`] pre-images,
+/// and the codes of combined witnesses are moved to its `code_db`.
+///
+/// Only the paths to the accessed keys are resolved: deleting a key whose
+/// sibling is hashed out may still fail, see [`OnOrphanedHashNode`].
+pub async fn hydrate_pre_images<F, Fut>(
+    trace: BlockTrace,
+    other: &OtherBlockData,
+    options: DecodingOptions,
+    mut resolve: F,
+) -> anyhow::Result<BlockTrace>
+where
+    F: FnMut(H256) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let BlockTrace {
+        trie_pre_images,
+        code_db,
+        txn_info,
+        jumpdest_tables,
+    } = trace;
+    let ProcessedBlockTracePreImages {
+        tries: PartialTriePreImages {
+            mut state,
+            mut storage,
+        },
+        extra_code_hash_mappings,
+    } = process_pre_images(trie_pre_images, options)?;
+
+    let traces = txn_info.iter().flat_map(|txn| {
+        txn.traces
+            .iter()
+            .map(|(address, trace)| (*address, Some(trace)))
+    });
+    let withdrawals = other
+        .b_data
+        .withdrawals
+        .iter()
+        .map(|(address, _)| (*address, None));
+
+    let mut resolved = 0;
+    for (address, trace) in traces.chain(withdrawals) {
+        let key = TrieKey::from_address(address);
+        resolved += state.hydrate(key, &mut resolve).await?;
+
+        let Some(trace) = trace else { continue };
+        let slots = trace
+            .storage_read
+            .iter()
+            .flatten()
+            .chain(trace.storage_written.iter().flat_map(|it| it.keys()))
+            .collect::<Vec<_>>();
+        // Accounts created by the block have no storage to resolve.
+        let Some(account) = state.get_by_key(key) else {
+            continue;
+        };
+        if slots.is_empty() || account.storage_root == EMPTY_TRIE_HASH {
+            continue;
+        }
+        let storage_trie = storage.entry(hash(address)).or_insert_with(|| {
+            let mut trie = StorageTrie::new(OnOrphanedHashNode::Reject);
+            **trie.as_mut_hashed_partial_trie_unchecked() = Node::Hash(account.storage_root);
+            trie
+        });
+        for slot in slots {
+            resolved += storage_trie
+                .hydrate(TrieKey::from_hash(hash(slot)), &mut resolve)
+                .await?;
+        }
+    }
+    log::debug!("resolved {resolved} hashed-out trie nodes");
+
+    let mut code_db = code_db.unwrap_or_default();
+    code_db.extend(
+        extra_code_hash_mappings
+            .into_iter()
+            .flatten()
+            .map(|(code_hash, code)| (B256::from(code_hash.0), code)),
+    );
+    Ok(BlockTrace {
+        trie_pre_images: BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+            state: SeparateTriePreImage::Direct(state.as_hashed_partial_trie().clone()),
+            storage: SeparateStorageTriesPreImage::MultipleTries(
+                storage
+                    .into_iter()
+                    .map(|(hashed_address, trie)| {
+                        (
+                            B256::from(hashed_address.0),
+                            SeparateTriePreImage::Direct(trie.as_hashed_partial_trie().clone()),
+                        )
+                    })
+                    .collect(),
+            ),
+        }),
+        code_db: Some(code_db).filter(|it| !it.is_empty()),
+        txn_info,
+        jumpdest_tables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{keccak256, Address};
+    use ethereum_types::U256;
+    use evm_arithmetization::generation::mpt::AccountRlp;
+    use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
+    use mpt_trie::nibbles::Nibbles;
+    use mpt_trie::partial_trie::{HashedPartialTrie, PartialTrie as _};
+
+    use super::*;
+    use crate::{BlockLevelData, TxnInfo, TxnMeta, TxnTrace};
+
+    fn leaf(key: H256, value: Vec<u8>) -> Vec<u8> {
+        rlp::encode_list::<Vec<u8>, _>(&[
+            Nibbles::from_h256_be(key)
+                .to_hex_prefix_encoding(true)
+                .to_vec(),
+            value,
+        ])
+        .to_vec()
+    }
+
+    #[test]
+    fn resolves_accessed_paths() {
+        let address = Address::repeat_byte(1);
+        let slot = B256::ZERO;
+
+        let storage_node = leaf(hash(slot), rlp::encode(&U256::from(7)).to_vec());
+        let account = AccountRlp {
+            storage_root: hash(&storage_node),
+            ..Default::default()
+        };
+        let state_node = leaf(hash(address), rlp::encode(&account).to_vec());
+        let state_root = hash(&state_node);
+        let nodes = HashMap::from([
+            (state_root, state_node),
+            (account.storage_root, storage_node),
+        ]);
+
+        let trace = BlockTrace {
+            trie_pre_images: BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+                state: SeparateTriePreImage::Direct(HashedPartialTrie::new(Node::Hash(state_root))),
+                storage: SeparateStorageTriesPreImage::MultipleTries(HashMap::new()),
+            }),
+            code_db: None,
+            txn_info: vec![TxnInfo {
+                traces: HashMap::from([(
+                    address,
+                    TxnTrace {
+                        storage_read: Some(vec![slot]),
+                        ..Default::default()
+                    },
+                )]),
+                meta: TxnMeta {
+                    byte_code: vec![],
+                    new_receipt_trie_node_byte: vec![],
+                    gas_used: 0,
+                },
+            }],
+            jumpdest_tables: Default::default(),
+        };
+        let other = OtherBlockData {
+            b_data: BlockLevelData {
+                b_meta: BlockMetadata::default(),
+                b_hashes: BlockHashes::default(),
+                withdrawals: vec![],
+                transactions_root: None,
+                global_exit_roots: vec![],
+                burn_addr: None,
+                ommers: vec![],
+            },
+            checkpoint_state_trie_root: B256::from(state_root.0),
+        };
+
+        let mut requested = vec![];
+        let trace = futures::executor::block_on(hydrate_pre_images(
+            trace,
+            &other,
+            DecodingOptions::default(),
+            |hash| {
+                requested.push(hash);
+                let node = nodes.get(&hash).cloned();
+                async move { node.ok_or_else(|| anyhow::anyhow!("unknown node")) }
+            },
+        ))
+        .unwrap();
+        assert_eq!(requested, [state_root, account.storage_root]);
+
+        let BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+            state: SeparateTriePreImage::Direct(state),
+            storage: SeparateStorageTriesPreImage::MultipleTries(storage),
+        }) = trace.trie_pre_images
+        else {
+            panic!("expected separate pre-images");
+        };
+        assert_eq!(state.hash(), state_root);
+        assert!(state.get(Nibbles::from_h256_be(hash(address))).is_some());
+        let SeparateTriePreImage::Direct(storage) = &storage[&keccak256(address)];
+        assert_eq!(storage.hash(), account.storage_root);
+        assert!(storage.get(Nibbles::from_h256_be(hash(slot))).is_some());
+    }
+}
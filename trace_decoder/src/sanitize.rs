@@ -0,0 +1,201 @@
+//! Detection of non-canonical structures in the trie pre-images.
+//!
+//! Some providers emit pre-images holding nodes which a canonical MPT never
+//! contains, like extension nodes wrapping a single-child branch or leaves
+//! with an empty value.
+//!
+//! The decoder rebuilds its tries by re-inserting the leaves and hashed-out
+//! sub-tries of the pre-images, which restores the canonical structure, and
+//! skips empty-value leaves, which stand for absent entries.
+//! [`Sanitation::Reject`] rejects such pre-images instead.
+
+use std::fmt;
+
+use ethereum_types::H256;
+use mpt_trie::nibbles::Nibbles;
+use mpt_trie::partial_trie::{HashedPartialTrie, Node, WrappedNode};
+
+/// How to handle non-canonical structures in the trie pre-images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sanitation {
+    /// Normalize the tries to their canonical structure.
+    #[default]
+    Normalize,
+    /// Reject pre-images holding non-canonical structures.
+    Reject,
+}
+
+/// The trie of a pre-image holding a [`NonCanonical`] node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrieId {
+    State,
+    /// The storage trie of the account with the given hashed address.
+    Storage(H256),
+}
+
+impl fmt::Display for TrieId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrieId::State => write!(f, "state trie"),
+            TrieId::Storage(account) => write!(f, "storage trie of {account:x}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NonCanonicalKind {
+    /// An extension node without nibbles.
+    EmptyExtension,
+    /// An extension node whose child is neither a branch nor hashed out.
+    ExtensionWithoutBranch,
+    /// A branch node with less than two children.
+    SparseBranch,
+    /// A leaf node with an empty value.
+    EmptyValue,
+}
+
+/// A node of a pre-image which a canonical trie never contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NonCanonical {
+    pub trie: TrieId,
+    /// The path of the node from the root of its trie.
+    pub path: Nibbles,
+    pub kind: NonCanonicalKind,
+}
+
+impl fmt::Display for NonCanonical {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            NonCanonicalKind::EmptyExtension => "extension without nibbles",
+            NonCanonicalKind::ExtensionWithoutBranch => "extension without a branch child",
+            NonCanonicalKind::SparseBranch => "branch with less than two children",
+            NonCanonicalKind::EmptyValue => "leaf with an empty value",
+        };
+        write!(f, "{kind} at {} in the {}", self.path, self.trie)
+    }
+}
+
+impl Sanitation {
+    /// Fails on `non_canonical` nodes when rejecting them.
+    pub(crate) fn apply(self, non_canonical: &[NonCanonical]) -> anyhow::Result<()> {
+        /// The number of nodes listed in errors and logs.
+        const MAX_LISTED: usize = 8;
+
+        if non_canonical.is_empty() {
+            return Ok(());
+        }
+        let listed = non_canonical
+            .iter()
+            .take(MAX_LISTED)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self {
+            Sanitation::Normalize => {
+                log::debug!(
+                    "normalizing {} non-canonical pre-image nodes: {listed}",
+                    non_canonical.len()
+                );
+                Ok(())
+            }
+            Sanitation::Reject => anyhow::bail!(
+                "pre-images hold {} non-canonical nodes: {listed}",
+                non_canonical.len()
+            ),
+        }
+    }
+}
+
+/// Returns the non-canonical nodes of a pre-image `trie`.
+pub(crate) fn find_non_canonical(id: TrieId, trie: &HashedPartialTrie) -> Vec<NonCanonical> {
+    fn visit(
+        id: TrieId,
+        node: &Node<HashedPartialTrie>,
+        path: Nibbles,
+        acc: &mut Vec<NonCanonical>,
+    ) {
+        let mut push = |kind| {
+            acc.push(NonCanonical {
+                trie: id,
+                path,
+                kind,
+            })
+        };
+        match node {
+            Node::Empty | Node::Hash(_) => {}
+            Node::Leaf { value, .. } => {
+                if value.is_empty() {
+                    push(NonCanonicalKind::EmptyValue)
+                }
+            }
+            Node::Extension { nibbles, child } => {
+                if nibbles.is_empty() {
+                    push(NonCanonicalKind::EmptyExtension)
+                }
+                if !matches!(child_node(child), Node::Branch { .. } | Node::Hash(_)) {
+                    push(NonCanonicalKind::ExtensionWithoutBranch)
+                }
+                visit(id, child_node(child), path.merge_nibbles(nibbles), acc)
+            }
+            Node::Branch { children, value } => {
+                let num_children = children
+                    .iter()
+                    .filter(|child| !matches!(child_node(child), Node::Empty))
+                    .count();
+                if num_children + usize::from(!value.is_empty()) < 2 {
+                    push(NonCanonicalKind::SparseBranch)
+                }
+                for (ix, child) in (0u8..).zip(children) {
+                    visit(id, child_node(child), path.merge_nibble(ix), acc)
+                }
+            }
+        }
+    }
+
+    fn child_node(child: &WrappedNode<HashedPartialTrie>) -> &Node<HashedPartialTrie> {
+        child
+    }
+
+    let mut acc = Vec::new();
+    visit(id, trie, Nibbles::default(), &mut acc);
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use mpt_trie::partial_trie::PartialTrie as _;
+
+    use super::*;
+
+    #[test]
+    fn extension_over_single_child_branch() {
+        let mut children: [_; 16] = std::array::from_fn(|_| Node::Empty.into());
+        children[3] = Node::Leaf {
+            nibbles: Nibbles::from_str("0x45").unwrap(),
+            value: vec![1],
+        }
+        .into();
+        let trie = HashedPartialTrie::new(Node::Extension {
+            nibbles: Nibbles::from_str("0x12").unwrap(),
+            child: Node::Branch {
+                children,
+                value: vec![],
+            }
+            .into(),
+        });
+
+        let kinds = find_non_canonical(TrieId::State, &trie)
+            .into_iter()
+            .map(|it| it.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [NonCanonicalKind::SparseBranch]);
+        assert!(Sanitation::Normalize
+            .apply(&find_non_canonical(TrieId::State, &trie))
+            .is_ok());
+        assert!(Sanitation::Reject
+            .apply(&find_non_canonical(TrieId::State, &trie))
+            .is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Lookups into the state of the chain at the start of a block.
+//!
+//! The decoder reads the accounts and codes which existed before the block
+//! through [`StateAccess`], rather than through the trie pre-images directly.
+//! [`PreImageState`] serves them from the pre-images of a [`BlockTrace`], and
+//! other sources, e.g. the database of a node, can be plugged in by
+//! implementing the trait.
+
+use std::collections::HashMap;
+
+use alloy::primitives::B256;
+use ethereum_types::{H256, U256};
+use zk_evm_common::AccountRlp;
+
+use crate::processed_block_trace::{Hash2Code, ProcessedBlockTracePreImages};
+use crate::typed_mpt::TrieKey;
+use crate::{process_pre_images, BlockTrace, DecodingOptions, PartialTriePreImages};
+
+/// A source of the state of the chain at the start of a block.
+///
+/// Accounts and slots are keyed by hash, as in the tries and in the flat
+/// state of most clients. Lookups return [`None`] for the entries which don't
+/// exist, or which the source doesn't know of, e.g. because they are hashed
+/// out of a witness. Errors are reserved for failures of the source itself.
+pub trait StateAccess {
+    /// Returns the account at the hashed address.
+    fn account(&self, hashed_address: H256) -> anyhow::Result<Option<AccountRlp>>;
+
+    /// Returns the value of a slot of the account at the hashed address.
+    fn storage(&self, hashed_address: H256, hashed_slot: H256) -> anyhow::Result<Option<U256>>;
+
+    /// Returns the code with the given hash.
+    fn code(&self, code_hash: H256) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Returns whether the code with the given hash is known.
+    ///
+    /// Implementations may override this to avoid copying the code.
+    fn has_code(&self, code_hash: H256) -> anyhow::Result<bool> {
+        Ok(self.code(code_hash)?.is_some())
+    }
+}
+
+/// The state served by the pre-images and the codes of a [`BlockTrace`].
+#[derive(Debug)]
+pub struct PreImageState {
+    pub(crate) tries: PartialTriePreImages,
+    pub(crate) codes: Hash2Code,
+}
+
+impl PreImageState {
+    /// Decodes the pre-images of `trace`.
+    pub fn new(trace: &BlockTrace, options: DecodingOptions) -> anyhow::Result<Self> {
+        Ok(Self::from_pre_images(
+            process_pre_images(trace.trie_pre_images.clone(), options)?,
+            trace.code_db.clone(),
+        ))
+    }
+
+    /// Note that the hashes of `code_db` are discarded and recomputed.
+    pub(crate) fn from_pre_images(
+        pre_images: ProcessedBlockTracePreImages,
+        code_db: Option<HashMap<B256, Vec<u8>>>,
+    ) -> Self {
+        let ProcessedBlockTracePreImages {
+            tries,
+            extra_code_hash_mappings,
+        } = pre_images;
+        Self {
+            tries,
+            codes: code_db
+                .into_iter()
+                .flat_map(HashMap::into_values)
+                .chain(
+                    extra_code_hash_mappings
+                        .into_iter()
+                        .flat_map(HashMap::into_values),
+                )
+                .collect(),
+        }
+    }
+}
+
+impl StateAccess for PreImageState {
+    fn account(&self, hashed_address: H256) -> anyhow::Result<Option<AccountRlp>> {
+        Ok(self
+            .tries
+            .state
+            .get_by_key(TrieKey::from_hash(hashed_address)))
+    }
+
+    fn storage(&self, hashed_address: H256, hashed_slot: H256) -> anyhow::Result<Option<U256>> {
+        Ok(self
+            .tries
+            .storage
+            .get(&hashed_address)
+            .and_then(|trie| trie.get_slot_by_key(TrieKey::from_hash(hashed_slot))))
+    }
+
+    fn code(&self, code_hash: H256) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.codes.get(code_hash).ok().map(<[u8]>::to_vec))
+    }
+
+    fn has_code(&self, code_hash: H256) -> anyhow::Result<bool> {
+        Ok(self.codes.get(code_hash).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::Address;
+    use zk_evm_common::EMPTY_CODE_HASH;
+
+    use super::*;
+    use crate::{
+        hash, BlockTraceTriePreImages, SeparateStorageTriesPreImage, SeparateTriePreImage,
+        SeparateTriePreImages,
+    };
+
+    #[test]
+    fn serves_pre_images() {
+        let address = Address::repeat_byte(1);
+        let code = vec![0x60, 0x00];
+        let account = AccountRlp {
+            nonce: 1.into(),
+            code_hash: hash(&code),
+            ..Default::default()
+        };
+        let mut state = crate::typed_mpt::StateTrie::default();
+        state.insert_by_address(address, account).unwrap();
+
+        let trace = BlockTrace {
+            trie_pre_images: BlockTraceTriePreImages::Separate(SeparateTriePreImages {
+                state: SeparateTriePreImage::Direct(state.as_hashed_partial_trie().clone()),
+                storage: SeparateStorageTriesPreImage::MultipleTries(HashMap::new()),
+            }),
+            code_db: Some(HashMap::from([(B256::ZERO, code.clone())])),
+            txn_info: vec![],
+            jumpdest_tables: Default::default(),
+        };
+        let state = PreImageState::new(&trace, DecodingOptions::default()).unwrap();
+
+        assert_eq!(state.account(hash(address)).unwrap(), Some(account));
+        assert_eq!(state.account(hash(Address::zero())).unwrap(), None);
+        assert_eq!(state.storage(hash(address), H256::zero()).unwrap(), None);
+        // The user-provided hash of the code is ignored.
+        assert_eq!(state.code(hash(&code)).unwrap(), Some(code));
+        assert!(!state.has_code(H256::zero()).unwrap());
+        assert!(state.has_code(EMPTY_CODE_HASH).unwrap());
+    }
+}
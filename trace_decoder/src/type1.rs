@@ -6,11 +6,12 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::{bail, ensure, Context as _};
 use either::Either;
-use evm_arithmetization::generation::mpt::AccountRlp;
 use mpt_trie::partial_trie::OnOrphanedHashNode;
 use nunny::NonEmpty;
 use u4::U4;
+use zk_evm_common::AccountRlp;
 
+use crate::sanitize::{NonCanonical, NonCanonicalKind, TrieId};
 use crate::typed_mpt::{StateTrie, StorageTrie, TrieKey};
 use crate::wire::{Instruction, SmtLeaf};
 
@@ -21,6 +22,9 @@ pub struct Frontend {
     /// The key here matches the [`TriePath`] inside [`Self::state`] for
     /// accounts which had inline storage.
     pub storage: BTreeMap<TrieKey, StorageTrie>,
+    /// The non-canonical nodes of the witness, which are normalized in
+    /// [`Self::state`] and [`Self::storage`].
+    pub non_canonical: Vec<NonCanonical>,
 }
 
 impl Default for Frontend {
@@ -31,6 +35,7 @@ impl Default for Frontend {
             state: StateTrie::new(OnOrphanedHashNode::CollapseToExtension),
             code: BTreeSet::new(),
             storage: BTreeMap::new(),
+            non_canonical: Vec::new(),
         }
     }
 }
@@ -43,21 +48,87 @@ pub fn frontend(instructions: impl IntoIterator<Item = Instruction>) -> anyhow::
     );
     let execution = executions.into_vec().remove(0);
 
-    let mut frontend = Frontend::default();
-    visit(
-        &mut frontend,
-        &stackstack::Stack::new(),
-        match execution {
-            Execution::Leaf(it) => Node::Leaf(it),
-            Execution::Extension(it) => Node::Extension(it),
-            Execution::Branch(it) => Node::Branch(it),
-            Execution::Empty => Node::Empty,
-        },
-    )?;
+    let node = match execution {
+        Execution::Leaf(it) => Node::Leaf(it),
+        Execution::Extension(it) => Node::Extension(it),
+        Execution::Branch(it) => Node::Branch(it),
+        Execution::Empty => Node::Empty,
+    };
+    let mut frontend = Frontend {
+        non_canonical: find_non_canonical(&node),
+        ..Default::default()
+    };
+    visit(&mut frontend, &stackstack::Stack::new(), node)?;
 
     Ok(frontend)
 }
 
+/// Returns the non-canonical nodes of the state trie rooted at `node`, and of
+/// the storage tries of its accounts.
+fn find_non_canonical(node: &Node) -> Vec<NonCanonical> {
+    fn visit(trie: TrieId, node: &Node, path: &mut Vec<U4>, acc: &mut Vec<NonCanonical>) {
+        let mut push = |kind| {
+            let mut nibbles = mpt_trie::nibbles::Nibbles::default();
+            for nibble in path.iter() {
+                nibbles.push_nibble_back(*nibble as u8);
+            }
+            acc.push(NonCanonical {
+                trie,
+                path: nibbles,
+                kind,
+            })
+        };
+        match node {
+            Node::Hash(_) | Node::Code(_) | Node::Empty => {}
+            Node::Leaf(Leaf { key, value }) => {
+                if let Either::Right(Account {
+                    storage: Some(storage),
+                    ..
+                }) = value
+                {
+                    if let Ok(account) = TrieKey::new(path.iter().chain(key.iter()).copied()) {
+                        let account = TrieId::Storage(account.into_hash_left_padded());
+                        visit(account, storage, &mut Vec::new(), acc)
+                    }
+                }
+            }
+            Node::Extension(Extension { key, child }) => {
+                if !matches!(**child, Node::Branch(_) | Node::Hash(_)) {
+                    push(NonCanonicalKind::ExtensionWithoutBranch)
+                }
+                let len = path.len();
+                path.extend(key.iter().copied());
+                visit(trie, child, path, acc);
+                path.truncate(len);
+            }
+            Node::Branch(Branch { children }) => {
+                let num_children = children
+                    .iter()
+                    .flatten()
+                    .filter(|child| !matches!(***child, Node::Empty))
+                    .count();
+                if num_children < 2 {
+                    push(NonCanonicalKind::SparseBranch)
+                }
+                for (ix, child) in children.iter().enumerate() {
+                    if let Some(child) = child {
+                        path.push(
+                            U4::new(ix.try_into().expect("ix is in range 0..16"))
+                                .expect("ix is in range 0..16"),
+                        );
+                        visit(trie, child, path, acc);
+                        path.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    let mut acc = Vec::new();
+    visit(TrieId::State, node, &mut Vec::new(), &mut acc);
+    acc
+}
+
 fn visit(
     frontend: &mut Frontend,
     path: &stackstack::Stack<'_, U4>,
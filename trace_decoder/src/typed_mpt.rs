@@ -1,16 +1,43 @@
 //! Principled MPT types used in this library.
 
 use core::fmt;
+#[cfg(feature = "generation")]
+use std::collections::HashMap;
+#[cfg(feature = "generation")]
+use std::future::Future;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::slice::SliceIndex;
+#[cfg(feature = "generation")]
+use std::sync::Arc;
 
+#[cfg(any(feature = "generation", test))]
+use alloy::consensus::{ReceiptEnvelope, TxEnvelope};
+#[cfg(any(feature = "generation", test))]
+use alloy::providers::network::eip2718::Encodable2718 as _;
+#[cfg(feature = "generation")]
+use anyhow::{ensure, Context as _};
 use copyvec::CopyVec;
-use ethereum_types::{Address, H256};
-use evm_arithmetization::generation::mpt::AccountRlp;
+#[cfg(any(
+    feature = "generation",
+    feature = "cdk_erigon",
+    feature = "state_expiry",
+    test
+))]
+use ethereum_types::Address;
+use ethereum_types::{H256, U256};
+#[cfg(feature = "generation")]
+use mpt_trie::builder::PartialTrieBuilder;
+#[cfg(any(feature = "generation", test))]
+use mpt_trie::trie_subsets::{create_trie_subset, SubsetTrieError};
 use mpt_trie::{
     partial_trie::{HashedPartialTrie, Node, OnOrphanedHashNode, PartialTrie as _},
-    trie_ops::TrieOpError,
+    trie_ops::{TrieOpError, ValOrHash},
 };
 use u4::{AsNibbles, U4};
+use zk_evm_common::AccountRlp;
+
+use crate::IntoEthereumTypes;
 
 /// Map where keys are [up to 64 nibbles](TrieKey),
 /// and values are [`rlp::Encodable`]/[`rlp::Decodable`].
@@ -51,6 +78,22 @@ impl<T> TypedMpt<T> {
             .map_err(|source| Error { source })
             .map(|_| prev)
     }
+    /// Inserts all of `entries`, discarding the previous values.
+    ///
+    /// Unlike repeated calls to [`Self::insert`], previous values aren't looked
+    /// up and decoded.
+    fn extend(&mut self, entries: impl IntoIterator<Item = (TrieKey, T)>) -> Result<(), Error>
+    where
+        T: rlp::Encodable,
+    {
+        self.inner
+            .extend(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.into_nibbles(), rlp::encode(&value).to_vec())),
+            )
+            .map_err(|source| Error { source })
+    }
     /// Note that this returns [`None`] if `key` crosses into a part of the
     /// trie that isn't hydrated.
     ///
@@ -76,6 +119,7 @@ impl<T> TypedMpt<T> {
     fn as_hashed_partial_trie(&self) -> &HashedPartialTrie {
         &self.inner
     }
+    #[cfg(feature = "generation")]
     fn as_mut_hashed_partial_trie_unchecked(&mut self) -> &mut HashedPartialTrie {
         &mut self.inner
     }
@@ -92,6 +136,25 @@ impl<T> TypedMpt<T> {
             Some((path, self.get(path)?))
         })
     }
+    /// Returns the entries with keys in `range`, in lexicographic order.
+    ///
+    /// Stops at the first hashed-out part of the trie which may hold keys in
+    /// `range`.
+    fn range(&self, range: impl RangeBounds<TrieKey>) -> impl Iterator<Item = (TrieKey, T)> + '_
+    where
+        T: rlp::Decodable,
+    {
+        let bound = |it: Bound<&TrieKey>| it.map(|key| key.into_nibbles());
+        self.inner
+            .range((bound(range.start_bound()), bound(range.end_bound())))
+            .map_while(|(nibbles, value)| match value {
+                ValOrHash::Val(bytes) => Some((
+                    TrieKey::from_nibbles(nibbles),
+                    rlp::decode(&bytes).expect(Self::PANIC_MSG),
+                )),
+                ValOrHash::Hash(_) => None,
+            })
+    }
 }
 
 impl<T> Default for TypedMpt<T> {
@@ -111,12 +174,83 @@ where
     }
 }
 
+/// Replaces the hash nodes on the path to `key` with the nodes they stand for,
+/// as returned by `resolve`, until the path is fully hydrated.
+///
+/// Returns the number of resolved nodes. Resolving a node doesn't change the
+/// root of the trie.
+#[cfg(feature = "generation")]
+async fn hydrate<F, Fut>(
+    trie: &mut HashedPartialTrie,
+    key: TrieKey,
+    resolve: &mut F,
+) -> anyhow::Result<usize>
+where
+    F: FnMut(H256) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let mut resolved = 0;
+    while let Some((node, rest)) = hash_node_on_path(trie, key) {
+        let Node::Hash(hash) = **node else {
+            unreachable!("only hash nodes are returned")
+        };
+        let path = key
+            .slice(..key.len() - rest.len())
+            .expect("the rest of the key is a suffix of it");
+        let bytes = resolve(hash)
+            .await
+            .with_context(|| format!("couldn't resolve trie node {hash:x} at {path}"))?;
+        ensure!(
+            keccak_hash::keccak(&bytes) == hash,
+            "resolved trie node doesn't hash to {hash:x}"
+        );
+        let mut decoded =
+            PartialTrieBuilder::<HashedPartialTrie>::new(hash, HashMap::from([(hash, bytes)]))
+                .build();
+        **node = std::mem::take(&mut *decoded);
+        resolved += 1;
+    }
+    Ok(resolved)
+}
+
+/// Returns the first hash node on the path to `key`, if any, with the rest of
+/// the key below it.
+#[cfg(feature = "generation")]
+fn hash_node_on_path(
+    mut trie: &mut HashedPartialTrie,
+    mut key: TrieKey,
+) -> Option<(&mut HashedPartialTrie, TrieKey)> {
+    loop {
+        if let Node::Hash(_) = **trie {
+            return Some((trie, key));
+        }
+        match &mut **trie {
+            Node::Empty | Node::Hash(_) | Node::Leaf { .. } => return None,
+            Node::Branch { children, .. } => {
+                let (ix, rest) = key.split_first()?;
+                key = rest;
+                trie = &mut **Arc::make_mut(&mut children[ix as usize]);
+            }
+            Node::Extension { nibbles, child } => {
+                key = key.strip_prefix(TrieKey::from_nibbles(*nibbles))?;
+                trie = &mut **Arc::make_mut(child);
+            }
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error(transparent)]
 pub struct Error {
     source: TrieOpError,
 }
 
+impl From<Error> for TrieOpError {
+    fn from(Error { source }: Error) -> Self {
+        source
+    }
+}
+
 /// Bounded sequence of [`U4`],
 /// used as a key for [`TypedMpt`].
 ///
@@ -145,12 +279,25 @@ impl TrieKey {
         AsNibbles(&mut packed).pack_from_slice(&self.0);
         H256::from_slice(&packed)
     }
-    fn from_address(address: Address) -> Self {
-        Self::from_hash(keccak_hash::keccak(address))
+    #[cfg(any(
+        feature = "generation",
+        feature = "cdk_erigon",
+        feature = "state_expiry",
+        test
+    ))]
+    pub fn from_address(address: impl IntoEthereumTypes<Address>) -> Self {
+        Self::from_hash(keccak_hash::keccak(address.into_ethereum_types()))
     }
-    pub fn from_hash(H256(bytes): H256) -> Self {
+    pub fn from_hash(hash: impl IntoEthereumTypes<H256>) -> Self {
+        let H256(bytes) = hash.into_ethereum_types();
         Self::new(AsNibbles(bytes)).expect("32 bytes is 64 nibbles, which fits")
     }
+    /// The key of a storage slot, i.e. the hash of its big-endian index.
+    pub fn from_slot(slot: impl IntoEthereumTypes<U256>) -> Self {
+        Self::from_hash(keccak_hash::keccak(H256::from_uint(
+            &slot.into_ethereum_types(),
+        )))
+    }
 
     pub fn from_txn_ix(txn_ix: usize) -> Self {
         TrieKey::new(AsNibbles(rlp::encode(&txn_ix))).expect(
@@ -178,16 +325,75 @@ impl TrieKey {
         }
         Self(ours)
     }
+    /// Copies `components`, which hold at most 64 nibbles.
+    fn from_slice(components: &[U4]) -> Self {
+        Self::new(components.iter().copied()).expect("a key fits in 64 nibbles")
+    }
+    /// The number of nibbles of the key.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Whether the key is empty, i.e. is the path to the root.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// The nibbles of the key, from the root of the trie down.
+    pub fn components(&self) -> impl DoubleEndedIterator<Item = U4> + ExactSizeIterator + '_ {
+        self.0.iter().copied()
+    }
+    /// Returns the nibbles of the key in `range`, or [`None`] if it is out of
+    /// bounds.
+    pub fn slice(&self, range: impl SliceIndex<[U4], Output = [U4]>) -> Option<Self> {
+        self.0.get(range).map(Self::from_slice)
+    }
+    /// Splits the first nibble of the key, i.e. the branch it takes from the
+    /// root, from the rest.
+    pub fn split_first(&self) -> Option<(U4, Self)> {
+        self.0
+            .split_first()
+            .map(|(first, rest)| (*first, Self::from_slice(rest)))
+    }
+    /// Whether the path to `self` goes through `prefix`.
+    pub fn starts_with(&self, prefix: Self) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+    /// Returns the rest of the key after `prefix`, if it starts with it.
+    pub fn strip_prefix(&self, prefix: Self) -> Option<Self> {
+        self.0.strip_prefix(&prefix.0[..]).map(Self::from_slice)
+    }
+    /// The longest key both `self` and `other` start with.
+    pub fn common_prefix(&self, other: Self) -> Self {
+        let len = self
+            .components()
+            .zip(other.components())
+            .take_while(|(ours, theirs)| ours == theirs)
+            .count();
+        Self::from_slice(&self.0[..len])
+    }
+    /// Appends `suffix` to the key, returning an error if the result exceeds
+    /// 64 nibbles.
+    pub fn concat(&self, suffix: Self) -> anyhow::Result<Self> {
+        Self::new(self.components().chain(suffix.components()))
+    }
+    /// The prefixes of the key, from the empty key to the key itself.
+    ///
+    /// These are the paths to the nodes a lookup of the key goes through, and
+    /// are yielded in increasing order.
+    pub fn prefixes(&self) -> impl DoubleEndedIterator<Item = Self> + '_ {
+        (0..=self.len()).map(|len| Self::from_slice(&self.0[..len]))
+    }
 }
 
 /// Per-block, `txn_ix -> [u8]`.
 ///
 /// See <https://ethereum.org/en/developers/docs/data-structures-and-encoding/patricia-merkle-trie/#receipts-trie>
+#[cfg(any(feature = "generation", test))]
 #[derive(Debug, Clone, Default)]
 pub struct TransactionTrie {
     untyped: HashedPartialTrie,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl TransactionTrie {
     pub fn insert(&mut self, txn_ix: usize, val: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
         let prev = self
@@ -199,6 +405,15 @@ impl TransactionTrie {
             .map_err(|source| Error { source })?;
         Ok(prev)
     }
+    /// Inserts a signed transaction, encoded as in the trie: an RLP list for
+    /// legacy transactions, prefixed with their type byte for the others.
+    pub fn insert_txn(
+        &mut self,
+        txn_ix: usize,
+        txn: &TxEnvelope,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.insert(txn_ix, txn.encoded_2718())
+    }
     pub fn root(&self) -> H256 {
         self.untyped.hash()
     }
@@ -210,11 +425,13 @@ impl TransactionTrie {
 /// Per-block, `txn_ix -> [u8]`.
 ///
 /// See <https://ethereum.org/en/developers/docs/data-structures-and-encoding/patricia-merkle-trie/#transaction-trie>
+#[cfg(any(feature = "generation", test))]
 #[derive(Debug, Clone, Default)]
 pub struct ReceiptTrie {
     untyped: HashedPartialTrie,
 }
 
+#[cfg(any(feature = "generation", test))]
 impl ReceiptTrie {
     pub fn insert(&mut self, txn_ix: usize, val: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
         let prev = self
@@ -226,6 +443,16 @@ impl ReceiptTrie {
             .map_err(|source| Error { source })?;
         Ok(prev)
     }
+    /// Inserts the receipt of a transaction, encoded as in the trie: an RLP
+    /// list for legacy transactions, prefixed with their type byte for the
+    /// others.
+    pub fn insert_receipt(
+        &mut self,
+        txn_ix: usize,
+        receipt: &ReceiptEnvelope,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.insert(txn_ix, receipt.encoded_2718())
+    }
     pub fn root(&self) -> H256 {
         self.untyped.hash()
     }
@@ -251,9 +478,10 @@ impl StateTrie {
             },
         }
     }
+    #[cfg(any(feature = "generation", feature = "cdk_erigon", test))]
     pub fn insert_by_address(
         &mut self,
-        address: Address,
+        address: impl IntoEthereumTypes<Address>,
         account: AccountRlp,
     ) -> Result<Option<AccountRlp>, Error> {
         self.insert_by_key(TrieKey::from_address(address), account)
@@ -265,14 +493,23 @@ impl StateTrie {
     ) -> Result<Option<AccountRlp>, Error> {
         self.typed.insert(key, account)
     }
+    /// Inserts many accounts at once, which is cheaper than inserting them one
+    /// by one, as previous values aren't returned.
+    pub fn extend(
+        &mut self,
+        accounts: impl IntoIterator<Item = (TrieKey, AccountRlp)>,
+    ) -> Result<(), Error> {
+        self.typed.extend(accounts)
+    }
     pub fn insert_hash_by_key(&mut self, key: TrieKey, hash: H256) -> Result<(), Error> {
         self.typed.insert_hash(key, hash)
     }
     pub fn get_by_key(&self, key: TrieKey) -> Option<AccountRlp> {
         self.typed.get(key)
     }
-    pub fn get_by_address(&self, address: Address) -> Option<AccountRlp> {
-        self.get_by_key(TrieKey::from_hash(keccak_hash::keccak(address)))
+    #[cfg(any(feature = "generation", feature = "cdk_erigon", test))]
+    pub fn get_by_address(&self, address: impl IntoEthereumTypes<Address>) -> Option<AccountRlp> {
+        self.get_by_key(TrieKey::from_address(address))
     }
     pub fn root(&self) -> H256 {
         self.typed.root()
@@ -280,9 +517,21 @@ impl StateTrie {
     pub fn iter(&self) -> impl Iterator<Item = (TrieKey, AccountRlp)> + '_ {
         self.typed.iter()
     }
+    /// Returns the accounts with keys in `range`, in lexicographic order,
+    /// e.g. to process the trie in chunks.
+    ///
+    /// Stops at the first hashed-out part of the trie which may hold accounts
+    /// in `range`.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<TrieKey>,
+    ) -> impl Iterator<Item = (TrieKey, AccountRlp)> + '_ {
+        self.typed.range(range)
+    }
     pub fn as_hashed_partial_trie(&self) -> &mpt_trie::partial_trie::HashedPartialTrie {
         self.typed.as_hashed_partial_trie()
     }
+    #[cfg(feature = "generation")]
     pub fn as_mut_hashed_partial_trie_unchecked(&mut self) -> &mut HashedPartialTrie {
         self.typed.as_mut_hashed_partial_trie_unchecked()
     }
@@ -294,9 +543,35 @@ impl StateTrie {
             .as_hashed_partial_trie()
             .contains(key.into_nibbles())
     }
+    /// Resolves the hashed-out nodes on the path to `key` with `resolve`,
+    /// returning how many were resolved.
+    #[cfg(feature = "generation")]
+    pub async fn hydrate<F, Fut>(&mut self, key: TrieKey, resolve: &mut F) -> anyhow::Result<usize>
+    where
+        F: FnMut(H256) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+    {
+        hydrate(&mut self.typed.inner, key, resolve).await
+    }
+    /// Returns the minimal trie holding the accounts at `keys`, everything
+    /// else being hashed out, which has the same root as `self`.
+    ///
+    /// Returns an error if the path to one of `keys` is hashed out in `self`.
+    #[cfg(any(feature = "generation", test))]
+    pub fn trim_to(
+        &self,
+        keys: impl IntoIterator<Item = TrieKey>,
+    ) -> Result<Self, SubsetTrieError> {
+        create_trie_subset(
+            &self.typed.inner,
+            keys.into_iter().map(TrieKey::into_nibbles),
+        )
+        .map(Self::from_hashed_partial_trie_unchecked)
+    }
     /// This allows users to break the [`TypedMpt`] invariant.
     /// If data that isn't a [`rlp::encode`]-ed [`AccountRlp`] is inserted,
     /// subsequent API calls may panic.
+    #[cfg(any(feature = "generation", test))]
     pub fn from_hashed_partial_trie_unchecked(
         src: mpt_trie::partial_trie::HashedPartialTrie,
     ) -> Self {
@@ -344,14 +619,478 @@ impl StorageTrie {
             .insert(key.into_nibbles(), hash)
             .map_err(|source| Error { source })
     }
+    /// Inserts many values at once, which is cheaper than inserting them one
+    /// by one, as previous values aren't returned.
+    pub fn extend(
+        &mut self,
+        values: impl IntoIterator<Item = (TrieKey, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        self.untyped
+            .extend(
+                values
+                    .into_iter()
+                    .map(|(key, value)| (key.into_nibbles(), value)),
+            )
+            .map_err(|source| Error { source })
+    }
+    /// Returns the value of `slot`.
+    ///
+    /// Note that this returns [`None`] if the slot is empty, if its key
+    /// crosses into a part of the trie that isn't hydrated, or if it doesn't
+    /// hold an RLP-encoded integer.
+    pub fn get_slot(&self, slot: impl IntoEthereumTypes<U256>) -> Option<U256> {
+        self.get_slot_by_key(TrieKey::from_slot(slot))
+    }
+    /// Like [`Self::get_slot`], for the [hashed key](TrieKey::from_slot) of a
+    /// slot.
+    pub fn get_slot_by_key(&self, key: TrieKey) -> Option<U256> {
+        rlp::decode(self.untyped.get(key.into_nibbles())?).ok()
+    }
+    /// Returns the hashed keys of the non-empty slots, with their values.
+    pub fn iter_slots(&self) -> impl Iterator<Item = (TrieKey, U256)> + '_ {
+        self.untyped.keys().filter_map(|nibbles| {
+            let key = TrieKey::from_nibbles(nibbles);
+            Some((key, self.get_slot_by_key(key)?))
+        })
+    }
+    /// Sets the value of `slot`, returning its previous value.
+    ///
+    /// As in the EVM, writing zero empties the slot.
+    #[cfg(any(feature = "generation", test))]
+    pub fn set_slot(
+        &mut self,
+        slot: impl IntoEthereumTypes<U256>,
+        value: impl IntoEthereumTypes<U256>,
+    ) -> Result<Option<U256>, Error> {
+        let slot = slot.into_ethereum_types();
+        let key = TrieKey::from_slot(slot);
+        let value = value.into_ethereum_types();
+        let prev = self.get_slot(slot);
+        match value.is_zero() {
+            true => self.untyped.delete(key.into_nibbles()).map(drop),
+            false => self
+                .untyped
+                .insert(key.into_nibbles(), rlp::encode(&value).to_vec()),
+        }
+        .map_err(|source| Error { source })?;
+        Ok(prev)
+    }
     pub fn root(&self) -> H256 {
         self.untyped.hash()
     }
     pub fn as_hashed_partial_trie(&self) -> &HashedPartialTrie {
         &self.untyped
     }
+    /// Resolves the hashed-out nodes on the path to `key` with `resolve`,
+    /// returning how many were resolved.
+    #[cfg(feature = "generation")]
+    pub async fn hydrate<F, Fut>(&mut self, key: TrieKey, resolve: &mut F) -> anyhow::Result<usize>
+    where
+        F: FnMut(H256) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+    {
+        hydrate(&mut self.untyped, key, resolve).await
+    }
 
+    #[cfg(feature = "generation")]
     pub fn as_mut_hashed_partial_trie_unchecked(&mut self) -> &mut HashedPartialTrie {
         &mut self.untyped
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use keccak_hash::keccak;
+    use proptest::prelude::*;
+    use zk_evm_common::EMPTY_TRIE_HASH;
+
+    use super::*;
+
+    /// A from-scratch implementation of the root of a Merkle Patricia Trie,
+    /// as specified in the appendix D of the Yellow Paper, against which the
+    /// tries of this module are checked.
+    fn reference_root(entries: &BTreeMap<TrieKey, Vec<u8>>) -> H256 {
+        if entries.is_empty() {
+            return keccak(rlp::NULL_RLP);
+        }
+        let entries = entries
+            .iter()
+            .map(|(key, value)| (key.0.into_iter().map(|u| u as u8).collect(), &value[..]))
+            .collect::<Vec<(Vec<u8>, _)>>();
+        keccak(reference_node(&entries, 0))
+    }
+
+    /// The RLP encoding of the node holding `entries`, whose keys are
+    /// consumed up to `depth`.
+    fn reference_node(entries: &[(Vec<u8>, &[u8])], depth: usize) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new();
+        if let [(key, value)] = entries {
+            stream.begin_list(2);
+            stream.append(&hex_prefix(&key[depth..], true));
+            stream.append(*value);
+            return stream.out().to_vec();
+        }
+
+        let (first, _) = &entries[0];
+        let common = (depth..first.len())
+            .take_while(|&i| entries.iter().all(|(key, _)| key[i] == first[i]))
+            .count();
+        if common > 0 {
+            stream.begin_list(2);
+            stream.append(&hex_prefix(&first[depth..depth + common], false));
+            append_child(&mut stream, &reference_node(entries, depth + common));
+            return stream.out().to_vec();
+        }
+
+        stream.begin_list(17);
+        for nibble in 0..16 {
+            let children = entries
+                .iter()
+                .filter(|(key, _)| key[depth] == nibble)
+                .cloned()
+                .collect::<Vec<_>>();
+            match children.is_empty() {
+                true => stream.append_empty_data(),
+                false => append_child(&mut stream, &reference_node(&children, depth + 1)),
+            };
+        }
+        // Keys all have the same length, so branches never hold a value.
+        stream.append_empty_data();
+        stream.out().to_vec()
+    }
+
+    /// Children shorter than a hash are inlined in their parent.
+    fn append_child(stream: &mut rlp::RlpStream, child: &[u8]) {
+        match child.len() < 32 {
+            true => stream.append_raw(child, 1),
+            false => stream.append(&keccak(child)),
+        };
+    }
+
+    fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+        let flag = 2 * u8::from(is_leaf) + (nibbles.len() % 2) as u8;
+        let mut nibbles = nibbles.to_vec();
+        nibbles.insert(0, flag);
+        if flag % 2 == 0 {
+            nibbles.insert(1, 0);
+        }
+        nibbles
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect()
+    }
+
+    fn account() -> impl Strategy<Value = AccountRlp> {
+        (any::<u64>(), any::<[u8; 32]>(), any::<[u8; 32]>()).prop_map(
+            |(nonce, balance, code_hash)| AccountRlp {
+                nonce: nonce.into(),
+                balance: U256::from_big_endian(&balance),
+                storage_root: EMPTY_TRIE_HASH,
+                code_hash: H256(code_hash),
+            },
+        )
+    }
+
+    fn accounts() -> impl Strategy<Value = BTreeMap<Address, AccountRlp>> {
+        prop::collection::btree_map(any::<[u8; 20]>().prop_map(Address::from), account(), 0..64)
+    }
+
+    /// Storage slots with small keys, whose long common prefixes yield
+    /// extension nodes, unlike hashed keys.
+    fn slots() -> impl Strategy<Value = BTreeMap<TrieKey, Vec<u8>>> {
+        prop::collection::btree_map(
+            any::<u16>().prop_map(|slot| TrieKey::from_hash(H256::from_low_u64_be(slot.into()))),
+            (1..=u64::MAX).prop_map(|value| rlp::encode(&U256::from(value)).to_vec()),
+            0..64,
+        )
+    }
+
+    fn state_entries<'a>(
+        accounts: impl IntoIterator<Item = (&'a Address, &'a AccountRlp)>,
+    ) -> BTreeMap<TrieKey, Vec<u8>> {
+        accounts
+            .into_iter()
+            .map(|(&address, account)| {
+                (
+                    TrieKey::from_address(address),
+                    rlp::encode(account).to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    fn state_trie<'a>(
+        accounts: impl IntoIterator<Item = (&'a Address, &'a AccountRlp)>,
+    ) -> StateTrie {
+        let mut trie = StateTrie::default();
+        for (&address, &account) in accounts {
+            assert_eq!(trie.insert_by_address(address, account).unwrap(), None);
+        }
+        trie
+    }
+
+    fn key() -> impl Strategy<Value = TrieKey> {
+        prop::collection::vec(0..16u8, 0..=64).prop_map(|nibbles| {
+            TrieKey::new(nibbles.into_iter().map(|it| U4::new(it).unwrap())).unwrap()
+        })
+    }
+
+    #[test]
+    fn typed_txn_and_receipt_insertion() {
+        use alloy::providers::network::eip2718::Decodable2718 as _;
+
+        let txn_bytes = hex_literal::hex!(
+            "02f87101830e5ad7808501231a000f826ac194617c8de5bde54ffbb8d92716cc947858ca38f58287"
+            "6ca54625d8e66f80c001a0e0c1666544ae41c9fecaba65277c51af8755838a43c6e5bdc15c83970a"
+            "f18461a0405b701cfa1a3b22fc547c21955d55210f7fdd87b576466a2cf30cb27e9fb442"
+        );
+        let txn = TxEnvelope::decode_2718(&mut &txn_bytes[..]).unwrap();
+        let mut typed = TransactionTrie::default();
+        let mut untyped = TransactionTrie::default();
+        typed.insert_txn(0, &txn).unwrap();
+        untyped.insert(0, txn_bytes.to_vec()).unwrap();
+        assert_eq!(typed.root(), untyped.root());
+
+        // An EIP-1559 receipt: successful, 21000 gas used, without logs.
+        let mut stream = rlp::RlpStream::new_list(4);
+        stream
+            .append(&1u8)
+            .append(&21_000u64)
+            .append(&vec![0u8; 256])
+            .begin_list(0);
+        let receipt_bytes = [&[0x02], &stream.out()[..]].concat();
+        let receipt = ReceiptEnvelope::decode_2718(&mut &receipt_bytes[..]).unwrap();
+        let mut typed = ReceiptTrie::default();
+        let mut untyped = ReceiptTrie::default();
+        typed.insert_receipt(0, &receipt).unwrap();
+        untyped.insert(0, receipt_bytes).unwrap();
+        assert_eq!(typed.root(), untyped.root());
+    }
+
+    #[test]
+    fn state_trie_range() {
+        let key = |i: u64| TrieKey::from_hash(H256::from_low_u64_be(i));
+        let mut trie = StateTrie::default();
+        trie.extend((0..10).map(|i| {
+            let account = AccountRlp {
+                nonce: i.into(),
+                ..Default::default()
+            };
+            (key(2 * i), account)
+        }))
+        .unwrap();
+        let nonces = |trie: &StateTrie, range: (Bound<TrieKey>, Bound<TrieKey>)| {
+            trie.range(range)
+                .map(|(_, account)| account.nonce.as_u64())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            nonces(&trie, (Bound::Included(key(4)), Bound::Excluded(key(10)))),
+            [2, 3, 4]
+        );
+        assert_eq!(
+            nonces(&trie, (Bound::Excluded(key(4)), Bound::Unbounded)),
+            [3, 4, 5, 6, 7, 8, 9]
+        );
+
+        trie.insert_hash_by_key(key(9), H256::repeat_byte(1))
+            .unwrap();
+        assert_eq!(
+            nonces(&trie, (Bound::Unbounded, Bound::Unbounded)),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            nonces(&trie, (Bound::Included(key(10)), Bound::Unbounded)),
+            [5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn state_trie_trim_to() {
+        let key = |i: u64| TrieKey::from_hash(H256::from_low_u64_be(i));
+        let mut trie = StateTrie::default();
+        trie.extend((0..10).map(|i| {
+            let account = AccountRlp {
+                nonce: i.into(),
+                ..Default::default()
+            };
+            (key(i), account)
+        }))
+        .unwrap();
+        let nonces = |trie: &StateTrie| {
+            trie.iter()
+                .map(|(_, account)| account.nonce.as_u64())
+                .collect::<Vec<_>>()
+        };
+
+        let trimmed = trie.trim_to([key(2), key(5)]).unwrap();
+        assert_eq!(trimmed.root(), trie.root());
+        assert_eq!(nonces(&trimmed), [2, 5]);
+
+        // A trimmed trie may be trimmed further, but not to the accounts it
+        // has hashed out.
+        let trimmed_again = trimmed.trim_to([key(5)]).unwrap();
+        assert_eq!(trimmed_again.root(), trie.root());
+        assert_eq!(nonces(&trimmed_again), [5]);
+        assert!(trimmed.trim_to([key(7)]).is_err());
+    }
+
+    #[test]
+    fn empty_tries() {
+        assert_eq!(reference_root(&BTreeMap::new()), EMPTY_TRIE_HASH);
+        assert_eq!(StateTrie::default().root(), EMPTY_TRIE_HASH);
+        assert_eq!(StorageTrie::default().root(), EMPTY_TRIE_HASH);
+    }
+
+    proptest! {
+        #[test]
+        fn state_trie_matches_reference(accounts in accounts()) {
+            let trie = state_trie(&accounts);
+            prop_assert_eq!(trie.root(), reference_root(&state_entries(&accounts)));
+            for (&address, &account) in &accounts {
+                prop_assert_eq!(trie.get_by_address(address), Some(account));
+            }
+            prop_assert_eq!(trie.iter().count(), accounts.len());
+        }
+
+        #[test]
+        fn extend_matches_insert(accounts in accounts(), slots in slots()) {
+            let mut trie = StateTrie::default();
+            trie.extend(
+                accounts
+                    .iter()
+                    .map(|(&address, &account)| (TrieKey::from_address(address), account)),
+            )
+            .unwrap();
+            prop_assert_eq!(trie.root(), state_trie(&accounts).root());
+
+            let mut trie = StorageTrie::default();
+            trie.extend(slots.clone()).unwrap();
+            prop_assert_eq!(trie.root(), reference_root(&slots));
+        }
+
+        #[test]
+        fn state_trie_remove(
+            accounts in accounts(),
+            removed in prop::collection::vec(any::<bool>(), 64),
+        ) {
+            let mut trie = state_trie(&accounts);
+            let (removed, kept): (Vec<_>, Vec<_>) =
+                accounts.iter().zip(removed).partition(|(_, removed)| *removed);
+            for ((&address, &account), _) in removed {
+                let key = TrieKey::from_address(address);
+                prop_assert_eq!(trie.remove(key).unwrap(), Some(account));
+                prop_assert!(!trie.contains(key));
+            }
+
+            let kept = kept.into_iter().map(|(entry, _)| entry).collect::<Vec<_>>();
+            prop_assert_eq!(trie.root(), state_trie(kept.clone()).root());
+            prop_assert_eq!(trie.root(), reference_root(&state_entries(kept)));
+        }
+
+        #[test]
+        fn storage_trie_matches_reference(slots in slots()) {
+            let mut trie = StorageTrie::default();
+            for (&key, value) in &slots {
+                prop_assert_eq!(trie.insert(key, value.clone()).unwrap(), None);
+            }
+            prop_assert_eq!(trie.root(), reference_root(&slots));
+        }
+
+        #[test]
+        fn storage_trie_slots(
+            values in prop::collection::btree_map(any::<u64>(), any::<u64>(), 0..64),
+        ) {
+            let mut trie = StorageTrie::default();
+            let mut untyped = StorageTrie::default();
+            for (&slot, &value) in &values {
+                prop_assert_eq!(trie.set_slot(U256::from(slot), U256::from(value)).unwrap(), None);
+                if value != 0 {
+                    let key = TrieKey::from_hash(keccak(H256::from_low_u64_be(slot)));
+                    untyped.insert(key, rlp::encode(&U256::from(value)).to_vec()).unwrap();
+                }
+            }
+            prop_assert_eq!(trie.root(), untyped.root());
+            prop_assert_eq!(
+                trie.iter_slots().count(),
+                values.values().filter(|it| **it != 0).count()
+            );
+
+            for (&slot, &value) in &values {
+                let prev = (value != 0).then(|| U256::from(value));
+                prop_assert_eq!(trie.get_slot(U256::from(slot)), prev);
+                prop_assert_eq!(trie.set_slot(U256::from(slot), U256::zero()).unwrap(), prev);
+            }
+            prop_assert_eq!(trie.root(), EMPTY_TRIE_HASH);
+        }
+
+        #[test]
+        fn trimmed_state_trie_keeps_root(
+            accounts in accounts(),
+            kept in prop::collection::vec(any::<bool>(), 64),
+        ) {
+            let trie = state_trie(&accounts);
+            let kept = accounts
+                .iter()
+                .zip(kept)
+                .filter_map(|(entry, kept)| kept.then_some(entry))
+                .collect::<Vec<_>>();
+            let trimmed = trie
+                .trim_to(kept.iter().map(|(address, _)| TrieKey::from_address(**address)))
+                .unwrap();
+
+            prop_assert_eq!(trimmed.root(), trie.root());
+            for (&address, &account) in &kept {
+                prop_assert_eq!(trimmed.get_by_address(address), Some(account));
+            }
+            // Only the kept accounts remain, the others being hashed out.
+            prop_assert_eq!(trimmed.iter().count(), kept.len());
+        }
+
+        #[test]
+        fn key_prefix_ops(key in key(), other in key(), split in 0..=64usize) {
+            let split = split.min(key.len());
+            let (prefix, suffix) = (key.slice(..split).unwrap(), key.slice(split..).unwrap());
+            prop_assert!(key.starts_with(prefix));
+            prop_assert_eq!(key.strip_prefix(prefix), Some(suffix));
+            prop_assert_eq!(prefix.concat(suffix).unwrap(), key);
+            prop_assert_eq!(key.slice(..=key.len()), None);
+
+            let common = key.common_prefix(other);
+            prop_assert!(key.starts_with(common) && other.starts_with(common));
+            prop_assert_eq!(common, other.common_prefix(key));
+            prop_assert!(common.len() == key.len().min(other.len())
+                || key.components().nth(common.len()) != other.components().nth(common.len()));
+
+            let prefixes = key.prefixes().collect::<Vec<_>>();
+            prop_assert_eq!(prefixes.len(), key.len() + 1);
+            prop_assert!(prefixes.windows(2).all(|pair| pair[0] < pair[1]));
+            prop_assert!(prefixes.iter().all(|it| key.starts_with(*it)));
+
+            let first = key.split_first();
+            prop_assert_eq!(first.is_none(), key.is_empty());
+            if let Some((nibble, rest)) = first {
+                prop_assert_eq!(key.components().next(), Some(nibble));
+                prop_assert_eq!(rest.len(), key.len() - 1);
+            }
+            prop_assert_eq!(TrieKey::from_nibbles(key.into_nibbles()), key);
+        }
+
+        #[test]
+        fn state_trie_serde_round_trip(accounts in accounts()) {
+            let trie = state_trie(&accounts);
+            let json = serde_json::to_string(trie.as_hashed_partial_trie()).unwrap();
+            let round_tripped =
+                StateTrie::from_hashed_partial_trie_unchecked(serde_json::from_str(&json).unwrap());
+
+            prop_assert_eq!(round_tripped.root(), trie.root());
+            prop_assert!(round_tripped.iter().eq(trie.iter()));
+            for account in accounts.values() {
+                prop_assert_eq!(rlp::decode::<AccountRlp>(&rlp::encode(account)), Ok(*account));
+            }
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Validation of a [`BlockTrace`] against the header of its block, before
+//! proving it.
+//!
+//! An incomplete witness or an inconsistent trace otherwise only surfaces
+//! when the kernel hits a hash node or a mismatching root, deep into the
+//! proof of the block. Instead, [`validate`] replays the state diff of the
+//! transactions of the trace on its pre-images, and reports every
+//! [`Discrepancy`] it finds, down to the account or the slot at fault.
+
+use std::collections::{HashMap, HashSet};
+
+use ethereum_types::{Address, H256, U256};
+use mpt_trie::partial_trie::HashedPartialTrie;
+use mpt_trie::special_query::path_for_query;
+use mpt_trie::utils::TrieSegment;
+use zk_evm_common::{AccountRlp, EMPTY_TRIE_HASH};
+
+use crate::typed_mpt::TrieKey;
+use crate::{
+    entrypoint_with_options, hash, process_pre_images, BlockTrace, ContractCodeUsage,
+    DecodingOptions, IntoEthereumTypes, OtherBlockData, PartialTriePreImages, TxnInfo,
+};
+
+/// A discrepancy between a [`BlockTrace`] and the block it describes.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Discrepancy {
+    /// The state trie pre-image is not the state of the parent block.
+    #[error("the state pre-image has root {actual:x}, but the parent block has {expected:x}")]
+    PreStateRoot {
+        /// The state root of the parent block.
+        expected: H256,
+        /// The root of the state trie pre-image.
+        actual: H256,
+    },
+    /// The state after the block is not the one of its header.
+    #[error("the block results in state root {actual:x}, but its header has {expected:x}")]
+    PostStateRoot {
+        /// The state root of the block header.
+        expected: H256,
+        /// The state root after replaying the block.
+        actual: H256,
+    },
+    /// The storage trie pre-image of an account is not its storage.
+    #[error(
+        "the storage pre-image of account {address:x} has root {actual:x}, \
+         but the account has {expected:x}"
+    )]
+    StorageRoot {
+        /// The account.
+        address: Address,
+        /// The storage root of the account.
+        expected: H256,
+        /// The root of the storage trie pre-image.
+        actual: H256,
+    },
+    /// An account touched by a transaction is hashed out of the state
+    /// pre-image.
+    #[error("transaction {txn_ix} touches account {address:x}, which is hashed out")]
+    MissingAccount {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The account.
+        address: Address,
+    },
+    /// The storage of an account accessed by a transaction has no pre-image.
+    #[error("transaction {txn_ix} accesses the storage of account {address:x}, which is missing")]
+    MissingStorageTrie {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The account.
+        address: Address,
+    },
+    /// A storage slot accessed by a transaction is hashed out of the storage
+    /// pre-image of its account.
+    #[error(
+        "transaction {txn_ix} accesses slot {slot:x} of account {address:x}, which is hashed out"
+    )]
+    MissingSlot {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The account.
+        address: Address,
+        /// The slot, before hashing.
+        slot: H256,
+    },
+    /// A transaction decreases the nonce of an account.
+    #[error("transaction {txn_ix} sets the nonce of account {address:x} from {before} to {after}")]
+    WrongNonce {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The account.
+        address: Address,
+        /// The nonce before the transaction.
+        before: U256,
+        /// The nonce after the transaction.
+        after: U256,
+    },
+    /// A transaction reads a code which is not part of the trace.
+    #[error(
+        "transaction {txn_ix} reads code {code_hash:x} of account {address:x}, which is missing"
+    )]
+    MissingCode {
+        /// The index of the transaction in the block.
+        txn_ix: usize,
+        /// The account.
+        address: Address,
+        /// The hash of the missing code.
+        code_hash: H256,
+    },
+    /// The trace could not be decoded into the inputs of the prover.
+    #[error("the trace could not be decoded: {0}")]
+    Decoding(String),
+}
+
+/// Checks a [`BlockTrace`] against the state roots of its block and of the
+/// parent block, returning all the discrepancies found, if any.
+///
+/// The per-account checks replay the traces of the transactions on the
+/// pre-images. The state root after the block is the one computed by the
+/// decoder, which also applies the withdrawals and the system contract updates
+/// of the block.
+///
+/// Errors are only returned for malformed pre-images.
+pub fn validate(
+    trace: &BlockTrace,
+    other: &OtherBlockData,
+    parent_state_root: H256,
+    state_root: H256,
+    options: DecodingOptions,
+) -> anyhow::Result<Vec<Discrepancy>> {
+    let pre_images = process_pre_images(trace.trie_pre_images.clone(), options)?;
+    let codes = trace
+        .code_db
+        .iter()
+        .flat_map(HashMap::values)
+        .chain(
+            pre_images
+                .extra_code_hash_mappings
+                .iter()
+                .flat_map(HashMap::values),
+        )
+        .map(hash)
+        .collect();
+
+    let mut discrepancies =
+        account_discrepancies(&pre_images.tries, codes, &trace.txn_info, parent_state_root);
+
+    let batch_size = trace.txn_info.len().max(1);
+    match entrypoint_with_options(trace.clone(), other.clone(), batch_size, options) {
+        Ok(batches) => {
+            let actual = batches
+                .last()
+                .map(|batch| batch.trie_roots_after.state_root)
+                .unwrap_or(parent_state_root);
+            if actual != state_root {
+                discrepancies.push(Discrepancy::PostStateRoot {
+                    expected: state_root,
+                    actual,
+                })
+            }
+        }
+        Err(e) => discrepancies.push(Discrepancy::Decoding(format!(
+            "{:#}",
+            anyhow::Error::new(e)
+        ))),
+    }
+
+    Ok(discrepancies)
+}
+
+/// Replays the traces of the transactions on the pre-images, reporting the
+/// accounts and slots they are missing, and the inconsistent updates.
+fn account_discrepancies(
+    tries: &PartialTriePreImages,
+    mut codes: HashSet<H256>,
+    txn_info: &[TxnInfo],
+    parent_state_root: H256,
+) -> Vec<Discrepancy> {
+    let mut discrepancies = vec![];
+
+    let actual = tries.state.root();
+    if actual != parent_state_root {
+        discrepancies.push(Discrepancy::PreStateRoot {
+            expected: parent_state_root,
+            actual,
+        });
+    }
+
+    // The accounts updated so far, `None` standing for deleted ones.
+    let mut updated = HashMap::<H256, Option<AccountRlp>>::new();
+    let mut reported = HashSet::new();
+    for (txn_ix, txn) in txn_info.iter().enumerate() {
+        for (&address, trace) in &txn.traces {
+            let address: Address = address.into_ethereum_types();
+            let balance: Option<U256> = trace.balance.into_ethereum_types();
+            let nonce: Option<U256> = trace.nonce.into_ethereum_types();
+            let hashed_address = hash(address);
+            let key = TrieKey::from_hash(hashed_address);
+            if is_hashed_out(tries.state.as_hashed_partial_trie(), key) {
+                discrepancies.push(Discrepancy::MissingAccount { txn_ix, address });
+                continue;
+            }
+            let mut account = match updated.get(&hashed_address) {
+                Some(account) => account.clone(),
+                None => tries.state.get_by_key(key),
+            };
+
+            let slots = trace
+                .storage_read
+                .iter()
+                .flatten()
+                .chain(trace.storage_written.iter().flat_map(HashMap::keys))
+                .map(|&slot| slot.into_ethereum_types())
+                .collect::<HashSet<H256>>();
+            match tries.storage.get(&hashed_address) {
+                // Slots of accounts created by the block are not in the pre-images.
+                _ if updated.contains_key(&hashed_address) => {}
+                Some(storage) => {
+                    if let Some(expected) = account.as_ref().map(|it| it.storage_root) {
+                        let actual = storage.root();
+                        if actual != expected && reported.insert(hashed_address) {
+                            discrepancies.push(Discrepancy::StorageRoot {
+                                address,
+                                expected,
+                                actual,
+                            });
+                        }
+                    }
+                    for slot in slots {
+                        let key = TrieKey::from_hash(hash(slot));
+                        if is_hashed_out(storage.as_hashed_partial_trie(), key) {
+                            discrepancies.push(Discrepancy::MissingSlot {
+                                txn_ix,
+                                address,
+                                slot,
+                            });
+                        }
+                    }
+                }
+                None => {
+                    if !slots.is_empty()
+                        && account
+                            .as_ref()
+                            .is_some_and(|it| it.storage_root != EMPTY_TRIE_HASH)
+                    {
+                        discrepancies.push(Discrepancy::MissingStorageTrie { txn_ix, address });
+                    }
+                }
+            }
+
+            let code_hash = match &trace.code_usage {
+                Some(ContractCodeUsage::Read(code_hash)) => {
+                    let code_hash = code_hash.into_ethereum_types();
+                    if !codes.contains(&code_hash) {
+                        discrepancies.push(Discrepancy::MissingCode {
+                            txn_ix,
+                            address,
+                            code_hash,
+                        });
+                    }
+                    Some(code_hash)
+                }
+                Some(ContractCodeUsage::Write(code)) => {
+                    let code_hash = hash(code);
+                    codes.insert(code_hash);
+                    Some(code_hash)
+                }
+                None => None,
+            };
+
+            if let (Some(after), Some(before)) = (nonce, account.as_ref().map(|it| it.nonce)) {
+                if after < before {
+                    discrepancies.push(Discrepancy::WrongNonce {
+                        txn_ix,
+                        address,
+                        before,
+                        after,
+                    });
+                }
+            }
+
+            if trace.self_destructed.unwrap_or_default() {
+                updated.insert(hashed_address, None);
+            } else if balance.is_some() || nonce.is_some() || code_hash.is_some() {
+                let account = account.get_or_insert_with(AccountRlp::default);
+                account.balance = balance.unwrap_or(account.balance);
+                account.nonce = nonce.unwrap_or(account.nonce);
+                account.code_hash = code_hash.unwrap_or(account.code_hash);
+                updated.insert(hashed_address, Some(account.clone()));
+            }
+        }
+    }
+
+    discrepancies
+}
+
+/// Returns whether the path to `key` in `trie` ends in a hash node.
+fn is_hashed_out(trie: &HashedPartialTrie, key: TrieKey) -> bool {
+    matches!(
+        path_for_query(trie, key.into_nibbles(), true).last(),
+        Some(TrieSegment::Hash)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use mpt_trie::partial_trie::OnOrphanedHashNode;
+
+    use super::*;
+    use crate::typed_mpt::{StateTrie, StorageTrie};
+    use crate::{TxnMeta, TxnTrace};
+
+    #[test]
+    fn reports_account_discrepancies() {
+        let (alice, bob, carol) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let slot = H256::from_low_u64_be(1);
+
+        let mut storage = StorageTrie::new(OnOrphanedHashNode::Reject);
+        storage
+            .insert_hash(TrieKey::from_hash(hash(slot)), H256::repeat_byte(0xaa))
+            .unwrap();
+        let mut state = StateTrie::new(OnOrphanedHashNode::Reject);
+        state
+            .insert_by_address(
+                alice,
+                AccountRlp {
+                    nonce: 5.into(),
+                    storage_root: storage.root(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        state
+            .insert_by_address(
+                bob,
+                AccountRlp {
+                    storage_root: H256::repeat_byte(0xbb),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        state
+            .insert_hash_by_key(TrieKey::from_address(carol), H256::repeat_byte(0xcc))
+            .unwrap();
+        let tries = PartialTriePreImages {
+            state,
+            storage: HashMap::from([(hash(alice), storage)]),
+        };
+
+        let txn = TxnInfo {
+            traces: HashMap::from([
+                (
+                    alice.0.into(),
+                    TxnTrace {
+                        nonce: Some(alloy::primitives::U256::from(3)),
+                        storage_read: Some(vec![slot.0.into()]),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    bob.0.into(),
+                    TxnTrace {
+                        storage_read: Some(vec![slot.0.into()]),
+                        ..Default::default()
+                    },
+                ),
+                (carol.0.into(), TxnTrace::default()),
+            ]),
+            meta: TxnMeta {
+                byte_code: vec![],
+                new_receipt_trie_node_byte: vec![],
+                gas_used: 0,
+            },
+        };
+
+        let parent_state_root = tries.state.root();
+        let mut discrepancies =
+            account_discrepancies(&tries, HashSet::new(), &[txn], parent_state_root);
+        discrepancies.sort_by_key(|it| it.to_string());
+        assert_eq!(
+            discrepancies,
+            vec![
+                Discrepancy::MissingSlot {
+                    txn_ix: 0,
+                    address: alice,
+                    slot
+                },
+                Discrepancy::MissingStorageTrie {
+                    txn_ix: 0,
+                    address: bob
+                },
+                Discrepancy::WrongNonce {
+                    txn_ix: 0,
+                    address: alice,
+                    before: 5.into(),
+                    after: 3.into()
+                },
+                Discrepancy::MissingAccount {
+                    txn_ix: 0,
+                    address: carol
+                },
+            ]
+        );
+    }
+}
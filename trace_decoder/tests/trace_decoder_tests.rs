@@ -20,7 +20,7 @@ use plonky2_maybe_rayon::*;
 use pretty_env_logger::env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
 use prover::BlockProverInput;
 use rstest::rstest;
-use trace_decoder::OtherBlockData;
+use trace_decoder::{DecodeError, OtherBlockData};
 
 type F = GoldilocksField;
 
@@ -100,7 +100,7 @@ fn verify_generation_inputs(
 ) -> anyhow::Result<()> {
     assert!(generation_inputs.len() >= 2);
     assert_eq!(
-        other.checkpoint_state_trie_root,
+        ethereum_types::H256(other.checkpoint_state_trie_root.0),
         generation_inputs
             .first()
             .expect("generation inputs should have first element")
@@ -117,6 +117,16 @@ fn verify_generation_inputs(
                     == inputs[1].tries.transactions_trie.hash()
         })
         .all(|it| it));
+    let batch_roots = trace_decoder::batch_roots(&generation_inputs);
+    assert!(batch_roots
+        .windows(2)
+        .all(|roots| roots[0].txn_range.end == roots[1].txn_range.start));
+    assert_eq!(
+        batch_roots
+            .last()
+            .map(|roots| roots.trie_roots_after.state_root.0),
+        Some(header.state_root.0)
+    );
     let last_generation_input = generation_inputs
         .last()
         .expect("generation inputs should have last element");
@@ -251,7 +261,10 @@ fn test_generation_inputs_consistency(#[case] test_witness_directory: &str) {
             }
         })
         .flatten_ok()
-        .map_ok(|(block_header, block_prover_input)| {
+        .map_ok(|(block_header, mut block_prover_input)| {
+            // Have the decoder check the transactions it inserts against the header.
+            block_prover_input.other_data.b_data.transactions_root =
+                Some(block_header.transactions_root);
             let other_block_data = block_prover_input.other_data.clone();
             // Run trace decoder, create generation inputs for this block
             let block_generation_inputs = decode_generation_inputs(block_prover_input)?;
@@ -267,3 +280,130 @@ fn test_generation_inputs_consistency(#[case] test_witness_directory: &str) {
         }
     });
 }
+
+/// Test that the decoder rejects a trace whose raw transactions don't match
+/// the transactions root of the block header.
+#[test]
+fn test_transactions_root_mismatch() {
+    init_logger();
+
+    let file_path = find_witness_data_files(JERIGON_WITNESS_DIR)
+        .expect("valid json data files found")
+        .remove(0);
+    let mut block_prover_input = read_witness_file(&file_path)
+        .expect("valid witness file")
+        .remove(0);
+    let wrong_root = ethereum_types::H256::repeat_byte(0xab);
+    block_prover_input.other_data.b_data.transactions_root = Some(wrong_root.0.into());
+
+    let err = trace_decoder::entrypoint(
+        block_prover_input.block_trace,
+        block_prover_input.other_data,
+        3,
+    )
+    .expect_err("the decoder accepted a wrong transactions root");
+    let source = match err {
+        DecodeError::AtBlock { source, .. } => source,
+        err => panic!("unexpected error: {err:?}"),
+    };
+    assert!(
+        matches!(
+            *source,
+            DecodeError::TransactionsRoot { expected, .. } if expected == wrong_root
+        ),
+        "unexpected error: {source:?}"
+    );
+}
+
+/// Test that the minimal tries extracted for each batch, whatever the batch
+/// size, are those at the end of the previous batch.
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(100)]
+fn test_minimal_tries_of_each_batch(#[case] batch_size: usize) {
+    init_logger();
+
+    let file_path = find_witness_data_files(JERIGON_WITNESS_DIR)
+        .expect("valid json data files found")
+        .remove(0);
+    let block_prover_input = read_witness_file(&file_path)
+        .expect("valid witness file")
+        .remove(0);
+    let checkpoint_state_trie_root = block_prover_input.other_data.checkpoint_state_trie_root;
+
+    let generation_inputs = trace_decoder::entrypoint(
+        block_prover_input.block_trace,
+        block_prover_input.other_data,
+        batch_size,
+    )
+    .expect("valid block trace");
+
+    assert_eq!(
+        generation_inputs[0].tries.state_trie.hash(),
+        ethereum_types::H256(checkpoint_state_trie_root.0)
+    );
+    for (inputs, batch) in generation_inputs.windows(2).zip(1..) {
+        let (before, after) = (&inputs[0].trie_roots_after, &inputs[1].tries);
+        assert_eq!(after.state_trie.hash(), before.state_root, "batch {batch}");
+        assert_eq!(
+            after.transactions_trie.hash(),
+            before.transactions_root,
+            "batch {batch}"
+        );
+        assert_eq!(
+            after.receipts_trie.hash(),
+            before.receipts_root,
+            "batch {batch}"
+        );
+    }
+}
+
+/// Test that the first batch whose roots diverge from those of a node is
+/// found, with a logarithmic number of comparisons.
+#[test]
+fn test_first_divergent_batch() {
+    init_logger();
+
+    let file_path = find_witness_data_files(JERIGON_WITNESS_DIR)
+        .expect("valid json data files found")
+        .remove(0);
+    let block_prover_input = read_witness_file(&file_path)
+        .expect("valid witness file")
+        .remove(0);
+    let generation_inputs =
+        decode_generation_inputs(block_prover_input).expect("valid block trace");
+    let batch_roots = trace_decoder::batch_roots(&generation_inputs);
+    assert_eq!(batch_roots[0].txn_range.start, 0);
+
+    assert_eq!(
+        trace_decoder::first_divergent_batch(&batch_roots, |_| true),
+        None
+    );
+    for divergent in 0..batch_roots.len() {
+        // The node diverges from the decoded batches from `divergent` onwards.
+        let node_roots = batch_roots
+            .iter()
+            .enumerate()
+            .map(|(i, roots)| {
+                let mut roots = roots.clone();
+                if i >= divergent {
+                    roots.trie_roots_after.state_root = ethereum_types::H256::repeat_byte(0xab);
+                }
+                roots
+            })
+            .collect_vec();
+
+        let mut comparisons = 0;
+        let found = trace_decoder::first_divergent_batch(&batch_roots, |roots| {
+            comparisons += 1;
+            let i = batch_roots
+                .iter()
+                .position(|it| std::ptr::eq(it, roots))
+                .expect("roots of a batch");
+            node_roots[i] == *roots
+        });
+        assert_eq!(found, Some(divergent));
+        assert!(comparisons <= batch_roots.len().ilog2() as usize + 2);
+    }
+}
@@ -26,5 +26,24 @@ fn main() -> anyhow::Result<()> {
         version.minor
     );
 
+    // This build script is shared with the binaries, which do not have a
+    // `grpc` feature: only `zero_bin_common` compiles the service contract.
+    #[cfg(feature = "grpc")]
+    compile_protos()?;
+
+    Ok(())
+}
+
+/// Generates the messages, client and server of the proving service.
+///
+/// Protos are compiled with a vendored `protoc`, so that building does not
+/// require installing it.
+#[cfg(feature = "grpc")]
+fn compile_protos() -> anyhow::Result<()> {
+    const PROTO: &str = "proto/zero/proving/v1/proving.proto";
+
+    println!("cargo::rerun-if-changed={PROTO}");
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure().compile_protos(&[PROTO], &["proto"])?;
     Ok(())
 }
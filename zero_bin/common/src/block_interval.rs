@@ -92,6 +92,23 @@ impl BlockInterval {
         }
     }
 
+    /// Returns the number of the first block of the interval.
+    ///
+    /// Fails for a single block identified by its hash.
+    pub fn get_start_block(&self) -> anyhow::Result<u64> {
+        match self {
+            BlockInterval::SingleBlockId(BlockId::Number(num)) => num
+                .as_number()
+                .ok_or(anyhow!("invalid block number '{num}'")),
+            BlockInterval::SingleBlockId(BlockId::Hash(hash)) => Err(anyhow!(
+                "cannot get the start block number of block hash 0x{}",
+                hex::encode(hash.block_hash)
+            )),
+            BlockInterval::Range(range) => Ok(range.start),
+            BlockInterval::FollowFrom { start_block, .. } => Ok(*start_block),
+        }
+    }
+
     /// Convert the block interval into an async stream of block numbers.
     pub fn into_bounded_stream(self) -> anyhow::Result<impl Stream<Item = u64>> {
         match self {
@@ -261,6 +278,34 @@ mod test {
         assert_eq!(result, Vec::from_iter(1u64..10u64));
     }
 
+    #[test]
+    fn can_get_start_block() {
+        assert_eq!(
+            BlockInterval::new("10..20")
+                .unwrap()
+                .get_start_block()
+                .unwrap(),
+            10
+        );
+        assert_eq!(
+            BlockInterval::new("42").unwrap().get_start_block().unwrap(),
+            42
+        );
+        assert_eq!(
+            BlockInterval::new("100..")
+                .unwrap()
+                .get_start_block()
+                .unwrap(),
+            100
+        );
+        assert!(BlockInterval::new(
+            "0xb51ceca7ba912779ed6721d2b93849758af0d2354683170fb71dead6e439e6cb"
+        )
+        .unwrap()
+        .get_start_block()
+        .is_err());
+    }
+
     #[test]
     fn can_create_from_string() {
         use std::str::FromStr;
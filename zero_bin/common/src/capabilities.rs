@@ -0,0 +1,144 @@
+//! Capability tags routing proving tasks to heterogeneous workers.
+//!
+//! Workers advertise tags such as `gpu` or `high-mem`. Each tag set maps onto
+//! its own task queue, derived from the paladin task bus routing key: a worker
+//! consumes the queue of every subset of its tags, along with the untagged
+//! queue. A leader requiring some tags for segment proofs publishes them to the
+//! queue of exactly those tags, so that they only reach workers holding all of
+//! them, while all other tasks go to the untagged queue.
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// The routing key paladin publishes tasks with when none is configured.
+pub const DEFAULT_TASK_ROUTING_KEY: &str = "task";
+
+/// The maximum number of tags of a worker, which consumes a queue per subset
+/// of its tags.
+pub const MAX_CAPABILITY_TAGS: usize = 4;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CapabilityError {
+    #[error("invalid capability tag '{0}': tags are made of lowercase alphanumerics and '-'")]
+    InvalidTag(String),
+
+    #[error("a worker supports at most {MAX_CAPABILITY_TAGS} capability tags, got {0}")]
+    TooManyTags(usize),
+}
+
+/// A set of capability tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilityTags(BTreeSet<String>);
+
+impl CapabilityTags {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Adds a tag, which must be made of lowercase alphanumerics and `-`.
+    pub fn insert(&mut self, tag: String) {
+        self.0.insert(tag);
+    }
+
+    /// The routing key of the queue holding the tasks requiring exactly these
+    /// tags.
+    pub fn routing_key(&self, base: &str) -> String {
+        self.iter()
+            .fold(base.to_string(), |key, tag| key + "." + tag)
+    }
+
+    /// The routing keys of the queues a worker with these tags consumes, the
+    /// untagged one first.
+    pub fn worker_routing_keys(&self, base: &str) -> Result<Vec<String>, CapabilityError> {
+        if self.0.len() > MAX_CAPABILITY_TAGS {
+            return Err(CapabilityError::TooManyTags(self.0.len()));
+        }
+
+        let tags = self.iter().collect::<Vec<_>>();
+        Ok((0..1usize << tags.len())
+            .map(|subset| {
+                let subset = tags
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| subset & (1 << i) != 0)
+                    .map(|(_, tag)| tag.to_string())
+                    .collect();
+                Self(subset).routing_key(base)
+            })
+            .collect())
+    }
+}
+
+impl FromStr for CapabilityTags {
+    type Err = CapabilityError;
+
+    /// Parses comma-separated tags.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| {
+                if tag
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                {
+                    Ok(tag.to_string())
+                } else {
+                    Err(CapabilityError::InvalidTag(tag.to_string()))
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for CapabilityTags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iter().collect::<Vec<_>>().join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_tags() {
+        let tags: CapabilityTags = "high-mem, gpu,,gpu".parse().unwrap();
+        assert_eq!(tags.to_string(), "gpu,high-mem");
+        assert_eq!(tags.routing_key("task"), "task.gpu.high-mem");
+        assert!("".parse::<CapabilityTags>().unwrap().is_empty());
+        assert_eq!(
+            "GPU".parse::<CapabilityTags>(),
+            Err(CapabilityError::InvalidTag("GPU".to_string()))
+        );
+    }
+
+    #[test]
+    fn workers_consume_all_subsets_of_their_tags() {
+        let tags: CapabilityTags = "gpu,high-mem".parse().unwrap();
+        let keys = tags.worker_routing_keys("task").unwrap();
+        assert_eq!(
+            keys,
+            ["task", "task.gpu", "task.high-mem", "task.gpu.high-mem"]
+        );
+
+        // A leader requiring any of these subsets reaches this worker.
+        for required in ["gpu", "high-mem", "gpu,high-mem"] {
+            let required: CapabilityTags = required.parse().unwrap();
+            assert!(keys.contains(&required.routing_key("task")));
+        }
+
+        let too_many: CapabilityTags = "a,b,c,d,e".parse().unwrap();
+        assert_eq!(
+            too_many.worker_routing_keys("task"),
+            Err(CapabilityError::TooManyTags(5))
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Dictionary-based zstd compression of the payloads exchanged between the
+//! leader and the workers.
+//!
+//! Fleets proving similar blocks send highly redundant witness data over the
+//! broker. Compressing those payloads against a dictionary trained on typical
+//! [`GenerationInputs`](evm_arithmetization::GenerationInputs) and segment
+//! data cuts the bandwidth significantly.
+//!
+//! Dictionaries are trained with the `train-dictionary` command of the leader.
+//! Workers holding a dictionary advertise it as a [capability
+//! tag](crate::capabilities), which a leader compressing payloads requires from
+//! the workers it dispatches them to. Every compressed [`Payload`] is also
+//! tagged with the id of its dictionary, so that a misrouted payload is
+//! refused rather than decoded with the wrong dictionary.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use clap::Args;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::capabilities::CapabilityTags;
+
+/// The help heading for the compression arguments.
+const HEADING: &str = "Payload compression";
+
+/// The default zstd compression level.
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// The default maximum size, in bytes, of a trained dictionary.
+pub const DEFAULT_DICTIONARY_SIZE: usize = 112_640;
+
+/// The default maximum size, in bytes, of a decompressed payload.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 1 << 30;
+
+/// The period at which workers log their compression statistics.
+pub const METRICS_LOG_PERIOD: Duration = Duration::from_secs(300);
+
+static DICTIONARY: OnceLock<CompressionDictionary> = OnceLock::new();
+static MAX_PAYLOAD_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PAYLOAD_SIZE);
+static METRICS: Mutex<BTreeMap<&'static str, CompressionStats>> = Mutex::new(BTreeMap::new());
+
+/// An error type for payload compression.
+#[derive(Error, Debug)]
+pub enum CompressionError {
+    #[error("failed to read compression dictionary '{0}'")]
+    ReadDictionary(PathBuf, #[source] io::Error),
+
+    #[error("failed to train compression dictionary")]
+    TrainDictionary(#[source] io::Error),
+
+    #[error("compression dictionary has no id, which zstd trained dictionaries embed")]
+    MissingDictionaryId,
+
+    #[error("a compression dictionary has already been installed")]
+    DictionaryAlreadySet,
+
+    #[error("payload was compressed with dictionary {expected:?} but {installed:?} is installed")]
+    DictionaryMismatch {
+        expected: u32,
+        installed: Option<u32>,
+    },
+
+    #[error("payload decompresses to {raw_len} bytes, above the maximum of {max}")]
+    PayloadTooLarge { raw_len: usize, max: usize },
+
+    #[error("failed to serialize payload")]
+    Serialization(#[source] ciborium::ser::Error<io::Error>),
+
+    #[error("failed to deserialize payload")]
+    Deserialization(#[source] ciborium::de::Error<io::Error>),
+
+    #[error("zstd failure")]
+    Zstd(#[source] io::Error),
+}
+
+/// A zstd dictionary, identified by the id zstd embeds in it.
+#[derive(Debug, Clone)]
+pub struct CompressionDictionary {
+    id: u32,
+    bytes: Vec<u8>,
+}
+
+impl CompressionDictionary {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, CompressionError> {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(&bytes)
+            .ok_or(CompressionError::MissingDictionaryId)?;
+        Ok(Self {
+            id: id.into(),
+            bytes,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, CompressionError> {
+        fs::read(path)
+            .map_err(|e| CompressionError::ReadDictionary(path.to_path_buf(), e))
+            .and_then(Self::new)
+    }
+
+    /// Trains a dictionary of at most `max_size` bytes over the given
+    /// serialized payload samples.
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Self, CompressionError> {
+        zstd::dict::from_samples(samples, max_size)
+            .map_err(CompressionError::TrainDictionary)
+            .and_then(Self::new)
+    }
+
+    /// Serializes `value` the same way [`Payload`] does, for use as a
+    /// training sample.
+    pub fn sample<T: Serialize>(value: &T) -> Result<Vec<u8>, CompressionError> {
+        serialize(value)
+    }
+
+    /// The id of this dictionary, which the payloads compressed with it are
+    /// tagged with.
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The capability tag of the workers holding this dictionary.
+    pub fn capability_tag(&self) -> String {
+        format!("zstd-dict-{}", self.id)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Installs the process-wide dictionary used to compress and decompress
+/// payloads.
+pub fn set_dictionary(dictionary: CompressionDictionary) -> Result<(), CompressionError> {
+    DICTIONARY
+        .set(dictionary)
+        .map_err(|_| CompressionError::DictionaryAlreadySet)
+}
+
+/// Returns the process-wide dictionary, if any.
+pub fn dictionary() -> Option<&'static CompressionDictionary> {
+    DICTIONARY.get()
+}
+
+/// Sets the maximum size, in bytes, of the payloads [`Payload::into_inner`]
+/// decompresses.
+///
+/// The decompressed size is read from the payload itself, so this bounds the
+/// memory a malformed or malicious payload can make a worker allocate.
+pub fn set_max_payload_size(max: usize) {
+    MAX_PAYLOAD_SIZE.store(max, Ordering::Relaxed);
+}
+
+/// Returns the maximum size, in bytes, of decompressed payloads.
+pub fn max_payload_size() -> usize {
+    MAX_PAYLOAD_SIZE.load(Ordering::Relaxed)
+}
+
+/// Adds the capability tag of the process-wide dictionary, if any, to `tags`.
+///
+/// Workers advertise the dictionary they hold this way, and leaders require it
+/// from the workers they send compressed payloads to.
+pub fn add_dictionary_tag(tags: &mut CapabilityTags) {
+    if let Some(dictionary) = dictionary() {
+        tags.insert(dictionary.capability_tag());
+    }
+}
+
+/// CLI arguments for payload compression.
+#[derive(Args, Clone, Debug)]
+pub struct CliCompressionConfig {
+    /// Path to the zstd dictionary used to compress and decompress task
+    /// payloads. Leader and workers must use the same dictionary.
+    #[arg(long, help_heading = HEADING, env = "ZK_EVM_COMPRESSION_DICTIONARY")]
+    pub compression_dictionary: Option<PathBuf>,
+    /// Maximum size, in bytes, of a decompressed task payload. Larger
+    /// payloads are refused before being decompressed.
+    #[arg(
+        long,
+        help_heading = HEADING,
+        default_value_t = DEFAULT_MAX_PAYLOAD_SIZE,
+        env = "ZK_EVM_MAX_PAYLOAD_SIZE"
+    )]
+    pub max_payload_size: usize,
+}
+
+impl Default for CliCompressionConfig {
+    fn default() -> Self {
+        Self {
+            compression_dictionary: None,
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+}
+
+impl CliCompressionConfig {
+    /// Sets the configured maximum payload size, and loads and installs the
+    /// configured dictionary, if any.
+    pub fn install(&self) -> Result<(), CompressionError> {
+        set_max_payload_size(self.max_payload_size);
+        match &self.compression_dictionary {
+            Some(path) => set_dictionary(CompressionDictionary::from_file(path)?),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The serialized, zstd-compressed form of a value.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressedPayload {
+    /// The id of the dictionary used for compression, if any.
+    pub dictionary_id: Option<u32>,
+    /// The length of the serialized value before compression.
+    pub raw_len: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// A task payload, either sent as is or compressed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Payload<T> {
+    Plain(T),
+    Zstd(CompressedPayload),
+}
+
+impl<T: Serialize + DeserializeOwned> Payload<T> {
+    /// Wraps `value`, compressing it with the installed dictionary if
+    /// `compress` is set.
+    ///
+    /// Falls back to an uncompressed payload if compression fails.
+    pub fn new(value: T, compress: bool) -> Self {
+        if !compress {
+            return Self::Plain(value);
+        }
+
+        match Self::compress(&value, DEFAULT_COMPRESSION_LEVEL) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("failed to compress payload, sending it uncompressed: {e}");
+                Self::Plain(value)
+            }
+        }
+    }
+
+    /// Compresses `value` with the installed dictionary, if any.
+    pub fn compress(value: &T, level: i32) -> Result<Self, CompressionError> {
+        let raw = serialize(value)?;
+        let (dictionary_id, bytes) = match dictionary() {
+            Some(dict) => (
+                Some(dict.id()),
+                zstd::bulk::Compressor::with_dictionary(level, dict.as_bytes())
+                    .and_then(|mut c| c.compress(&raw))
+                    .map_err(CompressionError::Zstd)?,
+            ),
+            None => (
+                None,
+                zstd::bulk::compress(&raw, level).map_err(CompressionError::Zstd)?,
+            ),
+        };
+
+        Ok(Self::Zstd(CompressedPayload {
+            dictionary_id,
+            raw_len: raw.len(),
+            bytes,
+        }))
+    }
+
+    /// Unwraps the payload, recording its compression ratio under `op`.
+    ///
+    /// Payloads claiming to decompress to more than [`max_payload_size`]
+    /// bytes are refused.
+    pub fn into_inner(self, op: &'static str) -> Result<T, CompressionError> {
+        let CompressedPayload {
+            dictionary_id,
+            raw_len,
+            bytes,
+        } = match self {
+            Self::Plain(value) => return Ok(value),
+            Self::Zstd(payload) => payload,
+        };
+
+        let max = max_payload_size();
+        if raw_len > max {
+            return Err(CompressionError::PayloadTooLarge { raw_len, max });
+        }
+
+        let installed = dictionary();
+        let raw = match dictionary_id {
+            Some(expected) => {
+                let dict = installed
+                    .filter(|dict| dict.id() == expected)
+                    .ok_or_else(|| CompressionError::DictionaryMismatch {
+                        expected,
+                        installed: installed.map(CompressionDictionary::id),
+                    })?;
+                zstd::bulk::Decompressor::with_dictionary(dict.as_bytes())
+                    .and_then(|mut d| d.decompress(&bytes, raw_len))
+            }
+            None => zstd::bulk::decompress(&bytes, raw_len),
+        }
+        .map_err(CompressionError::Zstd)?;
+
+        record(op, raw_len, bytes.len());
+        ciborium::from_reader(raw.as_slice()).map_err(CompressionError::Deserialization)
+    }
+}
+
+fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, CompressionError> {
+    let mut raw = Vec::new();
+    ciborium::into_writer(value, &mut raw).map_err(CompressionError::Serialization)?;
+    Ok(raw)
+}
+
+/// Aggregated compression statistics for a single operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub payloads: u64,
+    pub raw_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+impl CompressionStats {
+    /// The overall compression ratio, i.e. `raw_bytes / compressed_bytes`.
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            return 1.0;
+        }
+        self.raw_bytes as f64 / self.compressed_bytes as f64
+    }
+}
+
+fn record(op: &'static str, raw_bytes: usize, compressed_bytes: usize) {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    let stats = metrics.entry(op).or_default();
+    stats.payloads += 1;
+    stats.raw_bytes += raw_bytes as u64;
+    stats.compressed_bytes += compressed_bytes as u64;
+
+    debug!(
+        op,
+        raw_bytes,
+        compressed_bytes,
+        total_ratio = stats.ratio(),
+        "decompressed payload"
+    );
+}
+
+/// Returns the compression statistics recorded so far, per operation.
+pub fn compression_metrics() -> BTreeMap<&'static str, CompressionStats> {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Logs the compression statistics recorded so far, per operation.
+pub fn log_compression_metrics() {
+    for (op, stats) in compression_metrics() {
+        info!(
+            op,
+            payloads = stats.payloads,
+            raw_bytes = stats.raw_bytes,
+            compressed_bytes = stats.compressed_bytes,
+            ratio = stats.ratio(),
+            "payload compression"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip_without_dictionary() {
+        let value = vec![42u64; 1024];
+        let payload = Payload::compress(&value, DEFAULT_COMPRESSION_LEVEL).unwrap();
+        let Payload::Zstd(compressed) = &payload else {
+            panic!("expected a compressed payload");
+        };
+        assert!(compressed.bytes.len() < compressed.raw_len);
+        assert_eq!(payload.into_inner("test").unwrap(), value);
+        assert_eq!(compression_metrics()["test"].payloads, 1);
+    }
+
+    #[test]
+    fn rejects_unknown_dictionary() {
+        let payload = Payload::<Vec<u8>>::Zstd(CompressedPayload {
+            dictionary_id: Some(7),
+            raw_len: 0,
+            bytes: vec![],
+        });
+        assert!(matches!(
+            payload.into_inner("test_mismatch"),
+            Err(CompressionError::DictionaryMismatch {
+                expected: 7,
+                installed: None
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_payload() {
+        let payload = Payload::<Vec<u8>>::Zstd(CompressedPayload {
+            dictionary_id: None,
+            raw_len: max_payload_size() + 1,
+            bytes: vec![],
+        });
+        assert!(matches!(
+            payload.into_inner("test_oversized"),
+            Err(CompressionError::PayloadTooLarge { .. })
+        ));
+    }
+}
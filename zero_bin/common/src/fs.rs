@@ -1,7 +1,80 @@
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 pub fn generate_block_proof_file_name(directory: &Option<&str>, block_height: u64) -> PathBuf {
     let mut path = PathBuf::from(directory.unwrap_or(""));
     path.push(format!("b{}.zkproof", block_height));
     path
 }
+
+/// Parses the block height out of a file name generated by
+/// [`generate_block_proof_file_name`].
+pub fn parse_block_proof_file_name(file_name: &str) -> Option<u64> {
+    file_name
+        .strip_prefix('b')?
+        .strip_suffix(".zkproof")?
+        .parse()
+        .ok()
+}
+
+/// Searches `directory` for the block proof with the highest block height
+/// strictly lower than `block_height`.
+///
+/// Returns the block height of the proof along with its path, if any.
+pub fn find_latest_block_proof_before(
+    directory: &Path,
+    block_height: u64,
+) -> io::Result<Option<(u64, PathBuf)>> {
+    let mut latest = None;
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let Some(height) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_block_proof_file_name)
+        else {
+            continue;
+        };
+
+        if height < block_height && latest.as_ref().map_or(true, |(h, _)| height > *h) {
+            latest = Some((height, path));
+        }
+    }
+
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn can_parse_block_proof_file_name() {
+        let path = generate_block_proof_file_name(&None, 1234);
+        assert_eq!(
+            parse_block_proof_file_name(path.to_str().unwrap()),
+            Some(1234)
+        );
+        assert_eq!(parse_block_proof_file_name("b12.json"), None);
+        assert_eq!(parse_block_proof_file_name("bx.zkproof"), None);
+    }
+
+    #[test]
+    fn can_find_latest_block_proof_before() {
+        let dir = std::env::temp_dir().join(format!("zero_bin_fs_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for height in [3, 7, 12] {
+            fs::write(generate_block_proof_file_name(&dir.to_str(), height), b"{}").unwrap();
+        }
+        fs::write(dir.join("b9.json"), b"{}").unwrap();
+
+        assert_eq!(
+            find_latest_block_proof_before(&dir, 10).unwrap(),
+            Some((7, generate_block_proof_file_name(&dir.to_str(), 7)))
+        );
+        assert_eq!(find_latest_block_proof_before(&dir, 3).unwrap(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+//! The gRPC contract of the remote proving service, served by the leader.
+//!
+//! Block inputs and proofs are carried as JSON, in the formats the leader
+//! reads and writes in its other modes. See
+//! `proto/zero/proving/v1/proving.proto` for the service definition.
+//!
+//! ```no_run
+//! # async fn run(prover_input: Vec<u8>) -> anyhow::Result<()> {
+//! use zero_bin_common::grpc::{proto::SubmitBlockRequest, ProvingServiceClient};
+//!
+//! let mut client = ProvingServiceClient::connect("http://localhost:50051").await?;
+//! let block_number = client
+//!     .submit_block(SubmitBlockRequest {
+//!         prover_input,
+//!         previous_proof: None,
+//!     })
+//!     .await?
+//!     .into_inner()
+//!     .block_number;
+//! # Ok(())
+//! # }
+//! ```
+
+/// The messages of the service, generated from its protobuf definition.
+#[allow(clippy::all, missing_docs)]
+pub mod proto {
+    tonic::include_proto!("zero.proving.v1");
+}
+
+pub use proto::proving_service_client::ProvingServiceClient;
+pub use proto::proving_service_server::{ProvingService, ProvingServiceServer};
+
+/// The port the leader serves the proving service on by default.
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
@@ -1,6 +1,16 @@
+#[cfg(feature = "alloy")]
 pub mod block_interval;
+pub mod capabilities;
+pub mod compression;
 pub mod debug_utils;
 pub mod fs;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod memory;
+pub mod op_stack;
 pub mod parsing;
+#[cfg(feature = "postgres")]
+pub mod proof_store;
 pub mod prover_state;
+pub mod tenant;
 pub mod version;
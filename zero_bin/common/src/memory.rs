@@ -0,0 +1,187 @@
+//! Memory watermarks of the operations run by the workers.
+//!
+//! Each operation reports how much the resident set size (RSS) of its worker
+//! grew while it ran, and the leader aggregates these reports per block, so
+//! that operators can right-size the memory of their workers and spot leaks
+//! over long runs.
+//!
+//! Watermarks are only measured on Linux, from `/proc/self/status`. They are
+//! process-wide, so they are approximate when a worker runs several operations
+//! concurrently.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+static METRICS: Mutex<BTreeMap<u64, MemoryStats>> = Mutex::new(BTreeMap::new());
+
+/// The memory used by an operation, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryWatermark {
+    /// The RSS of the worker when the operation started.
+    pub rss_before: u64,
+    /// The peak RSS of the worker while the operation ran.
+    pub peak_rss: u64,
+}
+
+impl MemoryWatermark {
+    /// How much the RSS of the worker grew at its peak.
+    pub fn peak_delta(&self) -> u64 {
+        self.peak_rss.saturating_sub(self.rss_before)
+    }
+}
+
+/// The output of an operation, along with its memory watermark, if it could be
+/// measured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measured<T> {
+    pub output: T,
+    pub memory: Option<MemoryWatermark>,
+}
+
+/// Runs `f`, measuring the memory watermark of the current process meanwhile.
+pub fn measure<T>(f: impl FnOnce() -> T) -> Measured<T> {
+    let rss_before = reset_peak_rss();
+    let output = f();
+    let memory = rss_before.and_then(|rss_before| {
+        Some(MemoryWatermark {
+            rss_before,
+            peak_rss: read_status("VmHWM")?,
+        })
+    });
+    if let Some(memory) = memory {
+        debug!(
+            rss_before = memory.rss_before,
+            peak_rss = memory.peak_rss,
+            "operation memory watermark"
+        );
+    }
+    Measured { output, memory }
+}
+
+/// Resets the peak RSS of the current process to its current RSS, which is
+/// returned.
+fn reset_peak_rss() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    // See `proc(5)`: writing 5 to `clear_refs` resets the peak RSS.
+    std::fs::write("/proc/self/clear_refs", "5").ok()?;
+    read_status("VmRSS")
+}
+
+/// Reads a memory `field` of `/proc/self/status`, in bytes.
+fn read_status(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_status(&status, field)
+}
+
+fn parse_status(status: &str, field: &str) -> Option<u64> {
+    let value = status.lines().find_map(|line| {
+        line.strip_prefix(field)
+            .and_then(|rest| rest.strip_prefix(':'))
+    })?;
+    let kb = value
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// The memory watermarks reported for the operations of a block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// The number of operations which reported a watermark.
+    pub ops: u64,
+    /// The largest peak RSS growth of an operation, in bytes.
+    pub max_peak_delta: u64,
+    /// The largest peak RSS of a worker, in bytes.
+    pub max_peak_rss: u64,
+    /// The sum of the peak RSS growths of the operations, in bytes.
+    pub total_peak_delta: u64,
+}
+
+impl MemoryStats {
+    /// The mean peak RSS growth of an operation, in bytes.
+    pub fn mean_peak_delta(&self) -> u64 {
+        self.total_peak_delta
+            .checked_div(self.ops)
+            .unwrap_or_default()
+    }
+
+    fn add(&mut self, memory: MemoryWatermark) {
+        self.ops += 1;
+        self.max_peak_delta = self.max_peak_delta.max(memory.peak_delta());
+        self.max_peak_rss = self.max_peak_rss.max(memory.peak_rss);
+        self.total_peak_delta += memory.peak_delta();
+    }
+}
+
+/// Records the watermark reported by an operation of block `block_number`.
+pub fn record(block_number: u64, memory: MemoryWatermark) {
+    METRICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(block_number)
+        .or_default()
+        .add(memory);
+}
+
+/// Returns the memory statistics recorded so far, per block.
+pub fn memory_metrics() -> BTreeMap<u64, MemoryStats> {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Removes and returns the memory statistics recorded for `block_number`.
+pub fn take_block_memory(block_number: u64) -> Option<MemoryStats> {
+    METRICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&block_number)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_status_fields() {
+        let status = "Name:\tworker\nVmHWM:\t  204800 kB\nVmRSS:\t  102400 kB\n";
+        assert_eq!(parse_status(status, "VmRSS"), Some(100 << 20));
+        assert_eq!(parse_status(status, "VmHWM"), Some(200 << 20));
+        assert_eq!(parse_status(status, "VmSwap"), None);
+    }
+
+    #[test]
+    fn stats_aggregate_watermarks() {
+        record(
+            u64::MAX,
+            MemoryWatermark {
+                rss_before: 10,
+                peak_rss: 30,
+            },
+        );
+        record(
+            u64::MAX,
+            MemoryWatermark {
+                rss_before: 20,
+                peak_rss: 60,
+            },
+        );
+        let stats = take_block_memory(u64::MAX).unwrap();
+        assert_eq!(
+            stats,
+            MemoryStats {
+                ops: 2,
+                max_peak_delta: 40,
+                max_peak_rss: 60,
+                total_peak_delta: 60,
+            }
+        );
+        assert_eq!(stats.mean_peak_delta(), 30);
+        assert_eq!(take_block_memory(u64::MAX), None);
+    }
+}
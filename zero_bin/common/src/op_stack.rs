@@ -0,0 +1,162 @@
+//! Settlement of block proofs on OP-stack style chains.
+//!
+//! OP-stack chains settle on L1 through output roots, which commit to the
+//! state of the L2 at a given block:
+//!
+//! ```text
+//! output_root = keccak256(version ++ state_root ++ withdrawal_storage_root ++ block_hash)
+//! ```
+//!
+//! where `withdrawal_storage_root` is the storage root of the
+//! `L2ToL1MessagePasser` predeploy. This module maps the public values of a
+//! final block proof onto such an output root, and encodes the call creating
+//! the corresponding dispute game on the L1 `DisputeGameFactory`.
+use ethereum_types::{H160, H256, U256};
+use evm_arithmetization::{
+    fixed_recursive_verifier::extract_block_public_values,
+    proof::{PublicValues, PublicValuesError},
+};
+use keccak_hash::keccak;
+use proof_gen::proof_types::GeneratedBlockProof;
+use serde::{Deserialize, Serialize};
+
+/// The version of the output roots computed by this module.
+pub const OUTPUT_ROOT_VERSION: H256 = H256::zero();
+
+/// The address of the `L2ToL1MessagePasser` predeploy, whose storage root is
+/// part of the output root.
+pub const L2_TO_L1_MESSAGE_PASSER: H160 = H160([
+    0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x16,
+]);
+
+/// The signature of `DisputeGameFactory.create`.
+const CREATE_GAME_SIGNATURE: &str = "create(uint32,bytes32,bytes)";
+
+/// The preimage of an output root.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputRootProof {
+    pub version: H256,
+    pub state_root: H256,
+    pub withdrawal_storage_root: H256,
+    pub latest_block_hash: H256,
+}
+
+impl OutputRootProof {
+    /// Builds the output root preimage of the block proved with the given
+    /// public values. The storage root of the `L2ToL1MessagePasser` is not
+    /// part of the public values and must be provided.
+    pub fn from_public_values(public_values: &PublicValues, withdrawal_storage_root: H256) -> Self {
+        Self {
+            version: OUTPUT_ROOT_VERSION,
+            state_root: public_values.trie_roots_after.state_root,
+            withdrawal_storage_root,
+            latest_block_hash: public_values.block_hashes.cur_hash,
+        }
+    }
+
+    pub fn output_root(&self) -> H256 {
+        let mut preimage = Vec::with_capacity(4 * 32);
+        preimage.extend_from_slice(self.version.as_bytes());
+        preimage.extend_from_slice(self.state_root.as_bytes());
+        preimage.extend_from_slice(self.withdrawal_storage_root.as_bytes());
+        preimage.extend_from_slice(self.latest_block_hash.as_bytes());
+        keccak(preimage)
+    }
+}
+
+/// The claim settling a block proof on L1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SettlementClaim {
+    pub l2_block_number: u64,
+    pub output_root_proof: OutputRootProof,
+    pub output_root: H256,
+}
+
+impl SettlementClaim {
+    pub fn new(public_values: &PublicValues, withdrawal_storage_root: H256) -> Self {
+        let output_root_proof =
+            OutputRootProof::from_public_values(public_values, withdrawal_storage_root);
+        Self {
+            l2_block_number: public_values.block_metadata.block_number.low_u64(),
+            output_root: output_root_proof.output_root(),
+            output_root_proof,
+        }
+    }
+
+    /// Builds the claim of a final block proof.
+    pub fn from_block_proof(
+        proof: &GeneratedBlockProof,
+        withdrawal_storage_root: H256,
+    ) -> Result<Self, PublicValuesError> {
+        let public_values = PublicValues::from_public_inputs(extract_block_public_values(
+            &proof.intern.public_inputs,
+        ))?;
+        Ok(Self::new(&public_values, withdrawal_storage_root))
+    }
+
+    /// Encodes the call to `DisputeGameFactory.create(GameType, Claim, bytes)`
+    /// creating a dispute game of type `game_type` for this claim. The extra
+    /// data of the game is the ABI-encoded L2 block number.
+    pub fn create_game_calldata(&self, game_type: u32) -> Vec<u8> {
+        let mut calldata = Vec::with_capacity(4 + 5 * 32);
+        calldata.extend_from_slice(&keccak(CREATE_GAME_SIGNATURE).as_bytes()[..4]);
+        calldata.extend_from_slice(&abi_word(game_type.into()));
+        calldata.extend_from_slice(self.output_root.as_bytes());
+        // Offset of the dynamic `extraData` argument, after the 3 head words.
+        calldata.extend_from_slice(&abi_word((3 * 32).into()));
+        calldata.extend_from_slice(&abi_word(32.into()));
+        calldata.extend_from_slice(&abi_word(self.l2_block_number.into()));
+        calldata
+    }
+}
+
+fn abi_word(value: U256) -> [u8; 32] {
+    let mut word = [0; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn create_game_calldata_layout() {
+        let claim = SettlementClaim {
+            l2_block_number: 0x1234,
+            output_root_proof: OutputRootProof {
+                version: OUTPUT_ROOT_VERSION,
+                state_root: H256::repeat_byte(1),
+                withdrawal_storage_root: H256::repeat_byte(2),
+                latest_block_hash: H256::repeat_byte(3),
+            },
+            output_root: H256::repeat_byte(4),
+        };
+
+        let calldata = claim.create_game_calldata(1);
+        assert_eq!(calldata.len(), 4 + 5 * 32);
+        // `create(uint32,bytes32,bytes)`
+        assert_eq!(calldata[..4], [0x82, 0xec, 0xf2, 0xf6]);
+        assert_eq!(U256::from_big_endian(&calldata[4..36]), 1.into());
+        assert_eq!(calldata[36..68], [4; 32]);
+        assert_eq!(U256::from_big_endian(&calldata[68..100]), 96.into());
+        assert_eq!(U256::from_big_endian(&calldata[100..132]), 32.into());
+        assert_eq!(U256::from_big_endian(&calldata[132..164]), 0x1234.into());
+    }
+
+    #[test]
+    fn output_root_hashes_its_preimage() {
+        let proof = OutputRootProof {
+            version: OUTPUT_ROOT_VERSION,
+            state_root: H256::repeat_byte(1),
+            withdrawal_storage_root: H256::repeat_byte(2),
+            latest_block_hash: H256::repeat_byte(3),
+        };
+        let mut preimage = vec![0; 32];
+        preimage.extend([1; 32]);
+        preimage.extend([2; 32]);
+        preimage.extend([3; 32]);
+        assert_eq!(proof.output_root(), keccak(preimage));
+    }
+}
@@ -0,0 +1,243 @@
+//! A PostgreSQL-backed persistence layer for block proofs.
+//!
+//! Proofs are stored along with their decoded public values and some proving
+//! metadata, and are indexed by chain id and block number so that proving
+//! farms can serve proof queries without scanning a proof directory.
+use evm_arithmetization::{
+    fixed_recursive_verifier::extract_block_public_values,
+    proof::{PublicValues, PublicValuesError},
+};
+use proof_gen::proof_types::GeneratedBlockProof;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio_postgres::{types::Json, Client, NoTls, Row};
+use tracing::{debug, error};
+
+use crate::prover_state::persistence::CIRCUIT_VERSION;
+
+const CREATE_TABLE: &str = "
+    CREATE TABLE IF NOT EXISTS block_proofs (
+        chain_id BIGINT NOT NULL,
+        block_number BIGINT NOT NULL,
+        proof BYTEA NOT NULL,
+        public_values JSONB NOT NULL,
+        metadata JSONB NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+        PRIMARY KEY (chain_id, block_number)
+    )";
+
+const UPSERT_PROOF: &str = "
+    INSERT INTO block_proofs (chain_id, block_number, proof, public_values, metadata)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (chain_id, block_number) DO UPDATE SET
+        proof = EXCLUDED.proof,
+        public_values = EXCLUDED.public_values,
+        metadata = EXCLUDED.metadata,
+        created_at = now()";
+
+const SELECT_PROOF: &str = "
+    SELECT proof, public_values, metadata FROM block_proofs
+    WHERE chain_id = $1 AND block_number = $2";
+
+const SELECT_LATEST_BLOCK_NUMBER: &str = "
+    SELECT MAX(block_number) FROM block_proofs WHERE chain_id = $1";
+
+/// An error type for the proof store.
+#[derive(Error, Debug)]
+pub enum ProofStoreError {
+    #[error("database error")]
+    Database(#[from] tokio_postgres::Error),
+
+    #[error("failed to (de)serialize block proof")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("value {0} does not fit in a BIGINT column")]
+    OutOfRange(String),
+
+    #[error("invalid block proof public values")]
+    PublicValues(#[from] PublicValuesError),
+}
+
+/// Metadata recorded alongside each stored proof.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProvingMetadata {
+    /// The version of the circuits the proof was generated with.
+    pub circuit_version: String,
+    /// The maximum number of transactions per batch.
+    pub batch_size: usize,
+    /// The log of the maximum CPU length of a segment.
+    pub max_cpu_len_log: usize,
+}
+
+impl ProvingMetadata {
+    pub fn new(batch_size: usize, max_cpu_len_log: usize) -> Self {
+        Self {
+            circuit_version: CIRCUIT_VERSION.clone(),
+            batch_size,
+            max_cpu_len_log,
+        }
+    }
+}
+
+/// A block proof retrieved from the store.
+#[derive(Debug, Clone)]
+pub struct StoredBlockProof {
+    pub proof: GeneratedBlockProof,
+    pub public_values: PublicValues,
+    pub metadata: ProvingMetadata,
+}
+
+/// A proof store backed by a PostgreSQL database.
+pub struct PostgresProofStore {
+    client: Client,
+}
+
+impl PostgresProofStore {
+    /// Connects to the database at `url`, creating the proof table if needed.
+    pub async fn connect(url: &str) -> Result<Self, ProofStoreError> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("proof store connection error: {e}");
+            }
+        });
+
+        client.batch_execute(CREATE_TABLE).await?;
+
+        Ok(Self { client })
+    }
+
+    /// Stores `proof`, replacing any proof previously stored for the same
+    /// chain and block. The chain id is read from the proof public values.
+    pub async fn put(
+        &self,
+        proof: &GeneratedBlockProof,
+        metadata: &ProvingMetadata,
+    ) -> Result<(), ProofStoreError> {
+        let public_values = PublicValues::from_public_inputs(extract_block_public_values(
+            &proof.intern.public_inputs,
+        ))?;
+        let block_chain_id = public_values.block_metadata.block_chain_id;
+        let chain_id = u64::try_from(block_chain_id)
+            .map_err(|_| ProofStoreError::OutOfRange(block_chain_id.to_string()))
+            .and_then(to_bigint)?;
+        let block_number = to_bigint(proof.b_height)?;
+
+        self.client
+            .execute(
+                UPSERT_PROOF,
+                &[
+                    &chain_id,
+                    &block_number,
+                    &serde_json::to_vec(proof)?,
+                    &Json(&public_values),
+                    &Json(metadata),
+                ],
+            )
+            .await?;
+        debug!("stored proof of block {block_number} for chain {chain_id}");
+
+        Ok(())
+    }
+
+    /// Retrieves the proof of `block_number` on chain `chain_id`, if any.
+    pub async fn get(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+    ) -> Result<Option<StoredBlockProof>, ProofStoreError> {
+        self.client
+            .query_opt(
+                SELECT_PROOF,
+                &[&to_bigint(chain_id)?, &to_bigint(block_number)?],
+            )
+            .await?
+            .map(|row| decode_row(&row))
+            .transpose()
+    }
+
+    /// Returns the highest block number with a stored proof on chain
+    /// `chain_id`, if any.
+    pub async fn latest_block_number(&self, chain_id: u64) -> Result<Option<u64>, ProofStoreError> {
+        let row = self
+            .client
+            .query_one(SELECT_LATEST_BLOCK_NUMBER, &[&to_bigint(chain_id)?])
+            .await?;
+        Ok(row.get::<_, Option<i64>>(0).map(|n| n as u64))
+    }
+}
+
+fn decode_row(row: &Row) -> Result<StoredBlockProof, ProofStoreError> {
+    let proof: Vec<u8> = row.get(0);
+    let Json(public_values) = row.get(1);
+    let Json(metadata) = row.get(2);
+
+    Ok(StoredBlockProof {
+        proof: serde_json::from_slice(&proof)?,
+        public_values,
+        metadata,
+    })
+}
+
+fn to_bigint(value: u64) -> Result<i64, ProofStoreError> {
+    i64::try_from(value).map_err(|_| ProofStoreError::OutOfRange(value.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use evm_arithmetization::proof::PublicValuesTarget;
+    use plonky2::field::types::Field as _;
+    use proof_gen::{proof_gen::dummy_proof, types::Field};
+
+    use super::*;
+
+    /// The proof of `block_number` of a dummy circuit, whose public inputs
+    /// decode to the default public values.
+    fn block_proof(block_number: u64) -> GeneratedBlockProof {
+        let mut intern = dummy_proof().unwrap();
+        intern.public_inputs = vec![Field::ZERO; PublicValuesTarget::SIZE];
+        GeneratedBlockProof {
+            b_height: block_number,
+            intern,
+        }
+    }
+
+    #[test]
+    fn rejects_values_above_bigint_range() {
+        assert_eq!(to_bigint(i64::MAX as u64).unwrap(), i64::MAX);
+        assert!(matches!(
+            to_bigint(i64::MAX as u64 + 1),
+            Err(ProofStoreError::OutOfRange(_))
+        ));
+    }
+
+    /// Runs against the PostgreSQL database at `PROOF_STORE_TEST_URL`, whose
+    /// proofs of chain 0 are overwritten.
+    #[tokio::test]
+    #[ignore]
+    async fn stores_and_replaces_proofs() -> anyhow::Result<()> {
+        let url = std::env::var("PROOF_STORE_TEST_URL")?;
+        let store = PostgresProofStore::connect(&url).await?;
+        let metadata = ProvingMetadata::new(8, 20);
+
+        for block_number in [1, 2] {
+            store.put(&block_proof(block_number), &metadata).await?;
+        }
+        assert!(store.latest_block_number(0).await?.unwrap() >= 2);
+
+        let stored = store.get(0, 2).await?.expect("the proof should be stored");
+        assert_eq!(stored.proof.b_height, 2);
+        assert_eq!(
+            stored.public_values,
+            PublicValues::from_public_inputs(&block_proof(2).intern.public_inputs)?
+        );
+        assert_eq!(stored.metadata, metadata);
+
+        // Storing a proof of the same block replaces the previous one.
+        let metadata = ProvingMetadata::new(16, 22);
+        store.put(&block_proof(2), &metadata).await?;
+        assert_eq!(store.get(0, 2).await?.unwrap().metadata, metadata);
+
+        Ok(())
+    }
+}
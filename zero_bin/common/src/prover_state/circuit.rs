@@ -1,19 +1,67 @@
 //! [`AllRecursiveCircuits`] dynamic circuit configuration.
 use std::{
+    collections::BTreeMap,
     fmt::Display,
+    io,
     ops::{Deref, Range},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
-use evm_arithmetization::{AllStark, StarkConfig};
+use evm_arithmetization::{
+    all_stark::Table, AllStark, AllStarkConfig, SecurityPreset, StarkConfig,
+};
 use proof_gen::types::AllRecursiveCircuits;
+use thiserror::Error;
 
 use crate::parsing::{parse_range_exclusive, RangeParseError};
 
 /// Number of tables defined in plonky2.
 ///
 /// TODO: This should be made public in the evm_arithmetization crate.
-pub(crate) const NUM_TABLES: usize = 9;
+pub(crate) const NUM_TABLES: usize = 11;
+
+/// The maximum degree bits of a table: its low-degree extension, with the
+/// rate of the standard fast config, must fit in the two-adic subgroup of the
+/// Goldilocks field, of order 2^32.
+pub const MAX_DEGREE_BITS: usize = 30;
+
+/// An error type for circuit configurations.
+#[derive(Error, Debug)]
+pub enum CircuitConfigError {
+    #[error("the {circuit} circuit range {range} is empty")]
+    EmptyRange {
+        circuit: Circuit,
+        range: CircuitSize,
+    },
+
+    #[error(
+        "the {circuit} circuit range {range} exceeds the maximum of {MAX_DEGREE_BITS} degree bits"
+    )]
+    RangeTooLarge {
+        circuit: Circuit,
+        range: CircuitSize,
+    },
+
+    #[error("the {circuit} table has {degree_bits} degree bits, outside of the configured range {range}")]
+    TraceOutOfRange {
+        circuit: Circuit,
+        degree_bits: usize,
+        range: CircuitSize,
+    },
+
+    #[error("failed to read circuit config file {0:?}")]
+    ReadFile(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse circuit config file {0:?}")]
+    ParseFile(PathBuf, #[source] toml::de::Error),
+
+    #[error("unknown circuit {0:?} in circuit config file")]
+    UnknownCircuit(String),
+
+    #[error("invalid range for the {0} circuit: {1}")]
+    InvalidRange(Circuit, RangeParseError<usize>),
+}
 
 /// New type wrapper for [`Range`] that implements [`FromStr`] and [`Display`].
 ///
@@ -68,6 +116,8 @@ pub enum Circuit {
     Memory,
     MemoryBefore,
     MemoryAfter,
+    RangeCheck,
+    Ec,
 }
 
 impl Display for Circuit {
@@ -80,7 +130,7 @@ impl Circuit {
     /// Get the default size for the circuit.
     pub const fn default_size(&self) -> Range<usize> {
         match self {
-            Circuit::Arithmetic => 16..23,
+            Circuit::Arithmetic => 9..23,
             Circuit::BytePacking => 9..21,
             Circuit::Cpu => 12..25,
             Circuit::Keccak => 14..20,
@@ -89,6 +139,8 @@ impl Circuit {
             Circuit::Memory => 17..28,
             Circuit::MemoryBefore => 7..23,
             Circuit::MemoryAfter => 7..27,
+            Circuit::RangeCheck => 16..17,
+            Circuit::Ec => 4..16,
         }
     }
 
@@ -104,6 +156,8 @@ impl Circuit {
             Circuit::Memory => "MEMORY_CIRCUIT_SIZE",
             Circuit::MemoryBefore => "MEMORY_BEFORE_CIRCUIT_SIZE",
             Circuit::MemoryAfter => "MEMORY_AFTER_CIRCUIT_SIZE",
+            Circuit::RangeCheck => "RANGE_CHECK_CIRCUIT_SIZE",
+            Circuit::Ec => "EC_CIRCUIT_SIZE",
         }
     }
 
@@ -119,6 +173,26 @@ impl Circuit {
             Circuit::Memory => "memory",
             Circuit::MemoryBefore => "memory before",
             Circuit::MemoryAfter => "memory after",
+            Circuit::RangeCheck => "range check",
+            Circuit::Ec => "ec",
+        }
+    }
+
+    /// Get the key of the circuit in circuit config files, which matches the
+    /// name of its CLI argument.
+    pub const fn as_config_key(&self) -> &'static str {
+        match self {
+            Circuit::Arithmetic => "arithmetic",
+            Circuit::BytePacking => "byte_packing",
+            Circuit::Cpu => "cpu",
+            Circuit::Keccak => "keccak",
+            Circuit::KeccakSponge => "keccak_sponge",
+            Circuit::Logic => "logic",
+            Circuit::Memory => "memory",
+            Circuit::MemoryBefore => "mem_before",
+            Circuit::MemoryAfter => "mem_after",
+            Circuit::RangeCheck => "range_check",
+            Circuit::Ec => "ec",
         }
     }
 
@@ -134,6 +208,8 @@ impl Circuit {
             Circuit::Memory => "m",
             Circuit::MemoryBefore => "m_b",
             Circuit::MemoryAfter => "m_a",
+            Circuit::RangeCheck => "rc",
+            Circuit::Ec => "ec",
         }
     }
 }
@@ -150,14 +226,36 @@ impl From<usize> for Circuit {
             6 => Circuit::Memory,
             7 => Circuit::MemoryBefore,
             8 => Circuit::MemoryAfter,
+            9 => Circuit::RangeCheck,
+            10 => Circuit::Ec,
             _ => unreachable!(),
         }
     }
 }
 
+impl From<Circuit> for Table {
+    fn from(item: Circuit) -> Self {
+        match item {
+            Circuit::Arithmetic => Table::Arithmetic,
+            Circuit::BytePacking => Table::BytePacking,
+            Circuit::Cpu => Table::Cpu,
+            Circuit::Keccak => Table::Keccak,
+            Circuit::KeccakSponge => Table::KeccakSponge,
+            Circuit::Logic => Table::Logic,
+            Circuit::Memory => Table::Memory,
+            Circuit::MemoryBefore => Table::MemBefore,
+            Circuit::MemoryAfter => Table::MemAfter,
+            Circuit::RangeCheck => Table::RangeCheck,
+            Circuit::Ec => Table::Ec,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CircuitConfig {
     circuits: [Range<usize>; NUM_TABLES],
+    /// The FRI security preset of the STARK proofs the circuits verify.
+    security: SecurityPreset,
 }
 
 impl std::ops::Index<usize> for CircuitConfig {
@@ -189,7 +287,10 @@ impl Default for CircuitConfig {
                 Circuit::Memory.default_size(),
                 Circuit::MemoryBefore.default_size(),
                 Circuit::MemoryAfter.default_size(),
+                Circuit::RangeCheck.default_size(),
+                Circuit::Ec.default_size(),
             ],
+            security: SecurityPreset::default(),
         }
     }
 }
@@ -213,11 +314,86 @@ impl CircuitConfig {
         self.circuits[key as usize] = size.into();
     }
 
+    pub const fn security(&self) -> SecurityPreset {
+        self.security
+    }
+
+    pub fn set_security(&mut self, security: SecurityPreset) {
+        self.security = security;
+    }
+
+    /// The configuration of the STARK proofs, following the security preset.
+    pub fn stark_config(&self) -> StarkConfig {
+        self.security.stark_config()
+    }
+
+    /// The configuration of the STARK proofs of all tables, which share the
+    /// one of the security preset, and of the recursion circuits.
+    pub fn all_stark_config(&self) -> AllStarkConfig {
+        self.security.all_stark_config()
+    }
+
     /// Get all circuits specified in the config.
     pub const fn as_degree_bits_ranges(&self) -> &[Range<usize>; NUM_TABLES] {
         &self.circuits
     }
 
+    /// Reads the circuit sizes from a TOML file mapping circuit keys (see
+    /// [`Circuit::as_config_key`]) to ranges, e.g. `cpu = "12..25"`. Circuits
+    /// missing from the file keep their current size.
+    pub fn update_from_file(&mut self, path: &Path) -> Result<(), CircuitConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| CircuitConfigError::ReadFile(path.to_path_buf(), e))?;
+        let sizes: BTreeMap<String, String> = toml::from_str(&contents)
+            .map_err(|e| CircuitConfigError::ParseFile(path.to_path_buf(), e))?;
+
+        for (key, range) in sizes {
+            let circuit = (0..NUM_TABLES)
+                .map(Circuit::from)
+                .find(|circuit| circuit.as_config_key() == key)
+                .ok_or(CircuitConfigError::UnknownCircuit(key))?;
+            let size = CircuitSize::from_str(&range)
+                .map_err(|e| CircuitConfigError::InvalidRange(circuit, e))?;
+            self.set_circuit_size(circuit, size);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every range is non-empty and within
+    /// [`MAX_DEGREE_BITS`].
+    pub fn validate(&self) -> Result<(), CircuitConfigError> {
+        for (circuit, range) in self.enumerate() {
+            let range = CircuitSize(range.clone());
+            if range.is_empty() {
+                return Err(CircuitConfigError::EmptyRange { circuit, range });
+            }
+            if range.end > MAX_DEGREE_BITS + 1 {
+                return Err(CircuitConfigError::RangeTooLarge { circuit, range });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that tables of the given degree bits, as returned by
+    /// [`AllProof::degree_bits`](evm_arithmetization::proof::AllProof::degree_bits),
+    /// fit in the configured ranges.
+    pub fn check_degree_bits(
+        &self,
+        degree_bits: &[usize; NUM_TABLES],
+    ) -> Result<(), CircuitConfigError> {
+        for ((circuit, range), &degree_bits) in self.enumerate().zip(degree_bits) {
+            if !range.contains(&degree_bits) {
+                return Err(CircuitConfigError::TraceOutOfRange {
+                    circuit,
+                    degree_bits,
+                    range: CircuitSize(range.clone()),
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Get a unique string representation of the config.
     pub fn get_configuration_digest(&self) -> String {
         self.enumerate()
@@ -238,7 +414,7 @@ impl CircuitConfig {
         AllRecursiveCircuits::new(
             &AllStark::default(),
             self.as_degree_bits_ranges(),
-            &StarkConfig::standard_fast_config(),
+            &self.all_stark_config(),
         )
     }
 }
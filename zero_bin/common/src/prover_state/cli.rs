@@ -1,11 +1,12 @@
 //! CLI arguments for constructing a [`CircuitConfig`], which can be used to
 //! construct table circuits.
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf};
 
 use clap::{Args, ValueEnum};
+use evm_arithmetization::SecurityPreset;
 
 use super::{
-    circuit::{Circuit, CircuitConfig, CircuitSize},
+    circuit::{Circuit, CircuitConfig, CircuitConfigError, CircuitSize},
     ProverStateManager, TableLoadStrategy,
 };
 
@@ -62,6 +63,19 @@ macro_rules! gen_prover_state_config {
             pub persistence: CircuitPersistence,
             #[clap(long, help_heading = HEADING, default_value_t = TableLoadStrategy::OnDemand)]
             pub load_strategy: TableLoadStrategy,
+            /// A TOML file mapping circuits to their min/max size, e.g.
+            /// `cpu = "12..25"`. Sizes given as arguments take precedence.
+            #[clap(long, help_heading = HEADING, env = "CIRCUIT_CONFIG_FILE")]
+            pub circuit_config_file: Option<PathBuf>,
+            /// Map the persisted circuits in memory when loading them, so that
+            /// the processes of a same host share their pages.
+            #[clap(long, help_heading = HEADING, env = "MMAP_CIRCUITS", default_value_t = false)]
+            pub mmap_circuits: bool,
+            /// The conjectured soundness, in bits, of the STARK proofs: one of
+            /// 80, 100 or 128. Circuits are built for a given preset, and
+            /// workers must all use the same one.
+            #[clap(long, help_heading = HEADING, env = "SECURITY_PRESET", default_value_t = SecurityPreset::default())]
+            pub security_preset: SecurityPreset,
 
             $(
                 #[clap(
@@ -86,12 +100,21 @@ gen_prover_state_config!(
     logic: Circuit::Logic,
     memory: Circuit::Memory,
     mem_before: Circuit::MemoryBefore,
-    mem_after: Circuit::MemoryAfter
+    mem_after: Circuit::MemoryAfter,
+    range_check: Circuit::RangeCheck,
+    ec: Circuit::Ec
 );
 
 impl CliProverStateConfig {
-    pub fn into_circuit_config(self) -> CircuitConfig {
+    /// Builds the circuit config from the default sizes, overridden by the
+    /// circuit config file if any, themselves overridden by the sizes given as
+    /// arguments.
+    pub fn into_circuit_config(self) -> Result<CircuitConfig, CircuitConfigError> {
         let mut config = CircuitConfig::default();
+        config.set_security(self.security_preset);
+        if let Some(path) = &self.circuit_config_file {
+            config.update_from_file(path)?;
+        }
 
         [
             (Circuit::Arithmetic, self.arithmetic),
@@ -103,24 +126,30 @@ impl CliProverStateConfig {
             (Circuit::Memory, self.memory),
             (Circuit::MemoryBefore, self.mem_before),
             (Circuit::MemoryAfter, self.mem_after),
+            (Circuit::RangeCheck, self.range_check),
+            (Circuit::Ec, self.ec),
         ]
         .into_iter()
         .filter_map(|(circuit, range)| range.map(|range| (circuit, range)))
         .for_each(|(circuit, range)| config.set_circuit_size(circuit, range));
 
-        config
+        config.validate()?;
+        Ok(config)
     }
 
-    pub fn into_prover_state_manager(self) -> ProverStateManager {
-        ProverStateManager {
+    pub fn into_prover_state_manager(self) -> Result<ProverStateManager, CircuitConfigError> {
+        Ok(ProverStateManager {
             persistence: self.persistence.with_load_strategy(self.load_strategy),
-            circuit_config: self.into_circuit_config(),
-        }
+            mmap_circuits: self.mmap_circuits,
+            circuit_config: self.into_circuit_config()?,
+        })
     }
 }
 
-impl From<CliProverStateConfig> for ProverStateManager {
-    fn from(config: CliProverStateConfig) -> Self {
+impl TryFrom<CliProverStateConfig> for ProverStateManager {
+    type Error = CircuitConfigError;
+
+    fn try_from(config: CliProverStateConfig) -> Result<Self, Self::Error> {
         config.into_prover_state_manager()
     }
 }
@@ -11,24 +11,26 @@
 //!   [`evm_arithmetization::fixed_recursive_verifier::AllRecursiveCircuits`].
 //! - Global prover state management via the [`P_STATE`] static and the
 //!   [`set_prover_state_from_config`] function.
-use std::{fmt::Display, sync::OnceLock};
+use std::{fmt::Display, sync::OnceLock, time::Instant};
 
 use clap::ValueEnum;
 use evm_arithmetization::{
     fixed_recursive_verifier::ProverOutputData,
     generation::TrimmedGenerationInputs,
     proof::AllProof,
-    prover::{prove, GenerationSegmentData},
-    AllStark, StarkConfig,
+    prover::{prove_with_metrics, GenerationSegmentData, ProvingMetrics},
+    AllStark, AllStarkConfig,
 };
-use plonky2::{
-    field::goldilocks_field::GoldilocksField, plonk::config::PoseidonGoldilocksConfig,
-    util::timing::TimingTree,
+use plonky2::{field::goldilocks_field::GoldilocksField, util::timing::TimingTree};
+use proof_gen::{
+    proof_types::GeneratedSegmentProof,
+    prover_state::ProverState,
+    types::{AllRecursiveCircuits, VerifierArtifact},
+    VerifierState,
 };
-use proof_gen::{proof_types::GeneratedSegmentProof, prover_state::ProverState, VerifierState};
-use tracing::info;
+use tracing::{debug, info};
 
-use self::circuit::{CircuitConfig, NUM_TABLES};
+use self::circuit::{Circuit, CircuitConfig, NUM_TABLES};
 use crate::prover_state::persistence::{
     BaseProverResource, DiskResource, MonolithicProverResource, RecursiveCircuitResource,
     VerifierResource,
@@ -38,7 +40,7 @@ pub mod circuit;
 pub mod cli;
 pub mod persistence;
 
-pub(crate) type Config = PoseidonGoldilocksConfig;
+pub(crate) type Config = proof_gen::types::Config;
 pub(crate) type Field = GoldilocksField;
 pub(crate) const SIZE: usize = 2;
 
@@ -126,6 +128,8 @@ impl Default for CircuitPersistence {
 pub struct ProverStateManager {
     pub circuit_config: CircuitConfig,
     pub persistence: CircuitPersistence,
+    /// Whether to map the persisted circuits in memory when loading them.
+    pub mmap_circuits: bool,
 }
 
 impl ProverStateManager {
@@ -133,27 +137,69 @@ impl ProverStateManager {
         match self.persistence {
             CircuitPersistence::None => self,
             CircuitPersistence::Disk(_) => Self {
-                circuit_config: self.circuit_config,
                 persistence: CircuitPersistence::Disk(load_strategy),
+                ..self
             },
         }
     }
 
+    /// Loads a persisted resource, mapping it in memory if configured to.
+    fn load<R: DiskResource>(
+        &self,
+        p: &R::PathConstrutor,
+    ) -> Result<R::Resource, persistence::DiskResourceError<R::Error>> {
+        if self.mmap_circuits {
+            R::get_mapped(p)
+        } else {
+            R::get(p)
+        }
+    }
+
+    /// Loads the circuits of a table at the given size, building and persisting
+    /// them first if they are missing from disk, e.g. when they were evicted
+    /// from the cache or never built on this machine.
+    fn load_or_create_table_circuits(
+        &self,
+        circuit: Circuit,
+        size: usize,
+    ) -> anyhow::Result<RecursiveCircuitsForTableSize> {
+        let key = (circuit, size, self.circuit_config.security());
+        match self.load::<RecursiveCircuitResource>(&key) {
+            Ok(table_circuits) => Ok(table_circuits),
+            Err(e) => {
+                info!("failed to load {circuit} circuit of size {size} ({e}). generating it...");
+                let table_circuits = AllRecursiveCircuits::create_table_circuits(
+                    &AllStark::default(),
+                    circuit.into(),
+                    size,
+                    &self.circuit_config.all_stark_config(),
+                );
+                RecursiveCircuitResource::put(&key, &table_circuits).map_err(|e| {
+                    anyhow::Error::from(e)
+                        .context(format!("Persisting circuit: {circuit:?} at size: {size}"))
+                })?;
+                Ok(table_circuits)
+            }
+        }
+    }
+
     /// Load the table circuits necessary to shrink the STARK proof.
     ///
     /// [`AllProof`] provides the necessary degree bits for each circuit via the
     /// [`AllProof::degree_bits`] method.
     /// Using this information, for each circuit, a tuple is returned,
     /// containing:
-    /// 1. The loaded table circuit at the specified size.
+    /// 1. The loaded table circuit at the specified size, built on first use if
+    ///    missing from disk.
     /// 2. An offset indicating the position of the specified size within the
     ///    configured range used when pre-generating the circuits.
     fn load_table_circuits(
         &self,
-        config: &StarkConfig,
+        config: &AllStarkConfig,
         all_proof: &AllProof<Field, Config, SIZE>,
     ) -> anyhow::Result<[(RecursiveCircuitsForTableSize, u8); NUM_TABLES]> {
         let degrees = all_proof.degree_bits(config);
+        self.circuit_config.check_degree_bits(&degrees)?;
 
         /// Given a recursive circuit index (e.g., Arithmetic / 0), return a
         /// tuple containing the loaded table at the specified size and
@@ -162,17 +208,10 @@ impl ProverStateManager {
         macro_rules! circuit {
             ($circuit_index:expr) => {
                 (
-                    RecursiveCircuitResource::get(&(
+                    self.load_or_create_table_circuits(
                         $circuit_index.into(),
                         degrees[$circuit_index],
-                    ))
-                    .map_err(|e| {
-                        let circuit: $crate::prover_state::circuit::Circuit = $circuit_index.into();
-                        let size = degrees[$circuit_index];
-                        anyhow::Error::from(e).context(format!(
-                            "Attempting to load circuit: {circuit:?} at size: {size}"
-                        ))
-                    })?,
+                    )?,
                     (degrees[$circuit_index] - self.circuit_config[$circuit_index].start) as u8,
                 )
             };
@@ -188,6 +227,8 @@ impl ProverStateManager {
             circuit!(6),
             circuit!(7),
             circuit!(8),
+            circuit!(9),
+            circuit!(10),
         ])
     }
 
@@ -199,24 +240,29 @@ impl ProverStateManager {
         input: TrimmedGenerationInputs,
         segment_data: &mut GenerationSegmentData,
     ) -> anyhow::Result<GeneratedSegmentProof> {
-        let config = StarkConfig::standard_fast_config();
+        let config = self.circuit_config.all_stark_config();
         let all_stark = AllStark::default();
+        let mut metrics = ProvingMetrics::default();
 
-        let all_proof = prove(
+        let all_proof = prove_with_metrics(
             &all_stark,
             &config,
             input,
             segment_data,
             &mut TimingTree::default(),
             None,
+            &mut metrics,
         )?;
 
         let table_circuits = self.load_table_circuits(&config, &all_proof)?;
 
+        let recursion_start = Instant::now();
         let (intern, p_vals) =
             p_state()
                 .state
                 .prove_segment_after_initial_stark(all_proof, &table_circuits, None)?;
+        metrics.recursion_time += recursion_start.elapsed();
+        log_metrics(segment_data.segment_index(), &metrics);
 
         Ok(GeneratedSegmentProof { p_vals, intern })
     }
@@ -228,14 +274,17 @@ impl ProverStateManager {
         input: TrimmedGenerationInputs,
         segment_data: &mut GenerationSegmentData,
     ) -> anyhow::Result<GeneratedSegmentProof> {
-        let p_out = p_state().state.prove_segment(
+        let mut metrics = ProvingMetrics::default();
+        let p_out = p_state().state.prove_segment_with_metrics(
             &AllStark::default(),
-            &StarkConfig::standard_fast_config(),
+            &self.circuit_config.all_stark_config(),
             input,
             segment_data,
             &mut TimingTree::default(),
             None,
+            &mut metrics,
         )?;
+        log_metrics(segment_data.segment_index(), &metrics);
 
         let ProverOutputData {
             is_dummy: _,
@@ -276,6 +325,7 @@ impl ProverStateManager {
     /// Initialize global prover state from the configuration.
     pub fn initialize(&self) -> anyhow::Result<()> {
         info!("initializing prover state...");
+        info!("{}", self.circuit_config.security().report());
 
         let state = match self.persistence {
             CircuitPersistence::None => {
@@ -288,9 +338,11 @@ impl ProverStateManager {
                 info!("attempting to load preprocessed circuits from disk...");
 
                 let disk_state = match strategy {
-                    TableLoadStrategy::OnDemand => BaseProverResource::get(&self.circuit_config),
+                    TableLoadStrategy::OnDemand => {
+                        self.load::<BaseProverResource>(&self.circuit_config)
+                    }
                     TableLoadStrategy::Monolithic => {
-                        MonolithicProverResource::get(&self.circuit_config)
+                        self.load::<MonolithicProverResource>(&self.circuit_config)
                     }
                 };
 
@@ -299,15 +351,25 @@ impl ProverStateManager {
                         info!("successfully loaded preprocessed circuits from disk");
                         ProverState { state: circuits }
                     }
-                    Err(_) => {
-                        info!("failed to load preprocessed circuits from disk. generating circuits...");
-                        let all_recursive_circuits =
-                            self.circuit_config.as_all_recursive_circuits();
-                        info!("saving preprocessed circuits to disk");
-                        persistence::persist_all_to_disk(
-                            &all_recursive_circuits,
-                            &self.circuit_config,
-                        )?;
+                    Err(e) => {
+                        info!("failed to load preprocessed circuits from disk ({e}). generating circuits...");
+                        let all_recursive_circuits = match strategy {
+                            // Table circuits are only needed from disk, so they are
+                            // persisted as they are built rather than all held in memory.
+                            TableLoadStrategy::OnDemand => {
+                                persistence::generate_on_demand_to_disk(&self.circuit_config)?
+                            }
+                            TableLoadStrategy::Monolithic => {
+                                let all_recursive_circuits =
+                                    self.circuit_config.as_all_recursive_circuits();
+                                info!("saving preprocessed circuits to disk");
+                                persistence::persist_all_to_disk(
+                                    &all_recursive_circuits,
+                                    &self.circuit_config,
+                                )?;
+                                all_recursive_circuits
+                            }
+                        };
                         ProverState {
                             state: all_recursive_circuits,
                         }
@@ -333,6 +395,15 @@ impl ProverStateManager {
         Ok(())
     }
 
+    /// Generates the circuits, and exports the verifier data of their block
+    /// and block aggregation circuits.
+    pub fn verifier_artifact(&self) -> VerifierArtifact {
+        info!("generating circuit...");
+        self.circuit_config
+            .as_all_recursive_circuits()
+            .export_verifier()
+    }
+
     /// Loads a verifier state from disk or generate it.
     pub fn verifier(&self) -> anyhow::Result<VerifierState> {
         info!("initializing verifier state...");
@@ -346,15 +417,15 @@ impl ProverStateManager {
             }
             CircuitPersistence::Disk(_) => {
                 info!("attempting to load preprocessed verifier circuit from disk...");
-                let disk_state = VerifierResource::get(&self.circuit_config);
+                let disk_state = self.load::<VerifierResource>(&self.circuit_config);
 
                 match disk_state {
                     Ok(state) => {
                         info!("successfully loaded preprocessed verifier circuit from disk");
                         Ok(VerifierState { state })
                     }
-                    Err(_) => {
-                        info!("failed to load preprocessed verifier circuit from disk. generating it...");
+                    Err(e) => {
+                        info!("failed to load preprocessed verifier circuit from disk ({e}). generating it...");
                         let prover_state = self.circuit_config.as_all_recursive_circuits();
 
                         info!("saving preprocessed verifier circuit to disk");
@@ -368,3 +439,17 @@ impl ProverStateManager {
         }
     }
 }
+
+/// Logs the metrics of the proof of the segment of index `segment_index`,
+/// with the per-table breakdown at debug level.
+fn log_metrics(segment_index: usize, metrics: &ProvingMetrics) {
+    info!(
+        segment_index,
+        commit_time = ?metrics.total_commit_time(),
+        prove_time = ?metrics.total_prove_time(),
+        recursion_time = ?metrics.recursion_time,
+        peak_rss = metrics.peak_rss,
+        "segment proving metrics"
+    );
+    debug!("{metrics}");
+}
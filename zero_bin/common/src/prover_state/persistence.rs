@@ -2,22 +2,23 @@ use std::{
     fmt::{Debug, Display},
     fs::{self, OpenOptions},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use alloy::hex;
 use directories::ProjectDirs;
-use evm_arithmetization::cpu::kernel::aggregator::KERNEL;
+use evm_arithmetization::{cpu::kernel::aggregator::KERNEL, AllStark, SecurityPreset, StarkConfig};
+use keccak_hash::keccak;
 use once_cell::sync::Lazy;
-use plonky2::util::serialization::{
-    Buffer, DefaultGateSerializer, DefaultGeneratorSerializer, IoError,
+use plonky2::util::serialization::{Buffer, IoError};
+use proof_gen::types::{
+    circuit_serializers as get_serializers, AllRecursiveCircuits, VerifierArtifact, VerifierData,
+    HASHER_NAME,
 };
-use proof_gen::types::{AllRecursiveCircuits, VerifierData};
 use thiserror::Error;
 
 use super::{
     circuit::{Circuit, CircuitConfig},
-    Config, RecursiveCircuitsForTableSize, SIZE,
+    RecursiveCircuitsForTableSize,
 };
 
 const PROVER_STATE_FILE_PREFIX: &str = "prover_state";
@@ -33,27 +34,39 @@ const KERNEL_HASH_PREFIX: usize = 8;
 /// When we serialize/deserialize circuits, we rely on the hash of the plonky
 /// kernel to determine if the circuit is compatible with our current binary. If
 /// the kernel hash of the circuit that we are loading in from disk differs,
-/// then using these circuits would cause failures during proof generation
-pub static CIRCUIT_VERSION: Lazy<String> =
-    Lazy::new(|| hex::encode(KERNEL.hash())[..KERNEL_HASH_PREFIX].to_string());
-
-fn get_serializers() -> (
-    DefaultGateSerializer,
-    DefaultGeneratorSerializer<Config, SIZE>,
-) {
-    let gate_serializer = DefaultGateSerializer;
-    let witness_serializer: DefaultGeneratorSerializer<Config, SIZE> =
-        DefaultGeneratorSerializer::default();
-
-    (gate_serializer, witness_serializer)
+/// then using these circuits would cause failures during proof generation.
+/// Circuits built with another hash for the recursive layers are equally
+/// incompatible, so the version also names that hash when it is not the
+/// default Poseidon one.
+pub static CIRCUIT_VERSION: Lazy<String> = Lazy::new(|| {
+    let kernel_hash = &hex::encode(KERNEL.hash())[..KERNEL_HASH_PREFIX];
+    match HASHER_NAME {
+        "poseidon" => kernel_hash.to_string(),
+        hasher => format!("{kernel_hash}_{hasher}"),
+    }
+});
+
+/// Circuits built with different STARK configurations are incompatible, hence
+/// serialized circuits are also keyed by a digest of the configuration they
+/// were built with.
+pub fn stark_config_digest(config: &StarkConfig) -> String {
+    hex::encode(keccak(format!("{config:?}")))[..KERNEL_HASH_PREFIX].to_string()
 }
 
+/// The header of every serialized circuit file, followed by the keccak hash of
+/// the serialized circuits, so that truncated or corrupted files are detected
+/// when loaded.
+const CACHE_FILE_MAGIC: &[u8; 8] = b"zkevmcc1";
+const CACHE_FILE_HEADER_LEN: usize = CACHE_FILE_MAGIC.len() + 32;
+
 #[derive(Error, Debug)]
 pub(crate) enum DiskResourceError<E> {
     #[error("Serialization error: {0}")]
     Serialization(E),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("integrity check failed for {0:?}")]
+    Corrupted(PathBuf),
 }
 
 /// A trait for generic resources that may be written to and read from disk,
@@ -78,12 +91,54 @@ pub(crate) trait DiskResource {
     /// Deserializes the resource from bytes.
     fn deserialize(bytes: &[u8]) -> Result<Self::Resource, DiskResourceError<Self::Error>>;
 
-    /// Reads the resource from disk and deserializes it.
+    /// Reads the resource from disk, checks its integrity and deserializes it.
     fn get(p: &Self::PathConstrutor) -> Result<Self::Resource, DiskResourceError<Self::Error>> {
-        Self::deserialize(&fs::read(Self::path(p))?)
+        let path = Self::path(p);
+        let path = path.as_ref();
+        Self::from_cache_file(path, &fs::read(path)?)
+    }
+
+    /// Same as [`DiskResource::get`], but maps the resource file in memory
+    /// rather than reading it into a private buffer.
+    ///
+    /// Processes of a same host loading the same resource then share the pages
+    /// of the serialized resource through the page cache, rather than each
+    /// holding its own copy while deserializing it.
+    fn get_mapped(
+        p: &Self::PathConstrutor,
+    ) -> Result<Self::Resource, DiskResourceError<Self::Error>> {
+        let path = Self::path(p);
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        // SAFETY: resource files are never modified in place: `put` writes to a
+        // temporary file which then atomically replaces the resource file.
+        let bytes = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_cache_file(path, &bytes)
+    }
+
+    /// Checks the integrity of the contents of the resource file at `path` and
+    /// deserializes them.
+    fn from_cache_file(
+        path: &Path,
+        bytes: &[u8],
+    ) -> Result<Self::Resource, DiskResourceError<Self::Error>> {
+        let corrupted = || DiskResourceError::Corrupted(path.to_path_buf());
+        if bytes.len() < CACHE_FILE_HEADER_LEN || !bytes.starts_with(CACHE_FILE_MAGIC) {
+            return Err(corrupted());
+        }
+        let (hash, payload) = bytes[CACHE_FILE_MAGIC.len()..].split_at(32);
+        if keccak(payload).as_bytes() != hash {
+            return Err(corrupted());
+        }
+
+        Self::deserialize(payload)
     }
 
     /// Writes the resource to disk after serializing it.
+    ///
+    /// The resource is first written to a temporary file which is then renamed,
+    /// so that concurrent readers, e.g. the workers of a single machine
+    /// starting up together, never see a partially written resource.
     fn put(
         p: &Self::PathConstrutor,
         r: &Self::Resource,
@@ -100,12 +155,22 @@ pub(crate) trait DiskResource {
             })?;
         }
 
-        Ok(OpenOptions::new()
+        let payload = Self::serialize(r)?;
+        let path = Self::path(p);
+        let path = path.as_ref();
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+
+        let mut file = OpenOptions::new()
             .write(true)
             .create(true)
             .truncate(true)
-            .open(Self::path(p))?
-            .write_all(&Self::serialize(r)?)?)
+            .open(&tmp_path)?;
+        file.write_all(CACHE_FILE_MAGIC)?;
+        file.write_all(keccak(&payload).as_bytes())?;
+        file.write_all(&payload)?;
+        file.sync_all()?;
+
+        Ok(fs::rename(tmp_path, path)?)
     }
 }
 
@@ -122,10 +187,11 @@ impl DiskResource for BaseProverResource {
 
     fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_base_{}_{}",
+            "{}/{}_base_{}_{}_{}",
             circuit_dir(),
             PROVER_STATE_FILE_PREFIX,
             *CIRCUIT_VERSION,
+            stark_config_digest(&p.stark_config()),
             p.get_configuration_digest()
         )
     }
@@ -158,10 +224,11 @@ impl DiskResource for MonolithicProverResource {
 
     fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_monolithic_{}_{}",
+            "{}/{}_monolithic_{}_{}_{}",
             circuit_dir(),
             PROVER_STATE_FILE_PREFIX,
             *CIRCUIT_VERSION,
+            stark_config_digest(&p.stark_config()),
             p.get_configuration_digest()
         )
     }
@@ -189,14 +256,15 @@ pub(crate) struct RecursiveCircuitResource;
 impl DiskResource for RecursiveCircuitResource {
     type Resource = RecursiveCircuitsForTableSize;
     type Error = IoError;
-    type PathConstrutor = (Circuit, usize);
+    type PathConstrutor = (Circuit, usize, SecurityPreset);
 
-    fn path((circuit_type, size): &Self::PathConstrutor) -> impl AsRef<Path> {
+    fn path((circuit_type, size, security): &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_{}_{}_{}",
+            "{}/{}_{}_{}_{}_{}",
             circuit_dir(),
             PROVER_STATE_FILE_PREFIX,
             *CIRCUIT_VERSION,
+            stark_config_digest(&security.stark_config()),
             circuit_type.as_short_str(),
             size
         )
@@ -237,10 +305,11 @@ impl DiskResource for VerifierResource {
 
     fn path(p: &Self::PathConstrutor) -> impl AsRef<Path> {
         format!(
-            "{}/{}_{}_{}",
+            "{}/{}_{}_{}_{}",
             circuit_dir(),
             VERIFIER_STATE_FILE_PREFIX,
             *CIRCUIT_VERSION,
+            stark_config_digest(&p.stark_config()),
             p.get_configuration_digest()
         )
     }
@@ -258,6 +327,29 @@ impl DiskResource for VerifierResource {
     }
 }
 
+/// Writes a [`VerifierArtifact`] to `path`.
+pub fn write_verifier_artifact(path: &Path, artifact: &VerifierArtifact) -> anyhow::Result<()> {
+    let (gate_serializer, _) = get_serializers();
+    let bytes = artifact
+        .to_bytes(&gate_serializer)
+        .map_err(|e| anyhow::anyhow!("failed to serialize the verifier artifact: {e:?}"))?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Reads a [`VerifierArtifact`] written by [`write_verifier_artifact`].
+pub fn read_verifier_artifact(path: &Path) -> anyhow::Result<VerifierArtifact> {
+    let (gate_serializer, _) = get_serializers();
+    let bytes = fs::read(path)?;
+    VerifierArtifact::from_bytes(&bytes, &gate_serializer).map_err(|e| {
+        anyhow::anyhow!(
+            "failed to deserialize the verifier artifact {}: {e:?}. It may have been \
+             exported with another version",
+            path.display()
+        )
+    })
+}
+
 /// Writes the provided [`AllRecursiveCircuits`] to disk with all
 /// configurations, along with the associated [`VerifierData`].
 pub fn persist_all_to_disk(
@@ -270,6 +362,31 @@ pub fn persist_all_to_disk(
     Ok(())
 }
 
+/// Builds the [`AllRecursiveCircuits`] of the configuration without its
+/// recursive table circuits, which are written to disk one at a time as they
+/// are built, and writes the base prover state to disk along with the
+/// associated [`VerifierData`].
+///
+/// Unlike [`persist_all_to_disk`], this never holds all the table circuits in
+/// memory, at the cost of not writing the monolithic prover state.
+pub fn generate_on_demand_to_disk(
+    circuit_config: &CircuitConfig,
+) -> anyhow::Result<AllRecursiveCircuits> {
+    let security = circuit_config.security();
+    let circuits = AllRecursiveCircuits::new_lazy(
+        &AllStark::default(),
+        circuit_config.as_degree_bits_ranges(),
+        &circuit_config.all_stark_config(),
+        |table, size, table_circuits| {
+            RecursiveCircuitResource::put(&(Circuit::from(*table), size, security), &table_circuits)
+        },
+    )?;
+    BaseProverResource::put(circuit_config, &circuits)?;
+    VerifierResource::put(circuit_config, &circuits.final_verifier_data())?;
+
+    Ok(circuits)
+}
+
 /// Writes the provided [`AllRecursiveCircuits`] to disk.
 ///
 /// In particular, we cover both the monolothic and base prover states, as well
@@ -286,7 +403,10 @@ fn prover_to_disk(
     for (circuit_type, tables) in circuits.by_table.iter().enumerate() {
         let circuit_type: Circuit = circuit_type.into();
         for (size, table) in tables.by_stark_size.iter() {
-            RecursiveCircuitResource::put(&(circuit_type, *size), table)?;
+            RecursiveCircuitResource::put(
+                &(circuit_type, *size, circuit_config.security()),
+                table,
+            )?;
         }
     }
 
@@ -0,0 +1,338 @@
+//! Tenants sharing a proving deployment.
+//!
+//! Each tenant writes its proofs to its own subdirectory of the output
+//! directory, and may be given quotas on the blocks it proves. The outcome of
+//! its blocks is tracked separately, to report per-tenant statistics.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The maximum length of a tenant name.
+pub const MAX_TENANT_LEN: usize = 64;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TenantError {
+    #[error(
+        "invalid tenant '{0}': tenants are made of at most {MAX_TENANT_LEN} lowercase \
+         alphanumerics, '-' and '_'"
+    )]
+    InvalidTenant(String),
+
+    #[error("unknown tenant '{0}'")]
+    UnknownTenant(Tenant),
+
+    #[error("tenant '{tenant}' already has {limit} blocks being proved")]
+    TooManyInFlight { tenant: Tenant, limit: usize },
+
+    #[error("tenant '{tenant}' exhausted its quota of {limit} blocks")]
+    BlockQuotaExhausted { tenant: Tenant, limit: u64 },
+}
+
+/// The name of a tenant, which is also the name of its output subdirectory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Tenant(String);
+
+impl Tenant {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The directory of the proofs of this tenant, within `output_dir`.
+    pub fn output_dir(&self, output_dir: &Path) -> PathBuf {
+        output_dir.join(&self.0)
+    }
+}
+
+impl FromStr for Tenant {
+    type Err = TenantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = !s.is_empty()
+            && s.len() <= MAX_TENANT_LEN
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if valid {
+            Ok(Self(s.to_string()))
+        } else {
+            Err(TenantError::InvalidTenant(s.to_string()))
+        }
+    }
+}
+
+impl TryFrom<String> for Tenant {
+    type Error = TenantError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Tenant> for String {
+    fn from(tenant: Tenant) -> Self {
+        tenant.0
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The limits on the blocks proved for a tenant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantQuota {
+    /// The maximum number of blocks being proved at once.
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// The maximum number of blocks proved over the lifetime of the
+    /// deployment, failed proofs included.
+    #[serde(default)]
+    pub max_blocks: Option<u64>,
+}
+
+/// The statistics of the blocks of a tenant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantStats {
+    /// The number of blocks being proved.
+    pub in_flight: usize,
+    pub proved: u64,
+    pub failed: u64,
+    /// The number of blocks refused for exceeding the quota of the tenant.
+    pub throttled: u64,
+    /// The total time spent proving the blocks of the tenant, in seconds.
+    pub proving_secs: f64,
+}
+
+/// The tenants of a deployment, along with their quotas and statistics.
+#[derive(Debug, Default)]
+pub struct TenantRegistry {
+    /// The quota of each tenant, if tenants must be declared.
+    quotas: Option<BTreeMap<Tenant, TenantQuota>>,
+    stats: Mutex<BTreeMap<Tenant, TenantStats>>,
+}
+
+impl TenantRegistry {
+    /// A registry accepting any tenant, without quotas.
+    pub fn open() -> Self {
+        Self::default()
+    }
+
+    /// A registry only accepting the tenants of `quotas`.
+    pub fn with_quotas(quotas: BTreeMap<Tenant, TenantQuota>) -> Self {
+        Self {
+            quotas: Some(quotas),
+            ..Default::default()
+        }
+    }
+
+    /// Reads the quotas of the tenants from a JSON object mapping each tenant
+    /// to its [`TenantQuota`].
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(Self::with_quotas(serde_json::from_reader(file)?))
+    }
+
+    /// Starts proving a block for `tenant`, within its quota.
+    ///
+    /// The block is counted as in flight until the returned permit is
+    /// finished or dropped.
+    pub fn acquire(&self, tenant: &Tenant) -> Result<TenantPermit<'_>, TenantError> {
+        let quota = match &self.quotas {
+            Some(quotas) => *quotas
+                .get(tenant)
+                .ok_or_else(|| TenantError::UnknownTenant(tenant.clone()))?,
+            None => TenantQuota::default(),
+        };
+
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = stats.entry(tenant.clone()).or_default();
+        let error = if let Some(limit) = quota.max_in_flight.filter(|&l| stats.in_flight >= l) {
+            Some(TenantError::TooManyInFlight {
+                tenant: tenant.clone(),
+                limit,
+            })
+        } else {
+            quota
+                .max_blocks
+                .filter(|&limit| stats.proved + stats.failed + stats.in_flight as u64 >= limit)
+                .map(|limit| TenantError::BlockQuotaExhausted {
+                    tenant: tenant.clone(),
+                    limit,
+                })
+        };
+        if let Some(error) = error {
+            stats.throttled += 1;
+            return Err(error);
+        }
+
+        stats.in_flight += 1;
+        Ok(TenantPermit {
+            registry: self,
+            tenant: tenant.clone(),
+            finished: false,
+        })
+    }
+
+    /// Returns the statistics of `tenant`.
+    pub fn stats(&self, tenant: &Tenant) -> TenantStats {
+        self.stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(tenant)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the statistics of all the tenants seen so far.
+    pub fn all_stats(&self) -> BTreeMap<Tenant, TenantStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn release(&self, tenant: &Tenant, outcome: Outcome) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = stats.entry(tenant.clone()).or_default();
+        stats.in_flight -= 1;
+        match outcome {
+            Outcome::Proved(elapsed) => {
+                stats.proved += 1;
+                stats.proving_secs += elapsed.as_secs_f64();
+            }
+            Outcome::Failed => stats.failed += 1,
+            Outcome::Abandoned => {}
+        }
+    }
+}
+
+/// The outcome of the block of a [`TenantPermit`].
+enum Outcome {
+    Proved(Duration),
+    Failed,
+    /// The permit was dropped before the block was proved.
+    Abandoned,
+}
+
+/// A block of a tenant being proved, released when finished or dropped.
+#[derive(Debug)]
+pub struct TenantPermit<'a> {
+    registry: &'a TenantRegistry,
+    tenant: Tenant,
+    finished: bool,
+}
+
+impl TenantPermit<'_> {
+    pub const fn tenant(&self) -> &Tenant {
+        &self.tenant
+    }
+
+    /// Records a block proved in `elapsed`.
+    pub fn proved(mut self, elapsed: Duration) {
+        self.finished = true;
+        self.registry
+            .release(&self.tenant, Outcome::Proved(elapsed));
+    }
+
+    /// Records a block whose proof failed.
+    pub fn failed(mut self) {
+        self.finished = true;
+        self.registry.release(&self.tenant, Outcome::Failed);
+    }
+}
+
+impl Drop for TenantPermit<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.registry.release(&self.tenant, Outcome::Abandoned);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tenant(name: &str) -> Tenant {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_tenants() {
+        assert_eq!(tenant("acme-l2_1").as_str(), "acme-l2_1");
+        for invalid in [
+            "",
+            "Acme",
+            "../acme",
+            "a.b",
+            &"a".repeat(MAX_TENANT_LEN + 1),
+        ] {
+            assert_eq!(
+                invalid.parse::<Tenant>(),
+                Err(TenantError::InvalidTenant(invalid.to_string()))
+            );
+        }
+        assert_eq!(
+            tenant("acme").output_dir(Path::new("/proofs")),
+            Path::new("/proofs/acme")
+        );
+    }
+
+    #[test]
+    fn enforces_quotas() {
+        let registry = TenantRegistry::with_quotas(BTreeMap::from([(
+            tenant("acme"),
+            TenantQuota {
+                max_in_flight: Some(1),
+                max_blocks: Some(2),
+            },
+        )]));
+
+        assert_eq!(
+            registry.acquire(&tenant("other")).unwrap_err(),
+            TenantError::UnknownTenant(tenant("other"))
+        );
+
+        let permit = registry.acquire(&tenant("acme")).unwrap();
+        assert_eq!(
+            registry.acquire(&tenant("acme")).unwrap_err(),
+            TenantError::TooManyInFlight {
+                tenant: tenant("acme"),
+                limit: 1
+            }
+        );
+        permit.proved(Duration::from_secs(3));
+
+        registry.acquire(&tenant("acme")).unwrap().failed();
+        assert_eq!(
+            registry.acquire(&tenant("acme")).unwrap_err(),
+            TenantError::BlockQuotaExhausted {
+                tenant: tenant("acme"),
+                limit: 2
+            }
+        );
+
+        assert_eq!(
+            registry.stats(&tenant("acme")),
+            TenantStats {
+                in_flight: 0,
+                proved: 1,
+                failed: 1,
+                throttled: 2,
+                proving_secs: 3.0,
+            }
+        );
+    }
+
+    #[test]
+    fn dropped_permits_are_released() {
+        let registry = TenantRegistry::open();
+        drop(registry.acquire(&tenant("acme")).unwrap());
+        assert_eq!(registry.stats(&tenant("acme")), TenantStats::default());
+    }
+}
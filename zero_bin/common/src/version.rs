@@ -1,10 +1,17 @@
+/// The chain the binaries target, selected by the `cdk_erigon` feature.
+pub const CHAIN: &str = if cfg!(feature = "cdk_erigon") {
+    "cdk_erigon"
+} else {
+    "ethereum"
+};
+
 pub fn print_version(
     evm_arithmetization_kernel_version: &str,
     rustc_commit_hash: &str,
     rustc_timestamp: &str,
 ) {
     println!(
-        "evm_arithmetization Kernel Version: {}\nBuild Commit Hash: {}\nBuild Timestamp: {}",
-        evm_arithmetization_kernel_version, rustc_commit_hash, rustc_timestamp
+        "evm_arithmetization Kernel Version: {}\nChain: {}\nBuild Commit Hash: {}\nBuild Timestamp: {}",
+        evm_arithmetization_kernel_version, CHAIN, rustc_commit_hash, rustc_timestamp
     )
 }
@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use proof_gen::proof_types::GeneratedBlockProof;
+use prover::archive::ArchiveReader;
+use prover::runtime::ProverRuntime;
+use prover::{BlockProverInputFuture, ProverConfig};
+use tracing::info;
+use zero_bin_common::block_interval::BlockInterval;
+
+/// The main function for the archive mode.
+///
+/// Proves the blocks of `block_interval`, or all the blocks of the archive if
+/// none is given.
+pub(crate) async fn archive_main(
+    runtime: ProverRuntime,
+    archive: PathBuf,
+    block_interval: Option<BlockInterval>,
+    previous_proof: Option<GeneratedBlockProof>,
+    proof_output_dir: Option<PathBuf>,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    use futures::StreamExt;
+
+    let reader = ArchiveReader::open(&archive)?;
+    let block_numbers: Vec<u64> = match block_interval {
+        Some(block_interval) => block_interval.into_bounded_stream()?.collect().await,
+        None => reader.block_numbers().collect(),
+    };
+    let reader = Arc::new(Mutex::new(reader));
+
+    let block_prover_inputs = block_numbers
+        .into_iter()
+        .map(|block_num| {
+            let reader = reader.clone();
+            // Entries are decompressed on a blocking thread when the prover
+            // polls the input.
+            let block_prover_input: BlockProverInputFuture = Box::pin(async move {
+                tokio::task::spawn_blocking(move || {
+                    reader
+                        .lock()
+                        .expect("archive reader poisoned")
+                        .read(block_num)
+                })
+                .await?
+            });
+            block_prover_input
+        })
+        .collect();
+
+    let proved_blocks = prover::prove(
+        block_prover_inputs,
+        &runtime,
+        previous_proof,
+        prover_config,
+        proof_output_dir,
+        None,
+    )
+    .await;
+    runtime.close().await?;
+    let proved_blocks = proved_blocks?;
+
+    if prover_config.dry_run {
+        info!("All blocks have been dry-run successfully.");
+    } else if prover_config.test_only {
+        info!("All proof witnesses have been generated successfully.");
+    } else {
+        info!("All proofs have been generated successfully.");
+    }
+
+    let proofs: Vec<GeneratedBlockProof> = proved_blocks
+        .into_iter()
+        .filter_map(|(_, proof)| proof)
+        .collect();
+    if !proofs.is_empty() {
+        std::io::stdout().write_all(&serde_json::to_vec(&proofs)?)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{ensure, Result};
+use futures::TryStreamExt as _;
+use ops::{SyntheticLoop, SyntheticSegmentProof, SyntheticSegmentReport, SyntheticWorkload};
+use paladin::directive::{Directive, IndexedStream};
+use prover::runtime::ProverRuntime;
+use serde::Serialize;
+use tracing::info;
+
+/// The timings of the synthetic segment proofs of one loop and CPU length.
+#[derive(Debug, Serialize)]
+struct CalibrationSummary {
+    body: SyntheticLoop,
+    max_cpu_len_log: usize,
+    runs: usize,
+    mean_proving: Duration,
+    min_proving: Duration,
+    max_proving: Duration,
+}
+
+#[derive(Debug, Serialize)]
+struct CalibrationReport {
+    summaries: Vec<CalibrationSummary>,
+    reports: Vec<SyntheticSegmentReport>,
+}
+
+/// The main function for the calibration mode.
+///
+/// Dispatches `repetitions` synthetic segment proofs of each loop and CPU
+/// length to the segment workers, and writes their timings, per worker and
+/// summarized per loop and CPU length, as JSON to `output` or stdout.
+pub(crate) async fn calibrate_main(
+    runtime: ProverRuntime,
+    mut max_cpu_len_logs: Vec<usize>,
+    mut loops: Vec<SyntheticLoop>,
+    repetitions: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    ensure!(repetitions > 0, "at least one repetition is required");
+    max_cpu_len_logs.sort_unstable();
+    max_cpu_len_logs.dedup();
+    loops.sort_unstable();
+    loops.dedup();
+
+    let sweep = loops
+        .iter()
+        .flat_map(|&body| {
+            max_cpu_len_logs
+                .iter()
+                .map(move |&max_cpu_len_log| (body, max_cpu_len_log))
+        })
+        .collect::<Vec<_>>();
+    let workloads = sweep
+        .iter()
+        .flat_map(|&(body, max_cpu_len_log)| {
+            (0..repetitions).map(move |run| SyntheticWorkload {
+                body,
+                max_cpu_len_log,
+                run,
+            })
+        })
+        .collect::<Vec<_>>();
+    info!("Dispatching {} synthetic segment proofs", workloads.len());
+
+    let mut reports = Directive::map(IndexedStream::from(workloads), &SyntheticSegmentProof)
+        .run(runtime.segments())
+        .await?
+        .map_ok(|(_, report)| report)
+        .try_collect::<Vec<_>>()
+        .await?;
+    runtime.close().await?;
+    reports.sort_by_key(|report| {
+        let workload = &report.workload;
+        (workload.body, workload.max_cpu_len_log, workload.run)
+    });
+
+    let summaries = sweep
+        .into_iter()
+        .map(|(body, max_cpu_len_log)| {
+            let proving = reports
+                .iter()
+                .filter(|report| {
+                    report.workload.body == body
+                        && report.workload.max_cpu_len_log == max_cpu_len_log
+                })
+                .map(|report| report.proving)
+                .collect::<Vec<_>>();
+            let summary = CalibrationSummary {
+                body,
+                max_cpu_len_log,
+                runs: proving.len(),
+                mean_proving: proving.iter().sum::<Duration>() / proving.len() as u32,
+                min_proving: proving.iter().min().copied().unwrap_or_default(),
+                max_proving: proving.iter().max().copied().unwrap_or_default(),
+            };
+            info!(
+                "{body}, 2^{max_cpu_len_log} cycles: mean {:?}, min {:?}, max {:?} over {} runs",
+                summary.mean_proving, summary.min_proving, summary.max_proving, summary.runs
+            );
+            summary
+        })
+        .collect();
+
+    let report = serde_json::to_vec_pretty(&CalibrationReport { summaries, reports })?;
+    match output {
+        Some(path) => std::fs::write(path, report)?,
+        None => std::io::stdout().write_all(&report)?,
+    }
+
+    Ok(())
+}
@@ -2,9 +2,15 @@ use std::path::PathBuf;
 
 use alloy::transports::http::reqwest::Url;
 use clap::{Parser, Subcommand, ValueHint};
+use ops::SyntheticLoop;
 use prover::cli::CliProverConfig;
+use rpc::provider::{DEFAULT_RPC_BATCH_SIZE, DEFAULT_RPC_MAX_CONCURRENCY};
 use rpc::RpcType;
+use zero_bin_common::capabilities::CapabilityTags;
+use zero_bin_common::compression::{CliCompressionConfig, DEFAULT_DICTIONARY_SIZE};
+use zero_bin_common::grpc::DEFAULT_GRPC_PORT;
 use zero_bin_common::prover_state::cli::CliProverStateConfig;
+use zero_bin_common::tenant::Tenant;
 
 /// zero-bin leader config
 #[derive(Parser)]
@@ -15,6 +21,11 @@ pub(crate) struct Cli {
     #[clap(flatten)]
     pub(crate) paladin: paladin::config::Config,
 
+    /// Comma-separated capability tags a worker must hold to be dispatched
+    /// segment proofs, e.g. `gpu,high-mem`. Other tasks go to any worker.
+    #[arg(long, env = "SEGMENT_WORKER_TAGS")]
+    pub(crate) segment_worker_tags: Option<CapabilityTags>,
+
     #[clap(flatten)]
     pub(crate) prover_config: CliProverConfig,
 
@@ -22,6 +33,9 @@ pub(crate) struct Cli {
     // mode.
     #[clap(flatten)]
     pub(crate) prover_state_config: CliProverStateConfig,
+
+    #[clap(flatten)]
+    pub(crate) compression_config: CliCompressionConfig,
 }
 
 #[derive(Subcommand)]
@@ -46,13 +60,19 @@ pub(crate) enum Command {
         /// The checkpoint block number.
         #[arg(short, long, default_value_t = 0)]
         checkpoint_block_number: u64,
-        /// The previous proof output.
+        /// The previous proof output. If not provided, the proof of the block
+        /// preceding the interval is looked up in `proof_output_dir`, which
+        /// must not hold a proof of an earlier block instead.
         #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
         previous_proof: Option<PathBuf>,
         /// If provided, write the generated proofs to this directory instead of
         /// stdout.
         #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
         proof_output_dir: Option<PathBuf>,
+        /// Prove the blocks on behalf of this tenant, writing its proofs to
+        /// its own subdirectory of `proof_output_dir`.
+        #[arg(long, env = "ZERO_BIN_TENANT")]
+        tenant: Option<Tenant>,
         /// Network block time in milliseconds. This value is used
         /// to determine the blockchain node polling interval.
         #[arg(short, long, env = "ZERO_BIN_BLOCK_TIME", default_value_t = 2000)]
@@ -72,6 +92,149 @@ pub(crate) enum Command {
         /// The maximum number of retries
         #[arg(long, default_value_t = 0)]
         max_retries: u32,
+        /// The maximum number of calls sent in a single JSON-RPC batch
+        #[arg(long, default_value_t = DEFAULT_RPC_BATCH_SIZE)]
+        rpc_batch_size: usize,
+        /// The maximum number of JSON-RPC batches in flight at once
+        #[arg(long, default_value_t = DEFAULT_RPC_MAX_CONCURRENCY)]
+        rpc_max_concurrency: usize,
+        /// If provided, wrap the last proof of the interval into a Groth16
+        /// proof over BN254 with this external prover, for on-chain
+        /// verification.
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        wrap_groth16: Option<PathBuf>,
+        /// If provided, only wrap with the Groth16 prover if it reports this
+        /// version when run with `--version`, i.e. if it's the build the
+        /// verifier contract was generated for.
+        #[arg(long, requires = "wrap_groth16")]
+        groth16_prover_version: Option<String>,
+        /// If true, compress the last proof of the interval with an extra
+        /// recursive circuit with an aggressive FRI rate, for storage or
+        /// transmission.
+        #[arg(long, default_value_t = false)]
+        compress_final_proof: bool,
+        /// If provided, also store the generated proofs in the PostgreSQL
+        /// database at this URL.
+        #[cfg(feature = "postgres")]
+        #[arg(long, env = "ZERO_BIN_PROOF_STORE_URL")]
+        proof_store_url: Option<String>,
+    },
+    /// Reads input from era1 archives and a witness directory, and writes
+    /// output to stdout.
+    Era {
+        /// The directory containing the era1 archives.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        era_dir: PathBuf,
+        /// The directory containing the state witness of each block, as
+        /// `b<block_number>.witness.json` files.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        witness_dir: PathBuf,
+        /// The block interval for which to generate a proof.
+        #[arg(long, short = 'i')]
+        block_interval: String,
+        /// The chain ID of the archived chain.
+        #[arg(long, default_value_t = 1)]
+        chain_id: u64,
+        /// The checkpoint block number.
+        #[arg(short, long, default_value_t = 0)]
+        checkpoint_block_number: u64,
+        /// The previous proof output.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        previous_proof: Option<PathBuf>,
+        /// If provided, write the generated proofs to this directory instead of
+        /// stdout.
+        #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
+        proof_output_dir: Option<PathBuf>,
+    },
+    /// Reads input from a prover input archive, as written by the rpc tool, and
+    /// writes output to stdout.
+    Archive {
+        /// The archive file.
+        #[arg(long, short = 'a', value_hint = ValueHint::FilePath)]
+        archive: PathBuf,
+        /// The block interval for which to generate a proof. If not provided,
+        /// all the blocks of the archive are proved.
+        #[arg(long, short = 'i')]
+        block_interval: Option<String>,
+        /// The previous proof output.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        previous_proof: Option<PathBuf>,
+        /// If provided, write the generated proofs to this directory instead of
+        /// stdout.
+        #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
+        proof_output_dir: Option<PathBuf>,
+    },
+    /// Estimates the cost of proving the blocks read from stdin, without
+    /// proving them.
+    Estimate {
+        /// Read the input from this file instead of stdin.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        input: Option<PathBuf>,
+        /// Attribute the cycles of each segment to the kernel routines they
+        /// were spent in, and write the resulting profiles to this directory,
+        /// in the folded stacks format of flamegraph tools.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        profile_cycles: Option<PathBuf>,
+    },
+    /// Trains a zstd dictionary on the segment payloads of the blocks read from
+    /// stdin, to be passed to the leader and the workers with
+    /// `--compression-dictionary`.
+    TrainDictionary {
+        /// Read the input from this file instead of stdin.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        input: Option<PathBuf>,
+        /// The file to write the dictionary to.
+        #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+        /// The maximum size of the dictionary, in bytes.
+        #[arg(long, default_value_t = DEFAULT_DICTIONARY_SIZE)]
+        max_size: usize,
+    },
+    /// Computes the OP-stack output root of each block proof, and writes the
+    /// calldata creating the corresponding dispute game to stdout.
+    OpSettle {
+        /// The file containing the block proofs, as a JSON array.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        proofs: PathBuf,
+        /// The L2 node RPC URL, used to retrieve the storage root of the
+        /// `L2ToL1MessagePasser` at each block.
+        #[arg(long, short = 'u', value_hint = ValueHint::Url)]
+        rpc_url: Url,
+        /// The type of the dispute games to create.
+        #[arg(long, default_value_t = 0)]
+        game_type: u32,
+    },
+    /// Packages a range of at most 257 consecutive block proofs into a light
+    /// client update, carrying the headers of their blocks.
+    LightClientUpdate {
+        /// The file containing the block proofs, as a JSON array.
+        #[arg(long, short = 'f', value_hint = ValueHint::FilePath)]
+        proofs: PathBuf,
+        /// The node RPC URL, used to retrieve the headers of the blocks.
+        #[arg(long, short = 'u', value_hint = ValueHint::Url)]
+        rpc_url: Url,
+        /// The file to write the update to.
+        #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+    },
+    /// Proves synthetic segments of the given CPU lengths on the segment
+    /// workers, to warm them up and compare their proving times, and writes
+    /// the timings as JSON.
+    Calibrate {
+        /// Comma-separated base-2 logarithms of the CPU lengths to prove.
+        #[arg(long, value_delimiter = ',', default_value = "16,18,20")]
+        max_cpu_len_logs: Vec<usize>,
+        /// Comma-separated loops run by the synthetic segments, selecting the
+        /// tables growing along with the CPU one: jump, arithmetic, keccak or
+        /// memory.
+        #[arg(long, value_delimiter = ',', default_value = "jump")]
+        loops: Vec<SyntheticLoop>,
+        /// The number of synthetic segments to prove for each CPU length.
+        #[arg(long, short = 'n', default_value_t = 4)]
+        repetitions: usize,
+        /// Write the timings to this file instead of stdout.
+        #[arg(long, short = 'o', value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
     },
     /// Reads input from HTTP and writes output to a directory.
     Http {
@@ -81,5 +244,19 @@ pub(crate) enum Command {
         /// The directory to which output should be written.
         #[arg(short, long, value_hint = ValueHint::DirPath)]
         output_dir: PathBuf,
+        /// A JSON file mapping each tenant allowed to use the
+        /// `/tenants/<tenant>/prove` endpoint to its quotas. If not provided,
+        /// any tenant is allowed, without quotas.
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        tenants: Option<PathBuf>,
+    },
+    /// Serves the gRPC proving service, and writes output to a directory.
+    Grpc {
+        /// The port on which to listen.
+        #[arg(short, long, default_value_t = DEFAULT_GRPC_PORT)]
+        port: u16,
+        /// The directory to which output should be written.
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        output_dir: PathBuf,
     },
 }
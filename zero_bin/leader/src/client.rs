@@ -2,16 +2,21 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use alloy::rpc::types::{BlockId, BlockNumberOrTag, BlockTransactionsKind};
+use alloy::rpc::types::{BlockId, BlockNumberOrTag};
 use alloy::transports::http::reqwest::Url;
-use anyhow::Result;
-use paladin::runtime::Runtime;
+use anyhow::{anyhow, Context as _, Result};
+use proof_gen::compression::BlockProofCompressor;
 use proof_gen::proof_types::GeneratedBlockProof;
+use proof_gen::wrapper::{wrap_proof, BlockProofWrapper, Groth16Prover};
+use prover::runtime::ProverRuntime;
 use prover::ProverConfig;
+use rpc::provider::RpcLimits;
 use rpc::{retry::build_http_retry_provider, RpcType};
 use tracing::{error, info, warn};
 use zero_bin_common::block_interval::BlockInterval;
 use zero_bin_common::fs::generate_block_proof_file_name;
+use zero_bin_common::prover_state::ProverStateManager;
+use zero_bin_common::tenant::Tenant;
 
 #[derive(Debug)]
 pub struct RpcParams {
@@ -19,6 +24,7 @@ pub struct RpcParams {
     pub rpc_type: RpcType,
     pub backoff: u64,
     pub max_retries: u32,
+    pub rpc_limits: RpcLimits,
 }
 
 #[derive(Debug)]
@@ -26,36 +32,62 @@ pub struct ProofParams {
     pub checkpoint_block_number: u64,
     pub previous_proof: Option<GeneratedBlockProof>,
     pub proof_output_dir: Option<PathBuf>,
+    /// The tenant the blocks are proved for, whose output directory is
+    /// `proof_output_dir`.
+    pub tenant: Option<Tenant>,
     pub prover_config: ProverConfig,
     pub keep_intermediate_proofs: bool,
+    pub wrap_groth16: Option<WrapParams>,
+    pub compress_final_proof: Option<CompressParams>,
+    #[cfg(feature = "postgres")]
+    pub proof_store_url: Option<String>,
+}
+
+/// The Groth16 wrapping of the last proof of an interval.
+#[derive(Debug)]
+pub struct WrapParams {
+    /// The external Groth16 prover, see [`Groth16Prover`].
+    pub groth16_prover: PathBuf,
+    /// The version the Groth16 prover must report, if pinned.
+    pub groth16_prover_version: Option<String>,
+    /// The circuits the block proofs are generated with.
+    pub prover_state_manager: ProverStateManager,
+}
+
+/// The compression of the last proof of an interval.
+#[derive(Debug)]
+pub struct CompressParams {
+    /// The circuits the block proofs are generated with.
+    pub prover_state_manager: ProverStateManager,
 }
 
 /// The main function for the client.
 pub(crate) async fn client_main(
-    runtime: Runtime,
+    runtime: ProverRuntime,
     rpc_params: RpcParams,
     block_interval: BlockInterval,
     mut params: ProofParams,
 ) -> Result<()> {
     use futures::{FutureExt, StreamExt};
 
-    let cached_provider = Arc::new(rpc::provider::CachedProvider::new(
+    let cached_provider = Arc::new(rpc::provider::CachedProvider::with_limits(
         build_http_retry_provider(
             rpc_params.rpc_url.clone(),
             rpc_params.backoff,
             rpc_params.max_retries,
         ),
+        rpc_params.rpc_limits,
     ));
 
-    // Grab interval checkpoint block state trie
-    let checkpoint_state_trie_root = cached_provider
-        .get_block(
-            params.checkpoint_block_number.into(),
-            BlockTransactionsKind::Hashes,
-        )
-        .await?
-        .header
-        .state_root;
+    // Grab interval checkpoint block state trie, along with the blocks of the
+    // interval in the same batched requests.
+    let checkpoint_state_trie_root = rpc::fetch_checkpoint_state_trie_root(
+        &cached_provider,
+        params.checkpoint_block_number.into(),
+        &block_interval,
+        rpc_params.rpc_type,
+    )
+    .await?;
 
     let mut block_prover_inputs = Vec::new();
     let mut block_interval = block_interval.into_bounded_stream()?;
@@ -72,21 +104,59 @@ pub(crate) async fn client_main(
         block_prover_inputs.push(block_prover_input);
     }
 
+    #[cfg(feature = "postgres")]
+    let proof_store = match params.proof_store_url.as_deref() {
+        Some(url) if !params.prover_config.test_only && !params.prover_config.dry_run => {
+            Some(ProofStoreWriter::spawn(url, &params).await?)
+        }
+        _ => None,
+    };
+
     // If `keep_intermediate_proofs` is not set we only keep the last block
     // proof from the interval. It contains all the necessary information to
     // verify the whole sequence.
-    let proved_blocks = prover::prove(
-        block_prover_inputs,
+    let mut proved_blocks = Vec::new();
+    let result = prover::prove_stream(
+        futures::stream::iter(block_prover_inputs),
         &runtime,
         params.previous_proof.take(),
         params.prover_config,
         params.proof_output_dir.clone(),
+        params.tenant.clone(),
+        |block_number, proof| {
+            // Store each proof as soon as it is generated, so that a failure
+            // later in the interval does not lose it.
+            #[cfg(feature = "postgres")]
+            if let Some(proof_store) = &proof_store {
+                proof_store.send(block_number, proof.clone())?;
+            }
+            proved_blocks.push((block_number, proof));
+            Ok(())
+        },
     )
     .await;
     runtime.close().await?;
-    let proved_blocks = proved_blocks?;
+    #[cfg(feature = "postgres")]
+    if let Some(proof_store) = proof_store {
+        proof_store.finish().await?;
+    }
+    result?;
 
-    if params.prover_config.test_only {
+    if let Some(wrap_params) = params.wrap_groth16.as_ref() {
+        if !params.prover_config.test_only && !params.prover_config.dry_run {
+            wrap_last_proof(wrap_params, &proved_blocks, &params)?;
+        }
+    }
+
+    if let Some(compress_params) = params.compress_final_proof.as_ref() {
+        if !params.prover_config.test_only && !params.prover_config.dry_run {
+            compress_last_proof(compress_params, &proved_blocks, &params)?;
+        }
+    }
+
+    if params.prover_config.dry_run {
+        info!("All blocks have been dry-run successfully.");
+    } else if params.prover_config.test_only {
         info!("All proof witnesses have been generated successfully.");
     } else {
         info!("All proofs have been generated successfully.");
@@ -120,7 +190,7 @@ pub(crate) async fn client_main(
                         error!("Failed to remove intermediate proof file: {e}");
                     }
                 });
-        } else {
+        } else if params.wrap_groth16.is_none() && params.compress_final_proof.is_none() {
             // Output only last proof to stdout
             if let Some(last_block) = proved_blocks
                 .into_iter()
@@ -134,3 +204,163 @@ pub(crate) async fn client_main(
 
     Ok(())
 }
+
+/// Returns the last of the generated proofs, read back from
+/// `proof_output_dir` if it was written there.
+fn last_proof(
+    proved_blocks: &[(u64, Option<GeneratedBlockProof>)],
+    params: &ProofParams,
+) -> Result<Option<(u64, GeneratedBlockProof)>> {
+    let Some((block_number, proof)) = proved_blocks.last() else {
+        return Ok(None);
+    };
+    let proof = match (proof, params.proof_output_dir.as_ref()) {
+        (Some(proof), _) => proof.clone(),
+        (None, Some(proof_output_dir)) => {
+            let path = generate_block_proof_file_name(&proof_output_dir.to_str(), *block_number);
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .with_context(|| format!("failed to read proof {path:?}"))?
+        }
+        (None, None) => return Ok(None),
+    };
+    Ok(Some((*block_number, proof)))
+}
+
+/// Wraps the last of the generated proofs into a Groth16 proof, which is
+/// written next to it in `proof_output_dir`, or to stdout in its place.
+fn wrap_last_proof(
+    wrap_params: &WrapParams,
+    proved_blocks: &[(u64, Option<GeneratedBlockProof>)],
+    params: &ProofParams,
+) -> Result<()> {
+    let Some((block_number, proof)) = last_proof(proved_blocks, params)? else {
+        return Ok(());
+    };
+
+    info!("Wrapping the proof of block {block_number} into a Groth16 proof");
+    let verifier = wrap_params.prover_state_manager.verifier()?;
+    let wrapper = BlockProofWrapper::new(&verifier.state);
+    let groth16_prover = match &wrap_params.groth16_prover_version {
+        Some(version) => Groth16Prover::new(&wrap_params.groth16_prover).pinned_to(version),
+        None => Groth16Prover::new(&wrap_params.groth16_prover),
+    };
+    let wrapped = wrap_proof(&wrapper, &groth16_prover, &proof)
+        .map_err(|err| anyhow!(err.0))
+        .with_context(|| format!("failed to wrap the proof of block {block_number}"))?;
+
+    let wrapped = serde_json::to_vec(&wrapped)?;
+    match params.proof_output_dir.as_ref() {
+        Some(proof_output_dir) => {
+            let path = proof_output_dir.join(format!("b{block_number}_groth16.json"));
+            std::fs::write(&path, wrapped)?;
+            info!("Wrote the Groth16 proof of block {block_number} to {path:?}");
+        }
+        None => std::io::stdout().write_all(&wrapped)?,
+    }
+
+    Ok(())
+}
+
+/// Compresses the last of the generated proofs, which is written next to it
+/// in `proof_output_dir`, or to stdout in its place.
+fn compress_last_proof(
+    compress_params: &CompressParams,
+    proved_blocks: &[(u64, Option<GeneratedBlockProof>)],
+    params: &ProofParams,
+) -> Result<()> {
+    let Some((block_number, proof)) = last_proof(proved_blocks, params)? else {
+        return Ok(());
+    };
+
+    info!("Compressing the proof of block {block_number}");
+    let verifier = compress_params.prover_state_manager.verifier()?;
+    let compressor = BlockProofCompressor::new(&verifier.state);
+    let compressed = compressor
+        .compress(&proof)
+        .map_err(|err| anyhow!(err.0))
+        .with_context(|| format!("failed to compress the proof of block {block_number}"))?;
+
+    let compressed = serde_json::to_vec(&compressed)?;
+    match params.proof_output_dir.as_ref() {
+        Some(proof_output_dir) => {
+            let path = proof_output_dir.join(format!("b{block_number}_compressed.json"));
+            std::fs::write(&path, compressed)?;
+            info!("Wrote the compressed proof of block {block_number} to {path:?}");
+        }
+        None => std::io::stdout().write_all(&compressed)?,
+    }
+
+    Ok(())
+}
+
+/// Stores the generated proofs in a PostgreSQL proof store, in the background,
+/// as soon as they are sent to it.
+#[cfg(feature = "postgres")]
+struct ProofStoreWriter {
+    proofs: tokio::sync::mpsc::UnboundedSender<(u64, Option<GeneratedBlockProof>)>,
+    task: tokio::task::JoinHandle<Result<usize>>,
+}
+
+#[cfg(feature = "postgres")]
+impl ProofStoreWriter {
+    /// Connects to the proof store at `url`, and spawns the task storing the
+    /// proofs sent to the writer.
+    ///
+    /// Proofs that have been written to `proof_output_dir` are read back from
+    /// there.
+    async fn spawn(url: &str, params: &ProofParams) -> Result<Self> {
+        use zero_bin_common::proof_store::{PostgresProofStore, ProvingMetadata};
+
+        let store = PostgresProofStore::connect(url)
+            .await
+            .context("failed to connect to the proof store")?;
+        let metadata = ProvingMetadata::new(
+            params.prover_config.batch_size,
+            params.prover_config.max_cpu_len_log,
+        );
+        let proof_output_dir = params.proof_output_dir.clone();
+
+        let (proofs, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut stored = 0;
+            while let Some((block_number, proof)) = rx.recv().await {
+                let proof = match (proof, proof_output_dir.as_ref()) {
+                    (Some(proof), _) => proof,
+                    (None, Some(proof_output_dir)) => {
+                        let path = generate_block_proof_file_name(
+                            &proof_output_dir.to_str(),
+                            block_number,
+                        );
+                        serde_json::from_slice(&tokio::fs::read(&path).await?)
+                            .with_context(|| format!("failed to read proof {path:?}"))?
+                    }
+                    (None, None) => continue,
+                };
+                store
+                    .put(&proof, &metadata)
+                    .await
+                    .with_context(|| format!("failed to store proof of block {block_number}"))?;
+                stored += 1;
+            }
+            Ok(stored)
+        });
+
+        Ok(Self { proofs, task })
+    }
+
+    /// Queues the proof of `block_number` for storage.
+    fn send(&self, block_number: u64, proof: Option<GeneratedBlockProof>) -> Result<()> {
+        self.proofs
+            .send((block_number, proof))
+            .map_err(|_| anyhow!("the proof store writer stopped"))
+    }
+
+    /// Waits for all the queued proofs to be stored.
+    async fn finish(self) -> Result<()> {
+        drop(self.proofs);
+        let stored = self.task.await??;
+        info!("Stored {stored} proofs in the proof store");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use proof_gen::proof_types::GeneratedBlockProof;
+use prover::runtime::ProverRuntime;
+use prover::{BlockProverInputFuture, ProverConfig};
+use rpc::era::Era1Index;
+use tracing::info;
+use zero_bin_common::block_interval::BlockInterval;
+
+#[derive(Debug)]
+pub struct EraParams {
+    pub era_dir: PathBuf,
+    pub witness_dir: PathBuf,
+    pub chain_id: u64,
+    pub checkpoint_block_number: u64,
+}
+
+/// The main function for the era mode.
+pub(crate) async fn era_main(
+    runtime: ProverRuntime,
+    era_params: EraParams,
+    block_interval: BlockInterval,
+    previous_proof: Option<GeneratedBlockProof>,
+    proof_output_dir: Option<PathBuf>,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    use futures::StreamExt;
+
+    let index = Arc::new(Era1Index::open(&era_params.era_dir)?);
+    let witness_dir = Arc::new(era_params.witness_dir);
+
+    // Grab interval checkpoint block state trie
+    let checkpoint_state_trie_root = index.header(era_params.checkpoint_block_number)?.state_root;
+
+    let mut block_prover_inputs = Vec::new();
+    let mut block_interval = block_interval.into_bounded_stream()?;
+    while let Some(block_num) = block_interval.next().await {
+        let index = index.clone();
+        let witness_dir = witness_dir.clone();
+        let chain_id = era_params.chain_id;
+        // Archives are read on a blocking thread when the prover polls the input.
+        let block_prover_input: BlockProverInputFuture = Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                rpc::era::block_prover_input(
+                    &index,
+                    &witness_dir,
+                    block_num,
+                    chain_id,
+                    checkpoint_state_trie_root,
+                )
+            })
+            .await?
+        });
+        block_prover_inputs.push(block_prover_input);
+    }
+
+    let proved_blocks = prover::prove(
+        block_prover_inputs,
+        &runtime,
+        previous_proof,
+        prover_config,
+        proof_output_dir,
+        None,
+    )
+    .await;
+    runtime.close().await?;
+    let proved_blocks = proved_blocks?;
+
+    if prover_config.dry_run {
+        info!("All blocks have been dry-run successfully.");
+    } else if prover_config.test_only {
+        info!("All proof witnesses have been generated successfully.");
+    } else {
+        info!("All proofs have been generated successfully.");
+    }
+
+    let proofs: Vec<GeneratedBlockProof> = proved_blocks
+        .into_iter()
+        .filter_map(|(_, proof)| proof)
+        .collect();
+    if !proofs.is_empty() {
+        std::io::stdout().write_all(&serde_json::to_vec(&proofs)?)?;
+    }
+
+    Ok(())
+}
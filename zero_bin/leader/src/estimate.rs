@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use prover::{BlockCostEstimate, BlockProverInput, ProverConfig};
+use tracing::info;
+
+/// The main function for the estimate mode.
+///
+/// Decodes and segments each block of the input, and writes the estimated
+/// proving cost of each of them to stdout.
+///
+/// With `profile_cycles`, the cycle profile of each segment is written to
+/// `b<block>_batch<batch>_segment<segment>.folded` in this directory.
+pub(crate) fn estimate_main(
+    input: Option<PathBuf>,
+    profile_cycles: Option<PathBuf>,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    let mut buffer = String::new();
+    match input {
+        Some(path) => File::open(path)?.read_to_string(&mut buffer)?,
+        None => std::io::stdin().read_to_string(&mut buffer)?,
+    };
+
+    let des = &mut serde_json::Deserializer::from_str(&buffer);
+    let block_prover_inputs = serde_path_to_error::deserialize::<_, Vec<BlockProverInput>>(des)?;
+
+    let estimates = block_prover_inputs
+        .into_iter()
+        .map(|input| {
+            let estimate = input.estimate(prover_config, profile_cycles.is_some())?;
+            if let Some(dir) = &profile_cycles {
+                write_cycle_profiles(dir, &estimate)?;
+            }
+            info!(
+                "Block {}: {} batches, {} segments, {} cycles",
+                estimate.block_number,
+                estimate.batches.len(),
+                estimate.total_segments,
+                estimate.total_cycles
+            );
+            info!(
+                "Block {}: {} cycles computing the final state tries, skipping {} unchanged \
+                 slots and {} unchanged accounts",
+                estimate.block_number,
+                estimate.total_final_tries.cycles,
+                estimate.total_final_tries.skipped_slots,
+                estimate.total_final_tries.skipped_accounts
+            );
+            Ok(estimate)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    std::io::stdout().write_all(&serde_json::to_vec_pretty(&estimates)?)?;
+
+    Ok(())
+}
+
+fn write_cycle_profiles(dir: &Path, estimate: &BlockCostEstimate) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (batch_index, batch) in estimate.batches.iter().enumerate() {
+        for (segment_index, profile) in batch.cycle_profiles.iter().enumerate() {
+            let path = dir.join(format!(
+                "b{}_batch{batch_index}_segment{segment_index}.folded",
+                estimate.block_number
+            ));
+            std::fs::write(&path, profile.to_folded())
+                .with_context(|| format!("couldn't write the cycle profile {path:?}"))?;
+        }
+    }
+    info!(
+        "Block {}: wrote the cycle profiles to {}",
+        estimate.block_number,
+        dir.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, Result};
+use futures::{Stream, StreamExt as _};
+use proof_gen::proof_types::GeneratedBlockProof;
+use prover::api::ProvingEvent;
+use prover::runtime::ProverRuntime;
+use prover::{BlockProverInput, ProverConfig};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, warn};
+use zero_bin_common::grpc::proto::{self, proving_event::Kind, BlockState};
+use zero_bin_common::grpc::{ProvingService, ProvingServiceServer};
+
+use crate::http::write_to_file;
+
+/// The number of progress events buffered for slow subscribers of
+/// `StreamEvents`, beyond which they miss events.
+const EVENT_BUFFER: usize = 1024;
+
+/// The main function for the gRPC mode.
+pub(crate) async fn grpc_main(
+    runtime: ProverRuntime,
+    port: u16,
+    output_dir: PathBuf,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    debug!("listening on {}", addr);
+
+    let service = GrpcProver {
+        runtime: Arc::new(runtime),
+        output_dir,
+        prover_config,
+        jobs: Default::default(),
+        events: broadcast::channel(EVENT_BUFFER).0,
+    };
+    tonic::transport::Server::builder()
+        .add_service(ProvingServiceServer::new(service))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+/// The state of the proof of a submitted block.
+#[derive(Debug, Clone)]
+enum Job {
+    Proving,
+    Proved(PathBuf),
+    Failed(String),
+}
+
+struct GrpcProver {
+    runtime: Arc<ProverRuntime>,
+    output_dir: PathBuf,
+    prover_config: ProverConfig,
+    jobs: Arc<Mutex<HashMap<u64, Job>>>,
+    events: broadcast::Sender<proto::ProvingEvent>,
+}
+
+impl GrpcProver {
+    fn job(&self, block_number: u64) -> Result<Job, Status> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&block_number)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("block {block_number} was not submitted")))
+    }
+}
+
+#[tonic::async_trait]
+impl ProvingService for GrpcProver {
+    async fn submit_block(
+        &self,
+        request: Request<proto::SubmitBlockRequest>,
+    ) -> Result<Response<proto::SubmitBlockResponse>, Status> {
+        let request = request.into_inner();
+        let prover_input: BlockProverInput = serde_json::from_slice(&request.prover_input)
+            .map_err(|e| Status::invalid_argument(format!("invalid prover input: {e}")))?;
+        let previous: Option<GeneratedBlockProof> = request
+            .previous_proof
+            .map(|proof| serde_json::from_slice(&proof))
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("invalid previous proof: {e}")))?;
+        let block_number = u64::try_from(prover_input.get_block_number())
+            .map_err(|_| Status::invalid_argument("the block number does not fit in 64 bits"))?;
+
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(Job::Proving) = jobs.get(&block_number) {
+                return Err(Status::already_exists(format!(
+                    "block {block_number} is already being proved"
+                )));
+            }
+            jobs.insert(block_number, Job::Proving);
+        }
+        info!("Proving block {block_number}");
+
+        let runtime = self.runtime.clone();
+        let output_dir = self.output_dir.clone();
+        let prover_config = self.prover_config;
+        let jobs = self.jobs.clone();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let job = match prove(
+                prover_input,
+                previous,
+                &runtime,
+                &output_dir,
+                prover_config,
+                &events,
+            )
+            .await
+            {
+                Ok(file) => {
+                    info!("Successfully wrote proof to {}", file.display());
+                    Job::Proved(file)
+                }
+                Err(e) => {
+                    error!("Error while proving block {block_number}: {e:#?}");
+                    let _ = events.send(proto::ProvingEvent {
+                        block_number,
+                        kind: Some(Kind::BlockFailed(proto::BlockFailed {
+                            error: format!("{e:#}"),
+                        })),
+                    });
+                    Job::Failed(format!("{e:#}"))
+                }
+            };
+            jobs.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(block_number, job);
+        });
+
+        Ok(Response::new(proto::SubmitBlockResponse { block_number }))
+    }
+
+    async fn get_status(
+        &self,
+        request: Request<proto::GetStatusRequest>,
+    ) -> Result<Response<proto::GetStatusResponse>, Status> {
+        let (state, error) = match self.job(request.into_inner().block_number)? {
+            Job::Proving => (BlockState::Proving, None),
+            Job::Proved(_) => (BlockState::Proved, None),
+            Job::Failed(e) => (BlockState::Failed, Some(e)),
+        };
+        Ok(Response::new(proto::GetStatusResponse {
+            state: state.into(),
+            error,
+        }))
+    }
+
+    async fn fetch_proof(
+        &self,
+        request: Request<proto::FetchProofRequest>,
+    ) -> Result<Response<proto::FetchProofResponse>, Status> {
+        let block_number = request.into_inner().block_number;
+        let Job::Proved(file) = self.job(block_number)? else {
+            return Err(Status::failed_precondition(format!(
+                "block {block_number} is not proved"
+            )));
+        };
+        let proof = tokio::fs::read(&file)
+            .await
+            .map_err(|e| Status::internal(format!("couldn't read {}: {e}", file.display())))?;
+        Ok(Response::new(proto::FetchProofResponse { proof }))
+    }
+
+    type StreamEventsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::ProvingEvent, Status>> + Send>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let block_number = request.into_inner().block_number;
+        let events = BroadcastStream::new(self.events.subscribe()).filter_map(move |event| {
+            let event = match event {
+                Ok(event) => event,
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    warn!("event subscriber lagging behind, dropped {missed} events");
+                    return futures::future::ready(None);
+                }
+            };
+            let wanted = block_number.map_or(true, |b| b == event.block_number);
+            futures::future::ready(wanted.then_some(Ok(event)))
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Proves a block as in the HTTP mode, forwarding its progress events to
+/// `events`, and returns the file its proof was written to.
+async fn prove(
+    prover_input: BlockProverInput,
+    previous: Option<GeneratedBlockProof>,
+    runtime: &ProverRuntime,
+    output_dir: &Path,
+    prover_config: ProverConfig,
+    events: &broadcast::Sender<proto::ProvingEvent>,
+) -> Result<PathBuf> {
+    let block_number = prover_input.get_block_number();
+    let previous = previous.map(futures::future::ok);
+
+    let proof = if prover_config.dry_run {
+        prover_input
+            .prove_dry_run(runtime, previous, prover_config)
+            .await
+            .map(|(proof, _)| proof)?
+    } else if prover_config.test_only {
+        prover_input
+            .prove_test(runtime, previous, prover_config)
+            .await?
+    } else {
+        let (sender, mut receiver) = mpsc::channel(EVENT_BUFFER);
+        let forward = {
+            let events = events.clone();
+            tokio::spawn(async move {
+                while let Some(event) = receiver.recv().await {
+                    // Sending only fails when there is no subscriber.
+                    let _ = events.send(to_proto(event));
+                }
+            })
+        };
+        let proof = prover_input
+            .prove(
+                runtime,
+                previous,
+                prover_config,
+                Some(output_dir),
+                Some(sender),
+            )
+            .await;
+        forward.await.context("event forwarding panicked")?;
+        proof?
+    };
+
+    write_to_file(output_dir.to_path_buf(), block_number, &proof)
+}
+
+/// Converts a progress event of the prover to its message.
+fn to_proto(event: ProvingEvent) -> proto::ProvingEvent {
+    let (block_number, kind) = match event {
+        ProvingEvent::BlockStarted {
+            block_number,
+            batches,
+        } => (
+            block_number,
+            Kind::BlockStarted(proto::BlockStarted {
+                batches: batches as u64,
+            }),
+        ),
+        ProvingEvent::SegmentProved {
+            block_number,
+            batch_index,
+            segment_index,
+            elapsed,
+        } => (
+            block_number,
+            Kind::SegmentProved(proto::SegmentProved {
+                batch_index: batch_index as u64,
+                segment_index: segment_index as u64,
+                elapsed_ms: elapsed.as_millis() as u64,
+            }),
+        ),
+        ProvingEvent::BatchAggregated {
+            block_number,
+            batch_index,
+            elapsed,
+        } => (
+            block_number,
+            Kind::BatchAggregated(proto::BatchAggregated {
+                batch_index: batch_index as u64,
+                elapsed_ms: elapsed.as_millis() as u64,
+            }),
+        ),
+        ProvingEvent::BlockProved {
+            block_number,
+            elapsed,
+        } => (
+            block_number,
+            Kind::BlockProved(proto::BlockProved {
+                elapsed_ms: elapsed.as_millis() as u64,
+            }),
+        ),
+    };
+    proto::ProvingEvent {
+        block_number,
+        kind: Some(kind),
+    }
+}
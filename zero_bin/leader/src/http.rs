@@ -1,33 +1,74 @@
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Instant};
 
 use alloy::primitives::U256;
 use anyhow::{bail, Result};
-use axum::{http::StatusCode, routing::post, Json, Router};
-use paladin::runtime::Runtime;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
 use proof_gen::proof_types::GeneratedBlockProof;
+use prover::runtime::ProverRuntime;
 use prover::{BlockProverInput, ProverConfig};
 use serde::{Deserialize, Serialize};
 use serde_json::to_writer;
 use tracing::{debug, error, info};
+use zero_bin_common::tenant::{Tenant, TenantError, TenantRegistry, TenantStats};
 
 /// The main function for the HTTP mode.
+///
+/// Blocks posted to `/prove` are written to `output_dir`, and those posted to
+/// `/tenants/<tenant>/prove` to the directory of the tenant within it, within
+/// the quotas of `tenants`.
 pub(crate) async fn http_main(
-    runtime: Runtime,
+    runtime: ProverRuntime,
     port: u16,
     output_dir: PathBuf,
+    tenants: TenantRegistry,
     prover_config: ProverConfig,
 ) -> Result<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     debug!("listening on {}", addr);
 
     let runtime = Arc::new(runtime);
-    let app = Router::new().route(
-        "/prove",
-        post({
-            let runtime = runtime.clone();
-            move |body| prove(body, runtime, output_dir.clone(), prover_config)
-        }),
-    );
+    let tenants = Arc::new(tenants);
+    let app = Router::new()
+        .route(
+            "/prove",
+            post({
+                let runtime = runtime.clone();
+                let output_dir = output_dir.clone();
+                move |body| prove(body, runtime, output_dir.clone(), prover_config, None)
+            }),
+        )
+        .route(
+            "/tenants/:tenant/prove",
+            post({
+                let tenants = tenants.clone();
+                move |tenant, body| {
+                    tenant_prove(
+                        tenant,
+                        body,
+                        runtime,
+                        output_dir.clone(),
+                        tenants,
+                        prover_config,
+                    )
+                }
+            }),
+        )
+        .route(
+            "/tenants/:tenant/stats",
+            get({
+                let tenants = tenants.clone();
+                move |tenant| tenant_stats(tenant, tenants)
+            }),
+        )
+        .route(
+            "/tenants",
+            get(move || async move { Json(tenants.all_stats()) }),
+        );
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     Ok(axum::serve(listener, app).await?)
 }
@@ -35,7 +76,7 @@ pub(crate) async fn http_main(
 /// Writes the generated block proof to a file.
 ///
 /// Returns the fully qualified file name.
-fn write_to_file(
+pub(crate) fn write_to_file(
     output_dir: PathBuf,
     block_number: U256,
     generated_block_proof: &GeneratedBlockProof,
@@ -63,15 +104,27 @@ struct HttpProverInput {
 
 async fn prove(
     Json(payload): Json<HttpProverInput>,
-    runtime: Arc<Runtime>,
+    runtime: Arc<ProverRuntime>,
     output_dir: PathBuf,
     prover_config: ProverConfig,
+    tenant: Option<&Tenant>,
 ) -> StatusCode {
+    let tenant = tenant.map(Tenant::as_str);
     debug!("Received payload: {:#?}", payload);
 
     let block_number = payload.prover_input.get_block_number();
 
-    let proof_res = if prover_config.test_only {
+    let proof_res = if prover_config.dry_run {
+        payload
+            .prover_input
+            .prove_dry_run(
+                &runtime,
+                payload.previous.map(futures::future::ok),
+                prover_config,
+            )
+            .await
+            .map(|(proof, _)| proof)
+    } else if prover_config.test_only {
         payload
             .prover_input
             .prove_test(
@@ -87,24 +140,90 @@ async fn prove(
                 &runtime,
                 payload.previous.map(futures::future::ok),
                 prover_config,
+                Some(&output_dir),
+                None,
             )
             .await
     };
 
+    if let Some(memory) = u64::try_from(block_number)
+        .ok()
+        .and_then(zero_bin_common::memory::take_block_memory)
+    {
+        info!(
+            tenant,
+            "Block {block_number}: {} ops, peak RSS growth of {} bytes at most, worker peak RSS of {} bytes",
+            memory.ops, memory.max_peak_delta, memory.max_peak_rss
+        );
+    }
+
     match proof_res {
         Ok(b_proof) => match write_to_file(output_dir, block_number, &b_proof) {
             Ok(file) => {
-                info!("Successfully wrote proof to {}", file.display());
+                info!(tenant, "Successfully wrote proof to {}", file.display());
                 StatusCode::OK
             }
             Err(e) => {
-                error!("{e}");
+                error!(tenant, "{e}");
                 StatusCode::INTERNAL_SERVER_ERROR
             }
         },
         Err(e) => {
-            error!("Error while proving block {block_number}: {e:#?}");
+            error!(tenant, "Error while proving block {block_number}: {e:#?}");
             StatusCode::INTERNAL_SERVER_ERROR
         }
     }
 }
+
+/// Proves a block for a tenant, within its quota.
+async fn tenant_prove(
+    Path(tenant): Path<String>,
+    payload: Json<HttpProverInput>,
+    runtime: Arc<ProverRuntime>,
+    output_dir: PathBuf,
+    tenants: Arc<TenantRegistry>,
+    prover_config: ProverConfig,
+) -> (StatusCode, String) {
+    let tenant = match tenant.parse::<Tenant>() {
+        Ok(tenant) => tenant,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let permit = match tenants.acquire(&tenant) {
+        Ok(permit) => permit,
+        Err(e @ TenantError::UnknownTenant(_)) => return (StatusCode::NOT_FOUND, e.to_string()),
+        Err(e) => {
+            info!(tenant = tenant.as_str(), "{e}");
+            return (StatusCode::TOO_MANY_REQUESTS, e.to_string());
+        }
+    };
+
+    let output_dir = tenant.output_dir(&output_dir);
+    if let Err(e) = std::fs::create_dir_all(&output_dir) {
+        error!(
+            tenant = tenant.as_str(),
+            "Error while creating {output_dir:?}: {e}"
+        );
+        permit.failed();
+        return (StatusCode::INTERNAL_SERVER_ERROR, String::new());
+    }
+
+    let start = Instant::now();
+    let status = prove(payload, runtime, output_dir, prover_config, Some(&tenant)).await;
+    if status.is_success() {
+        permit.proved(start.elapsed());
+    } else {
+        permit.failed();
+    }
+    (status, String::new())
+}
+
+/// Returns the statistics of a tenant.
+async fn tenant_stats(
+    Path(tenant): Path<String>,
+    tenants: Arc<TenantRegistry>,
+) -> Result<Json<TenantStats>, (StatusCode, String)> {
+    let tenant = tenant
+        .parse::<Tenant>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(tenants.stats(&tenant)))
+}
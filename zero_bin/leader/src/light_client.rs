@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use alloy::rpc::types::BlockTransactionsKind;
+use alloy::transports::http::reqwest::Url;
+use anyhow::{Context as _, Result};
+use proof_gen::light_client::LightClientUpdate;
+use proof_gen::proof_types::GeneratedBlockProof;
+use rpc::{provider::CachedProvider, retry::build_http_retry_provider};
+use tracing::info;
+
+/// The main function for the light client update export mode.
+///
+/// Packages the block proofs in `proofs`, along with the headers of their
+/// blocks retrieved from the node at `rpc_url`, into a light client update
+/// written to `output`.
+pub(crate) async fn light_client_update_main(
+    proofs: PathBuf,
+    rpc_url: Url,
+    output: &Path,
+) -> Result<()> {
+    let file = File::open(&proofs).with_context(|| format!("failed to open {proofs:?}"))?;
+    let des = &mut serde_json::Deserializer::from_reader(&file);
+    let mut proofs: Vec<GeneratedBlockProof> = serde_path_to_error::deserialize(des)?;
+    proofs.sort_by_key(|proof| proof.b_height);
+
+    let provider = CachedProvider::new(build_http_retry_provider(rpc_url, 0, 0));
+    let numbers = proofs
+        .iter()
+        .map(|proof| proof.b_height)
+        .collect::<Vec<_>>();
+    let headers = provider
+        .get_blocks_by_number(&numbers, BlockTransactionsKind::Hashes)
+        .await?
+        .into_iter()
+        .map(|block| {
+            let header = alloy::consensus::Header::try_from(block.header)
+                .map_err(|err| anyhow::anyhow!("invalid block header: {err}"))?;
+            Ok(alloy::rlp::encode(&header))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let update = LightClientUpdate::from_block_proofs(&proofs, headers)?;
+    info!(
+        "Exporting a light client update of blocks {}..={}",
+        numbers[0], update.header.number
+    );
+    let file = File::create(output).with_context(|| format!("failed to create {output:?}"))?;
+    serde_json::to_writer(file, &update)?;
+
+    Ok(())
+}
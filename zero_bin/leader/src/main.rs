@@ -1,28 +1,40 @@
 use std::{env, io};
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use clap::Parser;
 use cli::Command;
 use client::RpcParams;
 use dotenvy::dotenv;
-use ops::register;
-use paladin::runtime::Runtime;
 use proof_gen::proof_types::GeneratedBlockProof;
+use prover::runtime::ProverRuntime;
 use prover::ProverConfig;
+use rpc::provider::RpcLimits;
 use tracing::{info, warn};
 use zero_bin_common::{
-    block_interval::BlockInterval, prover_state::persistence::set_circuit_cache_dir_env_if_not_set,
+    block_interval::BlockInterval, compression::log_compression_metrics,
+    prover_state::persistence::set_circuit_cache_dir_env_if_not_set, tenant::TenantRegistry,
 };
 use zero_bin_common::{prover_state::persistence::CIRCUIT_VERSION, version};
 
-use crate::client::{client_main, ProofParams};
+use crate::client::{client_main, CompressParams, ProofParams, WrapParams};
 
+mod archive;
+mod calibrate;
 mod cli;
 mod client;
+mod era;
+mod estimate;
+mod grpc;
 mod http;
 mod init;
+mod light_client;
+mod op_settle;
 mod stdio;
+mod train_dictionary;
 
 fn get_previous_proof(path: Option<PathBuf>) -> Result<Option<GeneratedBlockProof>> {
     if path.is_none() {
@@ -36,6 +48,20 @@ fn get_previous_proof(path: Option<PathBuf>) -> Result<Option<GeneratedBlockProo
     Ok(Some(proof))
 }
 
+/// Reads the proof of the block immediately preceding `block_interval` from
+/// `proof_output_dir`, to be used as the previous proof.
+///
+/// See [`prover::previous_block_proof`] for the handling of gaps.
+fn discover_previous_proof(
+    proof_output_dir: &Path,
+    block_interval: &BlockInterval,
+) -> Result<Option<GeneratedBlockProof>> {
+    let Ok(start_block) = block_interval.get_start_block() else {
+        return Ok(None);
+    };
+    prover::previous_block_proof(proof_output_dir, start_block)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     load_dotenvy_vars_if_present();
@@ -54,18 +80,63 @@ async fn main() -> Result<()> {
     }
 
     let args = cli::Cli::parse();
-
-    let runtime = Runtime::from_config(&args.paladin, register()).await?;
+    args.compression_config.install()?;
 
     let prover_config: ProverConfig = args.prover_config.into();
 
-    // If not in test_only mode and running in emulation mode, we'll need to
-    // initialize the prover state here.
-    if !prover_config.test_only {
+    // Estimation does not prove anything, hence requires neither a runtime nor
+    // the prover state.
+    if let Command::Estimate {
+        input,
+        profile_cycles,
+    } = args.command
+    {
+        return estimate::estimate_main(input, profile_cycles, prover_config);
+    }
+
+    // Neither does training a compression dictionary.
+    if let Command::TrainDictionary {
+        input,
+        output,
+        max_size,
+    } = args.command
+    {
+        return train_dictionary::train_dictionary_main(input, &output, max_size, prover_config);
+    }
+
+    // Settlement only reads existing proofs.
+    if let Command::OpSettle {
+        proofs,
+        rpc_url,
+        game_type,
+    } = args.command
+    {
+        return op_settle::op_settle_main(proofs, rpc_url, game_type).await;
+    }
+
+    if let Command::LightClientUpdate {
+        proofs,
+        rpc_url,
+        output,
+    } = args.command
+    {
+        return light_client::light_client_update_main(proofs, rpc_url, &output).await;
+    }
+
+    let segment_worker_tags = args.segment_worker_tags.unwrap_or_default();
+    let runtime = ProverRuntime::from_config(
+        &args.paladin,
+        &segment_worker_tags,
+        prover_config.compress_payloads,
+    )
+    .await?;
+
+    // If not in test_only or dry_run mode and running in emulation mode, we'll
+    // need to initialize the prover state here.
+    let prover_state_manager = args.prover_state_config.into_prover_state_manager()?;
+    if !prover_config.test_only && !prover_config.dry_run {
         if let paladin::config::Runtime::InMemory = args.paladin.runtime {
-            args.prover_state_config
-                .into_prover_state_manager()
-                .initialize()?;
+            prover_state_manager.initialize()?;
         }
     }
 
@@ -74,17 +145,91 @@ async fn main() -> Result<()> {
             let previous_proof = get_previous_proof(previous_proof)?;
             stdio::stdio_main(runtime, previous_proof, prover_config).await?;
         }
-        Command::Http { port, output_dir } => {
-            // check if output_dir exists, is a directory, and is writable
-            let output_dir_metadata = std::fs::metadata(&output_dir);
-            if output_dir_metadata.is_err() {
-                // Create output directory
-                std::fs::create_dir(&output_dir)?;
-            } else if !output_dir.is_dir() || output_dir_metadata?.permissions().readonly() {
-                panic!("output-dir is not a writable directory");
-            }
+        Command::Era {
+            era_dir,
+            witness_dir,
+            block_interval,
+            chain_id,
+            checkpoint_block_number,
+            previous_proof,
+            proof_output_dir,
+        } => {
+            let block_interval = BlockInterval::new(&block_interval)?;
+            let previous_proof = get_previous_proof(previous_proof)?;
 
-            http::http_main(runtime, port, output_dir, prover_config).await?;
+            info!("Proving interval {block_interval} from era1 archives");
+            era::era_main(
+                runtime,
+                era::EraParams {
+                    era_dir,
+                    witness_dir,
+                    chain_id,
+                    checkpoint_block_number,
+                },
+                block_interval,
+                previous_proof,
+                proof_output_dir,
+                prover_config,
+            )
+            .await?;
+        }
+        Command::Archive {
+            archive,
+            block_interval,
+            previous_proof,
+            proof_output_dir,
+        } => {
+            let block_interval = block_interval
+                .map(|it| BlockInterval::new(&it))
+                .transpose()?;
+            let previous_proof = get_previous_proof(previous_proof)?;
+
+            info!("Proving blocks from archive {archive:?}");
+            archive::archive_main(
+                runtime,
+                archive,
+                block_interval,
+                previous_proof,
+                proof_output_dir,
+                prover_config,
+            )
+            .await?;
+        }
+        Command::Estimate { .. }
+        | Command::TrainDictionary { .. }
+        | Command::OpSettle { .. }
+        | Command::LightClientUpdate { .. } => {
+            unreachable!("handled above")
+        }
+        Command::Calibrate {
+            max_cpu_len_logs,
+            loops,
+            repetitions,
+            output,
+        } => {
+            anyhow::ensure!(
+                !prover_config.test_only && !prover_config.dry_run,
+                "calibration proves segments, and is not available in test-only or dry-run mode"
+            );
+            calibrate::calibrate_main(runtime, max_cpu_len_logs, loops, repetitions, output)
+                .await?;
+        }
+        Command::Http {
+            port,
+            output_dir,
+            tenants,
+        } => {
+            ensure_output_dir(&output_dir)?;
+            let tenants = match tenants {
+                Some(path) => TenantRegistry::from_file(&path)
+                    .with_context(|| format!("invalid tenants file {path:?}"))?,
+                None => TenantRegistry::open(),
+            };
+            http::http_main(runtime, port, output_dir, tenants, prover_config).await?;
+        }
+        Command::Grpc { port, output_dir } => {
+            ensure_output_dir(&output_dir)?;
+            grpc::grpc_main(runtime, port, output_dir, prover_config).await?;
         }
         Command::Rpc {
             rpc_url,
@@ -93,14 +238,36 @@ async fn main() -> Result<()> {
             checkpoint_block_number,
             previous_proof,
             proof_output_dir,
+            tenant,
             block_time,
             keep_intermediate_proofs,
             backoff,
             max_retries,
+            rpc_batch_size,
+            rpc_max_concurrency,
+            wrap_groth16,
+            groth16_prover_version,
+            compress_final_proof,
+            #[cfg(feature = "postgres")]
+            proof_store_url,
         } => {
-            let runtime = Runtime::from_config(&args.paladin, register()).await?;
-            let previous_proof = get_previous_proof(previous_proof)?;
+            let runtime = ProverRuntime::from_config(
+                &args.paladin,
+                &segment_worker_tags,
+                prover_config.compress_payloads,
+            )
+            .await?;
             let mut block_interval = BlockInterval::new(&block_interval)?;
+            let proof_output_dir = match (&tenant, proof_output_dir) {
+                (Some(tenant), Some(dir)) => Some(tenant.output_dir(&dir)),
+                (_, dir) => dir,
+            };
+            let previous_proof = match (previous_proof, &proof_output_dir) {
+                (None, Some(proof_output_dir)) => {
+                    discover_previous_proof(proof_output_dir, &block_interval)?
+                }
+                (previous_proof, _) => get_previous_proof(previous_proof)?,
+            };
 
             if let BlockInterval::FollowFrom {
                 start_block: _,
@@ -118,20 +285,51 @@ async fn main() -> Result<()> {
                     rpc_type,
                     backoff,
                     max_retries,
+                    rpc_limits: RpcLimits {
+                        batch_size: rpc_batch_size,
+                        max_concurrency: rpc_max_concurrency,
+                    },
                 },
                 block_interval,
                 ProofParams {
                     checkpoint_block_number,
                     previous_proof,
                     proof_output_dir,
+                    tenant,
                     prover_config,
                     keep_intermediate_proofs,
+                    compress_final_proof: compress_final_proof.then(|| CompressParams {
+                        prover_state_manager: prover_state_manager.clone(),
+                    }),
+                    wrap_groth16: wrap_groth16.map(|groth16_prover| WrapParams {
+                        groth16_prover,
+                        groth16_prover_version,
+                        prover_state_manager,
+                    }),
+                    #[cfg(feature = "postgres")]
+                    proof_store_url,
                 },
             )
             .await?;
         }
     }
 
+    // The in-memory workers decompress the payloads within this process.
+    log_compression_metrics();
+
+    Ok(())
+}
+
+/// Creates `output_dir` if it does not exist, and checks that it is a writable
+/// directory otherwise.
+fn ensure_output_dir(output_dir: &Path) -> Result<()> {
+    let output_dir_metadata = std::fs::metadata(output_dir);
+    if output_dir_metadata.is_err() {
+        // Create output directory
+        std::fs::create_dir(output_dir)?;
+    } else if !output_dir.is_dir() || output_dir_metadata?.permissions().readonly() {
+        panic!("output-dir is not a writable directory");
+    }
     Ok(())
 }
 
@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use alloy::primitives::Address;
+use alloy::transports::http::reqwest::Url;
+use anyhow::{Context as _, Result};
+use ethereum_types::H256;
+use proof_gen::proof_types::GeneratedBlockProof;
+use rpc::{provider::CachedProvider, retry::build_http_retry_provider};
+use serde::Serialize;
+use tracing::info;
+use zero_bin_common::op_stack::{SettlementClaim, L2_TO_L1_MESSAGE_PASSER};
+
+/// A settlement claim along with the calldata submitting it.
+#[derive(Serialize)]
+struct Settlement {
+    #[serde(flatten)]
+    claim: SettlementClaim,
+    game_type: u32,
+    calldata: String,
+}
+
+/// The main function for the OP-stack settlement mode.
+///
+/// Computes the output root of each block proof in `proofs`, and writes the
+/// calldata creating the corresponding dispute game to stdout, one proof per
+/// line.
+pub(crate) async fn op_settle_main(proofs: PathBuf, rpc_url: Url, game_type: u32) -> Result<()> {
+    let file = File::open(&proofs).with_context(|| format!("failed to open {proofs:?}"))?;
+    let des = &mut serde_json::Deserializer::from_reader(&file);
+    let proofs: Vec<GeneratedBlockProof> = serde_path_to_error::deserialize(des)?;
+
+    let provider = CachedProvider::new(build_http_retry_provider(rpc_url, 0, 0));
+    let message_passer = Address::from(L2_TO_L1_MESSAGE_PASSER.0);
+
+    let mut stdout = std::io::stdout().lock();
+    for proof in proofs {
+        let [account] = provider
+            .get_proofs(vec![(message_passer, vec![])], proof.b_height.into())
+            .await?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("expected a single account proof"))?;
+        let withdrawal_storage_root = H256::from(account.storage_hash.0);
+
+        let claim = SettlementClaim::from_block_proof(&proof, withdrawal_storage_root)?;
+        anyhow::ensure!(
+            claim.l2_block_number == proof.b_height,
+            "proof of block {} commits to block {}",
+            proof.b_height,
+            claim.l2_block_number
+        );
+        info!(
+            "Output root of block {}: {:?}",
+            claim.l2_block_number, claim.output_root
+        );
+
+        let settlement = Settlement {
+            calldata: format!("0x{}", hex::encode(claim.create_game_calldata(game_type))),
+            claim,
+            game_type,
+        };
+        serde_json::to_writer(&mut stdout, &settlement)?;
+        stdout.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
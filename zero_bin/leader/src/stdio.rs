@@ -1,36 +1,75 @@
-use std::io::{Read, Write};
+use std::io::Write;
 
-use anyhow::Result;
-use paladin::runtime::Runtime;
+use anyhow::{Context as _, Result};
+use futures::stream;
 use proof_gen::proof_types::GeneratedBlockProof;
+use prover::runtime::ProverRuntime;
 use prover::{BlockProverInput, BlockProverInputFuture, ProverConfig};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines, Stdin};
 use tracing::info;
 
 /// The main function for the stdio mode.
+///
+/// The input is either a JSON array of [`BlockProverInput`]s, in which case
+/// the generated proofs are written to stdout as a JSON array once all of them
+/// are generated, or a stream of newline-delimited [`BlockProverInput`]s, in
+/// which case each block is proved as soon as it is received and its proof is
+/// written to stdout on its own line.
 pub(crate) async fn stdio_main(
-    runtime: Runtime,
+    runtime: ProverRuntime,
     previous: Option<GeneratedBlockProof>,
     prover_config: ProverConfig,
 ) -> Result<()> {
-    let mut buffer = String::new();
-    std::io::stdin().read_to_string(&mut buffer)?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
 
-    let des = &mut serde_json::Deserializer::from_str(&buffer);
+    // Skip leading blank lines to detect the input format.
+    let mut first_line = None;
+    while let Some(line) = lines.next_line().await? {
+        if !line.trim().is_empty() {
+            first_line = Some(line);
+            break;
+        }
+    }
+    let Some(first_line) = first_line else {
+        runtime.close().await?;
+        return Ok(());
+    };
+
+    if first_line.trim_start().starts_with('[') {
+        let mut buffer = first_line;
+        lines.into_inner().read_to_string(&mut buffer).await?;
+        prove_json_array(runtime, &buffer, previous, prover_config).await
+    } else {
+        prove_ndjson(runtime, first_line, lines, previous, prover_config).await
+    }
+}
+
+/// Proves a JSON array of block prover inputs.
+async fn prove_json_array(
+    runtime: ProverRuntime,
+    buffer: &str,
+    previous: Option<GeneratedBlockProof>,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    let des = &mut serde_json::Deserializer::from_str(buffer);
     let block_prover_inputs = serde_path_to_error::deserialize::<_, Vec<BlockProverInput>>(des)?
         .into_iter()
         .map(Into::into)
         .collect::<Vec<BlockProverInputFuture>>();
 
-    let proved_blocks =
-        prover::prove(block_prover_inputs, &runtime, previous, prover_config, None).await;
+    let proved_blocks = prover::prove(
+        block_prover_inputs,
+        &runtime,
+        previous,
+        prover_config,
+        None,
+        None,
+    )
+    .await;
     runtime.close().await?;
     let proved_blocks = proved_blocks?;
 
-    if prover_config.test_only {
-        info!("All proof witnesses have been generated successfully.");
-    } else {
-        info!("All proofs have been generated successfully.");
-    }
+    log_success(prover_config);
 
     let proofs: Vec<GeneratedBlockProof> = proved_blocks
         .into_iter()
@@ -40,3 +79,73 @@ pub(crate) async fn stdio_main(
 
     Ok(())
 }
+
+/// Proves a stream of newline-delimited block prover inputs, writing each proof
+/// to stdout as soon as it is generated.
+async fn prove_ndjson(
+    runtime: ProverRuntime,
+    first_line: String,
+    lines: Lines<BufReader<Stdin>>,
+    previous: Option<GeneratedBlockProof>,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    let block_prover_inputs = stream::unfold(
+        (Some(first_line), lines),
+        |(pending, mut lines)| async move {
+            let line = match pending {
+                Some(line) => Ok(Some(line)),
+                None => loop {
+                    match lines.next_line().await {
+                        Ok(Some(line)) if line.trim().is_empty() => continue,
+                        other => break other,
+                    }
+                },
+            };
+
+            let input: BlockProverInputFuture = match line {
+                Ok(Some(line)) => Box::pin(async move {
+                    serde_json::from_str::<BlockProverInput>(&line)
+                        .context("invalid block prover input")
+                }),
+                Ok(None) => return None,
+                Err(e) => Box::pin(async move { Err(anyhow::Error::new(e)) }),
+            };
+            Some((input, (None, lines)))
+        },
+    );
+
+    let result = prover::prove_stream(
+        block_prover_inputs,
+        &runtime,
+        previous,
+        prover_config,
+        None,
+        None,
+        |_, proof| {
+            if let Some(proof) = proof {
+                let mut stdout = std::io::stdout().lock();
+                serde_json::to_writer(&mut stdout, &proof)?;
+                stdout.write_all(b"\n")?;
+                stdout.flush()?;
+            }
+            Ok(())
+        },
+    )
+    .await;
+    runtime.close().await?;
+    result?;
+
+    log_success(prover_config);
+
+    Ok(())
+}
+
+fn log_success(prover_config: ProverConfig) {
+    if prover_config.dry_run {
+        info!("All blocks have been dry-run successfully.");
+    } else if prover_config.test_only {
+        info!("All proof witnesses have been generated successfully.");
+    } else {
+        info!("All proofs have been generated successfully.");
+    }
+}
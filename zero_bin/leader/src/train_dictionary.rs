@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _, Result};
+use prover::{BlockProverInput, ProverConfig};
+use tracing::info;
+use zero_bin_common::compression::CompressionDictionary;
+
+/// The main function for the dictionary training mode.
+///
+/// Decodes and segments each block of the input, and trains a dictionary of
+/// at most `max_size` bytes on the resulting segment payloads, which is
+/// written to `output`.
+pub(crate) fn train_dictionary_main(
+    input: Option<PathBuf>,
+    output: &Path,
+    max_size: usize,
+    prover_config: ProverConfig,
+) -> Result<()> {
+    let mut buffer = String::new();
+    match input {
+        Some(path) => File::open(path)?.read_to_string(&mut buffer)?,
+        None => std::io::stdin().read_to_string(&mut buffer)?,
+    };
+
+    let des = &mut serde_json::Deserializer::from_str(&buffer);
+    let block_prover_inputs = serde_path_to_error::deserialize::<_, Vec<BlockProverInput>>(des)?;
+
+    let mut samples = vec![];
+    for input in block_prover_inputs {
+        samples.extend(input.payload_samples(prover_config)?);
+    }
+    ensure!(!samples.is_empty(), "no segment to train the dictionary on");
+
+    let dictionary = CompressionDictionary::train(&samples, max_size)?;
+    std::fs::write(output, dictionary.as_bytes())
+        .with_context(|| format!("couldn't write the dictionary to {output:?}"))?;
+    info!(
+        "Trained dictionary {:?} of {} bytes on {} segments, written to {}",
+        dictionary.id(),
+        dictionary.as_bytes().len(),
+        samples.len(),
+        output.display()
+    );
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use evm_arithmetization::generation::TrimmedGenerationInputs;
 use evm_arithmetization::proof::PublicValues;
+use evm_arithmetization::synthetic::synthetic_segment;
+pub use evm_arithmetization::synthetic::SyntheticLoop;
 use evm_arithmetization::{prover::testing::simulate_execution_all_segments, GenerationInputs};
 use paladin::{
     operation::{FatalError, FatalStrategy, Monoid, Operation, Result},
@@ -17,7 +19,12 @@ use proof_gen::{
 use serde::{Deserialize, Serialize};
 use tracing::error;
 use tracing::{event, info_span, Level};
-use zero_bin_common::{debug_utils::save_inputs_to_disk, prover_state::p_state};
+use zero_bin_common::{
+    compression::Payload,
+    debug_utils::save_inputs_to_disk,
+    memory::{measure, Measured, MemoryWatermark},
+    prover_state::p_state,
+};
 
 registry!();
 
@@ -27,12 +34,27 @@ pub struct SegmentProof {
 }
 
 impl Operation for SegmentProof {
-    type Input = evm_arithmetization::AllData;
-    type Output = proof_gen::proof_types::SegmentAggregatableProof;
+    type Input = Payload<evm_arithmetization::AllData>;
+    type Output = Measured<SegmentAggregatableProof>;
 
     fn execute(&self, all_data: Self::Input) -> Result<Self::Output> {
-        let all_data =
-            all_data.map_err(|err| FatalError::from_str(&err.0, FatalStrategy::Terminate))?;
+        let Measured { output, memory } = measure(|| self.prove(all_data));
+        Ok(Measured {
+            output: output?,
+            memory,
+        })
+    }
+}
+
+impl SegmentProof {
+    fn prove(
+        &self,
+        all_data: Payload<evm_arithmetization::AllData>,
+    ) -> Result<SegmentAggregatableProof> {
+        let all_data = all_data
+            .into_inner("segment_proof")
+            .map_err(|err| FatalError::from_anyhow(err.into(), FatalStrategy::Terminate))?
+            .map_err(|err| FatalError::from_str(&err.0, FatalStrategy::Terminate))?;
 
         let input = all_data.0.clone();
         let segment_index = all_data.1.segment_index();
@@ -72,10 +94,20 @@ pub struct SegmentProofTestOnly {
 }
 
 impl Operation for SegmentProofTestOnly {
-    type Input = (GenerationInputs, usize);
-    type Output = ();
+    type Input = Payload<(GenerationInputs, usize)>;
+    type Output = Option<MemoryWatermark>;
 
     fn execute(&self, inputs: Self::Input) -> Result<Self::Output> {
+        let Measured { output, memory } = measure(|| self.simulate(inputs));
+        output.map(|()| memory)
+    }
+}
+
+impl SegmentProofTestOnly {
+    fn simulate(&self, inputs: Payload<(GenerationInputs, usize)>) -> Result<()> {
+        let inputs = inputs
+            .into_inner("segment_proof_test_only")
+            .map_err(|err| FatalError::from_anyhow(err.into(), FatalStrategy::Terminate))?;
         if self.save_inputs_on_error {
             simulate_execution_all_segments::<Field>(inputs.0.clone(), inputs.1).map_err(|err| {
                 if let Err(write_err) = save_inputs_to_disk(
@@ -101,6 +133,96 @@ impl Operation for SegmentProofTestOnly {
     }
 }
 
+/// Stands in for [`SegmentProof`] in dry runs: the segment is only decoded
+/// by the worker, which checks the plumbing from the leader to the workers
+/// without proving anything.
+#[derive(Deserialize, Serialize, RemoteExecute)]
+pub struct SegmentProofDryRun;
+
+impl Operation for SegmentProofDryRun {
+    type Input = Payload<evm_arithmetization::AllData>;
+    type Output = ();
+
+    fn execute(&self, all_data: Self::Input) -> Result<Self::Output> {
+        all_data
+            .into_inner("segment_proof_dry_run")
+            .map_err(|err| FatalError::from_anyhow(err.into(), FatalStrategy::Terminate))?
+            .map_err(|err| FatalError::from_str(&err.0, FatalStrategy::Terminate))?;
+        Ok(())
+    }
+}
+
+/// A synthetic segment to prove, independent of any chain data.
+///
+/// The segment is the first one of a [synthetic
+/// block](evm_arithmetization::synthetic) running `body`, whose CPU table
+/// holds `2^max_cpu_len_log` rows. The body of the loop selects the other
+/// tables growing along with the CPU one.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct SyntheticWorkload {
+    pub body: SyntheticLoop,
+    pub max_cpu_len_log: usize,
+    /// The index of this workload in the calibration sweep.
+    pub run: usize,
+}
+
+/// The time taken by a worker to prove a [`SyntheticWorkload`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SyntheticSegmentReport {
+    pub workload: SyntheticWorkload,
+    /// The host name of the worker, if known.
+    pub worker: Option<String>,
+    pub witness_generation: Duration,
+    pub proving: Duration,
+}
+
+/// Proves a [`SyntheticWorkload`], to warm up, benchmark and compare workers
+/// without touching real chain data.
+#[derive(Deserialize, Serialize, RemoteExecute)]
+pub struct SyntheticSegmentProof;
+
+impl Operation for SyntheticSegmentProof {
+    type Input = SyntheticWorkload;
+    type Output = SyntheticSegmentReport;
+
+    fn execute(&self, workload: Self::Input) -> Result<Self::Output> {
+        let fatal = |err| FatalError::from_anyhow(err, FatalStrategy::Terminate);
+
+        let _span = info_span!(
+            "synthetic",
+            %workload.body,
+            workload.max_cpu_len_log,
+            workload.run
+        )
+        .entered();
+
+        let start = Instant::now();
+        let segment =
+            synthetic_segment::<Field>(workload.body, workload.max_cpu_len_log).map_err(fatal)?;
+        let witness_generation = start.elapsed();
+
+        let start = Instant::now();
+        zero_bin_common::prover_state::p_manager()
+            .generate_segment_proof(segment)
+            .map_err(fatal)?;
+        let proving = start.elapsed();
+
+        event!(
+            Level::INFO,
+            "synthetic segment proof ({}, 2^{} cycles) took {:?}",
+            workload.body,
+            workload.max_cpu_len_log,
+            witness_generation + proving
+        );
+        Ok(SyntheticSegmentReport {
+            workload,
+            worker: std::env::var("HOSTNAME").ok(),
+            witness_generation,
+            proving,
+        })
+    }
+}
+
 /// RAII struct to measure the time taken by a transaction proof.
 ///
 /// - When created, it starts a span with the transaction proof id.
@@ -0,0 +1,68 @@
+//! The supported API for integrators embedding the prover.
+//!
+//! The items of this module are the stable surface of the prover: they follow
+//! semantic versioning, and are deprecated for at least one minor release
+//! before being removed. Everything else, in this crate as well as in
+//! `evm_arithmetization`, `proof_gen` and `trace_decoder`, is an
+//! implementation detail which may change in any release.
+//!
+//! ```no_run
+//! # async fn run(inputs: Vec<prover::api::BlockProverInput>) -> anyhow::Result<()> {
+//! use prover::api::{verify_block_proof, ProverBuilder, ProverStateManager};
+//!
+//! let mut prover = ProverBuilder::new().in_memory(4).build().await?;
+//! let proved = prover.prove(inputs.into_iter().map(Into::into)).await?;
+//! prover.close().await?;
+//!
+//! let verifier = ProverStateManager::default().verifier()?;
+//! for (_, proof) in proved {
+//!     verify_block_proof(&verifier, &proof.expect("no proof output directory"))?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, Context as _, Result};
+pub use evm_arithmetization::proof::Hardfork;
+pub use evm_arithmetization::GenerationInputs;
+pub use proof_gen::proof_types::GeneratedBlockProof;
+pub use proof_gen::VerifierState;
+pub use trace_decoder::{BlockTrace, DecodingOptions, OtherBlockData};
+pub use zero_bin_common::prover_state::ProverStateManager;
+pub use zero_bin_common::tenant::Tenant;
+
+pub use crate::builder::{Checkpoint, ProofSink, Prover, ProverBuilder};
+pub use crate::events::ProvingEvent;
+pub use crate::guardrails::Guardrails;
+pub use crate::{BlockProverInput, BlockProverInputFuture, ProverConfig};
+
+/// Decodes the trace of a block into the inputs of its proof, grouping its
+/// transactions in batches of `batch_size`.
+pub fn decode_block(
+    trace: BlockTrace,
+    other: OtherBlockData,
+    batch_size: usize,
+    options: DecodingOptions,
+) -> Result<Vec<GenerationInputs>> {
+    Ok(trace_decoder::entrypoint_with_options(
+        trace, other, batch_size, options,
+    )?)
+}
+
+/// Verifies the proof of a block against the circuits of `verifier`.
+pub fn verify_block_proof(verifier: &VerifierState, proof: &GeneratedBlockProof) -> Result<()> {
+    verifier
+        .verify(&proof.intern)
+        .map_err(|err| anyhow!(err.0))
+        .with_context(|| format!("invalid proof of block {}", proof.b_height))
+}
+
+/// Decodes the trace of a block with the default [`DecodingOptions`].
+#[deprecated(note = "use `decode_block` instead")]
+pub fn entrypoint(
+    trace: BlockTrace,
+    other: OtherBlockData,
+    batch_size: usize,
+) -> Result<Vec<GenerationInputs>> {
+    decode_block(trace, other, batch_size, DecodingOptions::default())
+}
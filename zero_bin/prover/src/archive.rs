@@ -0,0 +1,258 @@
+//! Archives of [`BlockProverInput`]s, for proving without access to a node,
+//! e.g. on air-gapped machines or long after the blocks were fetched.
+//!
+//! An archive is a single file laid out as follows, integers being
+//! little-endian:
+//!
+//! ```text
+//! magic (8 bytes) | version (u32)
+//! entry 0 | entry 1 | ...
+//! index
+//! index offset (u64) | magic (8 bytes)
+//! ```
+//!
+//! Each entry is a [`BlockProverInput`] serialized to CBOR and compressed with
+//! zstd on its own, so that any block can be read without decompressing the
+//! others. The index maps the number of each block to the position of its
+//! entry, and is located through the trailer at the end of the file.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use alloy::primitives::BlockNumber;
+use anyhow::{bail, ensure, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use zero_bin_common::compression::DEFAULT_COMPRESSION_LEVEL;
+
+use crate::BlockProverInput;
+
+/// The magic bytes opening and closing an archive.
+const MAGIC: &[u8; 8] = b"ZKPINPUT";
+
+/// The version of the archive format written by [`ArchiveWriter`].
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// The size of the trailer locating the index.
+const TRAILER_LEN: u64 = 8 + MAGIC.len() as u64;
+
+/// The position of an entry in an archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexEntry {
+    offset: u64,
+    len: u64,
+}
+
+/// Writes [`BlockProverInput`]s to an archive.
+///
+/// The archive is only readable once [`ArchiveWriter::finish`] has written its
+/// index.
+pub struct ArchiveWriter<W: Write + Seek> {
+    writer: W,
+    index: BTreeMap<BlockNumber, IndexEntry>,
+}
+
+impl ArchiveWriter<BufWriter<File>> {
+    /// Creates an archive at `path`, overwriting any existing file.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("cannot create archive {path:?}"))?;
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    /// Starts an archive at the current position of `writer`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            index: BTreeMap::new(),
+        })
+    }
+
+    /// Appends the input of a block to the archive.
+    pub fn append(&mut self, input: &BlockProverInput) -> Result<()> {
+        let block_number = input.other_data.b_data.b_meta.block_number.low_u64();
+        ensure!(
+            !self.index.contains_key(&block_number),
+            "block {block_number} is already archived"
+        );
+
+        let mut raw = Vec::new();
+        ciborium::into_writer(input, &mut raw)
+            .with_context(|| format!("cannot serialize block {block_number}"))?;
+        let compressed = zstd::bulk::compress(&raw, DEFAULT_COMPRESSION_LEVEL)?;
+
+        let offset = self.writer.stream_position()?;
+        self.writer.write_all(&compressed)?;
+        self.index.insert(
+            block_number,
+            IndexEntry {
+                offset,
+                len: compressed.len() as u64,
+            },
+        );
+        Ok(())
+    }
+
+    /// Writes the index of the archive and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        let index_offset = self.writer.stream_position()?;
+        ciborium::into_writer(&self.index, &mut self.writer)
+            .context("cannot serialize archive index")?;
+        self.writer.write_all(&index_offset.to_le_bytes())?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes the inputs of several blocks to an archive at `path`.
+pub fn write_archive<'a>(
+    path: &Path,
+    inputs: impl IntoIterator<Item = &'a BlockProverInput>,
+) -> Result<()> {
+    let mut writer = ArchiveWriter::create(path)?;
+    for input in inputs {
+        writer.append(input)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Reads [`BlockProverInput`]s from an archive.
+pub struct ArchiveReader<R: Read + Seek> {
+    reader: R,
+    index: BTreeMap<BlockNumber, IndexEntry>,
+}
+
+impl ArchiveReader<BufReader<File>> {
+    /// Opens the archive at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("cannot open archive {path:?}"))?;
+        Self::new(BufReader::new(file)).with_context(|| format!("invalid archive {path:?}"))
+    }
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Reads the header and the index of the archive.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let mut header = [0; MAGIC.len() + 4];
+        reader.read_exact(&mut header)?;
+        ensure!(header.starts_with(MAGIC), "not a prover input archive");
+        let version = u32::from_le_bytes(header[MAGIC.len()..].try_into().unwrap());
+        if version != ARCHIVE_VERSION {
+            bail!("unsupported archive version {version}, expected {ARCHIVE_VERSION}");
+        }
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        ensure!(
+            end >= start + header.len() as u64 + TRAILER_LEN,
+            "truncated archive"
+        );
+        reader.seek(SeekFrom::Start(end - TRAILER_LEN))?;
+        let mut trailer = [0; TRAILER_LEN as usize];
+        reader.read_exact(&mut trailer)?;
+        ensure!(
+            trailer.ends_with(MAGIC),
+            "truncated archive, its index is missing"
+        );
+        let index_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        ensure!(
+            index_offset <= end - TRAILER_LEN,
+            "corrupted archive trailer"
+        );
+
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let index = ciborium::from_reader((&mut reader).take(end - TRAILER_LEN - index_offset))
+            .context("cannot deserialize archive index")?;
+        Ok(Self { reader, index })
+    }
+
+    /// Returns the numbers of the archived blocks, in increasing order.
+    pub fn block_numbers(&self) -> impl Iterator<Item = BlockNumber> + '_ {
+        self.index.keys().copied()
+    }
+
+    /// Returns the number of archived blocks.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns whether the archive holds no block.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Reads the input of a block.
+    pub fn read(&mut self, block_number: BlockNumber) -> Result<BlockProverInput> {
+        let Some(&IndexEntry { offset, len }) = self.index.get(&block_number) else {
+            bail!("block {block_number} is not archived");
+        };
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut compressed = vec![0; len as usize];
+        self.reader.read_exact(&mut compressed)?;
+        let raw = zstd::stream::decode_all(compressed.as_slice())?;
+        ciborium::from_reader(raw.as_slice())
+            .with_context(|| format!("cannot deserialize block {block_number}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
+    use trace_decoder::{
+        BlockLevelData, BlockTrace, BlockTraceTriePreImages, CombinedPreImages, OtherBlockData,
+    };
+
+    use super::*;
+
+    fn input(block_number: u64) -> BlockProverInput {
+        BlockProverInput {
+            block_trace: BlockTrace {
+                trie_pre_images: BlockTraceTriePreImages::Combined(CombinedPreImages {
+                    compact: vec![1, 2, 3],
+                }),
+                code_db: None,
+                txn_info: vec![],
+                jumpdest_tables: Default::default(),
+            },
+            other_data: OtherBlockData {
+                b_data: BlockLevelData {
+                    b_meta: BlockMetadata {
+                        block_number: block_number.into(),
+                        ..Default::default()
+                    },
+                    b_hashes: BlockHashes::default(),
+                    withdrawals: vec![],
+                    transactions_root: None,
+                    global_exit_roots: vec![],
+                    burn_addr: None,
+                    ommers: vec![],
+                },
+                checkpoint_state_trie_root: Default::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.append(&input(7)).unwrap();
+        writer.append(&input(5)).unwrap();
+        assert!(writer.append(&input(5)).is_err());
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(reader.block_numbers().collect::<Vec<_>>(), [5, 7]);
+        let block = reader.read(7).unwrap();
+        assert_eq!(block.get_block_number(), alloy::primitives::U256::from(7));
+        assert!(reader.read(6).is_err());
+
+        assert!(ArchiveReader::new(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+    }
+}
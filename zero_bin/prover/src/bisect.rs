@@ -0,0 +1,159 @@
+//! Bisection of blocks failing witness generation down to the transaction
+//! causing the failure.
+use std::fmt;
+
+use evm_arithmetization::prover::testing::simulate_execution;
+use tracing::info;
+
+use crate::BlockProverInput;
+
+/// The stage at which a block failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureStage {
+    /// The trace decoder could not process the transaction.
+    Decoding,
+    /// The zkEVM kernel failed to execute the transaction.
+    Simulation,
+}
+
+/// The first transaction of a block failing witness generation.
+#[derive(Debug, Clone)]
+pub struct Culprit {
+    pub stage: FailureStage,
+    /// The index of the failing transaction within the block, or [`None`] if
+    /// the failure lies in the processing that follows the last transaction
+    /// of the block (e.g. withdrawals).
+    pub txn_index: Option<usize>,
+    /// The index of the batch of the failing transaction with the batch size
+    /// the block was proved with.
+    pub batch_index: Option<usize>,
+    /// The error reported for the failing transaction alone.
+    pub error: String,
+}
+
+impl fmt::Display for Culprit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            FailureStage::Decoding => "decoding",
+            FailureStage::Simulation => "simulation",
+        };
+        match (self.txn_index, self.batch_index) {
+            (Some(txn_index), Some(batch_index)) => write!(
+                f,
+                "{stage} first fails at transaction {txn_index} (batch {batch_index}): {}",
+                self.error
+            ),
+            _ => write!(
+                f,
+                "{stage} fails after the last transaction of the block: {}",
+                self.error
+            ),
+        }
+    }
+}
+
+/// Returns the smallest prefix length in `1..=len` for which `fails` holds,
+/// assuming that any prefix extending a failing prefix fails as well.
+fn first_failing_prefix(len: usize, mut fails: impl FnMut(usize) -> bool) -> Option<usize> {
+    if len == 0 || !fails(len) {
+        return None;
+    }
+
+    // Invariant: the prefix of length `lo` passes, the one of length `hi` fails.
+    let (mut lo, mut hi) = (0, len);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if fails(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Some(hi)
+}
+
+impl BlockProverInput {
+    /// Identifies the first transaction of this block which fails decoding or
+    /// simulation, by re-running them over shrinking transaction prefixes.
+    ///
+    /// Simulation runs locally and is expensive: this is meant to be called
+    /// once a block is known to fail, to report where it fails.
+    pub fn bisect_failure(&self, batch_size: usize) -> Option<Culprit> {
+        let block_number = self.get_block_number();
+        let num_txns = self.block_trace.txn_info.len();
+        let txn_of = |index: usize| (index < num_txns).then_some(index);
+        info!("Bisecting block {block_number} over its {num_txns} transactions");
+
+        // Decode each transaction in its own batch, so that each of them can be
+        // simulated independently.
+        let decode = |len: usize| {
+            let mut block_trace = self.block_trace.clone();
+            block_trace.txn_info.truncate(len);
+            trace_decoder::entrypoint(block_trace, self.other_data.clone(), 1)
+        };
+
+        let inputs = match decode(num_txns) {
+            Ok(inputs) => inputs,
+            Err(_) => {
+                let mut error = String::new();
+                let len = first_failing_prefix(num_txns, |len| match decode(len) {
+                    Ok(_) => false,
+                    Err(e) => {
+                        error = format!("{:#}", anyhow::Error::new(e));
+                        true
+                    }
+                })?;
+                let txn_index = txn_of(len - 1);
+                return Some(Culprit {
+                    stage: FailureStage::Decoding,
+                    txn_index,
+                    batch_index: txn_index.map(|i| i / batch_size),
+                    error,
+                });
+            }
+        };
+
+        // A prefix of the inputs fails if any of its batches fails. Each batch
+        // is simulated at most once.
+        let mut results: Vec<Option<Result<(), String>>> = vec![None; inputs.len()];
+        let mut simulate = |index: usize| {
+            results[index]
+                .get_or_insert_with(|| {
+                    simulate_execution::<proof_gen::types::Field>(inputs[index].clone())
+                        .map_err(|e| format!("{e:#}"))
+                })
+                .clone()
+        };
+        let len = first_failing_prefix(inputs.len(), |len| {
+            (0..len).any(|index| simulate(index).is_err())
+        })?;
+
+        let txn_index = txn_of(len - 1);
+        Some(Culprit {
+            stage: FailureStage::Simulation,
+            txn_index,
+            batch_index: txn_index.map(|i| i / batch_size),
+            error: simulate(len - 1).err().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_first_failing_prefix() {
+        for first_failure in 1..=10 {
+            let mut calls = 0;
+            let found = first_failing_prefix(10, |len| {
+                calls += 1;
+                len >= first_failure
+            });
+            assert_eq!(found, Some(first_failure));
+            assert!(calls <= 5);
+        }
+        assert_eq!(first_failing_prefix(10, |_| false), None);
+        assert_eq!(first_failing_prefix(0, |_| true), None);
+    }
+}
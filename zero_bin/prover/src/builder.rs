@@ -0,0 +1,318 @@
+//! A builder to embed block proving in other services.
+//!
+//! [`ProverBuilder`] bundles what the leader binary otherwise assembles from
+//! its command line: the paladin runtime, the [`ProverConfig`], the prover
+//! state, where the generated proofs go and which proof to resume from.
+//!
+//! ```no_run
+//! # async fn run(inputs: Vec<prover::BlockProverInput>) -> anyhow::Result<()> {
+//! use prover::builder::ProverBuilder;
+//!
+//! let mut prover = ProverBuilder::new()
+//!     .in_memory(4)
+//!     .proof_output_dir("proofs")
+//!     .build()
+//!     .await?;
+//!
+//! prover.prove(inputs.into_iter().map(Into::into)).await?;
+//! prover.close().await
+//! # }
+//! ```
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::BlockNumber;
+use anyhow::{Context as _, Result};
+use futures::{stream, Stream};
+use paladin::config::{Config, Runtime as RuntimeKind};
+use proof_gen::proof_types::GeneratedBlockProof;
+use tokio::sync::mpsc;
+use zero_bin_common::capabilities::CapabilityTags;
+use zero_bin_common::fs::generate_block_proof_file_name;
+use zero_bin_common::prover_state::ProverStateManager;
+use zero_bin_common::tenant::Tenant;
+
+use crate::events::ProvingEvent;
+use crate::guardrails::Guardrails;
+use crate::runtime::ProverRuntime;
+use crate::{previous_block_proof, BlockProverInputFuture, ProverConfig};
+
+/// A destination for the generated block proofs, called in block order.
+pub type ProofSink = Box<dyn FnMut(&GeneratedBlockProof) -> Result<()> + Send>;
+
+/// Where to resume proving from.
+#[derive(Debug, Clone, Default)]
+pub enum Checkpoint {
+    /// The first block to prove is the first block of the chain.
+    #[default]
+    Genesis,
+    /// The proof of the block preceding the first block to prove.
+    Proof(Box<GeneratedBlockProof>),
+    /// Resume from the proof of the block preceding the given one in the
+    /// proof output directory, see [`previous_block_proof`].
+    LatestBefore(BlockNumber),
+}
+
+/// Builds a [`Prover`].
+pub struct ProverBuilder {
+    paladin: Config,
+    segment_worker_tags: CapabilityTags,
+    config: ProverConfig,
+    prover_state: Option<ProverStateManager>,
+    proof_output_dir: Option<PathBuf>,
+    checkpoint: Checkpoint,
+    tenant: Option<Tenant>,
+    sinks: Vec<ProofSink>,
+    events: Option<mpsc::Sender<ProvingEvent>>,
+}
+
+impl Default for ProverBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProverBuilder {
+    /// Creates a builder for an in-memory runtime with the default prover
+    /// configuration of the leader.
+    pub fn new() -> Self {
+        Self {
+            paladin: Config {
+                runtime: RuntimeKind::InMemory,
+                ..Default::default()
+            },
+            segment_worker_tags: CapabilityTags::default(),
+            config: ProverConfig {
+                batch_size: 10,
+                max_cpu_len_log: 19,
+                save_inputs_on_error: false,
+                test_only: false,
+                dry_run: false,
+                compress_payloads: false,
+                save_segment_proofs: false,
+                guardrails: Guardrails::default(),
+            },
+            prover_state: None,
+            proof_output_dir: None,
+            checkpoint: Checkpoint::default(),
+            tenant: None,
+            sinks: Vec::new(),
+            events: None,
+        }
+    }
+
+    /// Proves on an in-memory runtime with `num_workers` workers.
+    pub fn in_memory(mut self, num_workers: usize) -> Self {
+        self.paladin.runtime = RuntimeKind::InMemory;
+        self.paladin.num_workers = Some(num_workers);
+        self
+    }
+
+    /// Dispatches proving tasks to the workers listening on the AMQP broker
+    /// at `amqp_uri`.
+    pub fn amqp(mut self, amqp_uri: impl Into<String>) -> Self {
+        self.paladin.runtime = RuntimeKind::Amqp;
+        self.paladin.amqp_uri = Some(amqp_uri.into());
+        self
+    }
+
+    /// Uses the given paladin configuration as is.
+    pub fn paladin_config(mut self, paladin: Config) -> Self {
+        self.paladin = paladin;
+        self
+    }
+
+    /// Dispatches segment proofs only to the workers holding all of `tags`.
+    pub fn segment_worker_tags(mut self, tags: CapabilityTags) -> Self {
+        self.segment_worker_tags = tags;
+        self
+    }
+
+    pub const fn config(mut self, config: ProverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the prover state used by an in-memory runtime. Defaults to
+    /// [`ProverStateManager::default`].
+    pub fn prover_state(mut self, prover_state: ProverStateManager) -> Self {
+        self.prover_state = Some(prover_state);
+        self
+    }
+
+    /// Writes the generated proofs to `dir` rather than returning them.
+    pub fn proof_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.proof_output_dir = Some(dir.into());
+        self
+    }
+
+    pub fn checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Proves the blocks on behalf of `tenant`: its proofs are written to its
+    /// own directory within the proof output directory, from which the
+    /// checkpoint is also resolved, and its blocks are tagged with it in the
+    /// logs.
+    pub fn tenant(mut self, tenant: Tenant) -> Self {
+        self.tenant = Some(tenant);
+        self
+    }
+
+    /// Calls `sink` on each generated proof, in block order.
+    pub fn sink(
+        mut self,
+        sink: impl FnMut(&GeneratedBlockProof) -> Result<()> + Send + 'static,
+    ) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Reports the progress of each proved block to `events`.
+    pub fn events(mut self, events: mpsc::Sender<ProvingEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Resolves the checkpoint, initializes the prover state if proving
+    /// in memory and starts the runtime.
+    pub async fn build(self) -> Result<Prover> {
+        let proof_output_dir = match (&self.tenant, self.proof_output_dir) {
+            (Some(tenant), Some(dir)) => Some(tenant.output_dir(&dir)),
+            (_, dir) => dir,
+        };
+        let previous_proof = match self.checkpoint {
+            Checkpoint::Genesis => None,
+            Checkpoint::Proof(proof) => Some(*proof),
+            Checkpoint::LatestBefore(block_number) => {
+                let dir = proof_output_dir
+                    .as_deref()
+                    .context("resuming from the latest proof requires a proof output dir")?;
+                previous_block_proof(dir, block_number)?
+            }
+        };
+
+        if !self.config.test_only && !self.config.dry_run {
+            if let RuntimeKind::InMemory = self.paladin.runtime {
+                self.prover_state.unwrap_or_default().initialize()?;
+            }
+        }
+
+        let runtime = ProverRuntime::from_config(
+            &self.paladin,
+            &self.segment_worker_tags,
+            self.config.compress_payloads,
+        )
+        .await?;
+
+        Ok(Prover {
+            runtime,
+            config: self.config,
+            proof_output_dir,
+            tenant: self.tenant,
+            previous_proof,
+            sinks: self.sinks,
+            events: self.events,
+        })
+    }
+}
+
+fn write_proof(dir: &Path, proof: &GeneratedBlockProof) -> Result<()> {
+    let path = generate_block_proof_file_name(&dir.to_str(), proof.b_height);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_vec(proof)?)
+        .with_context(|| format!("failed to write proof to {path:?}"))
+}
+
+/// A block prover, built by [`ProverBuilder`].
+///
+/// Successive calls to [`Prover::prove`] and [`Prover::prove_stream`] chain
+/// the proofs of their blocks.
+pub struct Prover {
+    runtime: ProverRuntime,
+    config: ProverConfig,
+    proof_output_dir: Option<PathBuf>,
+    tenant: Option<Tenant>,
+    previous_proof: Option<GeneratedBlockProof>,
+    sinks: Vec<ProofSink>,
+    events: Option<mpsc::Sender<ProvingEvent>>,
+}
+
+impl Prover {
+    /// Proves the given blocks, returning their numbers along with their
+    /// proofs, unless they are written to the proof output directory.
+    pub async fn prove(
+        &mut self,
+        block_prover_inputs: impl IntoIterator<Item = BlockProverInputFuture>,
+    ) -> Result<Vec<(BlockNumber, Option<GeneratedBlockProof>)>> {
+        let mut proved = Vec::new();
+        self.prove_stream(stream::iter(block_prover_inputs), |block_number, proof| {
+            proved.push((block_number, proof));
+            Ok(())
+        })
+        .await?;
+        Ok(proved)
+    }
+
+    /// Proves the blocks as their inputs are yielded by
+    /// `block_prover_inputs`, calling `on_proved` on each of them in order.
+    pub async fn prove_stream<S, F>(
+        &mut self,
+        block_prover_inputs: S,
+        mut on_proved: F,
+    ) -> Result<()>
+    where
+        S: Stream<Item = BlockProverInputFuture>,
+        F: FnMut(BlockNumber, Option<GeneratedBlockProof>) -> Result<()>,
+    {
+        let Self {
+            runtime,
+            config,
+            proof_output_dir,
+            tenant,
+            previous_proof,
+            sinks,
+            events,
+        } = self;
+
+        crate::prove_stream_with_events(
+            block_prover_inputs,
+            runtime,
+            previous_proof.take(),
+            *config,
+            None,
+            tenant.clone(),
+            events.clone(),
+            |block_number, proof| {
+                let Some(proof) = proof else {
+                    return on_proved(block_number, None);
+                };
+                for sink in sinks.iter_mut() {
+                    sink(&proof)?;
+                }
+                *previous_proof = Some(proof.clone());
+
+                match proof_output_dir {
+                    Some(dir) => {
+                        write_proof(dir, &proof)?;
+                        on_proved(block_number, None)
+                    }
+                    None => on_proved(block_number, Some(proof)),
+                }
+            },
+        )
+        .await
+    }
+
+    /// The proof the next proved block will be chained to, if any.
+    pub const fn previous_proof(&self) -> Option<&GeneratedBlockProof> {
+        self.previous_proof.as_ref()
+    }
+
+    /// Shuts the runtime down.
+    pub async fn close(self) -> Result<()> {
+        self.runtime.close().await
+    }
+}
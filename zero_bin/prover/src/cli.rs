@@ -18,6 +18,34 @@ pub struct CliProverConfig {
     /// generating a proof.
     #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
     test_only: bool,
+    /// If true, run the whole pipeline with proving stubbed out: blocks are
+    /// fetched, decoded and segmented, and their segments sent to the workers,
+    /// but dummy proofs are output. This validates the configuration of a
+    /// deployment before proving.
+    #[arg(long, help_heading = HELP_HEADING, default_value_t = false, conflicts_with = "test_only")]
+    dry_run: bool,
+    /// If true, compress the segment payloads sent to the workers with zstd,
+    /// using the configured compression dictionary if any.
+    #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
+    compress_payloads: bool,
+    /// If true, write the segment and batch aggregation proofs of each block
+    /// to the proof output directory, alongside the block proof.
+    #[arg(long, help_heading = HELP_HEADING, default_value_t = false)]
+    save_segment_proofs: bool,
+    /// Reject the blocks using more gas than this.
+    #[arg(long, help_heading = HELP_HEADING, env = "MAX_BLOCK_GAS_USED")]
+    max_gas_used: Option<u64>,
+    /// Reject the blocks with more transactions than this.
+    #[arg(long, help_heading = HELP_HEADING, env = "MAX_BLOCK_TXNS")]
+    max_txns: Option<usize>,
+    /// Reject the blocks whose JSON-encoded input is larger than this many
+    /// bytes.
+    #[arg(long, help_heading = HELP_HEADING, env = "MAX_WITNESS_SIZE")]
+    max_witness_size: Option<usize>,
+    /// Reject the blocks estimated to span more segments than this. The
+    /// estimation simulates each block on the leader.
+    #[arg(long, help_heading = HELP_HEADING, env = "MAX_BLOCK_SEGMENTS")]
+    max_segments: Option<usize>,
 }
 
 impl From<CliProverConfig> for crate::ProverConfig {
@@ -27,6 +55,15 @@ impl From<CliProverConfig> for crate::ProverConfig {
             max_cpu_len_log: cli.max_cpu_len_log,
             save_inputs_on_error: cli.save_inputs_on_error,
             test_only: cli.test_only,
+            dry_run: cli.dry_run,
+            compress_payloads: cli.compress_payloads,
+            save_segment_proofs: cli.save_segment_proofs,
+            guardrails: crate::guardrails::Guardrails {
+                max_gas_used: cli.max_gas_used,
+                max_txns: cli.max_txns,
+                max_witness_size: cli.max_witness_size,
+                max_segments: cli.max_segments,
+            },
         }
     }
 }
@@ -0,0 +1,50 @@
+//! Progress events emitted while proving a block.
+use std::time::Duration;
+
+use alloy::primitives::BlockNumber;
+use tokio::sync::mpsc::{error::TrySendError, Sender};
+use tracing::debug;
+
+/// A step of the proof of a block. Durations are measured from the start of
+/// the proof of the block, excluding the time spent waiting for its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProvingEvent {
+    /// The witness of the block has been decoded into `batches` batches.
+    BlockStarted {
+        block_number: BlockNumber,
+        batches: usize,
+    },
+    /// A segment of a batch has been proved.
+    SegmentProved {
+        block_number: BlockNumber,
+        batch_index: usize,
+        segment_index: usize,
+        elapsed: Duration,
+    },
+    /// All the segments of a batch have been proved and aggregated.
+    BatchAggregated {
+        block_number: BlockNumber,
+        batch_index: usize,
+        elapsed: Duration,
+    },
+    /// The block proof has been generated.
+    BlockProved {
+        block_number: BlockNumber,
+        elapsed: Duration,
+    },
+}
+
+/// Sends `event` to `events`, if any.
+///
+/// Proving never waits on the receiver: events are dropped if its buffer is
+/// full or if it has been closed.
+pub(crate) fn emit(events: Option<&Sender<ProvingEvent>>, event: ProvingEvent) {
+    let Some(events) = events else {
+        return;
+    };
+    match events.try_send(event) {
+        Ok(()) => {}
+        Err(TrySendError::Full(event)) => debug!("dropped proving event {event:?}"),
+        Err(TrySendError::Closed(_)) => {}
+    }
+}
@@ -0,0 +1,141 @@
+//! Sanity guards rejecting outlier blocks before they reach the workers.
+//!
+//! A pathological block can monopolize a fleet for days. Blocks exceeding any
+//! configured limit are rejected with a [`BlockRejected`] error, and their
+//! input is quarantined in the proof output directory for manual review.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{BlockProverInput, ProverConfig};
+
+/// The directory of the proof output directory holding rejected inputs.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Limits on the blocks to prove. Unset limits are not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Guardrails {
+    pub max_gas_used: Option<u64>,
+    pub max_txns: Option<usize>,
+    /// The maximum size, in bytes, of the JSON-encoded block input.
+    pub max_witness_size: Option<usize>,
+    /// The maximum number of segments of the block. Enforcing it requires
+    /// simulating the block on the leader.
+    pub max_segments: Option<usize>,
+}
+
+/// A limit exceeded by a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "guard", rename_all = "snake_case")]
+pub enum GuardrailViolation {
+    GasUsed { gas_used: u64, limit: u64 },
+    TxnCount { txns: usize, limit: usize },
+    WitnessSize { bytes: usize, limit: usize },
+    Segments { segments: usize, limit: usize },
+}
+
+impl fmt::Display for GuardrailViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GasUsed { gas_used, limit } => {
+                write!(f, "gas used {gas_used} exceeds {limit}")
+            }
+            Self::TxnCount { txns, limit } => write!(f, "{txns} transactions exceed {limit}"),
+            Self::WitnessSize { bytes, limit } => {
+                write!(f, "witness of {bytes} bytes exceeds {limit}")
+            }
+            Self::Segments { segments, limit } => {
+                write!(f, "{segments} estimated segments exceed {limit}")
+            }
+        }
+    }
+}
+
+/// The error returned when proving a block exceeding the guardrails.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRejected {
+    pub block_number: u64,
+    pub violations: Vec<GuardrailViolation>,
+    /// Where the input of the block has been quarantined, if anywhere.
+    pub quarantined: Option<PathBuf>,
+}
+
+impl fmt::Display for BlockRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {} rejected by guardrails: ", self.block_number)?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BlockRejected {}
+
+impl Guardrails {
+    pub const fn is_empty(&self) -> bool {
+        self.max_gas_used.is_none()
+            && self.max_txns.is_none()
+            && self.max_witness_size.is_none()
+            && self.max_segments.is_none()
+    }
+
+    /// Returns the limits exceeded by `block`. The segments are only
+    /// estimated if the cheaper checks pass.
+    pub fn check(
+        &self,
+        block: &BlockProverInput,
+        prover_config: ProverConfig,
+    ) -> Result<Vec<GuardrailViolation>> {
+        let mut violations = Vec::new();
+
+        if let Some(limit) = self.max_gas_used {
+            let gas_used =
+                u64::try_from(block.other_data.b_data.b_meta.block_gas_used).unwrap_or(u64::MAX);
+            if gas_used > limit {
+                violations.push(GuardrailViolation::GasUsed { gas_used, limit });
+            }
+        }
+
+        if let Some(limit) = self.max_txns {
+            let txns = block.block_trace.txn_info.len();
+            if txns > limit {
+                violations.push(GuardrailViolation::TxnCount { txns, limit });
+            }
+        }
+
+        if let Some(limit) = self.max_witness_size {
+            let bytes = serde_json::to_vec(block)?.len();
+            if bytes > limit {
+                violations.push(GuardrailViolation::WitnessSize { bytes, limit });
+            }
+        }
+
+        if let Some(limit) = self.max_segments {
+            if violations.is_empty() {
+                let segments = block.clone().estimate(prover_config, false)?.total_segments;
+                if segments > limit {
+                    violations.push(GuardrailViolation::Segments { segments, limit });
+                }
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+/// Writes the input of a rejected block to the quarantine directory of
+/// `output_dir`, returning its path.
+pub(crate) fn quarantine(output_dir: &Path, block: &BlockProverInput) -> Result<PathBuf> {
+    let dir = output_dir.join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("b{}_input.json", block.get_block_number()));
+    std::fs::write(&path, serde_json::to_vec(block)?)
+        .with_context(|| format!("failed to quarantine block input to {path:?}"))?;
+    Ok(path)
+}
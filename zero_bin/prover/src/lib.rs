@@ -1,20 +1,41 @@
+pub mod api;
+pub mod archive;
+pub mod bisect;
+pub mod builder;
 pub mod cli;
+pub mod events;
+pub mod guardrails;
+pub mod manifest;
+pub mod runtime;
 
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use alloy::primitives::{BlockNumber, U256};
 use anyhow::{Context, Result};
-use futures::{future::BoxFuture, stream::FuturesOrdered, FutureExt, TryFutureExt, TryStreamExt};
+use evm_arithmetization::prover::{CycleProfile, FinalTriesStats};
+use futures::{
+    future::BoxFuture, stream::FuturesOrdered, FutureExt, Stream, StreamExt, TryFutureExt,
+    TryStreamExt,
+};
 use num_traits::ToPrimitive as _;
-use paladin::runtime::Runtime;
-use proof_gen::proof_types::GeneratedBlockProof;
+use proof_gen::proof_types::{BatchAggregatableProof, GeneratedBlockProof};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 use trace_decoder::{BlockTrace, OtherBlockData};
-use tracing::info;
-use zero_bin_common::fs::generate_block_proof_file_name;
+use tracing::{info, warn};
+use zero_bin_common::fs::{find_latest_block_proof_before, generate_block_proof_file_name};
+use zero_bin_common::tenant::Tenant;
+
+use crate::events::{emit, ProvingEvent};
+use crate::guardrails::{BlockRejected, Guardrails};
+use crate::manifest::{BlockOutcome, Manifest};
+use crate::runtime::ProverRuntime;
+
+/// The number of segments of a batch whose data is generated ahead of their
+/// proving.
+const SEGMENT_PREFETCH_DEPTH: usize = 2;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ProverConfig {
@@ -22,6 +43,14 @@ pub struct ProverConfig {
     pub max_cpu_len_log: usize,
     pub save_inputs_on_error: bool,
     pub test_only: bool,
+    /// If true, run the whole pipeline with proving stubbed out, see
+    /// [`BlockProverInput::prove_dry_run`].
+    pub dry_run: bool,
+    pub compress_payloads: bool,
+    /// If true, write the segment and batch aggregation proofs of each block
+    /// alongside its proof in the proof output directory, if any.
+    pub save_segment_proofs: bool,
+    pub guardrails: Guardrails,
 }
 
 pub type BlockProverInputFuture = std::pin::Pin<
@@ -37,6 +66,32 @@ impl From<BlockProverInput> for BlockProverInputFuture {
     }
 }
 
+/// The estimated proving cost of a batch of transactions.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BatchCostEstimate {
+    /// The number of segments the batch is split into.
+    pub segments: usize,
+    /// The total number of kernel CPU cycles of the batch.
+    pub cycles: usize,
+    /// The profile of the computation of the final state trie of the batch.
+    pub final_tries: FinalTriesStats,
+    /// The cycle profile of each segment of the batch, if requested.
+    #[serde(skip)]
+    pub cycle_profiles: Vec<CycleProfile>,
+}
+
+/// The estimated proving cost of a block.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlockCostEstimate {
+    pub block_number: u64,
+    pub batches: Vec<BatchCostEstimate>,
+    pub total_segments: usize,
+    pub total_cycles: usize,
+    /// The profiles of the computation of the final state tries of the
+    /// batches, summed.
+    pub total_final_tries: FinalTriesStats,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BlockProverInput {
     pub block_trace: BlockTrace,
@@ -48,28 +103,142 @@ impl BlockProverInput {
         self.other_data.b_data.b_meta.block_number.into()
     }
 
+    /// Estimates the cost of proving this block, by decoding it and running
+    /// the segmentation of each of its batches, without generating any trace
+    /// or proof.
+    ///
+    /// With `profile_cycles`, the cycles of each segment are also attributed
+    /// to the kernel routines they were spent in.
+    pub fn estimate(
+        self,
+        prover_config: ProverConfig,
+        profile_cycles: bool,
+    ) -> Result<BlockCostEstimate> {
+        use evm_arithmetization::prover::SegmentDataIterator;
+
+        let block_number = self
+            .get_block_number()
+            .to_u64()
+            .context("block number overflows u64")?;
+        let block_generation_inputs =
+            trace_decoder::entrypoint(self.block_trace, self.other_data, prover_config.batch_size)?;
+
+        let batches = block_generation_inputs
+            .iter()
+            .map(|txn_batch| {
+                let mut segment_data_iterator = SegmentDataIterator::<proof_gen::types::Field>::new(
+                    txn_batch,
+                    Some(prover_config.max_cpu_len_log),
+                );
+                if profile_cycles {
+                    segment_data_iterator = segment_data_iterator.with_cycle_profiling();
+                }
+                let mut segments = 0;
+                for segment in segment_data_iterator.by_ref() {
+                    segment?;
+                    segments += 1;
+                }
+
+                Ok(BatchCostEstimate {
+                    segments,
+                    cycles: segment_data_iterator.cycles(),
+                    final_tries: segment_data_iterator.final_tries_stats(),
+                    cycle_profiles: segment_data_iterator.cycle_profiles().to_vec(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(BlockCostEstimate {
+            block_number,
+            total_segments: batches.iter().map(|batch| batch.segments).sum(),
+            total_cycles: batches.iter().map(|batch| batch.cycles).sum(),
+            total_final_tries: batches
+                .iter()
+                .fold(FinalTriesStats::default(), |total, batch| FinalTriesStats {
+                    cycles: total.cycles + batch.final_tries.cycles,
+                    skipped_slots: total.skipped_slots + batch.final_tries.skipped_slots,
+                    skipped_accounts: total.skipped_accounts + batch.final_tries.skipped_accounts,
+                }),
+            batches,
+        })
+    }
+
+    /// Decodes and segments this block, and returns the serialized segment
+    /// payloads, to train a compression dictionary on.
+    pub fn payload_samples(self, prover_config: ProverConfig) -> Result<Vec<Vec<u8>>> {
+        use evm_arithmetization::prover::SegmentDataIterator;
+        use zero_bin_common::compression::CompressionDictionary;
+
+        let block_generation_inputs =
+            trace_decoder::entrypoint(self.block_trace, self.other_data, prover_config.batch_size)?;
+
+        let mut samples = vec![];
+        for txn_batch in &block_generation_inputs {
+            for segment in SegmentDataIterator::<proof_gen::types::Field>::new(
+                txn_batch,
+                Some(prover_config.max_cpu_len_log),
+            ) {
+                if let Err(err) = &segment {
+                    anyhow::bail!("{}", err.0);
+                }
+                samples.push(CompressionDictionary::sample(&segment)?);
+            }
+        }
+
+        Ok(samples)
+    }
+
+    /// Proves this block, chaining it to `previous` if any.
+    ///
+    /// If `events` is provided, the progress of the proof is reported to it as
+    /// [`ProvingEvent`]s. If [`ProverConfig::save_segment_proofs`] is set, the
+    /// segment and batch aggregation proofs are written to `proof_output_dir`.
     pub async fn prove(
         self,
-        runtime: &Runtime,
+        runtime: &ProverRuntime,
         previous: Option<impl Future<Output = Result<GeneratedBlockProof>>>,
         prover_config: ProverConfig,
+        proof_output_dir: Option<&Path>,
+        events: Option<mpsc::Sender<ProvingEvent>>,
     ) -> Result<GeneratedBlockProof> {
+        use std::time::Instant;
+
         use anyhow::Context as _;
         use evm_arithmetization::prover::SegmentDataIterator;
-        use futures::{stream::FuturesUnordered, FutureExt};
         use paladin::directive::{Directive, IndexedStream};
+        use zero_bin_common::compression::Payload;
+        use zero_bin_common::memory::Measured;
 
         let ProverConfig {
             max_cpu_len_log,
             batch_size,
             save_inputs_on_error,
             test_only: _,
+            dry_run: _,
+            compress_payloads,
+            save_segment_proofs,
+            guardrails: _,
         } = prover_config;
 
+        let start = Instant::now();
         let block_number = self.get_block_number();
+        let block_height = block_number
+            .to_u64()
+            .context("block number overflows u64")?;
+
+        let save_dir = proof_output_dir
+            .filter(|_| save_segment_proofs)
+            .map(|dir| segment_proofs_dir(dir, block_height));
 
         let block_generation_inputs =
             trace_decoder::entrypoint(self.block_trace, self.other_data, batch_size)?;
+        emit(
+            events.as_ref(),
+            ProvingEvent::BlockStarted {
+                block_number: block_height,
+                batches: block_generation_inputs.len(),
+            },
+        );
 
         // Create segment proof.
         let seg_prove_ops = ops::SegmentProof {
@@ -88,34 +257,81 @@ impl BlockProverInput {
 
         // Segment the batches, prove segments and aggregate them to resulting batch
         // proofs.
-        let batch_proof_futs: FuturesUnordered<_> = block_generation_inputs
+        let batch_proof_futs: Vec<_> = block_generation_inputs
             .iter()
             .enumerate()
             .map(|(idx, txn_batch)| {
                 let segment_data_iterator = SegmentDataIterator::<proof_gen::types::Field>::new(
                     txn_batch,
                     Some(max_cpu_len_log),
-                );
-
-                Directive::map(IndexedStream::from(segment_data_iterator), &seg_prove_ops)
-                    .fold(&seg_agg_ops)
-                    .run(runtime)
-                    .map(move |e| {
-                        e.map(|p| (idx, proof_gen::proof_types::BatchAggregatableProof::from(p)))
-                    })
+                )
+                .in_background(SEGMENT_PREFETCH_DEPTH);
+
+                let segment_payloads = segment_data_iterator.map(move |segment| {
+                    log_segment_size(block_number, idx, &segment);
+                    Payload::new(segment, compress_payloads)
+                });
+
+                let events = events.clone();
+                let (seg_prove_ops, seg_agg_ops) = (&seg_prove_ops, &seg_agg_ops);
+                let save_dir = save_dir.as_deref();
+                async move {
+                    let segment_proofs =
+                        Directive::map(IndexedStream::from(segment_payloads), seg_prove_ops)
+                            .run(runtime.segments())
+                            .await?
+                            .map_ok(|(segment_index, Measured { output, memory })| {
+                                if let Some(memory) = memory {
+                                    zero_bin_common::memory::record(block_height, memory);
+                                }
+                                (segment_index, output)
+                            })
+                            .inspect_ok(|(segment_index, proof)| {
+                                if let Some(dir) = save_dir {
+                                    save_segment_proof(
+                                        dir,
+                                        &format!("segment_{idx}_{segment_index}.json"),
+                                        proof,
+                                    );
+                                }
+                                emit(
+                                    events.as_ref(),
+                                    ProvingEvent::SegmentProved {
+                                        block_number: block_height,
+                                        batch_index: idx,
+                                        segment_index: *segment_index,
+                                        elapsed: start.elapsed(),
+                                    },
+                                )
+                            });
+
+                    let batch_proof =
+                        Directive::fold(IndexedStream::new(segment_proofs), seg_agg_ops)
+                            .run(runtime)
+                            .await?;
+                    if let Some(dir) = save_dir {
+                        save_segment_proof(dir, &format!("batch_{idx}.json"), &batch_proof);
+                    }
+                    emit(
+                        events.as_ref(),
+                        ProvingEvent::BatchAggregated {
+                            block_number: block_height,
+                            batch_index: idx,
+                            elapsed: start.elapsed(),
+                        },
+                    );
+
+                    anyhow::Ok(BatchAggregatableProof::from(batch_proof))
+                }
+                .boxed()
             })
             .collect();
 
-        // Fold the batch aggregated proof stream into a single proof.
+        // Aggregate the batch proofs into a single proof.
         let final_batch_proof =
-            Directive::fold(IndexedStream::new(batch_proof_futs), &batch_agg_ops)
-                .run(runtime)
-                .await?;
+            aggregate_batch_proofs(runtime, &batch_agg_ops, batch_proof_futs).await?;
 
-        if let proof_gen::proof_types::BatchAggregatableProof::Agg(proof) = final_batch_proof {
-            let block_number = block_number
-                .to_u64()
-                .context("block number overflows u64")?;
+        if let BatchAggregatableProof::Agg(proof) = final_batch_proof {
             let prev = match previous {
                 Some(it) => Some(it.await?),
                 None => None,
@@ -130,6 +346,13 @@ impl BlockProverInput {
                 .await?;
 
             info!("Successfully proved block {block_number}");
+            emit(
+                events.as_ref(),
+                ProvingEvent::BlockProved {
+                    block_number: block_height,
+                    elapsed: start.elapsed(),
+                },
+            );
 
             Ok(block_proof.0)
         } else {
@@ -139,7 +362,7 @@ impl BlockProverInput {
 
     pub async fn prove_test(
         self,
-        runtime: &Runtime,
+        runtime: &ProverRuntime,
         previous: Option<impl Future<Output = Result<GeneratedBlockProof>>>,
         prover_config: ProverConfig,
     ) -> Result<GeneratedBlockProof> {
@@ -147,38 +370,68 @@ impl BlockProverInput {
 
         use futures::future;
         use paladin::directive::{Directive, IndexedStream};
+        use zero_bin_common::compression::Payload;
 
         let ProverConfig {
             max_cpu_len_log,
             batch_size,
             save_inputs_on_error,
             test_only: _,
+            dry_run: _,
+            compress_payloads,
+            save_segment_proofs: _,
+            guardrails: _,
         } = prover_config;
 
         let block_number = self.get_block_number();
+        let block_height = block_number
+            .to_u64()
+            .context("block number overflows u64")?;
         info!("Testing witness generation for block {block_number}.");
 
-        let block_generation_inputs =
-            trace_decoder::entrypoint(self.block_trace, self.other_data, batch_size)?;
+        // Kept to locate the failure, should witness generation fail.
+        let input = self.clone();
 
-        let seg_ops = ops::SegmentProofTestOnly {
-            save_inputs_on_error,
-        };
+        let witness_generation = async {
+            let block_generation_inputs =
+                trace_decoder::entrypoint(self.block_trace, self.other_data, batch_size)?;
 
-        let simulation = Directive::map(
-            IndexedStream::from(
-                block_generation_inputs
-                    .into_iter()
-                    .zip(repeat(max_cpu_len_log)),
-            ),
-            &seg_ops,
-        );
+            let seg_ops = ops::SegmentProofTestOnly {
+                save_inputs_on_error,
+            };
 
-        simulation
-            .run(runtime)
-            .await?
-            .try_for_each(|_| future::ok(()))
-            .await?;
+            let simulation = Directive::map(
+                IndexedStream::from(
+                    block_generation_inputs
+                        .into_iter()
+                        .zip(repeat(max_cpu_len_log))
+                        .map(|inputs| Payload::new(inputs, compress_payloads)),
+                ),
+                &seg_ops,
+            );
+
+            simulation
+                .run(runtime.segments())
+                .await?
+                .try_for_each(|(_, memory)| {
+                    if let Some(memory) = memory {
+                        zero_bin_common::memory::record(block_height, memory);
+                    }
+                    future::ok(())
+                })
+                .await?;
+
+            anyhow::Ok(())
+        };
+
+        if let Err(e) = witness_generation.await {
+            info!("Witness generation failed for block {block_number}, bisecting it.");
+            return match tokio::task::spawn_blocking(move || input.bisect_failure(batch_size)).await
+            {
+                Ok(Some(culprit)) => Err(e.context(format!("block {block_number}: {culprit}"))),
+                _ => Err(e),
+            };
+        }
 
         info!("Successfully generated witness for block {block_number}.");
 
@@ -190,101 +443,477 @@ impl BlockProverInput {
 
         // Dummy proof to match expected output type.
         Ok(GeneratedBlockProof {
-            b_height: block_number
-                .to_u64()
-                .expect("Block number should fit in a u64"),
+            b_height: block_height,
             intern: proof_gen::proof_gen::dummy_proof()?,
         })
     }
+
+    /// Runs the proving pipeline of this block with proving stubbed out, to
+    /// validate the configuration of a deployment end-to-end.
+    ///
+    /// The block is decoded and segmented on the leader, and its segments are
+    /// sent to the segment workers, which only decode them. No proof is
+    /// generated: a dummy one is returned, chained after `previous`, along
+    /// with the outcome to record in the manifest.
+    pub async fn prove_dry_run(
+        self,
+        runtime: &ProverRuntime,
+        previous: Option<impl Future<Output = Result<GeneratedBlockProof>>>,
+        prover_config: ProverConfig,
+    ) -> Result<(GeneratedBlockProof, BlockOutcome)> {
+        use evm_arithmetization::prover::SegmentDataIterator;
+        use futures::future;
+        use paladin::directive::{Directive, IndexedStream};
+        use zero_bin_common::compression::Payload;
+
+        let block_number = self.get_block_number();
+        let block_height = block_number
+            .to_u64()
+            .context("block number overflows u64")?;
+        info!("Dry-running block {block_number}.");
+
+        let block_generation_inputs =
+            trace_decoder::entrypoint(self.block_trace, self.other_data, prover_config.batch_size)?;
+        let batches = block_generation_inputs.len();
+
+        let segments_per_batch =
+            future::try_join_all(block_generation_inputs.iter().map(|txn_batch| async move {
+                let segment_payloads = SegmentDataIterator::<proof_gen::types::Field>::new(
+                    txn_batch,
+                    Some(prover_config.max_cpu_len_log),
+                )
+                .map(|segment| Payload::new(segment, prover_config.compress_payloads));
+
+                let segments = Directive::map(
+                    IndexedStream::from(segment_payloads),
+                    &ops::SegmentProofDryRun,
+                )
+                .run(runtime.segments())
+                .await?
+                .try_fold(0, |segments, _| future::ok(segments + 1))
+                .await?;
+                anyhow::Ok(segments)
+            }))
+            .await?;
+        let segments = segments_per_batch.iter().sum();
+
+        info!("Successfully dry-ran block {block_number}: {batches} batches, {segments} segments.");
+
+        // Wait for previous block proof
+        let _prev = match previous {
+            Some(it) => Some(it.await?),
+            None => None,
+        };
+
+        Ok((
+            GeneratedBlockProof {
+                b_height: block_height,
+                intern: proof_gen::proof_gen::dummy_proof()?,
+            },
+            BlockOutcome::DryRun { batches, segments },
+        ))
+    }
 }
 
 /// Prove all the blocks in the input, or simulate their execution depending on
 /// the selected prover configuration. Return the list of block numbers that are
 /// proved and if the proof data is not saved to disk, return the generated
 /// block proofs as well.
+///
+/// The `tenant` the blocks are proved for, if any, is recorded in the manifest
+/// and in the logs. Its proofs are written to `proof_output_dir` as is, which
+/// should already be the output directory of the tenant.
 pub async fn prove(
     block_prover_inputs: Vec<BlockProverInputFuture>,
-    runtime: &Runtime,
+    runtime: &ProverRuntime,
     previous_proof: Option<GeneratedBlockProof>,
     prover_config: ProverConfig,
     proof_output_dir: Option<PathBuf>,
+    tenant: Option<Tenant>,
 ) -> Result<Vec<(BlockNumber, Option<GeneratedBlockProof>)>> {
-    let mut prev: Option<BoxFuture<Result<GeneratedBlockProof>>> =
-        previous_proof.map(|proof| Box::pin(futures::future::ok(proof)) as BoxFuture<_>);
-
-    let mut results = FuturesOrdered::new();
-    for block_prover_input in block_prover_inputs {
-        let (tx, rx) = oneshot::channel::<GeneratedBlockProof>();
-        let proof_output_dir = proof_output_dir.clone();
-        let previous_block_proof = prev.take();
-        let fut = async move {
-            // Get the prover input data from the external source (e.g. Erigon node).
-            let block = block_prover_input.await?;
-            let block_number = block.get_block_number();
-            info!("Proving block {block_number}");
-
-            // Prove the block
-            let block_proof = if prover_config.test_only {
-                block
-                    .prove_test(runtime, previous_block_proof, prover_config)
-                    .then(move |proof| async move {
-                        let proof = proof?;
-                        let block_number = proof.b_height;
-
-                        // Write latest generated proof to disk if proof_output_dir is provided
-                        // or alternatively return proof as function result.
-                        let return_proof: Option<GeneratedBlockProof> =
-                            if let Some(output_dir) = proof_output_dir {
-                                write_proof_to_dir(output_dir, &proof).await?;
-                                None
-                            } else {
-                                Some(proof.clone())
-                            };
-
-                        if tx.send(proof).is_err() {
-                            anyhow::bail!("Failed to send proof");
-                        }
-
-                        Ok((block_number, return_proof))
-                    })
-                    .await?
-            } else {
-                block
-                    .prove(runtime, previous_block_proof, prover_config)
-                    .then(move |proof| async move {
-                        let proof = proof?;
-                        let block_number = proof.b_height;
-
-                        // Write latest generated proof to disk if proof_output_dir is provided
-                        // or alternatively return proof as function result.
-                        let return_proof: Option<GeneratedBlockProof> =
-                            if let Some(output_dir) = proof_output_dir {
-                                write_proof_to_dir(output_dir, &proof).await?;
-                                None
-                            } else {
-                                Some(proof.clone())
-                            };
-
-                        if tx.send(proof).is_err() {
-                            anyhow::bail!("Failed to send proof");
-                        }
-
-                        Ok((block_number, return_proof))
-                    })
-                    .await?
-            };
+    let mut proved = Vec::new();
+    prove_stream(
+        futures::stream::iter(block_prover_inputs),
+        runtime,
+        previous_proof,
+        prover_config,
+        proof_output_dir,
+        tenant,
+        |block_number, proof| {
+            proved.push((block_number, proof));
+            Ok(())
+        },
+    )
+    .await?;
+    Ok(proved)
+}
+
+/// Same as [`prove`], but starts proving each block as soon as its input is
+/// yielded by `block_prover_inputs`, rather than requiring all the inputs
+/// upfront.
+///
+/// `on_proved` is called, in order, on each block as soon as it is proved.
+pub async fn prove_stream<S, F>(
+    block_prover_inputs: S,
+    runtime: &ProverRuntime,
+    previous_proof: Option<GeneratedBlockProof>,
+    prover_config: ProverConfig,
+    proof_output_dir: Option<PathBuf>,
+    tenant: Option<Tenant>,
+    on_proved: F,
+) -> Result<()>
+where
+    S: Stream<Item = BlockProverInputFuture>,
+    F: FnMut(BlockNumber, Option<GeneratedBlockProof>) -> Result<()>,
+{
+    prove_stream_with_events(
+        block_prover_inputs,
+        runtime,
+        previous_proof,
+        prover_config,
+        proof_output_dir,
+        tenant,
+        None,
+        on_proved,
+    )
+    .await
+}
+
+/// Same as [`prove_stream`], reporting the progress of each block to
+/// `events`.
+pub(crate) async fn prove_stream_with_events<S, F>(
+    block_prover_inputs: S,
+    runtime: &ProverRuntime,
+    previous_proof: Option<GeneratedBlockProof>,
+    prover_config: ProverConfig,
+    proof_output_dir: Option<PathBuf>,
+    tenant: Option<Tenant>,
+    events: Option<mpsc::Sender<ProvingEvent>>,
+    mut on_proved: F,
+) -> Result<()>
+where
+    S: Stream<Item = BlockProverInputFuture>,
+    F: FnMut(BlockNumber, Option<GeneratedBlockProof>) -> Result<()>,
+{
+    // The outcome of each block, written to the proof output directory once
+    // the run ends.
+    let mut manifest = Manifest {
+        tenant: tenant.clone(),
+        ..Default::default()
+    };
+    let mut on_proved =
+        |block_number: BlockNumber, proof: Option<GeneratedBlockProof>, outcome: BlockOutcome| {
+            let memory = zero_bin_common::memory::take_block_memory(block_number);
+            if let Some(memory) = memory {
+                info!(
+                    block_number,
+                    tenant = tenant.as_ref().map(Tenant::as_str),
+                    ops = memory.ops,
+                    max_peak_delta = memory.max_peak_delta,
+                    mean_peak_delta = memory.mean_peak_delta(),
+                    max_peak_rss = memory.max_peak_rss,
+                    "worker memory watermarks"
+                );
+            }
+            match outcome {
+                BlockOutcome::Proved => manifest.record_proved(block_number, memory),
+                outcome => manifest.record(block_number, outcome),
+            }
+            on_proved(block_number, proof)
+        };
+
+    let result = async {
+        let mut prev: Option<BoxFuture<Result<GeneratedBlockProof>>> =
+            previous_proof.map(|proof| Box::pin(futures::future::ok(proof)) as BoxFuture<_>);
+
+        let block_prover_inputs = block_prover_inputs.fuse();
+        futures::pin_mut!(block_prover_inputs);
+        let mut results = FuturesOrdered::new();
+        loop {
+            tokio::select! {
+                block_prover_input = block_prover_inputs.next() => match block_prover_input {
+                    Some(block_prover_input) => {
+                        let (fut, next) = prove_block(
+                            block_prover_input,
+                            runtime,
+                            prev.take(),
+                            prover_config,
+                            proof_output_dir.clone(),
+                            events.clone(),
+                        );
+                        prev = Some(next);
+                        results.push_back(fut);
+                    }
+                    None => break,
+                },
+                Some(result) = results.next(), if !results.is_empty() => {
+                    let (block_number, proof, outcome) = result?;
+                    on_proved(block_number, proof, outcome)?;
+                }
+            }
+        }
+
+        // All the inputs have been received, wait for the remaining proofs.
+        while let Some(result) = results.next().await {
+            let (block_number, proof, outcome) = result?;
+            on_proved(block_number, proof, outcome)?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    if let Some(rejected) = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<BlockRejected>())
+    {
+        manifest.record_rejected(rejected);
+    }
+    if let Some(dir) = &proof_output_dir {
+        manifest.write(dir)?;
+    }
+    result
+}
+
+/// Aggregates the batch proofs of a block along a balanced binary tree, so
+/// that any two adjacent subtrees are combined as soon as both are proved, in
+/// O(log n) aggregation steps rather than the O(n) of a linear fold.
+fn aggregate_batch_proofs<'a>(
+    runtime: &'a ProverRuntime,
+    batch_agg_ops: &'a ops::BatchAggProof,
+    batch_proofs: Vec<BoxFuture<'a, Result<BatchAggregatableProof>>>,
+) -> BoxFuture<'a, Result<BatchAggregatableProof>> {
+    use paladin::directive::{Directive, IndexedStream};
+
+    async move {
+        let combine = move |lhs: BatchAggregatableProof,
+                            rhs: BatchAggregatableProof|
+              -> BoxFuture<'a, Result<BatchAggregatableProof>> {
+            async move {
+                Ok(
+                    Directive::fold(IndexedStream::from([lhs, rhs]), batch_agg_ops)
+                        .run(runtime)
+                        .await?,
+                )
+            }
+            .boxed()
+        };
+        aggregate_tree(batch_proofs, &combine).await
+    }
+    .boxed()
+}
+
+/// Combines `leaves` in order along a balanced binary tree, the left subtree
+/// holding the extra leaf when their number is odd.
+///
+/// As long as `combine` is associative, the result is the one of a linear
+/// fold.
+fn aggregate_tree<'a, 'c, T, F>(
+    mut leaves: Vec<BoxFuture<'a, Result<T>>>,
+    combine: &'c F,
+) -> BoxFuture<'c, Result<T>>
+where
+    'a: 'c,
+    T: Send + 'a,
+    F: Fn(T, T) -> BoxFuture<'a, Result<T>> + Sync,
+{
+    async move {
+        if leaves.len() <= 1 {
+            return leaves.pop().context("no batch proofs to aggregate")?.await;
+        }
+
+        let rhs = leaves.split_off(leaves.len().div_ceil(2));
+        let (lhs, rhs) = futures::try_join!(
+            aggregate_tree(leaves, combine),
+            aggregate_tree(rhs, combine),
+        )?;
+
+        combine(lhs, rhs).await
+    }
+    .boxed()
+}
+
+/// Returns a future proving the given block, along with a future resolving to
+/// its proof, to be used as the previous proof of the next block.
+///
+/// The first future resolves to the number of the block, its proof if it is not
+/// written to `proof_output_dir`, and its outcome.
+fn prove_block<'a>(
+    block_prover_input: BlockProverInputFuture,
+    runtime: &'a ProverRuntime,
+    previous_block_proof: Option<BoxFuture<'a, Result<GeneratedBlockProof>>>,
+    prover_config: ProverConfig,
+    proof_output_dir: Option<PathBuf>,
+    events: Option<mpsc::Sender<ProvingEvent>>,
+) -> (
+    BoxFuture<'a, Result<(BlockNumber, Option<GeneratedBlockProof>, BlockOutcome)>>,
+    BoxFuture<'a, Result<GeneratedBlockProof>>,
+) {
+    let (tx, rx) = oneshot::channel::<GeneratedBlockProof>();
+    let fut = async move {
+        // Get the prover input data from the external source (e.g. Erigon node).
+        let block = block_prover_input.await?;
+        let block_number = block.get_block_number();
+        if !prover_config.guardrails.is_empty() {
+            check_guardrails(&block, prover_config, proof_output_dir.as_deref()).await?;
+        }
+        info!("Proving block {block_number}");
+
+        // Prove the block
+        let (proof, outcome) = if prover_config.dry_run {
+            block
+                .prove_dry_run(runtime, previous_block_proof, prover_config)
+                .await?
+        } else if prover_config.test_only {
+            let proof = block
+                .prove_test(runtime, previous_block_proof, prover_config)
+                .await?;
+            (proof, BlockOutcome::Proved)
+        } else {
+            let proof = block
+                .prove(
+                    runtime,
+                    previous_block_proof,
+                    prover_config,
+                    proof_output_dir.as_deref(),
+                    events,
+                )
+                .await?;
+            (proof, BlockOutcome::Proved)
+        };
+        let block_number = proof.b_height;
+
+        // Write latest generated proof to disk if proof_output_dir is provided
+        // or alternatively return proof as function result.
+        let return_proof: Option<GeneratedBlockProof> = if let Some(output_dir) = proof_output_dir {
+            write_proof_to_dir(output_dir, &proof).await?;
+            None
+        } else {
+            Some(proof.clone())
+        };
 
-            Ok(block_proof)
+        if tx.send(proof).is_err() {
+            anyhow::bail!("Failed to send proof");
         }
-        .boxed();
-        prev = Some(Box::pin(rx.map_err(anyhow::Error::new)));
-        results.push_back(fut);
+
+        Ok((block_number, return_proof, outcome))
+    }
+    .boxed();
+
+    (fut, Box::pin(rx.map_err(anyhow::Error::new)))
+}
+
+/// Fails with [`BlockRejected`] if `block` exceeds the guardrails of
+/// `prover_config`, quarantining its input in `proof_output_dir` if any.
+async fn check_guardrails(
+    block: &BlockProverInput,
+    prover_config: ProverConfig,
+    proof_output_dir: Option<&Path>,
+) -> Result<()> {
+    // Estimating the segments of the block simulates it.
+    let violations = {
+        let block = block.clone();
+        tokio::task::spawn_blocking(move || prover_config.guardrails.check(&block, prover_config))
+            .await??
+    };
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let quarantined = proof_output_dir
+        .map(|dir| guardrails::quarantine(dir, block))
+        .transpose()?;
+    let rejected = BlockRejected {
+        block_number: block
+            .get_block_number()
+            .to_u64()
+            .context("block number overflows u64")?,
+        violations,
+        quarantined,
+    };
+    tracing::warn!("{rejected}");
+    Err(rejected.into())
+}
+
+/// Logs the size of the data shipped to workers for a segment.
+fn log_segment_size(
+    block_number: U256,
+    batch_index: usize,
+    segment: &evm_arithmetization::AllData,
+) {
+    let Ok((_, segment_data)) = segment else {
+        return;
+    };
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
     }
 
-    results.try_collect().await
+    let stats = segment_data.memory_stats();
+    let bytes = zero_bin_common::compression::CompressionDictionary::sample(segment_data)
+        .map(|bytes| bytes.len())
+        .unwrap_or_default();
+    tracing::debug!(
+        %block_number,
+        batch_index,
+        segment_index = segment_data.segment_index(),
+        set_cells = stats.set_cells,
+        encoded_cells = stats.encoded_cells,
+        bytes,
+        "segment data size"
+    );
 }
 
 /// Write the proof to the `output_dir` directory.
+/// The directory of the proof output directory holding the segment and batch
+/// aggregation proofs of the given block.
+pub fn segment_proofs_dir(output_dir: &Path, block_height: u64) -> PathBuf {
+    output_dir.join(format!("b{block_height}_segments"))
+}
+
+/// Reads the proof of the block preceding `block_number` from `dir`, onto
+/// which the proof of `block_number` is chained.
+///
+/// Returns `None` if `dir` holds no proof of an earlier block, e.g. when the
+/// first block to prove follows a checkpoint. Bails if the latest proof of an
+/// earlier block isn't the one of the preceding block, rather than proving
+/// from a checkpoint and leaving a gap in the chain of proofs.
+pub fn previous_block_proof(
+    dir: &Path,
+    block_number: BlockNumber,
+) -> Result<Option<GeneratedBlockProof>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    match find_latest_block_proof_before(dir, block_number)? {
+        Some((latest, path)) if latest + 1 == block_number => {
+            info!("Using proof of block {latest} at {path:?} as previous proof");
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("failed to open proof {path:?}"))?;
+            Ok(Some(serde_json::from_reader(file)?))
+        }
+        Some((latest, _)) => anyhow::bail!(
+            "latest proof in {dir:?} is for block {latest}, which does not immediately precede \
+             block {block_number}"
+        ),
+        None => Ok(None),
+    }
+}
+
+/// Writes an intermediate proof of a block to `dir`. These proofs are only kept
+/// for audits, so failing to write one is logged rather than failing the
+/// block.
+fn save_segment_proof(dir: &Path, file_name: &str, proof: &impl Serialize) {
+    let path = dir.join(file_name);
+    let res = std::fs::create_dir_all(dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| Ok(std::fs::write(&path, serde_json::to_vec(proof)?)?));
+    if let Err(e) = res {
+        warn!("Failed to save intermediate proof to {path:?}: {e:#}");
+    }
+}
+
 async fn write_proof_to_dir(output_dir: PathBuf, proof: &GeneratedBlockProof) -> Result<()> {
     let proof_serialized = serde_json::to_vec(proof)?;
     let block_proof_file_path =
@@ -299,3 +928,35 @@ async fn write_proof_to_dir(output_dir: PathBuf, proof: &GeneratedBlockProof) ->
         .await
         .context("Failed to write proof to disk")
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn tree_aggregation_matches_linear_fold() {
+        for n in 1..=9 {
+            let calls = AtomicUsize::new(0);
+            // Concatenation is associative but not commutative, so any
+            // reordering of the leaves would show up.
+            let combine =
+                |lhs: Vec<usize>, rhs: Vec<usize>| -> BoxFuture<'static, Result<Vec<usize>>> {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    async move { Ok([lhs, rhs].concat()) }.boxed()
+                };
+            let leaves = (0..n).map(|i| async move { Ok(vec![i]) }.boxed()).collect();
+
+            let aggregated = futures::executor::block_on(aggregate_tree(leaves, &combine)).unwrap();
+            assert_eq!(aggregated, (0..n).collect::<Vec<_>>());
+            assert_eq!(calls.load(Ordering::Relaxed), n - 1);
+        }
+    }
+
+    #[test]
+    fn tree_aggregation_of_no_proofs_fails() {
+        let combine = |_: (), _: ()| -> BoxFuture<'static, Result<()>> { async { Ok(()) }.boxed() };
+        assert!(futures::executor::block_on(aggregate_tree(vec![], &combine)).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! The manifest of a proving run, recording the outcome of each block.
+//!
+//! It is written to the proof output directory, if any, once the run ends,
+//! whether it succeeds or not.
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::BlockNumber;
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use zero_bin_common::memory::MemoryStats;
+use zero_bin_common::tenant::Tenant;
+
+use crate::guardrails::{BlockRejected, GuardrailViolation};
+
+/// The name of the manifest file in the proof output directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// The outcome of a block of the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum BlockOutcome {
+    Proved,
+    /// The block exceeded the guardrails and was not proved, and neither were
+    /// the blocks following it.
+    Rejected {
+        violations: Vec<GuardrailViolation>,
+        quarantined: Option<PathBuf>,
+    },
+    /// The block went through a dry run, and was not proved.
+    DryRun {
+        /// The number of transaction batches of the block.
+        batches: usize,
+        /// The number of segments sent to the workers.
+        segments: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEntry {
+    pub block_number: BlockNumber,
+    #[serde(flatten)]
+    pub outcome: BlockOutcome,
+    /// The memory watermarks reported by the workers for the block, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The tenant the blocks were proved for, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<Tenant>,
+    pub blocks: Vec<BlockEntry>,
+}
+
+impl Manifest {
+    pub fn record(&mut self, block_number: BlockNumber, outcome: BlockOutcome) {
+        self.blocks.push(BlockEntry {
+            block_number,
+            outcome,
+            memory: None,
+        });
+    }
+
+    /// Records a proved block, along with the memory watermarks reported for
+    /// it.
+    pub fn record_proved(&mut self, block_number: BlockNumber, memory: Option<MemoryStats>) {
+        self.blocks.push(BlockEntry {
+            block_number,
+            outcome: BlockOutcome::Proved,
+            memory,
+        });
+    }
+
+    pub fn record_rejected(&mut self, rejected: &BlockRejected) {
+        self.record(
+            rejected.block_number,
+            BlockOutcome::Rejected {
+                violations: rejected.violations.clone(),
+                quarantined: rejected.quarantined.clone(),
+            },
+        );
+    }
+
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let file =
+            std::fs::File::open(&path).with_context(|| format!("failed to open {path:?}"))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Writes the manifest to `dir`, replacing any previous one.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(MANIFEST_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp_path, &path).with_context(|| format!("failed to write {path:?}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outcomes_are_tagged() {
+        let mut manifest = Manifest::default();
+        manifest.record(1, BlockOutcome::Proved);
+        manifest.record_rejected(&BlockRejected {
+            block_number: 2,
+            violations: vec![GuardrailViolation::TxnCount { txns: 9, limit: 8 }],
+            quarantined: None,
+        });
+        manifest.record(
+            3,
+            BlockOutcome::DryRun {
+                batches: 2,
+                segments: 5,
+            },
+        );
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "blocks": [
+                    { "block_number": 1, "outcome": "proved" },
+                    {
+                        "block_number": 2,
+                        "outcome": "rejected",
+                        "violations": [{ "guard": "txn_count", "txns": 9, "limit": 8 }],
+                        "quarantined": null,
+                    },
+                    { "block_number": 3, "outcome": "dry_run", "batches": 2, "segments": 5 },
+                ]
+            })
+        );
+        assert_eq!(serde_json::from_value::<Manifest>(json).unwrap(), manifest);
+    }
+}
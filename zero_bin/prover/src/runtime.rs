@@ -0,0 +1,92 @@
+//! The paladin runtimes proving tasks are dispatched to.
+use std::ops::Deref;
+
+use anyhow::Result;
+use ops::register;
+use paladin::config::{Config, Runtime as RuntimeKind};
+use paladin::runtime::Runtime;
+use tracing::info;
+use zero_bin_common::capabilities::{CapabilityTags, DEFAULT_TASK_ROUTING_KEY};
+use zero_bin_common::compression::add_dictionary_tag;
+
+/// A paladin runtime, along with an optional runtime dispatching segment
+/// proofs, and any other compressed payloads, only to the workers holding
+/// some capability tags.
+///
+/// It dereferences to the runtime the other tasks are dispatched to.
+pub struct ProverRuntime {
+    runtime: Runtime,
+    segment_runtime: Option<Runtime>,
+}
+
+impl ProverRuntime {
+    /// Starts the runtimes, dispatching segment proofs to workers holding all
+    /// of `segment_worker_tags`, along with the installed compression
+    /// dictionary if `compress_payloads` is set.
+    ///
+    /// Tags are ignored by the in-memory runtime, whose workers are all alike.
+    pub async fn from_config(
+        paladin: &Config,
+        segment_worker_tags: &CapabilityTags,
+        compress_payloads: bool,
+    ) -> Result<Self> {
+        let mut segment_worker_tags = segment_worker_tags.clone();
+        if compress_payloads {
+            add_dictionary_tag(&mut segment_worker_tags);
+        }
+
+        let runtime = Runtime::from_config(paladin, register()).await?;
+        let segment_runtime = match paladin.runtime {
+            RuntimeKind::Amqp if !segment_worker_tags.is_empty() => {
+                let base = paladin
+                    .task_bus_routing_key
+                    .as_deref()
+                    .unwrap_or(DEFAULT_TASK_ROUTING_KEY);
+                let routing_key = segment_worker_tags.routing_key(base);
+                info!("dispatching segment proofs to {routing_key}");
+
+                let config = Config {
+                    task_bus_routing_key: Some(routing_key),
+                    ..paladin.clone()
+                };
+                Some(Runtime::from_config(&config, register()).await?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            runtime,
+            segment_runtime,
+        })
+    }
+
+    /// The runtime segment proofs, and the other possibly compressed payloads,
+    /// are dispatched to.
+    pub fn segments(&self) -> &Runtime {
+        self.segment_runtime.as_ref().unwrap_or(&self.runtime)
+    }
+
+    pub async fn close(self) -> Result<()> {
+        if let Some(segment_runtime) = self.segment_runtime {
+            segment_runtime.close().await?;
+        }
+        self.runtime.close().await
+    }
+}
+
+impl From<Runtime> for ProverRuntime {
+    fn from(runtime: Runtime) -> Self {
+        Self {
+            runtime,
+            segment_runtime: None,
+        }
+    }
+}
+
+impl Deref for ProverRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        &self.runtime
+    }
+}
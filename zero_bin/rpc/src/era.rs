@@ -0,0 +1,470 @@
+//! Prover input retrieval from [era1] archive files.
+//!
+//! era1 files store the pre-merge history of Ethereum as a sequence of
+//! [e2store] entries: for each block, its snappy-compressed header, body and
+//! receipts along with its total difficulty, followed by an accumulator and a
+//! block index giving the offset of each block within the file.
+//!
+//! Archives only contain the chain history, hence the state witness of each
+//! block is read from a separate witness directory, holding one serialized
+//! [`BlockTrace`] per block.
+//!
+//! [era1]: https://github.com/ethereum/go-ethereum/blob/master/internal/era/era.go
+//! [e2store]: https://github.com/status-im/nimbus-eth2/blob/stable/docs/e2store.md
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use alloy::{
+    consensus::Header,
+    primitives::{B256, U256},
+    rlp::Decodable,
+};
+use anyhow::{bail, ensure, Context as _};
+use compat::Compat;
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, Hardfork};
+use prover::BlockProverInput;
+use trace_decoder::{BlockLevelData, BlockTrace, OtherBlockData};
+
+use crate::{berlin_block, PREVIOUS_HASHES_COUNT};
+
+/// The length of an e2store entry header.
+const ENTRY_HEADER_LEN: u64 = 8;
+
+const VERSION: u16 = 0x3265;
+const COMPRESSED_HEADER: u16 = 0x03;
+const COMPRESSED_BODY: u16 = 0x04;
+const COMPRESSED_RECEIPTS: u16 = 0x05;
+const TOTAL_DIFFICULTY: u16 = 0x06;
+const BLOCK_INDEX: u16 = 0x3266;
+
+/// The file extension of era1 archives.
+pub const ERA1_EXTENSION: &str = "era1";
+
+/// A block read from an era1 archive.
+#[derive(Debug, Clone)]
+pub struct Era1Block {
+    pub header: Header,
+    /// The RLP-encoded block body.
+    pub body: Vec<u8>,
+    /// The RLP-encoded block receipts.
+    pub receipts: Vec<u8>,
+    pub total_difficulty: U256,
+}
+
+impl Era1Block {
+    /// Decodes the ommer headers of the block from its body, the RLP list
+    /// `[transactions, ommers, ..]`.
+    pub fn ommers(&self) -> anyhow::Result<Vec<Header>> {
+        let mut body = self.body.as_slice();
+        let list = alloy::rlp::Header::decode(&mut body).context("invalid block body")?;
+        ensure!(list.list, "block body is not a list");
+        let transactions = alloy::rlp::Header::decode(&mut body).context("invalid block body")?;
+        ensure!(transactions.list, "block transactions are not a list");
+        body = body
+            .get(transactions.payload_length..)
+            .context("truncated block body")?;
+        Vec::<Header>::decode(&mut body).context("invalid block ommers")
+    }
+}
+
+/// A single era1 archive, along with its block index.
+#[derive(Debug, Clone)]
+pub struct Era1Archive {
+    path: PathBuf,
+    start_block: u64,
+    /// Absolute offset of each block tuple in the file.
+    offsets: Vec<u64>,
+}
+
+impl Era1Archive {
+    /// Opens the archive at `path` and loads its block index.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut file = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+
+        let (version, _) = read_entry(&mut file, 0)?;
+        ensure!(version == VERSION, "{path:?} is not an e2store file");
+
+        // The block index is the last entry of the file, and ends with the
+        // number of blocks it indexes.
+        let file_len = file.metadata()?.len();
+        let mut count = [0; 8];
+        file.seek(SeekFrom::Start(file_len.saturating_sub(8)))?;
+        file.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+
+        let index_len = ENTRY_HEADER_LEN + 16 + 8 * count;
+        let index_start = file_len
+            .checked_sub(index_len)
+            .context("truncated block index")?;
+        let (typ, index) = read_entry(&mut file, index_start)?;
+        ensure!(
+            typ == BLOCK_INDEX,
+            "{path:?} does not end with a block index"
+        );
+
+        let mut words = index
+            .chunks_exact(8)
+            .map(|word| i64::from_le_bytes(word.try_into().expect("chunks of 8 bytes")));
+        let start_block = words.next().context("empty block index")? as u64;
+        let offsets = words
+            .take(count as usize)
+            .map(|offset| {
+                index_start
+                    .checked_add_signed(offset)
+                    .context("block offset out of bounds")
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            start_block,
+            offsets,
+        })
+    }
+
+    /// The range of blocks contained in this archive.
+    pub fn blocks(&self) -> std::ops::Range<u64> {
+        self.start_block..self.start_block + self.offsets.len() as u64
+    }
+
+    /// Reads block `block_number` from the archive.
+    pub fn block(&self, block_number: u64) -> anyhow::Result<Era1Block> {
+        let mut file = File::open(&self.path)?;
+        let mut offset = self.offset(block_number)?;
+        let mut next_entry =
+            |expected: u16| read_block_entry(&mut file, &mut offset, block_number, expected);
+
+        let header = next_entry(COMPRESSED_HEADER)?;
+        let body = decompress(&next_entry(COMPRESSED_BODY)?)?;
+        let receipts = decompress(&next_entry(COMPRESSED_RECEIPTS)?)?;
+        let total_difficulty = U256::from_le_slice(&next_entry(TOTAL_DIFFICULTY)?);
+
+        Ok(Era1Block {
+            header: self.decode_header(&header, block_number)?,
+            body,
+            receipts,
+            total_difficulty,
+        })
+    }
+
+    /// Reads the header of block `block_number` from the archive, without
+    /// decompressing the rest of the block.
+    pub fn header(&self, block_number: u64) -> anyhow::Result<Header> {
+        let mut file = File::open(&self.path)?;
+        let mut offset = self.offset(block_number)?;
+        let header = read_block_entry(&mut file, &mut offset, block_number, COMPRESSED_HEADER)?;
+        self.decode_header(&header, block_number)
+    }
+
+    /// Returns the offset of the entries of block `block_number`.
+    fn offset(&self, block_number: u64) -> anyhow::Result<u64> {
+        ensure!(
+            self.blocks().contains(&block_number),
+            "block {block_number} is not in {:?}",
+            self.path
+        );
+        Ok(self.offsets[(block_number - self.start_block) as usize])
+    }
+
+    /// Decodes the compressed header of block `block_number`.
+    fn decode_header(&self, compressed: &[u8], block_number: u64) -> anyhow::Result<Header> {
+        let header = decompress(compressed)?;
+        let header = Header::decode(&mut header.as_slice())
+            .with_context(|| format!("invalid header for block {block_number}"))?;
+        ensure!(
+            header.number == block_number,
+            "block index of {:?} points to block {} instead of {block_number}",
+            self.path,
+            header.number
+        );
+        Ok(header)
+    }
+}
+
+/// An index over a directory of era1 archives, giving random access to blocks
+/// by number.
+#[derive(Debug, Default)]
+pub struct Era1Index {
+    /// Archives indexed by their first block.
+    archives: BTreeMap<u64, Era1Archive>,
+    /// The hashes of the blocks read so far. Consecutive blocks share all but
+    /// one of their previous block hashes.
+    hashes: Mutex<HashMap<u64, B256>>,
+}
+
+impl Era1Index {
+    /// Indexes all the era1 archives in `dir`.
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        let mut archives = BTreeMap::new();
+        for entry in fs::read_dir(dir).with_context(|| format!("failed to read {dir:?}"))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ERA1_EXTENSION) {
+                continue;
+            }
+            let archive = Era1Archive::open(&path)?;
+            archives.insert(archive.start_block, archive);
+        }
+        ensure!(!archives.is_empty(), "no era1 archive found in {dir:?}");
+
+        Ok(Self {
+            archives,
+            hashes: Mutex::default(),
+        })
+    }
+
+    /// Returns the archive containing block `block_number`, if any.
+    pub fn archive(&self, block_number: u64) -> Option<&Era1Archive> {
+        self.archives
+            .range(..=block_number)
+            .next_back()
+            .map(|(_, archive)| archive)
+            .filter(|archive| archive.blocks().contains(&block_number))
+    }
+
+    /// Reads block `block_number`.
+    pub fn block(&self, block_number: u64) -> anyhow::Result<Era1Block> {
+        self.covering_archive(block_number)?.block(block_number)
+    }
+
+    /// Reads the header of block `block_number`.
+    pub fn header(&self, block_number: u64) -> anyhow::Result<Header> {
+        self.covering_archive(block_number)?.header(block_number)
+    }
+
+    /// Returns the hash of block `block_number`, reading its header only if it
+    /// wasn't read before.
+    pub fn block_hash(&self, block_number: u64) -> anyhow::Result<B256> {
+        if let Some(hash) = self.hashes.lock().unwrap().get(&block_number) {
+            return Ok(*hash);
+        }
+        let hash = self.header(block_number)?.hash_slow();
+        self.hashes.lock().unwrap().insert(block_number, hash);
+        Ok(hash)
+    }
+
+    fn covering_archive(&self, block_number: u64) -> anyhow::Result<&Era1Archive> {
+        self.archive(block_number)
+            .with_context(|| format!("block {block_number} is not covered by the era1 archives"))
+    }
+}
+
+/// Reads the e2store entry at `offset`, returning its type and data.
+fn read_entry<R: Read + Seek>(reader: &mut R, offset: u64) -> anyhow::Result<(u16, Vec<u8>)> {
+    let mut header = [0; ENTRY_HEADER_LEN as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut header)?;
+
+    let typ = u16::from_le_bytes([header[0], header[1]]);
+    let len = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+    if header[6..] != [0, 0] {
+        bail!("invalid e2store entry header at offset {offset}");
+    }
+
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data)?;
+    Ok((typ, data))
+}
+
+/// Reads the entry of type `expected` of block `block_number` at `offset`,
+/// and moves `offset` to the next entry.
+fn read_block_entry(
+    file: &mut File,
+    offset: &mut u64,
+    block_number: u64,
+    expected: u16,
+) -> anyhow::Result<Vec<u8>> {
+    let (typ, data) = read_entry(file, *offset)?;
+    ensure!(
+        typ == expected,
+        "unexpected entry {typ:#06x} for block {block_number}, expected {expected:#06x}"
+    );
+    *offset += ENTRY_HEADER_LEN + data.len() as u64;
+    Ok(data)
+}
+
+/// Decompresses a snappy framed entry.
+fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    snap::read::FrameDecoder::new(data)
+        .read_to_end(&mut out)
+        .context("invalid snappy frame")?;
+    Ok(out)
+}
+
+/// Returns the path of the witness of block `block_number` in `witness_dir`.
+pub fn witness_file_name(witness_dir: &Path, block_number: u64) -> PathBuf {
+    witness_dir.join(format!("b{block_number}.witness.json"))
+}
+
+/// Builds the prover input of block `block_number`, reading the block data
+/// from the era1 archives and its state witness from `witness_dir`.
+pub fn block_prover_input(
+    index: &Era1Index,
+    witness_dir: &Path,
+    block_number: u64,
+    chain_id: u64,
+    checkpoint_state_trie_root: B256,
+) -> anyhow::Result<BlockProverInput> {
+    let path = witness_file_name(witness_dir, block_number);
+    let block_trace: BlockTrace = serde_json::from_slice(
+        &fs::read(&path).with_context(|| format!("failed to read witness {path:?}"))?,
+    )
+    .with_context(|| format!("invalid witness {path:?}"))?;
+
+    let other_data = other_block_data(index, block_number, chain_id, checkpoint_state_trie_root)?;
+
+    Ok(BlockProverInput {
+        block_trace,
+        other_data,
+    })
+}
+
+/// Builds the block data of block `block_number` from the era1 archives.
+///
+/// era1 archives only cover pre-merge blocks, hence the fields introduced
+/// since then are left to zero. Blocks older than Berlin are not supported by
+/// the prover, and are rejected: the kernel neither implements the rules of
+/// the earlier hardforks, nor the status-root receipts of pre-Byzantium
+/// blocks.
+pub fn other_block_data(
+    index: &Era1Index,
+    block_number: u64,
+    chain_id: u64,
+    checkpoint_state_trie_root: B256,
+) -> anyhow::Result<OtherBlockData> {
+    let berlin_block = berlin_block(chain_id)
+        .with_context(|| format!("unknown Berlin activation block for chain {chain_id}"))?;
+    ensure!(
+        block_number >= berlin_block,
+        "block {block_number} predates Berlin (block {berlin_block}), which the prover doesn't support"
+    );
+
+    let block = index.block(block_number)?;
+    // The miners of the ommers are rewarded along with the miner of the block.
+    let ommers = block
+        .ommers()?
+        .into_iter()
+        .map(|ommer| (ommer.beneficiary, U256::from(ommer.number)))
+        .collect();
+    let header = block.header;
+
+    let mut prev_hashes = [B256::ZERO; PREVIOUS_HASHES_COUNT];
+    let first = block_number.saturating_sub(PREVIOUS_HASHES_COUNT as u64);
+    for (i, number) in (first..block_number).rev().enumerate() {
+        // Most recent previous block hash is expected at the end of the array
+        prev_hashes[PREVIOUS_HASHES_COUNT - 1 - i] = index.block_hash(number)?;
+    }
+
+    Ok(OtherBlockData {
+        b_data: BlockLevelData {
+            b_meta: BlockMetadata {
+                block_beneficiary: header.beneficiary.compat(),
+                block_timestamp: header.timestamp.into(),
+                block_number: block_number.into(),
+                block_difficulty: header.difficulty.into(),
+                block_random: header.mix_hash.compat(),
+                block_gaslimit: header.gas_limit.into(),
+                block_chain_id: chain_id.into(),
+                block_base_fee: header.base_fee_per_gas.unwrap_or_default().into(),
+                block_gas_used: header.gas_used.into(),
+                block_bloom: header.logs_bloom.compat(),
+                block_hardfork: match header.base_fee_per_gas {
+                    Some(_) => Hardfork::London,
+                    None => Hardfork::Berlin,
+                },
+                ..Default::default()
+            },
+            b_hashes: BlockHashes {
+                prev_hashes: prev_hashes.map(|it| it.compat()).into(),
+                cur_hash: index.block_hash(block_number)?.compat(),
+            },
+            withdrawals: Vec::new(),
+            transactions_root: Some(header.transactions_root),
+            global_exit_roots: Vec::new(),
+            burn_addr: None,
+            ommers,
+        },
+        checkpoint_state_trie_root,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use alloy::rlp::Encodable;
+
+    use super::*;
+
+    fn entry(typ: u16, data: &[u8]) -> Vec<u8> {
+        let mut out = typ.to_le_bytes().to_vec();
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend([0, 0]);
+        out.extend(data);
+        out
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+        encoder.write_all(data).unwrap();
+        encoder.into_inner().unwrap()
+    }
+
+    /// Builds an era1 file containing empty blocks `start..start + count`.
+    fn era1_file(start: u64, count: u64) -> Vec<u8> {
+        let mut file = entry(VERSION, &[]);
+        let mut offsets = vec![];
+        for number in start..start + count {
+            offsets.push(file.len() as i64);
+            let mut header = vec![];
+            Header {
+                number,
+                ..Default::default()
+            }
+            .encode(&mut header);
+            file.extend(entry(COMPRESSED_HEADER, &compress(&header)));
+            file.extend(entry(COMPRESSED_BODY, &compress(&[0xc3, 0xc0, 0xc0, 0xc0])));
+            file.extend(entry(COMPRESSED_RECEIPTS, &compress(&[0xc0])));
+            file.extend(entry(
+                TOTAL_DIFFICULTY,
+                &U256::from(number).to_le_bytes::<32>(),
+            ));
+        }
+
+        let index_start = file.len() as i64;
+        let mut index = (start as i64).to_le_bytes().to_vec();
+        for offset in offsets {
+            index.extend((offset - index_start).to_le_bytes());
+        }
+        index.extend((count as i64).to_le_bytes());
+        file.extend(entry(BLOCK_INDEX, &index));
+        file
+    }
+
+    #[test]
+    fn random_access_by_block_number() {
+        let dir = std::env::temp_dir().join(format!("era1_index_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mainnet-00000.era1"), era1_file(0, 8)).unwrap();
+        fs::write(dir.join("mainnet-00001.era1"), era1_file(8, 8)).unwrap();
+
+        let index = Era1Index::open(&dir).unwrap();
+        for number in [0, 5, 8, 15] {
+            let block = index.block(number).unwrap();
+            assert_eq!(block.header.number, number);
+            assert_eq!(block.total_difficulty, U256::from(number));
+            assert!(block.ommers().unwrap().is_empty());
+        }
+        assert!(index.block(16).is_err());
+        assert_eq!(index.header(12).unwrap().number, 12);
+        assert_eq!(
+            index.block_hash(3).unwrap(),
+            index.block(3).unwrap().header.hash_slow()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
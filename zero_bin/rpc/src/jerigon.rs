@@ -56,6 +56,7 @@ where
             }),
             txn_info: tx_results.into_iter().map(|it| it.result).collect(),
             code_db: Default::default(),
+            jumpdest_tables: Default::default(),
         },
         other_data,
     })
@@ -1,19 +1,20 @@
 use std::sync::Arc;
 
 use alloy::{
-    primitives::B256,
+    primitives::{B256, U256},
     providers::Provider,
-    rpc::types::eth::{BlockId, BlockTransactionsKind, Withdrawal},
+    rpc::types::eth::{BlockId, BlockNumberOrTag, BlockTransactionsKind, Header, Withdrawal},
     transports::Transport,
 };
 use anyhow::Context as _;
 use clap::ValueEnum;
 use compat::Compat;
-use evm_arithmetization::proof::{BlockHashes, BlockMetadata};
-use futures::{StreamExt as _, TryStreamExt as _};
+use evm_arithmetization::proof::{BlockHashes, BlockMetadata, Hardfork};
 use prover::BlockProverInput;
 use trace_decoder::{BlockLevelData, OtherBlockData};
+use zero_bin_common::block_interval::BlockInterval;
 
+pub mod era;
 pub mod jerigon;
 pub mod native;
 pub mod provider;
@@ -30,6 +31,17 @@ pub enum RpcType {
     Native,
 }
 
+impl RpcType {
+    /// The form of the transactions of the blocks fetched to build the prover
+    /// input of a block, which batched prefetches should request.
+    pub const fn transactions_kind(self) -> BlockTransactionsKind {
+        match self {
+            Self::Jerigon => BlockTransactionsKind::Hashes,
+            Self::Native => BlockTransactionsKind::Full,
+        }
+    }
+}
+
 /// Obtain the prover input for one block
 pub async fn block_prover_input<ProviderT, TransportT>(
     cached_provider: Arc<CachedProvider<ProviderT, TransportT>>,
@@ -51,6 +63,91 @@ where
     }
 }
 
+/// Fetches the state root of the checkpoint block.
+///
+/// The blocks of a bounded `block_interval` are fetched into the cache in the
+/// same batched requests, so that building their prover inputs does not fetch
+/// them one by one.
+pub async fn fetch_checkpoint_state_trie_root<ProviderT, TransportT>(
+    cached_provider: &CachedProvider<ProviderT, TransportT>,
+    checkpoint_block_id: BlockId,
+    block_interval: &BlockInterval,
+    rpc_type: RpcType,
+) -> anyhow::Result<B256>
+where
+    ProviderT: Provider<TransportT>,
+    TransportT: Transport + Clone,
+{
+    let BlockId::Number(BlockNumberOrTag::Number(checkpoint_block_number)) = checkpoint_block_id
+    else {
+        return Ok(cached_provider
+            .get_block(checkpoint_block_id, BlockTransactionsKind::Hashes)
+            .await?
+            .header
+            .state_root);
+    };
+
+    // The checkpoint block is fetched in the same batched requests as the
+    // blocks of the interval.
+    let mut block_numbers = vec![checkpoint_block_number];
+    if let BlockInterval::Range(range) = block_interval {
+        block_numbers.extend(range.clone());
+    }
+    Ok(cached_provider
+        .get_blocks_by_number(&block_numbers, rpc_type.transactions_kind())
+        .await
+        .context("couldn't fetch the checkpoint block")?
+        .swap_remove(0)
+        .header
+        .state_root)
+}
+
+/// Returns the hardfork of the block with the given `header` on chain
+/// `chain_id`, from the header fields each hardfork introduced.
+///
+/// Fails on blocks of hardforks later than Cancun or earlier than Berlin,
+/// which are not supported. As Berlin headers are not distinguishable from
+/// older ones, pre-London blocks are only accepted on chains whose Berlin
+/// activation block is known.
+pub fn hardfork(header: &Header, chain_id: u64) -> anyhow::Result<Hardfork> {
+    anyhow::ensure!(
+        header.requests_root.is_none(),
+        "blocks after Cancun are not supported"
+    );
+
+    Ok(if header.parent_beacon_block_root.is_some() {
+        Hardfork::Cancun
+    } else if header.withdrawals_root.is_some() {
+        Hardfork::Shanghai
+    } else if header.difficulty.is_zero() {
+        Hardfork::Paris
+    } else if header.base_fee_per_gas.is_some() {
+        Hardfork::London
+    } else {
+        let block_number = header.number.context("block is missing field `number`")?;
+        let berlin_block = berlin_block(chain_id)
+            .with_context(|| format!("unknown Berlin activation block for chain {chain_id}"))?;
+        anyhow::ensure!(
+            block_number >= berlin_block,
+            "block {block_number} predates Berlin (block {berlin_block}), which the prover doesn't support"
+        );
+        Hardfork::Berlin
+    })
+}
+
+/// Returns the first Berlin block of the chains with pre-merge history.
+pub(crate) const fn berlin_block(chain_id: u64) -> Option<u64> {
+    match chain_id {
+        // Mainnet.
+        1 => Some(12_244_000),
+        // Goerli.
+        5 => Some(4_460_644),
+        // Sepolia started on Berlin.
+        11_155_111 => Some(0),
+        _ => None,
+    }
+}
+
 /// Fetches other block data
 async fn fetch_other_block_data<ProviderT, TransportT>(
     cached_provider: Arc<CachedProvider<ProviderT, TransportT>>,
@@ -62,14 +159,16 @@ where
     TransportT: Transport + Clone,
 {
     use itertools::Itertools;
-    let target_block = cached_provider
-        .get_block(target_block_id, BlockTransactionsKind::Hashes)
-        .await?;
-    let target_block_number = target_block
-        .header
-        .number
-        .context("target block is missing field `number`")?;
-    let chain_id = cached_provider.as_provider().get_chain_id().await?;
+    let target_block_number = match target_block_id {
+        BlockId::Number(BlockNumberOrTag::Number(number)) => number,
+        _ => cached_provider
+            .get_block(target_block_id, BlockTransactionsKind::Hashes)
+            .await?
+            .header
+            .number
+            .context("target block is missing field `number`")?,
+    };
+    let chain_id = cached_provider.get_chain_id().await?;
 
     // For one block, we will fetch 128 previous blocks to get hashes instead of
     // 256. But for two consecutive blocks (odd and even) we would fetch 256
@@ -96,29 +195,30 @@ where
         })
         .collect::<Vec<_>>();
 
-    let concurrency = previous_block_numbers.len();
-    let collected_hashes = futures::stream::iter(
-        previous_block_numbers
-            .into_iter() // we get hash for previous and current block with one request
-            .map(|(current_block_number, previous_block_number)| {
-                let cached_provider = &cached_provider;
-                let block_num = current_block_number;
-                async move {
-                    let block = cached_provider
-                        .get_block((block_num as u64).into(), BlockTransactionsKind::Hashes)
-                        .await
-                        .context("couldn't get block")?;
-                    anyhow::Ok([
-                        (block.header.hash, Some(block_num)),
-                        (Some(block.header.parent_hash), previous_block_number),
-                    ])
-                }
-            }),
-    )
-    .buffered(concurrency)
-    .try_collect::<Vec<_>>()
-    .await
-    .context("couldn't fill previous hashes")?;
+    // We get hash for previous and current block with one request, and all
+    // requests are batched, along with the one of the target block.
+    let mut blocks = cached_provider
+        .get_blocks_by_number(
+            &std::iter::once(target_block_number)
+                .chain(
+                    previous_block_numbers
+                        .iter()
+                        .map(|(current_block_number, _)| *current_block_number as u64),
+                )
+                .collect::<Vec<_>>(),
+            BlockTransactionsKind::Hashes,
+        )
+        .await
+        .context("couldn't fill previous hashes")?;
+    let target_block = blocks.remove(0);
+    let collected_hashes = blocks.into_iter().zip(previous_block_numbers).map(
+        |(block, (current_block_number, previous_block_number))| {
+            [
+                (block.header.hash, Some(current_block_number)),
+                (Some(block.header.parent_hash), previous_block_number),
+            ]
+        },
+    );
 
     let mut prev_hashes = [B256::ZERO; PREVIOUS_HASHES_COUNT];
     collected_hashes
@@ -135,6 +235,30 @@ where
             }
         });
 
+    let block_hardfork = hardfork(&target_block.header, chain_id)?;
+
+    // The miners of the ommers of pre-Merge blocks are rewarded along with the
+    // miner of the block.
+    let mut ommers = Vec::new();
+    if block_hardfork < Hardfork::Paris {
+        for idx in 0..target_block.uncles.len() {
+            let ommer = cached_provider
+                .as_provider()
+                .get_uncle(target_block_number.into(), idx as u64)
+                .await?
+                .context(format!("target block is missing ommer {idx}"))?;
+            ommers.push((
+                ommer.header.miner,
+                U256::from(
+                    ommer
+                        .header
+                        .number
+                        .context("ommer is missing field `number`")?,
+                ),
+            ));
+        }
+    }
+
     let other_data = OtherBlockData {
         b_data: BlockLevelData {
             b_meta: BlockMetadata {
@@ -149,28 +273,33 @@ where
                     .compat(),
                 block_gaslimit: target_block.header.gas_limit.into(),
                 block_chain_id: chain_id.into(),
+                // Blocks have no base fee before London.
                 block_base_fee: target_block
                     .header
                     .base_fee_per_gas
-                    .context("target block is missing field `base_fee_per_gas`")?
+                    .unwrap_or_default()
                     .into(),
                 block_gas_used: target_block.header.gas_used.into(),
                 block_bloom: target_block.header.logs_bloom.compat(),
+                // These fields were introduced in Cancun.
                 parent_beacon_block_root: target_block
                     .header
                     .parent_beacon_block_root
-                    .context("target block is missing field `parent_beacon_block_root`")?
+                    .unwrap_or_default()
                     .compat(),
-                block_blob_gas_used: target_block
-                    .header
-                    .blob_gas_used
-                    .context("target block is missing field `blob_gas_used`")?
-                    .into(),
+                block_blob_gas_used: target_block.header.blob_gas_used.unwrap_or_default().into(),
                 block_excess_blob_gas: target_block
                     .header
                     .excess_blob_gas
-                    .context("target block is missing field `excess_blob_gas`")?
+                    .unwrap_or_default()
                     .into(),
+                // Introduced in Shanghai.
+                withdrawals_root: target_block
+                    .header
+                    .withdrawals_root
+                    .unwrap_or_default()
+                    .compat(),
+                block_hardfork,
             },
             b_hashes: BlockHashes {
                 prev_hashes: prev_hashes.map(|it| it.compat()).into(),
@@ -187,11 +316,49 @@ where
                 .map(
                     |Withdrawal {
                          address, amount, ..
-                     }| { (address.compat(), amount.into()) },
+                     }| { (address, U256::from(amount)) },
                 )
                 .collect(),
+            transactions_root: Some(target_block.header.transactions_root),
+            global_exit_roots: Vec::new(),
+            burn_addr: None,
+            ommers,
         },
-        checkpoint_state_trie_root: checkpoint_state_trie_root.compat(),
+        checkpoint_state_trie_root,
     };
     Ok(other_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::*;
+
+    #[test]
+    fn rejects_blocks_before_berlin() {
+        let pre_london_header = |number| Header {
+            number: Some(number),
+            difficulty: U256::from(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            hardfork(&pre_london_header(12_244_000), 1).unwrap(),
+            Hardfork::Berlin
+        );
+        assert!(hardfork(&pre_london_header(12_243_999), 1).is_err());
+        assert_eq!(
+            hardfork(&pre_london_header(0), 11_155_111).unwrap(),
+            Hardfork::Berlin
+        );
+        // Whether a block predates Berlin is unknown on other chains.
+        assert!(hardfork(&pre_london_header(12_244_000), 42).is_err());
+
+        let london_header = Header {
+            base_fee_per_gas: Some(7),
+            ..pre_london_header(12_965_000)
+        };
+        assert_eq!(hardfork(&london_header, 42).unwrap(), Hardfork::London);
+    }
+}
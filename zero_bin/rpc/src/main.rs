@@ -1,11 +1,14 @@
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use alloy::rpc::types::eth::BlockId;
-use alloy::rpc::types::{BlockNumberOrTag, BlockTransactionsKind};
+use alloy::rpc::types::BlockNumberOrTag;
 use clap::{Parser, ValueHint};
 use futures::StreamExt;
-use rpc::provider::CachedProvider;
+use rpc::provider::{
+    CachedProvider, RpcLimits, DEFAULT_RPC_BATCH_SIZE, DEFAULT_RPC_MAX_CONCURRENCY,
+};
 use rpc::{retry::build_http_retry_provider, RpcType};
 use tracing_subscriber::{prelude::*, EnvFilter};
 use url::Url;
@@ -38,6 +41,17 @@ pub enum Cli {
         /// The maximum number of retries
         #[arg(long, default_value_t = 0)]
         max_retries: u32,
+        /// The maximum number of calls sent in a single JSON-RPC batch
+        #[arg(long, default_value_t = DEFAULT_RPC_BATCH_SIZE)]
+        rpc_batch_size: usize,
+        /// The maximum number of JSON-RPC batches in flight at once
+        #[arg(long, default_value_t = DEFAULT_RPC_MAX_CONCURRENCY)]
+        rpc_max_concurrency: usize,
+        /// If provided, write the prover inputs to an archive at this path,
+        /// to be proven offline with the leader `archive` command, instead of
+        /// writing them to stdout as JSON.
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        archive: Option<PathBuf>,
     },
 }
 
@@ -53,23 +67,31 @@ impl Cli {
                 checkpoint_block_number,
                 backoff,
                 max_retries,
+                rpc_batch_size,
+                rpc_max_concurrency,
+                archive,
             } => {
                 let checkpoint_block_number =
                     checkpoint_block_number.unwrap_or((start_block - 1).into());
                 let block_interval = BlockInterval::Range(start_block..end_block + 1);
 
-                let cached_provider = Arc::new(CachedProvider::new(build_http_retry_provider(
-                    rpc_url.clone(),
-                    backoff,
-                    max_retries,
-                )));
+                let cached_provider = Arc::new(CachedProvider::with_limits(
+                    build_http_retry_provider(rpc_url.clone(), backoff, max_retries),
+                    RpcLimits {
+                        batch_size: rpc_batch_size,
+                        max_concurrency: rpc_max_concurrency,
+                    },
+                ));
 
-                // Grab interval checkpoint block state trie
-                let checkpoint_state_trie_root = cached_provider
-                    .get_block(checkpoint_block_number, BlockTransactionsKind::Hashes)
-                    .await?
-                    .header
-                    .state_root;
+                // Grab interval checkpoint block state trie, along with the
+                // blocks of the interval in the same batched requests.
+                let checkpoint_state_trie_root = rpc::fetch_checkpoint_state_trie_root(
+                    &cached_provider,
+                    checkpoint_block_number,
+                    &block_interval,
+                    rpc_type,
+                )
+                .await?;
 
                 let mut block_prover_inputs = Vec::new();
                 let mut block_interval = block_interval.clone().into_bounded_stream()?;
@@ -87,7 +109,10 @@ impl Cli {
                     block_prover_inputs.push(result);
                 }
 
-                serde_json::to_writer_pretty(std::io::stdout(), &block_prover_inputs)?;
+                match archive {
+                    Some(path) => prover::archive::write_archive(&path, &block_prover_inputs)?,
+                    None => serde_json::to_writer_pretty(std::io::stdout(), &block_prover_inputs)?,
+                }
             }
         }
         Ok(())
@@ -16,7 +16,7 @@ use crate::provider::CachedProvider;
 mod state;
 mod txn;
 
-type CodeDb = HashMap<__compat_primitive_types::H256, Vec<u8>>;
+type CodeDb = HashMap<B256, Vec<u8>>;
 
 /// Fetches the prover input for the given BlockId.
 pub async fn block_prover_input<ProviderT, TransportT>(
@@ -52,13 +52,18 @@ where
         .get_block(block_number, BlockTransactionsKind::Full)
         .await?;
 
+    let chain_id = cached_provider.get_chain_id().await?;
+    let hardfork = crate::hardfork(&block.header, chain_id)?;
+
     let (code_db, txn_info) =
-        txn::process_transactions(&block, cached_provider.as_provider()).await?;
-    let trie_pre_images = state::process_state_witness(cached_provider, block, &txn_info).await?;
+        txn::process_transactions(&block, hardfork, cached_provider.as_provider()).await?;
+    let trie_pre_images =
+        state::process_state_witness(cached_provider, block, hardfork, &txn_info).await?;
 
     Ok(BlockTrace {
         txn_info,
         code_db: Option::from(code_db).filter(|x| !x.is_empty()),
         trie_pre_images,
+        jumpdest_tables: Default::default(),
     })
 }
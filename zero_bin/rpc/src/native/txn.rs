@@ -1,56 +1,148 @@
 use std::collections::{HashMap, HashSet};
 
-use __compat_primitive_types::{H256, U256};
 use alloy::{
-    primitives::{keccak256, Address, B256},
-    providers::{
-        ext::DebugApi as _,
-        network::{eip2718::Encodable2718, Ethereum, Network},
-        Provider,
-    },
+    consensus::{ReceiptEnvelope, TxEnvelope},
+    primitives::{Address, B256},
+    providers::{network::eip2718::Encodable2718, Provider},
     rpc::types::{
         eth::Transaction,
-        eth::{AccessList, Block},
+        eth::{AccessList, Block, BlockNumberOrTag},
         trace::geth::{
-            AccountState, DiffMode, GethDebugBuiltInTracerType, GethTrace, PreStateConfig,
-            PreStateFrame, PreStateMode,
+            AccountState, DiffMode, GethDebugBuiltInTracerType, PreStateConfig, PreStateMode,
         },
         trace::geth::{GethDebugTracerType, GethDebugTracingOptions},
     },
     transports::Transport,
 };
-use anyhow::Context as _;
+use anyhow::{bail, ensure, Context as _};
+use evm_arithmetization::proof::Hardfork;
 use futures::stream::{FuturesOrdered, TryStreamExt};
-use trace_decoder::{ContractCodeUsage, TxnInfo, TxnMeta, TxnTrace};
+use serde::Deserialize;
+use trace_decoder::{geth, TxnInfo, TxnMeta, TxnTrace};
 
 use super::CodeDb;
-use crate::Compat;
+
+/// A single entry of a `debug_traceBlockByNumber` response.
+#[derive(Debug, Deserialize)]
+struct BlockTraceResult<T> {
+    result: T,
+}
 
 /// Processes the transactions in the given block and updates the code db.
+///
+/// The `prestateTracer` is invoked once per block, in both default and diff
+/// mode, so that any node exposing the standard Geth debug namespace can be
+/// used to fetch the witness.
 pub(super) async fn process_transactions<ProviderT, TransportT>(
     block: &Block,
+    hardfork: Hardfork,
     provider: &ProviderT,
 ) -> anyhow::Result<(CodeDb, Vec<TxnInfo>)>
 where
     ProviderT: Provider<TransportT>,
     TransportT: Transport + Clone,
 {
-    block
+    let txns = block
         .transactions
         .as_transactions()
-        .context("No transactions in block")?
+        .context("No transactions in block")?;
+    let block_number = block.header.number.context("Block number not found.")?;
+
+    let (read_traces, diff_traces) = fetch_block_traces(provider, block_number).await?;
+    if read_traces.len() != txns.len() || diff_traces.len() != txns.len() {
+        bail!(
+            "mismatched trace count for block {}: {} transactions, {} prestate traces, {} diff traces",
+            block_number,
+            txns.len(),
+            read_traces.len(),
+            diff_traces.len()
+        );
+    }
+
+    let (code_db, txn_infos, (envelopes, receipts)) = txns
         .iter()
-        .map(|tx| process_transaction(provider, tx))
+        .zip(read_traces.into_iter().zip(diff_traces))
+        .map(|(tx, (read, diff))| {
+            process_transaction(provider, tx, read.result, diff.result, hardfork)
+        })
         .collect::<FuturesOrdered<_>>()
         .try_fold(
-            (HashMap::new(), Vec::new()),
-            |(mut code_db, mut txn_infos), (tx_code_db, txn_info)| async move {
+            (HashMap::new(), Vec::new(), (Vec::new(), Vec::new())),
+            |(mut code_db, mut txn_infos, (mut envelopes, mut receipts)),
+             (tx_code_db, txn_info, (envelope, receipt))| async move {
                 code_db.extend(tx_code_db);
                 txn_infos.push(txn_info);
-                Ok((code_db, txn_infos))
+                envelopes.push(envelope);
+                receipts.push(receipt);
+                Ok((code_db, txn_infos, (envelopes, receipts)))
             },
         )
-        .await
+        .await?;
+
+    check_txn_and_receipt_roots(block, &envelopes, &receipts)?;
+
+    Ok((code_db, txn_infos))
+}
+
+/// Checks the transactions and receipts fetched for `block` against the roots
+/// of its header, so that a node serving inconsistent data is caught before
+/// building the witness.
+fn check_txn_and_receipt_roots(
+    block: &Block,
+    txns: &[TxEnvelope],
+    receipts: &[ReceiptEnvelope],
+) -> anyhow::Result<()> {
+    let (transactions_root, receipts_root) = trace_decoder::txn_and_receipt_roots(txns, receipts)?;
+    ensure!(
+        transactions_root == block.header.transactions_root,
+        "the transactions fetched for block {:?} don't match its transactions root",
+        block.header.number
+    );
+    ensure!(
+        receipts_root == block.header.receipts_root,
+        "the receipts fetched for block {:?} don't match its receipts root",
+        block.header.number
+    );
+    Ok(())
+}
+
+/// Fetches the `prestateTracer` traces of all transactions in the given block,
+/// in both default and diff mode.
+async fn fetch_block_traces<ProviderT, TransportT>(
+    provider: &ProviderT,
+    block_number: u64,
+) -> anyhow::Result<(
+    Vec<BlockTraceResult<PreStateMode>>,
+    Vec<BlockTraceResult<DiffMode>>,
+)>
+where
+    ProviderT: Provider<TransportT>,
+    TransportT: Transport + Clone,
+{
+    let block_number = BlockNumberOrTag::Number(block_number);
+    let read_traces_fut = provider.raw_request::<_, Vec<BlockTraceResult<PreStateMode>>>(
+        "debug_traceBlockByNumber".into(),
+        (block_number, prestate_tracing_options(false)),
+    );
+    let diff_traces_fut = provider.raw_request::<_, Vec<BlockTraceResult<DiffMode>>>(
+        "debug_traceBlockByNumber".into(),
+        (block_number, prestate_tracing_options(true)),
+    );
+
+    futures::try_join!(read_traces_fut, diff_traces_fut)
+        .context("Failed to fetch prestateTracer traces for block.")
+}
+
+/// Some nodes report a chain ID for legacy transactions signed before
+/// [EIP-155](https://eips.ethereum.org/EIPS/eip-155), whose `v` is 27 or 28.
+/// It must be left out of their encoding for their signature to be valid.
+fn without_pre_eip155_chain_id(mut tx: Transaction) -> Transaction {
+    if let Some(signature) = &tx.signature {
+        if matches!(u64::try_from(signature.v), Ok(27 | 28)) {
+            tx.chain_id = None;
+        }
+    }
+    tx
 }
 
 /// Processes the transaction with the given transaction hash and updates the
@@ -58,72 +150,57 @@ where
 async fn process_transaction<ProviderT, TransportT>(
     provider: &ProviderT,
     tx: &Transaction,
-) -> anyhow::Result<(CodeDb, TxnInfo)>
+    read_trace: PreStateMode,
+    diff_trace: DiffMode,
+    hardfork: Hardfork,
+) -> anyhow::Result<(CodeDb, TxnInfo, (TxEnvelope, ReceiptEnvelope))>
 where
     ProviderT: Provider<TransportT>,
     TransportT: Transport + Clone,
 {
-    let (tx_receipt, pre_trace, diff_trace) = fetch_tx_data(provider, &tx.hash).await?;
+    let tx_receipt = provider
+        .get_transaction_receipt(tx.hash)
+        .await?
+        .context("Transaction receipt not found.")?;
     let tx_status = tx_receipt.status();
     let tx_receipt = tx_receipt.map_inner(rlp::map_receipt_envelope);
     let access_list = parse_access_list(tx.access_list.as_ref());
 
+    let envelope = TxEnvelope::try_from(without_pre_eip155_chain_id(tx.clone()))?;
     let tx_meta = TxnMeta {
-        byte_code: <Ethereum as Network>::TxEnvelope::try_from(tx.clone())?.encoded_2718(),
-        new_receipt_trie_node_byte: alloy::rlp::encode(tx_receipt.inner),
+        byte_code: envelope.encoded_2718(),
+        new_receipt_trie_node_byte: alloy::rlp::encode(&tx_receipt.inner),
         gas_used: tx_receipt.gas_used as u64,
     };
 
-    let (code_db, mut tx_traces) = match (pre_trace, diff_trace) {
-        (
-            GethTrace::PreStateTracer(PreStateFrame::Default(read)),
-            GethTrace::PreStateTracer(PreStateFrame::Diff(diff)),
-        ) => process_tx_traces(access_list, read, diff).await?,
-        _ => unreachable!(),
-    };
+    let DiffMode { pre, post } = diff_trace;
+    let (code_db, mut traces) = geth::txn_traces(
+        &geth_account_states(read_trace.0),
+        &geth::DiffMode {
+            pre: geth_account_states(pre),
+            post: geth_account_states(post),
+        },
+        &access_list,
+        hardfork,
+    );
 
     // Handle case when transaction failed and a contract creation was reverted
-    if !tx_status && tx_receipt.contract_address.is_some() {
-        tx_traces.insert(tx_receipt.contract_address.unwrap(), TxnTrace::default());
+    if let Some(address) = tx_receipt.contract_address.filter(|_| !tx_status) {
+        traces.insert(address, TxnTrace::default());
     }
 
     Ok((
         code_db,
         TxnInfo {
             meta: tx_meta,
-            traces: tx_traces
-                .into_iter()
-                .map(|(k, v)| (k.compat(), v))
-                .collect(),
+            traces,
         },
-    ))
-}
-
-/// Fetches the transaction data for the given transaction hash.
-async fn fetch_tx_data<ProviderT, TransportT>(
-    provider: &ProviderT,
-    tx_hash: &B256,
-) -> anyhow::Result<(<Ethereum as Network>::ReceiptResponse, GethTrace, GethTrace), anyhow::Error>
-where
-    ProviderT: Provider<TransportT>,
-    TransportT: Transport + Clone,
-{
-    let tx_receipt_fut = provider.get_transaction_receipt(*tx_hash);
-    let pre_trace_fut = provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(false));
-    let diff_trace_fut = provider.debug_trace_transaction(*tx_hash, prestate_tracing_options(true));
-
-    let (tx_receipt, pre_trace, diff_trace) =
-        futures::try_join!(tx_receipt_fut, pre_trace_fut, diff_trace_fut,)?;
-
-    Ok((
-        tx_receipt.context("Transaction receipt not found.")?,
-        pre_trace,
-        diff_trace,
+        (envelope, tx_receipt.inner),
     ))
 }
 
 /// Parse the access list data into a hashmap.
-fn parse_access_list(access_list: Option<&AccessList>) -> HashMap<Address, HashSet<H256>> {
+fn parse_access_list(access_list: Option<&AccessList>) -> HashMap<Address, HashSet<B256>> {
     let mut result = HashMap::new();
 
     if let Some(access_list) = access_list {
@@ -131,179 +208,30 @@ fn parse_access_list(access_list: Option<&AccessList>) -> HashMap<Address, HashS
             result
                 .entry(item.address)
                 .or_insert_with(HashSet::new)
-                .extend(item.storage_keys.into_iter().map(Compat::compat));
+                .extend(item.storage_keys);
         }
     }
 
     result
 }
 
-/// Processes the transaction traces and updates the accounts state.
-async fn process_tx_traces(
-    mut access_list: HashMap<Address, HashSet<H256>>,
-    read_trace: PreStateMode,
-    diff_trace: DiffMode,
-) -> anyhow::Result<(CodeDb, HashMap<Address, TxnTrace>)> {
-    let DiffMode {
-        pre: pre_trace,
-        post: post_trace,
-    } = diff_trace;
-
-    let addresses: HashSet<_> = read_trace
-        .0
-        .keys()
-        .chain(post_trace.keys())
-        .chain(pre_trace.keys())
-        .chain(access_list.keys())
-        .copied()
-        .collect();
-
-    let mut traces = HashMap::new();
-    let mut code_db: CodeDb = HashMap::new();
-
-    for address in addresses {
-        let read_state = read_trace.0.get(&address);
-        let pre_state = pre_trace.get(&address);
-        let post_state = post_trace.get(&address);
-
-        let balance = post_state.and_then(|x| x.balance.map(Compat::compat));
-        let (storage_read, storage_written) = process_storage(
-            access_list.remove(&address).unwrap_or_default(),
-            read_state,
-            post_state,
-            pre_state,
-        );
-        let code = process_code(post_state, read_state, &mut code_db).await;
-        let nonce = process_nonce(post_state, &code);
-        let self_destructed = process_self_destruct(post_state, pre_state);
-
-        let result = TxnTrace {
-            balance,
-            nonce,
-            storage_read,
-            storage_written,
-            code_usage: code,
-            self_destructed,
-        };
-
-        traces.insert(address, result);
-    }
-
-    Ok((code_db, traces))
-}
-
-/// Processes the nonce for the given account state.
-///
-/// If a contract is created, the nonce is set to 1.
-fn process_nonce(
-    post_state: Option<&AccountState>,
-    code_usage: &Option<ContractCodeUsage>,
-) -> Option<U256> {
-    post_state
-        .and_then(|x| x.nonce.map(Into::into))
-        .or_else(|| {
-            if let Some(ContractCodeUsage::Write(_)) = code_usage.as_ref() {
-                Some(U256::from(1))
-            } else {
-                None
-            }
-        })
-}
-
-/// Processes the self destruct for the given account state.
-/// This wraps the actual boolean indicator into an `Option` so that we can skip
-/// serialization of `None` values, which represent most cases.
-fn process_self_destruct(
-    post_state: Option<&AccountState>,
-    pre_state: Option<&AccountState>,
-) -> Option<bool> {
-    if post_state.is_none() {
-        // EIP-6780:
-        // A contract is considered created at the beginning of a create
-        // transaction or when a CREATE series operation begins execution (CREATE,
-        // CREATE2, and other operations that deploy contracts in the future). If a
-        // balance exists at the contract’s new address it is still considered to be a
-        // contract creation.
-        if let Some(acc) = pre_state {
-            if acc.code.is_none() && acc.storage.keys().collect::<Vec<_>>().is_empty() {
-                return Some(true);
-            }
-        }
-    }
-
-    None
-}
-
-/// Processes the storage for the given account state.
-///
-/// Returns the storage read and written for the given account in the
-/// transaction and updates the storage keys.
-fn process_storage(
-    access_list: HashSet<__compat_primitive_types::H256>,
-    acct_state: Option<&AccountState>,
-    post_acct: Option<&AccountState>,
-    pre_acct: Option<&AccountState>,
-) -> (Option<Vec<H256>>, Option<HashMap<H256, U256>>) {
-    let mut storage_read = access_list;
-    storage_read.extend(
-        acct_state
-            .map(|acct| {
-                acct.storage
-                    .keys()
-                    .copied()
-                    .map(Compat::compat)
-                    .collect::<Vec<H256>>()
-            })
-            .unwrap_or_default(),
-    );
-
-    let mut storage_written: HashMap<H256, U256> = post_acct
-        .map(|x| {
-            x.storage
-                .iter()
-                .map(|(k, v)| ((*k).compat(), U256::from_big_endian(&v.0)))
-                .collect()
+/// Converts the account states of a `prestateTracer` trace into the ones of
+/// [`trace_decoder::geth`].
+fn geth_account_states(
+    states: impl IntoIterator<Item = (Address, AccountState)>,
+) -> HashMap<Address, geth::AccountState> {
+    states
+        .into_iter()
+        .map(|(address, state)| {
+            let state = geth::AccountState {
+                balance: state.balance,
+                nonce: state.nonce,
+                code: state.code.map(|code| geth::Bytes(code.to_vec())),
+                storage: state.storage,
+            };
+            (address, state)
         })
-        .unwrap_or_default();
-
-    // Add the deleted keys to the storage written
-    if let Some(pre_acct) = pre_acct {
-        for key in pre_acct.storage.keys() {
-            storage_written
-                .entry((*key).compat())
-                .or_insert(U256::zero());
-        }
-    };
-
-    (
-        Option::from(storage_read.into_iter().collect::<Vec<H256>>()).filter(|v| !v.is_empty()),
-        Option::from(storage_written).filter(|v| !v.is_empty()),
-    )
-}
-
-/// Processes the code usage for the given account state.
-async fn process_code(
-    post_state: Option<&AccountState>,
-    read_state: Option<&AccountState>,
-    code_db: &mut CodeDb,
-) -> Option<ContractCodeUsage> {
-    match (
-        post_state.and_then(|x| x.code.as_ref()),
-        read_state.and_then(|x| x.code.as_ref()),
-    ) {
-        (Some(post_code), _) => {
-            let code_hash = keccak256(post_code).compat();
-            code_db.insert(code_hash, post_code.to_vec());
-            Some(ContractCodeUsage::Write(post_code.to_vec()))
-        }
-        (_, Some(read_code)) => {
-            let code_hash = keccak256(read_code).compat();
-            code_db.insert(code_hash, read_code.to_vec());
-
-            Some(ContractCodeUsage::Read(code_hash))
-        }
-        _ => None,
-    }
+        .collect()
 }
 
 mod rlp {
@@ -344,7 +272,7 @@ mod rlp {
     }
 }
 
-/// Tracing options for the debug_traceTransaction call.
+/// Tracing options for the debug_traceBlockByNumber call.
 fn prestate_tracing_options(diff_mode: bool) -> GethDebugTracingOptions {
     GethDebugTracingOptions {
         tracer_config: PreStateConfig {
@@ -1,19 +1,49 @@
 use std::sync::Arc;
 
-use alloy::primitives::BlockHash;
-use alloy::rpc::types::{Block, BlockId, BlockTransactionsKind};
+use alloy::primitives::{Address, BlockHash, B256};
+use alloy::rpc::types::{
+    Block, BlockId, BlockNumberOrTag, BlockTransactionsKind, EIP1186AccountProofResponse,
+};
 use alloy::{providers::Provider, transports::Transport};
 use anyhow::Context;
-use tokio::sync::Mutex;
+use futures::{StreamExt as _, TryStreamExt as _};
+use tokio::sync::{Mutex, OnceCell, Semaphore};
 
 const CACHE_SIZE: usize = 1024;
 
+/// The default maximum number of calls sent in a single JSON-RPC batch.
+pub const DEFAULT_RPC_BATCH_SIZE: usize = 64;
+/// The default maximum number of JSON-RPC batches in flight at once.
+pub const DEFAULT_RPC_MAX_CONCURRENCY: usize = 16;
+
+/// Limits applied to the batched JSON-RPC requests issued by a
+/// [`CachedProvider`].
+#[derive(Debug, Clone, Copy)]
+pub struct RpcLimits {
+    /// The maximum number of calls sent in a single JSON-RPC batch.
+    pub batch_size: usize,
+    /// The maximum number of JSON-RPC batches in flight at once.
+    pub max_concurrency: usize,
+}
+
+impl Default for RpcLimits {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_RPC_BATCH_SIZE,
+            max_concurrency: DEFAULT_RPC_MAX_CONCURRENCY,
+        }
+    }
+}
+
 /// Wrapper around alloy provider to cache blocks and other
 /// frequently used data.
 pub struct CachedProvider<ProviderT, TransportT> {
     provider: ProviderT,
     blocks_by_number: Arc<Mutex<lru::LruCache<u64, Block>>>,
     blocks_by_hash: Arc<Mutex<lru::LruCache<BlockHash, u64>>>,
+    chain_id: OnceCell<u64>,
+    limits: RpcLimits,
+    in_flight: Semaphore,
     _phantom: std::marker::PhantomData<TransportT>,
 }
 
@@ -23,6 +53,14 @@ where
     TransportT: Transport + Clone,
 {
     pub fn new(provider: ProviderT) -> Self {
+        Self::with_limits(provider, RpcLimits::default())
+    }
+
+    pub fn with_limits(provider: ProviderT, limits: RpcLimits) -> Self {
+        let limits = RpcLimits {
+            batch_size: limits.batch_size.max(1),
+            max_concurrency: limits.max_concurrency.max(1),
+        };
         Self {
             provider,
             blocks_by_number: Arc::new(Mutex::new(lru::LruCache::new(
@@ -31,10 +69,17 @@ where
             blocks_by_hash: Arc::new(Mutex::new(lru::LruCache::new(
                 std::num::NonZero::new(CACHE_SIZE).unwrap(),
             ))),
+            chain_id: OnceCell::new(),
+            limits,
+            in_flight: Semaphore::new(limits.max_concurrency),
             _phantom: std::marker::PhantomData,
         }
     }
 
+    pub const fn limits(&self) -> RpcLimits {
+        self.limits
+    }
+
     pub fn as_mut_provider(&mut self) -> &mut ProviderT {
         &mut self.provider
     }
@@ -43,6 +88,19 @@ where
         &self.provider
     }
 
+    /// Retrieves the chain id, which is only fetched once.
+    pub async fn get_chain_id(&self) -> anyhow::Result<u64> {
+        self.chain_id
+            .get_or_try_init(|| async {
+                self.provider
+                    .get_chain_id()
+                    .await
+                    .context("couldn't fetch chain id")
+            })
+            .await
+            .copied()
+    }
+
     /// Retrieves block by number or hash, caching it if it's not already
     /// cached.
     pub async fn get_block(
@@ -68,7 +126,8 @@ where
                 self.blocks_by_number.lock().await.get(&number).cloned()
             }
             _ => None,
-        };
+        }
+        .filter(|block| has_transactions_kind(block, kind));
 
         if let Some(block) = cached_block {
             Ok(block)
@@ -91,4 +150,244 @@ where
             Ok(block)
         }
     }
+
+    /// Retrieves the blocks with the given numbers, in order.
+    ///
+    /// Blocks missing from the cache are fetched with batched JSON-RPC
+    /// requests, honouring the configured [`RpcLimits`].
+    pub async fn get_blocks_by_number(
+        &self,
+        numbers: &[u64],
+        kind: BlockTransactionsKind,
+    ) -> anyhow::Result<Vec<Block>> {
+        let mut missing = Vec::new();
+        {
+            let mut cache = self.blocks_by_number.lock().await;
+            for number in numbers {
+                if !cache
+                    .get(number)
+                    .is_some_and(|block| has_transactions_kind(block, kind))
+                {
+                    missing.push(*number);
+                }
+            }
+        }
+        missing.sort_unstable();
+        missing.dedup();
+
+        let full = matches!(kind, BlockTransactionsKind::Full);
+        let fetched: Vec<Option<Block>> = self
+            .batched(missing, |number| {
+                (
+                    "eth_getBlockByNumber",
+                    (BlockNumberOrTag::Number(number), full),
+                )
+            })
+            .await
+            .context("couldn't fetch blocks")?;
+
+        let mut fetched_blocks = std::collections::HashMap::new();
+        for block in fetched {
+            let block = block.context("block does not exist")?;
+            let number = block
+                .header
+                .number
+                .context("block is missing field `number`")?;
+            if let Some(hash) = block.header.hash {
+                self.blocks_by_hash.lock().await.put(hash, number);
+            }
+            self.blocks_by_number
+                .lock()
+                .await
+                .put(number, block.clone());
+            fetched_blocks.insert(number, block);
+        }
+
+        let cache = self.blocks_by_number.lock().await;
+        numbers
+            .iter()
+            .map(|number| {
+                fetched_blocks
+                    .get(number)
+                    .or_else(|| cache.peek(number))
+                    .cloned()
+                    .context(format!("target block {number} does not exist"))
+            })
+            .collect()
+    }
+
+    /// Retrieves the account and storage proofs for the given accounts at the
+    /// given block, in order, with batched JSON-RPC requests.
+    pub async fn get_proofs(
+        &self,
+        accounts: Vec<(Address, Vec<B256>)>,
+        block_id: BlockId,
+    ) -> anyhow::Result<Vec<EIP1186AccountProofResponse>> {
+        self.batched(accounts, |(address, keys)| {
+            ("eth_getProof", (address, keys, block_id))
+        })
+        .await
+        .context("Failed to get proof for account")
+    }
+
+    /// Issues one call per item, grouped in JSON-RPC batches of at most
+    /// `batch_size` calls with at most `max_concurrency` batches in flight.
+    /// Responses are returned in the order of the items.
+    async fn batched<ItemT, ParamsT, RespT>(
+        &self,
+        items: Vec<ItemT>,
+        to_call: impl Fn(ItemT) -> (&'static str, ParamsT),
+    ) -> anyhow::Result<Vec<RespT>>
+    where
+        ParamsT: alloy::rpc::json_rpc::RpcParam,
+        RespT: alloy::rpc::json_rpc::RpcReturn,
+    {
+        let mut batches = Vec::new();
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            let mut batch = self.provider.client().new_batch();
+            let mut waiters = Vec::with_capacity(self.limits.batch_size);
+            for item in items.by_ref().take(self.limits.batch_size) {
+                let (method, params) = to_call(item);
+                waiters.push(batch.add_call::<_, RespT>(method, &params)?);
+            }
+            batches.push((batch, waiters));
+        }
+
+        futures::stream::iter(batches)
+            .map(|(batch, waiters)| async move {
+                let _permit = self.in_flight.acquire().await?;
+                batch.send().await?;
+                futures::future::try_join_all(waiters)
+                    .await
+                    .map_err(anyhow::Error::from)
+            })
+            .buffered(self.limits.max_concurrency)
+            .try_fold(Vec::new(), |mut acc, responses| async move {
+                acc.extend(responses);
+                Ok(acc)
+            })
+            .await
+    }
+}
+
+/// Whether `block` holds its transactions in the form requested by `kind`.
+///
+/// A block fetched with full transactions also serves requests for hashes
+/// only, but not the other way around.
+fn has_transactions_kind(block: &Block, kind: BlockTransactionsKind) -> bool {
+    match kind {
+        BlockTransactionsKind::Hashes => true,
+        BlockTransactionsKind::Full => {
+            block.transactions.is_full() || block.transactions.is_empty()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    use alloy::providers::RootProvider;
+    use alloy::rpc::client::RpcClient;
+    use alloy::rpc::json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload};
+    use alloy::transports::{TransportError, TransportFut};
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    /// A transport answering `eth_getBlockByNumber` calls with empty blocks,
+    /// counting the packets it receives.
+    #[derive(Clone, Default)]
+    struct MockTransport {
+        packets: Arc<AtomicUsize>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl tower::Service<RequestPacket> for MockTransport {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, packet: RequestPacket) -> Self::Future {
+            self.packets.fetch_add(1, Ordering::SeqCst);
+            let respond = |request: &alloy::rpc::json_rpc::SerializedRequest| {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                assert_eq!(request.method(), "eth_getBlockByNumber");
+                let call: serde_json::Value =
+                    serde_json::from_str(request.serialized().get()).unwrap();
+                let number = serde_json::from_value::<BlockNumberOrTag>(call["params"][0].clone())
+                    .unwrap()
+                    .as_number()
+                    .unwrap();
+                let mut block = Block::default();
+                block.header.number = Some(number);
+                block.header.hash = Some(B256::with_last_byte(number as u8));
+                Response {
+                    id: request.id().clone(),
+                    payload: ResponsePayload::Success(
+                        RawValue::from_string(serde_json::to_string(&block).unwrap()).unwrap(),
+                    ),
+                }
+            };
+            let response = match packet {
+                RequestPacket::Single(request) => ResponsePacket::Single(respond(&request)),
+                RequestPacket::Batch(requests) => {
+                    ResponsePacket::Batch(requests.iter().map(respond).collect())
+                }
+            };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_blocks_in_batches() {
+        let transport = MockTransport::default();
+        let provider = CachedProvider::with_limits(
+            RootProvider::new(RpcClient::new(transport.clone(), true)),
+            RpcLimits {
+                batch_size: 4,
+                max_concurrency: 2,
+            },
+        );
+
+        let numbers = [9, 3, 5, 0, 1, 2, 8, 7, 6, 4];
+        let blocks = provider
+            .get_blocks_by_number(&numbers, BlockTransactionsKind::Hashes)
+            .await
+            .unwrap();
+        assert_eq!(
+            blocks
+                .iter()
+                .map(|block| block.header.number.unwrap())
+                .collect::<Vec<_>>(),
+            numbers
+        );
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 10);
+        assert_eq!(transport.packets.load(Ordering::SeqCst), 3);
+
+        // Cached blocks are not fetched again, whether by number or by hash.
+        provider
+            .get_blocks_by_number(&[3, 11], BlockTransactionsKind::Hashes)
+            .await
+            .unwrap();
+        provider
+            .get_block(BlockId::number(5), BlockTransactionsKind::Hashes)
+            .await
+            .unwrap();
+        provider
+            .get_block(
+                BlockId::hash(B256::with_last_byte(7)),
+                BlockTransactionsKind::Hashes,
+            )
+            .await
+            .unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 11);
+        assert_eq!(transport.packets.load(Ordering::SeqCst), 4);
+    }
 }
@@ -19,7 +19,7 @@ export RUSTFLAGS='-C target-cpu=native -Zlinker-features=-lld'
 
 # Circuit sizes only matter in non test_only mode.
 if ! [[ $8 == "test_only" ]]; then
-    export ARITHMETIC_CIRCUIT_SIZE="16..21"
+    export ARITHMETIC_CIRCUIT_SIZE="9..21"
     export BYTE_PACKING_CIRCUIT_SIZE="8..21"
     export CPU_CIRCUIT_SIZE="8..21"
     export KECCAK_CIRCUIT_SIZE="4..20"
@@ -28,6 +28,8 @@ if ! [[ $8 == "test_only" ]]; then
     export MEMORY_CIRCUIT_SIZE="17..24"
     export MEMORY_BEFORE_CIRCUIT_SIZE="16..23"
     export MEMORY_AFTER_CIRCUIT_SIZE="7..23"
+    export RANGE_CHECK_CIRCUIT_SIZE="16..17"
+    export EC_CIRCUIT_SIZE="4..16"
 fi
 
 # Force the working directory to always be the `tools/` directory. 
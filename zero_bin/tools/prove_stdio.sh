@@ -58,6 +58,8 @@ if ! [[ $TEST_ONLY == "test_only" ]]; then
         export MEMORY_CIRCUIT_SIZE="18..22"
         export MEMORY_BEFORE_CIRCUIT_SIZE="16..20"
         export MEMORY_AFTER_CIRCUIT_SIZE="7..20"
+        export RANGE_CHECK_CIRCUIT_SIZE="16..17"
+        export EC_CIRCUIT_SIZE="4..16"
     elif [[ $INPUT_FILE == *"witness_b3_b6"* ]]; then
       # These sizes are configured specifically for custom blocks 3 to 6. Don't use this in other scenarios
         echo "Using specific circuit sizes for witness_b3_b6.json"
@@ -70,8 +72,10 @@ if ! [[ $TEST_ONLY == "test_only" ]]; then
         export MEMORY_CIRCUIT_SIZE="17..22"
         export MEMORY_BEFORE_CIRCUIT_SIZE="17..18"
         export MEMORY_AFTER_CIRCUIT_SIZE="7..8"
+        export RANGE_CHECK_CIRCUIT_SIZE="16..17"
+        export EC_CIRCUIT_SIZE="4..16"
     else
-        export ARITHMETIC_CIRCUIT_SIZE="16..21"
+        export ARITHMETIC_CIRCUIT_SIZE="9..21"
         export BYTE_PACKING_CIRCUIT_SIZE="8..21"
         export CPU_CIRCUIT_SIZE="8..21"
         export KECCAK_CIRCUIT_SIZE="4..20"
@@ -80,6 +84,8 @@ if ! [[ $TEST_ONLY == "test_only" ]]; then
         export MEMORY_CIRCUIT_SIZE="17..24"
         export MEMORY_BEFORE_CIRCUIT_SIZE="16..23"
         export MEMORY_AFTER_CIRCUIT_SIZE="7..23"
+        export RANGE_CHECK_CIRCUIT_SIZE="16..17"
+        export EC_CIRCUIT_SIZE="4..16"
     fi
 fi
 
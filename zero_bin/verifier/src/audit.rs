@@ -0,0 +1,220 @@
+use std::fmt::Debug;
+use std::io::Write;
+
+use alloy::{
+    providers::Provider,
+    rpc::types::eth::{BlockId, BlockTransactionsKind},
+};
+use anyhow::{Context as _, Result};
+use compat::Compat;
+use evm_arithmetization::{
+    fixed_recursive_verifier::extract_block_public_values, proof::PublicValues,
+};
+use proof_gen::proof_types::GeneratedBlockProof;
+use rpc::{provider::CachedProvider, retry::build_http_retry_provider};
+use serde::Serialize;
+use tracing::{info, warn};
+use url::Url;
+
+/// A public value of a block proof which differs from the canonical chain.
+#[derive(Debug, Serialize)]
+struct Divergence {
+    block: u64,
+    field: &'static str,
+    proof: String,
+    chain: String,
+}
+
+/// The public values of the proofs of one block, compared against the
+/// canonical chain.
+#[derive(Default)]
+struct BlockAudit {
+    block: u64,
+    divergences: Vec<Divergence>,
+}
+
+impl BlockAudit {
+    fn check<T: Debug + PartialEq>(&mut self, field: &'static str, proof: T, chain: T) {
+        if proof != chain {
+            self.divergences.push(Divergence {
+                block: self.block,
+                field,
+                proof: format!("{proof:?}"),
+                chain: format!("{chain:?}"),
+            });
+        }
+    }
+}
+
+/// Compares the public values of each proof of `proofs` against the headers
+/// of the canonical chain fetched from `rpc_url`.
+///
+/// Divergences are written to stdout as JSON, one per line, and the audit
+/// fails if any is found.
+pub(crate) async fn audit_main(proofs: &[GeneratedBlockProof], rpc_url: Url) -> Result<()> {
+    let provider = CachedProvider::new(build_http_retry_provider(rpc_url, 0, 0));
+    let chain_id = provider.as_provider().get_chain_id().await?;
+
+    let mut stdout = std::io::stdout().lock();
+    let mut num_divergences = 0;
+    for proof in proofs {
+        let divergences = audit_block(&provider, chain_id, proof).await?;
+        if divergences.is_empty() {
+            info!("Block {} matches the chain", proof.b_height);
+        } else {
+            warn!(
+                "Block {} diverges from the chain on {} public values",
+                proof.b_height,
+                divergences.len()
+            );
+        }
+        num_divergences += divergences.len();
+        for divergence in divergences {
+            serde_json::to_writer(&mut stdout, &divergence)?;
+            stdout.write_all(b"\n")?;
+        }
+    }
+
+    anyhow::ensure!(
+        num_divergences == 0,
+        "found {num_divergences} divergences from the chain"
+    );
+    info!("All {} proofs match the chain", proofs.len());
+    Ok(())
+}
+
+async fn audit_block<ProviderT, TransportT>(
+    provider: &CachedProvider<ProviderT, TransportT>,
+    chain_id: u64,
+    proof: &GeneratedBlockProof,
+) -> Result<Vec<Divergence>>
+where
+    ProviderT: Provider<TransportT>,
+    TransportT: alloy::transports::Transport + Clone,
+{
+    let p_vals =
+        PublicValues::from_public_inputs(extract_block_public_values(&proof.intern.public_inputs))?;
+    let header = provider
+        .get_block(proof.b_height.into(), BlockTransactionsKind::Hashes)
+        .await
+        .with_context(|| format!("couldn't fetch block {}", proof.b_height))?
+        .header;
+
+    let mut audit = BlockAudit {
+        block: proof.b_height,
+        ..Default::default()
+    };
+    let metadata = &p_vals.block_metadata;
+    let roots = &p_vals.trie_roots_after;
+
+    audit.check(
+        "block_number",
+        metadata.block_number,
+        header.number.unwrap_or_default().into(),
+    );
+    audit.check(
+        "block_hash",
+        Some(p_vals.block_hashes.cur_hash),
+        header.hash.map(Compat::compat),
+    );
+    audit.check(
+        "parent_hash",
+        p_vals.block_hashes.parent_hash(),
+        header.parent_hash.compat(),
+    );
+    audit.check("state_root", roots.state_root, header.state_root.compat());
+    audit.check(
+        "transactions_root",
+        roots.transactions_root,
+        header.transactions_root.compat(),
+    );
+    audit.check(
+        "receipts_root",
+        roots.receipts_root,
+        header.receipts_root.compat(),
+    );
+    audit.check(
+        "block_beneficiary",
+        metadata.block_beneficiary,
+        header.miner.compat(),
+    );
+    audit.check(
+        "block_timestamp",
+        metadata.block_timestamp,
+        header.timestamp.into(),
+    );
+    audit.check(
+        "block_difficulty",
+        metadata.block_difficulty,
+        header.difficulty.into(),
+    );
+    audit.check(
+        "block_random",
+        Some(metadata.block_random),
+        header.mix_hash.map(Compat::compat),
+    );
+    audit.check(
+        "block_gaslimit",
+        metadata.block_gaslimit,
+        header.gas_limit.into(),
+    );
+    audit.check(
+        "block_gas_used",
+        metadata.block_gas_used,
+        header.gas_used.into(),
+    );
+    audit.check("block_chain_id", metadata.block_chain_id, chain_id.into());
+    // Fields introduced by later hardforks are zero in the proofs of earlier
+    // blocks.
+    audit.check(
+        "block_base_fee",
+        metadata.block_base_fee,
+        header.base_fee_per_gas.unwrap_or_default().into(),
+    );
+    audit.check(
+        "block_blob_gas_used",
+        metadata.block_blob_gas_used,
+        header.blob_gas_used.unwrap_or_default().into(),
+    );
+    audit.check(
+        "block_excess_blob_gas",
+        metadata.block_excess_blob_gas,
+        header.excess_blob_gas.unwrap_or_default().into(),
+    );
+    audit.check(
+        "parent_beacon_block_root",
+        metadata.parent_beacon_block_root,
+        header.parent_beacon_block_root.unwrap_or_default().compat(),
+    );
+    audit.check(
+        "withdrawals_root",
+        metadata.withdrawals_root,
+        header.withdrawals_root.unwrap_or_default().compat(),
+    );
+    audit.check(
+        "block_bloom",
+        metadata.block_bloom,
+        header.logs_bloom.compat(),
+    );
+    audit.check(
+        "block_hardfork",
+        metadata.block_hardfork,
+        rpc::hardfork(&header, chain_id)?,
+    );
+
+    // The state the block starts from is the one its parent ends with.
+    if let Some(parent_number) = proof.b_height.checked_sub(1) {
+        let parent = provider
+            .get_block(BlockId::from(parent_number), BlockTransactionsKind::Hashes)
+            .await
+            .with_context(|| format!("couldn't fetch block {parent_number}"))?
+            .header;
+        audit.check(
+            "state_root_before",
+            p_vals.trie_roots_before.state_root,
+            parent.state_root.compat(),
+        );
+    }
+
+    Ok(audit.divergences)
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use clap::{Parser, ValueHint};
+use clap::{Parser, Subcommand, ValueHint};
+use url::Url;
 use zero_bin_common::prover_state::cli::CliProverStateConfig;
 
 #[derive(Parser)]
@@ -8,8 +9,33 @@ pub(crate) struct Cli {
     /// The file containing the proof to verify
     #[arg(short, long, value_hint = ValueHint::FilePath)]
     pub(crate) file_path: PathBuf,
+    /// Read the file as a light client update exported by the leader instead
+    /// of block proofs, and write the headers it attests to to stdout.
+    #[arg(long, conflicts_with = "command")]
+    pub(crate) light_client_update: bool,
+    /// Verify the proofs against a verifier artifact exported with
+    /// `--export-artifact`, instead of the preprocessed circuits.
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "export_artifact")]
+    pub(crate) artifact: Option<PathBuf>,
+    /// Export the verifier data of the preprocessed circuits to this file,
+    /// for later verifications with `--artifact`.
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub(crate) export_artifact: Option<PathBuf>,
     /// The prover configuration used to generate the preprocessed circuits
     /// and the verifier state.
     #[clap(flatten)]
     pub(crate) prover_state_config: CliProverStateConfig,
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Verify the proofs, then check their public values against the
+    /// canonical chain, reporting each divergence to stdout.
+    Audit {
+        /// The RPC URL of the node serving the canonical chain.
+        #[arg(long, value_hint = ValueHint::Url)]
+        rpc: Url,
+    },
 }
@@ -4,14 +4,18 @@ use std::fs::File;
 use anyhow::Result;
 use clap::Parser;
 use dotenvy::dotenv;
-use proof_gen::proof_types::GeneratedBlockProof;
+use proof_gen::{light_client::LightClientUpdate, proof_types::GeneratedBlockProof};
 use serde_json::Deserializer;
 use tracing::info;
 use zero_bin_common::{
-    prover_state::persistence::{set_circuit_cache_dir_env_if_not_set, CIRCUIT_VERSION},
+    prover_state::persistence::{
+        read_verifier_artifact, set_circuit_cache_dir_env_if_not_set, write_verifier_artifact,
+        CIRCUIT_VERSION,
+    },
     version,
 };
 
+mod audit;
 mod cli;
 mod init;
 
@@ -34,14 +38,37 @@ fn main() -> Result<()> {
 
     let file = File::open(args.file_path)?;
     let des = &mut Deserializer::from_reader(&file);
-    let input_proofs: Vec<GeneratedBlockProof> = serde_path_to_error::deserialize(des)?;
 
-    let verifier = args
-        .prover_state_config
-        .into_prover_state_manager()
-        .verifier()?;
+    let verifier = match (args.artifact, args.export_artifact) {
+        (Some(path), _) => read_verifier_artifact(&path)?.into(),
+        (None, Some(path)) => {
+            let artifact = args
+                .prover_state_config
+                .into_prover_state_manager()?
+                .verifier_artifact();
+            write_verifier_artifact(&path, &artifact)?;
+            info!("Exported the verifier artifact to {}", path.display());
+            artifact.into()
+        }
+        (None, None) => args
+            .prover_state_config
+            .into_prover_state_manager()?
+            .verifier()?,
+    };
 
-    if input_proofs.into_iter().all(|block_proof| {
+    if args.light_client_update {
+        let update: LightClientUpdate = serde_path_to_error::deserialize(des)?;
+        let headers = update.verify(&verifier)?;
+        info!(
+            "Light client update of {} blocks verified successfully!",
+            headers.len()
+        );
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &headers)?;
+        return Ok(());
+    }
+
+    let input_proofs: Vec<GeneratedBlockProof> = serde_path_to_error::deserialize(des)?;
+    if input_proofs.iter().all(|block_proof| {
         verifier
             .verify(&block_proof.intern)
             .map_err(|e| {
@@ -50,7 +77,13 @@ fn main() -> Result<()> {
             .is_ok()
     }) {
         info!("All proofs verified successfully!");
+    } else if args.command.is_some() {
+        anyhow::bail!("Not auditing invalid proofs");
     };
 
+    if let Some(cli::Command::Audit { rpc }) = args.command {
+        tokio::runtime::Runtime::new()?.block_on(audit::audit_main(&input_proofs, rpc))?;
+    }
+
     Ok(())
 }
@@ -3,8 +3,14 @@ use std::env;
 use anyhow::Result;
 use clap::Parser;
 use dotenvy::dotenv;
+use futures::future::try_join_all;
 use ops::register;
 use paladin::runtime::WorkerRuntime;
+use tracing::info;
+use zero_bin_common::capabilities::{CapabilityTags, DEFAULT_TASK_ROUTING_KEY};
+use zero_bin_common::compression::{
+    add_dictionary_tag, log_compression_metrics, CliCompressionConfig, METRICS_LOG_PERIOD,
+};
 use zero_bin_common::prover_state::{
     cli::CliProverStateConfig,
     persistence::{set_circuit_cache_dir_env_if_not_set, CIRCUIT_VERSION},
@@ -12,6 +18,8 @@ use zero_bin_common::prover_state::{
 use zero_bin_common::version;
 
 mod init;
+#[cfg(feature = "self_test")]
+mod self_test;
 
 // TODO: https://github.com/0xPolygonZero/zk_evm/issues/302
 //       this should probably be removed.
@@ -25,6 +33,18 @@ struct Cli {
     paladin: paladin::config::Config,
     #[clap(flatten)]
     prover_state_config: CliProverStateConfig,
+    #[clap(flatten)]
+    compression_config: CliCompressionConfig,
+    /// Comma-separated capability tags of this worker, e.g. `gpu,high-mem`.
+    /// The worker also consumes the tasks requiring any subset of them. The tag
+    /// of the compression dictionary, if any, is added to them.
+    #[arg(long, env = "WORKER_CAPABILITIES")]
+    capabilities: Option<CapabilityTags>,
+    /// Prove and verify a tiny built-in block, report the time taken, then
+    /// exit rather than joining the fleet.
+    #[cfg(feature = "self_test")]
+    #[arg(long)]
+    self_test: bool,
 }
 
 #[tokio::main]
@@ -43,13 +63,45 @@ async fn main() -> Result<()> {
     init::tracing();
     set_circuit_cache_dir_env_if_not_set()?;
     let args = Cli::parse();
+    args.compression_config.install()?;
+
+    let prover_state_manager = args.prover_state_config.into_prover_state_manager()?;
+    #[cfg(feature = "self_test")]
+    if args.self_test {
+        return self_test::run(prover_state_manager);
+    }
+    prover_state_manager.initialize()?;
+
+    let base = args
+        .paladin
+        .task_bus_routing_key
+        .as_deref()
+        .unwrap_or(DEFAULT_TASK_ROUTING_KEY);
+    // Workers advertise their compression dictionary, so that the payloads
+    // compressed with it are only dispatched to them.
+    let mut capabilities = args.capabilities.unwrap_or_default();
+    add_dictionary_tag(&mut capabilities);
+    let routing_keys = capabilities.worker_routing_keys(base)?;
 
-    args.prover_state_config
-        .into_prover_state_manager()
-        .initialize()?;
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(METRICS_LOG_PERIOD);
+        loop {
+            interval.tick().await;
+            log_compression_metrics();
+        }
+    });
 
-    let runtime = WorkerRuntime::from_config(&args.paladin, register()).await?;
-    runtime.main_loop().await?;
+    let mut main_loops = Vec::with_capacity(routing_keys.len());
+    for routing_key in routing_keys {
+        info!("consuming tasks from {routing_key}");
+        let config = paladin::config::Config {
+            task_bus_routing_key: Some(routing_key),
+            ..args.paladin.clone()
+        };
+        let runtime = WorkerRuntime::from_config(&config, register()).await?;
+        main_loops.push(async move { runtime.main_loop().await });
+    }
+    try_join_all(main_loops).await?;
 
     Ok(())
 }
@@ -0,0 +1,50 @@
+//! A readiness probe proving a tiny built-in block before joining the fleet.
+//!
+//! It exercises the whole proving stack of the worker: building or loading
+//! the circuits, generating the witness of a segment, proving and verifying
+//! it. Broken CPU feature sets or insufficient memory then fail the probe
+//! rather than the first proving task of the worker.
+use std::time::{Duration, Instant};
+
+use anyhow::{Context as _, Result};
+use evm_arithmetization::prover::SegmentDataIterator;
+use evm_arithmetization::testing_utils::TestBlockBuilder;
+use proof_gen::types::Field;
+use zero_bin_common::prover_state::{p_manager, p_state, ProverStateManager};
+
+/// The maximum CPU length of the self-test segment. With this length, the
+/// tables of the segment fit the default circuit sizes.
+const SELF_TEST_MAX_CPU_LEN_LOG: usize = 20;
+
+/// Initializes the prover state, then proves and verifies the first segment of
+/// an empty block, reporting the time taken by each step.
+pub(crate) fn run(prover_state_manager: ProverStateManager) -> Result<()> {
+    let ((), circuits) = timed(|| prover_state_manager.initialize())
+        .context("self-test failed to initialize the prover state")?;
+
+    let inputs = TestBlockBuilder::new().build()?;
+    let (segment, witness) = timed(|| {
+        SegmentDataIterator::<Field>::new(&inputs, Some(SELF_TEST_MAX_CPU_LEN_LOG))
+            .next()
+            .context("self-test block has no segment")?
+            .map_err(anyhow::Error::from)
+    })
+    .context("self-test failed to generate the segment witness")?;
+
+    let (proof, proving) = timed(|| p_manager().generate_segment_proof(segment))
+        .context("self-test failed to prove the segment")?;
+
+    let ((), verification) = timed(|| p_state().state.verify_root(proof.intern))
+        .context("self-test failed to verify the segment proof")?;
+
+    println!(
+        "self-test passed: circuits {circuits:?}, witness {witness:?}, proof {proving:?}, \
+         verification {verification:?}"
+    );
+    Ok(())
+}
+
+fn timed<T>(f: impl FnOnce() -> Result<T>) -> Result<(T, Duration)> {
+    let start = Instant::now();
+    f().map(|out| (out, start.elapsed()))
+}